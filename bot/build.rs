@@ -0,0 +1,24 @@
+// Generates the gRPC service for bot/src/grpc.rs. The messages are hand-written prost
+// types, so this uses tonic's manual builder and needs no protoc; bot/proto/
+// dao_updates.proto documents the same schema for clients.
+
+fn main() {
+    let subscribe = tonic_build::manual::Method::builder()
+        .name("subscribe")
+        .route_name("Subscribe")
+        .input_type("crate::grpc::SubscribeRequest")
+        .output_type("crate::grpc::ProposalUpdate")
+        .codec_path("tonic::codec::ProstCodec")
+        .server_streaming()
+        .build();
+
+    let service = tonic_build::manual::Service::builder()
+        .name("DaoUpdates")
+        .package("solana_dao.updates")
+        .method(subscribe)
+        .build();
+
+    tonic_build::manual::Builder::new()
+        .build_client(false)
+        .compile(&[service]);
+}
@@ -0,0 +1,295 @@
+// Automatic result announcements: a periodic sweep that finalizes proposals whose
+// voting has ended and posts their results to the group's chat, so nobody has to
+// remember /results.
+//
+// finalize_proposal is permissionless; the bot pays the result certificate's rent and
+// collects the proposal's finalize bounty. Finalizing is retried each sweep while the
+// program refuses it (e.g. a pending off-chain tally or council tie-break), and a
+// proposal is announced once it is sealed, whoever finalized it. The announcement
+// replies to the proposal's original announcement when the bot still knows it, and is
+// pinned where the bot may pin. Announced proposal ids are kept per chat so a restart
+// doesn't repeat them. The sweep runs every RESULTS_INTERVAL_SECS (0 disables it).
+
+use crate::{
+    build_finalize_proposal_instruction, chart, chat_vocabulary, get_all_groups,
+    get_group_proposals, results_text, send_instructions, solana_dao, voter_lottery_pda, BotState,
+};
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::solana_sdk::signer::Signer;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use teloxide::prelude::*;
+use teloxide::types::MessageId;
+
+pub const DEFAULT_ANNOUNCED_RESULTS_PATH: &str = "bot/announced-results.json";
+pub const DEFAULT_RESULTS_INTERVAL_SECS: u64 = 60;
+// Proposals that ended longer ago than this are never announced, so a first run doesn't
+// flood chats with old results
+pub const RESULTS_LOOKBACK_SECS: i64 = 24 * 60 * 60;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct AnnouncedResults {
+    #[serde(default)]
+    pub proposal_ids: Vec<String>,
+}
+
+// Mirrors finalize_proposal's timing checks, so the sweep only sends finalizes that can
+// succeed. Slot-windowed proposals end by slot, the rest by timestamp.
+pub fn ready_to_finalize(proposal: &solana_dao::Proposal, now: i64, slot: u64) -> bool {
+    let ended = match proposal.slot_window {
+        Some(window) => slot > window.end_slot,
+        None => now > proposal.voting_end,
+    };
+    proposal.status == solana_dao::ProposalStatus::Active
+        && ended
+        && (proposal.offchain_aggregator.is_none() || proposal.offchain_voter_count.is_some())
+}
+
+// Sealed with a result certificate, and recent enough to be news
+pub fn announceable(proposal: &solana_dao::Proposal, now: i64) -> bool {
+    matches!(
+        proposal.status,
+        solana_dao::ProposalStatus::Succeeded
+            | solana_dao::ProposalStatus::Defeated
+            | solana_dao::ProposalStatus::Executed
+    ) && proposal.voting_end >= now.saturating_sub(RESULTS_LOOKBACK_SECS)
+}
+
+// The account a weight quorum is measured against: the SPL mint, or the registrar of
+// SOL deposit voting
+fn quorum_supply(proposal: &solana_dao::Proposal) -> Option<Pubkey> {
+    match (proposal.token_mint, &proposal.deposit_voting) {
+        (Some(mint), _) if mint != spl_token::native_mint::ID => Some(mint),
+        (Some(_), Some(deposit_voting)) => Some(deposit_voting.registrar),
+        _ => None,
+    }
+}
+
+async fn finalize(state: &BotState, proposal: &solana_dao::Proposal) -> anyhow::Result<()> {
+    let lottery = voter_lottery_pda(&proposal.group_id, &proposal.proposal_id);
+    let lottery = state
+        .program
+        .rpc()
+        .get_account(&lottery)
+        .await
+        .ok()
+        .map(|_| lottery);
+    let instruction = build_finalize_proposal_instruction(
+        &proposal.group_id,
+        &proposal.proposal_id,
+        state.payer.pubkey(),
+        lottery,
+        quorum_supply(proposal),
+    );
+    let signature = send_instructions(
+        state,
+        &[instruction],
+        &state.payer.pubkey(),
+        &[&*state.payer],
+    )
+    .await?;
+    log::info!(
+        "Finalized proposal {} in {}: {}",
+        proposal.proposal_id,
+        proposal.group_id,
+        signature
+    );
+    Ok(())
+}
+
+async fn announce(
+    bot: &Bot,
+    state: &BotState,
+    chat_id: ChatId,
+    proposal: &solana_dao::Proposal,
+) -> anyhow::Result<()> {
+    let vocab = chat_vocabulary(state, chat_id).await;
+    let language = state.settings.get(chat_id.0).await.announcement_language;
+    let results = results_text(
+        state,
+        chat_id,
+        &proposal.group_id,
+        &proposal.proposal_id,
+        proposal,
+        language.as_deref(),
+    )
+    .await;
+    let text = format!("🏁 <b>Voting has ended</b>\n\n{}", results);
+
+    let announcement = state
+        .discussions
+        .get(chat_id.0)
+        .await
+        .announcements
+        .iter()
+        .find(|(_, proposal_id)| **proposal_id == proposal.proposal_id)
+        .map(|(&message_id, _)| MessageId(message_id));
+    let message = chart::send_results(bot, &vocab, chat_id, proposal, text, announcement).await?;
+
+    if let Err(e) = bot
+        .pin_chat_message(chat_id, message.id)
+        .disable_notification(true)
+        .await
+    {
+        log::info!("Couldn't pin the results in {}: {}", chat_id, e);
+    }
+    Ok(())
+}
+
+// One pass over every group linked to a chat; returns how many results were announced
+async fn sweep(bot: &Bot, state: &BotState) -> anyhow::Result<usize> {
+    let slot = state.program.rpc().get_slot().await?;
+    let mut announced = 0;
+    for group in get_all_groups(state).await? {
+        let Some(chat_id) = group.external_chat_id.map(ChatId) else {
+            continue;
+        };
+
+        let now = Utc::now().timestamp();
+        let mut proposals = get_group_proposals(state, &group.group_id).await?;
+        let mut finalized = false;
+        for proposal in proposals.iter().filter(|p| ready_to_finalize(p, now, slot)) {
+            match finalize(state, proposal).await {
+                Ok(()) => finalized = true,
+                Err(e) => log::debug!("Can't finalize {} yet: {}", proposal.proposal_id, e),
+            }
+        }
+        if finalized {
+            proposals = get_group_proposals(state, &group.group_id).await?;
+        }
+
+        let done = state.announced_results.get(chat_id.0).await.proposal_ids;
+        for proposal in proposals
+            .iter()
+            .filter(|p| announceable(p, now) && !done.contains(&p.proposal_id))
+        {
+            match announce(bot, state, chat_id, proposal).await {
+                Ok(()) => {
+                    announced += 1;
+                    state
+                        .announced_results
+                        .update(chat_id.0, |record| {
+                            // Forget proposals that no longer exist, so the record stays small
+                            record
+                                .proposal_ids
+                                .retain(|id| proposals.iter().any(|p| p.proposal_id == *id));
+                            record.proposal_ids.push(proposal.proposal_id.clone());
+                        })
+                        .await?;
+                }
+                Err(e) => log::warn!(
+                    "Failed to announce the results of {}: {}",
+                    proposal.proposal_id,
+                    e
+                ),
+            }
+        }
+    }
+    Ok(announced)
+}
+
+// Sweep every `interval` until the process exits
+pub async fn run(bot: Bot, state: BotState, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        ticker.tick().await;
+        match sweep(&bot, &state).await {
+            Ok(0) => {}
+            Ok(announced) => log::info!("Announced the results of {} proposals", announced),
+            Err(e) => log::warn!("Result announcement sweep failed: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proposal(status: solana_dao::ProposalStatus, voting_end: i64) -> solana_dao::Proposal {
+        solana_dao::Proposal {
+            version: 1,
+            proposal_id: "p1".to_string(),
+            group_id: "tg_1".to_string(),
+            title: String::new(),
+            description: String::new(),
+            choices: vec!["Yes".to_string(), "No".to_string()],
+            choice_votes: vec![0, 0],
+            voting_start: 0,
+            voting_end,
+            token_mint: None,
+            creator: Pubkey::default(),
+            voters: Vec::new(),
+            created_at: 0,
+            index: 0,
+            metadata_uri: None,
+            status,
+            finalize_bounty: 0,
+            shadow_votes: Vec::new(),
+            offchain_aggregator: None,
+            offchain_voter_count: None,
+            price_weighted: false,
+            member_weighted: false,
+            kind: solana_dao::ProposalKind::Text,
+            emergency: false,
+            split_voting: false,
+            slot_window: None,
+            weight_decay_bps: 0,
+            deposit_voting: None,
+            council_chamber: None,
+            winner: None,
+            tied: false,
+            sponsors_needed: 0,
+            sponsor_count: 0,
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn only_ended_active_proposals_are_finalized() {
+        let active = proposal(solana_dao::ProposalStatus::Active, 1_000);
+        assert!(!ready_to_finalize(&active, 1_000, 0));
+        assert!(ready_to_finalize(&active, 1_001, 0));
+        assert!(!ready_to_finalize(
+            &proposal(solana_dao::ProposalStatus::Succeeded, 1_000),
+            1_001,
+            0
+        ));
+
+        let mut pending_tally = active.clone();
+        pending_tally.offchain_aggregator = Some(Pubkey::new_unique());
+        assert!(!ready_to_finalize(&pending_tally, 1_001, 0));
+
+        // Slot windows end by slot, whatever the timestamp says
+        let mut slotted = active;
+        slotted.slot_window = Some(solana_dao::SlotWindow {
+            start_slot: 10,
+            end_slot: 20,
+        });
+        assert!(!ready_to_finalize(&slotted, 1_001, 20));
+        assert!(ready_to_finalize(&slotted, 0, 21));
+    }
+
+    #[test]
+    fn only_recently_sealed_proposals_are_announced() {
+        let now = 1_000_000;
+        assert!(announceable(
+            &proposal(solana_dao::ProposalStatus::Defeated, now - 60),
+            now
+        ));
+        assert!(!announceable(
+            &proposal(solana_dao::ProposalStatus::Active, now - 60),
+            now
+        ));
+        assert!(!announceable(
+            &proposal(
+                solana_dao::ProposalStatus::Succeeded,
+                now - RESULTS_LOOKBACK_SECS - 1
+            ),
+            now
+        ));
+    }
+}
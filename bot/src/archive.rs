@@ -0,0 +1,185 @@
+// Discussion archives: the Telegram conversation around a proposal, preserved as
+// message links and content hashes so the context of a decision can be checked later.
+//
+// The Bot API can't read chat history, so the bot logs messages as they arrive. A
+// message belongs to a proposal's thread when it mentions the id of a proposal the bot
+// announced in the chat, or replies to that announcement or to another message in the
+// thread. Only hashes of the text are kept; the messages themselves stay in Telegram.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+pub const DEFAULT_DISCUSSIONS_PATH: &str = "bot/discussions.json";
+// Messages kept per chat; older ones are dropped once a chat goes past this
+pub const MAX_LOGGED_MESSAGES: usize = 2_000;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LoggedMessage {
+    pub message_id: i32,
+    pub reply_to: Option<i32>,
+    pub date: i64,
+    // Hex SHA-256 of the message text
+    pub sha256: String,
+    pub proposal_id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ArchivedMessage {
+    // t.me link for supergroups; other chats have no shareable message links
+    pub link: Option<String>,
+    pub message_id: i32,
+    pub date: i64,
+    pub sha256: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Archive {
+    pub proposal_id: String,
+    pub group_id: String,
+    pub chat_id: i64,
+    pub archived_at: i64,
+    // The proposal's metadata URI before it was pointed at this archive
+    pub previous_metadata_uri: Option<String>,
+    pub messages: Vec<ArchivedMessage>,
+    // SHA-256 over the message hashes in order, committing to the whole thread
+    pub digest: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct DiscussionLog {
+    // Bot announcement message id -> proposal id
+    #[serde(default)]
+    pub announcements: BTreeMap<i32, String>,
+    #[serde(default)]
+    pub messages: Vec<LoggedMessage>,
+    // Proposal id -> its latest archive
+    #[serde(default)]
+    pub archives: BTreeMap<String, Archive>,
+}
+
+impl DiscussionLog {
+    // Log a message, attributing it to a proposal by mention or by reply chain
+    pub fn record(&mut self, message_id: i32, reply_to: Option<i32>, date: i64, text: &str) {
+        let proposal_id = self
+            .announcements
+            .values()
+            .find(|id| text.contains(id.as_str()))
+            .cloned()
+            .or_else(|| reply_to.and_then(|parent| self.proposal_of(parent)));
+
+        self.messages.push(LoggedMessage {
+            message_id,
+            reply_to,
+            date,
+            sha256: sha256_hex(text.as_bytes()),
+            proposal_id,
+        });
+        if self.messages.len() > MAX_LOGGED_MESSAGES {
+            let excess = self.messages.len() - MAX_LOGGED_MESSAGES;
+            self.messages.drain(..excess);
+        }
+    }
+
+    fn proposal_of(&self, message_id: i32) -> Option<String> {
+        self.announcements.get(&message_id).cloned().or_else(|| {
+            self.messages
+                .iter()
+                .find(|m| m.message_id == message_id)
+                .and_then(|m| m.proposal_id.clone())
+        })
+    }
+
+    // Build an archive of a proposal's thread from the logged messages
+    pub fn archive(
+        &self,
+        chat_id: i64,
+        group_id: &str,
+        proposal_id: &str,
+        previous_metadata_uri: Option<String>,
+        now: i64,
+    ) -> Archive {
+        let messages: Vec<ArchivedMessage> = self
+            .messages
+            .iter()
+            .filter(|m| m.proposal_id.as_deref() == Some(proposal_id))
+            .map(|m| ArchivedMessage {
+                link: message_link(chat_id, m.message_id),
+                message_id: m.message_id,
+                date: m.date,
+                sha256: m.sha256.clone(),
+            })
+            .collect();
+
+        let mut digest = Sha256::new();
+        for message in &messages {
+            digest.update(message.sha256.as_bytes());
+        }
+
+        Archive {
+            proposal_id: proposal_id.to_string(),
+            group_id: group_id.to_string(),
+            chat_id,
+            archived_at: now,
+            previous_metadata_uri,
+            messages,
+            digest: hex::encode(digest.finalize()),
+        }
+    }
+}
+
+// Public link to a message; only supergroups (ids starting with -100) have them
+pub fn message_link(chat_id: i64, message_id: i32) -> Option<String> {
+    let internal_id = chat_id.to_string().strip_prefix("-100")?.to_string();
+    Some(format!("https://t.me/c/{}/{}", internal_id, message_id))
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follows_mentions_and_reply_chains() {
+        let mut log = DiscussionLog::default();
+        log.announcements.insert(1, "abc12345".to_string());
+        log.announcements.insert(2, "def67890".to_string());
+
+        log.record(3, Some(1), 10, "I support this");
+        log.record(4, Some(3), 11, "Me too");
+        log.record(5, None, 12, "What about def67890?");
+        log.record(6, None, 13, "Unrelated chatter");
+
+        let archive = log.archive(-1001234567890, "tg_1001234567890", "abc12345", None, 20);
+        let ids: Vec<_> = archive.messages.iter().map(|m| m.message_id).collect();
+        assert_eq!(ids, vec![3, 4]);
+        assert_eq!(
+            archive.messages[0].link.as_deref(),
+            Some("https://t.me/c/1234567890/3")
+        );
+        assert_eq!(archive.messages[1].sha256, sha256_hex(b"Me too"));
+
+        let other = log.archive(-1001234567890, "tg_1001234567890", "def67890", None, 20);
+        assert_eq!(other.messages.len(), 1);
+        assert_ne!(archive.digest, other.digest);
+    }
+
+    #[test]
+    fn only_supergroups_have_message_links() {
+        assert_eq!(message_link(-4001234, 7), None);
+        assert_eq!(message_link(42, 7), None);
+    }
+
+    #[test]
+    fn keeps_a_bounded_log() {
+        let mut log = DiscussionLog::default();
+        for id in 0..(MAX_LOGGED_MESSAGES as i32 + 5) {
+            log.record(id, None, 0, "hi");
+        }
+        assert_eq!(log.messages.len(), MAX_LOGGED_MESSAGES);
+        assert_eq!(log.messages[0].message_id, 5);
+    }
+}
@@ -0,0 +1,298 @@
+// Off-chain ballots for proposals whose voting was moved off-chain with
+// enable_offchain_voting, naming the bot's payer as aggregator.
+//
+// Wallets sign OFFCHAIN_BALLOT_MESSAGE_PREFIX || proposal address || choice and post it
+// to the web service; nothing touches the chain until voting ends. The bot then submits
+// the root of a Merkle sum tree over the counted ballots together with their tallies:
+// each inner node commits to its children's hashes and per-choice subtotals. Ballots are
+// published with their proofs, so anyone can rebuild the tree and, within the challenge
+// window, dispute a tally that counted a voter for a choice they didn't sign or whose
+// totals don't add up. Ballot weights are the bot's own reading and aren't checked
+// on-chain.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+
+pub const DEFAULT_BALLOTS_PATH: &str = "bot/ballots.json";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Ballot {
+    pub voter: String,
+    pub choice: u8,
+    // Weight read when the ballot arrived: 1, lamports or token balance
+    pub vote_weight: u64,
+    // Base58 ed25519 signature of the ballot message
+    pub signature: String,
+    pub received_at: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ProposalBallots {
+    // Voter -> latest ballot; sorted, which fixes the leaf order
+    #[serde(default)]
+    pub ballots: BTreeMap<String, Ballot>,
+    // Voters who signed ballots for different choices. Either ballot would dispute a
+    // tally that counted the other, so they are left out entirely.
+    #[serde(default)]
+    pub equivocators: BTreeSet<String>,
+    // Root of the last tally submitted on-chain, if any
+    #[serde(default)]
+    pub submitted_root: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct BallotBox {
+    // Proposal id -> its ballots
+    #[serde(default)]
+    pub proposals: BTreeMap<String, ProposalBallots>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CastOutcome {
+    Recorded,
+    // A repeat of the voter's earlier choice, kept with the latest weight
+    Updated,
+    // The voter already signed a different choice; neither ballot counts
+    Equivocated,
+}
+
+impl BallotBox {
+    pub fn cast(&mut self, proposal_id: &str, ballot: Ballot) -> CastOutcome {
+        let entry = self.proposals.entry(proposal_id.to_string()).or_default();
+        if entry.equivocators.contains(&ballot.voter) {
+            return CastOutcome::Equivocated;
+        }
+        match entry.ballots.get(&ballot.voter) {
+            Some(earlier) if earlier.choice != ballot.choice => {
+                entry.ballots.remove(&ballot.voter);
+                entry.equivocators.insert(ballot.voter);
+                CastOutcome::Equivocated
+            }
+            Some(_) => {
+                entry.ballots.insert(ballot.voter.clone(), ballot);
+                CastOutcome::Updated
+            }
+            None => {
+                entry.ballots.insert(ballot.voter.clone(), ballot);
+                CastOutcome::Recorded
+            }
+        }
+    }
+}
+
+// A subtree of the tally tree: its hash and what it counts, as its parent commits to it
+// (the program's TallySubtree)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Subtree {
+    pub hash: [u8; 32],
+    pub choice_votes: Vec<u64>,
+    pub voter_count: u32,
+}
+
+impl Subtree {
+    fn sums_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.choice_votes.len() * 8 + 4);
+        for votes in &self.choice_votes {
+            bytes.extend_from_slice(&votes.to_le_bytes());
+        }
+        bytes.extend_from_slice(&self.voter_count.to_le_bytes());
+        bytes
+    }
+}
+
+impl ProposalBallots {
+    // Counted ballots in leaf order, with their leaves; ballots for a choice the
+    // proposal doesn't have aren't counted
+    pub fn leaves(&self, choice_count: usize) -> Vec<(&Ballot, Subtree)> {
+        self.ballots
+            .values()
+            .filter(|ballot| (ballot.choice as usize) < choice_count)
+            .filter_map(|ballot| {
+                let voter = ballot.voter.parse::<Pubkey>().ok()?;
+                let mut choice_votes = vec![0; choice_count];
+                choice_votes[ballot.choice as usize] = ballot.vote_weight;
+                Some((
+                    ballot,
+                    Subtree {
+                        hash: ballot_leaf(&voter, ballot.choice, ballot.vote_weight),
+                        choice_votes,
+                        voter_count: 1,
+                    },
+                ))
+            })
+            .collect()
+    }
+
+    // Per-choice totals and the number of counted ballots
+    pub fn tally(&self, choice_count: usize) -> (Vec<u64>, u32) {
+        let mut choice_votes = vec![0u64; choice_count];
+        let mut voter_count = 0u32;
+        for ballot in self.ballots.values() {
+            if let Some(votes) = choice_votes.get_mut(ballot.choice as usize) {
+                *votes = votes.saturating_add(ballot.vote_weight);
+                voter_count += 1;
+            }
+        }
+        (choice_votes, voter_count)
+    }
+}
+
+fn hashv(parts: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+// The program's offchain_ballot_leaf: SHA-256 of 0 || voter || choice || weight (u64 LE)
+pub fn ballot_leaf(voter: &Pubkey, choice: u8, vote_weight: u64) -> [u8; 32] {
+    hashv(&[&[0], voter.as_ref(), &[choice], &vote_weight.to_le_bytes()])
+}
+
+// The program's offchain_tally_node: SHA-256 of 1 and both children's hashes and sums,
+// ordered by hash then sums, so proofs carry no left/right bits. The parent counts what
+// both children do.
+fn join(a: &Subtree, b: &Subtree) -> Subtree {
+    let (a_sums, b_sums) = (a.sums_bytes(), b.sums_bytes());
+    let hash = if (a.hash, &a_sums) <= (b.hash, &b_sums) {
+        hashv(&[&[1], &a.hash, &a_sums, &b.hash, &b_sums])
+    } else {
+        hashv(&[&[1], &b.hash, &b_sums, &a.hash, &a_sums])
+    };
+    Subtree {
+        hash,
+        choice_votes: a
+            .choice_votes
+            .iter()
+            .zip(&b.choice_votes)
+            .map(|(a, b)| a.saturating_add(*b))
+            .collect(),
+        voter_count: a.voter_count.saturating_add(b.voter_count),
+    }
+}
+
+// Tree levels from the leaves up; an odd node is carried up unchanged
+fn levels(leaves: &[Subtree]) -> Vec<Vec<Subtree>> {
+    let mut levels = vec![leaves.to_vec()];
+    while levels.last().is_some_and(|level| level.len() > 1) {
+        let next = levels
+            .last()
+            .unwrap()
+            .chunks(2)
+            .map(|pair| match pair {
+                [a, b] => join(a, b),
+                [a] => a.clone(),
+                _ => unreachable!(),
+            })
+            .collect();
+        levels.push(next);
+    }
+    levels
+}
+
+// Root over `leaves`; all zeroes when nothing was counted
+pub fn merkle_root(leaves: &[Subtree]) -> [u8; 32] {
+    levels(leaves)
+        .last()
+        .and_then(|level| level.first())
+        .map_or([0; 32], |root| root.hash)
+}
+
+// Sibling subtrees from leaf `index` up to the root
+pub fn merkle_proof(leaves: &[Subtree], mut index: usize) -> Vec<Subtree> {
+    let mut proof = Vec::new();
+    for level in levels(leaves).iter().take_while(|level| level.len() > 1) {
+        if let Some(sibling) = level.get(index ^ 1) {
+            proof.push(sibling.clone());
+        }
+        index /= 2;
+    }
+    proof
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The program's offchain_tally_root
+    fn root_from_proof(leaf: Subtree, proof: &[Subtree]) -> Subtree {
+        proof
+            .iter()
+            .fold(leaf, |node, sibling| join(&node, sibling))
+    }
+
+    fn ballot(voter: Pubkey, choice: u8, vote_weight: u64) -> Ballot {
+        Ballot {
+            voter: voter.to_string(),
+            choice,
+            vote_weight,
+            signature: String::new(),
+            received_at: 0,
+        }
+    }
+
+    #[test]
+    fn every_proof_leads_to_the_root_and_totals() {
+        for count in 1..=9u8 {
+            let mut ballot_box = BallotBox::default();
+            for n in 0..count {
+                ballot_box.cast(
+                    "p",
+                    ballot(Pubkey::new_from_array([n; 32]), n % 3, n as u64 + 1),
+                );
+            }
+            let ballots = &ballot_box.proposals["p"];
+            let leaves: Vec<Subtree> = ballots
+                .leaves(3)
+                .into_iter()
+                .map(|(_, leaf)| leaf)
+                .collect();
+            let root = merkle_root(&leaves);
+            let (choice_votes, voter_count) = ballots.tally(3);
+            for (index, leaf) in leaves.iter().enumerate() {
+                let top = root_from_proof(leaf.clone(), &merkle_proof(&leaves, index));
+                assert_eq!(top.hash, root);
+                assert_eq!(
+                    (top.choice_votes, top.voter_count),
+                    (choice_votes.clone(), voter_count)
+                );
+            }
+        }
+        assert_eq!(merkle_root(&[]), [0; 32]);
+    }
+
+    #[test]
+    fn equivocating_voters_are_left_out() {
+        let (alice, bob) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut ballot_box = BallotBox::default();
+        assert_eq!(
+            ballot_box.cast("p", ballot(alice, 0, 1)),
+            CastOutcome::Recorded
+        );
+        assert_eq!(
+            ballot_box.cast("p", ballot(bob, 1, 1)),
+            CastOutcome::Recorded
+        );
+        assert_eq!(
+            ballot_box.cast("p", ballot(bob, 1, 1)),
+            CastOutcome::Updated
+        );
+        assert_eq!(
+            ballot_box.cast("p", ballot(alice, 1, 1)),
+            CastOutcome::Equivocated
+        );
+        // Switching back doesn't restore the voter
+        assert_eq!(
+            ballot_box.cast("p", ballot(alice, 0, 1)),
+            CastOutcome::Equivocated
+        );
+
+        let ballots = &ballot_box.proposals["p"];
+        assert_eq!(ballots.tally(2), (vec![0, 1], 1));
+        assert_eq!(ballots.leaves(2).len(), 1);
+    }
+}
@@ -0,0 +1,564 @@
+#![allow(deprecated)]
+// Load-test harness for the Solana DAO program.
+//
+// Spins up a throwaway group on localnet, creates proposals, funds N voter keypairs and
+// fires concurrent votes, then reports throughput, latency, failure modes and how the
+// proposal accounts grow with their voter lists.
+//
+// Usage:
+//   cargo run --bin loadtest -- --voters 2000 --proposals 10 --concurrency 64
+//
+// Options (all optional):
+//   --rpc <url>           RPC endpoint (default: SOLANA_RPC_URL or http://127.0.0.1:8899)
+//   --voters <n>          number of voter keypairs (default 1000)
+//   --proposals <n>       number of proposals votes are spread across (default 4)
+//   --choices <n>         choices per proposal (default 3)
+//   --concurrency <n>     in-flight transactions (default 64)
+//   --fund-lamports <n>   lamports sent to each voter (default 10_000_000)
+
+use anchor_client::solana_client::nonblocking::rpc_client::RpcClient;
+use anchor_client::solana_sdk::{
+    commitment_config::CommitmentConfig,
+    instruction::{AccountMeta, Instruction},
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    system_instruction, system_program,
+    transaction::Transaction,
+};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, Semaphore};
+
+const PROGRAM_ID: &str = "4mwBvEQbpGJKDDZCvEPTujCefmphw1fZ99Jxhz69oHcT";
+const NATIVE_MINT: &str = "So11111111111111111111111111111111111111112";
+
+// Largest size an account can be reallocated to
+const MAX_ACCOUNT_DATA_LEN: usize = 10 * 1024 * 1024;
+// Serialized size of one VoterInfo entry (pubkey + choice + weight + timestamp)
+const VOTER_INFO_LEN: usize = 32 + 1 + 8 + 8;
+// Transfers packed into each funding transaction
+const FUNDING_BATCH: usize = 20;
+// Sample the first proposal's account size every this many successful votes
+const SIZE_SAMPLE_EVERY: usize = 100;
+
+struct Config {
+    rpc_url: String,
+    voters: usize,
+    proposals: usize,
+    choices: usize,
+    concurrency: usize,
+    fund_lamports: u64,
+}
+
+impl Config {
+    fn from_args() -> anyhow::Result<Self> {
+        let mut config = Self {
+            // The bot accepts a comma-separated list; the load test uses the first
+            rpc_url: std::env::var("SOLANA_RPC_URL")
+                .ok()
+                .and_then(|urls| urls.split(',').next().map(|url| url.trim().to_string()))
+                .unwrap_or_else(|| "http://127.0.0.1:8899".to_string()),
+            voters: 1000,
+            proposals: 4,
+            choices: 3,
+            concurrency: 64,
+            fund_lamports: 10_000_000,
+        };
+
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        for pair in args.chunks(2) {
+            let value = pair
+                .get(1)
+                .ok_or_else(|| anyhow::anyhow!("Missing value for {}", pair[0]))?;
+            match pair[0].as_str() {
+                "--rpc" => config.rpc_url = value.clone(),
+                "--voters" => config.voters = value.parse()?,
+                "--proposals" => config.proposals = value.parse()?,
+                "--choices" => config.choices = value.parse()?,
+                "--concurrency" => config.concurrency = value.parse()?,
+                "--fund-lamports" => config.fund_lamports = value.parse()?,
+                other => return Err(anyhow::anyhow!("Unknown option {}", other)),
+            }
+        }
+
+        if config.proposals == 0 || config.voters == 0 || config.concurrency == 0 {
+            return Err(anyhow::anyhow!(
+                "--voters, --proposals and --concurrency must be positive"
+            ));
+        }
+        if !(2..=10).contains(&config.choices) {
+            return Err(anyhow::anyhow!("--choices must be between 2 and 10"));
+        }
+        Ok(config)
+    }
+}
+
+// Outcome counters for one phase of the run
+#[derive(Default)]
+struct PhaseStats {
+    ok: usize,
+    latencies_ms: Vec<u128>,
+    failures: BTreeMap<String, usize>, // failure mode -> count
+    first_failure_at: Option<usize>,   // successful operations before the first failure
+}
+
+impl PhaseStats {
+    fn record(&mut self, result: &anyhow::Result<()>, latency: Duration) {
+        match result {
+            Ok(()) => {
+                self.ok += 1;
+                self.latencies_ms.push(latency.as_millis());
+            }
+            Err(e) => {
+                self.first_failure_at.get_or_insert(self.ok);
+                *self.failures.entry(classify_error(e)).or_default() += 1;
+            }
+        }
+    }
+
+    fn report(&mut self, name: &str, elapsed: Duration) {
+        self.latencies_ms.sort_unstable();
+        let percentile = |p: f64| -> u128 {
+            if self.latencies_ms.is_empty() {
+                return 0;
+            }
+            let idx = ((self.latencies_ms.len() - 1) as f64 * p).round() as usize;
+            self.latencies_ms[idx]
+        };
+        let failed: usize = self.failures.values().sum();
+
+        println!("\n== {} ==", name);
+        println!(
+            "  ok: {}  failed: {}  elapsed: {:.2}s  throughput: {:.1} tx/s",
+            self.ok,
+            failed,
+            elapsed.as_secs_f64(),
+            self.ok as f64 / elapsed.as_secs_f64().max(f64::EPSILON)
+        );
+        println!(
+            "  latency ms: p50 {}  p90 {}  p99 {}  max {}",
+            percentile(0.5),
+            percentile(0.9),
+            percentile(0.99),
+            self.latencies_ms.last().copied().unwrap_or(0)
+        );
+        if let Some(at) = self.first_failure_at {
+            println!("  first failure after {} successful operations", at);
+        }
+        for (mode, count) in &self.failures {
+            println!("  failure [{}]: {}", mode, count);
+        }
+    }
+}
+
+// Bucket an RPC/program error into a coarse failure mode
+fn classify_error(e: &anyhow::Error) -> String {
+    let text = e.to_string();
+    let modes = [
+        ("AlreadyVoted", "already voted"),
+        ("VotingNotActive", "voting not active"),
+        ("TallyOverflow", "tally overflow"),
+        ("ProgramPaused", "program paused"),
+        ("already in use", "account already in use"),
+        ("exceeded maximum", "account size limit"),
+        ("InvalidRealloc", "account size limit"),
+        ("insufficient", "insufficient funds"),
+        ("Blockhash not found", "blockhash expired"),
+        ("timed out", "timeout"),
+        ("unable to confirm", "unconfirmed"),
+        ("429", "rate limited"),
+        ("Connection refused", "rpc unreachable"),
+    ];
+    modes
+        .iter()
+        .find(|(needle, _)| text.contains(needle))
+        .map(|(_, mode)| mode.to_string())
+        .unwrap_or_else(|| format!("other: {}", text.chars().take(80).collect::<String>()))
+}
+
+// Anchor instruction discriminator: sha256("global:<name>")[..8]
+fn discriminator(name: &str) -> Vec<u8> {
+    Sha256::digest(format!("global:{}", name).as_bytes())[..8].to_vec()
+}
+
+fn push_string(data: &mut Vec<u8>, value: &str) {
+    data.extend_from_slice(&(value.len() as u32).to_le_bytes());
+    data.extend_from_slice(value.as_bytes());
+}
+
+async fn send(
+    rpc: &RpcClient,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> anyhow::Result<()> {
+    let blockhash = rpc.get_latest_blockhash().await?;
+    let transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&signers[0].pubkey()),
+        signers,
+        blockhash,
+    );
+    rpc.send_and_confirm_transaction(&transaction).await?;
+    Ok(())
+}
+
+async fn airdrop(rpc: &RpcClient, to: &Pubkey, lamports: u64) -> anyhow::Result<()> {
+    let signature = rpc.request_airdrop(to, lamports).await?;
+    for _ in 0..60 {
+        if rpc.confirm_transaction(&signature).await? {
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+    Err(anyhow::anyhow!("Airdrop to {} was not confirmed", to))
+}
+
+fn program_config_pda(program_id: Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"program_config"], &program_id).0
+}
+
+fn participation_pda(program_id: Pubkey, group: &Pubkey, member: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"participation", group.as_ref(), member.as_ref()],
+        &program_id,
+    )
+    .0
+}
+
+fn create_group_instruction(program_id: Pubkey, authority: Pubkey, group_id: &str) -> Instruction {
+    let (registry, _) = Pubkey::find_program_address(&[b"dao_registry"], &program_id);
+    let (group, _) = Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &program_id);
+
+    let mut data = discriminator("create_group");
+    push_string(&mut data, group_id);
+    push_string(&mut data, "Load test");
+    push_string(
+        &mut data,
+        "Throwaway group created by the load-test harness",
+    );
+    data.push(0); // no metadata_uri
+
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(group, false),
+            AccountMeta::new(registry, false),
+            AccountMeta::new(authority, true),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new_readonly(program_config_pda(program_id), false),
+        ],
+        data,
+    }
+}
+
+// Read the group's proposal counter (u64 after group_id, name, description and authority)
+async fn group_proposal_count(rpc: &RpcClient, group: &Pubkey) -> anyhow::Result<u64> {
+    let data = rpc.get_account_data(group).await?;
+    let mut offset = 8;
+    for _ in 0..3 {
+        let len = u32::from_le_bytes(data[offset..offset + 4].try_into()?) as usize;
+        offset += 4 + len;
+    }
+    offset += 32;
+    Ok(u64::from_le_bytes(data[offset..offset + 8].try_into()?))
+}
+
+fn proposal_pda(program_id: Pubkey, group: &Pubkey, proposal_id: &str) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"proposal",
+            &group.to_bytes()[..8],
+            &proposal_id.as_bytes()[..8],
+        ],
+        &program_id,
+    )
+    .0
+}
+
+#[allow(clippy::too_many_arguments)]
+fn create_proposal_instruction(
+    program_id: Pubkey,
+    authority: Pubkey,
+    group: Pubkey,
+    proposal_count: u64,
+    proposal_id: &str,
+    choices: usize,
+    voting_start: i64,
+    voting_end: i64,
+) -> anyhow::Result<Instruction> {
+    let (proposal_index, _) = Pubkey::find_program_address(
+        &[
+            b"proposal_index",
+            group.as_ref(),
+            &proposal_count.to_le_bytes(),
+        ],
+        &program_id,
+    );
+
+    let mut data = discriminator("create_proposal");
+    push_string(&mut data, proposal_id);
+    push_string(&mut data, &format!("Load test {}", &proposal_id[..8]));
+    push_string(&mut data, "Proposal created by the load-test harness");
+    data.extend_from_slice(&(choices as u32).to_le_bytes());
+    for i in 0..choices {
+        push_string(&mut data, &format!("Choice {}", i));
+    }
+    data.extend_from_slice(&voting_start.to_le_bytes());
+    data.extend_from_slice(&voting_end.to_le_bytes());
+    data.push(1); // SOL-weighted voting
+    data.extend_from_slice(&Pubkey::from_str(NATIVE_MINT)?.to_bytes());
+    data.push(0); // no metadata_uri
+    data.push(0); // not a draft
+
+    Ok(Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(proposal_pda(program_id, &group, proposal_id), false),
+            AccountMeta::new(proposal_index, false),
+            AccountMeta::new(group, false),
+            AccountMeta::new(authority, true),
+            AccountMeta::new(participation_pda(program_id, &group, &authority), false),
+            AccountMeta::new(
+                Pubkey::find_program_address(&[b"treasury", group.as_ref()], &program_id).0,
+                false,
+            ),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new_readonly(program_config_pda(program_id), false),
+        ],
+        data,
+    })
+}
+
+fn vote_instruction(
+    program_id: Pubkey,
+    group: Pubkey,
+    proposal: Pubkey,
+    voter: Pubkey,
+    choice: u8,
+) -> Instruction {
+    let mut data = discriminator("vote_on_proposal");
+    data.push(choice);
+    data.push(0); // no disclosure
+
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(proposal, false),
+            AccountMeta::new(group, false),
+            AccountMeta::new(voter, true),
+            AccountMeta::new(participation_pda(program_id, &group, &voter), false),
+            AccountMeta::new_readonly(voter, false), // voter_token_account placeholder
+            AccountMeta::new_readonly(system_program::ID, false), // token_program placeholder
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new_readonly(program_config_pda(program_id), false),
+        ],
+        data,
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let config = Config::from_args()?;
+    let program_id = Pubkey::from_str(PROGRAM_ID)?;
+    let rpc = Arc::new(RpcClient::new_with_commitment(
+        config.rpc_url.clone(),
+        CommitmentConfig::confirmed(),
+    ));
+
+    println!(
+        "Load test against {}: {} voters, {} proposals, concurrency {}",
+        config.rpc_url, config.voters, config.proposals, config.concurrency
+    );
+
+    // Payer: fresh keypair funded by airdrops (localnet faucets allow large amounts)
+    let payer = Arc::new(Keypair::new());
+    let needed = config.voters as u64 * (config.fund_lamports + 5_000) + 10 * LAMPORTS_PER_SOL;
+    let mut funded = 0;
+    while funded < needed {
+        let chunk = (needed - funded).min(500 * LAMPORTS_PER_SOL);
+        airdrop(&rpc, &payer.pubkey(), chunk).await?;
+        funded += chunk;
+    }
+    println!(
+        "Payer {} funded with {} SOL",
+        payer.pubkey(),
+        funded / LAMPORTS_PER_SOL
+    );
+
+    // Group
+    let group_id = format!("lt_{}", &uuid::Uuid::new_v4().simple().to_string()[..12]);
+    let (group, _) = Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &program_id);
+    send(
+        &rpc,
+        &[create_group_instruction(
+            program_id,
+            payer.pubkey(),
+            &group_id,
+        )],
+        &[&payer],
+    )
+    .await?;
+    println!("Created group {} ({})", group_id, group);
+
+    // Proposals: created concurrently to surface contention on the group's proposal counter
+    let now = chrono::Utc::now().timestamp();
+    let voting_start = now + 10;
+    let voting_end = now + 24 * 60 * 60;
+    let proposal_stats = Arc::new(Mutex::new(PhaseStats::default()));
+    let proposals = Arc::new(Mutex::new(Vec::new()));
+    let started = Instant::now();
+    let mut handles = Vec::new();
+    for _ in 0..config.proposals {
+        let (rpc, payer, stats, proposals) = (
+            rpc.clone(),
+            payer.clone(),
+            proposal_stats.clone(),
+            proposals.clone(),
+        );
+        let choices = config.choices;
+        handles.push(tokio::spawn(async move {
+            let proposal_id = uuid::Uuid::new_v4().to_string();
+            let op_started = Instant::now();
+            let result = async {
+                let count = group_proposal_count(&rpc, &group).await?;
+                let instruction = create_proposal_instruction(
+                    program_id,
+                    payer.pubkey(),
+                    group,
+                    count,
+                    &proposal_id,
+                    choices,
+                    voting_start,
+                    voting_end,
+                )?;
+                send(&rpc, &[instruction], &[&payer]).await
+            }
+            .await;
+            if result.is_ok() {
+                proposals
+                    .lock()
+                    .await
+                    .push(proposal_pda(program_id, &group, &proposal_id));
+            }
+            stats.lock().await.record(&result, op_started.elapsed());
+        }));
+    }
+    for handle in handles {
+        handle.await?;
+    }
+    proposal_stats
+        .lock()
+        .await
+        .report("create_proposal", started.elapsed());
+
+    let proposals = proposals.lock().await.clone();
+    if proposals.is_empty() {
+        return Err(anyhow::anyhow!("No proposals were created; aborting"));
+    }
+
+    // Voters: funded in batches of transfers from the payer
+    let voters: Vec<Arc<Keypair>> = (0..config.voters)
+        .map(|_| Arc::new(Keypair::new()))
+        .collect();
+    let funding_stats = Arc::new(Mutex::new(PhaseStats::default()));
+    let semaphore = Arc::new(Semaphore::new(config.concurrency));
+    let started = Instant::now();
+    let mut handles = Vec::new();
+    for batch in voters.chunks(FUNDING_BATCH) {
+        let instructions: Vec<Instruction> = batch
+            .iter()
+            .map(|voter| {
+                system_instruction::transfer(&payer.pubkey(), &voter.pubkey(), config.fund_lamports)
+            })
+            .collect();
+        let (rpc, payer, stats) = (rpc.clone(), payer.clone(), funding_stats.clone());
+        let permit = semaphore.clone().acquire_owned().await?;
+        handles.push(tokio::spawn(async move {
+            let op_started = Instant::now();
+            let result = send(&rpc, &instructions, &[&payer]).await;
+            stats.lock().await.record(&result, op_started.elapsed());
+            drop(permit);
+        }));
+    }
+    for handle in handles {
+        handle.await?;
+    }
+    funding_stats
+        .lock()
+        .await
+        .report("fund voters", started.elapsed());
+
+    // Votes can only be cast once the voting window opens
+    let wait = voting_start - chrono::Utc::now().timestamp() + 1;
+    if wait > 0 {
+        println!("\nWaiting {}s for voting to open...", wait);
+        tokio::time::sleep(Duration::from_secs(wait as u64)).await;
+    }
+
+    let vote_stats = Arc::new(Mutex::new(PhaseStats::default()));
+    let size_samples = Arc::new(Mutex::new(Vec::<(usize, usize)>::new())); // (votes, data len)
+    let started = Instant::now();
+    let mut handles = Vec::new();
+    for (i, voter) in voters.iter().enumerate() {
+        let proposal = proposals[i % proposals.len()];
+        let choice = (i % config.choices) as u8;
+        let (rpc, voter, stats, samples) = (
+            rpc.clone(),
+            voter.clone(),
+            vote_stats.clone(),
+            size_samples.clone(),
+        );
+        let sampled_proposal = proposals[0];
+        let permit = semaphore.clone().acquire_owned().await?;
+        handles.push(tokio::spawn(async move {
+            let op_started = Instant::now();
+            let instruction = vote_instruction(program_id, group, proposal, voter.pubkey(), choice);
+            let result = send(&rpc, &[instruction], &[&voter]).await;
+            let ok_count = {
+                let mut stats = stats.lock().await;
+                stats.record(&result, op_started.elapsed());
+                stats.ok
+            };
+            if result.is_ok() && ok_count % SIZE_SAMPLE_EVERY == 0 {
+                if let Ok(account) = rpc.get_account(&sampled_proposal).await {
+                    samples.lock().await.push((ok_count, account.data.len()));
+                }
+            }
+            drop(permit);
+        }));
+    }
+    for handle in handles {
+        handle.await?;
+    }
+    vote_stats
+        .lock()
+        .await
+        .report("vote_on_proposal", started.elapsed());
+
+    // Account-size breakpoints
+    println!("\n== account sizes ==");
+    for (votes, len) in size_samples.lock().await.iter() {
+        println!(
+            "  after {:>6} votes: first proposal is {} bytes",
+            votes, len
+        );
+    }
+    for proposal in &proposals {
+        let account = rpc.get_account(proposal).await?;
+        let headroom = MAX_ACCOUNT_DATA_LEN.saturating_sub(account.data.len()) / VOTER_INFO_LEN;
+        println!(
+            "  {}: {} bytes, rent {:.4} SOL, room for ~{} more voters before the 10 MiB limit",
+            proposal,
+            account.data.len(),
+            account.lamports as f64 / LAMPORTS_PER_SOL as f64,
+            headroom
+        );
+    }
+
+    Ok(())
+}
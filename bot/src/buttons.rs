@@ -0,0 +1,163 @@
+// Inline voting buttons under proposal announcements, so nobody has to type a proposal
+// id to vote.
+//
+// Each button carries a CallbackAction::Vote (see callbacks.rs). A press casts the
+// presser's vote through vote_on_proposal, exactly as /vote would, confirms it in the
+// callback answer (only the voter sees it) and edits the announcement's buttons to show
+// how many ballots each choice has. Split-weight votes and conflict-of-interest notes
+// still go through /vote.
+
+use crate::callbacks::CallbackAction;
+use crate::{
+    chat_vocabulary, ensure_user_account, get_proposal_results, solana_dao, vote_error_text,
+    vote_on_proposal, BotState,
+};
+
+use anchor_client::solana_sdk::signer::Signer;
+use teloxide::prelude::*;
+use teloxide::types::{InlineKeyboardMarkup, MessageId};
+
+// Telegram's limit on the text of a callback answer
+const MAX_ANSWER_LEN: usize = 200;
+
+// One button per row, so long choices stay readable. With `ballots`, each label ends in
+// the choice's ballot count. None when the proposal id is too long for callback data.
+pub fn vote_keyboard(
+    proposal_id: &str,
+    choices: &[String],
+    ballots: Option<&[u32]>,
+) -> Option<InlineKeyboardMarkup> {
+    let rows = choices
+        .iter()
+        .enumerate()
+        .map(|(i, choice)| {
+            let label = match ballots.and_then(|counts| counts.get(i)) {
+                Some(count) => format!("{}. {} ({})", i, choice, count),
+                None => format!("{}. {}", i, choice),
+            };
+            let action = CallbackAction::Vote {
+                proposal_id: proposal_id.to_string(),
+                choice: i as u8,
+            };
+            Some(vec![action.button(label)?])
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some(InlineKeyboardMarkup::new(rows))
+}
+
+pub fn ballot_counts(proposal: &solana_dao::Proposal) -> Vec<u32> {
+    let mut counts = vec![0; proposal.choices.len()];
+    for ballot in proposal.ballots() {
+        if let Some(count) = counts.get_mut(ballot.choice as usize) {
+            *count += 1;
+        }
+    }
+    counts
+}
+
+// Callback answers are one short line: the first line of the reply /vote would send
+fn answer_text(text: &str) -> String {
+    let line = text.lines().next().unwrap_or_default();
+    line.chars().take(MAX_ANSWER_LEN).collect()
+}
+
+pub async fn press_vote(
+    bot: Bot,
+    q: CallbackQuery,
+    message: Message,
+    proposal_id: String,
+    choice: u8,
+    state: BotState,
+) -> ResponseResult<()> {
+    let vocab = chat_vocabulary(&state, message.chat.id).await;
+    let telegram_id = q.from.id.0 as i64;
+    let group_id = format!("tg_{}", message.chat.id.0.abs());
+
+    let result = match ensure_user_account(&state, telegram_id).await {
+        Ok(keypair) => {
+            vote_on_proposal(
+                &state,
+                telegram_id,
+                &group_id,
+                &proposal_id,
+                choice,
+                None,
+                None,
+                keypair.pubkey(),
+            )
+            .await
+        }
+        Err(e) => Err(anyhow::anyhow!(
+            "Failed to access your account: {}. Please try /login first.",
+            e
+        )),
+    };
+
+    let (text, failed) = match result {
+        Ok(signature) => {
+            log::info!(
+                "Button vote by {} on {}: {}",
+                telegram_id,
+                proposal_id,
+                signature
+            );
+            (format!("✅ Vote cast for choice {}!", choice), false)
+        }
+        Err(e) => (vote_error_text(&e), true),
+    };
+    bot.answer_callback_query(q.id.clone())
+        .text(answer_text(&vocab.apply(&text)))
+        .show_alert(failed)
+        .await?;
+
+    if !failed {
+        if let Err(e) = refresh_vote_keyboard(
+            &bot,
+            &state,
+            message.chat.id,
+            message.id,
+            &group_id,
+            &proposal_id,
+        )
+        .await
+        {
+            log::warn!("Failed to refresh voting buttons: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+// Reloads the proposal and updates the ballot counts on an announcement's buttons
+pub async fn refresh_vote_keyboard(
+    bot: &Bot,
+    state: &BotState,
+    chat_id: ChatId,
+    message_id: MessageId,
+    group_id: &str,
+    proposal_id: &str,
+) -> anyhow::Result<()> {
+    let proposal = get_proposal_results(state, group_id, proposal_id).await?;
+    let counts = ballot_counts(&proposal);
+    if let Some(keyboard) = vote_keyboard(proposal_id, &proposal.choices, Some(&counts)) {
+        bot.edit_message_reply_markup(chat_id, message_id)
+            .reply_markup(keyboard)
+            .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keyboard_has_a_button_per_choice() {
+        let proposal_id = "0b5e4c8e-6a43-4a8e-9d1e-2f1f3c1b7a11";
+        let choices = vec!["Yes".to_string(), "No".to_string()];
+        let keyboard = vote_keyboard(proposal_id, &choices, Some(&[3, 1])).unwrap();
+        assert_eq!(keyboard.inline_keyboard.len(), 2);
+        assert_eq!(keyboard.inline_keyboard[0][0].text, "0. Yes (3)");
+        assert!(vote_keyboard(&"x".repeat(60), &choices, None).is_none());
+    }
+}
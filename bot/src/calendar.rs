@@ -0,0 +1,241 @@
+// Governance calendar: upcoming voting windows as an iCalendar (RFC 5545) feed and as
+// a summary for the /calendar command.
+//
+// Each proposal becomes one event spanning its voting window, so calendar apps show
+// when voting opens and remind members before it closes.
+
+use crate::solana_dao;
+
+use chrono::{DateTime, Utc};
+
+// How far ahead /calendar looks
+pub const CALENDAR_HORIZON_SECS: i64 = 14 * 24 * 60 * 60;
+// Calendar apps expect lines of at most 75 octets, continued with a leading space
+const ICS_LINE_LIMIT: usize = 75;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeadlineKind {
+    Opens,
+    Closes,
+}
+
+// A voting window opening or closing
+pub struct Deadline<'a> {
+    pub at: i64,
+    pub kind: DeadlineKind,
+    pub proposal: &'a solana_dao::Proposal,
+}
+
+// Deadlines between `now` and `now + horizon_secs`, soonest first
+pub fn upcoming_deadlines(
+    proposals: &[solana_dao::Proposal],
+    now: i64,
+    horizon_secs: i64,
+) -> Vec<Deadline<'_>> {
+    let until = now.saturating_add(horizon_secs);
+    let mut deadlines: Vec<Deadline> = proposals
+        .iter()
+        .flat_map(|proposal| {
+            [
+                (proposal.voting_start, DeadlineKind::Opens),
+                (proposal.voting_end, DeadlineKind::Closes),
+            ]
+            .into_iter()
+            .map(move |(at, kind)| Deadline { at, kind, proposal })
+        })
+        .filter(|d| d.at >= now && d.at <= until)
+        .collect();
+    deadlines.sort_by_key(|d| d.at);
+    deadlines
+}
+
+// Render a group's proposals as an iCalendar feed
+pub fn ics_feed(group_id: &str, proposals: &[solana_dao::Proposal], now: i64) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//solana-dao//governance calendar//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        format!(
+            "X-WR-CALNAME:{}",
+            escape_text(&format!("{} governance", group_id))
+        ),
+    ];
+
+    for proposal in proposals {
+        let mut description = proposal.description.clone();
+        if !proposal.choices.is_empty() {
+            description.push_str(&format!("\n\nChoices: {}", proposal.choices.join(", ")));
+        }
+        description.push_str(&format!("\nProposal ID: {}", proposal.proposal_id));
+
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!(
+            "UID:{}",
+            escape_text(&format!("{}.{}@solana-dao", proposal.proposal_id, group_id))
+        ));
+        lines.push(format!("DTSTAMP:{}", ics_time(now)));
+        lines.push(format!("DTSTART:{}", ics_time(proposal.voting_start)));
+        lines.push(format!("DTEND:{}", ics_time(proposal.voting_end)));
+        lines.push(format!(
+            "SUMMARY:{}",
+            escape_text(&format!("Voting: {}", proposal.title))
+        ));
+        lines.push(format!("DESCRIPTION:{}", escape_text(&description)));
+        if let Some(uri) = &proposal.metadata_uri {
+            lines.push(format!("URL:{}", uri));
+        }
+        // Remind attendees a day before voting closes
+        lines.push("BEGIN:VALARM".to_string());
+        lines.push("ACTION:DISPLAY".to_string());
+        lines.push(format!(
+            "DESCRIPTION:{}",
+            escape_text(&format!("Voting closes: {}", proposal.title))
+        ));
+        lines.push("TRIGGER;RELATED=END:-P1D".to_string());
+        lines.push("END:VALARM".to_string());
+        lines.push("END:VEVENT".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    lines
+        .iter()
+        .map(|line| fold_line(line))
+        .collect::<Vec<_>>()
+        .join("\r\n")
+        + "\r\n"
+}
+
+fn ics_time(timestamp: i64) -> String {
+    DateTime::<Utc>::from_timestamp(timestamp, 0)
+        .unwrap_or_default()
+        .format("%Y%m%dT%H%M%SZ")
+        .to_string()
+}
+
+// Escape a TEXT value (RFC 5545 section 3.3.11)
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Fold a content line into 75-octet chunks without splitting UTF-8 characters
+fn fold_line(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + line.len() / ICS_LINE_LIMIT * 3);
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > ICS_LINE_LIMIT {
+            folded.push_str("\r\n ");
+            // The leading space counts toward the continuation line's length
+            width = 1;
+        }
+        folded.push(c);
+        width += c.len_utf8();
+    }
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_client::solana_sdk::pubkey::Pubkey;
+
+    fn proposal(
+        proposal_id: &str,
+        title: &str,
+        voting_start: i64,
+        voting_end: i64,
+    ) -> solana_dao::Proposal {
+        solana_dao::Proposal {
+            version: 1,
+            proposal_id: proposal_id.to_string(),
+            group_id: "tg_1".to_string(),
+            title: title.to_string(),
+            description: "Line one\nline two".to_string(),
+            choices: vec!["Yes".to_string(), "No".to_string()],
+            choice_votes: vec![0, 0],
+            voting_start,
+            voting_end,
+            token_mint: None,
+            creator: Pubkey::default(),
+            voters: Vec::new(),
+            created_at: 0,
+            index: 0,
+            metadata_uri: None,
+            status: solana_dao::ProposalStatus::Active,
+            finalize_bounty: 0,
+            shadow_votes: Vec::new(),
+            offchain_aggregator: None,
+            offchain_voter_count: None,
+            price_weighted: false,
+            member_weighted: false,
+            kind: solana_dao::ProposalKind::Text,
+            emergency: false,
+            split_voting: false,
+            slot_window: None,
+            weight_decay_bps: 0,
+            deposit_voting: None,
+            council_chamber: None,
+            winner: None,
+            tied: false,
+            sponsors_needed: 0,
+            sponsor_count: 0,
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn lists_deadlines_within_the_horizon_in_order() {
+        let proposals = vec![
+            proposal("later", "Later", 500, 2_000),
+            proposal("now", "Now", 50, 300),
+        ];
+        let deadlines = upcoming_deadlines(&proposals, 100, 1_000);
+
+        let summary: Vec<_> = deadlines
+            .iter()
+            .map(|d| (d.proposal.proposal_id.as_str(), d.kind, d.at))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("now", DeadlineKind::Closes, 300),
+                ("later", DeadlineKind::Opens, 500),
+            ]
+        );
+    }
+
+    #[test]
+    fn renders_escaped_events() {
+        let feed = ics_feed("tg_1", &[proposal("p1", "Budget; Q3, part 2", 0, 3_600)], 0);
+
+        assert!(feed.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(feed.ends_with("END:VCALENDAR\r\n"));
+        assert!(feed.contains("UID:p1.tg_1@solana-dao\r\n"));
+        assert!(feed.contains("DTSTART:19700101T000000Z\r\n"));
+        assert!(feed.contains("DTEND:19700101T010000Z\r\n"));
+        assert!(feed.contains("SUMMARY:Voting: Budget\\; Q3\\, part 2\r\n"));
+        assert!(feed.contains("DESCRIPTION:Line one\\nline two"));
+    }
+
+    #[test]
+    fn folds_long_lines_on_character_boundaries() {
+        let line = format!("SUMMARY:{}", "é".repeat(60));
+        let folded = fold_line(&line);
+
+        for part in folded.split("\r\n") {
+            assert!(part.len() <= ICS_LINE_LIMIT);
+        }
+        assert_eq!(folded.replace("\r\n ", ""), line);
+    }
+}
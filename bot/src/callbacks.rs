@@ -0,0 +1,426 @@
+// Routing for inline-button presses, so votes, confirmations and list pages can be
+// button-driven instead of command-only.
+//
+// A button's callback data is a serialized CallbackAction: an action tag followed by its
+// ids, joined with ':' (e.g. "vote:<proposal_id>:<choice>", "page:proposals:2"). Telegram
+// caps callback data at 64 bytes, so actions that wouldn't fit produce no button.
+//
+// Commands that need a yes/no before signing register a PendingConfirmation and attach
+// confirm_keyboard; only the user who asked can confirm, and only for
+// CONFIRMATION_TTL_SECS. Pending confirmations are kept in memory, so a restart drops them.
+
+use crate::{
+    buttons, chat_vocabulary, localization, proposal_list_page, reader_language_of, run_delegate,
+    run_ragequit, run_undelegate, run_withdraw, BotState,
+};
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use chrono::Utc;
+use std::collections::HashMap;
+use teloxide::prelude::*;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup, ParseMode};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+// Telegram's limit on callback data
+pub const MAX_CALLBACK_DATA_LEN: usize = 64;
+pub const CONFIRMATION_TTL_SECS: i64 = 600;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CallbackAction {
+    Vote { proposal_id: String, choice: u8 },
+    Page { list: PagedList, page: usize },
+    Confirm { id: String },
+    Cancel { id: String },
+}
+
+// Lists whose pages are turned with buttons
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PagedList {
+    Proposals,
+}
+
+impl PagedList {
+    fn tag(self) -> &'static str {
+        match self {
+            PagedList::Proposals => "proposals",
+        }
+    }
+
+    fn from_tag(tag: &str) -> Option<Self> {
+        match tag {
+            "proposals" => Some(PagedList::Proposals),
+            _ => None,
+        }
+    }
+}
+
+impl CallbackAction {
+    // None when the data would exceed MAX_CALLBACK_DATA_LEN
+    pub fn encode(&self) -> Option<String> {
+        let data = match self {
+            CallbackAction::Vote {
+                proposal_id,
+                choice,
+            } => format!("vote:{}:{}", proposal_id, choice),
+            CallbackAction::Page { list, page } => format!("page:{}:{}", list.tag(), page),
+            CallbackAction::Confirm { id } => format!("confirm:{}", id),
+            CallbackAction::Cancel { id } => format!("cancel:{}", id),
+        };
+        (data.len() <= MAX_CALLBACK_DATA_LEN).then_some(data)
+    }
+
+    pub fn decode(data: &str) -> Option<Self> {
+        let (tag, rest) = data.split_once(':')?;
+        let action = match tag {
+            "vote" => {
+                // Proposal ids may contain ':', the choice never does
+                let (proposal_id, choice) = rest.rsplit_once(':')?;
+                if proposal_id.is_empty() {
+                    return None;
+                }
+                CallbackAction::Vote {
+                    proposal_id: proposal_id.to_string(),
+                    choice: choice.parse().ok()?,
+                }
+            }
+            "page" => {
+                let (list, page) = rest.split_once(':')?;
+                CallbackAction::Page {
+                    list: PagedList::from_tag(list)?,
+                    page: page.parse().ok()?,
+                }
+            }
+            "confirm" if !rest.is_empty() => CallbackAction::Confirm {
+                id: rest.to_string(),
+            },
+            "cancel" if !rest.is_empty() => CallbackAction::Cancel {
+                id: rest.to_string(),
+            },
+            _ => return None,
+        };
+        Some(action)
+    }
+
+    pub fn button(&self, label: impl Into<String>) -> Option<InlineKeyboardButton> {
+        Some(InlineKeyboardButton::callback(label, self.encode()?))
+    }
+}
+
+// Previous/next buttons for page `page` of `pages`; None when there's only one page
+pub fn page_keyboard(
+    vocab: &localization::Vocabulary,
+    list: PagedList,
+    page: usize,
+    pages: usize,
+) -> Option<InlineKeyboardMarkup> {
+    let mut row = Vec::new();
+    if page > 0 {
+        row.push(
+            CallbackAction::Page {
+                list,
+                page: page - 1,
+            }
+            .button(vocab.apply("◀️ Previous"))?,
+        );
+    }
+    if page + 1 < pages {
+        row.push(
+            CallbackAction::Page {
+                list,
+                page: page + 1,
+            }
+            .button(vocab.apply("Next ▶️"))?,
+        );
+    }
+    (!row.is_empty()).then(|| InlineKeyboardMarkup::new(vec![row]))
+}
+
+// Actions that wait for their requester to press Confirm
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConfirmedAction {
+    Ragequit {
+        group_id: String,
+        shares: u64,
+    },
+    Delegate {
+        group_id: String,
+        delegate: Pubkey,
+        // How the delegate was named, for the outcome message
+        label: String,
+        expires_at: Option<i64>,
+    },
+    Undelegate {
+        group_id: String,
+    },
+    Withdraw {
+        destination: Pubkey,
+        lamports: u64,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct PendingConfirmation {
+    pub user_id: UserId,
+    pub chat_id: ChatId,
+    pub action: ConfirmedAction,
+    pub expires_at: i64,
+}
+
+#[derive(Default)]
+pub struct Confirmations {
+    pending: Mutex<HashMap<String, PendingConfirmation>>,
+}
+
+impl Confirmations {
+    // Returns the id to put in the Confirm and Cancel buttons
+    pub async fn register(
+        &self,
+        user_id: UserId,
+        chat_id: ChatId,
+        action: ConfirmedAction,
+    ) -> String {
+        let now = Utc::now().timestamp();
+        let id = Uuid::new_v4().simple().to_string();
+        let mut pending = self.pending.lock().await;
+        pending.retain(|_, confirmation| confirmation.expires_at > now);
+        pending.insert(
+            id.clone(),
+            PendingConfirmation {
+                user_id,
+                chat_id,
+                action,
+                expires_at: now + CONFIRMATION_TTL_SECS,
+            },
+        );
+        id
+    }
+
+    // Removes and returns the confirmation if `user_id` asked for it in `chat_id` and it
+    // hasn't expired; anyone else's press leaves it in place
+    pub async fn take(
+        &self,
+        id: &str,
+        user_id: UserId,
+        chat_id: ChatId,
+    ) -> Result<ConfirmedAction, &'static str> {
+        let mut pending = self.pending.lock().await;
+        let confirmation = pending.get(id).ok_or("⌛ This request has expired.")?;
+        if confirmation.user_id != user_id || confirmation.chat_id != chat_id {
+            return Err("❌ Only the person who asked can answer this.");
+        }
+        let confirmation = pending.remove(id).ok_or("⌛ This request has expired.")?;
+        if confirmation.expires_at <= Utc::now().timestamp() {
+            return Err("⌛ This request has expired.");
+        }
+        Ok(confirmation.action)
+    }
+}
+
+pub fn confirm_keyboard(
+    vocab: &localization::Vocabulary,
+    id: &str,
+) -> Option<InlineKeyboardMarkup> {
+    Some(InlineKeyboardMarkup::new(vec![vec![
+        CallbackAction::Confirm { id: id.to_string() }.button(vocab.apply("✅ Confirm"))?,
+        CallbackAction::Cancel { id: id.to_string() }.button(vocab.apply("✖️ Cancel"))?,
+    ]]))
+}
+
+pub async fn handle_callback(bot: Bot, q: CallbackQuery, state: BotState) -> ResponseResult<()> {
+    let (Some(action), Some(message)) = (
+        q.data.as_deref().and_then(CallbackAction::decode),
+        q.message.clone(),
+    ) else {
+        bot.answer_callback_query(q.id).await?;
+        return Ok(());
+    };
+
+    match action {
+        CallbackAction::Vote {
+            proposal_id,
+            choice,
+        } => buttons::press_vote(bot, q, message, proposal_id, choice, state).await,
+        CallbackAction::Page { list, page } => {
+            bot.answer_callback_query(q.id.clone()).await?;
+            match list {
+                PagedList::Proposals => {
+                    let group_id = format!("tg_{}", message.chat.id.0.abs());
+                    let language = reader_language_of(&state, &q.from).await;
+                    match proposal_list_page(&state, &group_id, language.as_deref(), page).await {
+                        Ok(Some((text, pages))) => {
+                            let vocab = chat_vocabulary(&state, message.chat.id).await;
+                            let mut edit = bot
+                                .edit_message_text(message.chat.id, message.id, vocab.apply(&text))
+                                .parse_mode(ParseMode::Html);
+                            if let Some(keyboard) = page_keyboard(&vocab, list, page, pages) {
+                                edit = edit.reply_markup(keyboard);
+                            }
+                            if let Err(e) = edit.await {
+                                log::warn!("Failed to turn the proposal list page: {}", e);
+                            }
+                        }
+                        Ok(None) => {}
+                        Err(e) => log::warn!("Failed to fetch proposals: {}", e),
+                    }
+                }
+            }
+            Ok(())
+        }
+        CallbackAction::Confirm { id } => {
+            answer_confirmation(bot, q, message, &id, true, state).await
+        }
+        CallbackAction::Cancel { id } => {
+            answer_confirmation(bot, q, message, &id, false, state).await
+        }
+    }
+}
+
+// Carries out or drops a pending confirmation and replaces its prompt with the outcome
+async fn answer_confirmation(
+    bot: Bot,
+    q: CallbackQuery,
+    message: Message,
+    id: &str,
+    confirmed: bool,
+    state: BotState,
+) -> ResponseResult<()> {
+    let vocab = chat_vocabulary(&state, message.chat.id).await;
+    let action = match state
+        .confirmations
+        .take(id, q.from.id, message.chat.id)
+        .await
+    {
+        Ok(action) => action,
+        Err(reason) => {
+            bot.answer_callback_query(q.id)
+                .text(vocab.apply(reason))
+                .show_alert(true)
+                .await?;
+            return Ok(());
+        }
+    };
+    bot.answer_callback_query(q.id).await?;
+
+    let outcome = if !confirmed {
+        "✖️ Cancelled.".to_string()
+    } else {
+        match action {
+            ConfirmedAction::Ragequit { group_id, shares } => {
+                run_ragequit(&state, q.from.id.0 as i64, &group_id, shares).await
+            }
+            ConfirmedAction::Delegate {
+                group_id,
+                delegate,
+                label,
+                expires_at,
+            } => {
+                run_delegate(
+                    &state,
+                    q.from.id.0 as i64,
+                    &group_id,
+                    delegate,
+                    &label,
+                    expires_at,
+                )
+                .await
+            }
+            ConfirmedAction::Undelegate { group_id } => {
+                run_undelegate(&state, q.from.id.0 as i64, &group_id).await
+            }
+            ConfirmedAction::Withdraw {
+                destination,
+                lamports,
+            } => run_withdraw(&state, q.from.id.0 as i64, destination, lamports).await,
+        }
+    };
+    if let Err(e) = bot
+        .edit_message_text(message.chat.id, message.id, vocab.apply(&outcome))
+        .await
+    {
+        log::warn!("Failed to record the confirmation outcome: {}", e);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn actions_round_trip_through_callback_data() {
+        let actions = [
+            CallbackAction::Vote {
+                proposal_id: "0b5e4c8e-6a43-4a8e-9d1e-2f1f3c1b7a11".to_string(),
+                choice: 12,
+            },
+            CallbackAction::Page {
+                list: PagedList::Proposals,
+                page: 3,
+            },
+            CallbackAction::Confirm {
+                id: Uuid::new_v4().simple().to_string(),
+            },
+            CallbackAction::Cancel {
+                id: "abc".to_string(),
+            },
+        ];
+        for action in actions {
+            let data = action.encode().unwrap();
+            assert!(data.len() <= MAX_CALLBACK_DATA_LEN);
+            assert_eq!(CallbackAction::decode(&data), Some(action));
+        }
+
+        for data in [
+            "vote::1",
+            "vote:abc:x",
+            "page:groups:1",
+            "confirm:",
+            "other:abc",
+        ] {
+            assert_eq!(CallbackAction::decode(data), None, "{}", data);
+        }
+        let long = CallbackAction::Vote {
+            proposal_id: "x".repeat(60),
+            choice: 0,
+        };
+        assert_eq!(long.encode(), None);
+    }
+
+    #[test]
+    fn page_keyboard_offers_only_existing_pages() {
+        let vocab = localization::Vocabulary::default();
+        assert!(page_keyboard(&vocab, PagedList::Proposals, 0, 1).is_none());
+        let first = page_keyboard(&vocab, PagedList::Proposals, 0, 3).unwrap();
+        assert_eq!(first.inline_keyboard[0].len(), 1);
+        assert_eq!(first.inline_keyboard[0][0].text, "Next ▶️");
+        let middle = page_keyboard(&vocab, PagedList::Proposals, 1, 3).unwrap();
+        assert_eq!(middle.inline_keyboard[0].len(), 2);
+    }
+
+    #[tokio::test]
+    async fn only_the_requester_can_confirm() {
+        let confirmations = Confirmations::default();
+        let action = ConfirmedAction::Ragequit {
+            group_id: "tg_1".to_string(),
+            shares: 5,
+        };
+        let id = confirmations
+            .register(UserId(7), ChatId(-1), action.clone())
+            .await;
+
+        assert!(confirmations
+            .take(&id, UserId(8), ChatId(-1))
+            .await
+            .is_err());
+        assert_eq!(
+            confirmations.take(&id, UserId(7), ChatId(-1)).await,
+            Ok(action)
+        );
+        // Taken once, so a second press finds nothing
+        assert!(confirmations
+            .take(&id, UserId(7), ChatId(-1))
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,211 @@
+// Results charts: a horizontal bar chart of a proposal's tally, sent as a photo with the
+// results as its caption by /results and the automatic result announcements.
+//
+// Labels are drawn with the TrueType font at CHART_FONT (DejaVu Sans by default, as
+// packaged by most Linux distributions); without it the bars are drawn unlabeled, in
+// choice order. Captions are capped at Telegram's 1024 characters, so longer results go
+// out as a message right after the chart. Where a chart can't be rendered or sent, the
+// results are sent as text alone. CHARTS=off turns charts off.
+
+use crate::{localization, solana_dao};
+
+use image::{codecs::png::PngEncoder, ColorType, ImageEncoder};
+use plotters::prelude::*;
+use std::sync::OnceLock;
+use teloxide::prelude::*;
+use teloxide::types::{InputFile, MessageId, ParseMode};
+
+pub const DEFAULT_CHART_FONT: &str = "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf";
+// Telegram's caption limit
+pub const CAPTION_LIMIT: usize = 1024;
+const WIDTH: u32 = 800;
+const ROW_HEIGHT: u32 = 64;
+const MARGIN: i32 = 24;
+const LABEL_CHARS: usize = 48;
+const FONT_FAMILY: &str = "sans-serif";
+
+// Bar colors, cycled through for proposals with more choices
+const PALETTE: [RGBColor; 6] = [
+    RGBColor(59, 130, 246),
+    RGBColor(239, 68, 68),
+    RGBColor(34, 197, 94),
+    RGBColor(234, 179, 8),
+    RGBColor(168, 85, 247),
+    RGBColor(249, 115, 22),
+];
+const TRACK: RGBColor = RGBColor(229, 231, 235);
+const INK: RGBColor = RGBColor(31, 41, 55);
+
+pub fn enabled() -> bool {
+    std::env::var("CHARTS").map_or(true, |value| value != "off")
+}
+
+// Registers CHART_FONT with plotters once; false when no font could be loaded
+fn font_loaded() -> bool {
+    static LOADED: OnceLock<bool> = OnceLock::new();
+    *LOADED.get_or_init(|| {
+        let path = std::env::var("CHART_FONT").unwrap_or_else(|_| DEFAULT_CHART_FONT.to_string());
+        let bytes = match std::fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                log::info!(
+                    "No chart font at {} ({}); drawing charts unlabeled",
+                    path,
+                    e
+                );
+                return false;
+            }
+        };
+        // plotters keeps fonts for the life of the process
+        let bytes: &'static [u8] = Box::leak(bytes.into_boxed_slice());
+        match plotters::style::register_font(FONT_FAMILY, FontStyle::Normal, bytes) {
+            Ok(()) => true,
+            Err(_) => {
+                log::warn!(
+                    "{} isn't a usable TrueType font; drawing charts unlabeled",
+                    path
+                );
+                false
+            }
+        }
+    })
+}
+
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() > max {
+        format!("{}…", text.chars().take(max - 1).collect::<String>())
+    } else {
+        text.to_string()
+    }
+}
+
+// A PNG with one bar per choice, its length the choice's share of the votes
+pub fn render(choices: &[String], votes: &[u64]) -> anyhow::Result<Vec<u8>> {
+    let labeled = font_loaded();
+    let rows = choices.len().max(1) as u32;
+    let height = ROW_HEIGHT * rows + 2 * MARGIN as u32;
+    let mut pixels = vec![0u8; (WIDTH * height * 3) as usize];
+    {
+        let root = BitMapBackend::with_buffer(&mut pixels, (WIDTH, height)).into_drawing_area();
+        root.fill(&WHITE)?;
+
+        // Summed as u128: lamport-weighted tallies can overflow u64 when added together
+        let total: u128 = votes.iter().map(|&v| v as u128).sum();
+        let bar_left = MARGIN;
+        let bar_right = WIDTH as i32 - MARGIN;
+        for (i, choice) in choices.iter().enumerate() {
+            let count = votes.get(i).copied().unwrap_or_default();
+            let share = if total > 0 {
+                count as f64 / total as f64
+            } else {
+                0.0
+            };
+            let top = MARGIN + (i as u32 * ROW_HEIGHT) as i32;
+            // Labels sit above the bar when there's a font to draw them with
+            let bar_top = if labeled { top + 26 } else { top + 12 };
+            let bar_bottom = top + ROW_HEIGHT as i32 - 12;
+
+            root.draw(&Rectangle::new(
+                [(bar_left, bar_top), (bar_right, bar_bottom)],
+                TRACK.filled(),
+            ))?;
+            let filled = bar_left + ((bar_right - bar_left) as f64 * share).round() as i32;
+            if filled > bar_left {
+                root.draw(&Rectangle::new(
+                    [(bar_left, bar_top), (filled, bar_bottom)],
+                    PALETTE[i % PALETTE.len()].filled(),
+                ))?;
+            }
+
+            if labeled {
+                let style = (FONT_FAMILY, 18).into_font().color(&INK);
+                root.draw(&Text::new(
+                    format!("{}. {}", i, truncate(choice, LABEL_CHARS)),
+                    (bar_left, top),
+                    style.clone(),
+                ))?;
+                let figure = format!("{} ({:.1}%)", count, share * 100.0);
+                let (width, _) = root.estimate_text_size(&figure, &style)?;
+                root.draw(&Text::new(figure, (bar_right - width as i32, top), style))?;
+            }
+        }
+        root.present()?;
+    }
+
+    let mut png = Vec::new();
+    PngEncoder::new(&mut png).write_image(&pixels, WIDTH, height, ColorType::Rgb8)?;
+    Ok(png)
+}
+
+// Sends `text` (HTML) with the proposal's chart, replying to `reply_to` if given, and
+// returns the message carrying the results text
+pub async fn send_results(
+    bot: &Bot,
+    vocab: &localization::Vocabulary,
+    chat_id: ChatId,
+    proposal: &solana_dao::Proposal,
+    text: String,
+    reply_to: Option<MessageId>,
+) -> anyhow::Result<Message> {
+    let text = vocab.apply(&text);
+    if enabled() {
+        match render(&proposal.choices, &proposal.choice_votes) {
+            Ok(png) => {
+                let fits = text.chars().count() <= CAPTION_LIMIT;
+                let photo = InputFile::memory(png)
+                    .file_name(format!("results-{}.png", proposal.proposal_id));
+                let mut request = bot.send_photo(chat_id, photo);
+                if fits {
+                    request = request.caption(text.clone()).parse_mode(ParseMode::Html);
+                }
+                if let Some(message_id) = reply_to {
+                    request = request
+                        .reply_to_message_id(message_id)
+                        .allow_sending_without_reply(true);
+                }
+                match request.await {
+                    Ok(message) if fits => return Ok(message),
+                    Ok(message) => {
+                        // The chart leads; the results follow as a reply to it
+                        return Ok(bot
+                            .send_message(chat_id, text)
+                            .parse_mode(ParseMode::Html)
+                            .reply_to_message_id(message.id)
+                            .allow_sending_without_reply(true)
+                            .await?);
+                    }
+                    Err(e) => log::warn!("Failed to send the results chart: {}", e),
+                }
+            }
+            Err(e) => log::warn!(
+                "Failed to render the chart of {}: {}",
+                proposal.proposal_id,
+                e
+            ),
+        }
+    }
+
+    let mut request = bot.send_message(chat_id, text).parse_mode(ParseMode::Html);
+    if let Some(message_id) = reply_to {
+        request = request
+            .reply_to_message_id(message_id)
+            .allow_sending_without_reply(true);
+    }
+    Ok(request.await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn charts_render_as_png() {
+        let png = render(&["Yes".to_string(), "No".to_string()], &[3, 1]).unwrap();
+        let image = image::load_from_memory(&png).unwrap();
+        assert_eq!(image.width(), WIDTH);
+        assert_eq!(image.height(), 2 * ROW_HEIGHT + 2 * MARGIN as u32);
+
+        // A tally without votes still draws empty tracks
+        assert!(render(&["Yes".to_string()], &[0]).is_ok());
+    }
+}
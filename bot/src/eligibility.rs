@@ -0,0 +1,236 @@
+// Voting eligibility preview: who could vote on a proposal right now and with what
+// weight, so admins can sanity-check the member list and token gating before voting
+// opens.
+//
+// The group's member records are the voter list. Weights are read live, the way
+// vote_on_proposal computes them when a ballot is cast: one vote per member, each
+// member's set weight on member-weighted proposals, the wallet's lamports for
+// SOL-weighted proposals, or the balance of its associated token account for SPL
+// proposals. Balances can still change before a member votes. Linked
+// wallets add weight at vote time but can't be found from the roster, so they're left out.
+
+use crate::{get_proposal_results, group_members, solana_dao, BotState};
+
+use anchor_client::solana_sdk::{program_pack::Pack, pubkey::Pubkey};
+use chrono::Utc;
+use serde::Serialize;
+
+// Accounts fetched per getMultipleAccounts call (the RPC limit)
+const ACCOUNTS_PER_REQUEST: usize = 100;
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct EligibleVoter {
+    pub wallet: String,
+    pub weight: u64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct EligibilityPreview {
+    pub group_id: String,
+    pub proposal_id: String,
+    // "member", "weighted", "sol" or "spl"
+    pub mode: &'static str,
+    pub token_mint: Option<String>,
+    // Members with voting power, heaviest first
+    pub eligible: Vec<EligibleVoter>,
+    // Members who would be rejected with NoVotingPower
+    pub ineligible: Vec<String>,
+    pub total_weight: u128,
+    pub generated_at: i64,
+}
+
+pub fn mode(proposal: &solana_dao::Proposal) -> &'static str {
+    match proposal.token_mint {
+        None if proposal.member_weighted => "weighted",
+        None => "member",
+        Some(mint) if mint == solana_dao::NATIVE_MINT => "sol",
+        Some(_) => "spl",
+    }
+}
+
+// Split members by weight; ties keep roster order
+pub fn build_preview(
+    group_id: &str,
+    proposal: &solana_dao::Proposal,
+    weights: Vec<(Pubkey, u64)>,
+    now: i64,
+) -> EligibilityPreview {
+    let (mut eligible, ineligible): (Vec<_>, Vec<_>) =
+        weights.into_iter().partition(|(_, weight)| *weight > 0);
+    eligible.sort_by_key(|(_, weight)| std::cmp::Reverse(*weight));
+
+    EligibilityPreview {
+        group_id: group_id.to_string(),
+        proposal_id: proposal.proposal_id.clone(),
+        mode: mode(proposal),
+        token_mint: proposal.token_mint.map(|mint| mint.to_string()),
+        total_weight: eligible.iter().map(|(_, weight)| *weight as u128).sum(),
+        eligible: eligible
+            .into_iter()
+            .map(|(wallet, weight)| EligibleVoter {
+                wallet: wallet.to_string(),
+                weight,
+            })
+            .collect(),
+        ineligible: ineligible
+            .into_iter()
+            .map(|(wallet, _)| wallet.to_string())
+            .collect(),
+        generated_at: now,
+    }
+}
+
+// One wallet's weight, read the same way as the roster's
+pub async fn wallet_weight(
+    state: &BotState,
+    token_mint: Option<Pubkey>,
+    wallet: &Pubkey,
+) -> anyhow::Result<u64> {
+    let rpc = state.program.rpc();
+    Ok(match token_mint {
+        None => 1,
+        Some(mint) if mint == solana_dao::NATIVE_MINT => rpc.get_balance(wallet).await?,
+        Some(mint) => {
+            let address = spl_associated_token_account::get_associated_token_address(wallet, &mint);
+            match rpc.get_account(&address).await {
+                Ok(account) if account.owner == spl_token::ID => {
+                    spl_token::state::Account::unpack(&account.data)
+                        .map(|token_account| token_account.amount)
+                        .unwrap_or(0)
+                }
+                _ => 0,
+            }
+        }
+    })
+}
+
+pub async fn preview(
+    state: &BotState,
+    group_id: &str,
+    proposal_id: &str,
+) -> anyhow::Result<EligibilityPreview> {
+    let proposal = get_proposal_results(state, group_id, proposal_id).await?;
+
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let records = group_members(state, &group_pda).await?;
+    let members: Vec<Pubkey> = records.iter().map(|m| m.wallet).collect();
+
+    let weights = match proposal.token_mint {
+        None if proposal.member_weighted => records
+            .iter()
+            .map(|m| (m.wallet, u64::from(m.weight)))
+            .collect(),
+        None => members.iter().map(|wallet| (*wallet, 1)).collect(),
+        Some(mint) => {
+            let sol = mint == solana_dao::NATIVE_MINT;
+            let addresses: Vec<Pubkey> = members
+                .iter()
+                .map(|wallet| {
+                    if sol {
+                        *wallet
+                    } else {
+                        spl_associated_token_account::get_associated_token_address(wallet, &mint)
+                    }
+                })
+                .collect();
+
+            let mut weights = Vec::with_capacity(members.len());
+            for (wallets, chunk) in members
+                .chunks(ACCOUNTS_PER_REQUEST)
+                .zip(addresses.chunks(ACCOUNTS_PER_REQUEST))
+            {
+                let accounts = state.program.rpc().get_multiple_accounts(chunk).await?;
+                for (wallet, account) in wallets.iter().zip(accounts) {
+                    let weight = match account {
+                        Some(account) if sol => account.lamports,
+                        Some(account) if account.owner == spl_token::ID => {
+                            spl_token::state::Account::unpack(&account.data)
+                                .map(|token_account| token_account.amount)
+                                .unwrap_or(0)
+                        }
+                        _ => 0,
+                    };
+                    weights.push((*wallet, weight));
+                }
+            }
+            weights
+        }
+    };
+
+    Ok(build_preview(
+        group_id,
+        &proposal,
+        weights,
+        Utc::now().timestamp(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranks_members_with_voting_power() {
+        let proposal = solana_dao::Proposal {
+            version: 1,
+            proposal_id: "p1".to_string(),
+            group_id: "tg_1".to_string(),
+            title: String::new(),
+            description: String::new(),
+            choices: vec!["Yes".to_string(), "No".to_string()],
+            choice_votes: vec![0, 0],
+            voting_start: 0,
+            voting_end: 0,
+            token_mint: Some(solana_dao::NATIVE_MINT),
+            creator: Pubkey::default(),
+            voters: Vec::new(),
+            created_at: 0,
+            index: 0,
+            metadata_uri: None,
+            status: solana_dao::ProposalStatus::Draft,
+            finalize_bounty: 0,
+            shadow_votes: Vec::new(),
+            offchain_aggregator: None,
+            offchain_voter_count: None,
+            price_weighted: false,
+            member_weighted: false,
+            kind: solana_dao::ProposalKind::Text,
+            emergency: false,
+            split_voting: false,
+            slot_window: None,
+            weight_decay_bps: 0,
+            deposit_voting: None,
+            council_chamber: None,
+            winner: None,
+            tied: false,
+            sponsors_needed: 0,
+            sponsor_count: 0,
+            bump: 0,
+        };
+        let (a, b, c) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+
+        let preview = build_preview("tg_1", &proposal, vec![(a, 5), (b, 0), (c, 9)], 42);
+
+        assert_eq!(preview.mode, "sol");
+        assert_eq!(
+            preview.eligible,
+            vec![
+                EligibleVoter {
+                    wallet: c.to_string(),
+                    weight: 9
+                },
+                EligibleVoter {
+                    wallet: a.to_string(),
+                    weight: 5
+                },
+            ]
+        );
+        assert_eq!(preview.ineligible, vec![b.to_string()]);
+        assert_eq!(preview.total_weight, 14);
+    }
+}
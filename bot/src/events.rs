@@ -0,0 +1,367 @@
+// Live on-chain events: a logsSubscribe WebSocket subscription to the program, so chats
+// linked to a group see activity as it lands instead of only when someone runs a
+// command.
+//
+// Anchor events arrive as "Program data: <base64>" log lines, an 8-byte event
+// discriminator followed by the borsh-encoded event. Only lines logged by this program
+// itself are decoded, never those of programs it invokes, and failed transactions are
+// skipped. A VoteCastEvent refreshes the ballot counts on the proposal's voting buttons
+// and its live scoreboard (see scoreboard.rs);
+// a ProposalCreatedEvent for a proposal the bot didn't announce itself (one created from
+// the web, a wallet or another client) is announced in the group's chat with voting
+// buttons. Chats are found through the group's external_chat_id.
+//
+// The subscription goes to EVENTS_WS_URL (the configured cluster's WebSocket by default;
+// "off" disables it) and reconnects with backoff when the connection drops.
+
+use crate::{
+    buttons, chat_vocabulary, get_proposal_results, html_escape, scoreboard, solana_dao, BotState,
+    SendLocalized,
+};
+
+use anchor_client::solana_client::nonblocking::pubsub_client::PubsubClient;
+use anchor_client::solana_client::rpc_config::{
+    RpcTransactionLogsConfig, RpcTransactionLogsFilter,
+};
+use anchor_client::solana_sdk::commitment_config::CommitmentConfig;
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_lang::AnchorDeserialize;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::time::Duration;
+use teloxide::prelude::*;
+use teloxide::types::{MessageId, ParseMode};
+use tokio_stream::StreamExt;
+
+pub const MAX_RECONNECT_DELAY_SECS: u64 = 60;
+// How long a new proposal is given to be announced by the command that created it
+// before the subscription announces it instead
+pub const ANNOUNCE_GRACE_SECS: u64 = 15;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProgramEvent {
+    VoteCast(solana_dao::VoteCastEvent),
+    ProposalCreated(solana_dao::ProposalCreatedEvent),
+}
+
+impl ProgramEvent {
+    // None for events the bot doesn't follow
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        if data.len() < 8 {
+            return None;
+        }
+        let (discriminator, mut body) = data.split_at(8);
+        match discriminator {
+            d if d == solana_dao::VOTE_CAST_EVENT_DISCRIMINATOR => {
+                solana_dao::VoteCastEvent::deserialize(&mut body)
+                    .ok()
+                    .map(Self::VoteCast)
+            }
+            d if d == solana_dao::PROPOSAL_CREATED_EVENT_DISCRIMINATOR => {
+                solana_dao::ProposalCreatedEvent::deserialize(&mut body)
+                    .ok()
+                    .map(Self::ProposalCreated)
+            }
+            _ => None,
+        }
+    }
+}
+
+// The event payloads `program_id` logged itself, tracking the invocation stack so data
+// logged by programs it calls isn't mistaken for its own
+pub fn program_data(logs: &[String], program_id: &Pubkey) -> Vec<Vec<u8>> {
+    let program_id = program_id.to_string();
+    let mut stack: Vec<&str> = Vec::new();
+    let mut payloads = Vec::new();
+    for line in logs {
+        let Some(rest) = line.strip_prefix("Program ") else {
+            continue;
+        };
+        if let Some(data) = rest.strip_prefix("data: ") {
+            if stack.last() == Some(&program_id.as_str()) {
+                if let Some(Ok(payload)) = data
+                    .split_whitespace()
+                    .next()
+                    .map(|data| base64::engine::general_purpose::STANDARD.decode(data))
+                {
+                    payloads.push(payload);
+                }
+            }
+        } else if let Some((id, result)) = rest.split_once(' ') {
+            if result.starts_with("invoke [") {
+                stack.push(id);
+            } else if result == "success" || result.starts_with("failed") {
+                stack.pop();
+            }
+        }
+    }
+    payloads
+}
+
+// Chat linked to each group, fetched once per group
+#[derive(Default)]
+struct LinkedChats {
+    chats: HashMap<String, Option<ChatId>>,
+}
+
+impl LinkedChats {
+    async fn get(&mut self, state: &BotState, group_id: &str) -> Option<ChatId> {
+        if let Some(chat_id) = self.chats.get(group_id) {
+            return *chat_id;
+        }
+        let (group_pda, _) =
+            Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+        let chat_id = state
+            .program
+            .account::<solana_dao::Group>(group_pda)
+            .await
+            .ok()?
+            .external_chat_id
+            .map(ChatId);
+        self.chats.insert(group_id.to_string(), chat_id);
+        chat_id
+    }
+}
+
+// Messages the bot posted announcing `proposal_id` in `chat_id`
+async fn announcements_of(state: &BotState, chat_id: ChatId, proposal_id: &str) -> Vec<MessageId> {
+    state
+        .discussions
+        .get(chat_id.0)
+        .await
+        .announcements
+        .iter()
+        .filter(|(_, id)| *id == proposal_id)
+        .map(|(&message_id, _)| MessageId(message_id))
+        .collect()
+}
+
+async fn on_vote(bot: &Bot, state: &BotState, chat_id: ChatId, event: &solana_dao::VoteCastEvent) {
+    if let Err(e) =
+        scoreboard::refresh(bot, state, chat_id, &event.group_id, &event.proposal_id).await
+    {
+        log::warn!(
+            "Failed to update the scoreboard of {}: {}",
+            event.proposal_id,
+            e
+        );
+    }
+    for message_id in announcements_of(state, chat_id, &event.proposal_id).await {
+        if let Err(e) = buttons::refresh_vote_keyboard(
+            bot,
+            state,
+            chat_id,
+            message_id,
+            &event.group_id,
+            &event.proposal_id,
+        )
+        .await
+        {
+            // Also expected when a button press already refreshed them
+            log::debug!(
+                "Voting buttons of {} not refreshed: {}",
+                event.proposal_id,
+                e
+            );
+        }
+    }
+}
+
+async fn on_proposal_created(
+    bot: Bot,
+    state: BotState,
+    chat_id: ChatId,
+    event: solana_dao::ProposalCreatedEvent,
+) {
+    tokio::time::sleep(Duration::from_secs(ANNOUNCE_GRACE_SECS)).await;
+    if !announcements_of(&state, chat_id, &event.proposal_id)
+        .await
+        .is_empty()
+    {
+        return;
+    }
+    let proposal = match get_proposal_results(&state, &event.group_id, &event.proposal_id).await {
+        Ok(proposal) => proposal,
+        Err(e) => {
+            log::warn!("Failed to load new proposal {}: {}", event.proposal_id, e);
+            return;
+        }
+    };
+
+    let choices = proposal
+        .choices
+        .iter()
+        .enumerate()
+        .map(|(i, choice)| format!("{}. {}", i, html_escape(choice)))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let next_step = if event.draft {
+        "📝 It's a draft, so voting hasn't opened yet.".to_string()
+    } else {
+        format!(
+            "Tap a choice below to vote, or use <code>/vote {} &lt;choice_number&gt;</code>.",
+            event.proposal_id
+        )
+    };
+    let text = format!(
+        "🆕 <b>New proposal</b>\n\n\
+        📋 <b>{}</b>\n\
+        📝 {}\n\
+        🆔 <b>Proposal ID:</b> <code>{}</code>\n\
+        ⏰ <b>Voting ends:</b> {}\n\n\
+        <b>Choices:</b>\n{}\n\n{}",
+        html_escape(&proposal.title),
+        html_escape(&proposal.description),
+        event.proposal_id,
+        DateTime::<Utc>::from_timestamp(event.voting_end, 0)
+            .map(|dt| dt.format("%Y-%m-%d %H:%M UTC").to_string())
+            .unwrap_or_else(|| "Unknown time".to_string()),
+        choices,
+        next_step
+    );
+
+    let vocab = chat_vocabulary(&state, chat_id).await;
+    let mut request = bot
+        .send_localized(&vocab, chat_id, text)
+        .parse_mode(ParseMode::Html);
+    if !event.draft {
+        if let Some(keyboard) = buttons::vote_keyboard(&event.proposal_id, &proposal.choices, None)
+        {
+            request = request.reply_markup(keyboard);
+        }
+    }
+    match request.await {
+        Ok(message) => {
+            let proposal_id = event.proposal_id.clone();
+            if let Err(e) = state
+                .discussions
+                .update(chat_id.0, |log| {
+                    log.announcements.insert(message.id.0, proposal_id);
+                })
+                .await
+            {
+                log::error!("Failed to record proposal announcement: {}", e);
+            }
+        }
+        Err(e) => log::warn!("Failed to announce proposal {}: {}", event.proposal_id, e),
+    }
+}
+
+// Runs until the stream ends or fails
+async fn subscribe(bot: &Bot, state: &BotState, ws_url: &str) -> anyhow::Result<()> {
+    let client = PubsubClient::new(ws_url).await?;
+    let (mut logs, _unsubscribe) = client
+        .logs_subscribe(
+            RpcTransactionLogsFilter::Mentions(vec![solana_dao::ID.to_string()]),
+            RpcTransactionLogsConfig {
+                commitment: Some(CommitmentConfig::confirmed()),
+            },
+        )
+        .await?;
+    log::info!("Subscribed to program events at {}", ws_url);
+
+    let mut linked_chats = LinkedChats::default();
+    while let Some(response) = logs.next().await {
+        if response.value.err.is_some() {
+            continue;
+        }
+        for data in program_data(&response.value.logs, &solana_dao::ID) {
+            match ProgramEvent::decode(&data) {
+                Some(ProgramEvent::VoteCast(event)) => {
+                    if let Some(chat_id) = linked_chats.get(state, &event.group_id).await {
+                        on_vote(bot, state, chat_id, &event).await;
+                    }
+                }
+                Some(ProgramEvent::ProposalCreated(event)) => {
+                    if let Some(chat_id) = linked_chats.get(state, &event.group_id).await {
+                        tokio::spawn(on_proposal_created(
+                            bot.clone(),
+                            state.clone(),
+                            chat_id,
+                            event,
+                        ));
+                    }
+                }
+                None => {}
+            }
+        }
+    }
+    anyhow::bail!("the event stream ended")
+}
+
+// Keep a subscription open until the process exits, unless EVENTS_WS_URL is "off"
+pub async fn run(bot: Bot, state: BotState) {
+    let ws_url = std::env::var("EVENTS_WS_URL")
+        .unwrap_or_else(|_| state.network.cluster.ws_url().to_string());
+    if ws_url == "off" {
+        return;
+    }
+
+    let mut delay = 1;
+    loop {
+        let started = tokio::time::Instant::now();
+        if let Err(e) = subscribe(&bot, &state, &ws_url).await {
+            log::warn!("Program event subscription dropped: {}", e);
+        }
+        // A connection that held for a while starts the backoff over
+        if started.elapsed() > Duration::from_secs(MAX_RECONNECT_DELAY_SECS) {
+            delay = 1;
+        }
+        tokio::time::sleep(Duration::from_secs(delay)).await;
+        delay = (delay * 2).min(MAX_RECONNECT_DELAY_SECS);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::AnchorSerialize;
+
+    fn data_line(event: &[u8]) -> String {
+        format!(
+            "Program data: {}",
+            base64::engine::general_purpose::STANDARD.encode(event)
+        )
+    }
+
+    #[test]
+    fn decodes_only_the_programs_own_events() {
+        let vote = solana_dao::VoteCastEvent {
+            group_id: "tg_1".to_string(),
+            event_seq: 4,
+            proposal_id: "0b5e4c8e".to_string(),
+            voter: Pubkey::new_unique(),
+            choice: 1,
+            vote_weight: 10,
+            timestamp: 100,
+            disclosure: None,
+            allocations: None,
+        };
+        let mut data = solana_dao::VOTE_CAST_EVENT_DISCRIMINATOR.to_vec();
+        vote.serialize(&mut data).unwrap();
+
+        let program = solana_dao::ID.to_string();
+        let other = Pubkey::new_unique().to_string();
+        let logs = vec![
+            format!("Program {} invoke [1]", program),
+            "Program log: Instruction: VoteOnProposal".to_string(),
+            format!("Program {} invoke [2]", other),
+            // Logged by the invoked program, so not one of ours
+            data_line(&data),
+            format!("Program {} success", other),
+            data_line(&data),
+            data_line(&[1, 2, 3]),
+            format!("Program {} consumed 5000 of 200000 compute units", program),
+            format!("Program {} success", program),
+        ];
+
+        let payloads = program_data(&logs, &solana_dao::ID);
+        assert_eq!(payloads.len(), 2);
+        assert_eq!(
+            ProgramEvent::decode(&payloads[0]),
+            Some(ProgramEvent::VoteCast(vote))
+        );
+        assert_eq!(ProgramEvent::decode(&payloads[1]), None);
+    }
+}
@@ -0,0 +1,264 @@
+// Result exports: a proposal's tallies and every counted ballot as a CSV or JSON file,
+// for audits and record keeping.
+//
+// The CSV is one table with a `record` column, "tally" rows first (one per choice, the
+// wallet columns empty) and then a "vote" row per ballot, so it filters cleanly in a
+// spreadsheet. Wallets counted through another wallet's vote (zero weight) aren't
+// ballots and are left out, as in /results. Timestamps are RFC 3339 in UTC. Exports of
+// private votes carry the tallies only.
+
+use crate::{proposal_result_pda, solana_dao};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Json,
+}
+
+impl Format {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "csv" => Some(Self::Csv),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Json => "json",
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ChoiceTally {
+    pub index: usize,
+    pub choice: String,
+    pub votes: u64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct VoteRecord {
+    pub wallet: String,
+    pub choice_index: u8,
+    pub choice: Option<String>,
+    pub weight: u64,
+    pub timestamp: String,
+    pub disclosure: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ResultsExport {
+    pub group_id: String,
+    pub proposal_id: String,
+    pub title: String,
+    pub status: &'static str,
+    pub voting_start: String,
+    pub voting_end: String,
+    // Only set once the proposal is finalized
+    pub result_certificate: Option<String>,
+    pub total_votes: u128,
+    pub choices: Vec<ChoiceTally>,
+    pub votes: Vec<VoteRecord>,
+    pub exported_at: String,
+}
+
+fn rfc3339(timestamp: i64) -> String {
+    DateTime::<Utc>::from_timestamp(timestamp, 0)
+        .map(|dt| dt.to_rfc3339())
+        .unwrap_or_default()
+}
+
+pub fn status_name(status: solana_dao::ProposalStatus) -> &'static str {
+    match status {
+        solana_dao::ProposalStatus::Draft => "draft",
+        solana_dao::ProposalStatus::Active => "active",
+        solana_dao::ProposalStatus::Succeeded => "succeeded",
+        solana_dao::ProposalStatus::Defeated => "defeated",
+        solana_dao::ProposalStatus::Cancelled => "cancelled",
+        solana_dao::ProposalStatus::Executed => "executed",
+    }
+}
+
+// `with_ballots` is false for chats with private votes, leaving `votes` empty
+pub fn build(proposal: &solana_dao::Proposal, now: i64, with_ballots: bool) -> ResultsExport {
+    let finalized = matches!(
+        proposal.status,
+        solana_dao::ProposalStatus::Succeeded
+            | solana_dao::ProposalStatus::Defeated
+            | solana_dao::ProposalStatus::Executed
+    );
+    ResultsExport {
+        group_id: proposal.group_id.clone(),
+        proposal_id: proposal.proposal_id.clone(),
+        title: proposal.title.clone(),
+        status: status_name(proposal.status),
+        voting_start: rfc3339(proposal.voting_start),
+        voting_end: rfc3339(proposal.voting_end),
+        result_certificate: finalized
+            .then(|| proposal_result_pda(&proposal.group_id, &proposal.proposal_id).to_string()),
+        total_votes: proposal.choice_votes.iter().map(|&v| v as u128).sum(),
+        choices: proposal
+            .choices
+            .iter()
+            .zip(proposal.choice_votes.iter())
+            .enumerate()
+            .map(|(index, (choice, &votes))| ChoiceTally {
+                index,
+                choice: choice.clone(),
+                votes,
+            })
+            .collect(),
+        votes: proposal
+            .ballots()
+            .filter(|_| with_ballots)
+            .map(|ballot| VoteRecord {
+                wallet: ballot.voter.to_string(),
+                choice_index: ballot.choice,
+                choice: proposal.choices.get(ballot.choice as usize).cloned(),
+                weight: ballot.vote_weight,
+                timestamp: rfc3339(ballot.timestamp),
+                disclosure: ballot.disclosure.clone(),
+            })
+            .collect(),
+        exported_at: rfc3339(now),
+    }
+}
+
+// Quotes a field when it holds a separator, quote or line break (RFC 4180), and defuses
+// text a spreadsheet would run as a formula
+fn csv_field(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+pub fn to_csv(export: &ResultsExport) -> String {
+    let mut csv =
+        String::from("record,choice_index,choice,votes_or_weight,wallet,timestamp,disclosure\n");
+    for tally in &export.choices {
+        csv.push_str(&format!(
+            "tally,{},{},{},,,\n",
+            tally.index,
+            csv_field(&tally.choice),
+            tally.votes
+        ));
+    }
+    for vote in &export.votes {
+        csv.push_str(&format!(
+            "vote,{},{},{},{},{},{}\n",
+            vote.choice_index,
+            csv_field(vote.choice.as_deref().unwrap_or_default()),
+            vote.weight,
+            vote.wallet,
+            vote.timestamp,
+            csv_field(vote.disclosure.as_deref().unwrap_or_default())
+        ));
+    }
+    csv
+}
+
+pub fn render(export: &ResultsExport, format: Format) -> anyhow::Result<Vec<u8>> {
+    Ok(match format {
+        Format::Csv => to_csv(export).into_bytes(),
+        Format::Json => serde_json::to_vec_pretty(export)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_client::solana_sdk::pubkey::Pubkey;
+
+    #[test]
+    fn exports_list_tallies_and_ballots() {
+        let voter = Pubkey::new_unique();
+        let ballot = |voter, choice, vote_weight, disclosure: Option<&str>| solana_dao::VoterInfo {
+            voter,
+            choice,
+            vote_weight,
+            timestamp: 1_700_000_000,
+            disclosure: disclosure.map(str::to_string),
+        };
+        let proposal = solana_dao::Proposal {
+            version: 1,
+            proposal_id: "prop_0001".to_string(),
+            group_id: "tg_1".to_string(),
+            title: "Budget".to_string(),
+            description: String::new(),
+            choices: vec!["Yes, fund it".to_string(), "=No".to_string()],
+            choice_votes: vec![5, 0],
+            voting_start: 0,
+            voting_end: 1_700_000_100,
+            token_mint: None,
+            creator: Pubkey::default(),
+            voters: vec![
+                ballot(voter, 0, 5, Some("I'm on the \"team\"")),
+                // Counted through another wallet's vote
+                ballot(Pubkey::new_unique(), 0, 0, None),
+            ],
+            created_at: 0,
+            index: 0,
+            metadata_uri: None,
+            status: solana_dao::ProposalStatus::Succeeded,
+            finalize_bounty: 0,
+            shadow_votes: Vec::new(),
+            offchain_aggregator: None,
+            offchain_voter_count: None,
+            price_weighted: false,
+            member_weighted: false,
+            kind: solana_dao::ProposalKind::Text,
+            emergency: false,
+            split_voting: false,
+            slot_window: None,
+            weight_decay_bps: 0,
+            deposit_voting: None,
+            council_chamber: None,
+            winner: Some(0),
+            tied: false,
+            sponsors_needed: 0,
+            sponsor_count: 0,
+            bump: 0,
+        };
+
+        let private = build(&proposal, 1_700_000_200, false);
+        assert!(private.votes.is_empty());
+        assert_eq!(to_csv(&private).lines().count(), 3);
+
+        let export = build(&proposal, 1_700_000_200, true);
+        assert_eq!(export.status, "succeeded");
+        assert!(export.result_certificate.is_some());
+        assert_eq!(export.votes.len(), 1);
+
+        let csv = to_csv(&export);
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[1], "tally,0,\"Yes, fund it\",5,,,");
+        assert_eq!(lines[2], "tally,1,'=No,0,,,");
+        assert_eq!(
+            lines[3],
+            format!(
+                "vote,0,\"Yes, fund it\",5,{},2023-11-14T22:13:20+00:00,\"I'm on the \"\"team\"\"\"",
+                voter
+            )
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_slice(&render(&export, Format::Json).unwrap()).unwrap();
+        assert_eq!(json["total_votes"], 5);
+        assert_eq!(json["votes"][0]["wallet"], voter.to_string());
+        assert_eq!(json["choices"][1]["choice"], "=No");
+    }
+}
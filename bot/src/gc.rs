@@ -0,0 +1,306 @@
+// Stale-proposal garbage collection: a periodic sweep that closes settled proposals
+// whose grace period has run out, so their rent goes back to the protocol.
+//
+// close_stale_proposal is permissionless and pays the rent to the configured fee
+// destination rather than the caller, so the bot only spends transaction fees. Each
+// proposal's outcome stays on-chain in its ProposalResult certificate. A spam deposit
+// still escrowed against a proposal is settled in the same transaction, just before its
+// close. The sweep is off unless GC_INTERVAL_SECS is set.
+
+use crate::{
+    build_close_stale_proposal_instruction, build_settle_proposal_deposit_instruction,
+    get_all_groups, get_group_proposals, program_config_pda, proposal_deposit_pda, proposal_pda,
+    send_instructions, solana_dao, BotState,
+};
+
+use anchor_client::solana_sdk::{pubkey::Pubkey, signer::Signer};
+use anchor_lang::AnchorDeserialize;
+use chrono::Utc;
+use std::time::Duration;
+
+// Closes per transaction; each adds nine accounts, most of them shared within a group,
+// and settling a deposit first adds two more
+pub const DEFAULT_GC_BATCH_SIZE: usize = 6;
+
+// Accounts per getMultipleAccounts call
+const MAX_ACCOUNTS_PER_FETCH: usize = 100;
+
+// Mirrors the program's close_stale_proposal checks on the proposal itself. A pending
+// callback or program upgrade also keeps a proposal open; see awaits_hook.
+pub fn is_stale(proposal: &solana_dao::Proposal, now: i64) -> bool {
+    let settled = match proposal.status {
+        solana_dao::ProposalStatus::Succeeded => !proposal.awaits_execution(),
+        solana_dao::ProposalStatus::Defeated
+        | solana_dao::ProposalStatus::Executed
+        | solana_dao::ProposalStatus::Cancelled => true,
+        _ => false,
+    };
+    settled
+        && now
+            > proposal
+                .voting_end
+                .saturating_add(solana_dao::STALE_PROPOSAL_GRACE_SECS)
+}
+
+// Mirrors the rest of close_stale_proposal: a passed or executed proposal whose callback
+// account is still there, or a passed one whose approved upgrade hasn't run, stays open.
+// The sweep skips these rather than failing the batch they'd be in.
+pub fn awaits_hook(
+    proposal: &solana_dao::Proposal,
+    has_callback: bool,
+    upgrade: Option<&solana_dao::UpgradeProposal>,
+) -> bool {
+    match proposal.status {
+        solana_dao::ProposalStatus::Succeeded => {
+            has_callback
+                || upgrade.is_some_and(|upgrade| {
+                    upgrade.executed_at == 0 && proposal.winner == Some(upgrade.approve_choice)
+                })
+        }
+        solana_dao::ProposalStatus::Executed => has_callback,
+        _ => false,
+    }
+}
+
+// One pass over every group; returns how many proposals were closed
+async fn sweep(state: &BotState, batch_size: usize) -> anyhow::Result<usize> {
+    let account = state
+        .program
+        .rpc()
+        .get_account(&program_config_pda())
+        .await?;
+    let program_config = solana_dao::ProgramConfig::deserialize(&mut &account.data[8..])?;
+    if program_config.paused {
+        return Ok(0);
+    }
+
+    let now = Utc::now().timestamp();
+    let mut closed = 0;
+    for group in get_all_groups(state).await? {
+        let proposals = get_group_proposals(state, &group.group_id).await?;
+        let stale: Vec<_> = proposals
+            .iter()
+            .filter(|proposal| is_stale(proposal, now))
+            .collect();
+        if stale.is_empty() {
+            continue;
+        }
+        // Each proposal's deposit, callback and upgrade accounts, in that order
+        let pdas: Vec<_> = stale
+            .iter()
+            .flat_map(|proposal| {
+                let proposal = proposal_pda(&group.group_id, &proposal.proposal_id);
+                [
+                    proposal_deposit_pda(&proposal),
+                    Pubkey::find_program_address(
+                        &[b"callback", proposal.as_ref()],
+                        &solana_dao::ID,
+                    )
+                    .0,
+                    Pubkey::find_program_address(
+                        &[b"program_upgrade", proposal.as_ref()],
+                        &solana_dao::ID,
+                    )
+                    .0,
+                ]
+            })
+            .collect();
+        let mut accounts = Vec::with_capacity(pdas.len());
+        for chunk in pdas.chunks(MAX_ACCOUNTS_PER_FETCH) {
+            accounts.extend(state.program.rpc().get_multiple_accounts(chunk).await?);
+        }
+        // Each proposal's close, preceded by settling its deposit if one is still held
+        let closes: Vec<Vec<_>> = stale
+            .iter()
+            .zip(accounts.chunks(3))
+            .filter_map(|(proposal, accounts)| {
+                let [deposit, callback, upgrade] = accounts else {
+                    return None;
+                };
+                let upgrade = upgrade.as_ref().and_then(|account| {
+                    solana_dao::UpgradeProposal::deserialize(&mut &account.data[8..]).ok()
+                });
+                if awaits_hook(proposal, callback.is_some(), upgrade.as_ref()) {
+                    return None;
+                }
+                let deposit = deposit.as_ref().and_then(|account| {
+                    solana_dao::ProposalDeposit::deserialize(&mut &account.data[8..]).ok()
+                });
+                let settle = deposit.map(|deposit| {
+                    build_settle_proposal_deposit_instruction(
+                        &group.group_id,
+                        &proposal.proposal_id,
+                        deposit.depositor,
+                        state.payer.pubkey(),
+                    )
+                });
+                let close = build_close_stale_proposal_instruction(
+                    &group.group_id,
+                    &proposal.proposal_id,
+                    proposal.index,
+                    state.payer.pubkey(),
+                    program_config.fee_destination,
+                );
+                Some(settle.into_iter().chain([close]).collect())
+            })
+            .collect();
+
+        for batch in closes.chunks(batch_size) {
+            let instructions = batch.concat();
+            match send_instructions(
+                state,
+                &instructions,
+                &state.payer.pubkey(),
+                &[&*state.payer],
+            )
+            .await
+            {
+                Ok(signature) => {
+                    log::info!(
+                        "Closed {} stale proposals in {}: {}",
+                        batch.len(),
+                        group.group_id,
+                        signature
+                    );
+                    closed += batch.len();
+                }
+                Err(e) => log::warn!(
+                    "Failed to close stale proposals in {}: {}",
+                    group.group_id,
+                    e
+                ),
+            }
+        }
+    }
+    Ok(closed)
+}
+
+// Sweep every `interval` until the process exits. GC_BATCH_SIZE sets how many proposals
+// are closed per transaction.
+pub async fn run(state: BotState, interval: Duration) {
+    let batch_size = std::env::var("GC_BATCH_SIZE")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&n| n > 0)
+        .unwrap_or(DEFAULT_GC_BATCH_SIZE);
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        ticker.tick().await;
+        match sweep(&state, batch_size).await {
+            Ok(0) => {}
+            Ok(closed) => log::info!("Stale-proposal sweep closed {} proposals", closed),
+            Err(e) => log::warn!("Stale-proposal sweep failed: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proposal(status: solana_dao::ProposalStatus, voting_end: i64) -> solana_dao::Proposal {
+        solana_dao::Proposal {
+            version: 1,
+            proposal_id: "p1".to_string(),
+            group_id: "tg_1".to_string(),
+            title: String::new(),
+            description: String::new(),
+            choices: vec!["Yes".to_string(), "No".to_string()],
+            choice_votes: vec![0, 0],
+            voting_start: 0,
+            voting_end,
+            token_mint: None,
+            creator: Pubkey::default(),
+            voters: Vec::new(),
+            created_at: 0,
+            index: 0,
+            metadata_uri: None,
+            status,
+            finalize_bounty: 0,
+            shadow_votes: Vec::new(),
+            offchain_aggregator: None,
+            offchain_voter_count: None,
+            price_weighted: false,
+            member_weighted: false,
+            kind: solana_dao::ProposalKind::Text,
+            emergency: false,
+            split_voting: false,
+            slot_window: None,
+            weight_decay_bps: 0,
+            deposit_voting: None,
+            council_chamber: None,
+            winner: None,
+            tied: false,
+            sponsors_needed: 0,
+            sponsor_count: 0,
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn only_settled_proposals_past_the_grace_period_are_stale() {
+        let expired = 1_000 + solana_dao::STALE_PROPOSAL_GRACE_SECS + 1;
+
+        assert!(is_stale(
+            &proposal(solana_dao::ProposalStatus::Defeated, 1_000),
+            expired
+        ));
+        assert!(is_stale(
+            &proposal(solana_dao::ProposalStatus::Cancelled, 1_000),
+            expired
+        ));
+        assert!(!is_stale(
+            &proposal(solana_dao::ProposalStatus::Succeeded, 1_000),
+            expired - 1
+        ));
+        // A passed proposal stays until it has been executed
+        let mut transfer = proposal(solana_dao::ProposalStatus::Succeeded, 1_000);
+        transfer.kind = solana_dao::ProposalKind::TreasuryTransfer(solana_dao::TreasuryTransfer {
+            recipient: Pubkey::new_unique(),
+            lamports: 1,
+        });
+        transfer.winner = Some(0);
+        assert!(!is_stale(&transfer, expired));
+        transfer.winner = Some(1);
+        assert!(is_stale(&transfer, expired));
+        let mut text = proposal(solana_dao::ProposalStatus::Succeeded, 1_000);
+        text.winner = Some(0);
+        assert!(is_stale(&text, expired));
+        // Unfinalized proposals have no result certificate yet
+        assert!(!is_stale(
+            &proposal(solana_dao::ProposalStatus::Active, 1_000),
+            expired
+        ));
+    }
+
+    #[test]
+    fn pending_callbacks_and_upgrades_keep_proposals_open() {
+        let mut passed = proposal(solana_dao::ProposalStatus::Succeeded, 1_000);
+        passed.winner = Some(0);
+        let mut upgrade = solana_dao::UpgradeProposal {
+            proposal: Pubkey::new_unique(),
+            buffer: Pubkey::new_unique(),
+            approve_choice: 0,
+            eta: 0,
+            executed_at: 0,
+            bump: 0,
+        };
+
+        assert!(!awaits_hook(&passed, false, None));
+        assert!(awaits_hook(&passed, true, None));
+        assert!(awaits_hook(&passed, false, Some(&upgrade)));
+        upgrade.executed_at = 2_000;
+        assert!(!awaits_hook(&passed, false, Some(&upgrade)));
+        // An upgrade whose choice lost never runs
+        upgrade.executed_at = 0;
+        passed.winner = Some(1);
+        assert!(!awaits_hook(&passed, false, Some(&upgrade)));
+
+        let executed = proposal(solana_dao::ProposalStatus::Executed, 1_000);
+        assert!(awaits_hook(&executed, true, None));
+        let defeated = proposal(solana_dao::ProposalStatus::Defeated, 1_000);
+        assert!(!awaits_hook(&defeated, true, None));
+    }
+}
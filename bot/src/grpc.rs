@@ -0,0 +1,391 @@
+// gRPC service streaming proposal and vote updates to third-party clients.
+//
+// Clients subscribe to one or more groups and get the current state of each proposal,
+// then a message whenever a proposal is created or its tallies change. A single poller
+// watches only the groups that have live subscribers and fans changes out over a
+// broadcast channel, so RPC load doesn't grow with the number of clients.
+//
+// Only aggregate tallies are streamed, never individual voters, so the chat's vote
+// privacy setting can't be bypassed through this API. The schema is documented in
+// bot/proto/dao_updates.proto; the message types below mirror it by hand.
+
+use crate::{get_group_proposals, solana_dao, BotState};
+
+use chrono::Utc;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio_stream::{wrappers::BroadcastStream, Stream, StreamExt};
+use tonic::{Request, Response, Status};
+
+mod generated {
+    include!(concat!(
+        env!("OUT_DIR"),
+        "/solana_dao.updates.DaoUpdates.rs"
+    ));
+}
+
+use generated::dao_updates_server::{DaoUpdates, DaoUpdatesServer};
+
+const DEFAULT_POLL_INTERVAL_MS: u64 = 2_000;
+// Updates buffered per subscriber before a slow client starts missing them
+const UPDATE_CHANNEL_CAPACITY: usize = 1_024;
+const MAX_GROUPS_PER_SUBSCRIPTION: usize = 32;
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SubscribeRequest {
+    #[prost(string, repeated, tag = "1")]
+    pub group_ids: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, prost::Enumeration)]
+#[repr(i32)]
+pub enum UpdateKind {
+    Snapshot = 0,
+    Created = 1,
+    Votes = 2,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Choice {
+    #[prost(string, tag = "1")]
+    pub label: String,
+    #[prost(uint64, tag = "2")]
+    pub votes: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ProposalUpdate {
+    #[prost(string, tag = "1")]
+    pub group_id: String,
+    #[prost(string, tag = "2")]
+    pub proposal_id: String,
+    #[prost(enumeration = "UpdateKind", tag = "3")]
+    pub kind: i32,
+    #[prost(string, tag = "4")]
+    pub title: String,
+    #[prost(message, repeated, tag = "5")]
+    pub choices: Vec<Choice>,
+    #[prost(uint64, tag = "6")]
+    pub total_voters: u64,
+    #[prost(int64, tag = "7")]
+    pub voting_start: i64,
+    #[prost(int64, tag = "8")]
+    pub voting_end: i64,
+    #[prost(int64, tag = "9")]
+    pub observed_at: i64,
+    #[prost(string, optional, tag = "10")]
+    pub metadata_uri: Option<String>,
+}
+
+// What the poller last saw of a proposal: its tallies and number of ballots
+type Tally = (Vec<u64>, usize);
+
+// Shared between the poller and all subscriptions
+struct UpdateHub {
+    sender: broadcast::Sender<ProposalUpdate>,
+    watched: Mutex<HashMap<String, usize>>, // group_id -> live subscriptions
+}
+
+impl UpdateHub {
+    fn watch(self: &Arc<Self>, group_ids: &[String]) -> WatchGuard {
+        let mut watched = self.watched.lock().unwrap();
+        for group_id in group_ids {
+            *watched.entry(group_id.clone()).or_default() += 1;
+        }
+        WatchGuard {
+            hub: self.clone(),
+            group_ids: group_ids.to_vec(),
+        }
+    }
+
+    fn watched_groups(&self) -> Vec<String> {
+        self.watched.lock().unwrap().keys().cloned().collect()
+    }
+}
+
+// Unwatches a subscription's groups when its stream is dropped (client disconnected)
+struct WatchGuard {
+    hub: Arc<UpdateHub>,
+    group_ids: Vec<String>,
+}
+
+impl Drop for WatchGuard {
+    fn drop(&mut self) {
+        let mut watched = self.hub.watched.lock().unwrap();
+        for group_id in &self.group_ids {
+            if let Some(count) = watched.get_mut(group_id) {
+                *count -= 1;
+                if *count == 0 {
+                    watched.remove(group_id);
+                }
+            }
+        }
+    }
+}
+
+struct UpdateService {
+    bot_state: BotState,
+    hub: Arc<UpdateHub>,
+}
+
+type UpdateStream = Pin<Box<dyn Stream<Item = Result<ProposalUpdate, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl DaoUpdates for UpdateService {
+    type SubscribeStream = UpdateStream;
+
+    async fn subscribe(
+        &self,
+        request: Request<SubscribeRequest>,
+    ) -> Result<Response<Self::SubscribeStream>, Status> {
+        let mut group_ids = request.into_inner().group_ids;
+        group_ids.sort();
+        group_ids.dedup();
+        if group_ids.is_empty() {
+            return Err(Status::invalid_argument(
+                "At least one group_id is required",
+            ));
+        }
+        if group_ids.len() > MAX_GROUPS_PER_SUBSCRIPTION {
+            return Err(Status::invalid_argument(format!(
+                "At most {} groups per subscription",
+                MAX_GROUPS_PER_SUBSCRIPTION
+            )));
+        }
+
+        // Subscribe before taking the snapshot so no change falls between the two
+        let receiver = self.hub.sender.subscribe();
+        let guard = self.hub.watch(&group_ids);
+
+        let now = Utc::now().timestamp();
+        let mut snapshot = Vec::new();
+        for group_id in &group_ids {
+            let proposals = get_group_proposals(&self.bot_state, group_id)
+                .await
+                .map_err(|e| Status::unavailable(e.to_string()))?;
+            snapshot.extend(
+                proposals
+                    .iter()
+                    .map(|p| proposal_update(group_id, p, UpdateKind::Snapshot, now)),
+            );
+        }
+
+        let live = BroadcastStream::new(receiver).filter_map(move |update| {
+            let _guard = &guard;
+            match update {
+                Ok(update) if group_ids.binary_search(&update.group_id).is_ok() => Some(Ok(update)),
+                Ok(_) => None,
+                Err(tokio_stream::wrappers::errors::BroadcastStreamRecvError::Lagged(missed)) => {
+                    Some(Err(Status::data_loss(format!(
+                        "Client too slow, {} updates dropped; resubscribe for a fresh snapshot",
+                        missed
+                    ))))
+                }
+            }
+        });
+
+        let stream = tokio_stream::iter(snapshot.into_iter().map(Ok)).chain(live);
+        Ok(Response::new(Box::pin(stream)))
+    }
+}
+
+fn proposal_update(
+    group_id: &str,
+    proposal: &solana_dao::Proposal,
+    kind: UpdateKind,
+    observed_at: i64,
+) -> ProposalUpdate {
+    ProposalUpdate {
+        group_id: group_id.to_string(),
+        proposal_id: proposal.proposal_id.clone(),
+        kind: kind as i32,
+        title: proposal.title.clone(),
+        choices: proposal
+            .choices
+            .iter()
+            .zip(proposal.choice_votes.iter())
+            .map(|(label, votes)| Choice {
+                label: label.clone(),
+                votes: *votes,
+            })
+            .collect(),
+        total_voters: proposal.ballots().count() as u64,
+        voting_start: proposal.voting_start,
+        voting_end: proposal.voting_end,
+        observed_at,
+        metadata_uri: proposal.metadata_uri.clone(),
+    }
+}
+
+// Compare a group's proposals with what was last seen and return the changes
+fn diff_proposals(
+    seen: &mut HashMap<(String, String), Tally>,
+    group_id: &str,
+    proposals: &[solana_dao::Proposal],
+    observed_at: i64,
+) -> Vec<ProposalUpdate> {
+    let mut updates = Vec::new();
+    for proposal in proposals {
+        let tally = (proposal.choice_votes.clone(), proposal.ballots().count());
+        let key = (group_id.to_string(), proposal.proposal_id.clone());
+        let kind = match seen.get(&key) {
+            None => UpdateKind::Created,
+            Some(previous) if *previous != tally => UpdateKind::Votes,
+            Some(_) => continue,
+        };
+        seen.insert(key, tally);
+        updates.push(proposal_update(group_id, proposal, kind, observed_at));
+    }
+    updates
+}
+
+// Poll the watched groups and broadcast their changes until the process exits
+async fn run_poller(bot_state: BotState, hub: Arc<UpdateHub>, interval: Duration) {
+    let mut seen: HashMap<(String, String), Tally> = HashMap::new();
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        ticker.tick().await;
+        let groups = hub.watched_groups();
+        // Forget groups nobody watches; they start over if someone subscribes again
+        seen.retain(|(group_id, _), _| groups.contains(group_id));
+
+        for group_id in groups {
+            let first_poll = !seen.keys().any(|(g, _)| *g == group_id);
+            let proposals = match get_group_proposals(&bot_state, &group_id).await {
+                Ok(proposals) => proposals,
+                Err(e) => {
+                    log::warn!("gRPC poller failed to load group {}: {}", group_id, e);
+                    continue;
+                }
+            };
+            let mut updates =
+                diff_proposals(&mut seen, &group_id, &proposals, Utc::now().timestamp());
+            // The first poll of a group only establishes a baseline. Send it as a snapshot
+            // anyway: every update carries the full state, so a repeat is harmless, while
+            // skipping it could lose a vote landing just after a client's own snapshot.
+            if first_poll {
+                for update in &mut updates {
+                    update.kind = UpdateKind::Snapshot as i32;
+                }
+            }
+            for update in updates {
+                // Err only means no subscriber is listening right now
+                let _ = hub.sender.send(update);
+            }
+        }
+    }
+}
+
+// Start the gRPC service; runs until the listener fails. GRPC_POLL_INTERVAL_MS sets
+// how often watched groups are checked for changes.
+pub async fn serve(bot_state: BotState, bind_addr: SocketAddr) -> anyhow::Result<()> {
+    let interval = std::env::var("GRPC_POLL_INTERVAL_MS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_POLL_INTERVAL_MS);
+
+    let (sender, _) = broadcast::channel(UPDATE_CHANNEL_CAPACITY);
+    let hub = Arc::new(UpdateHub {
+        sender,
+        watched: Mutex::new(HashMap::new()),
+    });
+    tokio::spawn(run_poller(
+        bot_state.clone(),
+        hub.clone(),
+        Duration::from_millis(interval),
+    ));
+
+    log::info!("gRPC service listening on {}", bind_addr);
+    tonic::transport::Server::builder()
+        .add_service(DaoUpdatesServer::new(UpdateService { bot_state, hub }))
+        .serve(bind_addr)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_client::solana_sdk::pubkey::Pubkey;
+
+    fn proposal(proposal_id: &str, choice_votes: Vec<u64>, voters: usize) -> solana_dao::Proposal {
+        solana_dao::Proposal {
+            version: 1,
+            proposal_id: proposal_id.to_string(),
+            group_id: "tg_1".to_string(),
+            title: "Title".to_string(),
+            description: String::new(),
+            choices: vec!["Yes".to_string(), "No".to_string()],
+            choice_votes,
+            voting_start: 0,
+            voting_end: 100,
+            token_mint: None,
+            creator: Pubkey::default(),
+            voters: (0..voters)
+                .map(|_| solana_dao::VoterInfo {
+                    voter: Pubkey::new_unique(),
+                    choice: 0,
+                    vote_weight: 1,
+                    timestamp: 0,
+                    disclosure: None,
+                })
+                .collect(),
+            created_at: 0,
+            index: 0,
+            metadata_uri: None,
+            status: solana_dao::ProposalStatus::Active,
+            finalize_bounty: 0,
+            shadow_votes: Vec::new(),
+            offchain_aggregator: None,
+            offchain_voter_count: None,
+            price_weighted: false,
+            member_weighted: false,
+            kind: solana_dao::ProposalKind::Text,
+            emergency: false,
+            split_voting: false,
+            slot_window: None,
+            weight_decay_bps: 0,
+            deposit_voting: None,
+            council_chamber: None,
+            winner: None,
+            tied: false,
+            sponsors_needed: 0,
+            sponsor_count: 0,
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn reports_new_proposals_then_only_changes() {
+        let mut seen = HashMap::new();
+
+        let updates = diff_proposals(&mut seen, "tg_1", &[proposal("p1", vec![0, 0], 0)], 1);
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].kind, UpdateKind::Created as i32);
+
+        let unchanged = diff_proposals(&mut seen, "tg_1", &[proposal("p1", vec![0, 0], 0)], 2);
+        assert!(unchanged.is_empty());
+
+        let voted = diff_proposals(&mut seen, "tg_1", &[proposal("p1", vec![1, 0], 1)], 3);
+        assert_eq!(voted.len(), 1);
+        assert_eq!(voted[0].kind, UpdateKind::Votes as i32);
+        assert_eq!(voted[0].choices[0].votes, 1);
+        assert_eq!(voted[0].total_voters, 1);
+    }
+
+    #[test]
+    fn keeps_groups_apart() {
+        let mut seen = HashMap::new();
+        diff_proposals(&mut seen, "tg_1", &[proposal("p1", vec![0, 0], 0)], 1);
+
+        let other = diff_proposals(&mut seen, "tg_2", &[proposal("p1", vec![0, 0], 0)], 1);
+        assert_eq!(other.len(), 1);
+        assert_eq!(other[0].group_id, "tg_2");
+    }
+}
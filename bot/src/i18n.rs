@@ -0,0 +1,246 @@
+// Message catalogs for the bot's own wording, picked per chat with /language.
+//
+// Messages are written in English and translated here just before sending, ahead of the
+// chat's vocabulary, so handlers don't need to know about it. A catalog
+// (locales/<code>.json) maps English text to its translation, either a whole message or
+// a single line of one. `{}` in an entry stands for a value filled in at runtime; the
+// translation takes the values in order, or by position with `{0}`, `{1}`, ... Values
+// that are catalog entries themselves, such as status words, are translated as well.
+// Anything a catalog lacks is sent in English.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::OnceLock;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    #[default]
+    En,
+    Es,
+    Ru,
+}
+
+impl Language {
+    pub const ALL: [Language; 3] = [Language::En, Language::Es, Language::Ru];
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "en" | "english" => Some(Self::En),
+            "es" | "spanish" | "español" => Some(Self::Es),
+            "ru" | "russian" | "русский" => Some(Self::Ru),
+            _ => None,
+        }
+    }
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::En => "en",
+            Self::Es => "es",
+            Self::Ru => "ru",
+        }
+    }
+
+    // The language's own name, as shown by /language
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::En => "English",
+            Self::Es => "Español",
+            Self::Ru => "Русский",
+        }
+    }
+
+    fn catalog(self) -> Option<&'static Catalog> {
+        static ES: OnceLock<Catalog> = OnceLock::new();
+        static RU: OnceLock<Catalog> = OnceLock::new();
+        match self {
+            Self::En => None,
+            Self::Es => Some(ES.get_or_init(|| Catalog::parse(include_str!("../locales/es.json")))),
+            Self::Ru => Some(RU.get_or_init(|| Catalog::parse(include_str!("../locales/ru.json")))),
+        }
+    }
+}
+
+struct Template {
+    // The English text split at each `{}`
+    segments: Vec<String>,
+    translation: String,
+}
+
+struct Catalog {
+    exact: HashMap<String, String>,
+    // Most specific (longest literal text) first
+    templates: Vec<Template>,
+}
+
+impl Catalog {
+    fn parse(source: &str) -> Self {
+        let entries: BTreeMap<String, String> =
+            serde_json::from_str(source).expect("locale catalogs are valid JSON");
+        let mut exact = HashMap::new();
+        let mut templates = Vec::new();
+        for (english, translation) in entries {
+            if english.contains("{}") {
+                let segments: Vec<String> = english.split("{}").map(str::to_string).collect();
+                // An entry of nothing but values would match any line
+                if segments.iter().any(|segment| !segment.trim().is_empty()) {
+                    templates.push(Template {
+                        segments,
+                        translation,
+                    });
+                }
+            } else {
+                exact.insert(english, translation);
+            }
+        }
+        templates.sort_by_key(|template| {
+            std::cmp::Reverse(template.segments.iter().map(String::len).sum::<usize>())
+        });
+        Self { exact, templates }
+    }
+
+    fn line(&self, line: &str, translate_values: bool) -> String {
+        let body = line.trim_start();
+        if body.is_empty() {
+            return line.to_string();
+        }
+        let indent = &line[..line.len() - body.len()];
+        if let Some(translation) = self.exact.get(body) {
+            return format!("{}{}", indent, translation);
+        }
+        for template in &self.templates {
+            if let Some(values) = captures(&template.segments, body) {
+                let values: Vec<String> = values
+                    .into_iter()
+                    .map(|value| {
+                        if translate_values {
+                            self.line(value, false)
+                        } else {
+                            value.to_string()
+                        }
+                    })
+                    .collect();
+                return format!("{}{}", indent, fill(&template.translation, &values));
+            }
+        }
+        line.to_string()
+    }
+}
+
+// The values in `text` where `segments` leave gaps, or None when it doesn't fit
+fn captures<'a>(segments: &[String], text: &'a str) -> Option<Vec<&'a str>> {
+    let (first, rest) = segments.split_first()?;
+    let (last, middle) = rest.split_last()?;
+    let mut remaining = text.strip_prefix(first.as_str())?;
+    remaining = remaining.strip_suffix(last.as_str())?;
+    let mut values = Vec::with_capacity(segments.len() - 1);
+    for segment in middle {
+        let at = remaining.find(segment.as_str())?;
+        values.push(&remaining[..at]);
+        remaining = &remaining[at + segment.len()..];
+    }
+    values.push(remaining);
+    Some(values)
+}
+
+fn fill(translation: &str, values: &[String]) -> String {
+    let mut filled = String::with_capacity(translation.len());
+    let mut next = 0;
+    let mut rest = translation;
+    while let Some(open) = rest.find('{') {
+        filled.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        match after.find('}') {
+            Some(close) if after[..close].chars().all(|c| c.is_ascii_digit()) => {
+                let index = if close == 0 {
+                    next += 1;
+                    next - 1
+                } else {
+                    after[..close].parse().unwrap_or(usize::MAX)
+                };
+                filled.push_str(values.get(index).map_or("", String::as_str));
+                rest = &after[close + 1..];
+            }
+            _ => {
+                filled.push('{');
+                rest = after;
+            }
+        }
+    }
+    filled.push_str(rest);
+    filled
+}
+
+pub fn translate(language: Language, text: &str) -> String {
+    let Some(catalog) = language.catalog() else {
+        return text.to_string();
+    };
+    if let Some(translation) = catalog.exact.get(text) {
+        return translation.clone();
+    }
+    text.split('\n')
+        .map(|line| catalog.line(line, true))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Translates the "/command — description" lines of Command::descriptions()
+pub fn help(language: Language, descriptions: &str) -> String {
+    descriptions
+        .split('\n')
+        .map(|line| match line.split_once(" — ") {
+            Some((command, description)) => {
+                format!("{} — {}", command, translate(language, description))
+            }
+            None => translate(language, line),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_are_translated_by_line_and_template() {
+        for language in Language::ALL {
+            assert_eq!(Language::parse(language.code()), Some(language));
+        }
+        assert_eq!(
+            translate(Language::Es, "❌ Unknown invite code."),
+            "❌ Código de invitación desconocido."
+        );
+        // Values are kept, and translated where the catalog knows them
+        assert_eq!(
+            translate(
+                Language::Ru,
+                "✅ Ties for first place are now settled this way: the group council picks the winner"
+            ),
+            "✅ Теперь ничья за первое место разрешается так: победителя выбирает совет группы"
+        );
+        assert_eq!(
+            translate(
+                Language::Es,
+                "📊 <b>Results for: Budget</b>\n\n<b>Results:</b>\n0. Yes - 3 votes (75.0%)\n   Unlisted line"
+            ),
+            "📊 <b>Resultados de: Budget</b>\n\n<b>Resultados:</b>\n0. Yes - 3 votos (75.0%)\n   Unlisted line"
+        );
+        assert_eq!(
+            translate(Language::En, "❌ Unknown invite code."),
+            "❌ Unknown invite code."
+        );
+        assert_eq!(
+            help(
+                Language::Es,
+                "Solana DAO Bot Commands\n\n/help — Display help message\n/start — Unlisted"
+            ),
+            "Comandos de Solana DAO Bot\n\n/help — Mostrar la ayuda\n/start — Unlisted"
+        );
+
+        assert_eq!(
+            fill("{1} / {0} / {}", &["a".into(), "b".into()]),
+            "b / a / a"
+        );
+    }
+}
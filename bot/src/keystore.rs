@@ -0,0 +1,239 @@
+// Persistent store for the seeds behind users' bot-managed wallets, so wallets survive a
+// restart without being re-derived from their Telegram id.
+//
+// Seeds live in a sled database at KEYSTORE_PATH, each sealed with ChaCha20-Poly1305
+// under a 32-byte key: KEYSTORE_KEY (hex, e.g. a data key unwrapped from a KMS), or one
+// derived with PBKDF2-HMAC-SHA256 from KEYSTORE_PASSPHRASE and a salt kept in the
+// database. A record binds its Telegram id as associated data, so records can't be
+// swapped between users, and a sealed check value catches a wrong key at startup.
+//
+// Wallets created before the keystore existed were derived from the Telegram id and
+// SECRET_SEED; the bot adopts those seeds into the keystore the first time it needs one.
+
+use chacha20poly1305::aead::{rand_core::RngCore, Aead, KeyInit, OsRng, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use std::path::Path;
+
+pub const DEFAULT_KEYSTORE_PATH: &str = "bot/keystore";
+#[cfg(not(test))]
+pub const PBKDF2_ROUNDS: u32 = 600_000;
+// Unoptimized test builds would spend seconds on each derivation
+#[cfg(test)]
+pub const PBKDF2_ROUNDS: u32 = 1_000;
+
+const RECORD_VERSION: u8 = 1;
+const NONCE_LEN: usize = 12;
+const SALT_KEY: &[u8] = b"meta:salt";
+const CHECK_KEY: &[u8] = b"meta:check";
+const CHECK_PLAINTEXT: &[u8] = b"solana-dao-bot keystore";
+const SEED_PREFIX: &[u8] = b"seed:";
+
+pub struct Keystore {
+    db: sled::Db,
+    cipher: ChaCha20Poly1305,
+}
+
+// Where the encryption key comes from
+pub enum KeySource {
+    Key([u8; 32]),
+    Passphrase(String),
+}
+
+impl KeySource {
+    // KEYSTORE_KEY wins over KEYSTORE_PASSPHRASE; without either, SECRET_SEED doubles as
+    // the passphrase so existing deployments keep starting
+    pub fn from_env() -> anyhow::Result<Self> {
+        if let Ok(key) = std::env::var("KEYSTORE_KEY") {
+            let key: [u8; 32] = hex::decode(key.trim())?
+                .try_into()
+                .map_err(|_| anyhow::anyhow!("KEYSTORE_KEY must be 32 bytes of hex"))?;
+            return Ok(Self::Key(key));
+        }
+        if let Ok(passphrase) = std::env::var("KEYSTORE_PASSPHRASE") {
+            return Ok(Self::Passphrase(passphrase));
+        }
+        log::warn!(
+            "KEYSTORE_KEY and KEYSTORE_PASSPHRASE unset; encrypting the keystore with SECRET_SEED"
+        );
+        let passphrase = std::env::var("SECRET_SEED")
+            .map_err(|_| anyhow::anyhow!("Set KEYSTORE_KEY or KEYSTORE_PASSPHRASE"))?;
+        Ok(Self::Passphrase(passphrase))
+    }
+}
+
+impl Keystore {
+    pub fn open_from_env() -> anyhow::Result<Self> {
+        let path =
+            std::env::var("KEYSTORE_PATH").unwrap_or_else(|_| DEFAULT_KEYSTORE_PATH.to_string());
+        Self::open(Path::new(&path), KeySource::from_env()?)
+    }
+
+    pub fn open(path: &Path, source: KeySource) -> anyhow::Result<Self> {
+        let db = sled::open(path)?;
+        let key = match source {
+            KeySource::Key(key) => key,
+            KeySource::Passphrase(passphrase) => {
+                let salt = match db.get(SALT_KEY)? {
+                    Some(salt) => salt.to_vec(),
+                    None => {
+                        let mut salt = vec![0u8; 16];
+                        OsRng.fill_bytes(&mut salt);
+                        db.insert(SALT_KEY, salt.as_slice())?;
+                        salt
+                    }
+                };
+                let mut key = [0u8; 32];
+                pbkdf2::pbkdf2_hmac::<sha2::Sha256>(
+                    passphrase.as_bytes(),
+                    &salt,
+                    PBKDF2_ROUNDS,
+                    &mut key,
+                );
+                key
+            }
+        };
+        let keystore = Self {
+            db,
+            cipher: ChaCha20Poly1305::new(Key::from_slice(&key)),
+        };
+
+        match keystore.db.get(CHECK_KEY)? {
+            Some(check) => {
+                if keystore.open_record(&check, CHECK_KEY)? != CHECK_PLAINTEXT {
+                    anyhow::bail!("Keystore check value is corrupt");
+                }
+            }
+            None => {
+                let check = keystore.seal(CHECK_PLAINTEXT, CHECK_KEY)?;
+                keystore.db.insert(CHECK_KEY, check)?;
+            }
+        }
+        keystore.db.flush()?;
+        Ok(keystore)
+    }
+
+    // Number of stored seeds
+    pub fn seed_count(&self) -> usize {
+        self.db.scan_prefix(SEED_PREFIX).count()
+    }
+
+    pub fn get(&self, telegram_id: i64) -> anyhow::Result<Option<[u8; 32]>> {
+        let id = seed_key(telegram_id);
+        let Some(record) = self.db.get(&id)? else {
+            return Ok(None);
+        };
+        let seed = self
+            .open_record(&record, &id)?
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Stored seed for {} is malformed", telegram_id))?;
+        Ok(Some(seed))
+    }
+
+    // Writes through to disk before returning, so a crash can't lose a new wallet's key
+    pub fn insert(&self, telegram_id: i64, seed: &[u8; 32]) -> anyhow::Result<()> {
+        let id = seed_key(telegram_id);
+        let record = self.seal(seed, &id)?;
+        self.db.insert(id, record)?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    // version || nonce || ciphertext
+    fn seal(&self, plaintext: &[u8], aad: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+        let ciphertext = self
+            .cipher
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: plaintext,
+                    aad,
+                },
+            )
+            .map_err(|_| anyhow::anyhow!("Failed to encrypt keystore record"))?;
+        let mut record = Vec::with_capacity(1 + NONCE_LEN + ciphertext.len());
+        record.push(RECORD_VERSION);
+        record.extend_from_slice(&nonce);
+        record.extend_from_slice(&ciphertext);
+        Ok(record)
+    }
+
+    fn open_record(&self, record: &[u8], aad: &[u8]) -> anyhow::Result<Vec<u8>> {
+        match record.split_first() {
+            Some((&RECORD_VERSION, rest)) if rest.len() > NONCE_LEN => {
+                let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+                self.cipher
+                    .decrypt(
+                        Nonce::from_slice(nonce),
+                        Payload {
+                            msg: ciphertext,
+                            aad,
+                        },
+                    )
+                    .map_err(|_| {
+                        anyhow::anyhow!("Keystore record doesn't decrypt; is the key right?")
+                    })
+            }
+            _ => anyhow::bail!("Unknown keystore record format"),
+        }
+    }
+}
+
+fn seed_key(telegram_id: i64) -> Vec<u8> {
+    [SEED_PREFIX, &telegram_id.to_le_bytes()].concat()
+}
+
+// Seed for a new user's wallet
+pub fn new_seed() -> [u8; 32] {
+    let mut seed = [0u8; 32];
+    OsRng.fill_bytes(&mut seed);
+    seed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("keystore-{}-{}", name, uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn seeds_survive_reopening() {
+        let path = temp_path("reopen");
+        let seed = new_seed();
+        {
+            let keystore =
+                Keystore::open(&path, KeySource::Passphrase("correct horse".into())).unwrap();
+            assert_eq!(keystore.seed_count(), 0);
+            keystore.insert(42, &seed).unwrap();
+            assert_eq!(keystore.get(42).unwrap(), Some(seed));
+            assert_eq!(keystore.get(43).unwrap(), None);
+        }
+        let keystore =
+            Keystore::open(&path, KeySource::Passphrase("correct horse".into())).unwrap();
+        assert_eq!(keystore.get(42).unwrap(), Some(seed));
+        assert_eq!(keystore.seed_count(), 1);
+        drop(keystore);
+
+        // Another passphrase derives another key, which the check value rejects
+        assert!(Keystore::open(&path, KeySource::Passphrase("battery staple".into())).is_err());
+        let _ = std::fs::remove_dir_all(&path);
+    }
+
+    #[test]
+    fn records_are_bound_to_their_user() {
+        let path = temp_path("bound");
+        let keystore = Keystore::open(&path, KeySource::Key([7; 32])).unwrap();
+        keystore.insert(1, &[1; 32]).unwrap();
+
+        // Copying user 1's record under user 2's id doesn't hand user 2 the seed
+        let record = keystore.db.get(seed_key(1)).unwrap().unwrap();
+        assert!(!record.windows(32).any(|window| window == [1; 32]));
+        keystore.db.insert(seed_key(2), record).unwrap();
+        assert!(keystore.get(2).is_err());
+        drop(keystore);
+        let _ = std::fs::remove_dir_all(&path);
+    }
+}
@@ -0,0 +1,261 @@
+// Localization layer applied to everything the bot sends to a chat.
+//
+// Groups can rename the bot's terminology ("proposal" -> "motion", "group" -> "guild")
+// and swap or drop its emoji. Messages are written with the default vocabulary and
+// rewritten here just before sending, so handlers don't need to know about it.
+// Commands such as /createproposal are never rewritten. Messages are translated into
+// the chat's language (see i18n) first, so custom terms only replace English wording.
+
+use crate::i18n::{self, Language};
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// Terms a chat can rename; each has a singular and plural form
+pub const TERMS: [(&str, &str); 4] = [
+    ("proposal", "proposals"),
+    ("group", "groups"),
+    ("vote", "votes"),
+    ("member", "members"),
+];
+
+// Emoji the bot uses in its messages, as affected by emoji sets and overrides
+pub const BOT_EMOJI: [&str; 25] = [
+    "❌", "✅", "🗳️", "🔗", "💡", "📝", "👤", "📋", "📎", "💰", "🔑", "🌐", "🔒", "🚀", "⚙️", "🕵️",
+    "✔️", "📊", "👥", "📅", "⚠️", "💎", "🤖", "⏰", "⏳",
+];
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EmojiSet {
+    // The bot's usual emoji
+    #[default]
+    Default,
+    // No emoji at all, for chats that prefer plain text
+    Plain,
+}
+
+impl EmojiSet {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "default" => Some(Self::Default),
+            "plain" | "none" | "off" => Some(Self::Plain),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Default => "default",
+            Self::Plain => "plain",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Term {
+    pub singular: String,
+    pub plural: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Vocabulary {
+    // Default term (singular, e.g. "proposal") -> replacement
+    #[serde(default)]
+    pub terms: BTreeMap<String, Term>,
+    #[serde(default)]
+    pub emoji_set: EmojiSet,
+    // Individual emoji replacements, applied after the emoji set
+    #[serde(default)]
+    pub emoji: BTreeMap<String, String>,
+    // The chat's language, stored in GroupSettings and filled in by chat_vocabulary
+    #[serde(skip)]
+    pub language: Language,
+}
+
+impl Vocabulary {
+    // Rename `term` (one of TERMS); the plural defaults to the singular plus "s"
+    pub fn set_term(&mut self, term: &str, singular: &str, plural: Option<&str>) -> bool {
+        if !TERMS.iter().any(|(t, _)| *t == term) || !is_word(singular) {
+            return false;
+        }
+        let plural = plural.map_or_else(|| format!("{}s", singular), str::to_string);
+        if !is_word(&plural) {
+            return false;
+        }
+        self.terms.insert(
+            term.to_string(),
+            Term {
+                singular: singular.to_lowercase(),
+                plural: plural.to_lowercase(),
+            },
+        );
+        true
+    }
+
+    // Rewrite a message into this vocabulary
+    pub fn apply(&self, text: &str) -> String {
+        if *self == Self::default() {
+            return text.to_string();
+        }
+        let mut text = self.replace_terms(&i18n::translate(self.language, text));
+        if self.emoji_set == EmojiSet::Plain {
+            for emoji in BOT_EMOJI {
+                text = text.replace(&format!("{} ", emoji), "").replace(emoji, "");
+            }
+        }
+        for (from, to) in &self.emoji {
+            text = text.replace(from.as_str(), to);
+        }
+        text
+    }
+
+    // Human-readable summary for /settings
+    pub fn describe(&self) -> String {
+        let terms = if self.terms.is_empty() {
+            "default".to_string()
+        } else {
+            self.terms
+                .iter()
+                .map(|(term, custom)| format!("{} → {}", term, custom.singular))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let mut emoji = self.emoji_set.as_str().to_string();
+        for (from, to) in &self.emoji {
+            emoji.push_str(&format!(", {} → {}", from, to));
+        }
+        format!("terms: {}; emoji: {}", terms, emoji)
+    }
+
+    fn replace_terms(&self, text: &str) -> String {
+        if self.terms.is_empty() {
+            return text.to_string();
+        }
+
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+        let mut prev: Option<char> = None;
+        while let Some(start) = rest.find(|c: char| c.is_ascii_alphabetic()) {
+            let (before, from_word) = rest.split_at(start);
+            out.push_str(before);
+            prev = before.chars().last().or(prev);
+
+            let len = from_word
+                .find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or(from_word.len());
+            let (word, after) = from_word.split_at(len);
+            let next = after.chars().next();
+            // Leave parts of commands, identifiers and longer words alone
+            let standalone = !prev.is_some_and(|c| c.is_alphanumeric() || c == '/' || c == '_')
+                && !next.is_some_and(|c| c.is_alphanumeric() || c == '_');
+
+            match self.lookup(word).filter(|_| standalone) {
+                Some(replacement) => out.push_str(&match_case(word, replacement)),
+                None => out.push_str(word),
+            }
+            prev = word.chars().last();
+            rest = after;
+        }
+        out.push_str(rest);
+        out
+    }
+
+    fn lookup(&self, word: &str) -> Option<&str> {
+        let lower = word.to_lowercase();
+        TERMS.iter().find_map(|(singular, plural)| {
+            let custom = self.terms.get(*singular)?;
+            if lower == *singular {
+                Some(custom.singular.as_str())
+            } else if lower == *plural {
+                Some(custom.plural.as_str())
+            } else {
+                None
+            }
+        })
+    }
+}
+
+fn is_word(value: &str) -> bool {
+    !value.is_empty() && value.len() <= 32 && value.chars().all(|c| c.is_alphabetic() || c == '-')
+}
+
+// Give `replacement` the capitalization of `original` (lower, Title or UPPER)
+fn match_case(original: &str, replacement: &str) -> String {
+    let mut chars = original.chars();
+    let first_upper = chars.next().is_some_and(|c| c.is_uppercase());
+    if first_upper && original.len() > 1 && chars.all(|c| c.is_uppercase()) {
+        return replacement.to_uppercase();
+    }
+    if first_upper {
+        let mut replacement = replacement.chars();
+        return replacement
+            .next()
+            .map(|c| c.to_uppercase().chain(replacement).collect())
+            .unwrap_or_default();
+    }
+    replacement.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guild_vocabulary() -> Vocabulary {
+        let mut vocabulary = Vocabulary::default();
+        assert!(vocabulary.set_term("proposal", "motion", None));
+        assert!(vocabulary.set_term("group", "guild", None));
+        vocabulary
+    }
+
+    #[test]
+    fn default_vocabulary_leaves_text_alone() {
+        let text = "✅ Proposal created in group tg_1";
+        assert_eq!(Vocabulary::default().apply(text), text);
+    }
+
+    #[test]
+    fn replaces_terms_keeping_case_and_number() {
+        let vocabulary = guild_vocabulary();
+        assert_eq!(
+            vocabulary.apply("Proposal created. Listing proposals for this group."),
+            "Motion created. Listing motions for this guild."
+        );
+        assert_eq!(vocabulary.apply("NO GROUPS FOUND"), "NO GUILDS FOUND");
+    }
+
+    #[test]
+    fn leaves_commands_and_identifiers_alone() {
+        let vocabulary = guild_vocabulary();
+        assert_eq!(
+            vocabulary.apply("Use /createproposal or /creategroup; group_id: <b>group</b>"),
+            "Use /createproposal or /creategroup; group_id: <b>guild</b>"
+        );
+        assert_eq!(
+            vocabulary.apply("subgroup proposals2"),
+            "subgroup proposals2"
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_terms_and_non_words() {
+        let mut vocabulary = Vocabulary::default();
+        assert!(!vocabulary.set_term("wallet", "purse", None));
+        assert!(!vocabulary.set_term("group", "<b>", None));
+        assert!(vocabulary.set_term("group", "Guild", Some("Guilds")));
+        assert_eq!(vocabulary.apply("groups"), "guilds");
+    }
+
+    #[test]
+    fn applies_emoji_set_and_overrides() {
+        let mut vocabulary = Vocabulary {
+            emoji_set: EmojiSet::Plain,
+            ..Vocabulary::default()
+        };
+        assert_eq!(vocabulary.apply("✅ Vote cast! 🗳️ Done"), "Vote cast! Done");
+
+        vocabulary.emoji_set = EmojiSet::Default;
+        vocabulary.emoji.insert("✅".to_string(), "🟢".to_string());
+        assert_eq!(vocabulary.apply("✅ Vote cast!"), "🟢 Vote cast!");
+    }
+}
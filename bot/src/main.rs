@@ -10,22 +10,69 @@ use teloxide::types::BotCommand;
 use tokio::sync::Mutex;
 
 use anchor_client::solana_sdk::{
-    commitment_config::CommitmentConfig, native_token::LAMPORTS_PER_SOL, pubkey::Pubkey,
-    signature::Keypair, system_instruction,
+    native_token::LAMPORTS_PER_SOL, pubkey::Pubkey, signature::Keypair, system_instruction,
 };
-use anchor_client::{Client, Cluster, Program};
+use anchor_client::{Client, Program};
 use anchor_lang::system_program;
 use chrono::{DateTime, Utc};
 use std::str::FromStr;
 use teloxide::{prelude::*, utils::command::BotCommands};
 use uuid::Uuid;
 
+mod announcer;
+mod archive;
+mod ballots;
+mod buttons;
+mod calendar;
+mod callbacks;
+mod chart;
+mod eligibility;
+mod events;
+mod export;
+mod gc;
+mod grpc;
+mod i18n;
+mod keystore;
+mod localization;
+mod network;
+mod rpc;
+mod scoreboard;
+mod settings;
+mod translation;
+mod web;
+mod webhook;
+
 mod solana_dao {
+    // The derives below must use anchor's borsh, not the bot's own borsh dependency
+    use anchor_lang::prelude::borsh;
     use anchor_lang::prelude::*;
     use anchor_lang::AccountDeserialize;
 
     declare_id!("4mwBvEQbpGJKDDZCvEPTujCefmphw1fZ99Jxhz69oHcT");
 
+    pub const METADATA_URI_SCHEMES: [&str; 3] = ["ipfs://", "ar://", "https://"];
+    pub const LOGIN_MESSAGE_PREFIX: &[u8] = b"solana-dao login:";
+    pub const LINK_WALLET_MESSAGE_PREFIX: &str = "solana-dao link wallet:";
+    pub const RELAYED_VOTE_MESSAGE_PREFIX: &[u8] = b"solana-dao vote:";
+    pub const OFFCHAIN_BALLOT_MESSAGE_PREFIX: &[u8] = b"solana-dao ballot:";
+    // Wrapped SOL mint, marking SOL-weighted proposals
+    pub const NATIVE_MINT: Pubkey = pubkey!("So11111111111111111111111111111111111111112");
+    // Settled proposals can be closed this long after voting ends
+    pub const STALE_PROPOSAL_GRACE_SECS: i64 = 30 * 24 * 60 * 60;
+    // Submitted off-chain tallies can be disputed for this long
+    pub const OFFCHAIN_CHALLENGE_SECS: i64 = 2 * 24 * 60 * 60;
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+    pub struct ProgramConfig {
+        pub paused: bool,
+        pub guardian: Option<Pubkey>,
+        pub max_groups: u32,
+        pub max_choices: u8,
+        pub fee_destination: Pubkey,
+        pub updated_at: i64,
+        pub bump: u8,
+    }
+
     #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
     pub struct GroupInfo {
         pub group_id: String,
@@ -34,16 +81,36 @@ mod solana_dao {
     }
 
     #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
-    pub struct ProposalInfo {
+    pub struct ProposalIndex {
+        pub group: Pubkey,
+        pub index: u64,
         pub proposal_id: String,
         pub pubkey: Pubkey,
         pub created_at: i64,
+        pub large: bool,
+        pub bump: u8,
     }
 
+    // Discriminator of the program's Member account: sha256("account:Member")[..8]
+    pub const MEMBER_DISCRIMINATOR: [u8; 8] = [54, 19, 162, 21, 29, 166, 17, 198];
+
     #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
-    pub struct GroupMember {
-        pub pubkey: Pubkey,
+    pub struct Member {
+        pub group: Pubkey,
+        pub wallet: Pubkey,
         pub joined_at: i64,
+        pub weight: u32,
+        pub bump: u8,
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum ProposalStatus {
+        Draft,
+        Active,
+        Succeeded,
+        Defeated,
+        Cancelled,
+        Executed,
     }
 
     #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
@@ -52,12 +119,18 @@ mod solana_dao {
         pub choice: u8,
         pub vote_weight: u64,
         pub timestamp: i64,
+        pub disclosure: Option<String>,
     }
 
     #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
     pub struct DaoRegistry {
         pub authority: Pubkey,
+        pub name: String,
         pub groups: Vec<GroupInfo>,
+        pub open_registration: bool,
+        pub group_deposit_lamports: u64,
+        pub group_deposit_lock_days: u32,
+        pub event_seq: u64,
         pub bump: u8,
     }
 
@@ -70,16 +143,118 @@ mod solana_dao {
 
     #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
     pub struct Group {
+        pub version: u8,
         pub group_id: String,
         pub name: String,
         pub description: String,
         pub authority: Pubkey,
-        pub proposals: Vec<ProposalInfo>,
-        pub members: Vec<GroupMember>,
+        pub proposal_count: u64,
+        pub member_count: u32,
         pub created_at: i64,
+        pub deposit_lamports: u64,
+        pub deposit_unlock_at: i64,
+        pub slug: String,
+        pub metadata_uri: Option<String>,
+        pub event_seq: u64,
+        pub proposal_fee_lamports: u64,
+        pub members_only_voting: bool,
+        pub max_members: u32,
+        pub visibility: GroupVisibility,
+        pub governance: GovernanceConfig,
+        pub dues: Option<DuesPolicy>,
+        pub stake_to_join: Option<StakeToJoin>,
+        pub max_active_proposals: u16,
+        pub active_proposals: u16,
+        pub proposal_cooldown_secs: i64,
+        pub external_chat_id: Option<i64>,
+        pub tie_break: TieBreak,
+        pub sponsor_threshold: u8,
+        pub registry: Pubkey,
+        pub proposal_deposit_lamports: u64,
+        pub committed_lamports: u64,
+        pub bump: u8,
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+    pub enum TieBreak {
+        #[default]
+        Fail,
+        Revote {
+            window_secs: i64,
+        },
+        EarliestLeading,
+        Council,
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct StakeToJoin {
+        pub price_lamports: u64,
+        pub total_shares: u64,
+        pub unowned_shares: u64,
+    }
+
+    impl StakeToJoin {
+        // Mirrors the program: the share price, or the treasury's value per share when
+        // that is higher
+        pub fn cost(&self, pool_lamports: u64, shares: u64) -> Option<u64> {
+            let at_price = self.price_lamports.checked_mul(shares)?;
+            if self.total_shares == 0 {
+                return Some(at_price);
+            }
+            let at_value =
+                (pool_lamports as u128 * shares as u128).div_ceil(self.total_shares as u128);
+            u64::try_from(at_value)
+                .ok()
+                .map(|at_value| at_value.max(at_price))
+        }
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+    pub struct MemberShares {
+        pub group: Pubkey,
+        pub member: Pubkey,
+        pub shares: u64,
+        pub locked_until: i64,
+        pub bump: u8,
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct DuesPolicy {
+        pub mint: Pubkey,
+        pub amount: u64,
+        pub period_secs: i64,
+        pub grace_secs: i64,
+        pub required_for_voting: bool,
+    }
+
+    // Discriminator of the program's DuesRecord account: sha256("account:DuesRecord")[..8]
+    pub const DUES_RECORD_DISCRIMINATOR: [u8; 8] = [33, 206, 142, 12, 22, 161, 190, 193];
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+    pub struct DuesRecord {
+        pub group: Pubkey,
+        pub member: Pubkey,
+        pub paid_until: i64,
+        pub last_paid_at: i64,
+        pub overdue_flagged: bool,
         pub bump: u8,
     }
 
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum GroupVisibility {
+        Public,
+        Private,
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+    pub struct GovernanceConfig {
+        pub quorum_bps: u16,
+        pub approval_threshold_bps: u16,
+        pub min_voting_duration: i64,
+        pub max_voting_duration: i64,
+        pub weight_quorum_bps: u16,
+    }
+
     impl AccountDeserialize for Group {
         fn try_deserialize_unchecked(buf: &mut &[u8]) -> anchor_lang::Result<Self> {
             AnchorDeserialize::deserialize(buf)
@@ -89,6 +264,7 @@ mod solana_dao {
 
     #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
     pub struct Proposal {
+        pub version: u8,
         pub proposal_id: String,
         pub group_id: String,
         pub title: String,
@@ -101,9 +277,313 @@ mod solana_dao {
         pub creator: Pubkey,
         pub voters: Vec<VoterInfo>,
         pub created_at: i64,
+        pub index: u64,
+        pub metadata_uri: Option<String>,
+        pub status: ProposalStatus,
+        pub finalize_bounty: u64,
+        pub shadow_votes: Vec<u32>,
+        pub offchain_aggregator: Option<Pubkey>,
+        pub offchain_voter_count: Option<u32>,
+        pub price_weighted: bool,
+        pub member_weighted: bool,
+        pub kind: ProposalKind,
+        pub emergency: bool,
+        pub split_voting: bool,
+        pub slot_window: Option<SlotWindow>,
+        pub weight_decay_bps: u16,
+        pub deposit_voting: Option<DepositVoting>,
+        pub council_chamber: Option<CouncilChamber>,
+        pub winner: Option<u8>,
+        pub tied: bool,
+        pub sponsors_needed: u8,
+        pub sponsor_count: u8,
+        pub bump: u8,
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+    pub struct CouncilChamber {
+        pub members: Vec<Pubkey>,
+        pub threshold: u8,
+        pub choice_votes: Vec<u32>,
+        pub voted: u8,
+    }
+
+    // Only deposits into `registrar` made by `cutoff` count
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct DepositVoting {
+        pub registrar: Pubkey,
+        pub cutoff: i64,
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+    pub struct DepositRegistrar {
+        pub group: Pubkey,
+        pub mint: Pubkey,
+        pub vault: Pubkey,
+        pub min_holding_secs: i64,
+        pub bump: u8,
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+    pub struct VoterDeposit {
+        pub registrar: Pubkey,
+        pub voter: Pubkey,
+        pub amount: u64,
+        pub deposited_at: i64,
+        pub bump: u8,
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+    pub struct VoterLottery {
+        pub group: Pubkey,
+        pub proposal: Pubkey,
+        pub funder: Pubkey,
+        pub total_amount: u64,
+        pub winner_count: u8,
+        pub winners: Vec<Pubkey>,
+        pub prize: u64,
+        pub claimed_mask: u16,
+        pub claim_deadline: i64,
+        pub drawn: bool,
+        pub bump: u8,
+    }
+
+    // When set, voting_start and voting_end only estimate the window
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct SlotWindow {
+        pub start_slot: u64,
+        pub end_slot: u64,
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+    pub enum ProposalKind {
+        Text,
+        TreasuryTransfer(TreasuryTransfer),
+        ConfigChange(ConfigChange),
+        MembershipChange(MembershipChange),
+        Election(Election),
+        Grant(GrantTerms),
+        Stream(StreamTerms),
+        Bounty(BountyTerms),
+        TreasuryRecovery(TreasuryRecovery),
+        TreasuryStake(StakeAction),
+        TreasurySwap(SwapTerms),
+        TokenDistribution(DistributionTerms),
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+    pub struct DistributionTerms {
+        pub mint: Pubkey,
+        pub source: DistributionSource,
+        pub merkle_root: [u8; 32],
+        pub total_amount: u64,
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum DistributionSource {
+        Mint,
+        Treasury,
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+    pub struct SwapTerms {
+        pub swap_program: Pubkey,
+        pub input_mint: Pubkey,
+        pub output_mint: Pubkey,
+        pub amount_in: u64,
+        pub min_amount_out: u64,
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+    pub enum StakeAction {
+        Delegate { vote_account: Pubkey, lamports: u64 },
+        Deactivate { stake_account: Pubkey },
+        Withdraw { stake_account: Pubkey },
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+    pub struct TreasuryRecovery {
+        pub recovery: Pubkey,
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+    pub struct TreasuryTransfer {
+        pub recipient: Pubkey,
+        pub lamports: u64,
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+    pub struct ConfigChange {
+        pub proposal_fee_lamports: Option<u64>,
+        pub members_only_voting: Option<bool>,
+        pub max_members: Option<u32>,
+        pub visibility: Option<GroupVisibility>,
+        pub governance: Option<GovernanceConfig>,
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum MembershipAction {
+        Add,
+        Remove,
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+    pub struct MembershipChange {
+        pub member: Pubkey,
+        pub action: MembershipAction,
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+    pub struct Election {
+        pub candidates: Vec<Pubkey>,
+        pub seats: u8,
+        pub threshold: u8,
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+    pub struct GrantTerms {
+        pub recipient: Pubkey,
+        pub milestones: Vec<u64>,
+        pub approval: MilestoneApproval,
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum MilestoneApproval {
+        Council,
+        MemberVote,
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+    pub struct ChoiceDetails {
+        pub proposal: Pubkey,
+        pub details: Vec<ChoiceDetail>,
+        pub bump: u8,
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default, PartialEq, Eq)]
+    pub struct ChoiceDetail {
+        pub description: Option<String>,
+        pub uri: Option<String>,
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default)]
+    pub struct GroupProfile {
+        pub group: Pubkey,
+        pub avatar_uri: Option<String>,
+        pub website: Option<String>,
+        pub socials: Vec<String>,
+        pub contact: Option<String>,
+        pub updated_at: i64,
+        pub bump: u8,
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+    pub struct Grant {
+        pub group: Pubkey,
+        pub proposal: Pubkey,
+        pub recipient: Pubkey,
+        pub approval: MilestoneApproval,
+        pub milestones: Vec<Milestone>,
+        pub released: u64,
+        pub created_at: i64,
+        pub bump: u8,
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+    pub struct Milestone {
+        pub amount: u64,
+        pub approvals: u32,
+        pub approved_at: i64,
+        pub claimed: bool,
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+    pub struct StreamTerms {
+        pub recipient: Pubkey,
+        pub amount: u64,
+        pub start: i64,
+        pub cliff: i64,
+        pub end: i64,
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+    pub struct Stream {
+        pub group: Pubkey,
+        pub proposal: Pubkey,
+        pub recipient: Pubkey,
+        pub amount: u64,
+        pub start: i64,
+        pub cliff: i64,
+        pub end: i64,
+        pub withdrawn: u64,
+        pub bump: u8,
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+    pub struct BountyTerms {
+        pub bounty_id: String,
+        pub description_uri: String,
+        pub reward: u64,
+    }
+
+    // Discriminator of the program's Bounty account: sha256("account:Bounty")[..8]
+    pub const BOUNTY_DISCRIMINATOR: [u8; 8] = [237, 16, 105, 198, 19, 69, 242, 234];
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+    pub struct Bounty {
+        pub group: Pubkey,
+        pub bounty_id: String,
+        pub description_uri: String,
+        pub reward: u64,
+        pub status: BountyStatus,
+        pub posted_by: Pubkey,
+        pub hunter: Option<Pubkey>,
+        pub submission_uri: Option<String>,
+        pub created_at: i64,
         pub bump: u8,
     }
 
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum BountyStatus {
+        Open,
+        Claimed,
+        Submitted,
+        Paid,
+        Cancelled,
+    }
+
+    impl Stream {
+        // Same schedule as the program's Stream::vested_at
+        pub fn vested_at(&self, now: i64) -> u64 {
+            if now < self.cliff {
+                return 0;
+            }
+            if now >= self.end {
+                return self.amount;
+            }
+            let elapsed = (now - self.start) as u128;
+            let duration = (self.end - self.start) as u128;
+            (self.amount as u128 * elapsed / duration) as u64
+        }
+    }
+
+    impl Proposal {
+        // Ballots actually cast; linked wallets counted into another vote are recorded
+        // with zero weight only to stop them voting twice
+        pub fn ballots(&self) -> impl Iterator<Item = &VoterInfo> {
+            self.voters.iter().filter(|v| v.vote_weight > 0)
+        }
+
+        // Mirrors the program: whether a succeeded proposal's kind still has to execute
+        pub fn awaits_execution(&self) -> bool {
+            match self.kind {
+                ProposalKind::Text => false,
+                ProposalKind::Election(_) => true,
+                _ => self.winner == Some(0),
+            }
+        }
+    }
+
     impl AccountDeserialize for Proposal {
         fn try_deserialize_unchecked(buf: &mut &[u8]) -> anchor_lang::Result<Self> {
             AnchorDeserialize::deserialize(buf)
@@ -111,11 +591,59 @@ mod solana_dao {
         }
     }
 
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+    pub struct PricedMint {
+        pub mint: Pubkey,
+        pub oracle: Pubkey,
+        pub decimals: u8,
+    }
+
+    // A program upgrade hanging off a proposal, executable once it passed and the
+    // timelock ran out
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+    pub struct UpgradeProposal {
+        pub proposal: Pubkey,
+        pub buffer: Pubkey,
+        pub approve_choice: u8,
+        pub eta: i64,
+        pub executed_at: i64,
+        pub bump: u8,
+    }
+
+    // A proposer's anti-spam deposit, escrowed until the proposal is settled
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+    pub struct ProposalDeposit {
+        pub proposal: Pubkey,
+        pub group: Pubkey,
+        pub depositor: Pubkey,
+        pub amount: u64,
+        pub spam_choice: u8,
+        pub flags: Vec<Pubkey>,
+        pub flagged: bool,
+        pub bump: u8,
+    }
+
+    // Accepted mints and their price accounts on a price-weighted proposal
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+    pub struct PriceWeighting {
+        pub proposal: Pubkey,
+        pub feeds: Vec<PricedMint>,
+        pub max_staleness_secs: u32,
+        pub bump: u8,
+    }
+
+    // Discriminator of the program's UserAccount account: sha256("account:UserAccount")[..8]
+    pub const USER_ACCOUNT_DISCRIMINATOR: [u8; 8] = [211, 33, 136, 16, 186, 110, 242, 127];
+
     #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
     pub struct UserAccount {
+        pub version: u8,
         pub telegram_id: i64,
         pub wallet_pubkey: Pubkey,
         pub created_at: i64,
+        pub linked_wallets: Vec<Pubkey>,
+        pub last_login: i64,
+        pub login_nonce: u64,
         pub bump: u8,
     }
 
@@ -125,6 +653,82 @@ mod solana_dao {
                 .map_err(|_| anchor_lang::error::ErrorCode::AccountDidNotDeserialize.into())
         }
     }
+
+    // Discriminator of the program's ProposalTemplate account: sha256("account:ProposalTemplate")[..8]
+    pub const PROPOSAL_TEMPLATE_DISCRIMINATOR: [u8; 8] = [118, 192, 108, 30, 132, 192, 0, 111];
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+    pub struct ProposalTemplate {
+        pub group: Pubkey,
+        pub template_id: String,
+        pub title: String,
+        pub description: String,
+        pub choices: Vec<String>,
+        pub voting_duration: i64,
+        pub token_mint: Option<Pubkey>,
+        pub metadata_uri: Option<String>,
+        pub kind: ProposalKind,
+        pub member_weighted: bool,
+        pub split_voting: bool,
+        pub instance_count: u64,
+        pub created_at: i64,
+        pub bump: u8,
+    }
+
+    // Discriminator of the program's Delegation account: sha256("account:Delegation")[..8]
+    pub const DELEGATION_DISCRIMINATOR: [u8; 8] = [237, 90, 140, 159, 124, 255, 243, 80];
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+    pub struct Delegation {
+        pub delegator: Pubkey,
+        pub delegate: Pubkey,
+        // The default key for a delegation covering every group
+        pub group: Pubkey,
+        pub kind: Option<u8>,
+        pub expires_at: Option<i64>,
+        pub created_at: i64,
+        pub bump: u8,
+    }
+
+    impl Delegation {
+        pub fn expired(&self, now: i64) -> bool {
+            self.expires_at.is_some_and(|expires_at| now > expires_at)
+        }
+    }
+
+    // Event discriminators: sha256("event:<Name>")[..8]
+    pub const VOTE_CAST_EVENT_DISCRIMINATOR: [u8; 8] = [241, 151, 159, 134, 250, 234, 71, 234];
+    pub const PROPOSAL_CREATED_EVENT_DISCRIMINATOR: [u8; 8] =
+        [154, 240, 33, 66, 194, 233, 203, 209];
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+    pub struct VoteCastEvent {
+        pub group_id: String,
+        pub event_seq: u64,
+        pub proposal_id: String,
+        pub voter: Pubkey,
+        pub choice: u8,
+        pub vote_weight: u64,
+        pub timestamp: i64,
+        pub disclosure: Option<String>,
+        pub allocations: Option<Vec<u64>>,
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+    pub struct ProposalCreatedEvent {
+        pub group_id: String,
+        pub event_seq: u64,
+        pub proposal_id: String,
+        pub creator: Pubkey,
+        pub proposal_pubkey: Pubkey,
+        pub voting_start: i64,
+        pub voting_end: i64,
+        pub metadata_uri: Option<String>,
+        pub draft: bool,
+        pub emergency: bool,
+        pub creation_fee: u64,
+        pub timestamp: i64,
+    }
 }
 
 #[derive(BotCommands, Clone, Debug)]
@@ -135,15 +739,17 @@ enum Command {
     #[command(description = "Start the bot")]
     Start,
     #[command(description = "Create a new DAO group")]
-    CreateGroup(String), // Combined: "name description"
+    CreateGroup(String), // Combined: "name description [metadata_uri]"
     #[command(description = "List all DAO groups")]
     ListGroups,
     #[command(description = "Create a new proposal")]
-    CreateProposal(String), // Combined: "title description choices duration_hours"
+    CreateProposal(String), // Combined: "title description choices duration_hours [metadata_uri]"
     #[command(description = "List proposals for a group")]
     ListProposals,
-    #[command(description = "Vote on a proposal", parse_with = "split")]
-    Vote { proposal_id: String, choice: u8 },
+    #[command(description = "Vote on a proposal")]
+    Vote(String), // "proposal_id choice|allocations [coi [note]]"
+    #[command(description = "Cast a non-counting vote as a prospective member")]
+    ShadowVote(String), // "proposal_id choice"
     #[command(description = "Get proposal results")]
     Results { proposal_id: String },
     #[command(description = "Create or access your Solana account")]
@@ -154,6 +760,64 @@ enum Command {
     Balance,
     #[command(description = "Fund your account with SOL for voting")]
     FundAccount,
+    #[command(description = "Open the voting kiosk")]
+    Kiosk,
+    #[command(description = "Show or change group settings")]
+    Settings(String), // "" to show, "privacy public|private" or "language <code>|off"
+    #[command(description = "Translate proposals into your language")]
+    Translate(String), // "<code>", "auto" or "off"
+    #[command(description = "Show voting deadlines for the next two weeks")]
+    Calendar,
+    #[command(description = "Archive the discussion around a proposal")]
+    Archive { proposal_id: String },
+    #[command(description = "Preview who can vote on a proposal and with what weight")]
+    Eligible { proposal_id: String },
+    #[command(description = "Export a proposal's results as a CSV or JSON file")]
+    Export(String), // "<proposal_id> [csv|json]"
+    #[command(description = "Collect a proposal's votes off-chain and settle them on-chain")]
+    Offchain(String), // "enable|submit|settle proposal_id"
+    #[command(description = "Create an invite code for joining the group's DAO")]
+    Invite(String), // "[max_uses] [valid_days]"
+    #[command(description = "Join the group's DAO with an invite code")]
+    Join { code: String },
+    #[command(description = "Set the group's member cap and visibility")]
+    Membership(String), // "<max_members> <public|private>"
+    #[command(description = "Set how many proposals can be open for voting at once")]
+    ProposalCap(String), // "<max_active_proposals>"
+    #[command(description = "Set how long a creator waits between proposals")]
+    Cooldown(String), // "<hours>"
+    #[command(description = "Set how a tie for first place is settled")]
+    TieBreak(String), // "fail", "revote <hours>", "earliest" or "council"
+    #[command(description = "Ban a wallet from joining and voting, or lift a ban")]
+    Ban(String), // "<wallet> <days> [reason]" or "lift <wallet>"
+    #[command(description = "Describe a proposal's choice, e.g. a candidate")]
+    ChoiceInfo(String), // "<proposal_id> <choice> <description> [uri]"
+    #[command(description = "List, save or reuse proposal templates")]
+    Template(String), // "", "save <template_id> <proposal_id>" or "use <template_id> [start_in_hours]"
+    #[command(description = "Lock SOL for deposit-weighted votes")]
+    Deposit(String), // "", "<sol>", "withdraw <sol>", "setup <holding_days>" or "require <proposal_id>"
+    #[command(description = "Pay membership dues or check who is behind")]
+    Dues(String), // "", "pay [periods]", "overdue", "setup <sol> <period_days> <grace_days> [required]" or "off"
+    #[command(description = "Buy shares to join, or ragequit with your cut of the treasury")]
+    Shares(String), // "", "buy <shares>", "ragequit <shares>", "price <sol>" or "off"
+    #[command(description = "Browse, claim and hand in bounties")]
+    Bounty(String), // "", "post <bounty_id> <sol> <uri>", "claim|approve|cancel <bounty_id>" or "submit <bounty_id> <uri>"
+    #[command(description = "Show the group's profile card, or edit it")]
+    Profile(String), // "" or "avatar|website|socials|contact [value]"
+    #[command(description = "Co-sponsor a draft so it can open for voting")]
+    Sponsor(String), // "<proposal_id>" or "require <members>"
+    #[command(description = "Show which Solana cluster the bot is using")]
+    Network,
+    #[command(description = "Show or change the bot's language in this chat")]
+    Language(String), // "[en|es|ru]"
+    #[command(description = "Delegate your votes to another member, or see who delegates to you")]
+    Delegate(String), // "[wallet] [days]", or a reply to the delegate's message
+    #[command(description = "Take back the votes you delegated")]
+    Undelegate,
+    #[command(description = "Send SOL from your wallet to another address")]
+    Withdraw(String), // "<address> <sol|all>"
+    #[command(description = "Link your own wallet so its holdings count toward your votes")]
+    LinkWallet(String), // "", "<wallet> <signature>" or "remove <wallet>"
 }
 
 #[derive(Clone)]
@@ -161,8 +825,18 @@ struct BotState {
     solana_client: Arc<anchor_client::Client<Arc<Keypair>>>,
     program: Arc<Program<Arc<Keypair>>>,
     payer: Arc<Keypair>,
-    user_seeds: Arc<Mutex<HashMap<UserId, [u8; 32]>>>, // telegram_id -> seed for keypair generation
-    admin_groups: Arc<Mutex<HashMap<i64, String>>>,    // chat_id -> group_id
+    keystore: Arc<keystore::Keystore>, // telegram_id -> encrypted seed for keypair generation
+    admin_groups: Arc<Mutex<HashMap<i64, String>>>, // chat_id -> group_id
+    webapp_url: Option<String>,        // public base URL of the web service
+    settings: Arc<settings::SettingsStore<settings::GroupSettings>>, // per-chat bot settings
+    user_settings: Arc<settings::SettingsStore<settings::UserSettings>>, // per-user preferences
+    translator: Option<Arc<translation::Translator>>, // configured translation provider
+    discussions: Arc<settings::SettingsStore<archive::DiscussionLog>>, // per-chat message log
+    ballots: Arc<settings::SettingsStore<ballots::BallotBox>>, // per-chat off-chain ballots
+    confirmations: Arc<callbacks::Confirmations>, // actions awaiting a Confirm button
+    announced_results: Arc<settings::SettingsStore<announcer::AnnouncedResults>>, // per-chat announced results
+    scoreboards: Arc<settings::SettingsStore<scoreboard::Scoreboards>>, // per-chat live scoreboards
+    network: Arc<network::NetworkConfig>, // cluster, RPC endpoint and explorer links
 }
 
 impl BotState {
@@ -170,16 +844,25 @@ impl BotState {
         // Load or create keypair for the bot payer
         let payer = Arc::new(load_or_create_payer_keypair().await?);
 
-        let client = Client::new_with_options(
-            Cluster::Localnet, // Change to Devnet/Mainnet as needed
-            payer.clone(),
-            CommitmentConfig::processed(),
+        let network = network::NetworkConfig::from_env().await?;
+        log::info!(
+            "Using {} at {} with {:?} commitment",
+            network.name,
+            network.rpc_host(),
+            network.commitment.commitment
         );
+        let client =
+            Client::new_with_options(network.cluster.clone(), payer.clone(), network.commitment);
 
         let program = client.program(solana_dao::ID)?;
 
-        // Ensure the payer has some SOL for transactions
-        let _ = ensure_payer_funded(&client, &payer).await;
+        let keystore = keystore::Keystore::open_from_env()?;
+        log::info!("Keystore loaded with {} user keys", keystore.seed_count());
+
+        // Ensure the payer has some SOL for transactions; mainnet has no faucet
+        if !network.is_mainnet() {
+            let _ = ensure_payer_funded(&client, &payer).await;
+        }
 
         // Initialize the DAO registry if it doesn't exist (ignore errors if already initialized)
         match initialize_dao_registry(&client, &program, &payer).await {
@@ -196,31 +879,75 @@ impl BotState {
             }
         }
 
+        // Only succeeds when the bot payer is the registry authority
+        match initialize_program_config(&client, &program, &payer).await {
+            Ok(result) => {
+                if result != "already_initialized" {
+                    log::info!("Program config initialized: {}", result);
+                }
+            }
+            Err(e) => {
+                log::warn!("Program config initialization failed: {}", e);
+            }
+        }
+
         Ok(Self {
             solana_client: Arc::new(client),
             program: Arc::new(program),
             payer,
-            user_seeds: Arc::new(Mutex::new(HashMap::new())),
+            keystore: Arc::new(keystore),
             admin_groups: Arc::new(Mutex::new(HashMap::new())),
+            webapp_url: std::env::var("WEBAPP_URL")
+                .ok()
+                .map(|url| url.trim_end_matches('/').to_string()),
+            settings: Arc::new(settings::SettingsStore::load(
+                "GROUP_SETTINGS_PATH",
+                settings::DEFAULT_GROUP_SETTINGS_PATH,
+            )?),
+            user_settings: Arc::new(settings::SettingsStore::load(
+                "USER_SETTINGS_PATH",
+                settings::DEFAULT_USER_SETTINGS_PATH,
+            )?),
+            translator: translation::Translator::from_env()?.map(Arc::new),
+            discussions: Arc::new(settings::SettingsStore::load(
+                "DISCUSSIONS_PATH",
+                archive::DEFAULT_DISCUSSIONS_PATH,
+            )?),
+            ballots: Arc::new(settings::SettingsStore::load(
+                "BALLOTS_PATH",
+                ballots::DEFAULT_BALLOTS_PATH,
+            )?),
+            confirmations: Arc::new(callbacks::Confirmations::default()),
+            announced_results: Arc::new(settings::SettingsStore::load(
+                "ANNOUNCED_RESULTS_PATH",
+                announcer::DEFAULT_ANNOUNCED_RESULTS_PATH,
+            )?),
+            scoreboards: Arc::new(settings::SettingsStore::load(
+                "SCOREBOARDS_PATH",
+                scoreboard::DEFAULT_SCOREBOARDS_PATH,
+            )?),
+            network: Arc::new(network),
         })
     }
 }
 
 async fn answer(bot: Bot, msg: Message, cmd: Command, state: BotState) -> ResponseResult<()> {
+    let vocab = chat_vocabulary(&state, msg.chat.id).await;
     log::info!("Command received: {:?}", cmd);
     match cmd {
         Command::Help => {
-            bot.send_message(msg.chat.id, Command::descriptions().to_string())
-                .await?;
+            let help = i18n::help(vocab.language, &Command::descriptions().to_string());
+            bot.send_localized(&vocab, msg.chat.id, help).await?;
         }
         Command::Start => {
             let welcome_msg = "Welcome to Solana DAO Bot! 🚀\n\n\
                 Use /help to see available commands.\n\
                 Use /login to create or access your account automatically.\n\
                 Use /account to view your wallet address and account info.";
-            bot.send_message(msg.chat.id, welcome_msg).await?;
+            bot.send_localized(&vocab, msg.chat.id, welcome_msg).await?;
         }
         Command::CreateGroup(args) => {
+            let (args, metadata_uri) = split_metadata_uri(&args);
             // Parse the arguments: "name description" or "name" "description"
             let (name, description) = if args.contains('"') {
                 // Handle quoted arguments
@@ -255,21 +982,22 @@ async fn answer(bot: Bot, msg: Message, cmd: Command, state: BotState) -> Respon
                 if parts.len() >= 2 {
                     (parts[0].to_string(), parts[1].to_string())
                 } else {
-                    (parts.get(0).unwrap_or(&"").to_string(), String::new())
+                    (parts.first().unwrap_or(&"").to_string(), String::new())
                 }
             };
 
             if name.is_empty() || description.is_empty() {
-                bot.send_message(msg.chat.id, "Usage: /creategroup <name> <description>\nExample: /creategroup \"My Group\" \"Group description\"").await?;
+                bot.send_localized(&vocab,msg.chat.id, "Usage: /creategroup <name> <description> [metadata_uri]\nExample: /creategroup \"My Group\" \"Group description\" ipfs://bafy...").await?;
                 return Ok(());
             }
 
-            handle_create_group(bot, msg, name, description, state).await?;
+            handle_create_group(bot, msg, name, description, metadata_uri, state).await?;
         }
         Command::ListGroups => {
             handle_list_groups(bot, msg, state).await?;
         }
         Command::CreateProposal(args) => {
+            let (args, metadata_uri) = split_metadata_uri(&args);
             // Parse the arguments: "title description choices duration_hours"
             let (title, description, choices, duration_hours) = if args.contains('"') {
                 // Handle quoted arguments
@@ -322,21 +1050,68 @@ async fn answer(bot: Bot, msg: Message, cmd: Command, state: BotState) -> Respon
             };
 
             if title.is_empty() || description.is_empty() || choices.is_empty() {
-                bot.send_message(msg.chat.id, "Usage: /createproposal <title> <description> <choices> <duration_hours>\nExample: /createproposal \"Budget Allocation\" \"How should we allocate the budget?\" \"Marketing,Development,Operations\" 48").await?;
+                bot.send_localized(&vocab,msg.chat.id, "Usage: /createproposal <title> <description> <choices> <duration_hours> [metadata_uri]\nExample: /createproposal \"Budget Allocation\" \"How should we allocate the budget?\" \"Marketing,Development,Operations\" 48 https://forum.example.org/t/budget").await?;
                 return Ok(());
             }
 
-            handle_create_proposal(bot, msg, title, description, choices, duration_hours, state)
-                .await?;
+            handle_create_proposal(
+                bot,
+                msg,
+                title,
+                description,
+                choices,
+                duration_hours,
+                metadata_uri,
+                state,
+            )
+            .await?;
         }
         Command::ListProposals => {
             handle_list_proposals(bot, msg, state).await?;
         }
-        Command::Vote {
-            proposal_id,
-            choice,
-        } => {
-            handle_vote(bot, msg, proposal_id, choice, state).await?;
+        Command::Vote(args) => match parse_vote_args(&args) {
+            Some((proposal_id, choice, allocations, disclosure)) => {
+                handle_vote(
+                    bot,
+                    msg,
+                    proposal_id,
+                    choice,
+                    allocations,
+                    disclosure,
+                    state,
+                )
+                .await?;
+            }
+            None => {
+                bot.send_localized(
+                    &vocab,
+                    msg.chat.id,
+                    "❌ Usage: /vote <proposal_id> <choice_number> [coi [note]]\n\
+                    Add \"coi\" to disclose a conflict of interest, e.g. \
+                    /vote abc123 1 coi I am the grant recipient\n\
+                    On split-weight proposals, give the weight for each choice instead, e.g. \
+                    /vote abc123 600,300,100",
+                )
+                .await?;
+            }
+        },
+        Command::ShadowVote(args) => {
+            let mut parts = args.split_whitespace();
+            match (parts.next(), parts.next().and_then(|c| c.parse().ok())) {
+                (Some(proposal_id), Some(choice)) => {
+                    handle_shadow_vote(bot, msg, proposal_id.to_string(), choice, state).await?;
+                }
+                _ => {
+                    bot.send_localized(
+                        &vocab,
+                        msg.chat.id,
+                        "❌ Usage: /shadowvote <proposal_id> <choice_number>\n\
+                        Shadow votes let non-members show where they stand; \
+                        they are listed separately and don't count toward the result.",
+                    )
+                    .await?;
+                }
+            }
         }
         Command::Results { proposal_id } => {
             handle_results(bot, msg, proposal_id, state).await?;
@@ -353,43 +1128,572 @@ async fn answer(bot: Bot, msg: Message, cmd: Command, state: BotState) -> Respon
         Command::FundAccount => {
             handle_fund_account(bot, msg, state).await?;
         }
+        Command::Kiosk => {
+            handle_kiosk(bot, msg, state).await?;
+        }
+        Command::Settings(args) => {
+            handle_settings(bot, msg, args, state).await?;
+        }
+        Command::Translate(args) => {
+            handle_translate(bot, msg, args, state).await?;
+        }
+        Command::Calendar => {
+            handle_calendar(bot, msg, state).await?;
+        }
+        Command::Export(args) => {
+            handle_export(bot, msg, args, state).await?;
+        }
+        Command::Archive { proposal_id } => {
+            handle_archive(bot, msg, proposal_id, state).await?;
+        }
+        Command::Eligible { proposal_id } => {
+            handle_eligible(bot, msg, proposal_id, state).await?;
+        }
+        Command::Offchain(args) => {
+            handle_offchain(bot, msg, args, state).await?;
+        }
+        Command::Invite(args) => {
+            handle_invite(bot, msg, args, state).await?;
+        }
+        Command::Join { code } => {
+            handle_join(bot, msg, code, state).await?;
+        }
+        Command::Membership(args) => {
+            handle_membership(bot, msg, args, state).await?;
+        }
+        Command::ProposalCap(args) => {
+            handle_proposal_cap(bot, msg, args, state).await?;
+        }
+        Command::Cooldown(args) => {
+            handle_cooldown(bot, msg, args, state).await?;
+        }
+        Command::TieBreak(args) => {
+            handle_tie_break(bot, msg, args, state).await?;
+        }
+        Command::Ban(args) => {
+            handle_ban(bot, msg, args, state).await?;
+        }
+        Command::ChoiceInfo(args) => {
+            handle_choice_info(bot, msg, args, state).await?;
+        }
+        Command::Template(args) => {
+            handle_template(bot, msg, args, state).await?;
+        }
+        Command::Deposit(args) => {
+            handle_deposit(bot, msg, args, state).await?;
+        }
+        Command::Dues(args) => {
+            handle_dues(bot, msg, args, state).await?;
+        }
+        Command::Shares(args) => {
+            handle_shares(bot, msg, args, state).await?;
+        }
+        Command::Sponsor(args) => {
+            handle_sponsor(bot, msg, args, state).await?;
+        }
+        Command::Bounty(args) => {
+            handle_bounty(bot, msg, args, state).await?;
+        }
+        Command::Profile(args) => {
+            handle_profile(bot, msg, args, state).await?;
+        }
+        Command::Network => {
+            handle_network(bot, msg, state).await?;
+        }
+        Command::Language(args) => {
+            handle_language(bot, msg, args, state).await?;
+        }
+        Command::Delegate(args) => {
+            handle_delegate(bot, msg, args, state).await?;
+        }
+        Command::Undelegate => {
+            handle_undelegate(bot, msg, state).await?;
+        }
+        Command::Withdraw(args) => {
+            handle_withdraw(bot, msg, args, state).await?;
+        }
+        Command::LinkWallet(args) => {
+            handle_link_wallet(bot, msg, args, state).await?;
+        }
     }
     Ok(())
 }
 
-async fn handle_fund_account(bot: Bot, msg: Message, state: BotState) -> ResponseResult<()> {
-    let user_id = match msg.from() {
-        Some(user) => user.id,
+async fn handle_kiosk(bot: Bot, msg: Message, state: BotState) -> ResponseResult<()> {
+    let vocab = chat_vocabulary(&state, msg.chat.id).await;
+    let base_url = match &state.webapp_url {
+        Some(url) => url,
         None => {
-            bot.send_message(msg.chat.id, "❌ Unable to identify user. Please try again.")
-                .await?;
-            return Ok(());
-        }
-    };
-    let telegram_id = user_id.0 as i64;
-
-    // Ensure user has an account
-    let user_keypair = match ensure_user_account(&state, telegram_id).await {
-        Ok(keypair) => keypair,
-        Err(e) => {
-            bot.send_message(
+            bot.send_localized(
+                &vocab,
                 msg.chat.id,
-                format!(
-                    "❌ Failed to access your account: {}. Please try /login first.",
-                    e
-                ),
+                "❌ The voting kiosk is not configured. Set WEBAPP_URL to enable it.",
             )
             .await?;
             return Ok(());
         }
     };
 
-    // Check current balance
+    let kiosk_url =
+        match reqwest::Url::parse(&format!("{}/kiosk?chat_id={}", base_url, msg.chat.id.0)) {
+            Ok(url) => url,
+            Err(e) => {
+                log::error!("Invalid WEBAPP_URL '{}': {}", base_url, e);
+                bot.send_localized(
+                    &vocab,
+                    msg.chat.id,
+                    "❌ The voting kiosk URL is misconfigured.",
+                )
+                .await?;
+                return Ok(());
+            }
+        };
+
+    // Telegram only allows Web App buttons in private chats; groups get a plain link
+    // (votes from a browser are signed with a connected wallet instead of the bot wallet)
+    let button = if msg.chat.is_private() {
+        teloxide::types::InlineKeyboardButton::web_app(
+            vocab.apply("🗳️ Open voting kiosk"),
+            teloxide::types::WebAppInfo { url: kiosk_url },
+        )
+    } else {
+        teloxide::types::InlineKeyboardButton::url(vocab.apply("🗳️ Open voting kiosk"), kiosk_url)
+    };
+
+    bot.send_localized(
+        &vocab,
+        msg.chat.id,
+        "🗳️ Browse this group's proposals and vote from the kiosk:",
+    )
+    .reply_markup(teloxide::types::InlineKeyboardMarkup::new(vec![vec![
+        button,
+    ]]))
+    .await?;
+
+    Ok(())
+}
+
+async fn handle_settings(
+    bot: Bot,
+    msg: Message,
+    args: String,
+    state: BotState,
+) -> ResponseResult<()> {
+    // Settings talk about the default terms ("/settings term group guild"), so they are
+    // the one place shown without the chat's custom vocabulary (but in its language)
+    let vocab = localization::Vocabulary {
+        language: state.settings.get(msg.chat.id.0).await.language,
+        ..localization::Vocabulary::default()
+    };
+    let parts: Vec<&str> = args.split_whitespace().collect();
+
+    if parts.is_empty() {
+        let current = state.settings.get(msg.chat.id.0).await;
+        let response = format!(
+            "⚙️ <b>Group settings</b>\n\n\
+            🕵️ Vote privacy: <b>{}</b>\n\
+            🌐 Announcement language: <b>{}</b>\n\
+            🔤 Vocabulary: <b>{}</b>\n\n\
+            Change with <code>/settings privacy public|private</code>, \
+            <code>/settings language &lt;code&gt;|off</code>, \
+            <code>/settings term proposal|group|vote|member &lt;word&gt; [plural]|reset</code> \
+            or <code>/settings emoji default|plain|reset|&lt;emoji&gt; &lt;replacement&gt;</code>",
+            current.vote_privacy.as_str(),
+            current
+                .announcement_language
+                .as_deref()
+                .unwrap_or("original"),
+            html_escape(&current.vocabulary.describe())
+        );
+        bot.send_localized(&vocab, msg.chat.id, response)
+            .parse_mode(teloxide::types::ParseMode::Html)
+            .await?;
+        return Ok(());
+    }
+
+    match is_chat_admin(&bot, &msg).await {
+        Ok(true) => {}
+        Ok(false) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                "Only group admins can change settings.",
+            )
+            .await?;
+            return Ok(());
+        }
+        Err(e) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                format!("Error checking admin status: {}", e),
+            )
+            .await?;
+            return Ok(());
+        }
+    }
+
+    let chat_id = msg.chat.id.0;
+    let outcome = match (parts[0], parts.get(1).copied()) {
+        ("privacy", Some(value)) => match settings::VotePrivacy::parse(value) {
+            Some(privacy) => Some(
+                state
+                    .settings
+                    .update(chat_id, |s| s.vote_privacy = privacy)
+                    .await
+                    .map(|_| match privacy {
+                        settings::VotePrivacy::Public => {
+                            "✅ Vote privacy set to public: results will list individual voters."
+                                .to_string()
+                        }
+                        settings::VotePrivacy::Private => {
+                            "✅ Vote privacy set to private: results will only show totals.\n\
+                            Note that votes remain publicly visible on-chain."
+                                .to_string()
+                        }
+                    }),
+            ),
+            None => None,
+        },
+        ("language", Some(value)) => {
+            let language = (value != "off").then(|| value.to_lowercase());
+            let response = match &language {
+                Some(language) if state.translator.is_some() => {
+                    format!("✅ Proposal announcements will be translated to '{}'.", language)
+                }
+                Some(language) => format!(
+                    "✅ Announcement language set to '{}', but translation is not configured on this bot.",
+                    language
+                ),
+                None => "✅ Proposal announcements will no longer be translated.".to_string(),
+            };
+            Some(
+                state
+                    .settings
+                    .update(chat_id, |s| s.announcement_language = language)
+                    .await
+                    .map(|_| response),
+            )
+        }
+        ("term", Some(term)) => {
+            let term = term.to_lowercase();
+            let word = parts.get(2).copied();
+            let plural = parts.get(3).copied();
+            let mut vocabulary = state.settings.get(chat_id).await.vocabulary;
+            let response = match word {
+                Some("reset") => {
+                    vocabulary.terms.remove(&term);
+                    Some(format!("✅ '{}' is back to its default wording.", term))
+                }
+                Some(word) if vocabulary.set_term(&term, word, plural) => Some(format!(
+                    "✅ '{}' will now be called '{}'.",
+                    term,
+                    word.to_lowercase()
+                )),
+                _ => None,
+            };
+            match response {
+                Some(response) => Some(
+                    state
+                        .settings
+                        .update(chat_id, |s| s.vocabulary = vocabulary)
+                        .await
+                        .map(|_| response),
+                ),
+                None => None,
+            }
+        }
+        ("emoji", Some(value)) => {
+            let mut vocabulary = state.settings.get(chat_id).await.vocabulary;
+            let response = match (localization::EmojiSet::parse(value), parts.get(2)) {
+                (Some(set), None) => {
+                    vocabulary.emoji_set = set;
+                    Some(format!("✅ Emoji set to '{}'.", set.as_str()))
+                }
+                _ if value == "reset" => {
+                    vocabulary.emoji_set = localization::EmojiSet::Default;
+                    vocabulary.emoji.clear();
+                    Some("✅ Emoji are back to the defaults.".to_string())
+                }
+                (None, Some(replacement))
+                    if localization::BOT_EMOJI.contains(&value)
+                        && replacement.chars().count() <= 8 =>
+                {
+                    vocabulary
+                        .emoji
+                        .insert(value.to_string(), replacement.to_string());
+                    Some(format!(
+                        "✅ {} will now be shown as {}.",
+                        value, replacement
+                    ))
+                }
+                _ => None,
+            };
+            match response {
+                Some(response) => Some(
+                    state
+                        .settings
+                        .update(chat_id, |s| s.vocabulary = vocabulary)
+                        .await
+                        .map(|_| response),
+                ),
+                None => None,
+            }
+        }
+        _ => None,
+    };
+
+    match outcome {
+        Some(Ok(response)) => {
+            bot.send_localized(&vocab, msg.chat.id, response).await?;
+        }
+        Some(Err(e)) => {
+            log::error!("Failed to save settings for chat {}: {}", msg.chat.id, e);
+            bot.send_localized(&vocab, msg.chat.id, "❌ Failed to save settings.")
+                .await?;
+        }
+        None => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                "❌ Usage: /settings privacy public|private\n\
+                /settings language <code>|off\n\
+                /settings term proposal|group|vote|member <word> [plural]|reset\n\
+                /settings emoji default|plain|reset|<emoji> <replacement>",
+            )
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+// The chat's custom vocabulary and emoji, applied to everything the bot sends there
+async fn chat_vocabulary(state: &BotState, chat_id: ChatId) -> localization::Vocabulary {
+    let settings = state.settings.get(chat_id.0).await;
+    localization::Vocabulary {
+        language: settings.language,
+        ..settings.vocabulary
+    }
+}
+
+trait SendLocalized {
+    fn send_localized(
+        &self,
+        vocabulary: &localization::Vocabulary,
+        chat_id: ChatId,
+        text: impl Into<String>,
+    ) -> <Bot as Requester>::SendMessage;
+}
+
+impl SendLocalized for Bot {
+    fn send_localized(
+        &self,
+        vocabulary: &localization::Vocabulary,
+        chat_id: ChatId,
+        text: impl Into<String>,
+    ) -> <Bot as Requester>::SendMessage {
+        self.send_message(chat_id, vocabulary.apply(&text.into()))
+    }
+}
+
+async fn handle_translate(
+    bot: Bot,
+    msg: Message,
+    args: String,
+    state: BotState,
+) -> ResponseResult<()> {
+    let vocab = chat_vocabulary(&state, msg.chat.id).await;
+    let user_id = match msg.from() {
+        Some(user) => user.id.0 as i64,
+        None => {
+            bot.send_localized(&vocab, msg.chat.id, "❌ Unable to identify user.")
+                .await?;
+            return Ok(());
+        }
+    };
+
+    if state.translator.is_none() {
+        bot.send_localized(
+            &vocab,
+            msg.chat.id,
+            "❌ Translation is not configured on this bot.",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let value = args.trim().to_lowercase();
+    if value.is_empty() {
+        let current = state.user_settings.get(user_id).await.translate_to;
+        let response = format!(
+            "🌐 Translation: {}\n\n\
+            Use /translate <language code> (e.g. es, de), /translate auto to follow your \
+            Telegram language, or /translate off.",
+            current.as_deref().unwrap_or("off")
+        );
+        bot.send_localized(&vocab, msg.chat.id, response).await?;
+        return Ok(());
+    }
+
+    let translate_to = (value != "off").then_some(value);
+    let response = match &translate_to {
+        Some(language) => format!("✅ Proposals will be shown translated to '{}'.", language),
+        None => "✅ Proposals will be shown in their original language.".to_string(),
+    };
+    match state
+        .user_settings
+        .update(user_id, |s| s.translate_to = translate_to)
+        .await
+    {
+        Ok(_) => {
+            bot.send_localized(&vocab, msg.chat.id, response).await?;
+        }
+        Err(e) => {
+            log::error!("Failed to save settings for user {}: {}", user_id, e);
+            bot.send_localized(&vocab, msg.chat.id, "❌ Failed to save settings.")
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn handle_language(
+    bot: Bot,
+    msg: Message,
+    args: String,
+    state: BotState,
+) -> ResponseResult<()> {
+    let vocab = chat_vocabulary(&state, msg.chat.id).await;
+    let value = args.trim();
+    if value.is_empty() {
+        let response = format!(
+            "🗣️ Bot language: <b>{}</b>\n\n\
+            Change with <code>/language en|es|ru</code>",
+            vocab.language.name()
+        );
+        bot.send_localized(&vocab, msg.chat.id, response)
+            .parse_mode(teloxide::types::ParseMode::Html)
+            .await?;
+        return Ok(());
+    }
+
+    match is_chat_admin(&bot, &msg).await {
+        Ok(true) => {}
+        Ok(false) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                "Only group admins can change the bot's language.",
+            )
+            .await?;
+            return Ok(());
+        }
+        Err(e) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                format!("Error checking admin status: {}", e),
+            )
+            .await?;
+            return Ok(());
+        }
+    }
+
+    let Some(language) = i18n::Language::parse(value) else {
+        bot.send_localized(
+            &vocab,
+            msg.chat.id,
+            format!("❌ Unknown language '{}'. Choose en, es or ru.", value),
+        )
+        .await?;
+        return Ok(());
+    };
+    match state
+        .settings
+        .update(msg.chat.id.0, |s| s.language = language)
+        .await
+    {
+        Ok(_) => {
+            // Confirmed in the new language
+            let vocab = chat_vocabulary(&state, msg.chat.id).await;
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                format!("✅ Bot language set to {}.", language.name()),
+            )
+            .await?;
+        }
+        Err(e) => {
+            log::error!("Failed to save settings for chat {}: {}", msg.chat.id, e);
+            bot.send_localized(&vocab, msg.chat.id, "❌ Failed to save settings.")
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+// Language the requesting user wants proposals translated into, if any
+async fn reader_language(state: &BotState, msg: &Message) -> Option<String> {
+    reader_language_of(state, msg.from()?).await
+}
+
+async fn reader_language_of(state: &BotState, user: &teloxide::types::User) -> Option<String> {
+    state.translator.as_ref()?;
+    match state
+        .user_settings
+        .get(user.id.0 as i64)
+        .await
+        .translate_to?
+        .as_str()
+    {
+        "auto" => user.language_code.clone(),
+        language => Some(language.to_string()),
+    }
+}
+
+async fn handle_fund_account(bot: Bot, msg: Message, state: BotState) -> ResponseResult<()> {
+    let vocab = chat_vocabulary(&state, msg.chat.id).await;
+    let user_id = match msg.from() {
+        Some(user) => user.id,
+        None => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                "❌ Unable to identify user. Please try again.",
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+    let telegram_id = user_id.0 as i64;
+
+    // Ensure user has an account
+    let user_keypair = match ensure_user_account(&state, telegram_id).await {
+        Ok(keypair) => keypair,
+        Err(e) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                format!(
+                    "❌ Failed to access your account: {}. Please try /login first.",
+                    e
+                ),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+
+    // Check current balance
     let program = match state.solana_client.program(solana_dao::ID) {
         Ok(program) => program,
         Err(e) => {
             log::error!("Failed to get program: {}", e);
-            bot.send_message(
+            bot.send_localized(
+                &vocab,
                 msg.chat.id,
                 "❌ Failed to access Solana program. Please try again later.",
             )
@@ -402,7 +1706,8 @@ async fn handle_fund_account(bot: Bot, msg: Message, state: BotState) -> Respons
         Ok(balance) => balance,
         Err(e) => {
             log::error!("Failed to get balance: {}", e);
-            bot.send_message(
+            bot.send_localized(
+                &vocab,
                 msg.chat.id,
                 "❌ Failed to check account balance. Please try again later.",
             )
@@ -414,7 +1719,8 @@ async fn handle_fund_account(bot: Bot, msg: Message, state: BotState) -> Respons
 
     if balance > 10_000_000 {
         // More than 0.01 SOL
-        bot.send_message(
+        bot.send_localized(
+            &vocab,
             msg.chat.id,
             format!(
                 "✅ Your account already has sufficient SOL balance!\n\n\
@@ -438,7 +1744,8 @@ async fn handle_fund_account(bot: Bot, msg: Message, state: BotState) -> Respons
         Ok(blockhash) => blockhash,
         Err(e) => {
             log::error!("Failed to get blockhash: {}", e);
-            bot.send_message(
+            bot.send_localized(
+                &vocab,
                 msg.chat.id,
                 "❌ Failed to get recent blockhash. Please try again later.",
             )
@@ -465,7 +1772,8 @@ async fn handle_fund_account(bot: Bot, msg: Message, state: BotState) -> Respons
                 Ok(balance) => balance,
                 Err(e) => {
                     log::error!("Failed to get new balance: {}", e);
-                    bot.send_message(
+                    bot.send_localized(
+                        &vocab,
                         msg.chat.id,
                         "❌ Failed to check new balance. Please try /balance to verify.",
                     )
@@ -475,21 +1783,24 @@ async fn handle_fund_account(bot: Bot, msg: Message, state: BotState) -> Respons
             };
             let new_balance_sol = new_balance as f64 / 1_000_000_000.0;
 
-            bot.send_message(
+            bot.send_localized(
+                &vocab,
                 msg.chat.id,
                 format!(
                     "✅ <b>Account funded successfully!</b>\n\n\
                     💰 New balance: {:.6} SOL\n\
-                    🔗 Transaction: https://explorer.solana.com/tx/{}?cluster=localnet\n\n\
+                    🔗 Transaction: {}\n\n\
                     💡 You can now vote on proposals!",
-                    new_balance_sol, signature
+                    new_balance_sol,
+                    html_escape(&state.network.tx_url(signature))
                 ),
             )
             .parse_mode(teloxide::types::ParseMode::Html)
             .await?;
         }
         Err(e) => {
-            bot.send_message(
+            bot.send_localized(
+                &vocab,
                 msg.chat.id,
                 format!(
                     "❌ Failed to fund account: {}\n\n\
@@ -509,8 +1820,10 @@ async fn handle_create_group(
     msg: Message,
     name: String,
     description: String,
+    metadata_uri: Option<String>,
     state: BotState,
 ) -> ResponseResult<()> {
+    let vocab = chat_vocabulary(&state, msg.chat.id).await;
     log::info!(
         "handle_create_group called with name: '{}', description: '{}'",
         name,
@@ -520,14 +1833,22 @@ async fn handle_create_group(
     match is_chat_admin(&bot, &msg).await {
         Ok(is_admin) => {
             if !is_admin {
-                bot.send_message(msg.chat.id, "Only group admins can create DAO groups.")
-                    .await?;
+                bot.send_localized(
+                    &vocab,
+                    msg.chat.id,
+                    "Only group admins can create DAO groups.",
+                )
+                .await?;
                 return Ok(());
             }
         }
         Err(e) => {
-            bot.send_message(msg.chat.id, format!("Error checking admin status: {}", e))
-                .await?;
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                format!("Error checking admin status: {}", e),
+            )
+            .await?;
             return Ok(());
         }
     }
@@ -543,17 +1864,32 @@ async fn handle_create_group(
     }
 
     // Try to create the group on Solana
-    match create_solana_group(&state, &group_id, &name, &description).await {
+    match create_solana_group(
+        &state,
+        &group_id,
+        &name,
+        &description,
+        metadata_uri.as_deref(),
+        msg.chat.id.0,
+    )
+    .await
+    {
         Ok(signature) => {
-            let response = format!(
+            let mut response = format!(
                 "✅ DAO Group created successfully!\n\n\
                 📋 Name: {}\n\
                 📝 Description: {}\n\
                 🆔 Group name: {}\n\
-                🔗 Transaction: https://explorer.solana.com/tx/{}?cluster=localnet",
-                name, description, group_name, signature
+                🔗 Transaction: {}",
+                name,
+                description,
+                group_name,
+                state.network.tx_url(signature)
             );
-            bot.send_message(msg.chat.id, response).await?;
+            if let Some(uri) = &metadata_uri {
+                response.push_str(&format!("\n📎 Details: {}", uri));
+            }
+            bot.send_localized(&vocab, msg.chat.id, response).await?;
         }
         Err(e) => {
             log::error!("Failed to create DAO group '{}': {}", name, e);
@@ -563,10 +1899,18 @@ async fn handle_create_group(
                 || error_str.contains("Allocate: account")
             {
                 "❌ A DAO group with this ID already exists in this chat."
+            } else if error_str.contains("RegistrationClosed") {
+                "❌ Group registration is closed on this DAO registry."
+            } else if error_str.contains("RegistryFull") {
+                "❌ The DAO registry has reached its group limit."
+            } else if error_str.contains("InvalidMetadataUri") {
+                "❌ Invalid metadata URI. Use an ipfs://, ar:// or https:// link of at most 200 characters."
+            } else if error_str.contains("ProgramPaused") {
+                "⏸️ The DAO program is paused for maintenance. Please try again later."
             } else {
                 "❌ Failed to create DAO group. Please try again later or contact support."
             };
-            bot.send_message(msg.chat.id, user_msg).await?;
+            bot.send_localized(&vocab, msg.chat.id, user_msg).await?;
         }
     }
 
@@ -574,22 +1918,32 @@ async fn handle_create_group(
 }
 
 async fn handle_list_groups(bot: Bot, msg: Message, state: BotState) -> ResponseResult<()> {
+    let vocab = chat_vocabulary(&state, msg.chat.id).await;
     match get_all_groups(&state).await {
         Ok(groups) => {
+            // Private groups are only reachable from their own chat
+            let groups: Vec<_> = groups
+                .into_iter()
+                .filter(|group| group.visibility == solana_dao::GroupVisibility::Public)
+                .collect();
             if groups.is_empty() {
-                bot.send_message(msg.chat.id, "No DAO groups found.")
+                bot.send_localized(&vocab, msg.chat.id, "No DAO groups found.")
                     .await?;
             } else {
                 let mut response = "📋 <b>DAO Groups:</b>\n\n".to_string();
                 for (i, group) in groups.iter().enumerate() {
                     response.push_str(&format!(
-                        "{}. <b>{}</b>\n   📝 {}\n\n",
+                        "{}. <b>{}</b>\n   📝 {}\n",
                         i + 1,
                         group.name,
                         group.description
                     ));
+                    if let Some(uri) = &group.metadata_uri {
+                        response.push_str(&format!("   📎 {}\n", html_escape(uri)));
+                    }
+                    response.push('\n');
                 }
-                bot.send_message(msg.chat.id, response)
+                bot.send_localized(&vocab, msg.chat.id, response)
                     .parse_mode(teloxide::types::ParseMode::Html)
                     .await?;
             }
@@ -602,12 +1956,13 @@ async fn handle_list_groups(bot: Bot, msg: Message, state: BotState) -> Response
             } else {
                 "❌ Failed to fetch groups. Please try again later."
             };
-            bot.send_message(msg.chat.id, user_msg).await?;
+            bot.send_localized(&vocab, msg.chat.id, user_msg).await?;
         }
     }
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn handle_create_proposal(
     bot: Bot,
     msg: Message,
@@ -615,20 +1970,30 @@ async fn handle_create_proposal(
     description: String,
     choices: String,
     duration_hours: u32,
+    metadata_uri: Option<String>,
     state: BotState,
 ) -> ResponseResult<()> {
+    let vocab = chat_vocabulary(&state, msg.chat.id).await;
     // Only allow group admins to create proposals
     match is_chat_admin(&bot, &msg).await {
         Ok(is_admin) => {
             if !is_admin {
-                bot.send_message(msg.chat.id, "Only group admins can create proposals.")
-                    .await?;
+                bot.send_localized(
+                    &vocab,
+                    msg.chat.id,
+                    "Only group admins can create proposals.",
+                )
+                .await?;
                 return Ok(());
             }
         }
         Err(e) => {
-            bot.send_message(msg.chat.id, format!("Error checking admin status: {}", e))
-                .await?;
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                format!("Error checking admin status: {}", e),
+            )
+            .await?;
             return Ok(());
         }
     }
@@ -640,7 +2005,8 @@ async fn handle_create_proposal(
         .collect();
 
     if choices_vec.len() < 2 || choices_vec.len() > 10 {
-        bot.send_message(
+        bot.send_localized(
+            &vocab,
             msg.chat.id,
             "Please provide between 2 and 10 choices, separated by commas.",
         )
@@ -648,6 +2014,16 @@ async fn handle_create_proposal(
         return Ok(());
     }
 
+    if choices_vec.iter().any(|choice| choice.len() > 50) {
+        bot.send_localized(
+            &vocab,
+            msg.chat.id,
+            "Each choice must be at most 50 characters long.",
+        )
+        .await?;
+        return Ok(());
+    }
+
     let proposal_id = Uuid::new_v4().to_string();
     log::info!(
         "Generated proposal_id: {} (length: {})",
@@ -669,10 +2045,22 @@ async fn handle_create_proposal(
         choices_vec.clone(),
         voting_start,
         voting_end,
+        metadata_uri.as_deref(),
     )
     .await
     {
-        Ok(signature) => {
+        Ok((signature, sponsors_needed)) => {
+            let next_step = if sponsors_needed > 0 {
+                format!(
+                    "🤝 Voting opens once {} members co-sponsor it with <code>/sponsor {}</code>",
+                    sponsors_needed, proposal_id
+                )
+            } else {
+                format!(
+                    "Tap a choice below to vote, or use <code>/vote {} &lt;choice_number&gt;</code>.",
+                    proposal_id
+                )
+            };
             let choices_text = choices_vec
                 .iter()
                 .enumerate()
@@ -683,1030 +2071,7995 @@ async fn handle_create_proposal(
             let response = format!(
                 "✅ <b>Proposal created successfully!</b>\n\n\
                 📋 <b>{}</b>\n\
-                📝 {}\n\
+                📝 {}\n{}\
                 🆔 <b>Proposal ID:</b> <code>{}</code>\n\
                 ⏰ <b>Voting ends:</b> {}\n\n\
                 <b>Choices:</b>\n{}\n\n\
-                🔗 <a href=\"https://explorer.solana.com/tx/{}?cluster=localnet\">View Transaction</a>\n\n\
-                Use <code>/vote {} &lt;choice_number&gt;</code> to vote!",
+                🔗 <a href=\"{}\">View Transaction</a>\n\n\
+                {}",
                 title,
                 description,
+                metadata_line(metadata_uri.as_deref()),
                 proposal_id,
                 DateTime::<Utc>::from_timestamp(voting_end, 0)
                     .map(|dt| dt.format("%Y-%m-%d %H:%M UTC").to_string())
                     .unwrap_or_else(|| "Unknown time".to_string()),
                 choices_text,
-                signature,
-                proposal_id
+                html_escape(&state.network.tx_url(signature)),
+                next_step
             );
-            bot.send_message(msg.chat.id, response)
-                .parse_mode(teloxide::types::ParseMode::Html)
-                .await?;
+            let mut announcement = bot
+                .send_localized(&vocab, msg.chat.id, response)
+                .parse_mode(teloxide::types::ParseMode::Html);
+            if let Some(keyboard) = buttons::vote_keyboard(&proposal_id, &choices_vec, None) {
+                announcement = announcement.reply_markup(keyboard);
+            }
+            let announcement = announcement.await?;
+
+            // Replies to the announcement form the proposal's discussion thread
+            if let Err(e) = state
+                .discussions
+                .update(msg.chat.id.0, |log| {
+                    log.announcements
+                        .insert(announcement.id.0, proposal_id.clone());
+                })
+                .await
+            {
+                log::error!("Failed to record proposal announcement: {}", e);
+            }
+
+            // Follow up with a translated copy for chats that asked for one
+            let announcement_language = state
+                .settings
+                .get(msg.chat.id.0)
+                .await
+                .announcement_language;
+            if let (Some(translator), Some(language)) =
+                (state.translator.as_ref(), announcement_language)
+            {
+                let title = translator.translate(&title, &language).await;
+                let description = translator.translate(&description, &language).await;
+                let mut choices_text = String::new();
+                for (i, choice) in choices_vec.iter().enumerate() {
+                    let choice = translator.translate(choice, &language).await;
+                    choices_text.push_str(&format!("{}. {}\n", i, html_escape(&choice)));
+                }
+                let translated = format!(
+                    "🌐 <b>[{}]</b>\n\n📋 <b>{}</b>\n📝 {}\n\n<b>Choices:</b>\n{}",
+                    html_escape(&language),
+                    html_escape(&title),
+                    html_escape(&description),
+                    choices_text
+                );
+                bot.send_localized(&vocab, msg.chat.id, translated)
+                    .parse_mode(teloxide::types::ParseMode::Html)
+                    .await?;
+            }
         }
         Err(e) => {
-            let error_msg = format!("❌ Failed to create proposal: {}", e);
-            bot.send_message(msg.chat.id, error_msg).await?;
-        }
-    }
-
-    Ok(())
-}
-
-async fn handle_list_proposals(bot: Bot, msg: Message, state: BotState) -> ResponseResult<()> {
-    let group_id = format!("tg_{}", msg.chat.id.0.abs());
-    match get_group_proposals(&state, &group_id).await {
-        Ok(proposals) => {
-            if proposals.is_empty() {
-                bot.send_message(msg.chat.id, "No proposals found for this group.")
-                    .await?;
+            let error_msg = if e.to_string().contains("ProgramPaused") {
+                "⏸️ Proposal creation is temporarily paused while the DAO program is under maintenance."
+                    .to_string()
+            } else if e.to_string().contains("InvalidMetadataUri") {
+                "❌ Invalid metadata URI. Use an ipfs://, ar:// or https:// link of at most 200 characters."
+                    .to_string()
+            } else if e.to_string().contains("VotingDurationOutOfBounds") {
+                "❌ That voting duration is outside what this group's governance rules allow."
+                    .to_string()
+            } else if e.to_string().contains("ProposalCooldownActive") {
+                "⏳ This group's proposal cooldown hasn't passed since the last proposal yet."
+                    .to_string()
+            } else if e.to_string().contains("TooManyActiveProposals") {
+                "❌ This group already has as many proposals open as it allows. Wait for one to end."
+                    .to_string()
             } else {
-                let mut response = "📋 <b>Proposals:</b>\n\n".to_string();
-                for (i, proposal) in proposals.iter().enumerate() {
-                    let status = if Utc::now().timestamp() > proposal.voting_end {
-                        "🔒 Ended"
-                    } else if Utc::now().timestamp() < proposal.voting_start {
-                        "⏳ Not started"
-                    } else {
-                        "🗳️ Active"
-                    };
+                format!("❌ Failed to create proposal: {}", e)
+            };
+            bot.send_localized(&vocab, msg.chat.id, error_msg).await?;
+        }
+    }
+
+    Ok(())
+}
 
-                    // Format choices for display
-                    let choices_text = proposal
-                        .choices
-                        .iter()
-                        .enumerate()
-                        .map(|(idx, choice)| format!("{}. {}", idx, choice))
-                        .collect::<Vec<_>>()
-                        .join("\n      ");
+// Proposals per /listproposals page; buttons under the list turn the pages
+const PROPOSALS_PER_PAGE: usize = 5;
 
-                    response.push_str(&format!(
-                        "{}. <b>{}</b> {}\n   📝 {}\n   🗳️ <b>Choices:</b>\n      {}\n   🆔 <b>ID:</b> <code>{}</code>\n   ⏰ <b>Ends:</b> {}\n\n",
-                        i + 1,
-                        proposal.title,
-                        status,
-                        proposal.description,
-                        choices_text,
-                        proposal.proposal_id,
-                        DateTime::<Utc>::from_timestamp(proposal.voting_end, 0)
-                            .map(|dt| dt.format("%Y-%m-%d %H:%M UTC").to_string())
-                            .unwrap_or_else(|| "Unknown time".to_string())
-                    ));
-                }
-                bot.send_message(msg.chat.id, response)
-                    .parse_mode(teloxide::types::ParseMode::Html)
-                    .await?;
+async fn handle_list_proposals(bot: Bot, msg: Message, state: BotState) -> ResponseResult<()> {
+    let vocab = chat_vocabulary(&state, msg.chat.id).await;
+    let group_id = format!("tg_{}", msg.chat.id.0.abs());
+    let language = reader_language(&state, &msg).await;
+    match proposal_list_page(&state, &group_id, language.as_deref(), 0).await {
+        Ok(None) => {
+            bot.send_localized(&vocab, msg.chat.id, "No proposals found for this group.")
+                .await?;
+        }
+        Ok(Some((response, pages))) => {
+            let mut request = bot
+                .send_localized(&vocab, msg.chat.id, response)
+                .parse_mode(teloxide::types::ParseMode::Html);
+            if let Some(keyboard) =
+                callbacks::page_keyboard(&vocab, callbacks::PagedList::Proposals, 0, pages)
+            {
+                request = request.reply_markup(keyboard);
             }
+            request.await?;
         }
         Err(e) => {
             let error_msg = format!("❌ Failed to fetch proposals: {}", e);
-            bot.send_message(msg.chat.id, error_msg).await?;
+            bot.send_localized(&vocab, msg.chat.id, error_msg).await?;
         }
     }
     Ok(())
 }
 
-async fn handle_vote(
+// One page of the group's proposal list and the number of pages; None without proposals.
+// Pages past the end show the last one.
+async fn proposal_list_page(
+    state: &BotState,
+    group_id: &str,
+    language: Option<&str>,
+    page: usize,
+) -> anyhow::Result<Option<(String, usize)>> {
+    let proposals = get_group_proposals(state, group_id).await?;
+    if proposals.is_empty() {
+        return Ok(None);
+    }
+    let pages = proposals.len().div_ceil(PROPOSALS_PER_PAGE);
+    let page = page.min(pages - 1);
+
+    let mut response = if pages > 1 {
+        format!("📋 <b>Proposals</b> (page {} of {}):\n\n", page + 1, pages)
+    } else {
+        "📋 <b>Proposals:</b>\n\n".to_string()
+    };
+    for (i, proposal) in proposals
+        .iter()
+        .enumerate()
+        .skip(page * PROPOSALS_PER_PAGE)
+        .take(PROPOSALS_PER_PAGE)
+    {
+        let text =
+            translation::display_proposal(state.translator.as_deref(), proposal, language).await;
+        let status = if proposal.status == solana_dao::ProposalStatus::Draft {
+            "📝 Draft"
+        } else if proposal.status == solana_dao::ProposalStatus::Cancelled {
+            "❌ Cancelled"
+        } else if Utc::now().timestamp() > proposal.voting_end {
+            "🔒 Ended"
+        } else if Utc::now().timestamp() < proposal.voting_start {
+            "⏳ Not started"
+        } else {
+            "🗳️ Active"
+        };
+
+        // Format choices for display
+        let choices_text = text
+            .choices
+            .iter()
+            .enumerate()
+            .map(|(idx, choice)| format!("{}. {}", idx, choice))
+            .collect::<Vec<_>>()
+            .join("\n      ");
+
+        response.push_str(&format!(
+            "{}. <b>{}</b> {}\n   📝 {}\n{}   🗳️ <b>Choices:</b>\n      {}\n   🆔 <b>ID:</b> <code>{}</code>\n   ⏰ <b>Ends:</b> {}\n\n",
+            i + 1,
+            text.title,
+            status,
+            text.description,
+            proposal
+                .metadata_uri
+                .as_deref()
+                .map(|uri| format!("   📎 {}\n", html_escape(uri)))
+                .unwrap_or_default(),
+            choices_text,
+            proposal.proposal_id,
+            DateTime::<Utc>::from_timestamp(proposal.voting_end, 0)
+                .map(|dt| dt.format("%Y-%m-%d %H:%M UTC").to_string())
+                .unwrap_or_else(|| "Unknown time".to_string())
+        ));
+    }
+    Ok(Some((response, pages)))
+}
+
+async fn handle_archive(
     bot: Bot,
     msg: Message,
     proposal_id: String,
-    choice: u8,
     state: BotState,
 ) -> ResponseResult<()> {
-    let user_id = match msg.from() {
-        Some(user) => user.id,
-        None => {
-            bot.send_message(msg.chat.id, "❌ Unable to identify user. Please try again.")
-                .await?;
+    let vocab = chat_vocabulary(&state, msg.chat.id).await;
+    match is_chat_admin(&bot, &msg).await {
+        Ok(true) => {}
+        Ok(false) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                "Only group admins can archive discussions.",
+            )
+            .await?;
             return Ok(());
         }
-    };
-    let telegram_id = user_id.0 as i64;
-    let group_id = format!("tg_{}", msg.chat.id.0.abs());
+        Err(e) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                format!("Error checking admin status: {}", e),
+            )
+            .await?;
+            return Ok(());
+        }
+    }
 
-    // Ensure user has an account
-    let user_keypair = match ensure_user_account(&state, telegram_id).await {
-        Ok(keypair) => keypair,
+    let chat_id = msg.chat.id.0;
+    let group_id = format!("tg_{}", chat_id.abs());
+    let proposal = match get_proposal_results(&state, &group_id, &proposal_id).await {
+        Ok(proposal) => proposal,
         Err(e) => {
-            bot.send_message(
+            bot.send_localized(
+                &vocab,
                 msg.chat.id,
-                format!(
-                    "❌ Failed to access your account: {}. Please try /login first.",
-                    e
-                ),
+                format!("❌ Failed to fetch proposal: {}", e),
             )
             .await?;
             return Ok(());
         }
     };
 
-    match vote_on_proposal(
-        &state,
+    let archive_url = state.webapp_url.as_ref().map(|base| {
+        format!(
+            "{}/api/chats/{}/proposals/{}/archive.json",
+            base, chat_id, proposal_id
+        )
+    });
+    let log = state.discussions.get(chat_id).await;
+    // Re-archiving keeps the link to the content the proposal pointed at originally
+    let previous_metadata_uri = match log.archives.get(&proposal_id) {
+        Some(earlier) if proposal.metadata_uri == archive_url => {
+            earlier.previous_metadata_uri.clone()
+        }
+        _ => proposal.metadata_uri.clone(),
+    };
+    let archive = log.archive(
+        chat_id,
         &group_id,
         &proposal_id,
-        choice,
-        user_keypair.pubkey(),
-    )
-    .await
+        previous_metadata_uri,
+        Utc::now().timestamp(),
+    );
+    let message_count = archive.messages.len();
+    let digest = archive.digest.clone();
+    if let Err(e) = state
+        .discussions
+        .update(chat_id, |log| {
+            log.archives.insert(proposal_id.clone(), archive);
+        })
+        .await
     {
-        Ok(signature) => {
-            let response = format!(
-                "✅ Vote cast successfully!\n\n\
-                🗳️ Proposal: {}\n\
-                ✔️ Your choice: {}\n\
-                👤 Wallet: {}\n\
-                🔗 Transaction: https://explorer.solana.com/tx/{}?cluster=localnet",
-                proposal_id,
-                choice,
-                user_keypair.pubkey(),
-                signature
-            );
-            bot.send_message(msg.chat.id, response).await?;
+        bot.send_localized(
+            &vocab,
+            msg.chat.id,
+            format!("❌ Failed to save archive: {}", e),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let mut response = format!(
+        "📎 <b>Discussion archived</b>\n\n\
+        🗳️ Proposal: <code>{}</code>\n\
+        💬 Messages: {}\n\
+        🔑 Digest: <code>{}</code>\n",
+        html_escape(&proposal_id),
+        message_count,
+        digest
+    );
+    match archive_url {
+        Some(url) if proposal.metadata_uri.as_deref() == Some(url.as_str()) => {
+            response.push_str(&format!("🔗 {}\n", html_escape(&url)));
         }
-        Err(e) => {
-            let error_str = e.to_string();
-            let user_msg = if error_str.contains("AlreadyVoted") || error_str.contains("0x177a") {
-                "❌ You have already voted on this proposal!\n\n\
-                🗳️ Each user can only vote once per proposal.\n\
-                💡 Use /results to see the current results."
-                    .to_string()
-            } else if error_str.contains("VotingNotActive") {
-                "❌ Voting is not currently active for this proposal.\n\n\
-                ⏰ The voting period may have ended or not started yet.\n\
-                💡 Use /results to check the proposal status."
-                    .to_string()
-            } else if error_str.contains("InvalidChoice") {
-                "❌ Invalid choice selected!\n\n\
-                🗳️ Please select a valid choice number for this proposal.\n\
-                💡 Use /listproposals to see available choices."
-                    .to_string()
-            } else if error_str.contains("You don't have enough SOL balance") {
-                "❌ Insufficient SOL balance!\n\n\
-                💰 You need at least 0.001 SOL for transaction fees.\n\
-                💡 Use /fundaccount to add SOL to your account."
-                    .to_string()
-            } else {
-                format!("❌ Failed to vote: {}", e)
-            };
-            bot.send_message(msg.chat.id, user_msg).await?;
+        Some(url) => {
+            let instruction = build_set_metadata_uri_instruction(
+                &group_id,
+                &proposal_id,
+                Some(&url),
+                state.payer.pubkey(),
+            );
+            match send_instructions(
+                &state,
+                &[instruction],
+                &state.payer.pubkey(),
+                &[&state.payer],
+            )
+            .await
+            {
+                Ok(_) => response.push_str(&format!(
+                    "🔗 Linked from the proposal's metadata: {}\n",
+                    html_escape(&url)
+                )),
+                Err(e) => response.push_str(&format!(
+                    "⚠️ Saved at {}, but linking it from the proposal failed: {}\n",
+                    html_escape(&url),
+                    html_escape(&e.to_string())
+                )),
+            }
         }
+        None => response
+            .push_str("⚠️ Set WEBAPP_URL to publish archives and link them from proposals.\n"),
     }
 
+    bot.send_localized(&vocab, msg.chat.id, response)
+        .parse_mode(teloxide::types::ParseMode::Html)
+        .await?;
     Ok(())
 }
 
-async fn handle_results(
+// Members listed per /eligible message; the full list is in the web endpoint
+const ELIGIBLE_LIST_LIMIT: usize = 30;
+
+// In chats with private vote privacy the file goes to the admin's private chat, so
+// per-voter records aren't posted to the whole group
+async fn handle_export(
     bot: Bot,
     msg: Message,
-    proposal_id: String,
+    args: String,
     state: BotState,
 ) -> ResponseResult<()> {
-    let group_id = format!("tg_{}", msg.chat.id.0.abs());
-    match get_proposal_results(&state, &group_id, &proposal_id).await {
-        Ok(proposal) => {
-            let total_votes: u64 = proposal.choice_votes.iter().sum();
-
-            let mut response = format!(
-                "📊 <b>Results for: {}</b>\n\n\
-                📝 {}\n\
-                🗳️ Total votes: {}\n\
-                👥 Total voters: {}\n\n\
-                <b>Results:</b>\n",
-                html_escape(&proposal.title),
-                html_escape(&proposal.description),
-                total_votes,
-                proposal.voters.len()
-            );
-
-            for (i, (choice, votes)) in proposal
-                .choices
-                .iter()
-                .zip(proposal.choice_votes.iter())
-                .enumerate()
-            {
-                let percentage = if total_votes > 0 {
-                    (*votes as f64 / total_votes as f64) * 100.0
-                } else {
-                    0.0
-                };
-                response.push_str(&format!(
-                    "{}. {} - {} votes ({:.1}%)\n",
-                    i,
-                    html_escape(choice),
-                    votes,
-                    percentage
-                ));
-            }
-
-            let status = if Utc::now().timestamp() > proposal.voting_end {
-                "🔒 Voting has ended"
-            } else {
-                "🗳️ Voting is still active"
-            };
-            response.push_str(&format!("\n{}", status));
-
-            bot.send_message(msg.chat.id, response)
-                .parse_mode(teloxide::types::ParseMode::Html)
+    let vocab = chat_vocabulary(&state, msg.chat.id).await;
+    match is_chat_admin(&bot, &msg).await {
+        Ok(true) => {}
+        Ok(false) => {
+            bot.send_localized(&vocab, msg.chat.id, "Only group admins can export results.")
                 .await?;
+            return Ok(());
         }
         Err(e) => {
-            let error_msg = format!("❌ Failed to get results: {}", e);
-            bot.send_message(msg.chat.id, error_msg).await?;
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                format!("Error checking admin status: {}", e),
+            )
+            .await?;
+            return Ok(());
         }
     }
-    Ok(())
-}
-
-// Helper function to escape HTML special characters
-fn html_escape(text: &str) -> String {
-    text.chars()
-        .map(|c| match c {
-            '&' => "&amp;".to_string(),
-            '<' => "&lt;".to_string(),
-            '>' => "&gt;".to_string(),
-            '"' => "&quot;".to_string(),
-            '\'' => "&#x27;".to_string(),
-            _ => c.to_string(),
-        })
-        .collect()
-}
 
-async fn handle_login(bot: Bot, msg: Message, state: BotState) -> ResponseResult<()> {
-    let user_id = match msg.from() {
-        Some(user) => user.id,
-        None => {
-            bot.send_message(msg.chat.id, "❌ Unable to identify user. Please try again.")
+    let mut parts = args.split_whitespace();
+    let (proposal_id, format) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(proposal_id), format, None) => match export::Format::parse(format.unwrap_or("csv")) {
+            Some(format) => (proposal_id.to_string(), format),
+            None => {
+                bot.send_localized(
+                    &vocab,
+                    msg.chat.id,
+                    "❌ Usage: /export <proposal_id> [csv|json]",
+                )
                 .await?;
+                return Ok(());
+            }
+        },
+        _ => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                "❌ Usage: /export <proposal_id> [csv|json]",
+            )
+            .await?;
             return Ok(());
         }
     };
-    let telegram_id = user_id.0 as i64;
-    let user = msg.from();
 
-    let username = user.and_then(|u| u.username.as_ref());
-
-    match create_user_account(&state, telegram_id).await {
-        Ok(keypair) => {
-            let response = format!(
-                "✅ Account ready!\n\n\
-                👤 Telegram username: {}\n\
-                🔑 Wallet Address: {}\n\n\
-                You can now participate in DAO voting!",
-                username
-                    .map(|s| s.to_string())
-                    .unwrap_or_else(|| "anonymous".to_string()),
-                keypair.pubkey()
-            );
-            bot.send_message(msg.chat.id, response).await?;
+    let group_id = format!("tg_{}", msg.chat.id.0.abs());
+    let proposal = match get_proposal_results(&state, &group_id, &proposal_id).await {
+        Ok(proposal) => proposal,
+        Err(e) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                format!("❌ Failed to get results: {}", e),
+            )
+            .await?;
+            return Ok(());
         }
+    };
+    // Private votes leave out who voted for what in every chat; the tallies still add up
+    let private =
+        state.settings.get(msg.chat.id.0).await.vote_privacy == settings::VotePrivacy::Private;
+    let export = export::build(&proposal, Utc::now().timestamp(), !private);
+    let file = match export::render(&export, format) {
+        Ok(file) => file,
         Err(e) => {
-            let error_msg = format!("❌ Failed to create/access account: {}", e);
-            bot.send_message(msg.chat.id, error_msg).await?;
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                format!("❌ Failed to build the export: {}", e),
+            )
+            .await?;
+            return Ok(());
         }
-    }
+    };
+    let document = teloxide::types::InputFile::memory(file).file_name(format!(
+        "results-{}.{}",
+        proposal_id,
+        format.extension()
+    ));
+    let caption = if private {
+        vocab.apply(&format!(
+            "📑 Results of {}: {} choices ({}). Votes in this chat are private, so the export has no individual ballots.",
+            proposal_id,
+            export.choices.len(),
+            export.status
+        ))
+    } else {
+        vocab.apply(&format!(
+            "📑 Results of {}: {} choices, {} votes ({})",
+            proposal_id,
+            export.choices.len(),
+            export.votes.len(),
+            export.status
+        ))
+    };
 
+    bot.send_document(msg.chat.id, document)
+        .caption(caption)
+        .await?;
     Ok(())
 }
 
-async fn handle_account(bot: Bot, msg: Message, state: BotState) -> ResponseResult<()> {
-    let user_id = match msg.from() {
-        Some(user) => user.id,
-        None => {
-            bot.send_message(msg.chat.id, "❌ Unable to identify user. Please try again.")
-                .await?;
+async fn handle_eligible(
+    bot: Bot,
+    msg: Message,
+    proposal_id: String,
+    state: BotState,
+) -> ResponseResult<()> {
+    let vocab = chat_vocabulary(&state, msg.chat.id).await;
+    match is_chat_admin(&bot, &msg).await {
+        Ok(true) => {}
+        Ok(false) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                "Only group admins can preview voter eligibility.",
+            )
+            .await?;
             return Ok(());
         }
-    };
-    let telegram_id = user_id.0 as i64;
+        Err(e) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                format!("Error checking admin status: {}", e),
+            )
+            .await?;
+            return Ok(());
+        }
+    }
 
-    let user = msg.from();
-    let username = user.and_then(|u| u.username.as_ref());
+    let chat_id = msg.chat.id.0;
+    let group_id = format!("tg_{}", chat_id.abs());
+    let preview = match eligibility::preview(&state, &group_id, &proposal_id).await {
+        Ok(preview) => preview,
+        Err(e) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                format!("❌ Failed to build eligibility preview: {}", e),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
 
-    // Check if user has an account
-    let user_seeds = state.user_seeds.lock().await;
-    let seed_opt = user_seeds.get(&user_id).copied();
-    drop(user_seeds);
+    let weighting = match preview.mode {
+        "sol" => "SOL balance (lamports)".to_string(),
+        "weighted" => "each member's set weight".to_string(),
+        "spl" => format!(
+            "token balance of <code>{}</code>",
+            preview.token_mint.as_deref().unwrap_or_default()
+        ),
+        _ => "one vote per member".to_string(),
+    };
+    let mut response = format!(
+        "🧮 <b>Voting eligibility</b>\n\n\
+        🗳️ Proposal: <code>{}</code>\n\
+        ⚖️ Weight: {}\n\
+        ✅ Eligible members: {} (total weight {})\n\
+        🚫 Without voting power: {}\n",
+        html_escape(&proposal_id),
+        weighting,
+        preview.eligible.len(),
+        preview.total_weight,
+        preview.ineligible.len()
+    );
+    if !preview.eligible.is_empty() {
+        response.push('\n');
+        for voter in preview.eligible.iter().take(ELIGIBLE_LIST_LIMIT) {
+            response.push_str(&format!(
+                "• <code>{}</code> — {}\n",
+                voter.wallet, voter.weight
+            ));
+        }
+        if preview.eligible.len() > ELIGIBLE_LIST_LIMIT {
+            response.push_str(&format!(
+                "…and {} more\n",
+                preview.eligible.len() - ELIGIBLE_LIST_LIMIT
+            ));
+        }
+    }
+    if let Some(base) = &state.webapp_url {
+        response.push_str(&format!(
+            "\n🔗 Full list: {}/api/chats/{}/proposals/{}/eligibility.json\n",
+            base,
+            chat_id,
+            html_escape(&proposal_id)
+        ));
+    }
+    response.push_str("\nWeights are current balances; linked wallets are not included.");
 
-    match seed_opt {
-        Some(seed) => {
-            let keypair = Keypair::new_from_array(seed);
-            let wallet_address = keypair.pubkey();
+    bot.send_localized(&vocab, msg.chat.id, response)
+        .parse_mode(teloxide::types::ParseMode::Html)
+        .await?;
+    Ok(())
+}
 
-            // Try to get account info from Solana
-            let (user_account_pda, _) = Pubkey::find_program_address(
-                &[b"user_account", telegram_id.to_le_bytes().as_ref()],
-                &solana_dao::ID,
-            );
-
-            match state
-                .program
-                .account::<solana_dao::UserAccount>(user_account_pda)
-                .await
-            {
-                Ok(user_account) => {
-                    let created_date = if user_account.created_at == 0 {
-                        "Just created".to_string()
-                    } else {
-                        match chrono::DateTime::<chrono::Utc>::from_timestamp(
-                            user_account.created_at,
-                            0,
-                        ) {
-                            Some(dt) => dt.format("%Y-%m-%d %H:%M UTC").to_string(),
-                            None => "Recently created".to_string(),
-                        }
-                    };
+// Admin-only off-chain voting, with the bot's payer as aggregator: `enable` moves a
+// proposal's ballots off-chain, `submit` posts the tally of the ballots collected by the
+// web service once voting ends, and `settle` applies it after the challenge window
+async fn handle_offchain(
+    bot: Bot,
+    msg: Message,
+    args: String,
+    state: BotState,
+) -> ResponseResult<()> {
+    let vocab = chat_vocabulary(&state, msg.chat.id).await;
+    match is_chat_admin(&bot, &msg).await {
+        Ok(true) => {}
+        Ok(false) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                "Only group admins can manage off-chain voting.",
+            )
+            .await?;
+            return Ok(());
+        }
+        Err(e) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                format!("Error checking admin status: {}", e),
+            )
+            .await?;
+            return Ok(());
+        }
+    }
 
-                    let response = format!(
-                        "👤 <b>Your Account Information</b>\n\n\
-                        👤 Username: <code>{}</code>\n\
-                        🔑 Wallet Address: <code>{}</code>\n\
-                        📅 Created: {}\n\
-                        🔗 View on Explorer: https://explorer.solana.com/address/{}?cluster=localnet\n\n\
-                        ✅ Account is active and ready for DAO participation!",
-                        username.map(|s| s.to_string()).unwrap_or_else(|| "anonymous".to_string()),
-                        wallet_address,
-                        created_date,
-                        wallet_address
-                    );
+    let mut parts = args.split_whitespace();
+    let (action, proposal_id) = match (parts.next(), parts.next()) {
+        (Some(action @ ("enable" | "submit" | "settle")), Some(proposal_id)) => {
+            (action, proposal_id.to_string())
+        }
+        _ => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                "❌ Usage: /offchain enable|submit|settle <proposal_id>",
+            )
+            .await?;
+            return Ok(());
+        }
+    };
 
-                    bot.send_message(msg.chat.id, response)
-                        .parse_mode(teloxide::types::ParseMode::Html)
-                        .await?;
+    let chat_id = msg.chat.id.0;
+    let group_id = format!("tg_{}", chat_id.abs());
+    let aggregator = state.payer.pubkey();
+    let mut submitted_root = None;
+    let (instruction, success) = match action {
+        "enable" => (
+            build_enable_offchain_voting_instruction(
+                &group_id,
+                &proposal_id,
+                aggregator,
+                aggregator,
+            ),
+            format!(
+                "🗳️ Votes on <code>{}</code> are now collected off-chain. Once voting ends, \
+                run /offchain submit {} to post the tally.",
+                html_escape(&proposal_id),
+                html_escape(&proposal_id)
+            ),
+        ),
+        "submit" => {
+            let proposal = match get_proposal_results(&state, &group_id, &proposal_id).await {
+                Ok(proposal) => proposal,
+                Err(e) => {
+                    bot.send_localized(
+                        &vocab,
+                        msg.chat.id,
+                        format!("❌ Failed to fetch proposal: {}", e),
+                    )
+                    .await?;
+                    return Ok(());
                 }
-                Err(_) => {
-                    let response = format!(
-                        "⚠️ <b>Account Found Locally</b>\n\n\
-                        👤 Username: <code>{}</code>\n\
-                        🔑 Wallet Address: <code>{}</code>\n\
-                        🔗 View on Explorer: https://explorer.solana.com/address/{}?cluster=localnet\n\n\
-                        ❌ Account not yet created on-chain. Use /login to create it.",
-                        username.map(|s| s.to_string()).unwrap_or_else(|| "anonymous".to_string()),
-                        wallet_address,
-                        wallet_address
-                    );
+            };
+            let ballots = state
+                .ballots
+                .get(chat_id)
+                .await
+                .proposals
+                .remove(&proposal_id)
+                .unwrap_or_default();
+            let leaves: Vec<ballots::Subtree> = ballots
+                .leaves(proposal.choices.len())
+                .into_iter()
+                .map(|(_, leaf)| leaf)
+                .collect();
+            let root = ballots::merkle_root(&leaves);
+            let (choice_votes, voter_count) = ballots.tally(proposal.choices.len());
+            submitted_root = Some(hex::encode(root));
+            (
+                build_submit_offchain_tally_instruction(
+                    &group_id,
+                    &proposal_id,
+                    root,
+                    &choice_votes,
+                    voter_count,
+                    aggregator,
+                ),
+                format!(
+                    "📮 <b>Off-chain tally submitted</b>\n\n\
+                    🗳️ Proposal: <code>{}</code>\n\
+                    👥 Ballots counted: {}\n\
+                    🌳 Merkle root: <code>{}</code>\n\n\
+                    It can be disputed for {} hours; then run /offchain settle {}.",
+                    html_escape(&proposal_id),
+                    voter_count,
+                    hex::encode(root),
+                    solana_dao::OFFCHAIN_CHALLENGE_SECS / 3600,
+                    html_escape(&proposal_id)
+                ),
+            )
+        }
+        _ => (
+            build_settle_offchain_tally_instruction(&group_id, &proposal_id),
+            format!(
+                "✅ Off-chain tally for <code>{}</code> settled; the proposal can now be finalized.",
+                html_escape(&proposal_id)
+            ),
+        ),
+    };
 
-                    bot.send_message(msg.chat.id, response)
-                        .parse_mode(teloxide::types::ParseMode::Html)
-                        .await?;
+    let response =
+        match send_instructions(&state, &[instruction], &aggregator, &[&state.payer]).await {
+            Ok(_) => {
+                if let Some(root) = submitted_root {
+                    if let Err(e) = state
+                        .ballots
+                        .update(chat_id, |ballot_box| {
+                            ballot_box
+                                .proposals
+                                .entry(proposal_id.clone())
+                                .or_default()
+                                .submitted_root = Some(root);
+                        })
+                        .await
+                    {
+                        log::warn!("Failed to record submitted tally root: {}", e);
+                    }
                 }
+                success
             }
+            Err(e) => format!(
+                "❌ Off-chain {} failed: {}",
+                action,
+                html_escape(&e.to_string())
+            ),
+        };
+    bot.send_localized(&vocab, msg.chat.id, response)
+        .parse_mode(teloxide::types::ParseMode::Html)
+        .await?;
+    Ok(())
+}
+
+// Invites last a week and admit 50 members unless the admin says otherwise
+const DEFAULT_INVITE_USES: u32 = 50;
+const DEFAULT_INVITE_DAYS: i64 = 7;
+
+async fn handle_invite(
+    bot: Bot,
+    msg: Message,
+    args: String,
+    state: BotState,
+) -> ResponseResult<()> {
+    let vocab = chat_vocabulary(&state, msg.chat.id).await;
+    match is_chat_admin(&bot, &msg).await {
+        Ok(true) => {}
+        Ok(false) => {
+            bot.send_localized(&vocab, msg.chat.id, "Only group admins can create invites.")
+                .await?;
+            return Ok(());
         }
-        None => {
-            // User doesn't have an account yet
-            bot.send_message(
+        Err(e) => {
+            bot.send_localized(
+                &vocab,
                 msg.chat.id,
-                "❌ You don't have an account yet. Use /login to create one.",
+                format!("Error checking admin status: {}", e),
             )
-            .parse_mode(teloxide::types::ParseMode::Html)
             .await?;
+            return Ok(());
         }
     }
 
-    Ok(())
-}
-
-async fn handle_balance(bot: Bot, msg: Message, state: BotState) -> ResponseResult<()> {
-    let user_id = match msg.from() {
-        Some(user) => user.id,
-        None => {
-            bot.send_message(msg.chat.id, "❌ Unable to identify user. Please try again.")
-                .await?;
+    let mut parts = args.split_whitespace();
+    let max_uses = parts.next().map(str::parse::<u32>);
+    let days = parts.next().map(str::parse::<i64>);
+    let (max_uses, days) = match (max_uses, days) {
+        (Some(Ok(uses)), Some(Ok(days))) if uses > 0 && days > 0 => (uses, days),
+        (Some(Ok(uses)), None) if uses > 0 => (uses, DEFAULT_INVITE_DAYS),
+        (None, None) => (DEFAULT_INVITE_USES, DEFAULT_INVITE_DAYS),
+        _ => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                "❌ Usage: /invite [max_uses] [valid_days]",
+            )
+            .await?;
             return Ok(());
         }
     };
 
-    let user = msg.from();
-    let username = user.and_then(|u| u.username.clone());
-
-    // Check if user has a seed (account exists)
-    if let Some(seed) = state.user_seeds.lock().await.get(&user_id) {
-        // Generate the same keypair from the seed
-        let keypair = Keypair::new_from_array(*seed);
-        let wallet_address = keypair.pubkey();
-
-        // Get the balance from Solana
-        match state.program.rpc().get_balance(&wallet_address).await {
-            Ok(balance_lamports) => {
-                let balance_sol = balance_lamports as f64 / LAMPORTS_PER_SOL as f64;
-
-                let response = format!(
-                    "💰 <b>Your SOL Balance</b>\n\n\
-                    👤 Username: <code>{}</code>\n\
-                    🔑 Wallet Address: <code>{}</code>\n\
-                    💎 Balance: <b>{:.6} SOL</b>\n\
-                    🔗 View on Explorer: https://explorer.solana.com/address/{}?cluster=localnet",
-                    username.unwrap_or_else(|| "anonymous".to_string()),
-                    wallet_address,
-                    balance_sol,
-                    wallet_address
-                );
+    let group_id = format!("tg_{}", msg.chat.id.0.abs());
+    let code = uuid::Uuid::new_v4().simple().to_string()[..12].to_string();
+    let expires_at = Utc::now().timestamp() + days * 24 * 60 * 60;
+    let instruction = build_create_invite_instruction(
+        &group_id,
+        &code,
+        max_uses,
+        expires_at,
+        state.payer.pubkey(),
+    );
 
-                bot.send_message(msg.chat.id, response)
-                    .parse_mode(teloxide::types::ParseMode::Html)
-                    .await?;
-            }
-            Err(e) => {
-                log::error!("Failed to get balance: {:?}", e);
-                bot.send_message(
-                    msg.chat.id,
-                    "❌ Failed to get balance. Please try again later.",
-                )
-                .parse_mode(teloxide::types::ParseMode::Html)
-                .await?;
-            }
-        }
-    } else {
-        // User doesn't have an account yet
-        bot.send_message(
-            msg.chat.id,
-            "❌ You don't have an account yet. Use /login to create one.",
-        )
+    let response = match send_instructions(
+        &state,
+        &[instruction],
+        &state.payer.pubkey(),
+        &[&state.payer],
+    )
+    .await
+    {
+        Ok(_) => format!(
+            "🎟️ <b>Invite created</b>\n\n\
+            Send <code>/join {}</code> in this chat to become a member.\n\
+            👥 Uses: {}\n\
+            ⏳ Valid for {} days",
+            code, max_uses, days
+        ),
+        Err(e) => format!(
+            "❌ Failed to create invite: {}",
+            html_escape(&e.to_string())
+        ),
+    };
+    bot.send_localized(&vocab, msg.chat.id, response)
         .parse_mode(teloxide::types::ParseMode::Html)
         .await?;
-    }
-
     Ok(())
 }
 
-// Helper function to ensure user has an account, creating one if needed
-async fn ensure_user_account(state: &BotState, telegram_id: i64) -> anyhow::Result<Keypair> {
-    // Check if we already have a seed for this user
-    {
-        let user_seeds = state.user_seeds.lock().await;
-        if let Some(seed) = user_seeds.get(&UserId(telegram_id as u64)) {
-            return Ok(Keypair::new_from_array(*seed));
-        }
+async fn handle_join(bot: Bot, msg: Message, code: String, state: BotState) -> ResponseResult<()> {
+    let vocab = chat_vocabulary(&state, msg.chat.id).await;
+    let code = code.trim().to_string();
+    if code.is_empty() {
+        bot.send_localized(&vocab, msg.chat.id, "❌ Usage: /join <invite_code>")
+            .await?;
+        return Ok(());
     }
+    let Some(user) = msg.from() else {
+        bot.send_localized(
+            &vocab,
+            msg.chat.id,
+            "❌ Unable to identify user. Please try again.",
+        )
+        .await?;
+        return Ok(());
+    };
+    let group_id = format!("tg_{}", msg.chat.id.0.abs());
 
-    // Try to get existing account from Solana
-    let (user_account_pda, _) = Pubkey::find_program_address(
-        &[b"user_account", telegram_id.to_le_bytes().as_ref()],
-        &solana_dao::ID,
-    );
+    let user_keypair = match ensure_user_account(&state, user.id.0 as i64).await {
+        Ok(keypair) => keypair,
+        Err(e) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                format!(
+                    "❌ Failed to access your account: {}. Please try /login first.",
+                    e
+                ),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
 
-    match state
-        .program
-        .account::<solana_dao::UserAccount>(user_account_pda)
-        .await
-    {
-        Ok(_user_account) => {
-            // Account exists, we need to generate/retrieve the keypair
-            // In a production system, you'd want to securely store and retrieve the private key
-            // For this demo, we'll generate a deterministic keypair based on telegram_id
-            let seed = generate_seed_from_telegram_id(telegram_id);
-            let keypair = Keypair::new_from_array(seed);
+    let instruction = build_join_with_invite_instruction(&group_id, &code, user_keypair.pubkey());
+    let result = send_instructions(
+        &state,
+        &[instruction],
+        &user_keypair.pubkey(),
+        &[&user_keypair],
+    )
+    .await;
 
-            // Store the seed for future use
-            {
-                let mut user_seeds = state.user_seeds.lock().await;
-                user_seeds.insert(UserId(telegram_id as u64), seed);
+    let response = match result {
+        Ok(_) => "🎉 Welcome! You are now a member of this group's DAO.".to_string(),
+        Err(e) => {
+            let error_str = e.to_string();
+            if error_str.contains("MemberAlreadyExists") {
+                "ℹ️ You are already a member of this group.".to_string()
+            } else if error_str.contains("GroupFull") {
+                "❌ This group has reached its member cap.".to_string()
+            } else if error_str.contains("InviteExpired") {
+                "❌ This invite has expired. Ask an admin for a new one.".to_string()
+            } else if error_str.contains("InviteExhausted") {
+                "❌ This invite has been used up. Ask an admin for a new one.".to_string()
+            } else if error_str.contains("AccountNotInitialized") {
+                "❌ Unknown invite code.".to_string()
+            } else {
+                format!("❌ Failed to join: {}", e)
             }
+        }
+    };
+    bot.send_localized(&vocab, msg.chat.id, response).await?;
+    Ok(())
+}
 
-            Ok(keypair)
+// 0 lifts the cap; private groups are left out of /listgroups
+async fn handle_membership(
+    bot: Bot,
+    msg: Message,
+    args: String,
+    state: BotState,
+) -> ResponseResult<()> {
+    let vocab = chat_vocabulary(&state, msg.chat.id).await;
+    match is_chat_admin(&bot, &msg).await {
+        Ok(true) => {}
+        Ok(false) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                "Only group admins can change the membership policy.",
+            )
+            .await?;
+            return Ok(());
         }
-        Err(_) => {
-            // Account doesn't exist, create it
-            create_user_account(state, telegram_id).await
+        Err(e) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                format!("Error checking admin status: {}", e),
+            )
+            .await?;
+            return Ok(());
         }
     }
-}
-
-// Generate a deterministic seed from telegram ID and secret seed
-// Uses SECRET_SEED environment variable for additional security
-fn generate_seed_from_telegram_id(telegram_id: i64) -> [u8; 32] {
-    use std::collections::hash_map::DefaultHasher;
-    use std::hash::{Hash, Hasher};
-
-    // Get the secret seed from environment variable
-    let secret_seed = std::env::var("SECRET_SEED")
-        .unwrap_or_else(|_| "default_secret_seed_change_this_in_production".to_string());
 
-    // Create a hash of telegram_id + secret_seed for cryptographic security
-    let mut hasher = DefaultHasher::new();
-    telegram_id.hash(&mut hasher);
-    secret_seed.hash(&mut hasher);
-    let hash = hasher.finish();
+    let mut parts = args.split_whitespace();
+    let max_members = parts.next().map(str::parse::<u32>);
+    let visibility = match parts.next() {
+        Some("public") => Some(solana_dao::GroupVisibility::Public),
+        Some("private") => Some(solana_dao::GroupVisibility::Private),
+        _ => None,
+    };
+    let (Some(Ok(max_members)), Some(visibility), None) = (max_members, visibility, parts.next())
+    else {
+        bot.send_localized(
+            &vocab,
+            msg.chat.id,
+            "❌ Usage: /membership <max_members> <public|private>\n\
+            Use 0 for no member cap.",
+        )
+        .await?;
+        return Ok(());
+    };
 
-    // Convert hash to 32-byte seed
-    let mut seed = [0u8; 32];
-    let hash_bytes = hash.to_le_bytes();
-
-    // Use the hash as the base and fill the rest with additional entropy
-    for i in 0..8 {
-        seed[i] = hash_bytes[i];
-    }
-
-    // Add additional entropy by mixing telegram_id and secret_seed
-    let id_bytes = telegram_id.to_le_bytes();
-    for i in 8..16 {
-        seed[i] = id_bytes[i - 8] ^ secret_seed.as_bytes()[(i - 8) % secret_seed.len()];
-    }
-
-    // Fill remaining bytes with deterministic but secure pattern
-    for i in 16..32 {
-        seed[i] = (hash_bytes[i % 8]
-            ^ id_bytes[i % 8]
-            ^ secret_seed.as_bytes()[i % secret_seed.len()]) as u8;
-    }
-
-    log::info!("Generated secure seed for telegram_id: {}", telegram_id);
-    seed
+    let group_id = format!("tg_{}", msg.chat.id.0.abs());
+    let instruction = build_set_membership_policy_instruction(
+        &group_id,
+        max_members,
+        visibility,
+        state.payer.pubkey(),
+    );
+    let response = match send_instructions(
+        &state,
+        &[instruction],
+        &state.payer.pubkey(),
+        &[&state.payer],
+    )
+    .await
+    {
+        Ok(_) => {
+            let cap = if max_members == 0 {
+                "no cap".to_string()
+            } else {
+                format!("at most {} members", max_members)
+            };
+            let visibility = match visibility {
+                solana_dao::GroupVisibility::Public => "public",
+                solana_dao::GroupVisibility::Private => "private",
+            };
+            format!("✅ Membership updated: {}, {} group.", cap, visibility)
+        }
+        Err(e) if e.to_string().contains("InvalidMemberCap") => {
+            "❌ The cap can't be below the current number of members.".to_string()
+        }
+        Err(e) => format!("❌ Failed to update membership: {}", e),
+    };
+    bot.send_localized(&vocab, msg.chat.id, response).await?;
+    Ok(())
 }
 
-// Create a new user account on Solana
-async fn create_user_account(state: &BotState, telegram_id: i64) -> anyhow::Result<Keypair> {
-    let seed = generate_seed_from_telegram_id(telegram_id);
-
-    // Create keypair from seed using the correct method
-    let keypair = Keypair::new_from_array(seed);
+async fn handle_proposal_cap(
+    bot: Bot,
+    msg: Message,
+    args: String,
+    state: BotState,
+) -> ResponseResult<()> {
+    let vocab = chat_vocabulary(&state, msg.chat.id).await;
+    match is_chat_admin(&bot, &msg).await {
+        Ok(true) => {}
+        Ok(false) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                "Only group admins can change the proposal cap.",
+            )
+            .await?;
+            return Ok(());
+        }
+        Err(e) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                format!("Error checking admin status: {}", e),
+            )
+            .await?;
+            return Ok(());
+        }
+    }
 
-    log::info!("Keypair created successfully: {}", keypair.pubkey());
+    let Ok(max_active_proposals) = args.trim().parse::<u16>() else {
+        bot.send_localized(
+            &vocab,
+            msg.chat.id,
+            "❌ Usage: /proposalcap <max_active_proposals>\n\
+            Use 0 for no cap.",
+        )
+        .await?;
+        return Ok(());
+    };
 
-    // Get the user account PDA
-    let (user_account_pda, _) = Pubkey::find_program_address(
-        &[b"user_account", telegram_id.to_le_bytes().as_ref()],
-        &solana_dao::ID,
+    let group_id = format!("tg_{}", msg.chat.id.0.abs());
+    let instruction = build_set_max_active_proposals_instruction(
+        &group_id,
+        max_active_proposals,
+        state.payer.pubkey(),
     );
+    let response = match send_instructions(
+        &state,
+        &[instruction],
+        &state.payer.pubkey(),
+        &[&state.payer],
+    )
+    .await
+    {
+        Ok(_) if max_active_proposals == 0 => {
+            "✅ Any number of proposals can now be open at once.".to_string()
+        }
+        Ok(_) => format!(
+            "✅ At most {} proposals can now be open for voting at once.",
+            max_active_proposals
+        ),
+        Err(e) => format!("❌ Failed to update the proposal cap: {}", e),
+    };
+    bot.send_localized(&vocab, msg.chat.id, response).await?;
+    Ok(())
+}
 
-    log::info!("Creating user account for telegram_id: {}", telegram_id);
-    log::info!("User wallet pubkey: {}", keypair.pubkey());
-    log::info!("User account PDA: {}", user_account_pda);
-    log::info!("Payer pubkey: {}", state.payer.pubkey());
-
-    // Check if account already exists
-    let program = state.solana_client.program(solana_dao::ID)?;
-    let rpc_client = program.rpc();
-
-    match rpc_client.get_account(&user_account_pda).await {
-        Ok(_account) => {
-            log::info!("User account already exists, returning existing keypair");
-            // Store the seed for future use if not already stored
-            {
-                let mut user_seeds = state.user_seeds.lock().await;
-                user_seeds.insert(UserId(telegram_id as u64), seed);
-            }
-            return Ok(keypair);
+async fn handle_cooldown(
+    bot: Bot,
+    msg: Message,
+    args: String,
+    state: BotState,
+) -> ResponseResult<()> {
+    let vocab = chat_vocabulary(&state, msg.chat.id).await;
+    match is_chat_admin(&bot, &msg).await {
+        Ok(true) => {}
+        Ok(false) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                "Only group admins can change the proposal cooldown.",
+            )
+            .await?;
+            return Ok(());
         }
-        Err(_) => {
-            log::info!("User account does not exist, creating new one");
+        Err(e) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                format!("Error checking admin status: {}", e),
+            )
+            .await?;
+            return Ok(());
         }
     }
 
-    // Build the transaction manually but with proper error handling
-    log::info!("Building transaction manually...");
-
-    // Build instruction data for create_user_account using correct discriminator
-    let mut instruction_data = vec![146, 68, 100, 69, 63, 46, 182, 199]; // create_user_account discriminator from IDL
-    instruction_data.extend_from_slice(&telegram_id.to_le_bytes());
-
-    log::info!("Instruction data: {:?}", instruction_data);
-    log::info!("Telegram ID bytes: {:?}", telegram_id.to_le_bytes());
+    let Ok(hours) = args.trim().parse::<u32>() else {
+        bot.send_localized(
+            &vocab,
+            msg.chat.id,
+            "❌ Usage: /cooldown <hours>\n\
+            Use 0 to let creators propose back to back.",
+        )
+        .await?;
+        return Ok(());
+    };
 
-    let accounts = vec![
-        anchor_client::solana_sdk::instruction::AccountMeta::new(user_account_pda, false),
-        anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
-            keypair.pubkey(),
-            false, // user_wallet is not a signer according to IDL
-        ),
-        anchor_client::solana_sdk::instruction::AccountMeta::new(state.payer.pubkey(), true),
-        anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
-            system_program::ID,
-            false,
+    let group_id = format!("tg_{}", msg.chat.id.0.abs());
+    let instruction = build_set_proposal_cooldown_instruction(
+        &group_id,
+        i64::from(hours) * 60 * 60,
+        state.payer.pubkey(),
+    );
+    let response = match send_instructions(
+        &state,
+        &[instruction],
+        &state.payer.pubkey(),
+        &[&state.payer],
+    )
+    .await
+    {
+        Ok(_) if hours == 0 => "✅ Proposal cooldown turned off.".to_string(),
+        Ok(_) => format!(
+            "✅ Each creator now waits {} hours between proposals. Emergencies are exempt.",
+            hours
         ),
-    ];
+        Err(e) => format!("❌ Failed to update the proposal cooldown: {}", e),
+    };
+    bot.send_localized(&vocab, msg.chat.id, response).await?;
+    Ok(())
+}
 
-    log::info!("Instruction accounts:");
-    for (i, account) in accounts.iter().enumerate() {
-        log::info!(
-            "  {}: {} (writable: {}, signer: {})",
-            i,
-            account.pubkey,
-            account.is_writable,
-            account.is_signer
-        );
+// Members back drafts with their own wallet; admins set how many sponsors a draft needs
+async fn handle_sponsor(
+    bot: Bot,
+    msg: Message,
+    args: String,
+    state: BotState,
+) -> ResponseResult<()> {
+    let vocab = chat_vocabulary(&state, msg.chat.id).await;
+    let group_id = format!("tg_{}", msg.chat.id.0.abs());
+    let parts: Vec<&str> = args.split_whitespace().collect();
+
+    if let ["require", members] = parts.as_slice() {
+        match is_chat_admin(&bot, &msg).await {
+            Ok(true) => {}
+            Ok(false) => {
+                bot.send_localized(
+                    &vocab,
+                    msg.chat.id,
+                    "Only group admins can change how many co-sponsors a draft needs.",
+                )
+                .await?;
+                return Ok(());
+            }
+            Err(e) => {
+                bot.send_localized(
+                    &vocab,
+                    msg.chat.id,
+                    format!("Error checking admin status: {}", e),
+                )
+                .await?;
+                return Ok(());
+            }
+        }
+        let Ok(threshold) = members.parse::<u8>() else {
+            bot.send_localized(&vocab, msg.chat.id, "❌ Usage: /sponsor require <members>")
+                .await?;
+            return Ok(());
+        };
+        let instruction =
+            build_set_sponsor_threshold_instruction(&group_id, threshold, state.payer.pubkey());
+        let response = match send_instructions(
+            &state,
+            &[instruction],
+            &state.payer.pubkey(),
+            &[&state.payer],
+        )
+        .await
+        {
+            Ok(_) if threshold == 0 => "✅ Proposals open without co-sponsors again.".to_string(),
+            Ok(_) => format!(
+                "✅ New proposals start as drafts and open once {} members co-sponsor them. \
+                Emergencies are exempt.",
+                threshold
+            ),
+            Err(e) if e.to_string().contains("InvalidSponsorThreshold") => {
+                "❌ A draft can need at most 32 co-sponsors.".to_string()
+            }
+            Err(e) => format!("❌ Failed to update the co-sponsor requirement: {}", e),
+        };
+        bot.send_localized(&vocab, msg.chat.id, response).await?;
+        return Ok(());
     }
 
-    let instruction = anchor_client::solana_sdk::instruction::Instruction {
-        program_id: solana_dao::ID,
-        accounts,
-        data: instruction_data,
+    let [proposal_id] = parts.as_slice() else {
+        bot.send_localized(
+            &vocab,
+            msg.chat.id,
+            "❌ Usage: /sponsor <proposal_id>\n\
+            Admins: /sponsor require <members> (0 to turn it off)",
+        )
+        .await?;
+        return Ok(());
+    };
+    let Some(user) = msg.from() else {
+        bot.send_localized(
+            &vocab,
+            msg.chat.id,
+            "❌ Unable to identify user. Please try again.",
+        )
+        .await?;
+        return Ok(());
+    };
+    let user_keypair = match ensure_user_account(&state, user.id.0 as i64).await {
+        Ok(keypair) => keypair,
+        Err(e) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                format!(
+                    "❌ Failed to access your account: {}. Please try /login first.",
+                    e
+                ),
+            )
+            .await?;
+            return Ok(());
+        }
     };
 
-    // Use the program's RPC client for better compatibility
-    let program = state.solana_client.program(solana_dao::ID)?;
-    let rpc_client = program.rpc();
-
-    log::info!("Getting recent blockhash...");
-    let recent_blockhash = rpc_client.get_latest_blockhash().await?;
-    log::info!("Recent blockhash: {}", recent_blockhash);
-
-    log::info!("Creating transaction...");
-    let mut transaction = anchor_client::solana_sdk::transaction::Transaction::new_with_payer(
+    let instruction = build_co_sponsor_instruction(&group_id, proposal_id, user_keypair.pubkey());
+    let result = send_instructions(
+        &state,
         &[instruction],
-        Some(&state.payer.pubkey()),
-    );
-    transaction.sign(&[&state.payer], recent_blockhash);
-
-    log::info!("Transaction created, attempting to send...");
-    log::info!("Transaction signatures: {:?}", transaction.signatures);
-
-    match rpc_client.send_and_confirm_transaction(&transaction).await {
-        Ok(signature) => {
-            log::info!("Transaction successful: {}", signature);
-        }
+        &user_keypair.pubkey(),
+        &[&user_keypair],
+    )
+    .await;
+    let response = match result {
+        Ok(_) => match get_proposal_results(&state, &group_id, proposal_id).await {
+            Ok(proposal) if proposal.status == solana_dao::ProposalStatus::Active => {
+                format!(
+                    "🎉 That was the last co-sponsor: voting on <code>{}</code> is open!",
+                    html_escape(proposal_id)
+                )
+            }
+            Ok(proposal) => sponsor_line(&proposal),
+            Err(_) => "✅ You co-sponsored the draft.".to_string(),
+        },
         Err(e) => {
-            log::error!("Transaction failed: {}", e);
-            return Err(e.into());
+            let error_str = e.to_string();
+            if error_str.contains("already in use") {
+                "ℹ️ You already co-sponsored this draft.".to_string()
+            } else if error_str.contains("SponsorshipComplete") {
+                "ℹ️ This draft already has all the co-sponsors it needs.".to_string()
+            } else if error_str.contains("InvalidProposalStatus") {
+                "❌ Only drafts can be co-sponsored.".to_string()
+            } else if error_str.contains("AccountNotInitialized") {
+                "❌ Only listed members can co-sponsor. Join with an invite first.".to_string()
+            } else {
+                format!("❌ Failed to co-sponsor: {}", e)
+            }
         }
-    }
+    };
+    bot.send_localized(&vocab, msg.chat.id, response)
+        .parse_mode(teloxide::types::ParseMode::Html)
+        .await?;
+    Ok(())
+}
 
-    // Store the seed for future use
-    {
-        let mut user_seeds = state.user_seeds.lock().await;
-        user_seeds.insert(UserId(telegram_id as u64), seed);
+// How far a draft is from the co-sponsors it needs, empty when it needs none
+fn sponsor_line(proposal: &solana_dao::Proposal) -> String {
+    if proposal.sponsors_needed == 0 || proposal.status != solana_dao::ProposalStatus::Draft {
+        return String::new();
     }
-
-    Ok(keypair)
+    format!(
+        "🤝 Co-sponsors: {} of {}; voting opens when the last one signs with <code>/sponsor {}</code>\n",
+        proposal.sponsor_count,
+        proposal.sponsors_needed,
+        html_escape(&proposal.proposal_id)
+    )
 }
 
-// Initialize the DAO registry
-async fn initialize_dao_registry(
-    client: &Client<Arc<Keypair>>,
-    program: &Program<Arc<Keypair>>,
-    payer: &Arc<Keypair>,
-) -> anyhow::Result<String> {
-    // Get the DAO registry PDA
-    let (dao_registry_pda, _) = Pubkey::find_program_address(&[b"dao_registry"], &solana_dao::ID);
-
-    println!("Init - Program ID: {}", solana_dao::ID);
-    println!("Init - DAO Registry PDA: {}", dao_registry_pda);
-
-    // Check if already initialized
-    if let Ok(_) = program
-        .account::<solana_dao::DaoRegistry>(dao_registry_pda)
-        .await
-    {
-        return Ok("already_initialized".to_string());
+async fn handle_tie_break(
+    bot: Bot,
+    msg: Message,
+    args: String,
+    state: BotState,
+) -> ResponseResult<()> {
+    let vocab = chat_vocabulary(&state, msg.chat.id).await;
+    match is_chat_admin(&bot, &msg).await {
+        Ok(true) => {}
+        Ok(false) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                "Only group admins can change the tie-break rule.",
+            )
+            .await?;
+            return Ok(());
+        }
+        Err(e) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                format!("Error checking admin status: {}", e),
+            )
+            .await?;
+            return Ok(());
+        }
     }
 
-    // Build initialize instruction using correct discriminator
-    let instruction_data = vec![175, 175, 109, 31, 13, 152, 155, 237]; // initialize discriminator from IDL
-
-    let instruction = anchor_client::solana_sdk::instruction::Instruction {
-        program_id: solana_dao::ID,
-        accounts: vec![
-            anchor_client::solana_sdk::instruction::AccountMeta::new(dao_registry_pda, false),
-            anchor_client::solana_sdk::instruction::AccountMeta::new(payer.pubkey(), true),
-            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
-                system_program::ID,
-                false,
-            ),
-        ],
-        data: instruction_data,
+    let Some(tie_break) = parse_tie_break(&args) else {
+        bot.send_localized(
+            &vocab,
+            msg.chat.id,
+            "❌ Usage: /tiebreak <fail|revote <hours>|earliest|council>\n\
+            fail - a tie defeats the proposal\n\
+            revote - reopen voting once for the given hours (1-168)\n\
+            earliest - the tied choice that got its votes first wins\n\
+            council - the group council picks among the tied choices",
+        )
+        .await?;
+        return Ok(());
     };
 
-    let program_id = instruction.program_id.clone();
-
-    let recent_blockhash = client
-        .program(program_id)?
-        .rpc()
-        .get_latest_blockhash()
-        .await?;
-    let transaction = anchor_client::solana_sdk::transaction::Transaction::new_signed_with_payer(
+    let group_id = format!("tg_{}", msg.chat.id.0.abs());
+    let instruction = build_set_tie_break_instruction(&group_id, tie_break, state.payer.pubkey());
+    let response = match send_instructions(
+        &state,
         &[instruction],
-        Some(&payer.pubkey()),
-        &[&**payer],
-        recent_blockhash,
-    );
-    let tx = client
-        .program(program_id)?
-        .rpc()
-        .send_and_confirm_transaction(&transaction)
-        .await?;
+        &state.payer.pubkey(),
+        &[&state.payer],
+    )
+    .await
+    {
+        Ok(_) => format!(
+            "✅ Ties for first place are now settled this way: {}",
+            tie_break_text(tie_break)
+        ),
+        Err(e) => format!("❌ Failed to update the tie-break rule: {}", e),
+    };
+    bot.send_localized(&vocab, msg.chat.id, response).await?;
+    Ok(())
+}
 
-    Ok(tx.to_string())
+// "/tiebreak" arguments; revote windows are given in hours, up to the program's 7 days
+fn parse_tie_break(args: &str) -> Option<solana_dao::TieBreak> {
+    let mut parts = args.split_whitespace();
+    let tie_break = match (parts.next()?, parts.next()) {
+        ("fail", None) => solana_dao::TieBreak::Fail,
+        ("earliest", None) => solana_dao::TieBreak::EarliestLeading,
+        ("council", None) => solana_dao::TieBreak::Council,
+        ("revote", Some(hours)) => {
+            let hours = hours
+                .parse::<i64>()
+                .ok()
+                .filter(|h| (1..=168).contains(h))?;
+            solana_dao::TieBreak::Revote {
+                window_secs: hours * 60 * 60,
+            }
+        }
+        _ => return None,
+    };
+    parts.next().is_none().then_some(tie_break)
 }
 
-// Helper functions for Solana interactions
-async fn create_solana_group(
-    state: &BotState,
-    group_id: &str,
-    name: &str,
-    description: &str,
-) -> anyhow::Result<String> {
-    // Get the DAO registry PDA
-    let (dao_registry_pda, _) = Pubkey::find_program_address(&[b"dao_registry"], &solana_dao::ID);
+fn tie_break_text(tie_break: solana_dao::TieBreak) -> String {
+    match tie_break {
+        solana_dao::TieBreak::Fail => "the proposal is defeated".to_string(),
+        solana_dao::TieBreak::Revote { window_secs } => {
+            format!("voting reopens once for {} hours", window_secs / (60 * 60))
+        }
+        solana_dao::TieBreak::EarliestLeading => {
+            "the tied choice that got its votes first wins".to_string()
+        }
+        solana_dao::TieBreak::Council => "the group council picks the winner".to_string(),
+    }
+}
 
-    // Get the group PDA
-    let (group_pda, _) =
-        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+async fn handle_choice_info(
+    bot: Bot,
+    msg: Message,
+    args: String,
+    state: BotState,
+) -> ResponseResult<()> {
+    let vocab = chat_vocabulary(&state, msg.chat.id).await;
+    match is_chat_admin(&bot, &msg).await {
+        Ok(true) => {}
+        Ok(false) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                "Only group admins can describe choices.",
+            )
+            .await?;
+            return Ok(());
+        }
+        Err(e) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                format!("Error checking admin status: {}", e),
+            )
+            .await?;
+            return Ok(());
+        }
+    }
 
-    // Build instruction data using correct discriminator
-    let mut instruction_data = vec![79, 60, 158, 134, 61, 199, 56, 248]; // create_group discriminator from IDL
-    instruction_data.extend_from_slice(&(group_id.len() as u32).to_le_bytes());
-    instruction_data.extend_from_slice(group_id.as_bytes());
-    instruction_data.extend_from_slice(&(name.len() as u32).to_le_bytes());
-    instruction_data.extend_from_slice(name.as_bytes());
-    instruction_data.extend_from_slice(&(description.len() as u32).to_le_bytes());
-    instruction_data.extend_from_slice(description.as_bytes());
+    let (args, uri) = split_metadata_uri(&args);
+    let mut parts = args.trim().splitn(3, char::is_whitespace);
+    let (Some(proposal_id), Some(Ok(choice))) = (
+        parts.next().filter(|id| !id.is_empty()),
+        parts.next().map(str::parse::<usize>),
+    ) else {
+        bot.send_localized(
+            &vocab,
+            msg.chat.id,
+            "❌ Usage: /choiceinfo <proposal_id> <choice> <description> [uri]\n\
+            Choices are numbered from 0, as in /results. Leave out the description and \
+            link to clear them. Only until voting starts.",
+        )
+        .await?;
+        return Ok(());
+    };
+    let description = parts.next().map(str::trim).unwrap_or_default();
 
-    let instruction = anchor_client::solana_sdk::instruction::Instruction {
-        program_id: solana_dao::ID,
-        accounts: vec![
-            anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
-            anchor_client::solana_sdk::instruction::AccountMeta::new(dao_registry_pda, false),
-            anchor_client::solana_sdk::instruction::AccountMeta::new(state.payer.pubkey(), true),
-            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
-                system_program::ID,
-                false,
-            ),
-        ],
-        data: instruction_data,
+    let group_id = format!("tg_{}", msg.chat.id.0.abs());
+    let proposal = match get_proposal_results(&state, &group_id, proposal_id).await {
+        Ok(proposal) => proposal,
+        Err(e) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                format!("❌ Failed to load the proposal: {}", e),
+            )
+            .await?;
+            return Ok(());
+        }
     };
+    if choice >= proposal.choices.len() {
+        bot.send_localized(
+            &vocab,
+            msg.chat.id,
+            format!(
+                "❌ This proposal has choices 0 to {}.",
+                proposal.choices.len() - 1
+            ),
+        )
+        .await?;
+        return Ok(());
+    }
 
-    let program_id = instruction.program_id.clone();
+    // The program replaces all details at once, so start from the ones already set
+    let mut details = fetch_choice_details(&state, &group_id, proposal_id).await;
+    details.resize(proposal.choices.len(), solana_dao::ChoiceDetail::default());
+    details[choice] = solana_dao::ChoiceDetail {
+        description: (!description.is_empty()).then(|| description.to_string()),
+        uri,
+    };
 
-    let recent_blockhash = state
-        .solana_client
-        .program(program_id)?
-        .rpc()
-        .get_latest_blockhash()
-        .await?;
-    let transaction = anchor_client::solana_sdk::transaction::Transaction::new_signed_with_payer(
+    let instruction = build_set_choice_details_instruction(
+        &group_id,
+        proposal_id,
+        &details,
+        state.payer.pubkey(),
+    );
+    let response = match send_instructions(
+        &state,
         &[instruction],
-        Some(&state.payer.pubkey()),
+        &state.payer.pubkey(),
         &[&state.payer],
-        recent_blockhash,
-    );
-    let tx = state
-        .solana_client
-        .program(program_id)?
-        .rpc()
-        .send_and_confirm_transaction(&transaction)
-        .await?;
-
-    Ok(tx.to_string())
+    )
+    .await
+    {
+        Ok(_) => format!(
+            "✅ Updated the details of choice {} ({}).",
+            choice, proposal.choices[choice]
+        ),
+        Err(e) if e.to_string().contains("InvalidChoiceDetails") => {
+            "❌ Choice descriptions can be at most 140 characters.".to_string()
+        }
+        Err(e) if e.to_string().contains("InvalidMetadataUri") => {
+            "❌ Invalid link. Use an ipfs://, ar:// or https:// link of at most 200 characters."
+                .to_string()
+        }
+        Err(e) if e.to_string().contains("InvalidProposalStatus") => {
+            "❌ Choices can only be described until voting starts.".to_string()
+        }
+        Err(e) => format!("❌ Failed to update the choice details: {}", e),
+    };
+    bot.send_localized(&vocab, msg.chat.id, response).await?;
+    Ok(())
 }
 
-async fn get_all_groups(state: &BotState) -> anyhow::Result<Vec<solana_dao::Group>> {
-    // Get the DAO registry PDA
-    let (dao_registry_pda, _) = Pubkey::find_program_address(&[b"dao_registry"], &solana_dao::ID);
-
-    println!("DAO Registry PDA: {}", dao_registry_pda);
-    println!("Program ID used: {}", solana_dao::ID);
-
-    // First check if the account exists
-    match state.program.rpc().get_account(&dao_registry_pda).await {
-        Ok(account) => {
-            log::info!(
-                "DAO registry account exists with {} bytes",
-                account.data.len()
-            );
+async fn handle_network(bot: Bot, msg: Message, state: BotState) -> ResponseResult<()> {
+    let vocab = chat_vocabulary(&state, msg.chat.id).await;
+    match is_chat_admin(&bot, &msg).await {
+        Ok(true) => {}
+        Ok(false) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                "Only group admins can view the network configuration.",
+            )
+            .await?;
+            return Ok(());
         }
         Err(e) => {
-            log::error!("DAO registry account does not exist or error: {}", e);
-            return Ok(Vec::new());
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                format!("Error checking admin status: {}", e),
+            )
+            .await?;
+            return Ok(());
         }
     }
 
-    // Try to fetch and deserialize the DAO registry account manually
-    match state.program.rpc().get_account(&dao_registry_pda).await {
-        Ok(account) => {
-            log::info!("Account data length: {} bytes", account.data.len());
+    let network = &state.network;
+    let rpc = state.program.rpc();
+    let status = match (rpc.get_slot().await, rpc.get_version().await) {
+        (Ok(slot), Ok(version)) => format!(
+            "✅ Reachable at slot {} (solana-core {})",
+            slot, version.solana_core
+        ),
+        (Err(e), _) | (_, Err(e)) => format!("❌ Unreachable: {}", e),
+    };
+    let response = format!(
+        "🌐 <b>Network</b>\n\n\
+        Cluster: <b>{}</b>\n\
+        RPC: <code>{}</code> ({} of {} configured)\n\
+        WebSocket: <code>{}</code>\n\
+        Commitment: {:?}\n\
+        Program: <code>{}</code>\n\
+        Payer: <code>{}</code>\n\
+        Explorer: {}\n\n\
+        {}",
+        network.name,
+        html_escape(&network.rpc_host()),
+        network
+            .rpc_urls
+            .iter()
+            .position(|url| url == network.cluster.url())
+            .map_or(1, |i| i + 1),
+        network.rpc_urls.len(),
+        html_escape(&network.ws_host()),
+        network.commitment.commitment,
+        solana_dao::ID,
+        state.payer.pubkey(),
+        html_escape(&network.address_url(solana_dao::ID)),
+        html_escape(&status)
+    );
+    bot.send_localized(&vocab, msg.chat.id, response)
+        .parse_mode(teloxide::types::ParseMode::Html)
+        .await?;
+    Ok(())
+}
 
-            if account.data.len() < 8 {
-                log::error!("Account data too short: {} bytes", account.data.len());
-                return Ok(Vec::new());
+async fn handle_profile(
+    bot: Bot,
+    msg: Message,
+    args: String,
+    state: BotState,
+) -> ResponseResult<()> {
+    let vocab = chat_vocabulary(&state, msg.chat.id).await;
+    let group_id = format!("tg_{}", msg.chat.id.0.abs());
+    let profile = fetch_group_profile(&state, &group_id).await;
+    let args = args.trim();
+
+    if args.is_empty() {
+        let (group_pda, _) =
+            Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+        let group = match state.program.account::<solana_dao::Group>(group_pda).await {
+            Ok(group) => group,
+            Err(e) => {
+                bot.send_localized(
+                    &vocab,
+                    msg.chat.id,
+                    format!("❌ Failed to fetch the group: {}", e),
+                )
+                .await?;
+                return Ok(());
             }
-
-            // Skip the 8-byte discriminator and deserialize manually
-            let data = &account.data[8..];
-
-            // Find the actual data length by looking for the end of meaningful data
-            // The account is padded with zeros, so we need to find where the real data ends
-            let mut actual_data_len = data.len();
-            for (i, &byte) in data.iter().enumerate().rev() {
-                if byte != 0 {
-                    actual_data_len = i + 1;
-                    break;
+        };
+        let mut response = format!(
+            "🪪 <b>{}</b>\n📝 {}\n👥 {} members\n",
+            html_escape(&group.name),
+            html_escape(&group.description),
+            group.member_count
+        );
+        match profile {
+            Some(profile) => {
+                if let Some(avatar) = &profile.avatar_uri {
+                    response.push_str(&format!("🖼 {}\n", html_escape(avatar)));
+                }
+                if let Some(website) = &profile.website {
+                    response.push_str(&format!("🌐 {}\n", html_escape(website)));
+                }
+                for social in &profile.socials {
+                    response.push_str(&format!("🔗 {}\n", html_escape(social)));
+                }
+                if let Some(contact) = &profile.contact {
+                    response.push_str(&format!("✉️ {}\n", html_escape(contact)));
                 }
             }
+            None => response.push_str(
+                "\nNo profile yet. Admins can add one with \
+                <code>/profile avatar|website|socials|contact &lt;value&gt;</code>",
+            ),
+        }
+        bot.send_localized(&vocab, msg.chat.id, response)
+            .parse_mode(teloxide::types::ParseMode::Html)
+            .await?;
+        return Ok(());
+    }
 
-            log::info!(
-                "Actual data length: {} bytes (out of {} total)",
-                actual_data_len,
-                data.len()
-            );
-
-            // Only deserialize the actual data portion
-            let actual_data = &data[..actual_data_len];
-
-            // Deserialize the DaoRegistry struct manually using Anchor
-            match solana_dao::DaoRegistry::try_from_slice(actual_data) {
-                Ok(dao_registry) => {
-                    log::info!(
-                        "Successfully deserialized DAO registry with {} groups",
-                        dao_registry.groups.len()
-                    );
-
-                    // Fetch all group accounts
-                    let mut groups = Vec::new();
-                    for group_info in dao_registry.groups {
-                        log::info!(
-                            "Attempting to fetch group: {} with pubkey: {}",
-                            group_info.group_id,
-                            group_info.pubkey
-                        );
-                        // Try to fetch group account manually (same approach as DAO registry)
-                        match state.program.rpc().get_account(&group_info.pubkey).await {
-                            Ok(account) => {
-                                log::info!(
-                                    "Group account exists with {} bytes",
-                                    account.data.len()
-                                );
-
-                                if account.data.len() < 8 {
-                                    log::error!(
-                                        "Group account data too short: {} bytes",
-                                        account.data.len()
-                                    );
-                                    continue;
-                                }
-
-                                // Skip the 8-byte discriminator
-                                let data = &account.data[8..];
+    match is_chat_admin(&bot, &msg).await {
+        Ok(true) => {}
+        Ok(false) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                "Only group admins can edit the profile.",
+            )
+            .await?;
+            return Ok(());
+        }
+        Err(e) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                format!("Error checking admin status: {}", e),
+            )
+            .await?;
+            return Ok(());
+        }
+    }
 
-                                // Find the actual data length by looking for the end of meaningful data
-                                let mut actual_data_len = data.len();
-                                for (i, &byte) in data.iter().enumerate().rev() {
-                                    if byte != 0 {
-                                        actual_data_len = i + 1;
-                                        break;
-                                    }
-                                }
+    // The program replaces the whole profile, so start from the one already set
+    let mut profile = profile.unwrap_or_default();
+    let (field, value) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+    let value = value.trim();
+    let optional = (!value.is_empty()).then(|| value.to_string());
+    match field {
+        "avatar" => profile.avatar_uri = optional,
+        "website" => profile.website = optional,
+        "socials" => profile.socials = value.split_whitespace().map(str::to_string).collect(),
+        "contact" => profile.contact = optional,
+        _ => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                "❌ Usage: /profile avatar|website|socials|contact [value]\n\
+                Socials take up to 4 links separated by spaces. Leave out the value to \
+                clear the field.",
+            )
+            .await?;
+            return Ok(());
+        }
+    }
 
-                                log::info!(
-                                    "Group actual data length: {} bytes (out of {} total)",
-                                    actual_data_len,
-                                    data.len()
-                                );
+    let instruction =
+        build_set_group_profile_instruction(&group_id, &profile, state.payer.pubkey());
+    let response = match send_instructions(
+        &state,
+        &[instruction],
+        &state.payer.pubkey(),
+        &[&state.payer],
+    )
+    .await
+    {
+        Ok(_) => format!("✅ Updated the group's {}.", field),
+        Err(e) if e.to_string().contains("InvalidMetadataUri") => {
+            "❌ Invalid link. Use an ipfs://, ar:// or https:// link of at most 200 characters."
+                .to_string()
+        }
+        Err(e) if e.to_string().contains("InvalidGroupProfile") => {
+            "❌ Profiles hold up to 4 distinct social links and a contact of at most 100 \
+            characters."
+                .to_string()
+        }
+        Err(e) => format!("❌ Failed to update the profile: {}", e),
+    };
+    bot.send_localized(&vocab, msg.chat.id, response).await?;
+    Ok(())
+}
 
-                                // Only deserialize the actual data portion
-                                let actual_data = &data[..actual_data_len];
+async fn handle_ban(bot: Bot, msg: Message, args: String, state: BotState) -> ResponseResult<()> {
+    let vocab = chat_vocabulary(&state, msg.chat.id).await;
+    match is_chat_admin(&bot, &msg).await {
+        Ok(true) => {}
+        Ok(false) => {
+            bot.send_localized(&vocab, msg.chat.id, "Only group admins can ban wallets.")
+                .await?;
+            return Ok(());
+        }
+        Err(e) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                format!("Error checking admin status: {}", e),
+            )
+            .await?;
+            return Ok(());
+        }
+    }
 
-                                match solana_dao::Group::try_from_slice(actual_data) {
-                                    Ok(group) => {
-                                        log::info!("Successfully fetched group: {}", group.name);
-                                        groups.push(group);
-                                    }
-                                    Err(e) => {
-                                        log::error!(
-                                            "Failed to deserialize group {}: {}",
-                                            group_info.group_id,
-                                            e
-                                        );
-                                    }
-                                }
-                            }
-                            Err(e) => {
-                                log::error!(
-                                    "Failed to get group account {}: {}",
-                                    group_info.group_id,
-                                    e
-                                );
-                            }
-                        }
+    let usage =
+        "❌ Usage: /ban <wallet> <days> [reason] (0 days bans for good) or /ban lift <wallet>";
+    let group_id = format!("tg_{}", msg.chat.id.0.abs());
+    let parts: Vec<&str> = args.split_whitespace().collect();
+    let response = match parts.as_slice() {
+        ["lift", wallet] => match Pubkey::from_str(wallet) {
+            Ok(wallet) => {
+                let instruction =
+                    build_unban_member_instruction(&group_id, wallet, state.payer.pubkey());
+                match send_instructions(
+                    &state,
+                    &[instruction],
+                    &state.payer.pubkey(),
+                    &[&state.payer],
+                )
+                .await
+                {
+                    Ok(_) => format!("✅ Ban on <code>{}</code> lifted.", wallet),
+                    Err(e) if e.to_string().contains("AccountNotInitialized") => {
+                        "❌ That wallet isn't banned.".to_string()
                     }
-                    Ok(groups)
+                    Err(e) => format!("❌ Failed to lift the ban: {}", e),
                 }
-                Err(e) => {
-                    log::error!("Manual deserialization failed: {}", e);
-                    log::info!("Returning empty groups list due to deserialization error");
-                    Ok(Vec::new())
+            }
+            Err(_) => "❌ Invalid wallet address.".to_string(),
+        },
+        [wallet, days, reason @ ..] => match (Pubkey::from_str(wallet), days.parse::<u32>()) {
+            (Ok(wallet), Ok(days)) => {
+                let expires_at = if days == 0 {
+                    0
+                } else {
+                    Utc::now().timestamp() + i64::from(days) * 24 * 60 * 60
+                };
+                let reason = reason.join(" ");
+                let instruction = match listed_member_record(&state, &group_id, wallet).await {
+                    Ok(member_record) => Ok(build_ban_member_instruction(
+                        &group_id,
+                        wallet,
+                        &reason,
+                        expires_at,
+                        member_record,
+                        state.payer.pubkey(),
+                    )),
+                    Err(e) => Err(e),
+                };
+                let result = match instruction {
+                    Ok(instruction) => {
+                        send_instructions(
+                            &state,
+                            &[instruction],
+                            &state.payer.pubkey(),
+                            &[&state.payer],
+                        )
+                        .await
+                    }
+                    Err(e) => Err(e),
+                };
+                match result {
+                    Ok(_) if days == 0 => format!(
+                        "🚫 <code>{}</code> is banned for good and can't join or vote.",
+                        wallet
+                    ),
+                    Ok(_) => format!(
+                        "🚫 <code>{}</code> is banned for {} days and can't join or vote until then.",
+                        wallet, days
+                    ),
+                    Err(e) if e.to_string().contains("InvalidBan") => {
+                        "❌ The reason can be at most 200 characters.".to_string()
+                    }
+                    Err(e) => format!("❌ Failed to ban: {}", e),
                 }
             }
-        }
-        Err(e) => {
-            log::error!("Failed to get account: {}", e);
-            Ok(Vec::new())
-        }
-    }
+            _ => usage.to_string(),
+        },
+        _ => usage.to_string(),
+    };
+    bot.send_localized(&vocab, msg.chat.id, response)
+        .parse_mode(teloxide::types::ParseMode::Html)
+        .await?;
+    Ok(())
 }
 
-async fn create_solana_proposal(
-    state: &BotState,
-    group_id: &str,
-    proposal_id: &str,
-    title: &str,
-    description: &str,
-    choices: Vec<String>,
-    voting_start: i64,
-    voting_end: i64,
-) -> anyhow::Result<String> {
-    // Get the group PDA
+async fn handle_template(
+    bot: Bot,
+    msg: Message,
+    args: String,
+    state: BotState,
+) -> ResponseResult<()> {
+    let vocab = chat_vocabulary(&state, msg.chat.id).await;
+    let group_id = format!("tg_{}", msg.chat.id.0.abs());
     let (group_pda, _) =
         Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let parts: Vec<&str> = args.split_whitespace().collect();
 
-    log::info!("Group PDA: {}", group_pda);
-    log::info!("Current payer (authority): {}", state.payer.pubkey());
-    log::info!("Looking for group with ID: '{}'", group_id);
-
-    // Check if group exists and get its authority
-    let program = state.solana_client.program(solana_dao::ID)?;
+    if parts.is_empty() {
+        let response = match group_templates(&state, &group_pda).await {
+            Ok(templates) if templates.is_empty() => {
+                "No proposal templates yet. Save one with /template save <template_id> <proposal_id>."
+                    .to_string()
+            }
+            Ok(templates) => {
+                let mut response = "📋 Proposal templates:\n".to_string();
+                for template in &templates {
+                    response.push_str(&format!(
+                        "\n• {} — {} ({}h, used {} times)",
+                        template.template_id,
+                        template.title,
+                        template.voting_duration / 3600,
+                        template.instance_count
+                    ));
+                }
+                response
+            }
+            Err(e) => format!("❌ Failed to fetch templates: {}", e),
+        };
+        bot.send_localized(&vocab, msg.chat.id, response).await?;
+        return Ok(());
+    }
 
-    // First, let's check if the account exists at all
-    match program.rpc().get_account(&group_pda).await {
-        Ok(account) => {
-            log::info!("Group account exists with {} bytes", account.data.len());
+    match is_chat_admin(&bot, &msg).await {
+        Ok(true) => {}
+        Ok(false) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                "Only group admins can save or use proposal templates.",
+            )
+            .await?;
+            return Ok(());
         }
         Err(e) => {
-            log::error!("Group account does not exist: {}", e);
-            return Err(anyhow::anyhow!(
-                "Group '{}' does not exist. Please create the group first.",
-                group_id
-            ));
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                format!("Error checking admin status: {}", e),
+            )
+            .await?;
+            return Ok(());
         }
     }
 
-    match program.account::<solana_dao::Group>(group_pda).await {
-        Ok(group) => {
-            log::info!("Group found - Authority: {}", group.authority);
-            log::info!(
-                "Group name: '{}', description: '{}'",
-                group.name,
-                group.description
-            );
-            if group.authority != state.payer.pubkey() {
-                return Err(anyhow::anyhow!(
-                    "Unauthorized: Group authority ({}) does not match current payer ({})",
-                    group.authority,
-                    state.payer.pubkey()
-                ));
+    let response = match parts.as_slice() {
+        ["save", template_id, proposal_id] => {
+            match get_proposal_results(&state, &group_id, proposal_id).await {
+                Ok(proposal) => {
+                    let template = solana_dao::ProposalTemplate {
+                        group: group_pda,
+                        template_id: template_id.to_string(),
+                        title: proposal.title,
+                        description: proposal.description,
+                        choices: proposal.choices,
+                        voting_duration: proposal.voting_end - proposal.voting_start,
+                        token_mint: proposal.token_mint,
+                        metadata_uri: proposal.metadata_uri,
+                        kind: proposal.kind,
+                        member_weighted: proposal.member_weighted,
+                        split_voting: proposal.split_voting,
+                        instance_count: 0,
+                        created_at: 0,
+                        bump: 0,
+                    };
+                    let instruction = build_create_proposal_template_instruction(
+                        &group_id,
+                        &template,
+                        state.payer.pubkey(),
+                    );
+                    match send_instructions(
+                        &state,
+                        &[instruction],
+                        &state.payer.pubkey(),
+                        &[&state.payer],
+                    )
+                    .await
+                    {
+                        Ok(_) => format!(
+                            "✅ Saved template '{}'. Start a new vote from it with /template use {}.",
+                            template_id, template_id
+                        ),
+                        Err(e) if e.to_string().contains("InvalidTemplateId") => {
+                            "❌ Template ids must be 1 to 32 bytes long.".to_string()
+                        }
+                        Err(e) => format!("❌ Failed to save template: {}", e),
+                    }
+                }
+                Err(e) => format!("❌ Couldn't load proposal {}: {}", proposal_id, e),
             }
         }
-        Err(e) => {
-            log::error!("Failed to deserialize group account: {}", e);
-
-            // Try manual deserialization like in get_all_groups
-            match program.rpc().get_account(&group_pda).await {
-                Ok(account) => {
-                    log::info!("Attempting manual deserialization...");
-                    if account.data.len() < 8 {
-                        log::error!("Account data too short: {} bytes", account.data.len());
-                        return Err(anyhow::anyhow!("Group '{}' data is corrupted.", group_id));
+        ["use", template_id, rest @ ..] if rest.len() <= 1 => {
+            let start_in_hours = match rest.first().map(|hours| hours.parse::<u32>()) {
+                None => Ok(0),
+                Some(hours) => hours,
+            };
+            match (
+                start_in_hours,
+                state.program.account::<solana_dao::Group>(group_pda).await,
+            ) {
+                (Err(_), _) => "❌ The start delay must be a whole number of hours.".to_string(),
+                (_, Err(e)) => format!("❌ Failed to load group: {}", e),
+                (Ok(start_in_hours), Ok(group)) => {
+                    let proposal_id = Uuid::new_v4().to_string();
+                    // A minute of slack so the start isn't already past when the transaction lands
+                    let voting_start =
+                        Utc::now().timestamp() + 60 + i64::from(start_in_hours) * 3600;
+                    let instruction = build_instantiate_from_template_instruction(
+                        &group_id,
+                        template_id,
+                        &proposal_id,
+                        voting_start,
+                        group.proposal_count,
+                        state.payer.pubkey(),
+                    );
+                    match send_instructions(
+                        &state,
+                        &[instruction],
+                        &state.payer.pubkey(),
+                        &[&state.payer],
+                    )
+                    .await
+                    {
+                        Ok(_) => format!(
+                            "✅ Proposal created from template '{}'.\nProposal ID: {}",
+                            template_id, proposal_id
+                        ),
+                        Err(e) => format!("❌ Failed to create proposal from template: {}", e),
                     }
+                }
+            }
+        }
+        _ => "❌ Usage: /template to list templates\n\
+            /template save <template_id> <proposal_id>\n\
+            /template use <template_id> [start_in_hours]"
+            .to_string(),
+    };
+    bot.send_localized(&vocab, msg.chat.id, response).await?;
+    Ok(())
+}
 
-                    let data = &account.data[8..];
-                    let mut actual_data_len = data.len();
-                    for (i, &byte) in data.iter().enumerate().rev() {
-                        if byte != 0 {
-                            actual_data_len = i + 1;
-                            break;
-                        }
-                    }
+async fn handle_deposit(
+    bot: Bot,
+    msg: Message,
+    args: String,
+    state: BotState,
+) -> ResponseResult<()> {
+    let vocab = chat_vocabulary(&state, msg.chat.id).await;
+    let group_id = format!("tg_{}", msg.chat.id.0.abs());
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let registrar_pda = sol_registrar_pda(&group_pda);
+    let parts: Vec<&str> = args.split_whitespace().collect();
+    let sol_amount = |text: &str| {
+        text.parse::<f64>()
+            .ok()
+            .filter(|sol| sol.is_finite() && *sol > 0.0)
+            .map(|sol| (sol * LAMPORTS_PER_SOL as f64) as u64)
+    };
 
-                    log::info!(
-                        "Manual deserialization - actual data length: {} bytes",
-                        actual_data_len
-                    );
-                    let actual_data = &data[..actual_data_len];
+    // Setting up the registrar and requiring deposits are admin actions
+    if matches!(parts.first(), Some(&"setup") | Some(&"require")) {
+        match is_chat_admin(&bot, &msg).await {
+            Ok(true) => {}
+            Ok(false) => {
+                bot.send_localized(
+                    &vocab,
+                    msg.chat.id,
+                    "Only group admins can set up deposit voting.",
+                )
+                .await?;
+                return Ok(());
+            }
+            Err(e) => {
+                bot.send_localized(
+                    &vocab,
+                    msg.chat.id,
+                    format!("Error checking admin status: {}", e),
+                )
+                .await?;
+                return Ok(());
+            }
+        }
+    }
+
+    let response = match parts.as_slice() {
+        ["setup", days] => match days.parse::<u32>() {
+            Ok(days) => {
+                let instruction = build_configure_deposit_registrar_instruction(
+                    &group_id,
+                    i64::from(days) * 24 * 60 * 60,
+                    state.payer.pubkey(),
+                );
+                match send_instructions(
+                    &state,
+                    &[instruction],
+                    &state.payer.pubkey(),
+                    &[&state.payer],
+                )
+                .await
+                {
+                    Ok(_) => format!(
+                        "✅ Deposit voting is set up. Deposits count on proposals created {} days or more after them.",
+                        days
+                    ),
+                    Err(e) => format!("❌ Failed to set up deposit voting: {}", e),
+                }
+            }
+            Err(_) => "❌ The holding period must be a whole number of days.".to_string(),
+        },
+        ["require", proposal_id] => {
+            let instruction =
+                build_set_deposit_voting_instruction(&group_id, proposal_id, state.payer.pubkey());
+            match send_instructions(
+                &state,
+                &[instruction],
+                &state.payer.pubkey(),
+                &[&state.payer],
+            )
+            .await
+            {
+                Ok(_) => format!(
+                    "✅ Proposal {} now counts only SOL deposited ahead of its creation.",
+                    proposal_id
+                ),
+                Err(e) if e.to_string().contains("DepositVotingUnsupported") => {
+                    "❌ Only SOL-weighted proposals with on-chain ballots can require deposits."
+                        .to_string()
+                }
+                Err(e) => format!("❌ Failed to require deposits: {}", e),
+            }
+        }
+        [] | [_] | ["withdraw", _] => {
+            let Some(user) = msg.from() else {
+                bot.send_localized(
+                    &vocab,
+                    msg.chat.id,
+                    "❌ Unable to identify user. Please try again.",
+                )
+                .await?;
+                return Ok(());
+            };
+            let keypair = match ensure_user_account(&state, user.id.0 as i64).await {
+                Ok(keypair) => keypair,
+                Err(e) => {
+                    bot.send_localized(
+                        &vocab,
+                        msg.chat.id,
+                        format!(
+                            "❌ Failed to access your account: {}. Please try /login first.",
+                            e
+                        ),
+                    )
+                    .await?;
+                    return Ok(());
+                }
+            };
+            let wallet = keypair.pubkey();
+            let instruction = match parts.as_slice() {
+                ["withdraw", amount] => sol_amount(amount).map(|lamports| {
+                    build_withdraw_voting_tokens_instruction(&group_id, lamports, wallet)
+                }),
+                [amount] => sol_amount(amount).map(|lamports| {
+                    build_deposit_voting_tokens_instruction(&group_id, lamports, wallet)
+                }),
+                _ => None,
+            };
+            match instruction {
+                None if !parts.is_empty() => {
+                    "❌ Usage: /deposit <sol> or /deposit withdraw <sol>".to_string()
+                }
+                None => deposit_summary(&state, &registrar_pda, &wallet).await,
+                Some(instruction) => {
+                    match send_instructions(&state, &[instruction], &wallet, &[&keypair]).await {
+                        Ok(_) => deposit_summary(&state, &registrar_pda, &wallet).await,
+                        Err(e) if e.to_string().contains("InsufficientDeposit") => {
+                            "❌ You can't withdraw more than you deposited.".to_string()
+                        }
+                        Err(e) => format!("❌ Failed to update your deposit: {}", e),
+                    }
+                }
+            }
+        }
+        _ => "❌ Usage: /deposit to see your deposit\n\
+            /deposit <sol> or /deposit withdraw <sol>\n\
+            /deposit setup <holding_days> (admins)\n\
+            /deposit require <proposal_id> (admins)"
+            .to_string(),
+    };
+    bot.send_localized(&vocab, msg.chat.id, response).await?;
+    Ok(())
+}
+
+async fn handle_dues(bot: Bot, msg: Message, args: String, state: BotState) -> ResponseResult<()> {
+    let vocab = chat_vocabulary(&state, msg.chat.id).await;
+    let group_id = format!("tg_{}", msg.chat.id.0.abs());
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let parts: Vec<&str> = args.split_whitespace().collect();
+
+    // Setting dues and reviewing who is behind are admin actions
+    if matches!(
+        parts.first(),
+        Some(&"setup") | Some(&"off") | Some(&"overdue")
+    ) {
+        match is_chat_admin(&bot, &msg).await {
+            Ok(true) => {}
+            Ok(false) => {
+                bot.send_localized(&vocab, msg.chat.id, "Only group admins can manage dues.")
+                    .await?;
+                return Ok(());
+            }
+            Err(e) => {
+                bot.send_localized(
+                    &vocab,
+                    msg.chat.id,
+                    format!("Error checking admin status: {}", e),
+                )
+                .await?;
+                return Ok(());
+            }
+        }
+    }
+
+    let policy = match state.program.account::<solana_dao::Group>(group_pda).await {
+        Ok(group) => group.dues,
+        Err(e) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                format!("❌ Failed to fetch the group: {}", e),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let response = match parts.as_slice() {
+        ["setup", sol, period_days, grace_days, rest @ ..] if rest.len() <= 1 => {
+            let amount = sol
+                .parse::<f64>()
+                .ok()
+                .filter(|sol| sol.is_finite() && *sol > 0.0)
+                .map(|sol| (sol * LAMPORTS_PER_SOL as f64) as u64);
+            let days = |text: &str| text.parse::<u32>().ok().map(|d| i64::from(d) * 86_400);
+            match (amount, days(period_days), days(grace_days)) {
+                (Some(amount), Some(period_secs), Some(grace_secs)) => {
+                    let dues = solana_dao::DuesPolicy {
+                        mint: solana_dao::NATIVE_MINT,
+                        amount,
+                        period_secs,
+                        grace_secs,
+                        required_for_voting: rest == ["required"],
+                    };
+                    let instruction = build_set_dues_policy_instruction(
+                        &group_id,
+                        Some(&dues),
+                        state.payer.pubkey(),
+                    );
+                    match send_instructions(
+                        &state,
+                        &[instruction],
+                        &state.payer.pubkey(),
+                        &[&state.payer],
+                    )
+                    .await
+                    {
+                        Ok(_) => format!("✅ Dues set: {}", dues_policy_summary(&dues)),
+                        Err(e) if e.to_string().contains("InvalidDuesPolicy") => {
+                            "❌ Dues need a period of at least a day, a grace period no longer than the period and at least 0.001 SOL."
+                                .to_string()
+                        }
+                        Err(e) => format!("❌ Failed to set dues: {}", e),
+                    }
+                }
+                _ => {
+                    "❌ Usage: /dues setup <sol> <period_days> <grace_days> [required]".to_string()
+                }
+            }
+        }
+        ["off"] => {
+            let instruction =
+                build_set_dues_policy_instruction(&group_id, None, state.payer.pubkey());
+            match send_instructions(
+                &state,
+                &[instruction],
+                &state.payer.pubkey(),
+                &[&state.payer],
+            )
+            .await
+            {
+                Ok(_) => "✅ This chat no longer charges dues.".to_string(),
+                Err(e) => format!("❌ Failed to clear dues: {}", e),
+            }
+        }
+        ["overdue"] => match policy {
+            None => "This chat doesn't charge dues.".to_string(),
+            Some(dues) => match group_owned_accounts(
+                &state,
+                solana_dao::DUES_RECORD_DISCRIMINATOR,
+                &group_pda,
+            )
+            .await
+            {
+                Ok(records) => {
+                    let now = Utc::now().timestamp();
+                    let mut overdue: Vec<_> = records
+                        .into_iter()
+                        .filter_map(|data| {
+                            solana_dao::DuesRecord::deserialize(&mut data.as_slice()).ok()
+                        })
+                        .filter(|record| record.paid_until < now)
+                        .collect();
+                    overdue.sort_by_key(|record| record.paid_until);
+                    overdue_dues_report(&overdue, &dues, now)
+                }
+                Err(e) => format!("❌ Failed to fetch dues records: {}", e),
+            },
+        },
+        [] | ["pay"] | ["pay", _] => {
+            let Some(dues) = policy else {
+                bot.send_localized(&vocab, msg.chat.id, "This chat doesn't charge dues.")
+                    .await?;
+                return Ok(());
+            };
+            let periods = match parts.get(1).map(|periods| periods.parse::<u32>()) {
+                None => 1,
+                Some(Ok(periods)) if periods > 0 => periods,
+                Some(_) => {
+                    bot.send_localized(&vocab, msg.chat.id, "❌ Usage: /dues pay [periods]")
+                        .await?;
+                    return Ok(());
+                }
+            };
+            let Some(user) = msg.from() else {
+                bot.send_localized(
+                    &vocab,
+                    msg.chat.id,
+                    "❌ Unable to identify user. Please try again.",
+                )
+                .await?;
+                return Ok(());
+            };
+            let keypair = match ensure_user_account(&state, user.id.0 as i64).await {
+                Ok(keypair) => keypair,
+                Err(e) => {
+                    bot.send_localized(
+                        &vocab,
+                        msg.chat.id,
+                        format!(
+                            "❌ Failed to access your account: {}. Please try /login first.",
+                            e
+                        ),
+                    )
+                    .await?;
+                    return Ok(());
+                }
+            };
+            let wallet = keypair.pubkey();
+            if parts.is_empty() {
+                dues_status(&state, &group_pda, &dues, &wallet).await
+            } else if dues.mint != solana_dao::NATIVE_MINT {
+                "❌ This chat's dues are in an SPL token; pay them from your wallet.".to_string()
+            } else {
+                let instruction = build_pay_dues_instruction(&group_id, periods, wallet);
+                match send_instructions(&state, &[instruction], &wallet, &[&keypair]).await {
+                    Ok(_) => dues_status(&state, &group_pda, &dues, &wallet).await,
+                    Err(e) => format!("❌ Failed to pay dues: {}", e),
+                }
+            }
+        }
+        _ => "❌ Usage: /dues to see your dues\n\
+            /dues pay [periods]\n\
+            /dues overdue (admins)\n\
+            /dues setup <sol> <period_days> <grace_days> [required] (admins)\n\
+            /dues off (admins)"
+            .to_string(),
+    };
+    bot.send_localized(&vocab, msg.chat.id, response).await?;
+    Ok(())
+}
+
+async fn handle_shares(
+    bot: Bot,
+    msg: Message,
+    args: String,
+    state: BotState,
+) -> ResponseResult<()> {
+    let vocab = chat_vocabulary(&state, msg.chat.id).await;
+    let group_id = format!("tg_{}", msg.chat.id.0.abs());
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let parts: Vec<&str> = args.split_whitespace().collect();
+
+    // Pricing shares is an admin action
+    if matches!(parts.first(), Some(&"price") | Some(&"off")) {
+        match is_chat_admin(&bot, &msg).await {
+            Ok(true) => {}
+            Ok(false) => {
+                bot.send_localized(&vocab, msg.chat.id, "Only group admins can price shares.")
+                    .await?;
+                return Ok(());
+            }
+            Err(e) => {
+                bot.send_localized(
+                    &vocab,
+                    msg.chat.id,
+                    format!("Error checking admin status: {}", e),
+                )
+                .await?;
+                return Ok(());
+            }
+        }
+    }
+
+    let stake_to_join = match state.program.account::<solana_dao::Group>(group_pda).await {
+        Ok(group) => group.stake_to_join,
+        Err(e) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                format!("❌ Failed to fetch the group: {}", e),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let admin = state.payer.pubkey();
+    let response = match parts.as_slice() {
+        ["price", sol] => {
+            match sol
+                .parse::<f64>()
+                .ok()
+                .filter(|sol| sol.is_finite() && *sol > 0.0)
+            {
+                Some(sol) => {
+                    let price = (sol * LAMPORTS_PER_SOL as f64) as u64;
+                    let instruction =
+                        build_set_stake_to_join_instruction(&group_id, Some(price), admin);
+                    match send_instructions(&state, &[instruction], &admin, &[&state.payer]).await
+                    {
+                        Ok(_) => format!(
+                            "✅ Shares cost {} SOL each. Only shareholders can vote; join with /shares buy <shares>",
+                            sol
+                        ),
+                        Err(e) if e.to_string().contains("InvalidSharePrice") => {
+                            "❌ Shares must cost at least 0.001 SOL.".to_string()
+                        }
+                        Err(e) => format!("❌ Failed to price shares: {}", e),
+                    }
+                }
+                None => "❌ Usage: /shares price <sol>".to_string(),
+            }
+        }
+        ["off"] => {
+            let instruction = build_set_stake_to_join_instruction(&group_id, None, admin);
+            match send_instructions(&state, &[instruction], &admin, &[&state.payer]).await {
+                Ok(_) => "✅ This chat no longer sells shares.".to_string(),
+                Err(e) if e.to_string().contains("SharesOutstanding") => {
+                    "❌ Members still hold shares; they have to ragequit first.".to_string()
+                }
+                Err(e) => format!("❌ Failed to stop selling shares: {}", e),
+            }
+        }
+        [] | ["buy", _] | ["ragequit", _] => {
+            if stake_to_join.is_none() {
+                bot.send_localized(&vocab, msg.chat.id, "This chat doesn't sell shares.")
+                    .await?;
+                return Ok(());
+            }
+            let shares = match parts.get(1).map(|shares| shares.parse::<u64>()) {
+                None => 0,
+                Some(Ok(shares)) if shares > 0 => shares,
+                Some(_) => {
+                    bot.send_localized(
+                        &vocab,
+                        msg.chat.id,
+                        "❌ Usage: /shares buy <shares> or /shares ragequit <shares>",
+                    )
+                    .await?;
+                    return Ok(());
+                }
+            };
+            let Some(user) = msg.from() else {
+                bot.send_localized(
+                    &vocab,
+                    msg.chat.id,
+                    "❌ Unable to identify user. Please try again.",
+                )
+                .await?;
+                return Ok(());
+            };
+            if parts.first() == Some(&"ragequit") {
+                // Burned shares can't be bought back at the old price, so this waits for
+                // the member to press Confirm
+                let id = state
+                    .confirmations
+                    .register(
+                        user.id,
+                        msg.chat.id,
+                        callbacks::ConfirmedAction::Ragequit {
+                            group_id: group_id.clone(),
+                            shares,
+                        },
+                    )
+                    .await;
+                let mut request = bot.send_localized(
+                    &vocab,
+                    msg.chat.id,
+                    format!(
+                        "⚠️ Ragequit {} shares? They are burned and you receive your cut of the \
+                        treasury's SOL. This can't be undone.",
+                        shares
+                    ),
+                );
+                if let Some(keyboard) = callbacks::confirm_keyboard(&vocab, &id) {
+                    request = request.reply_markup(keyboard);
+                }
+                request.await?;
+                return Ok(());
+            }
+            let keypair = match ensure_user_account(&state, user.id.0 as i64).await {
+                Ok(keypair) => keypair,
+                Err(e) => {
+                    bot.send_localized(
+                        &vocab,
+                        msg.chat.id,
+                        format!(
+                            "❌ Failed to access your account: {}. Please try /login first.",
+                            e
+                        ),
+                    )
+                    .await?;
+                    return Ok(());
+                }
+            };
+            let wallet = keypair.pubkey();
+            if parts.is_empty() {
+                share_status(&state, &group_pda, &wallet).await
+            } else {
+                let instruction = build_stake_shares_instruction(&group_id, shares, wallet);
+                match send_instructions(&state, &[instruction], &wallet, &[&keypair]).await {
+                    Ok(_) => share_status(&state, &group_pda, &wallet).await,
+                    Err(e) => format!("❌ Failed to update your shares: {}", e),
+                }
+            }
+        }
+        _ => "❌ Usage: /shares to see your shares\n\
+            /shares buy <shares>\n\
+            /shares ragequit <shares>\n\
+            /shares price <sol> (admins)\n\
+            /shares off (admins)"
+            .to_string(),
+    };
+    bot.send_localized(&vocab, msg.chat.id, response).await?;
+    Ok(())
+}
+
+// Carries out a confirmed /shares ragequit
+async fn run_ragequit(state: &BotState, telegram_id: i64, group_id: &str, shares: u64) -> String {
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    match state.program.account::<solana_dao::Group>(group_pda).await {
+        Ok(group) if group.stake_to_join.is_none() => {
+            return "This chat doesn't sell shares.".to_string()
+        }
+        Ok(_) => {}
+        Err(e) => return format!("❌ Failed to fetch the group: {}", e),
+    }
+    let keypair = match ensure_user_account(state, telegram_id).await {
+        Ok(keypair) => keypair,
+        Err(e) => {
+            return format!(
+                "❌ Failed to access your account: {}. Please try /login first.",
+                e
+            )
+        }
+    };
+    let wallet = keypair.pubkey();
+    let instruction = build_ragequit_instruction(group_id, shares, wallet);
+    match send_instructions(state, &[instruction], &wallet, &[&keypair]).await {
+        Ok(_) => share_status(state, &group_pda, &wallet).await,
+        Err(e) if e.to_string().contains("SharesLocked") => {
+            "❌ Your shares are locked for 3 days after you bought them, and after a proposal you voted on has ended."
+                .to_string()
+        }
+        Err(e) if e.to_string().contains("InvalidShareAmount") => {
+            "❌ You don't hold that many shares.".to_string()
+        }
+        Err(e) => format!("❌ Failed to update your shares: {}", e),
+    }
+}
+
+// A wallet's shares, its cut of the treasury's SOL and any lock
+async fn share_status(state: &BotState, group_pda: &Pubkey, wallet: &Pubkey) -> String {
+    let rpc = state.program.rpc();
+    let accounts = match rpc
+        .get_multiple_accounts(&[
+            *group_pda,
+            member_shares_pda(group_pda, wallet),
+            treasury_pda(group_pda),
+        ])
+        .await
+    {
+        Ok(accounts) => accounts,
+        Err(e) => return format!("❌ Failed to fetch the group: {}", e),
+    };
+    let group = accounts
+        .first()
+        .cloned()
+        .flatten()
+        .and_then(|account| solana_dao::Group::deserialize(&mut &account.data[8..]).ok());
+    let Some((stake_to_join, committed_lamports)) =
+        group.and_then(|group| Some((group.stake_to_join?, group.committed_lamports)))
+    else {
+        return "This chat doesn't sell shares.".to_string();
+    };
+    let member_shares =
+        accounts.get(1).cloned().flatten().and_then(|account| {
+            solana_dao::MemberShares::deserialize(&mut &account.data[8..]).ok()
+        });
+    let treasury_lamports = accounts
+        .get(2)
+        .cloned()
+        .flatten()
+        .map_or(0, |account| account.lamports);
+    // Mirrors the program's share pool: SOL above the rent minimum that open grants,
+    // streams and bounties aren't owed
+    let rent = rpc
+        .get_minimum_balance_for_rent_exemption(0)
+        .await
+        .unwrap_or_default();
+    let pool_lamports = treasury_lamports
+        .saturating_sub(rent)
+        .saturating_sub(committed_lamports);
+    share_summary(
+        &stake_to_join,
+        member_shares.as_ref(),
+        pool_lamports,
+        Utc::now().timestamp(),
+    )
+}
+
+fn share_summary(
+    stake_to_join: &solana_dao::StakeToJoin,
+    member_shares: Option<&solana_dao::MemberShares>,
+    pool_lamports: u64,
+    now: i64,
+) -> String {
+    let sol = |lamports: u64| lamports as f64 / LAMPORTS_PER_SOL as f64;
+    let mut summary = format!(
+        "📈 Shares cost {} SOL each; {} issued.\n",
+        sol(stake_to_join
+            .cost(pool_lamports, 1)
+            .unwrap_or(stake_to_join.price_lamports)),
+        stake_to_join.total_shares
+    );
+    match member_shares.filter(|member_shares| member_shares.shares > 0) {
+        None => summary.push_str("You hold no shares. Buy some with /shares buy <shares>."),
+        Some(member_shares) => {
+            let cut = (pool_lamports as u128 * member_shares.shares as u128
+                / stake_to_join.total_shares.max(1) as u128) as u64;
+            summary.push_str(&format!(
+                "You hold {} shares, worth about {} SOL of the treasury.",
+                member_shares.shares,
+                sol(cut)
+            ));
+            if member_shares.locked_until >= now {
+                let unlock = DateTime::<Utc>::from_timestamp(member_shares.locked_until, 0)
+                    .map(|time| time.format("%Y-%m-%d %H:%M UTC").to_string())
+                    .unwrap_or_else(|| member_shares.locked_until.to_string());
+                summary.push_str(&format!("\n🔒 Locked until {}", unlock));
+            }
+        }
+    }
+    summary
+}
+
+async fn handle_delegate(
+    bot: Bot,
+    msg: Message,
+    args: String,
+    state: BotState,
+) -> ResponseResult<()> {
+    let vocab = chat_vocabulary(&state, msg.chat.id).await;
+    let group_id = format!("tg_{}", msg.chat.id.0.abs());
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let Some(user) = msg.from() else {
+        bot.send_localized(
+            &vocab,
+            msg.chat.id,
+            "❌ Unable to identify user. Please try again.",
+        )
+        .await?;
+        return Ok(());
+    };
+    let mut parts: Vec<&str> = args.split_whitespace().collect();
+
+    // The delegate is the author of the message replied to, a mentioned user without a
+    // username, or a wallet address
+    let mentioned = msg
+        .reply_to_message()
+        .and_then(|parent| parent.from())
+        .filter(|parent| !parent.is_bot)
+        .or_else(|| {
+            msg.entities()?
+                .iter()
+                .find_map(|entity| match &entity.kind {
+                    teloxide::types::MessageEntityKind::TextMention { user } => Some(user),
+                    _ => None,
+                })
+        });
+    let delegate = match mentioned {
+        Some(target) => {
+            if target.id == user.id {
+                bot.send_localized(&vocab, msg.chat.id, "❌ You can't delegate to yourself.")
+                    .await?;
+                return Ok(());
+            }
+            // A text mention's words are part of the arguments
+            parts.retain(|part| part.parse::<u32>().is_ok());
+            match telegram_wallet(&state, target.id.0 as i64).await {
+                Ok(Some(wallet)) => Some((wallet, target.full_name())),
+                Ok(None) => {
+                    bot.send_localized(
+                        &vocab,
+                        msg.chat.id,
+                        format!(
+                            "❌ {} has no account yet; they can create one with /login.",
+                            target.full_name()
+                        ),
+                    )
+                    .await?;
+                    return Ok(());
+                }
+                Err(e) => {
+                    bot.send_localized(
+                        &vocab,
+                        msg.chat.id,
+                        format!("❌ Failed to look up the delegate: {}", e),
+                    )
+                    .await?;
+                    return Ok(());
+                }
+            }
+        }
+        None => match parts.first().map(|wallet| Pubkey::from_str(wallet)) {
+            Some(Ok(wallet)) => {
+                parts.remove(0);
+                Some((wallet, wallet.to_string()))
+            }
+            Some(Err(_)) => {
+                bot.send_localized(
+                    &vocab,
+                    msg.chat.id,
+                    "❌ Usage: reply to a member's message with /delegate [days], or /delegate <wallet> [days]",
+                )
+                .await?;
+                return Ok(());
+            }
+            None => None,
+        },
+    };
+
+    let Some((delegate, label)) = delegate else {
+        let keypair = match ensure_user_account(&state, user.id.0 as i64).await {
+            Ok(keypair) => keypair,
+            Err(e) => {
+                bot.send_localized(
+                    &vocab,
+                    msg.chat.id,
+                    format!(
+                        "❌ Failed to access your account: {}. Please try /login first.",
+                        e
+                    ),
+                )
+                .await?;
+                return Ok(());
+            }
+        };
+        let response =
+            match delegation_status(&bot, &state, msg.chat.id, &group_pda, &keypair.pubkey()).await
+            {
+                Ok(status) => status,
+                Err(e) => format!("❌ Failed to fetch delegations: {}", e),
+            };
+        bot.send_localized(&vocab, msg.chat.id, response)
+            .parse_mode(teloxide::types::ParseMode::Html)
+            .await?;
+        return Ok(());
+    };
+
+    if msg.chat.is_private() {
+        bot.send_localized(
+            &vocab,
+            msg.chat.id,
+            "❌ Delegate from the group whose votes you want to hand over.",
+        )
+        .await?;
+        return Ok(());
+    }
+    let days = match parts.first().map(|days| days.parse::<u32>()) {
+        None => None,
+        Some(Ok(days)) if days > 0 => Some(days),
+        Some(_) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                "❌ Usage: reply to a member's message with /delegate [days], or /delegate <wallet> [days]",
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+    let expires_at = days.map(|days| Utc::now().timestamp() + days as i64 * 86_400);
+
+    // Handing over votes is easy to get wrong with a reply, so it waits for Confirm
+    let id = state
+        .confirmations
+        .register(
+            user.id,
+            msg.chat.id,
+            callbacks::ConfirmedAction::Delegate {
+                group_id,
+                delegate,
+                label: label.clone(),
+                expires_at,
+            },
+        )
+        .await;
+    let until = match days {
+        Some(days) => format!("for {} days", days),
+        None => "until you take it back".to_string(),
+    };
+    let mut request = bot.send_localized(
+        &vocab,
+        msg.chat.id,
+        format!(
+            "⚠️ Delegate your votes in this group to {} {}?\n\
+            They can vote for you on proposals you haven't voted on yourself. \
+            Take it back any time with /undelegate.",
+            label, until
+        ),
+    );
+    if let Some(keyboard) = callbacks::confirm_keyboard(&vocab, &id) {
+        request = request.reply_markup(keyboard);
+    }
+    request.await?;
+    Ok(())
+}
+
+async fn handle_undelegate(bot: Bot, msg: Message, state: BotState) -> ResponseResult<()> {
+    let vocab = chat_vocabulary(&state, msg.chat.id).await;
+    let group_id = format!("tg_{}", msg.chat.id.0.abs());
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let Some(user) = msg.from() else {
+        bot.send_localized(
+            &vocab,
+            msg.chat.id,
+            "❌ Unable to identify user. Please try again.",
+        )
+        .await?;
+        return Ok(());
+    };
+    let keypair = match ensure_user_account(&state, user.id.0 as i64).await {
+        Ok(keypair) => keypair,
+        Err(e) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                format!(
+                    "❌ Failed to access your account: {}. Please try /login first.",
+                    e
+                ),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+    let delegation = match fetch_delegation(&state, &keypair.pubkey(), &group_pda).await {
+        Ok(Some(delegation)) => delegation,
+        Ok(None) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                "You haven't delegated your votes in this group.",
+            )
+            .await?;
+            return Ok(());
+        }
+        Err(e) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                format!("❌ Failed to fetch delegations: {}", e),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let id = state
+        .confirmations
+        .register(
+            user.id,
+            msg.chat.id,
+            callbacks::ConfirmedAction::Undelegate { group_id },
+        )
+        .await;
+    let mut request = bot.send_localized(
+        &vocab,
+        msg.chat.id,
+        format!(
+            "⚠️ Take back the votes you delegated to {}?",
+            delegation.delegate
+        ),
+    );
+    if let Some(keyboard) = callbacks::confirm_keyboard(&vocab, &id) {
+        request = request.reply_markup(keyboard);
+    }
+    request.await?;
+    Ok(())
+}
+
+async fn run_delegate(
+    state: &BotState,
+    telegram_id: i64,
+    group_id: &str,
+    delegate: Pubkey,
+    label: &str,
+    expires_at: Option<i64>,
+) -> String {
+    let keypair = match ensure_user_account(state, telegram_id).await {
+        Ok(keypair) => keypair,
+        Err(e) => {
+            return format!(
+                "❌ Failed to access your account: {}. Please try /login first.",
+                e
+            )
+        }
+    };
+    let wallet = keypair.pubkey();
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let instruction = build_delegate_votes_instruction(&group_pda, wallet, delegate, expires_at);
+    match send_instructions(state, &[instruction], &wallet, &[&keypair]).await {
+        Ok(_) => match expires_at.and_then(|at| DateTime::<Utc>::from_timestamp(at, 0)) {
+            Some(until) => format!(
+                "✅ Your votes in this group are delegated to {} until {}.",
+                label,
+                until.format("%Y-%m-%d %H:%M UTC")
+            ),
+            None => format!("✅ Your votes in this group are delegated to {}.", label),
+        },
+        Err(e) if e.to_string().contains("SelfDelegation") => {
+            "❌ You can't delegate to yourself.".to_string()
+        }
+        Err(e) if e.to_string().contains("ProgramPaused") => {
+            "⏸️ The DAO program is paused for maintenance. Please try again later.".to_string()
+        }
+        Err(e) => format!("❌ Failed to delegate: {}", e),
+    }
+}
+
+async fn run_undelegate(state: &BotState, telegram_id: i64, group_id: &str) -> String {
+    let keypair = match ensure_user_account(state, telegram_id).await {
+        Ok(keypair) => keypair,
+        Err(e) => {
+            return format!(
+                "❌ Failed to access your account: {}. Please try /login first.",
+                e
+            )
+        }
+    };
+    let wallet = keypair.pubkey();
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let instruction = build_undelegate_votes_instruction(&group_pda, wallet);
+    match send_instructions(state, &[instruction], &wallet, &[&keypair]).await {
+        Ok(_) => "✅ Your votes in this group are yours alone again.".to_string(),
+        Err(e) if e.to_string().contains("AccountNotInitialized") => {
+            "You haven't delegated your votes in this group.".to_string()
+        }
+        Err(e) if e.to_string().contains("ProgramPaused") => {
+            "⏸️ The DAO program is paused for maintenance. Please try again later.".to_string()
+        }
+        Err(e) => format!("❌ Failed to undelegate: {}", e),
+    }
+}
+
+// Whom the wallet delegates to in the group, and who delegates to it there (including
+// delegations that cover every group)
+async fn delegation_status(
+    bot: &Bot,
+    state: &BotState,
+    chat_id: ChatId,
+    group_pda: &Pubkey,
+    wallet: &Pubkey,
+) -> anyhow::Result<String> {
+    let now = Utc::now().timestamp();
+    let until = |delegation: &solana_dao::Delegation| {
+        delegation
+            .expires_at
+            .and_then(|at| DateTime::<Utc>::from_timestamp(at, 0))
+            .map(|at| format!(" until {}", at.format("%Y-%m-%d %H:%M UTC")))
+            .unwrap_or_default()
+    };
+
+    let mut status = "🤝 <b>Delegation</b>\n\n".to_string();
+    match fetch_delegation(state, wallet, group_pda)
+        .await?
+        .filter(|delegation| !delegation.expired(now))
+    {
+        Some(delegation) => status.push_str(&format!(
+            "You delegate your votes to <code>{}</code>{}.\n",
+            delegation.delegate,
+            until(&delegation)
+        )),
+        None => status.push_str("You haven't delegated your votes in this group.\n"),
+    }
+
+    let incoming: Vec<solana_dao::Delegation> = delegations_to(state, wallet)
+        .await?
+        .into_iter()
+        .filter(|delegation| {
+            !delegation.expired(now)
+                && (delegation.group == *group_pda || delegation.group == Pubkey::default())
+        })
+        .collect();
+    if incoming.is_empty() {
+        status.push_str("Nobody has delegated their votes to you here.\n");
+    } else {
+        status.push_str(&format!(
+            "\n<b>Delegated to you ({}):</b>\n",
+            incoming.len()
+        ));
+        for delegation in &incoming {
+            let key = delegation.delegator.to_string();
+            let name = match wallet_owner(state, &delegation.delegator).await {
+                Ok(Some(telegram_id)) => bot
+                    .get_chat_member(chat_id, UserId(telegram_id as u64))
+                    .await
+                    .map(|member| format!(" {}", html_escape(&member.user.full_name())))
+                    .unwrap_or_default(),
+                _ => String::new(),
+            };
+            let scope = if delegation.group == Pubkey::default() {
+                " (all groups)"
+            } else {
+                ""
+            };
+            status.push_str(&format!(
+                "• <code>{}…{}</code>{}{}{}\n",
+                &key[..4],
+                &key[key.len() - 4..],
+                name,
+                scope,
+                until(delegation)
+            ));
+        }
+    }
+    status.push_str(
+        "\nDelegate by replying to a member's message with /delegate [days], or take your votes back with /undelegate.",
+    );
+    Ok(status)
+}
+
+async fn handle_bounty(
+    bot: Bot,
+    msg: Message,
+    args: String,
+    state: BotState,
+) -> ResponseResult<()> {
+    let vocab = chat_vocabulary(&state, msg.chat.id).await;
+    let group_id = format!("tg_{}", msg.chat.id.0.abs());
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let parts: Vec<&str> = args.split_whitespace().collect();
+
+    // Posting, approving and cancelling bounties are admin actions
+    if matches!(
+        parts.first(),
+        Some(&"post") | Some(&"approve") | Some(&"cancel")
+    ) {
+        match is_chat_admin(&bot, &msg).await {
+            Ok(true) => {}
+            Ok(false) => {
+                bot.send_localized(
+                    &vocab,
+                    msg.chat.id,
+                    "Only group admins can manage bounties.",
+                )
+                .await?;
+                return Ok(());
+            }
+            Err(e) => {
+                bot.send_localized(
+                    &vocab,
+                    msg.chat.id,
+                    format!("Error checking admin status: {}", e),
+                )
+                .await?;
+                return Ok(());
+            }
+        }
+    }
+
+    let admin = state.payer.pubkey();
+    let response = match parts.as_slice() {
+        [] => {
+            match group_owned_accounts(&state, solana_dao::BOUNTY_DISCRIMINATOR, &group_pda).await {
+                Ok(accounts) => {
+                    let mut bounties: Vec<_> = accounts
+                        .into_iter()
+                        .filter_map(|data| {
+                            solana_dao::Bounty::deserialize(&mut data.as_slice()).ok()
+                        })
+                        .collect();
+                    bounties.sort_by_key(|bounty| bounty.created_at);
+                    bounty_board(&bounties)
+                }
+                Err(e) => format!("❌ Failed to fetch bounties: {}", e),
+            }
+        }
+        ["post", bounty_id, sol, uri] => {
+            match sol
+                .parse::<f64>()
+                .ok()
+                .filter(|sol| sol.is_finite() && *sol > 0.0)
+            {
+                Some(sol) => {
+                    let reward = (sol * LAMPORTS_PER_SOL as f64) as u64;
+                    let instruction =
+                        build_post_bounty_instruction(&group_id, bounty_id, uri, reward, admin);
+                    match send_instructions(&state, &[instruction], &admin, &[&state.payer]).await
+                    {
+                        Ok(_) => format!(
+                            "✅ Bounty <code>{}</code> posted for {} SOL. Claim it with /bounty claim {}",
+                            bounty_id, sol, bounty_id
+                        ),
+                        Err(e) if e.to_string().contains("InvalidBounty") => {
+                            "❌ Bounty ids are up to 32 characters and the reward must be positive."
+                                .to_string()
+                        }
+                        Err(e) if e.to_string().contains("InvalidMetadataUri") => {
+                            "❌ The description must be an https://, ipfs:// or ar:// link."
+                                .to_string()
+                        }
+                        Err(e) => format!("❌ Failed to post the bounty: {}", e),
+                    }
+                }
+                None => "❌ Usage: /bounty post <bounty_id> <sol> <description_uri>".to_string(),
+            }
+        }
+        ["approve", bounty_id] => {
+            let bounty = state
+                .program
+                .rpc()
+                .get_account(&bounty_pda(&group_pda, bounty_id))
+                .await
+                .ok()
+                .and_then(|account| solana_dao::Bounty::deserialize(&mut &account.data[8..]).ok());
+            match bounty {
+                None => format!("❌ No bounty <code>{}</code> in this chat.", bounty_id),
+                Some(bounty) => match (bounty.status, bounty.hunter) {
+                    (solana_dao::BountyStatus::Submitted, Some(hunter)) => {
+                        let instruction =
+                            build_approve_bounty_instruction(&group_id, bounty_id, hunter, admin);
+                        match send_instructions(&state, &[instruction], &admin, &[&state.payer])
+                            .await
+                        {
+                            Ok(_) => format!(
+                                "✅ Paid {} SOL from the treasury for bounty <code>{}</code>.",
+                                bounty.reward as f64 / LAMPORTS_PER_SOL as f64,
+                                bounty_id
+                            ),
+                            Err(e) => format!("❌ Failed to approve the bounty: {}", e),
+                        }
+                    }
+                    _ => format!(
+                        "❌ Bounty <code>{}</code> has no submission to approve.",
+                        bounty_id
+                    ),
+                },
+            }
+        }
+        ["cancel", bounty_id] => {
+            let instruction = build_cancel_bounty_instruction(&group_id, bounty_id, admin);
+            match send_instructions(&state, &[instruction], &admin, &[&state.payer]).await {
+                Ok(_) => format!("✅ Bounty <code>{}</code> cancelled.", bounty_id),
+                Err(e) => format!("❌ Failed to cancel the bounty: {}", e),
+            }
+        }
+        ["claim", bounty_id] | ["submit", bounty_id, _] => {
+            let Some(user) = msg.from() else {
+                bot.send_localized(
+                    &vocab,
+                    msg.chat.id,
+                    "❌ Unable to identify user. Please try again.",
+                )
+                .await?;
+                return Ok(());
+            };
+            let keypair = match ensure_user_account(&state, user.id.0 as i64).await {
+                Ok(keypair) => keypair,
+                Err(e) => {
+                    bot.send_localized(
+                        &vocab,
+                        msg.chat.id,
+                        format!(
+                            "❌ Failed to access your account: {}. Please try /login first.",
+                            e
+                        ),
+                    )
+                    .await?;
+                    return Ok(());
+                }
+            };
+            let hunter = keypair.pubkey();
+            let instruction = match parts.get(2) {
+                None => build_claim_bounty_instruction(&group_id, bounty_id, hunter),
+                Some(uri) => build_submit_bounty_instruction(&group_id, bounty_id, uri, hunter),
+            };
+            match send_instructions(&state, &[instruction], &hunter, &[&keypair]).await {
+                Ok(_) if parts[0] == "claim" => format!(
+                    "✅ Bounty <code>{}</code> is yours. Hand in your work with /bounty submit {} <uri>",
+                    bounty_id, bounty_id
+                ),
+                Ok(_) => format!(
+                    "✅ Work submitted for bounty <code>{}</code>. An admin will review it.",
+                    bounty_id
+                ),
+                Err(e) if e.to_string().contains("BountyNotOpen") => {
+                    format!("❌ Bounty <code>{}</code> isn't open.", bounty_id)
+                }
+                Err(e) if e.to_string().contains("Unauthorized") => {
+                    format!("❌ Bounty <code>{}</code> was claimed by someone else.", bounty_id)
+                }
+                Err(e) => format!("❌ Failed to update the bounty: {}", e),
+            }
+        }
+        _ => "❌ Usage: /bounty to list the bounty board\n\
+            /bounty claim <bounty_id>\n\
+            /bounty submit <bounty_id> <uri>\n\
+            /bounty post <bounty_id> <sol> <description_uri> (admins)\n\
+            /bounty approve <bounty_id> (admins)\n\
+            /bounty cancel <bounty_id> (admins)"
+            .to_string(),
+    };
+    bot.send_localized(&vocab, msg.chat.id, response).await?;
+    Ok(())
+}
+
+// Bounties still in play, oldest first; paid and cancelled ones are left out
+fn bounty_board(bounties: &[solana_dao::Bounty]) -> String {
+    let live: Vec<_> = bounties
+        .iter()
+        .filter(|bounty| {
+            !matches!(
+                bounty.status,
+                solana_dao::BountyStatus::Paid | solana_dao::BountyStatus::Cancelled
+            )
+        })
+        .collect();
+    if live.is_empty() {
+        return "No open bounties. Admins post one with /bounty post <bounty_id> <sol> <description_uri>."
+            .to_string();
+    }
+    let mut board = "🪙 <b>Bounty board:</b>\n".to_string();
+    for bounty in live {
+        let state = match (bounty.status, bounty.hunter) {
+            (solana_dao::BountyStatus::Open, _) => "open".to_string(),
+            (status, Some(hunter)) => {
+                let key = hunter.to_string();
+                let action = if status == solana_dao::BountyStatus::Submitted {
+                    "submitted by"
+                } else {
+                    "claimed by"
+                };
+                format!(
+                    "{} <code>{}…{}</code>",
+                    action,
+                    &key[..4],
+                    &key[key.len() - 4..]
+                )
+            }
+            (_, None) => "claimed".to_string(),
+        };
+        board.push_str(&format!(
+            "• <code>{}</code> - {} SOL, {} - {}\n",
+            bounty.bounty_id,
+            bounty.reward as f64 / LAMPORTS_PER_SOL as f64,
+            state,
+            bounty.description_uri
+        ));
+    }
+    board
+}
+
+fn dues_policy_summary(dues: &solana_dao::DuesPolicy) -> String {
+    let amount = if dues.mint == solana_dao::NATIVE_MINT {
+        format!("{} SOL", dues.amount as f64 / LAMPORTS_PER_SOL as f64)
+    } else {
+        format!("{} of <code>{}</code>", dues.amount, dues.mint)
+    };
+    format!(
+        "{} every {} days, {} days' grace{}",
+        amount,
+        dues.period_secs / 86_400,
+        dues.grace_secs / 86_400,
+        if dues.required_for_voting {
+            "; needed to vote"
+        } else {
+            ""
+        }
+    )
+}
+
+// A wallet's paid-up date under the chat's dues
+async fn dues_status(
+    state: &BotState,
+    group_pda: &Pubkey,
+    dues: &solana_dao::DuesPolicy,
+    wallet: &Pubkey,
+) -> String {
+    let record = state
+        .program
+        .rpc()
+        .get_account(&dues_record_pda(group_pda, wallet))
+        .await
+        .ok()
+        .and_then(|account| solana_dao::DuesRecord::deserialize(&mut &account.data[8..]).ok());
+    let status = match record {
+        None => "You haven't paid dues yet. Pay with /dues pay [periods].".to_string(),
+        Some(record) => {
+            let paid_until = DateTime::<Utc>::from_timestamp(record.paid_until, 0)
+                .map(|time| time.format("%Y-%m-%d %H:%M UTC").to_string())
+                .unwrap_or_else(|| record.paid_until.to_string());
+            if record.paid_until >= Utc::now().timestamp() {
+                format!("✅ Paid up until {}", paid_until)
+            } else {
+                format!(
+                    "⚠️ Overdue since {}. Pay with /dues pay [periods].",
+                    paid_until
+                )
+            }
+        }
+    };
+    format!("💳 Dues: {}\n{}", dues_policy_summary(dues), status)
+}
+
+// Members past due, split by whether they are still within the grace period
+fn overdue_dues_report(
+    overdue: &[solana_dao::DuesRecord],
+    dues: &solana_dao::DuesPolicy,
+    now: i64,
+) -> String {
+    if overdue.is_empty() {
+        return "✅ Everyone who has paid dues is up to date.".to_string();
+    }
+    let mut report = format!("⚠️ <b>{} members are behind on dues:</b>\n", overdue.len());
+    for record in overdue {
+        let key = record.member.to_string();
+        let days_late = (now - record.paid_until) / 86_400;
+        let state = if now <= record.paid_until + dues.grace_secs {
+            "in grace"
+        } else if dues.required_for_voting {
+            "can't vote"
+        } else {
+            "lapsed"
+        };
+        report.push_str(&format!(
+            "• <code>{}…{}</code> - {} days late ({})\n",
+            &key[..4],
+            &key[key.len() - 4..],
+            days_late,
+            state
+        ));
+    }
+    report
+}
+
+// A wallet's SOL deposit in the registrar and from when it counts
+async fn deposit_summary(state: &BotState, registrar_pda: &Pubkey, wallet: &Pubkey) -> String {
+    let deposit_pda = voter_deposit_pda(registrar_pda, wallet);
+    let accounts = match state
+        .program
+        .rpc()
+        .get_multiple_accounts(&[*registrar_pda, deposit_pda])
+        .await
+    {
+        Ok(accounts) => accounts,
+        Err(e) => return format!("❌ Failed to fetch your deposit: {}", e),
+    };
+    let registrar = accounts[0].as_ref().and_then(|account| {
+        solana_dao::DepositRegistrar::deserialize(&mut &account.data[8..]).ok()
+    });
+    let Some(registrar) = registrar else {
+        return "Deposit voting isn't set up in this chat. Admins can run /deposit setup <holding_days>."
+            .to_string();
+    };
+    let deposit = accounts[1]
+        .as_ref()
+        .and_then(|account| solana_dao::VoterDeposit::deserialize(&mut &account.data[8..]).ok())
+        .filter(|deposit| deposit.amount > 0);
+    match deposit {
+        None => "You have no SOL deposited. Lock some with /deposit <sol>.".to_string(),
+        Some(deposit) => {
+            let counts_from = deposit.deposited_at + registrar.min_holding_secs;
+            let counts_from = DateTime::<Utc>::from_timestamp(counts_from, 0)
+                .map(|time| time.format("%Y-%m-%d %H:%M UTC").to_string())
+                .unwrap_or_else(|| counts_from.to_string());
+            format!(
+                "💰 Deposited: {} SOL\nCounts on deposit-weighted proposals created from {}",
+                deposit.amount as f64 / LAMPORTS_PER_SOL as f64,
+                counts_from
+            )
+        }
+    }
+}
+
+async fn handle_calendar(bot: Bot, msg: Message, state: BotState) -> ResponseResult<()> {
+    let vocab = chat_vocabulary(&state, msg.chat.id).await;
+    let group_id = format!("tg_{}", msg.chat.id.0.abs());
+    let proposals = match get_group_proposals(&state, &group_id).await {
+        Ok(proposals) => proposals,
+        Err(e) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                format!("❌ Failed to fetch proposals: {}", e),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let deadlines = calendar::upcoming_deadlines(
+        &proposals,
+        Utc::now().timestamp(),
+        calendar::CALENDAR_HORIZON_SECS,
+    );
+    let mut response = if deadlines.is_empty() {
+        "📅 No voting deadlines in the next two weeks.\n".to_string()
+    } else {
+        let mut response = "📅 <b>Governance calendar (next two weeks)</b>\n\n".to_string();
+        for deadline in &deadlines {
+            let (icon, label) = match deadline.kind {
+                calendar::DeadlineKind::Opens => ("🗳️", "Voting opens"),
+                calendar::DeadlineKind::Closes => ("🔒", "Voting closes"),
+            };
+            response.push_str(&format!(
+                "{} {} — {}: <b>{}</b> (<code>{}</code>)\n",
+                icon,
+                DateTime::<Utc>::from_timestamp(deadline.at, 0)
+                    .map(|dt| dt.format("%a %Y-%m-%d %H:%M UTC").to_string())
+                    .unwrap_or_else(|| "Unknown time".to_string()),
+                label,
+                html_escape(&deadline.proposal.title),
+                html_escape(&deadline.proposal.proposal_id)
+            ));
+        }
+        response
+    };
+    if let Some(base_url) = &state.webapp_url {
+        response.push_str(&format!(
+            "\n🔗 Subscribe in your calendar app: {}/api/chats/{}/calendar.ics",
+            base_url.trim_end_matches('/'),
+            msg.chat.id.0
+        ));
+    }
+
+    bot.send_localized(&vocab, msg.chat.id, response)
+        .parse_mode(teloxide::types::ParseMode::Html)
+        .await?;
+    Ok(())
+}
+
+type VoteArgs = (String, u8, Option<Vec<u64>>, Option<String>);
+
+// Split "/vote" arguments into proposal id, choice, split-weight allocations and
+// optional conflict-of-interest disclosure ("coi" followed by an optional note). A
+// comma-separated list of weights is a split vote, named after its largest allocation.
+fn parse_vote_args(args: &str) -> Option<VoteArgs> {
+    let mut parts = args.split_whitespace();
+    let proposal_id = parts.next()?.to_string();
+    let (choice, allocations) = match parts.next()? {
+        arg if arg.contains(',') => {
+            let allocations = arg
+                .split(',')
+                .map(|amount| amount.trim().parse().ok())
+                .collect::<Option<Vec<u64>>>()?;
+            let largest = allocations.iter().max()?;
+            let choice = allocations.iter().position(|amount| amount == largest)?;
+            (u8::try_from(choice).ok()?, Some(allocations))
+        }
+        arg => (arg.parse().ok()?, None),
+    };
+    let disclosure = match parts.next() {
+        None => None,
+        Some(flag) if flag.eq_ignore_ascii_case("coi") => Some(parts.collect::<Vec<_>>().join(" ")),
+        Some(_) => return None,
+    };
+    Some((proposal_id, choice, allocations, disclosure))
+}
+
+async fn handle_vote(
+    bot: Bot,
+    msg: Message,
+    proposal_id: String,
+    choice: u8,
+    allocations: Option<Vec<u64>>,
+    disclosure: Option<String>,
+    state: BotState,
+) -> ResponseResult<()> {
+    let vocab = chat_vocabulary(&state, msg.chat.id).await;
+    let user_id = match msg.from() {
+        Some(user) => user.id,
+        None => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                "❌ Unable to identify user. Please try again.",
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+    let telegram_id = user_id.0 as i64;
+    let group_id = format!("tg_{}", msg.chat.id.0.abs());
+
+    // Ensure user has an account
+    let user_keypair = match ensure_user_account(&state, telegram_id).await {
+        Ok(keypair) => keypair,
+        Err(e) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                format!(
+                    "❌ Failed to access your account: {}. Please try /login first.",
+                    e
+                ),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+
+    match vote_on_proposal(
+        &state,
+        telegram_id,
+        &group_id,
+        &proposal_id,
+        choice,
+        allocations.as_deref(),
+        disclosure.as_deref(),
+        user_keypair.pubkey(),
+    )
+    .await
+    {
+        Ok(signature) => {
+            let your_choice = match &allocations {
+                Some(allocations) => allocations
+                    .iter()
+                    .enumerate()
+                    .map(|(i, amount)| format!("{}: {}", i, amount))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                None => choice.to_string(),
+            };
+            let response = format!(
+                "✅ Vote cast successfully!\n\n\
+                🗳️ Proposal: {}\n\
+                ✔️ Your choice: {}\n\
+                {}\
+                👤 Wallet: {}\n\
+                🔗 Transaction: {}",
+                proposal_id,
+                your_choice,
+                disclosure
+                    .as_deref()
+                    .map(|note| format!("⚠️ Conflict of interest disclosed{}\n", note_suffix(note)))
+                    .unwrap_or_default(),
+                user_keypair.pubkey(),
+                state.network.tx_url(signature)
+            );
+            bot.send_localized(&vocab, msg.chat.id, response).await?;
+        }
+        Err(e) => {
+            bot.send_localized(&vocab, msg.chat.id, vote_error_text(&e))
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+// What to tell a voter whose vote failed, shared by /vote and the voting buttons
+fn vote_error_text(e: &anyhow::Error) -> String {
+    let error_str = e.to_string();
+    if error_str.contains("AlreadyVoted") || error_str.contains("0x177a") {
+        "❌ You have already voted on this proposal!\n\n\
+        🗳️ Each user can only vote once per proposal.\n\
+        💡 Use /results to see the current results."
+            .to_string()
+    } else if error_str.contains("VotingNotActive") {
+        "❌ Voting is not currently active for this proposal.\n\n\
+        ⏰ The voting period may have ended or not started yet.\n\
+        💡 Use /results to check the proposal status."
+            .to_string()
+    } else if error_str.contains("InvalidSplitAllocation") {
+        "❌ This vote doesn't fit the proposal's voting mode!\n\n\
+        ⚖️ Split-weight proposals take a weight for every choice, e.g. 600,300,100, \
+        adding up to at most your voting weight; other proposals take one choice number.\n\
+        💡 Use /eligibility to see your weight."
+            .to_string()
+    } else if error_str.contains("InvalidChoice") {
+        "❌ Invalid choice selected!\n\n\
+        🗳️ Please select a valid choice number for this proposal.\n\
+        💡 Use /listproposals to see available choices."
+            .to_string()
+    } else if error_str.contains("You don't have enough SOL balance") {
+        "❌ Insufficient SOL balance!\n\n\
+        💰 You need at least 0.001 SOL for transaction fees.\n\
+        💡 Use /fundaccount to add SOL to your account."
+            .to_string()
+    } else if error_str.contains("ProgramPaused") {
+        "⏸️ Voting is temporarily paused while the DAO program is under maintenance.\n\n\
+        💡 Please try again later."
+            .to_string()
+    } else {
+        format!("❌ Failed to vote: {}", e)
+    }
+}
+
+async fn handle_shadow_vote(
+    bot: Bot,
+    msg: Message,
+    proposal_id: String,
+    choice: u8,
+    state: BotState,
+) -> ResponseResult<()> {
+    let vocab = chat_vocabulary(&state, msg.chat.id).await;
+    let Some(user) = msg.from() else {
+        bot.send_localized(
+            &vocab,
+            msg.chat.id,
+            "❌ Unable to identify user. Please try again.",
+        )
+        .await?;
+        return Ok(());
+    };
+    let group_id = format!("tg_{}", msg.chat.id.0.abs());
+
+    let user_keypair = match ensure_user_account(&state, user.id.0 as i64).await {
+        Ok(keypair) => keypair,
+        Err(e) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                format!(
+                    "❌ Failed to access your account: {}. Please try /login first.",
+                    e
+                ),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let instruction =
+        build_shadow_vote_instruction(&group_id, &proposal_id, choice, user_keypair.pubkey());
+    let result = send_instructions(
+        &state,
+        &[instruction],
+        &user_keypair.pubkey(),
+        &[&user_keypair],
+    )
+    .await;
+
+    let response = match result {
+        Ok(signature) => format!(
+            "👤 Shadow vote recorded!\n\n\
+            🗳️ Proposal: {}\n\
+            ✔️ Your choice: {}\n\
+            💡 Shadow votes show members where prospective members stand; \
+            they don't count toward the result.\n\
+            🔗 Transaction: {}",
+            proposal_id,
+            choice,
+            state.network.tx_url(signature)
+        ),
+        Err(e) => {
+            let error_str = e.to_string();
+            if error_str.contains("ShadowVoterIsMember") {
+                "❌ You are a member of this group, so your vote counts in full.\n\n\
+                💡 Use /vote instead."
+                    .to_string()
+            } else if error_str.contains("already in use") {
+                "❌ You have already cast a shadow vote on this proposal!".to_string()
+            } else if error_str.contains("VotingNotActive") {
+                "❌ Voting is not currently active for this proposal.".to_string()
+            } else if error_str.contains("InvalidChoice") {
+                "❌ Invalid choice selected!\n\n\
+                💡 Use /listproposals to see available choices."
+                    .to_string()
+            } else if error_str.contains("ProgramPaused") {
+                "⏸️ Voting is temporarily paused while the DAO program is under maintenance.\n\n\
+                💡 Please try again later."
+                    .to_string()
+            } else {
+                format!("❌ Failed to cast shadow vote: {}", e)
+            }
+        }
+    };
+    bot.send_localized(&vocab, msg.chat.id, response).await?;
+
+    Ok(())
+}
+
+async fn handle_results(
+    bot: Bot,
+    msg: Message,
+    proposal_id: String,
+    state: BotState,
+) -> ResponseResult<()> {
+    let vocab = chat_vocabulary(&state, msg.chat.id).await;
+    let group_id = format!("tg_{}", msg.chat.id.0.abs());
+    match get_proposal_results(&state, &group_id, &proposal_id).await {
+        Ok(proposal) => {
+            let language = reader_language(&state, &msg).await;
+            let response = results_text(
+                &state,
+                msg.chat.id,
+                &group_id,
+                &proposal_id,
+                &proposal,
+                language.as_deref(),
+            )
+            .await;
+            if let Err(e) =
+                chart::send_results(&bot, &vocab, msg.chat.id, &proposal, response, None).await
+            {
+                log::warn!("Failed to send the results of {}: {}", proposal_id, e);
+            }
+        }
+        Err(e) => {
+            let error_msg = format!("❌ Failed to get results: {}", e);
+            bot.send_localized(&vocab, msg.chat.id, error_msg).await?;
+        }
+    }
+    Ok(())
+}
+
+// The /results report for `proposal`, in `language` when a translator is configured.
+// Individual voters are listed only in chats whose vote privacy is public.
+async fn results_text(
+    state: &BotState,
+    chat_id: ChatId,
+    group_id: &str,
+    proposal_id: &str,
+    proposal: &solana_dao::Proposal,
+    language: Option<&str>,
+) -> String {
+    // Summed as u128: lamport-weighted tallies can overflow u64 when added together
+    let total_votes: u128 = proposal.choice_votes.iter().map(|&v| v as u128).sum();
+    let text = translation::display_proposal(state.translator.as_deref(), proposal, language).await;
+
+    let choice_details = fetch_choice_details(state, group_id, proposal_id).await;
+    let mut response = format!(
+        "📊 <b>Results for: {}</b>\n\n\
+        📝 {}\n{}\
+        🗳️ Total votes: {}\n\
+        👥 Total voters: {}\n\n\
+        <b>Results:</b>\n",
+        html_escape(&text.title),
+        html_escape(&text.description),
+        [
+            metadata_line(proposal.metadata_uri.as_deref()),
+            kind_line(&proposal.kind),
+            emergency_line(proposal.emergency),
+            split_voting_line(proposal.split_voting),
+            slot_window_line(proposal.slot_window),
+            weight_decay_line(proposal.weight_decay_bps),
+            council_chamber_line(proposal.council_chamber.as_ref(), &text.choices),
+            outcome_line(proposal, &text.choices),
+            sponsor_line(proposal),
+        ]
+        .concat(),
+        total_votes,
+        proposal.ballots().count()
+    );
+
+    for (i, (choice, votes)) in text
+        .choices
+        .iter()
+        .zip(proposal.choice_votes.iter())
+        .enumerate()
+    {
+        let percentage = if total_votes > 0 {
+            (*votes as f64 / total_votes as f64) * 100.0
+        } else {
+            0.0
+        };
+        response.push_str(&format!(
+            "{}. {} - {} votes ({:.1}%)\n",
+            i,
+            html_escape(choice),
+            votes,
+            percentage
+        ));
+        if let Some(detail) = choice_details.get(i) {
+            response.push_str(&choice_detail_line(detail));
+        }
+    }
+
+    // Non-members' shadow votes, shown apart so they aren't mistaken for the result
+    let shadow_total: u64 = proposal.shadow_votes.iter().map(|&v| v as u64).sum();
+    if shadow_total > 0 {
+        response.push_str(&format!(
+            "\n👤 <b>Shadow votes from non-members ({}, not counted):</b>\n",
+            shadow_total
+        ));
+        for (i, (choice, votes)) in text
+            .choices
+            .iter()
+            .zip(proposal.shadow_votes.iter())
+            .enumerate()
+        {
+            response.push_str(&format!("{}. {} - {}\n", i, html_escape(choice), votes));
+        }
+    }
+
+    let status = match proposal.status {
+        solana_dao::ProposalStatus::Draft => "📝 Draft: voting has not been opened yet",
+        solana_dao::ProposalStatus::Succeeded => "✅ Voting has ended: the proposal passed",
+        solana_dao::ProposalStatus::Defeated => "❌ Voting has ended: the proposal was defeated",
+        solana_dao::ProposalStatus::Cancelled => "❌ The proposal was cancelled",
+        solana_dao::ProposalStatus::Executed => "✅ The proposal passed and was executed",
+        solana_dao::ProposalStatus::Active if Utc::now().timestamp() > proposal.voting_end => {
+            "🔒 Voting has ended"
+        }
+        solana_dao::ProposalStatus::Active => "🗳️ Voting is still active",
+    };
+    response.push_str(&format!("\n{}", status));
+    if matches!(
+        proposal.status,
+        solana_dao::ProposalStatus::Succeeded
+            | solana_dao::ProposalStatus::Defeated
+            | solana_dao::ProposalStatus::Executed
+    ) {
+        response.push_str(&format!(
+            "\n🔏 Result certificate: <code>{}</code>",
+            proposal_result_pda(group_id, proposal_id)
+        ));
+    }
+    if let Ok(account) = state
+        .program
+        .rpc()
+        .get_account(&voter_lottery_pda(group_id, proposal_id))
+        .await
+    {
+        if let Ok(lottery) = solana_dao::VoterLottery::deserialize(&mut &account.data[8..]) {
+            response.push_str(&lottery_line(&lottery));
+        }
+    }
+    if matches!(proposal.kind, solana_dao::ProposalKind::Grant(_)) {
+        if let Ok(account) = state
+            .program
+            .rpc()
+            .get_account(&grant_pda(group_id, proposal_id))
+            .await
+        {
+            if let Ok(grant) = solana_dao::Grant::deserialize(&mut &account.data[8..]) {
+                response.push_str(&grant_line(&grant));
+            }
+        }
+    }
+    if matches!(proposal.kind, solana_dao::ProposalKind::Stream(_)) {
+        if let Ok(account) = state
+            .program
+            .rpc()
+            .get_account(&stream_pda(group_id, proposal_id))
+            .await
+        {
+            if let Ok(stream) = solana_dao::Stream::deserialize(&mut &account.data[8..]) {
+                response.push_str(&stream_line(&stream, Utc::now().timestamp()));
+            }
+        }
+    }
+
+    let privacy = state.settings.get(chat_id.0).await.vote_privacy;
+    if privacy == settings::VotePrivacy::Public && proposal.ballots().next().is_some() {
+        response.push_str("\n\n<b>Voters:</b>\n");
+        for voter in proposal.ballots() {
+            let voter_key = voter.voter.to_string();
+            response.push_str(&format!(
+                "• <code>{}…{}</code> → {} ({} weight){}\n",
+                &voter_key[..4],
+                &voter_key[voter_key.len() - 4..],
+                text.choices
+                    .get(voter.choice as usize)
+                    .map(|c| html_escape(c))
+                    .unwrap_or_else(|| voter.choice.to_string()),
+                voter.vote_weight,
+                voter
+                    .disclosure
+                    .as_deref()
+                    .map(|note| format!(" ⚠️ COI{}", html_escape(&note_suffix(note))))
+                    .unwrap_or_default()
+            ));
+        }
+    } else {
+        // Private results still surface disclosures, just not who made them
+        let disclosures: Vec<_> = proposal
+            .ballots()
+            .filter_map(|v| v.disclosure.as_deref().map(|note| (v.choice, note)))
+            .collect();
+        if !disclosures.is_empty() {
+            response.push_str(&format!(
+                "\n\n⚠️ <b>Conflict-of-interest disclosures: {}</b>\n",
+                disclosures.len()
+            ));
+            for (choice, note) in disclosures {
+                response.push_str(&format!(
+                    "• Voted {}{}\n",
+                    text.choices
+                        .get(choice as usize)
+                        .map(|c| html_escape(c))
+                        .unwrap_or_else(|| choice.to_string()),
+                    html_escape(&note_suffix(note))
+                ));
+            }
+        }
+    }
+    response
+}
+
+// Split a trailing metadata URI (ipfs://, ar:// or https://) off command arguments
+fn split_metadata_uri(args: &str) -> (String, Option<String>) {
+    let args = args.trim_end();
+    if let Some((rest, last)) = args.rsplit_once(char::is_whitespace) {
+        let last = last.trim_matches('"');
+        if solana_dao::METADATA_URI_SCHEMES
+            .iter()
+            .any(|scheme| last.starts_with(scheme))
+        {
+            return (rest.to_string(), Some(last.to_string()));
+        }
+    }
+    (args.to_string(), None)
+}
+
+// ": <note>" after a disclosure flag, or nothing for a flag without a note
+fn note_suffix(note: &str) -> String {
+    if note.is_empty() {
+        String::new()
+    } else {
+        format!(": {}", note)
+    }
+}
+
+// "📎 <uri>" line for HTML messages, or nothing when there is no metadata URI
+fn metadata_line(metadata_uri: Option<&str>) -> String {
+    metadata_uri
+        .map(|uri| format!("📎 {}\n", html_escape(uri)))
+        .unwrap_or_default()
+}
+
+// What a typed proposal does if it passes, empty for text proposals
+fn kind_line(kind: &solana_dao::ProposalKind) -> String {
+    let sol = |lamports: u64| lamports as f64 / LAMPORTS_PER_SOL as f64;
+    match kind {
+        solana_dao::ProposalKind::Text => String::new(),
+        solana_dao::ProposalKind::TreasuryTransfer(transfer) => format!(
+            "💸 If choice 0 wins: pays {} SOL from the treasury to <code>{}</code>\n",
+            sol(transfer.lamports),
+            transfer.recipient
+        ),
+        solana_dao::ProposalKind::ConfigChange(change) => {
+            let mut changes = Vec::new();
+            if let Some(fee) = change.proposal_fee_lamports {
+                changes.push(format!("proposal fee {} SOL", sol(fee)));
+            }
+            if let Some(enabled) = change.members_only_voting {
+                let state = if enabled { "on" } else { "off" };
+                changes.push(format!("members-only voting {}", state));
+            }
+            match change.max_members {
+                Some(0) => changes.push("no member cap".to_string()),
+                Some(cap) => changes.push(format!("member cap {}", cap)),
+                None => {}
+            }
+            match change.visibility {
+                Some(solana_dao::GroupVisibility::Public) => changes.push("public".to_string()),
+                Some(solana_dao::GroupVisibility::Private) => changes.push("private".to_string()),
+                None => {}
+            }
+            if let Some(governance) = change.governance {
+                changes.push(governance_summary(&governance));
+            }
+            format!("⚙️ If choice 0 wins: {}\n", changes.join(", "))
+        }
+        solana_dao::ProposalKind::MembershipChange(change) => {
+            let action = match change.action {
+                solana_dao::MembershipAction::Add => "adds",
+                solana_dao::MembershipAction::Remove => "removes",
+            };
+            format!(
+                "👥 If choice 0 wins: {} <code>{}</code> as a member\n",
+                action, change.member
+            )
+        }
+        solana_dao::ProposalKind::Election(election) => format!(
+            "🏛️ Council election: the top {} choices take a seat\n",
+            election.seats
+        ),
+        solana_dao::ProposalKind::Grant(terms) => {
+            let tranches: Vec<String> = terms
+                .milestones
+                .iter()
+                .map(|&amount| sol(amount).to_string())
+                .collect();
+            let approver = match terms.approval {
+                solana_dao::MilestoneApproval::Council => "the council",
+                solana_dao::MilestoneApproval::MemberVote => "a majority of members",
+            };
+            format!(
+                "🎯 If choice 0 wins: a grant to <code>{}</code> paid in tranches of {} SOL, each released once {} approves its milestone\n",
+                terms.recipient,
+                tranches.join(" + "),
+                approver
+            )
+        }
+        solana_dao::ProposalKind::Stream(terms) => {
+            let date = |timestamp: i64| {
+                DateTime::<Utc>::from_timestamp(timestamp, 0)
+                    .map(|time| time.format("%Y-%m-%d").to_string())
+                    .unwrap_or_else(|| timestamp.to_string())
+            };
+            format!(
+                "🚰 If choice 0 wins: streams {} SOL from the treasury to <code>{}</code> from {} to {}, withdrawable from {}\n",
+                sol(terms.amount),
+                terms.recipient,
+                date(terms.start),
+                date(terms.end),
+                date(terms.cliff)
+            )
+        }
+        solana_dao::ProposalKind::Bounty(terms) => format!(
+            "🪙 If choice 0 wins: posts bounty <code>{}</code> worth {} SOL on the group's board (/bounty)\n",
+            terms.bounty_id,
+            sol(terms.reward)
+        ),
+        solana_dao::ProposalKind::TreasuryRecovery(recovery) => format!(
+            "🆘 Treasury recovery: if choice 0 wins at least 80% of the votes with half the members voting, the whole treasury goes to <code>{}</code>\n",
+            recovery.recovery
+        ),
+        solana_dao::ProposalKind::TreasuryStake(solana_dao::StakeAction::Delegate {
+            vote_account,
+            lamports,
+        }) => format!(
+            "🥩 If choice 0 wins: stake {} SOL of the treasury with validator <code>{}</code>\n",
+            sol(*lamports),
+            vote_account
+        ),
+        solana_dao::ProposalKind::TreasuryStake(solana_dao::StakeAction::Deactivate {
+            stake_account,
+        }) => format!(
+            "🥩 If choice 0 wins: unstake treasury stake account <code>{}</code>\n",
+            stake_account
+        ),
+        solana_dao::ProposalKind::TreasuryStake(solana_dao::StakeAction::Withdraw {
+            stake_account,
+        }) => format!(
+            "🥩 If choice 0 wins: return treasury stake account <code>{}</code> to the treasury\n",
+            stake_account
+        ),
+        solana_dao::ProposalKind::TokenDistribution(terms) => format!(
+            "🪂 If choice 0 wins: up to {} of token <code>{}</code> (base units) {} to a list of recipients, who each claim their share\n",
+            terms.total_amount,
+            terms.mint,
+            match terms.source {
+                solana_dao::DistributionSource::Mint => "minted",
+                solana_dao::DistributionSource::Treasury => "paid from the treasury",
+            }
+        ),
+        solana_dao::ProposalKind::TreasurySwap(terms) => format!(
+            "🔄 If choice 0 wins: swap up to {} of token <code>{}</code> for at least {} of token <code>{}</code> through <code>{}</code> (base units)\n",
+            terms.amount_in, terms.input_mint, terms.min_amount_out, terms.output_mint, terms.swap_program
+        ),
+    }
+}
+
+// Council-raised emergencies pass only with a supermajority, empty otherwise
+fn emergency_line(emergency: bool) -> String {
+    if emergency {
+        "🚨 Emergency proposal: passes only with at least 75% of the votes\n".to_string()
+    } else {
+        String::new()
+    }
+}
+
+// How to vote on split-weight proposals, empty otherwise
+fn split_voting_line(split_voting: bool) -> String {
+    if split_voting {
+        "⚖️ Split-weight voting: spread your weight over the choices, e.g. /vote &lt;id&gt; 600,300,100\n"
+            .to_string()
+    } else {
+        String::new()
+    }
+}
+
+fn weight_decay_line(weight_decay_bps: u16) -> String {
+    if weight_decay_bps > 0 {
+        format!(
+            "⏳ Early votes count more: a vote loses up to {}% of its weight by the end\n",
+            weight_decay_bps as f64 / 100.0
+        )
+    } else {
+        String::new()
+    }
+}
+
+// The council chamber's tally on bicameral proposals, empty otherwise
+fn council_chamber_line(
+    chamber: Option<&solana_dao::CouncilChamber>,
+    choices: &[String],
+) -> String {
+    let Some(chamber) = chamber else {
+        return String::new();
+    };
+    let tally = choices
+        .iter()
+        .zip(&chamber.choice_votes)
+        .map(|(choice, votes)| format!("{} {}", html_escape(choice), votes))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        "🏛️ Bicameral: the winner also needs {} of {} council votes ({} voted: {})\n",
+        chamber.threshold,
+        chamber.members.len(),
+        chamber.voted.count_ones(),
+        tally
+    )
+}
+
+// The winner recorded at finalization and how a tie for first went, empty while
+// voting runs untied
+fn outcome_line(proposal: &solana_dao::Proposal, choices: &[String]) -> String {
+    let choice = |index: u8| {
+        choices
+            .get(index as usize)
+            .map(|choice| html_escape(choice))
+            .unwrap_or_else(|| format!("choice {}", index))
+    };
+    match (proposal.winner, proposal.tied) {
+        (Some(winner), false) => format!("🏆 Winner: {}\n", choice(winner)),
+        (Some(winner), true) => format!(
+            "⚖️ Tied for first; the tie-break picked {}\n",
+            choice(winner)
+        ),
+        (None, true) if proposal.status == solana_dao::ProposalStatus::Active => {
+            "⚖️ Tied for first: waiting on the tie-break (a revote or the council)\n".to_string()
+        }
+        (None, true) => {
+            "⚖️ Tied for first: the tie left the proposal without a winner\n".to_string()
+        }
+        (None, false) => String::new(),
+    }
+}
+
+// "   ↳ description · link" under a choice in /results, or nothing without details
+fn choice_detail_line(detail: &solana_dao::ChoiceDetail) -> String {
+    let parts: Vec<String> = [detail.description.as_deref(), detail.uri.as_deref()]
+        .into_iter()
+        .flatten()
+        .map(html_escape)
+        .collect();
+    if parts.is_empty() {
+        String::new()
+    } else {
+        format!("   ↳ {}\n", parts.join(" · "))
+    }
+}
+
+// Milestones approved and funds released so far on an executed grant
+fn grant_line(grant: &solana_dao::Grant) -> String {
+    let sol = |lamports: u64| lamports as f64 / LAMPORTS_PER_SOL as f64;
+    let approved = grant
+        .milestones
+        .iter()
+        .filter(|milestone| milestone.approved_at > 0)
+        .count();
+    let total: u64 = grant
+        .milestones
+        .iter()
+        .map(|milestone| milestone.amount)
+        .sum();
+    format!(
+        "\n🎯 Grant: {}/{} milestones approved, {} of {} SOL released",
+        approved,
+        grant.milestones.len(),
+        sol(grant.released),
+        sol(total)
+    )
+}
+
+// How much of an executed stream has vested and been withdrawn at `now`
+fn stream_line(stream: &solana_dao::Stream, now: i64) -> String {
+    let sol = |lamports: u64| lamports as f64 / LAMPORTS_PER_SOL as f64;
+    format!(
+        "\n🚰 Stream: {} of {} SOL vested, {} SOL withdrawn",
+        sol(stream.vested_at(now)),
+        sol(stream.amount),
+        sol(stream.withdrawn)
+    )
+}
+
+// Prize on offer before the draw, then who won it
+fn lottery_line(lottery: &solana_dao::VoterLottery) -> String {
+    let sol = |lamports: u64| lamports as f64 / LAMPORTS_PER_SOL as f64;
+    if !lottery.drawn {
+        return format!(
+            "\n🎟️ Voter lottery: {} SOL shared by {} voters drawn when the proposal is finalized",
+            sol(lottery.total_amount),
+            lottery.winner_count
+        );
+    }
+    if lottery.winners.is_empty() {
+        return "\n🎟️ Voter lottery: nobody voted, so there were no winners".to_string();
+    }
+    let winners: Vec<String> = lottery
+        .winners
+        .iter()
+        .map(|winner| {
+            let key = winner.to_string();
+            format!("<code>{}…{}</code>", &key[..4], &key[key.len() - 4..])
+        })
+        .collect();
+    format!(
+        "\n🎟️ Voter lottery winners ({} SOL each): {}",
+        sol(lottery.prize),
+        winners.join(", ")
+    )
+}
+
+fn slot_window_line(slot_window: Option<solana_dao::SlotWindow>) -> String {
+    match slot_window {
+        Some(window) => format!(
+            "⏱️ Voting runs from slot {} to slot {}; times shown are estimates\n",
+            window.start_slot, window.end_slot
+        ),
+        None => String::new(),
+    }
+}
+
+// Quorums, approval threshold and voting window bounds in one line
+fn governance_summary(governance: &solana_dao::GovernanceConfig) -> String {
+    let percent = |bps: u16| bps as f64 / 100.0;
+    let hours = |secs: i64| secs as f64 / 3600.0;
+    let max_duration = if governance.max_voting_duration == 0 {
+        "no limit".to_string()
+    } else {
+        format!("{}h", hours(governance.max_voting_duration))
+    };
+    let quorum = match (governance.quorum_bps, governance.weight_quorum_bps) {
+        (members, 0) => format!("{}% of members", percent(members)),
+        (0, weight) => format!("{}% of voting weight", percent(weight)),
+        (members, weight) => format!(
+            "{}% of members and {}% of voting weight",
+            percent(members),
+            percent(weight)
+        ),
+    };
+    format!(
+        "quorum {}, approval {}% of votes, voting {}h to {}",
+        quorum,
+        percent(governance.approval_threshold_bps),
+        hours(governance.min_voting_duration),
+        max_duration
+    )
+}
+
+// Helper function to escape HTML special characters
+fn html_escape(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '&' => "&amp;".to_string(),
+            '<' => "&lt;".to_string(),
+            '>' => "&gt;".to_string(),
+            '"' => "&quot;".to_string(),
+            '\'' => "&#x27;".to_string(),
+            _ => c.to_string(),
+        })
+        .collect()
+}
+
+async fn handle_login(bot: Bot, msg: Message, state: BotState) -> ResponseResult<()> {
+    let vocab = chat_vocabulary(&state, msg.chat.id).await;
+    let user_id = match msg.from() {
+        Some(user) => user.id,
+        None => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                "❌ Unable to identify user. Please try again.",
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+    let telegram_id = user_id.0 as i64;
+    let user = msg.from();
+
+    let username = user.and_then(|u| u.username.as_ref());
+
+    match create_user_account(&state, telegram_id).await {
+        Ok(keypair) => {
+            let verified = match login_with_signature(&state, telegram_id, &keypair).await {
+                Ok(signature) => format!("🔒 Login verified on-chain: {}\n\n", signature),
+                Err(e) => {
+                    log::warn!("Signed login failed for {}: {}", telegram_id, e);
+                    "⚠️ Could not record a verified login on-chain.\n\n".to_string()
+                }
+            };
+            let response = format!(
+                "✅ Account ready!\n\n\
+                👤 Telegram username: {}\n\
+                🔑 Wallet Address: {}\n\
+                {}\
+                You can now participate in DAO voting!",
+                username
+                    .map(|s| s.to_string())
+                    .unwrap_or_else(|| "anonymous".to_string()),
+                keypair.pubkey(),
+                verified
+            );
+            bot.send_localized(&vocab, msg.chat.id, response).await?;
+        }
+        Err(e) => {
+            let error_msg = format!("❌ Failed to create/access account: {}", e);
+            bot.send_localized(&vocab, msg.chat.id, error_msg).await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn handle_account(bot: Bot, msg: Message, state: BotState) -> ResponseResult<()> {
+    let vocab = chat_vocabulary(&state, msg.chat.id).await;
+    let user_id = match msg.from() {
+        Some(user) => user.id,
+        None => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                "❌ Unable to identify user. Please try again.",
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+    let telegram_id = user_id.0 as i64;
+
+    let user = msg.from();
+    let username = user.and_then(|u| u.username.as_ref());
+
+    // Check if user has an account
+    let seed_opt = stored_seed(&state, telegram_id).await.unwrap_or_else(|e| {
+        log::error!("Failed to read the keystore: {}", e);
+        None
+    });
+
+    match seed_opt {
+        Some(seed) => {
+            let keypair = Keypair::new_from_array(seed);
+            let wallet_address = keypair.pubkey();
+
+            // Try to get account info from Solana
+            let (user_account_pda, _) = Pubkey::find_program_address(
+                &[b"user_account", telegram_id.to_le_bytes().as_ref()],
+                &solana_dao::ID,
+            );
+
+            match state
+                .program
+                .account::<solana_dao::UserAccount>(user_account_pda)
+                .await
+            {
+                Ok(user_account) => {
+                    let created_date = if user_account.created_at == 0 {
+                        "Just created".to_string()
+                    } else {
+                        match chrono::DateTime::<chrono::Utc>::from_timestamp(
+                            user_account.created_at,
+                            0,
+                        ) {
+                            Some(dt) => dt.format("%Y-%m-%d %H:%M UTC").to_string(),
+                            None => "Recently created".to_string(),
+                        }
+                    };
+
+                    let response = format!(
+                        "👤 <b>Your Account Information</b>\n\n\
+                        👤 Username: <code>{}</code>\n\
+                        🔑 Wallet Address: <code>{}</code>\n\
+                        📅 Created: {}\n{}\
+                        🔗 View on Explorer: {}\n\n\
+                        ✅ Account is active and ready for DAO participation!",
+                        username
+                            .map(|s| s.to_string())
+                            .unwrap_or_else(|| "anonymous".to_string()),
+                        wallet_address,
+                        created_date,
+                        linked_wallets_line(&user_account.linked_wallets),
+                        html_escape(&state.network.address_url(wallet_address))
+                    );
+
+                    bot.send_localized(&vocab, msg.chat.id, response)
+                        .parse_mode(teloxide::types::ParseMode::Html)
+                        .await?;
+                }
+                Err(_) => {
+                    let response = format!(
+                        "⚠️ <b>Account Found Locally</b>\n\n\
+                        👤 Username: <code>{}</code>\n\
+                        🔑 Wallet Address: <code>{}</code>\n\
+                        🔗 View on Explorer: {}\n\n\
+                        ❌ Account not yet created on-chain. Use /login to create it.",
+                        username
+                            .map(|s| s.to_string())
+                            .unwrap_or_else(|| "anonymous".to_string()),
+                        wallet_address,
+                        html_escape(&state.network.address_url(wallet_address))
+                    );
+
+                    bot.send_localized(&vocab, msg.chat.id, response)
+                        .parse_mode(teloxide::types::ParseMode::Html)
+                        .await?;
+                }
+            }
+        }
+        None => {
+            // User doesn't have an account yet
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                "❌ You don't have an account yet. Use /login to create one.",
+            )
+            .parse_mode(teloxide::types::ParseMode::Html)
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn handle_balance(bot: Bot, msg: Message, state: BotState) -> ResponseResult<()> {
+    let vocab = chat_vocabulary(&state, msg.chat.id).await;
+    let user_id = match msg.from() {
+        Some(user) => user.id,
+        None => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                "❌ Unable to identify user. Please try again.",
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let user = msg.from();
+    let username = user.and_then(|u| u.username.clone());
+
+    // Check if user has a seed (account exists)
+    let seed_opt = stored_seed(&state, user_id.0 as i64)
+        .await
+        .unwrap_or_else(|e| {
+            log::error!("Failed to read the keystore: {}", e);
+            None
+        });
+    if let Some(seed) = seed_opt {
+        // Generate the same keypair from the seed
+        let keypair = Keypair::new_from_array(seed);
+        let wallet_address = keypair.pubkey();
+
+        // Get the balance from Solana
+        match state.program.rpc().get_balance(&wallet_address).await {
+            Ok(balance_lamports) => {
+                let balance_sol = balance_lamports as f64 / LAMPORTS_PER_SOL as f64;
+
+                let response = format!(
+                    "💰 <b>Your SOL Balance</b>\n\n\
+                    👤 Username: <code>{}</code>\n\
+                    🔑 Wallet Address: <code>{}</code>\n\
+                    💎 Balance: <b>{:.6} SOL</b>\n\
+                    🔗 View on Explorer: {}",
+                    username.unwrap_or_else(|| "anonymous".to_string()),
+                    wallet_address,
+                    balance_sol,
+                    html_escape(&state.network.address_url(wallet_address))
+                );
+
+                bot.send_localized(&vocab, msg.chat.id, response)
+                    .parse_mode(teloxide::types::ParseMode::Html)
+                    .await?;
+            }
+            Err(e) => {
+                log::error!("Failed to get balance: {:?}", e);
+                bot.send_localized(
+                    &vocab,
+                    msg.chat.id,
+                    "❌ Failed to get balance. Please try again later.",
+                )
+                .parse_mode(teloxide::types::ParseMode::Html)
+                .await?;
+            }
+        }
+    } else {
+        // User doesn't have an account yet
+        bot.send_localized(
+            &vocab,
+            msg.chat.id,
+            "❌ You don't have an account yet. Use /login to create one.",
+        )
+        .parse_mode(teloxide::types::ParseMode::Html)
+        .await?;
+    }
+
+    Ok(())
+}
+
+async fn handle_withdraw(
+    bot: Bot,
+    msg: Message,
+    args: String,
+    state: BotState,
+) -> ResponseResult<()> {
+    let vocab = chat_vocabulary(&state, msg.chat.id).await;
+    let Some(user) = msg.from() else {
+        bot.send_localized(
+            &vocab,
+            msg.chat.id,
+            "❌ Unable to identify user. Please try again.",
+        )
+        .await?;
+        return Ok(());
+    };
+    let parts: Vec<&str> = args.split_whitespace().collect();
+    let [address, amount] = parts[..] else {
+        bot.send_localized(
+            &vocab,
+            msg.chat.id,
+            "❌ Usage: /withdraw <address> <sol>, or /withdraw <address> all",
+        )
+        .await?;
+        return Ok(());
+    };
+    let Ok(destination) = Pubkey::from_str(address) else {
+        bot.send_localized(&vocab, msg.chat.id, "❌ That isn't a valid Solana address.")
+            .await?;
+        return Ok(());
+    };
+    let wallet = match stored_seed(&state, user.id.0 as i64).await {
+        Ok(Some(seed)) => Keypair::new_from_array(seed).pubkey(),
+        Ok(None) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                "❌ You don't have an account yet. Use /login to create one.",
+            )
+            .await?;
+            return Ok(());
+        }
+        Err(e) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                format!("❌ Failed to access your account: {}", e),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+    if destination == wallet {
+        bot.send_localized(
+            &vocab,
+            msg.chat.id,
+            "❌ That is your own wallet; send to another address.",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let balances = match withdrawal_balances(&state, wallet, destination).await {
+        Ok(balances) => balances,
+        Err(e) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                format!("❌ Failed to check balances: {}", e),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+    let lamports = if amount.eq_ignore_ascii_case("all") {
+        balances.wallet.saturating_sub(WITHDRAWAL_FEE_LAMPORTS)
+    } else {
+        match parse_sol_amount(amount) {
+            Some(lamports) => lamports,
+            None => {
+                bot.send_localized(
+                    &vocab,
+                    msg.chat.id,
+                    "❌ Usage: /withdraw <address> <sol>, or /withdraw <address> all",
+                )
+                .await?;
+                return Ok(());
+            }
+        }
+    };
+    if let Err(problem) = balances.check(lamports) {
+        bot.send_localized(&vocab, msg.chat.id, problem).await?;
+        return Ok(());
+    }
+
+    // A transfer can't be called back, so this waits for the user to press Confirm
+    let id = state
+        .confirmations
+        .register(
+            user.id,
+            msg.chat.id,
+            callbacks::ConfirmedAction::Withdraw {
+                destination,
+                lamports,
+            },
+        )
+        .await;
+    let mut request = bot
+        .send_localized(
+            &vocab,
+            msg.chat.id,
+            format!(
+                "⚠️ Send {} SOL from your wallet to <code>{}</code>?\n\
+            The network fee is paid from your wallet too. This can't be undone.",
+                lamports as f64 / LAMPORTS_PER_SOL as f64,
+                destination
+            ),
+        )
+        .parse_mode(teloxide::types::ParseMode::Html);
+    if let Some(keyboard) = callbacks::confirm_keyboard(&vocab, &id) {
+        request = request.reply_markup(keyboard);
+    }
+    request.await?;
+    Ok(())
+}
+
+async fn run_withdraw(
+    state: &BotState,
+    telegram_id: i64,
+    destination: Pubkey,
+    lamports: u64,
+) -> String {
+    let keypair = match stored_seed(state, telegram_id).await {
+        Ok(Some(seed)) => Keypair::new_from_array(seed),
+        Ok(None) => {
+            return "❌ You don't have an account yet. Use /login to create one.".to_string()
+        }
+        Err(e) => return format!("❌ Failed to access your account: {}", e),
+    };
+    let wallet = keypair.pubkey();
+    // Balances may have moved while the confirmation waited
+    match withdrawal_balances(state, wallet, destination).await {
+        Ok(balances) => {
+            if let Err(problem) = balances.check(lamports) {
+                return problem;
+            }
+        }
+        Err(e) => return format!("❌ Failed to check balances: {}", e),
+    }
+    let instruction = system_instruction::transfer(&wallet, &destination, lamports);
+    match send_instructions(state, &[instruction], &wallet, &[&keypair]).await {
+        Ok(signature) => format!(
+            "✅ Sent {} SOL to {}.\nTransaction: {}",
+            lamports as f64 / LAMPORTS_PER_SOL as f64,
+            destination,
+            state.network.tx_url(signature)
+        ),
+        Err(e) => format!("❌ Failed to withdraw: {}", e),
+    }
+}
+
+// A transfer carries one signature, paid for by the sending wallet
+const WITHDRAWAL_FEE_LAMPORTS: u64 = 5_000;
+
+// A positive SOL amount like "1.25" in exact lamports; more than nine decimals, signs,
+// exponents and amounts past u64 are rejected rather than rounded
+fn parse_sol_amount(text: &str) -> Option<u64> {
+    let (whole, fraction) = text.split_once('.').unwrap_or((text, ""));
+    if (whole.is_empty() && fraction.is_empty())
+        || fraction.len() > 9
+        || !whole
+            .bytes()
+            .chain(fraction.bytes())
+            .all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+    let whole: u64 = if whole.is_empty() {
+        0
+    } else {
+        whole.parse().ok()?
+    };
+    let fraction: u64 = format!("{:0<9}", fraction).parse().ok()?;
+    whole
+        .checked_mul(LAMPORTS_PER_SOL)?
+        .checked_add(fraction)
+        .filter(|&lamports| lamports > 0)
+}
+
+// What a withdrawal has to leave behind for both accounts to stay rent-exempt
+struct WithdrawalBalances {
+    wallet: u64,
+    destination: u64,
+    rent_minimum: u64,
+}
+
+impl WithdrawalBalances {
+    // The reason a transfer of `lamports` would fail, if it would
+    fn check(&self, lamports: u64) -> Result<(), String> {
+        let sol = |lamports: u64| lamports as f64 / LAMPORTS_PER_SOL as f64;
+        if lamports == 0 {
+            return Err("❌ Your wallet has nothing to withdraw.".to_string());
+        }
+        let Some(left) = self
+            .wallet
+            .checked_sub(lamports)
+            .and_then(|left| left.checked_sub(WITHDRAWAL_FEE_LAMPORTS))
+        else {
+            return Err(format!(
+                "❌ Your wallet holds {} SOL; with the network fee you can withdraw at most {} SOL.",
+                sol(self.wallet),
+                sol(self.wallet.saturating_sub(WITHDRAWAL_FEE_LAMPORTS))
+            ));
+        };
+        // The wallet may be emptied, but not left holding less than rent
+        if left > 0 && left < self.rent_minimum {
+            return Err(format!(
+                "❌ That would leave {} SOL in your wallet, below the {} SOL it needs to stay open. \
+                Withdraw at most {} SOL, or everything with /withdraw <address> all.",
+                sol(left),
+                sol(self.rent_minimum),
+                sol(self.wallet
+                    .saturating_sub(WITHDRAWAL_FEE_LAMPORTS + self.rent_minimum))
+            ));
+        }
+        if self
+            .destination
+            .checked_add(lamports)
+            .is_some_and(|total| total < self.rent_minimum)
+        {
+            return Err(format!(
+                "❌ The address is empty, so it needs at least {} SOL to open.",
+                sol(self.rent_minimum - self.destination)
+            ));
+        }
+        Ok(())
+    }
+}
+
+async fn withdrawal_balances(
+    state: &BotState,
+    wallet: Pubkey,
+    destination: Pubkey,
+) -> anyhow::Result<WithdrawalBalances> {
+    let rpc = state.program.rpc();
+    Ok(WithdrawalBalances {
+        wallet: rpc.get_balance(&wallet).await?,
+        destination: rpc.get_balance(&destination).await?,
+        rent_minimum: rpc.get_minimum_balance_for_rent_exemption(0).await?,
+    })
+}
+
+// Links a wallet the user controls elsewhere (Phantom, Solflare, ...) by having it sign
+// the account's link challenge. The signature is pasted back, either by hand or from the
+// signing page the wallet's in-app browser opens, and checked here before it is sent.
+async fn handle_link_wallet(
+    bot: Bot,
+    msg: Message,
+    args: String,
+    state: BotState,
+) -> ResponseResult<()> {
+    let vocab = chat_vocabulary(&state, msg.chat.id).await;
+    if !msg.chat.is_private() {
+        bot.send_localized(
+            &vocab,
+            msg.chat.id,
+            "🔒 Link wallets in a private chat with me, so the group doesn't see which wallets are yours.",
+        )
+        .await?;
+        return Ok(());
+    }
+    let Some(user) = msg.from() else {
+        bot.send_localized(
+            &vocab,
+            msg.chat.id,
+            "❌ Unable to identify user. Please try again.",
+        )
+        .await?;
+        return Ok(());
+    };
+    let telegram_id = user.id.0 as i64;
+    let owner = match stored_seed(&state, telegram_id).await {
+        Ok(Some(seed)) => Keypair::new_from_array(seed),
+        Ok(None) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                "❌ You don't have an account yet. Use /login to create one.",
+            )
+            .await?;
+            return Ok(());
+        }
+        Err(e) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                format!("❌ Failed to access your account: {}", e),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+    let (user_account_pda, _) = Pubkey::find_program_address(
+        &[b"user_account", telegram_id.to_le_bytes().as_ref()],
+        &solana_dao::ID,
+    );
+    let user_account = match state
+        .program
+        .account::<solana_dao::UserAccount>(user_account_pda)
+        .await
+    {
+        Ok(user_account) => user_account,
+        Err(e) => {
+            bot.send_localized(
+                &vocab,
+                msg.chat.id,
+                format!("❌ Failed to access your account: {}", e),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+    let challenge = link_wallet_message(&user_account_pda, user_account.login_nonce);
+
+    let parts: Vec<&str> = args.split_whitespace().collect();
+    let response = match parts[..] {
+        [] => {
+            let mut status = "🔗 <b>Linked wallets</b>\n\n".to_string();
+            if user_account.linked_wallets.is_empty() {
+                status.push_str("No wallets linked yet.\n");
+            }
+            for wallet in &user_account.linked_wallets {
+                status.push_str(&format!("• <code>{}</code>\n", wallet));
+            }
+            status.push_str(&format!(
+                "\nTheir balances count toward your votes on SOL- and token-weighted proposals. \
+                To link a wallet, sign this message with it:\n\n<code>{}</code>\n\n\
+                Then send /linkwallet &lt;wallet&gt; &lt;signature&gt;. \
+                Unlink one with /linkwallet remove &lt;wallet&gt;.",
+                html_escape(&challenge)
+            ));
+            let mut request = bot
+                .send_localized(&vocab, msg.chat.id, status)
+                .parse_mode(teloxide::types::ParseMode::Html);
+            if let Some(keyboard) = link_wallet_keyboard(&state, &vocab, &challenge) {
+                request = request.reply_markup(keyboard);
+            }
+            request.await?;
+            return Ok(());
+        }
+        ["remove", wallet] => match Pubkey::from_str(wallet) {
+            Ok(wallet) if user_account.linked_wallets.contains(&wallet) => {
+                let instruction =
+                    build_unlink_wallet_instruction(telegram_id, owner.pubkey(), wallet);
+                match send_instructions(
+                    &state,
+                    &[instruction],
+                    &state.payer.pubkey(),
+                    &[&state.payer, &owner],
+                )
+                .await
+                {
+                    Ok(_) => format!(
+                        "✅ Unlinked <code>{}</code>; its balance no longer counts toward your votes.",
+                        wallet
+                    ),
+                    Err(e) => format!("❌ Failed to unlink the wallet: {}", html_escape(&e.to_string())),
+                }
+            }
+            Ok(_) => "❌ That wallet isn't linked to your account.".to_string(),
+            Err(_) => "❌ That isn't a valid Solana address.".to_string(),
+        },
+        [wallet, signature] => {
+            let signature = bs58::decode(signature)
+                .into_vec()
+                .ok()
+                .and_then(|bytes| <[u8; 64]>::try_from(bytes).ok());
+            match (Pubkey::from_str(wallet), signature) {
+                (Err(_), _) => "❌ That isn't a valid Solana address.".to_string(),
+                (Ok(_), None) => {
+                    "❌ That isn't a valid signature; paste it as base58, as your wallet shows it."
+                        .to_string()
+                }
+                (Ok(wallet), Some(signature))
+                    if !anchor_client::solana_sdk::signature::Signature::from(signature)
+                        .verify(wallet.as_ref(), challenge.as_bytes()) =>
+                {
+                    "❌ That signature isn't this wallet's signature of your current challenge. \
+                    Send /linkwallet for the message to sign."
+                        .to_string()
+                }
+                (Ok(wallet), Some(signature)) => {
+                    let instructions = build_link_wallet_instructions(
+                        telegram_id,
+                        owner.pubkey(),
+                        wallet,
+                        &signature,
+                        &challenge,
+                    );
+                    match send_instructions(
+                        &state,
+                        &instructions,
+                        &state.payer.pubkey(),
+                        &[&state.payer, &owner],
+                    )
+                    .await
+                    {
+                        Ok(_) => format!(
+                            "✅ Linked <code>{}</code>. Its balance now counts toward your votes on SOL- and token-weighted proposals.",
+                            wallet
+                        ),
+                        Err(e) if e.to_string().contains("WalletAlreadyLinked") => {
+                            "❌ That wallet is already yours: it is linked, or it is your bot wallet."
+                                .to_string()
+                        }
+                        Err(e) if e.to_string().contains("TooManyLinkedWallets") => {
+                            "❌ Your account has as many linked wallets as it can hold; unlink one first."
+                                .to_string()
+                        }
+                        Err(e) => format!("❌ Failed to link the wallet: {}", html_escape(&e.to_string())),
+                    }
+                }
+            }
+        }
+        _ => {
+            "❌ Usage: /linkwallet, /linkwallet &lt;wallet&gt; &lt;signature&gt; or /linkwallet remove &lt;wallet&gt;"
+                .to_string()
+        }
+    };
+    bot.send_localized(&vocab, msg.chat.id, response)
+        .parse_mode(teloxide::types::ParseMode::Html)
+        .await?;
+    Ok(())
+}
+
+// Buttons opening the signing page in Phantom's and Solflare's in-app browsers, where
+// the wallet is available; None without WEBAPP_URL
+fn link_wallet_keyboard(
+    state: &BotState,
+    vocab: &localization::Vocabulary,
+    challenge: &str,
+) -> Option<teloxide::types::InlineKeyboardMarkup> {
+    let base_url = state.webapp_url.as_ref()?;
+    let page =
+        reqwest::Url::parse_with_params(&format!("{}/link", base_url), &[("message", challenge)])
+            .map_err(|e| log::error!("Invalid WEBAPP_URL '{}': {}", base_url, e))
+            .ok()?;
+    let encode =
+        |text: &str| url::form_urlencoded::byte_serialize(text.as_bytes()).collect::<String>();
+    let browse = |prefix: &str| {
+        reqwest::Url::parse(&format!(
+            "{}{}?ref={}",
+            prefix,
+            encode(page.as_str()),
+            encode(base_url)
+        ))
+        .ok()
+    };
+    Some(teloxide::types::InlineKeyboardMarkup::new(vec![vec![
+        teloxide::types::InlineKeyboardButton::url(
+            vocab.apply("👻 Sign with Phantom"),
+            browse("https://phantom.app/ul/browse/")?,
+        ),
+        teloxide::types::InlineKeyboardButton::url(
+            vocab.apply("☀️ Sign with Solflare"),
+            browse("https://solflare.com/ul/v1/browse/")?,
+        ),
+    ]]))
+}
+
+// Helper function to ensure user has an account, creating one if needed
+async fn ensure_user_account(state: &BotState, telegram_id: i64) -> anyhow::Result<Keypair> {
+    // Check if we already have a seed for this user
+    if let Some(seed) = stored_seed(state, telegram_id).await? {
+        return Ok(Keypair::new_from_array(seed));
+    }
+
+    // Account doesn't exist, create it
+    create_user_account(state, telegram_id).await
+}
+
+// The user's wallet seed from the keystore. Wallets created before the keystore existed
+// were derived from the telegram ID, so a user with an on-chain account but no stored
+// seed gets the derived seed adopted, provided it still matches the account's wallet.
+async fn stored_seed(state: &BotState, telegram_id: i64) -> anyhow::Result<Option<[u8; 32]>> {
+    if let Some(seed) = state.keystore.get(telegram_id)? {
+        return Ok(Some(seed));
+    }
+
+    let (user_account_pda, _) = Pubkey::find_program_address(
+        &[b"user_account", telegram_id.to_le_bytes().as_ref()],
+        &solana_dao::ID,
+    );
+    let Ok(user_account) = state
+        .program
+        .account::<solana_dao::UserAccount>(user_account_pda)
+        .await
+    else {
+        return Ok(None);
+    };
+
+    let seed = generate_seed_from_telegram_id(telegram_id);
+    if Keypair::new_from_array(seed).pubkey() != user_account.wallet_pubkey {
+        anyhow::bail!(
+            "No key is stored for wallet {}; was SECRET_SEED changed?",
+            user_account.wallet_pubkey
+        );
+    }
+    state.keystore.insert(telegram_id, &seed)?;
+    log::info!(
+        "Moved the derived seed for {} into the keystore",
+        telegram_id
+    );
+    Ok(Some(seed))
+}
+
+// Legacy seed derivation from telegram ID and SECRET_SEED, kept only to adopt wallets
+// created before the keystore; new wallets get random seeds
+fn generate_seed_from_telegram_id(telegram_id: i64) -> [u8; 32] {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    // Get the secret seed from environment variable
+    let secret_seed = std::env::var("SECRET_SEED")
+        .unwrap_or_else(|_| "default_secret_seed_change_this_in_production".to_string());
+
+    // Create a hash of telegram_id + secret_seed for cryptographic security
+    let mut hasher = DefaultHasher::new();
+    telegram_id.hash(&mut hasher);
+    secret_seed.hash(&mut hasher);
+    let hash = hasher.finish();
+
+    // Convert hash to 32-byte seed
+    let mut seed = [0u8; 32];
+    let hash_bytes = hash.to_le_bytes();
+
+    // Use the hash as the base and fill the rest with additional entropy
+    seed[..8].copy_from_slice(&hash_bytes);
+
+    // Add additional entropy by mixing telegram_id and secret_seed
+    let id_bytes = telegram_id.to_le_bytes();
+    for i in 8..16 {
+        seed[i] = id_bytes[i - 8] ^ secret_seed.as_bytes()[(i - 8) % secret_seed.len()];
+    }
+
+    // Fill remaining bytes with deterministic but secure pattern
+    for i in 16..32 {
+        seed[i] = (hash_bytes[i % 8]
+            ^ id_bytes[i % 8]
+            ^ secret_seed.as_bytes()[i % secret_seed.len()]) as u8;
+    }
+
+    log::info!("Derived legacy seed for telegram_id: {}", telegram_id);
+    seed
+}
+
+// Create a new user account on Solana
+async fn create_user_account(state: &BotState, telegram_id: i64) -> anyhow::Result<Keypair> {
+    // Get the user account PDA
+    let (user_account_pda, _) = Pubkey::find_program_address(
+        &[b"user_account", telegram_id.to_le_bytes().as_ref()],
+        &solana_dao::ID,
+    );
+
+    log::info!("Creating user account for telegram_id: {}", telegram_id);
+    log::info!("User account PDA: {}", user_account_pda);
+    log::info!("Payer pubkey: {}", state.payer.pubkey());
+
+    // Check if account already exists
+    let program = state.solana_client.program(solana_dao::ID)?;
+    let rpc_client = program.rpc();
+
+    match rpc_client.get_account(&user_account_pda).await {
+        Ok(_account) => {
+            log::info!("User account already exists, returning existing keypair");
+            let seed = stored_seed(state, telegram_id)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("User seed not found"))?;
+            return Ok(Keypair::new_from_array(seed));
+        }
+        Err(_) => {
+            log::info!("User account does not exist, creating new one");
+        }
+    }
+
+    // The seed is stored before the account is created, so a crash after the transaction
+    // lands can't lose the wallet's key. A seed left by an earlier attempt is reused: if
+    // that transaction landed late, the account's wallet is still the stored one.
+    let seed = match state.keystore.get(telegram_id)? {
+        Some(seed) => seed,
+        None => {
+            let seed = keystore::new_seed();
+            state.keystore.insert(telegram_id, &seed)?;
+            seed
+        }
+    };
+    let keypair = Keypair::new_from_array(seed);
+    log::info!("User wallet pubkey: {}", keypair.pubkey());
+
+    // Build the transaction manually but with proper error handling
+    log::info!("Building transaction manually...");
+
+    // Build instruction data for create_user_account using correct discriminator
+    let mut instruction_data = vec![146, 68, 100, 69, 63, 46, 182, 199]; // create_user_account discriminator from IDL
+    instruction_data.extend_from_slice(&telegram_id.to_le_bytes());
+
+    log::info!("Instruction data: {:?}", instruction_data);
+    log::info!("Telegram ID bytes: {:?}", telegram_id.to_le_bytes());
+
+    let accounts = vec![
+        anchor_client::solana_sdk::instruction::AccountMeta::new(user_account_pda, false),
+        anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+            keypair.pubkey(),
+            false, // user_wallet is not a signer according to IDL
+        ),
+        anchor_client::solana_sdk::instruction::AccountMeta::new(state.payer.pubkey(), true),
+        anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+            system_program::ID,
+            false,
+        ),
+    ];
+
+    log::info!("Instruction accounts:");
+    for (i, account) in accounts.iter().enumerate() {
+        log::info!(
+            "  {}: {} (writable: {}, signer: {})",
+            i,
+            account.pubkey,
+            account.is_writable,
+            account.is_signer
+        );
+    }
+
+    let instruction = anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts,
+        data: instruction_data,
+    };
+
+    // Use the program's RPC client for better compatibility
+    let program = state.solana_client.program(solana_dao::ID)?;
+    let rpc_client = program.rpc();
+
+    log::info!("Getting recent blockhash...");
+    let recent_blockhash = rpc_client.get_latest_blockhash().await?;
+    log::info!("Recent blockhash: {}", recent_blockhash);
+
+    log::info!("Creating transaction...");
+    let mut transaction = anchor_client::solana_sdk::transaction::Transaction::new_with_payer(
+        &[instruction],
+        Some(&state.payer.pubkey()),
+    );
+    transaction.sign(&[&state.payer], recent_blockhash);
+
+    log::info!("Transaction created, attempting to send...");
+    log::info!("Transaction signatures: {:?}", transaction.signatures);
+
+    match rpc_client.send_and_confirm_transaction(&transaction).await {
+        Ok(signature) => {
+            log::info!("Transaction successful: {}", signature);
+        }
+        Err(e) => {
+            log::error!("Transaction failed: {}", e);
+            return Err(e.into());
+        }
+    }
+
+    Ok(keypair)
+}
+
+// Prove control of the user's wallet on-chain: sign the account's current login
+// challenge and submit it through login_with_signature, with the bot paying the fee
+async fn login_with_signature(
+    state: &BotState,
+    telegram_id: i64,
+    wallet: &Keypair,
+) -> anyhow::Result<anchor_client::solana_sdk::signature::Signature> {
+    let (user_account_pda, _) = Pubkey::find_program_address(
+        &[b"user_account", telegram_id.to_le_bytes().as_ref()],
+        &solana_dao::ID,
+    );
+    let user_account = state
+        .program
+        .account::<solana_dao::UserAccount>(user_account_pda)
+        .await?;
+
+    let mut message = solana_dao::LOGIN_MESSAGE_PREFIX.to_vec();
+    message.extend_from_slice(user_account_pda.as_ref());
+    message.extend_from_slice(&user_account.login_nonce.to_le_bytes());
+    let signature = wallet.sign_message(&message);
+
+    let verify_instruction =
+        anchor_client::solana_sdk::ed25519_instruction::new_ed25519_instruction_with_signature(
+            &message,
+            &signature.into(),
+            &wallet.pubkey().to_bytes(),
+        );
+
+    let mut instruction_data = vec![36, 89, 67, 205, 11, 220, 139, 53]; // login_with_signature discriminator
+    instruction_data.extend_from_slice(&telegram_id.to_le_bytes());
+    let login_instruction = anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(user_account_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                anchor_client::solana_sdk::sysvar::instructions::ID,
+                false,
+            ),
+        ],
+        data: instruction_data,
+    };
+
+    send_instructions(
+        state,
+        &[verify_instruction, login_instruction],
+        &state.payer.pubkey(),
+        &[&state.payer],
+    )
+    .await
+}
+
+// The text a wallet signs to be linked to the user account, valid until the account's
+// next login or link
+fn link_wallet_message(user_account: &Pubkey, nonce: u64) -> String {
+    format!(
+        "{}{}:{}",
+        solana_dao::LINK_WALLET_MESSAGE_PREFIX,
+        user_account,
+        nonce
+    )
+}
+
+// The ed25519 check of `wallet`'s signature of `message`, then link_wallet_with_signature,
+// which `owner` (the account's primary wallet) signs
+fn build_link_wallet_instructions(
+    telegram_id: i64,
+    owner: Pubkey,
+    wallet: Pubkey,
+    signature: &[u8; 64],
+    message: &str,
+) -> [anchor_client::solana_sdk::instruction::Instruction; 2] {
+    let verify_instruction =
+        anchor_client::solana_sdk::ed25519_instruction::new_ed25519_instruction_with_signature(
+            message.as_bytes(),
+            signature,
+            &wallet.to_bytes(),
+        );
+
+    let (user_account_pda, _) = Pubkey::find_program_address(
+        &[b"user_account", telegram_id.to_le_bytes().as_ref()],
+        &solana_dao::ID,
+    );
+    let mut data = vec![149, 136, 38, 3, 134, 17, 49, 76]; // link_wallet_with_signature
+    data.extend_from_slice(&telegram_id.to_le_bytes());
+    let link_instruction = anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(user_account_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(owner, true),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                anchor_client::solana_sdk::sysvar::instructions::ID,
+                false,
+            ),
+        ],
+        data,
+    };
+    [verify_instruction, link_instruction]
+}
+
+fn build_unlink_wallet_instruction(
+    telegram_id: i64,
+    authority: Pubkey,
+    wallet: Pubkey,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    let (user_account_pda, _) = Pubkey::find_program_address(
+        &[b"user_account", telegram_id.to_le_bytes().as_ref()],
+        &solana_dao::ID,
+    );
+    let mut data = vec![220, 121, 97, 13, 193, 137, 209, 159]; // unlink_wallet
+    data.extend_from_slice(&telegram_id.to_le_bytes());
+    data.extend_from_slice(wallet.as_ref());
+
+    anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(user_account_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(authority, true),
+        ],
+        data,
+    }
+}
+
+// Initialize the DAO registry
+// Name of the registry this bot lists its groups in (DAO_REGISTRY_NAME); empty for the
+// program's default registry
+fn registry_name() -> String {
+    std::env::var("DAO_REGISTRY_NAME").unwrap_or_default()
+}
+
+fn dao_registry_pda() -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"dao_registry", registry_name().as_bytes()],
+        &solana_dao::ID,
+    )
+    .0
+}
+
+async fn initialize_dao_registry(
+    client: &Client<Arc<Keypair>>,
+    program: &Program<Arc<Keypair>>,
+    payer: &Arc<Keypair>,
+) -> anyhow::Result<String> {
+    let dao_registry_pda = dao_registry_pda();
+
+    println!("Init - Program ID: {}", solana_dao::ID);
+    println!("Init - DAO Registry PDA: {}", dao_registry_pda);
+
+    // Check if already initialized
+    if program
+        .account::<solana_dao::DaoRegistry>(dao_registry_pda)
+        .await
+        .is_ok()
+    {
+        return Ok("already_initialized".to_string());
+    }
+
+    // Build initialize instruction using correct discriminator
+    let mut instruction_data = vec![175, 175, 109, 31, 13, 152, 155, 237]; // initialize discriminator from IDL
+    let name = registry_name();
+    instruction_data.extend_from_slice(&(name.len() as u32).to_le_bytes());
+    instruction_data.extend_from_slice(name.as_bytes());
+
+    let instruction = anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(dao_registry_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(payer.pubkey(), true),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                system_program::ID,
+                false,
+            ),
+        ],
+        data: instruction_data,
+    };
+
+    let program_id = instruction.program_id;
+
+    let recent_blockhash = client
+        .program(program_id)?
+        .rpc()
+        .get_latest_blockhash()
+        .await?;
+    let transaction = anchor_client::solana_sdk::transaction::Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&payer.pubkey()),
+        &[&**payer],
+        recent_blockhash,
+    );
+    let tx = client
+        .program(program_id)?
+        .rpc()
+        .send_and_confirm_transaction(&transaction)
+        .await?;
+
+    Ok(tx.to_string())
+}
+
+// The program config PDA (limits + pause switch), checked by every state-changing
+// group/proposal instruction
+fn program_config_pda() -> Pubkey {
+    Pubkey::find_program_address(&[b"program_config"], &solana_dao::ID).0
+}
+
+// Per-proposal turnout figures, created by the program on the proposal's first vote
+fn turnout_pda(proposal_pda: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"turnout", proposal_pda.as_ref()], &solana_dao::ID).0
+}
+
+// Per-member activity counters, created by the program on a member's first vote or proposal
+fn participation_pda(group_pda: &Pubkey, member: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"participation", group_pda.as_ref(), member.as_ref()],
+        &solana_dao::ID,
+    )
+    .0
+}
+
+// Index from a Telegram chat to the group created for it
+fn chat_link_pda(chat_id: i64) -> Pubkey {
+    Pubkey::find_program_address(&[b"chat_link", &chat_id.to_le_bytes()], &solana_dao::ID).0
+}
+
+// A wallet's ban from a group. Every join and vote passes it, whether or not it exists.
+fn ban_pda(group_pda: &Pubkey, wallet: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"ban", group_pda.as_ref(), wallet.as_ref()],
+        &solana_dao::ID,
+    )
+    .0
+}
+
+// A listed member's record; it exists only while the wallet is a member
+fn member_pda(group_pda: &Pubkey, wallet: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"member", group_pda.as_ref(), wallet.as_ref()],
+        &solana_dao::ID,
+    )
+    .0
+}
+
+// The wallet's member record in the group, None if the wallet isn't listed
+async fn listed_member_record(
+    state: &BotState,
+    group_id: &str,
+    wallet: Pubkey,
+) -> anyhow::Result<Option<Pubkey>> {
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let record = member_pda(&group_pda, &wallet);
+    let account = state
+        .program
+        .rpc()
+        .get_multiple_accounts(&[record])
+        .await?
+        .pop()
+        .flatten();
+    Ok(account.map(|_| record))
+}
+
+// The wallet's dues record, once it has paid dues in the group
+async fn paid_dues_record(
+    state: &BotState,
+    group_id: &str,
+    wallet: Pubkey,
+) -> anyhow::Result<Option<Pubkey>> {
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let record = dues_record_pda(&group_pda, &wallet);
+    let account = state
+        .program
+        .rpc()
+        .get_multiple_accounts(&[record])
+        .await?
+        .pop()
+        .flatten();
+    Ok(account.map(|_| record))
+}
+
+// The wallet's shares record, once it has bought shares in the group
+async fn staked_member_shares(
+    state: &BotState,
+    group_id: &str,
+    wallet: Pubkey,
+) -> anyhow::Result<Option<Pubkey>> {
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let record = member_shares_pda(&group_pda, &wallet);
+    let account = state
+        .program
+        .rpc()
+        .get_multiple_accounts(&[record])
+        .await?
+        .pop()
+        .flatten();
+    Ok(account.map(|_| record))
+}
+
+// Data, after the discriminator, of every `discriminator` account whose first field is the group
+async fn group_owned_accounts(
+    state: &BotState,
+    discriminator: [u8; 8],
+    group_pda: &Pubkey,
+) -> anyhow::Result<Vec<Vec<u8>>> {
+    program_accounts_at(state, discriminator, 8, group_pda).await
+}
+
+// Data (past the discriminator) of the program's accounts of one type holding `key` at
+// byte `offset`
+async fn program_accounts_at(
+    state: &BotState,
+    discriminator: [u8; 8],
+    offset: usize,
+    key: &Pubkey,
+) -> anyhow::Result<Vec<Vec<u8>>> {
+    use anchor_client::solana_account_decoder::UiAccountEncoding;
+    use anchor_client::solana_client::rpc_config::{
+        RpcAccountInfoConfig, RpcProgramAccountsConfig,
+    };
+    use anchor_client::solana_client::rpc_filter::{Memcmp, RpcFilterType};
+
+    let config = RpcProgramAccountsConfig {
+        filters: Some(vec![
+            RpcFilterType::Memcmp(Memcmp::new_raw_bytes(0, discriminator.to_vec())),
+            RpcFilterType::Memcmp(Memcmp::new_raw_bytes(offset, key.to_bytes().to_vec())),
+        ]),
+        account_config: RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let accounts = state
+        .program
+        .rpc()
+        .get_program_accounts_with_config(&solana_dao::ID, config)
+        .await?;
+    Ok(accounts
+        .into_iter()
+        .map(|(_, account)| account.data[8..].to_vec())
+        .collect())
+}
+
+// The wallet of the Telegram user's account, once they have one
+async fn telegram_wallet(state: &BotState, telegram_id: i64) -> anyhow::Result<Option<Pubkey>> {
+    let (user_account_pda, _) = Pubkey::find_program_address(
+        &[b"user_account", telegram_id.to_le_bytes().as_ref()],
+        &solana_dao::ID,
+    );
+    let account = state
+        .program
+        .rpc()
+        .get_multiple_accounts(&[user_account_pda])
+        .await?
+        .pop()
+        .flatten();
+    Ok(account
+        .and_then(|account| solana_dao::UserAccount::deserialize(&mut &account.data[8..]).ok())
+        .map(|user_account| user_account.wallet_pubkey))
+}
+
+// The Telegram user whose account's wallet this is, if the bot created it
+async fn wallet_owner(state: &BotState, wallet: &Pubkey) -> anyhow::Result<Option<i64>> {
+    // Past the discriminator, the version byte and the telegram id
+    let accounts =
+        program_accounts_at(state, solana_dao::USER_ACCOUNT_DISCRIMINATOR, 17, wallet).await?;
+    Ok(accounts.into_iter().find_map(|data| {
+        solana_dao::UserAccount::deserialize(&mut data.as_slice())
+            .ok()
+            .map(|user_account| user_account.telegram_id)
+    }))
+}
+
+// The wallet's delegation in the group, if it has one
+async fn fetch_delegation(
+    state: &BotState,
+    delegator: &Pubkey,
+    group_pda: &Pubkey,
+) -> anyhow::Result<Option<solana_dao::Delegation>> {
+    let account = state
+        .program
+        .rpc()
+        .get_multiple_accounts(&[delegation_pda(delegator, group_pda)])
+        .await?
+        .pop()
+        .flatten();
+    Ok(account
+        .and_then(|account| solana_dao::Delegation::deserialize(&mut &account.data[8..]).ok()))
+}
+
+// Every delegation naming the wallet as delegate, in any group
+async fn delegations_to(
+    state: &BotState,
+    delegate: &Pubkey,
+) -> anyhow::Result<Vec<solana_dao::Delegation>> {
+    // Past the discriminator and the delegator
+    let mut delegations =
+        program_accounts_at(state, solana_dao::DELEGATION_DISCRIMINATOR, 40, delegate)
+            .await?
+            .into_iter()
+            .filter_map(|data| solana_dao::Delegation::deserialize(&mut data.as_slice()).ok())
+            .collect::<Vec<_>>();
+    delegations.sort_by_key(|delegation| (delegation.created_at, delegation.delegator));
+    Ok(delegations)
+}
+
+// Every member record of the group, oldest first
+async fn group_members(
+    state: &BotState,
+    group_pda: &Pubkey,
+) -> anyhow::Result<Vec<solana_dao::Member>> {
+    let mut members = group_owned_accounts(state, solana_dao::MEMBER_DISCRIMINATOR, group_pda)
+        .await?
+        .into_iter()
+        .filter_map(|data| solana_dao::Member::deserialize(&mut data.as_slice()).ok())
+        .collect::<Vec<_>>();
+    members.sort_by_key(|member| (member.joined_at, member.wallet));
+    Ok(members)
+}
+
+// The group's saved proposal templates, by id
+async fn group_templates(
+    state: &BotState,
+    group_pda: &Pubkey,
+) -> anyhow::Result<Vec<solana_dao::ProposalTemplate>> {
+    let mut templates = group_owned_accounts(
+        state,
+        solana_dao::PROPOSAL_TEMPLATE_DISCRIMINATOR,
+        group_pda,
+    )
+    .await?
+    .into_iter()
+    .filter_map(|data| solana_dao::ProposalTemplate::deserialize(&mut data.as_slice()).ok())
+    .collect::<Vec<_>>();
+    templates.sort_by(|a, b| a.template_id.cmp(&b.template_id));
+    Ok(templates)
+}
+
+// Trailing optional accounts are positional; the program id stands for one left out
+fn optional_account_meta(
+    account: Option<Pubkey>,
+) -> anchor_client::solana_sdk::instruction::AccountMeta {
+    anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+        account.unwrap_or(solana_dao::ID),
+        false,
+    )
+}
+
+// As optional_account_meta, for accounts the instruction writes when present
+fn optional_writable_account_meta(
+    account: Option<Pubkey>,
+) -> anchor_client::solana_sdk::instruction::AccountMeta {
+    match account {
+        Some(account) => anchor_client::solana_sdk::instruction::AccountMeta::new(account, false),
+        None => optional_account_meta(None),
+    }
+}
+
+// The program's invite_code_hash: SHA-256 of "invite" || group || code
+fn invite_code_hash(group_pda: &Pubkey, code: &str) -> [u8; 32] {
+    use sha2::{Digest, Sha256};
+
+    Sha256::new()
+        .chain_update(b"invite")
+        .chain_update(group_pda.as_ref())
+        .chain_update(code.as_bytes())
+        .finalize()
+        .into()
+}
+
+fn invite_pda(group_pda: &Pubkey, code: &str) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"invite",
+            group_pda.as_ref(),
+            &invite_code_hash(group_pda, code),
+        ],
+        &solana_dao::ID,
+    )
+    .0
+}
+
+// Group treasury, which collects proposal fees, dues and slashed spam deposits
+fn treasury_pda(group_pda: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"treasury", group_pda.as_ref()], &solana_dao::ID).0
+}
+
+fn bounty_pda(group_pda: &Pubkey, bounty_id: &str) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"bounty", group_pda.as_ref(), bounty_id.as_bytes()],
+        &solana_dao::ID,
+    )
+    .0
+}
+
+fn delegation_pda(delegator: &Pubkey, group_pda: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"delegation", delegator.as_ref(), group_pda.as_ref()],
+        &solana_dao::ID,
+    )
+    .0
+}
+
+fn member_shares_pda(group_pda: &Pubkey, member: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"member_shares", group_pda.as_ref(), member.as_ref()],
+        &solana_dao::ID,
+    )
+    .0
+}
+
+fn dues_record_pda(group_pda: &Pubkey, member: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"dues_record", group_pda.as_ref(), member.as_ref()],
+        &solana_dao::ID,
+    )
+    .0
+}
+
+fn proposal_deposit_pda(proposal: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"proposal_deposit", proposal.as_ref()], &solana_dao::ID).0
+}
+
+fn proposal_pda(group_id: &str, proposal_id: &str) -> Pubkey {
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    Pubkey::find_program_address(
+        &[
+            b"proposal",
+            &group_pda.to_bytes()[..8],
+            &proposal_id.as_bytes()[..8],
+        ],
+        &solana_dao::ID,
+    )
+    .0
+}
+
+// Result certificate written when a proposal is finalized
+fn proposal_result_pda(group_id: &str, proposal_id: &str) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"proposal_result",
+            proposal_pda(group_id, proposal_id).as_ref(),
+        ],
+        &solana_dao::ID,
+    )
+    .0
+}
+
+// Per-choice descriptions and links of a proposal
+fn choice_details_pda(group_id: &str, proposal_id: &str) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"choice_details",
+            proposal_pda(group_id, proposal_id).as_ref(),
+        ],
+        &solana_dao::ID,
+    )
+    .0
+}
+
+// A proposal's per-choice details, or none when they were never set
+async fn fetch_choice_details(
+    state: &BotState,
+    group_id: &str,
+    proposal_id: &str,
+) -> Vec<solana_dao::ChoiceDetail> {
+    match state
+        .program
+        .rpc()
+        .get_account(&choice_details_pda(group_id, proposal_id))
+        .await
+    {
+        Ok(account) => solana_dao::ChoiceDetails::deserialize(&mut &account.data[8..])
+            .map(|choice_details| choice_details.details)
+            .unwrap_or_default(),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn group_profile_pda(group_id: &str) -> Pubkey {
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    Pubkey::find_program_address(&[b"group_profile", group_pda.as_ref()], &solana_dao::ID).0
+}
+
+// The group's profile card, or None when it was never set
+async fn fetch_group_profile(state: &BotState, group_id: &str) -> Option<solana_dao::GroupProfile> {
+    let account = state
+        .program
+        .rpc()
+        .get_account(&group_profile_pda(group_id))
+        .await
+        .ok()?;
+    solana_dao::GroupProfile::deserialize(&mut &account.data[8..]).ok()
+}
+
+fn grant_pda(group_id: &str, proposal_id: &str) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"grant", proposal_pda(group_id, proposal_id).as_ref()],
+        &solana_dao::ID,
+    )
+    .0
+}
+
+fn stream_pda(group_id: &str, proposal_id: &str) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"stream", proposal_pda(group_id, proposal_id).as_ref()],
+        &solana_dao::ID,
+    )
+    .0
+}
+
+fn voter_lottery_pda(group_id: &str, proposal_id: &str) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"lottery", proposal_pda(group_id, proposal_id).as_ref()],
+        &solana_dao::ID,
+    )
+    .0
+}
+
+// Aggregated tally of a proposal's off-chain ballots
+fn offchain_tally_pda(proposal_pda: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"offchain_tally", proposal_pda.as_ref()], &solana_dao::ID).0
+}
+
+// Create the program config account if this deployment doesn't have one yet
+async fn initialize_program_config(
+    client: &Client<Arc<Keypair>>,
+    program: &Program<Arc<Keypair>>,
+    payer: &Arc<Keypair>,
+) -> anyhow::Result<String> {
+    // Program-wide settings answer to the default registry, whichever one the bot uses
+    let (dao_registry_pda, _) = Pubkey::find_program_address(&[b"dao_registry"], &solana_dao::ID);
+    let program_config = program_config_pda();
+
+    if program.rpc().get_account(&program_config).await.is_ok() {
+        return Ok("already_initialized".to_string());
+    }
+
+    // initialize_program_config discriminator + guardian: None
+    let instruction_data = vec![6, 131, 61, 237, 40, 110, 83, 124, 0];
+
+    let instruction = anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(program_config, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                dao_registry_pda,
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(payer.pubkey(), true),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                system_program::ID,
+                false,
+            ),
+        ],
+        data: instruction_data,
+    };
+
+    let recent_blockhash = client
+        .program(solana_dao::ID)?
+        .rpc()
+        .get_latest_blockhash()
+        .await?;
+    let transaction = anchor_client::solana_sdk::transaction::Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&payer.pubkey()),
+        &[&**payer],
+        recent_blockhash,
+    );
+    let tx = client
+        .program(solana_dao::ID)?
+        .rpc()
+        .send_and_confirm_transaction(&transaction)
+        .await?;
+
+    Ok(tx.to_string())
+}
+
+// Submit instructions with retries; see rpc::send_with_retry for the reconciliation rules
+async fn send_instructions(
+    state: &BotState,
+    instructions: &[anchor_client::solana_sdk::instruction::Instruction],
+    payer: &Pubkey,
+    signers: &[&Keypair],
+) -> anyhow::Result<anchor_client::solana_sdk::signature::Signature> {
+    rpc::send_with_retry(
+        &state.program.rpc(),
+        instructions,
+        payer,
+        signers,
+        &rpc::RetryPolicy::default(),
+    )
+    .await
+}
+
+// Borsh-encode an Option<String> instruction argument
+fn push_optional_string(data: &mut Vec<u8>, value: Option<&str>) {
+    match value {
+        Some(value) => {
+            data.push(1);
+            data.extend_from_slice(&(value.len() as u32).to_le_bytes());
+            data.extend_from_slice(value.as_bytes());
+        }
+        None => data.push(0),
+    }
+}
+
+// Helper functions for Solana interactions
+async fn create_solana_group(
+    state: &BotState,
+    group_id: &str,
+    name: &str,
+    description: &str,
+    metadata_uri: Option<&str>,
+    chat_id: i64,
+) -> anyhow::Result<String> {
+    // Get the DAO registry PDA
+    let dao_registry_pda = dao_registry_pda();
+
+    // Get the group PDA
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+
+    // Build instruction data using correct discriminator
+    let mut instruction_data = vec![79, 60, 158, 134, 61, 199, 56, 248]; // create_group discriminator from IDL
+    instruction_data.extend_from_slice(&(group_id.len() as u32).to_le_bytes());
+    instruction_data.extend_from_slice(group_id.as_bytes());
+    instruction_data.extend_from_slice(&(name.len() as u32).to_le_bytes());
+    instruction_data.extend_from_slice(name.as_bytes());
+    instruction_data.extend_from_slice(&(description.len() as u32).to_le_bytes());
+    instruction_data.extend_from_slice(description.as_bytes());
+    push_optional_string(&mut instruction_data, metadata_uri);
+    // external_chat_id: Some(chat_id), so clients can resolve this chat to the group
+    instruction_data.push(1);
+    instruction_data.extend_from_slice(&chat_id.to_le_bytes());
+
+    let instruction = anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(dao_registry_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(state.payer.pubkey(), true),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                system_program::ID,
+                false,
+            ),
+            // program_config - rejects the instruction while the program is paused
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+            // chat_link - created here; fails if the chat already has a group
+            anchor_client::solana_sdk::instruction::AccountMeta::new(chat_link_pda(chat_id), false),
+        ],
+        data: instruction_data,
+    };
+
+    let tx = send_instructions(
+        state,
+        &[instruction],
+        &state.payer.pubkey(),
+        &[&state.payer],
+    )
+    .await?;
+
+    Ok(tx.to_string())
+}
+
+async fn get_all_groups(state: &BotState) -> anyhow::Result<Vec<solana_dao::Group>> {
+    // Get the DAO registry PDA
+    let dao_registry_pda = dao_registry_pda();
+
+    println!("DAO Registry PDA: {}", dao_registry_pda);
+    println!("Program ID used: {}", solana_dao::ID);
+
+    // First check if the account exists
+    match state.program.rpc().get_account(&dao_registry_pda).await {
+        Ok(account) => {
+            log::info!(
+                "DAO registry account exists with {} bytes",
+                account.data.len()
+            );
+        }
+        Err(e) => {
+            log::error!("DAO registry account does not exist or error: {}", e);
+            return Ok(Vec::new());
+        }
+    }
+
+    // Try to fetch and deserialize the DAO registry account manually
+    match state.program.rpc().get_account(&dao_registry_pda).await {
+        Ok(account) => {
+            log::info!("Account data length: {} bytes", account.data.len());
+
+            if account.data.len() < 8 {
+                log::error!("Account data too short: {} bytes", account.data.len());
+                return Ok(Vec::new());
+            }
+
+            // Skip the 8-byte discriminator and deserialize manually
+            let data = &account.data[8..];
+
+            // Find the actual data length by looking for the end of meaningful data
+            // The account is padded with zeros, so we need to find where the real data ends
+            let mut actual_data_len = data.len();
+            for (i, &byte) in data.iter().enumerate().rev() {
+                if byte != 0 {
+                    actual_data_len = i + 1;
+                    break;
+                }
+            }
+
+            log::info!(
+                "Actual data length: {} bytes (out of {} total)",
+                actual_data_len,
+                data.len()
+            );
+
+            // Only deserialize the actual data portion
+            let actual_data = &data[..actual_data_len];
+
+            // Deserialize the DaoRegistry struct manually using Anchor
+            match solana_dao::DaoRegistry::try_from_slice(actual_data) {
+                Ok(dao_registry) => {
+                    log::info!(
+                        "Successfully deserialized DAO registry with {} groups",
+                        dao_registry.groups.len()
+                    );
+
+                    // Fetch all group accounts
+                    let mut groups = Vec::new();
+                    for group_info in dao_registry.groups {
+                        log::info!(
+                            "Attempting to fetch group: {} with pubkey: {}",
+                            group_info.group_id,
+                            group_info.pubkey
+                        );
+                        // Try to fetch group account manually (same approach as DAO registry)
+                        match state.program.rpc().get_account(&group_info.pubkey).await {
+                            Ok(account) => {
+                                log::info!(
+                                    "Group account exists with {} bytes",
+                                    account.data.len()
+                                );
+
+                                if account.data.len() < 8 {
+                                    log::error!(
+                                        "Group account data too short: {} bytes",
+                                        account.data.len()
+                                    );
+                                    continue;
+                                }
+
+                                // Skip the 8-byte discriminator
+                                let data = &account.data[8..];
+
+                                // Find the actual data length by looking for the end of meaningful data
+                                let mut actual_data_len = data.len();
+                                for (i, &byte) in data.iter().enumerate().rev() {
+                                    if byte != 0 {
+                                        actual_data_len = i + 1;
+                                        break;
+                                    }
+                                }
+
+                                log::info!(
+                                    "Group actual data length: {} bytes (out of {} total)",
+                                    actual_data_len,
+                                    data.len()
+                                );
+
+                                // Only deserialize the actual data portion
+                                let actual_data = &data[..actual_data_len];
+
+                                match solana_dao::Group::try_from_slice(actual_data) {
+                                    Ok(group) => {
+                                        log::info!("Successfully fetched group: {}", group.name);
+                                        groups.push(group);
+                                    }
+                                    Err(e) => {
+                                        log::error!(
+                                            "Failed to deserialize group {}: {}",
+                                            group_info.group_id,
+                                            e
+                                        );
+                                    }
+                                }
+                            }
+                            Err(e) => {
+                                log::error!(
+                                    "Failed to get group account {}: {}",
+                                    group_info.group_id,
+                                    e
+                                );
+                            }
+                        }
+                    }
+                    Ok(groups)
+                }
+                Err(e) => {
+                    log::error!("Manual deserialization failed: {}", e);
+                    log::info!("Returning empty groups list due to deserialization error");
+                    Ok(Vec::new())
+                }
+            }
+        }
+        Err(e) => {
+            log::error!("Failed to get account: {}", e);
+            Ok(Vec::new())
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn create_solana_proposal(
+    state: &BotState,
+    group_id: &str,
+    proposal_id: &str,
+    title: &str,
+    description: &str,
+    choices: Vec<String>,
+    voting_start: i64,
+    voting_end: i64,
+    metadata_uri: Option<&str>,
+) -> anyhow::Result<(String, u8)> {
+    // Get the group PDA
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+
+    log::info!("Group PDA: {}", group_pda);
+    log::info!("Current payer (authority): {}", state.payer.pubkey());
+    log::info!("Looking for group with ID: '{}'", group_id);
+
+    // Check if group exists and get its authority
+    let program = state.solana_client.program(solana_dao::ID)?;
+
+    // First, let's check if the account exists at all
+    match program.rpc().get_account(&group_pda).await {
+        Ok(account) => {
+            log::info!("Group account exists with {} bytes", account.data.len());
+        }
+        Err(e) => {
+            log::error!("Group account does not exist: {}", e);
+            return Err(anyhow::anyhow!(
+                "Group '{}' does not exist. Please create the group first.",
+                group_id
+            ));
+        }
+    }
+
+    let group = match program.account::<solana_dao::Group>(group_pda).await {
+        Ok(group) => {
+            log::info!("Group found - Authority: {}", group.authority);
+            log::info!(
+                "Group name: '{}', description: '{}'",
+                group.name,
+                group.description
+            );
+            group
+        }
+        Err(e) => {
+            log::error!("Failed to deserialize group account: {}", e);
+
+            // Try manual deserialization like in get_all_groups
+            match program.rpc().get_account(&group_pda).await {
+                Ok(account) => {
+                    log::info!("Attempting manual deserialization...");
+                    if account.data.len() < 8 {
+                        log::error!("Account data too short: {} bytes", account.data.len());
+                        return Err(anyhow::anyhow!("Group '{}' data is corrupted.", group_id));
+                    }
+
+                    let data = &account.data[8..];
+                    let mut actual_data_len = data.len();
+                    for (i, &byte) in data.iter().enumerate().rev() {
+                        if byte != 0 {
+                            actual_data_len = i + 1;
+                            break;
+                        }
+                    }
+
+                    log::info!(
+                        "Manual deserialization - actual data length: {} bytes",
+                        actual_data_len
+                    );
+                    let actual_data = &data[..actual_data_len];
+
+                    match solana_dao::Group::try_from_slice(actual_data) {
+                        Ok(group) => {
+                            log::info!(
+                                "Manual deserialization successful - Group: '{}', Authority: {}",
+                                group.name,
+                                group.authority
+                            );
+                            group
+                        }
+                        Err(deser_err) => {
+                            log::error!("Manual deserialization also failed: {}", deser_err);
+                            return Err(anyhow::anyhow!("Group '{}' exists but data is corrupted and cannot be deserialized.", group_id));
+                        }
+                    }
+                }
+                Err(acc_err) => {
+                    log::error!(
+                        "Failed to get account for manual deserialization: {}",
+                        acc_err
+                    );
+                    return Err(anyhow::anyhow!(
+                        "Group '{}' does not exist. Please create the group first.",
+                        group_id
+                    ));
+                }
+            }
+        }
+    };
+
+    if group.authority != state.payer.pubkey() {
+        return Err(anyhow::anyhow!(
+            "Unauthorized: Group authority ({}) does not match current payer ({})",
+            group.authority,
+            state.payer.pubkey()
+        ));
+    }
+
+    // Get the proposal PDA
+    // Use first 8 bytes of group_pda and proposal_id to stay within 32-byte seed limit (8 + 8 + 8 = 24 bytes)
+    let (proposal_pda, _) = Pubkey::find_program_address(
+        &[
+            b"proposal",
+            &group_pda.to_bytes()[..8],
+            &proposal_id.as_bytes()[..8],
+        ],
+        &solana_dao::ID,
+    );
+
+    log::info!("Proposal PDA: {}", proposal_pda);
+
+    // The next proposal is recorded in an index PDA keyed by the group's proposal counter
+    let (proposal_index_pda, _) = Pubkey::find_program_address(
+        &[
+            b"proposal_index",
+            group_pda.as_ref(),
+            &group.proposal_count.to_le_bytes(),
+        ],
+        &solana_dao::ID,
+    );
+
+    log::info!("Proposal index PDA: {}", proposal_index_pda);
+
+    // Build instruction data using correct discriminator
+    let mut instruction_data = vec![132, 116, 68, 174, 216, 160, 198, 22]; // create_proposal discriminator from IDL
+    instruction_data.extend_from_slice(&(proposal_id.len() as u32).to_le_bytes());
+    instruction_data.extend_from_slice(proposal_id.as_bytes());
+    instruction_data.extend_from_slice(&(title.len() as u32).to_le_bytes());
+    instruction_data.extend_from_slice(title.as_bytes());
+    instruction_data.extend_from_slice(&(description.len() as u32).to_le_bytes());
+    instruction_data.extend_from_slice(description.as_bytes());
+    instruction_data.extend_from_slice(&(choices.len() as u32).to_le_bytes());
+    for choice in &choices {
+        instruction_data.extend_from_slice(&(choice.len() as u32).to_le_bytes());
+        instruction_data.extend_from_slice(choice.as_bytes());
+    }
+    instruction_data.extend_from_slice(&voting_start.to_le_bytes());
+    instruction_data.extend_from_slice(&voting_end.to_le_bytes());
+    // Use NATIVE_MINT for SOL-weighted voting
+    instruction_data.push(1); // Some for token_mint
+                              // Native SOL mint address: So11111111111111111111111111111111111111112
+    let native_mint = match Pubkey::from_str("So11111111111111111111111111111111111111112") {
+        Ok(pubkey) => pubkey,
+        Err(e) => {
+            log::error!("Failed to parse native mint address: {}", e);
+            return Err(anyhow::anyhow!(
+                "Failed to parse native mint address: {}",
+                e
+            ));
+        }
+    };
+    instruction_data.extend_from_slice(&native_mint.to_bytes());
+    push_optional_string(&mut instruction_data, metadata_uri);
+    // Groups that require co-sponsors or a deposit take proposals as drafts; the last
+    // sponsor opens a sponsored draft
+    let draft = group.sponsor_threshold > 0 || group.proposal_deposit_lamports > 0;
+    instruction_data.push(draft as u8);
+    instruction_data.push(0); // kind: ProposalKind::Text
+    instruction_data.push(0); // emergency: false, created by the group authority
+
+    let instruction = anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(proposal_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(proposal_index_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(state.payer.pubkey(), true),
+            // participation - the creator's activity counters
+            anchor_client::solana_sdk::instruction::AccountMeta::new(
+                participation_pda(&group_pda, &state.payer.pubkey()),
+                false,
+            ),
+            // treasury - receives the group's proposal creation fee, if any
+            anchor_client::solana_sdk::instruction::AccountMeta::new(
+                treasury_pda(&group_pda),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                system_program::ID,
+                false,
+            ),
+            // program_config - rejects the instruction while the program is paused
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+            // council - only needed for emergency proposals
+            optional_account_meta(None),
+        ],
+        data: instruction_data,
+    };
+
+    // The bot payer creates every proposal, so it also posts a required deposit, and then
+    // opens the draft in the same transaction unless it still needs co-sponsors
+    let mut instructions = vec![instruction];
+    if group.proposal_deposit_lamports > 0 {
+        instructions.push(build_post_proposal_deposit_instruction(
+            group_id,
+            proposal_id,
+            state.payer.pubkey(),
+            group.proposal_deposit_lamports,
+        ));
+        if group.sponsor_threshold == 0 {
+            instructions.push(build_activate_proposal_instruction(
+                group_id,
+                proposal_id,
+                state.payer.pubkey(),
+            ));
+        }
+    }
+
+    let tx =
+        send_instructions(state, &instructions, &state.payer.pubkey(), &[&state.payer]).await?;
+
+    Ok((tx.to_string(), group.sponsor_threshold))
+}
+
+async fn get_group_proposals(
+    state: &BotState,
+    group_id: &str,
+) -> anyhow::Result<Vec<solana_dao::Proposal>> {
+    // Get the group PDA
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+
+    // Fetch the group account manually (same approach as get_all_groups)
+    let group = match state.program.rpc().get_account(&group_pda).await {
+        Ok(account) => {
+            if account.data.len() < 8 {
+                return Err(anyhow::anyhow!(
+                    "Group account data too short: {} bytes",
+                    account.data.len()
+                ));
+            }
+
+            // Skip the 8-byte discriminator
+            let data = &account.data[8..];
+
+            // Find the actual data length by looking for the end of meaningful data
+            let mut actual_data_len = data.len();
+            for (i, &byte) in data.iter().enumerate().rev() {
+                if byte != 0 {
+                    actual_data_len = i + 1;
+                    break;
+                }
+            }
+
+            // Only deserialize the actual data portion
+            let actual_data = &data[..actual_data_len];
+
+            match solana_dao::Group::try_from_slice(actual_data) {
+                Ok(group) => group,
+                Err(e) => {
+                    log::error!("Failed to deserialize group {}: {}", group_id, e);
+                    return Err(anyhow::anyhow!("Failed to deserialize group: {}", e));
+                }
+            }
+        }
+        Err(e) => {
+            log::error!("Failed to get group account {}: {}", group_id, e);
+            return Err(anyhow::anyhow!("Failed to get group account: {}", e));
+        }
+    };
+
+    // Walk the group's proposal index PDAs, then fetch each proposal they point to
+    let mut proposals = Vec::new();
+    for index in 0..group.proposal_count {
+        let (proposal_index_pda, _) = Pubkey::find_program_address(
+            &[b"proposal_index", group_pda.as_ref(), &index.to_le_bytes()],
+            &solana_dao::ID,
+        );
+
+        let proposal_info = match state.program.rpc().get_account(&proposal_index_pda).await {
+            Ok(account) => {
+                if account.data.len() < 8 {
+                    log::error!(
+                        "Proposal index account data too short: {} bytes",
+                        account.data.len()
+                    );
+                    continue;
+                }
+
+                match solana_dao::ProposalIndex::deserialize(&mut &account.data[8..]) {
+                    Ok(proposal_info) => proposal_info,
+                    Err(e) => {
+                        log::error!("Failed to deserialize proposal index {}: {}", index, e);
+                        continue;
+                    }
+                }
+            }
+            Err(e) => {
+                // Expected for proposals closed by close_stale_proposal
+                log::debug!("Proposal index account {} unavailable: {}", index, e);
+                continue;
+            }
+        };
+
+        if proposal_info.large {
+            log::info!(
+                "Skipping large proposal {} (not supported by the bot yet)",
+                proposal_info.proposal_id
+            );
+            continue;
+        }
+
+        match state.program.rpc().get_account(&proposal_info.pubkey).await {
+            Ok(account) => {
+                if account.data.len() < 8 {
+                    log::error!(
+                        "Proposal account data too short: {} bytes",
+                        account.data.len()
+                    );
+                    continue;
+                }
+
+                // Skip the 8-byte discriminator
+                let data = &account.data[8..];
+
+                // Find the actual data length by looking for the end of meaningful data
+                let mut actual_data_len = data.len();
+                for (i, &byte) in data.iter().enumerate().rev() {
+                    if byte != 0 {
+                        actual_data_len = i + 1;
+                        break;
+                    }
+                }
+
+                // Only deserialize the actual data portion
+                let actual_data = &data[..actual_data_len];
+
+                match solana_dao::Proposal::try_from_slice(actual_data) {
+                    Ok(proposal) => {
+                        log::info!("Successfully fetched proposal: {}", proposal.title);
+                        proposals.push(proposal);
+                    }
+                    Err(e) => {
+                        log::error!(
+                            "Failed to deserialize proposal {}: {}",
+                            proposal_info.proposal_id,
+                            e
+                        );
+                    }
+                }
+            }
+            Err(e) => {
+                log::error!(
+                    "Failed to get proposal account {}: {}",
+                    proposal_info.proposal_id,
+                    e
+                );
+            }
+        }
+    }
+
+    Ok(proposals)
+}
+
+// Build the vote_on_proposal instruction for a voter wallet
+fn build_vote_instruction(
+    group_id: &str,
+    proposal_id: &str,
+    choice: u8,
+    allocations: Option<&[u64]>,
+    disclosure: Option<&str>,
+    voter_wallet: Pubkey,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    let mut data = vec![188, 239, 13, 88, 119, 199, 251, 119, choice]; // discriminator + choice
+    push_optional_string(&mut data, disclosure);
+    match allocations {
+        Some(allocations) => {
+            data.push(1);
+            data.extend_from_slice(&(allocations.len() as u32).to_le_bytes());
+            for amount in allocations {
+                data.extend_from_slice(&amount.to_le_bytes());
+            }
+        }
+        None => data.push(0),
+    }
+
+    // Get the group PDA
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+
+    log::info!("Group PDA: {}", group_pda);
+
+    // Get the proposal PDA - use first 8 bytes of group_pda and proposal_id to stay within 32-byte seed limit
+    let (proposal_pda, _) = Pubkey::find_program_address(
+        &[
+            b"proposal",
+            &group_pda.to_bytes()[..8],
+            &proposal_id.as_bytes()[..8],
+        ],
+        &solana_dao::ID,
+    );
+
+    log::info!("Proposal PDA: {}", proposal_pda);
+
+    // For SOL-weighted voting, we can use simple placeholders since the program
+    // uses ctx.accounts.voter.lamports() directly and doesn't validate the token accounts
+    anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(proposal_pda, false),
+            // group - writable so the program can advance its event sequence
+            anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(voter_wallet, true),
+            // participation - the voter's activity counters
+            anchor_client::solana_sdk::instruction::AccountMeta::new(
+                participation_pda(&group_pda, &voter_wallet),
+                false,
+            ),
+            // turnout - the proposal's turnout figures
+            anchor_client::solana_sdk::instruction::AccountMeta::new(
+                turnout_pda(&proposal_pda),
+                false,
+            ),
+            // voter_token_account - use voter wallet as placeholder (not validated for SOL voting)
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                voter_wallet, // Use voter wallet as placeholder
+                false,
+            ),
+            // token_program - use system program as placeholder (not validated for SOL voting)
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                system_program::ID, // Use system program as placeholder
+                false,
+            ),
+            // system_program - funds the proposal realloc for the new voter entry
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                system_program::ID,
+                false,
+            ),
+            // program_config - rejects the instruction while the program is paused
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+        ],
+        data,
+    }
+}
+
+// `authority` must be the group authority: the bot payer for groups the bot created
+fn build_set_metadata_uri_instruction(
+    group_id: &str,
+    proposal_id: &str,
+    metadata_uri: Option<&str>,
+    authority: Pubkey,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    let mut data = vec![33, 144, 242, 129, 171, 164, 183, 49]; // set_proposal_metadata_uri
+    push_optional_string(&mut data, metadata_uri);
+
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let (proposal_pda, _) = Pubkey::find_program_address(
+        &[
+            b"proposal",
+            &group_pda.to_bytes()[..8],
+            &proposal_id.as_bytes()[..8],
+        ],
+        &solana_dao::ID,
+    );
+
+    anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(proposal_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(authority, true),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+        ],
+        data,
+    }
+}
+
+// Payload a wallet signs off-chain to have its vote relayed: the program's
+// RELAYED_VOTE_MESSAGE_PREFIX || proposal address || choice || expiry (i64 LE)
+fn relayed_vote_message(group_id: &str, proposal_id: &str, choice: u8, expiry: i64) -> Vec<u8> {
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let (proposal_pda, _) = Pubkey::find_program_address(
+        &[
+            b"proposal",
+            &group_pda.to_bytes()[..8],
+            &proposal_id.as_bytes()[..8],
+        ],
+        &solana_dao::ID,
+    );
+
+    let mut message = solana_dao::RELAYED_VOTE_MESSAGE_PREFIX.to_vec();
+    message.extend_from_slice(proposal_pda.as_ref());
+    message.push(choice);
+    message.extend_from_slice(&expiry.to_le_bytes());
+    message
+}
+
+// Ballot a wallet signs for a proposal whose votes are collected off-chain: the
+// program's OFFCHAIN_BALLOT_MESSAGE_PREFIX || proposal address || choice
+fn offchain_ballot_message(group_id: &str, proposal_id: &str, choice: u8) -> Vec<u8> {
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let (proposal_pda, _) = Pubkey::find_program_address(
+        &[
+            b"proposal",
+            &group_pda.to_bytes()[..8],
+            &proposal_id.as_bytes()[..8],
+        ],
+        &solana_dao::ID,
+    );
+
+    let mut message = solana_dao::OFFCHAIN_BALLOT_MESSAGE_PREFIX.to_vec();
+    message.extend_from_slice(proposal_pda.as_ref());
+    message.push(choice);
+    message
+}
+
+fn build_enable_offchain_voting_instruction(
+    group_id: &str,
+    proposal_id: &str,
+    aggregator: Pubkey,
+    authority: Pubkey,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    let mut data = vec![62, 73, 27, 73, 69, 4, 56, 166]; // enable_offchain_voting
+    data.extend_from_slice(aggregator.as_ref());
+
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let (proposal_pda, _) = Pubkey::find_program_address(
+        &[
+            b"proposal",
+            &group_pda.to_bytes()[..8],
+            &proposal_id.as_bytes()[..8],
+        ],
+        &solana_dao::ID,
+    );
+    let (offchain_tally_pda, _) =
+        Pubkey::find_program_address(&[b"offchain_tally", proposal_pda.as_ref()], &solana_dao::ID);
+
+    anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(proposal_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(authority, true),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+            // An undisputed tally keeps its aggregator
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                offchain_tally_pda,
+                false,
+            ),
+        ],
+        data,
+    }
+}
+
+fn build_submit_offchain_tally_instruction(
+    group_id: &str,
+    proposal_id: &str,
+    merkle_root: [u8; 32],
+    choice_votes: &[u64],
+    voter_count: u32,
+    aggregator: Pubkey,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    let mut data = vec![113, 139, 68, 126, 151, 25, 242, 180]; // submit_offchain_tally
+    data.extend_from_slice(&merkle_root);
+    data.extend_from_slice(&(choice_votes.len() as u32).to_le_bytes());
+    for votes in choice_votes {
+        data.extend_from_slice(&votes.to_le_bytes());
+    }
+    data.extend_from_slice(&voter_count.to_le_bytes());
+
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let (proposal_pda, _) = Pubkey::find_program_address(
+        &[
+            b"proposal",
+            &group_pda.to_bytes()[..8],
+            &proposal_id.as_bytes()[..8],
+        ],
+        &solana_dao::ID,
+    );
+
+    anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(proposal_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(
+                offchain_tally_pda(&proposal_pda),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(aggregator, true),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                system_program::ID,
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+        ],
+        data,
+    }
+}
+
+fn build_settle_offchain_tally_instruction(
+    group_id: &str,
+    proposal_id: &str,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let (proposal_pda, _) = Pubkey::find_program_address(
+        &[
+            b"proposal",
+            &group_pda.to_bytes()[..8],
+            &proposal_id.as_bytes()[..8],
+        ],
+        &solana_dao::ID,
+    );
+
+    anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(proposal_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                offchain_tally_pda(&proposal_pda),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+        ],
+        data: vec![165, 43, 218, 67, 180, 61, 161, 177], // settle_offchain_tally
+    }
+}
+
+// A vote signed off-chain by `voter_wallet`, submitted by `relayer`: the ed25519
+// precompile check of the signature followed by relay_vote
+#[allow(clippy::too_many_arguments)]
+fn build_relay_vote_instructions(
+    group_id: &str,
+    proposal_id: &str,
+    choice: u8,
+    expiry: i64,
+    voter_wallet: Pubkey,
+    signature: &[u8; 64],
+    voter_token_account: Option<Pubkey>,
+    member_record: Option<Pubkey>,
+    dues_record: Option<Pubkey>,
+    member_shares: Option<Pubkey>,
+    relayer: Pubkey,
+) -> Vec<anchor_client::solana_sdk::instruction::Instruction> {
+    let verify_instruction =
+        anchor_client::solana_sdk::ed25519_instruction::new_ed25519_instruction_with_signature(
+            &relayed_vote_message(group_id, proposal_id, choice, expiry),
+            signature,
+            &voter_wallet.to_bytes(),
+        );
+
+    let mut data = vec![95, 144, 14, 235, 21, 209, 78, 166, choice]; // relay_vote discriminator + choice
+    data.extend_from_slice(&expiry.to_le_bytes());
+
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let (proposal_pda, _) = Pubkey::find_program_address(
+        &[
+            b"proposal",
+            &group_pda.to_bytes()[..8],
+            &proposal_id.as_bytes()[..8],
+        ],
+        &solana_dao::ID,
+    );
+
+    let relay_instruction = anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(proposal_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(voter_wallet, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(
+                participation_pda(&group_pda, &voter_wallet),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(
+                turnout_pda(&proposal_pda),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(relayer, true),
+            // voter_token_account - SPL proposals only; the program id stands for None
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                voter_token_account.unwrap_or(solana_dao::ID),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                anchor_client::solana_sdk::sysvar::instructions::ID,
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                system_program::ID,
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+            // member_record - the voter's, when the group lists them
+            optional_account_meta(member_record),
+            // dues_record - the voter's, once they have paid dues
+            optional_account_meta(dues_record),
+            // member_shares - the voter's, once they have bought shares
+            optional_writable_account_meta(member_shares),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                ban_pda(&group_pda, &voter_wallet),
+                false,
+            ),
+        ],
+        data,
+    };
+
+    vec![verify_instruction, relay_instruction]
+}
+
+fn build_shadow_vote_instruction(
+    group_id: &str,
+    proposal_id: &str,
+    choice: u8,
+    voter_wallet: Pubkey,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    let data = vec![227, 35, 78, 82, 112, 215, 36, 19, choice]; // discriminator + choice
+
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let (proposal_pda, _) = Pubkey::find_program_address(
+        &[
+            b"proposal",
+            &group_pda.to_bytes()[..8],
+            &proposal_id.as_bytes()[..8],
+        ],
+        &solana_dao::ID,
+    );
+    let (shadow_ballot_pda, _) = Pubkey::find_program_address(
+        &[
+            b"shadow_ballot",
+            proposal_pda.as_ref(),
+            voter_wallet.as_ref(),
+        ],
+        &solana_dao::ID,
+    );
+
+    anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(proposal_pda, false),
+            // group - writable for its event sequence
+            anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(shadow_ballot_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(voter_wallet, true),
+            // member_record - must not exist; members vote normally
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                member_pda(&group_pda, &voter_wallet),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                system_program::ID,
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+        ],
+        data,
+    }
+}
+
+fn build_create_invite_instruction(
+    group_id: &str,
+    code: &str,
+    max_uses: u32,
+    expires_at: i64,
+    authority: Pubkey,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let mut data = vec![160, 94, 130, 54, 134, 245, 255, 229]; // create_invite
+    data.extend_from_slice(&invite_code_hash(&group_pda, code));
+    data.extend_from_slice(&max_uses.to_le_bytes());
+    data.extend_from_slice(&expires_at.to_le_bytes());
+
+    anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(
+                invite_pda(&group_pda, code),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(authority, true),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                system_program::ID,
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+        ],
+        data,
+    }
+}
+
+// `authority` must be the group authority: the bot payer for groups the bot created
+fn build_set_membership_policy_instruction(
+    group_id: &str,
+    max_members: u32,
+    visibility: solana_dao::GroupVisibility,
+    authority: Pubkey,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let mut data = vec![20, 34, 95, 146, 155, 154, 61, 229]; // set_membership_policy
+    data.extend_from_slice(&max_members.to_le_bytes());
+    data.push(visibility as u8);
+
+    anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(authority, true),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+        ],
+        data,
+    }
+}
+
+// `authority` must be the group authority: the bot payer for groups the bot created
+fn build_set_max_active_proposals_instruction(
+    group_id: &str,
+    max_active_proposals: u16,
+    authority: Pubkey,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let mut data = vec![92, 254, 168, 185, 13, 176, 251, 35]; // set_max_active_proposals
+    data.extend_from_slice(&max_active_proposals.to_le_bytes());
+
+    anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(authority, true),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+        ],
+        data,
+    }
+}
+
+// `authority` must be the group authority: the bot payer for groups the bot created
+fn build_set_choice_details_instruction(
+    group_id: &str,
+    proposal_id: &str,
+    details: &[solana_dao::ChoiceDetail],
+    authority: Pubkey,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let mut data = vec![156, 118, 105, 200, 236, 112, 80, 26]; // set_choice_details
+    data.extend_from_slice(&(details.len() as u32).to_le_bytes());
+    for detail in details {
+        push_optional_string(&mut data, detail.description.as_deref());
+        push_optional_string(&mut data, detail.uri.as_deref());
+    }
+
+    anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                proposal_pda(group_id, proposal_id),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(
+                choice_details_pda(group_id, proposal_id),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(authority, true),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                system_program::ID,
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+        ],
+        data,
+    }
+}
+
+// `authority` must be the group authority: the bot payer for groups the bot created
+fn build_set_group_profile_instruction(
+    group_id: &str,
+    profile: &solana_dao::GroupProfile,
+    authority: Pubkey,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let mut data = vec![36, 99, 55, 3, 40, 170, 203, 43]; // set_group_profile
+    push_optional_string(&mut data, profile.avatar_uri.as_deref());
+    push_optional_string(&mut data, profile.website.as_deref());
+    data.extend_from_slice(&(profile.socials.len() as u32).to_le_bytes());
+    for social in &profile.socials {
+        data.extend_from_slice(&(social.len() as u32).to_le_bytes());
+        data.extend_from_slice(social.as_bytes());
+    }
+    push_optional_string(&mut data, profile.contact.as_deref());
+
+    anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(
+                group_profile_pda(group_id),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(authority, true),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                system_program::ID,
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+        ],
+        data,
+    }
+}
+
+// `authority` must be the group authority: the bot payer for groups the bot created
+fn build_set_proposal_cooldown_instruction(
+    group_id: &str,
+    cooldown_secs: i64,
+    authority: Pubkey,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let mut data = vec![8, 112, 139, 253, 94, 50, 218, 199]; // set_proposal_cooldown
+    data.extend_from_slice(&cooldown_secs.to_le_bytes());
+
+    anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(authority, true),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+        ],
+        data,
+    }
+}
+
+// `authority` must be the group authority: the bot payer for groups the bot created
+fn build_set_sponsor_threshold_instruction(
+    group_id: &str,
+    threshold: u8,
+    authority: Pubkey,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let mut data = vec![88, 206, 157, 67, 109, 109, 62, 46]; // set_sponsor_threshold
+    data.push(threshold);
+
+    anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(authority, true),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+        ],
+        data,
+    }
+}
+
+// `depositor` must be the proposal's creator; the program adds the draft's spam choice
+fn build_post_proposal_deposit_instruction(
+    group_id: &str,
+    proposal_id: &str,
+    depositor: Pubkey,
+    lamports: u64,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let proposal = proposal_pda(group_id, proposal_id);
+    let mut data = vec![140, 81, 198, 36, 213, 205, 178, 10]; // post_proposal_deposit
+    data.extend_from_slice(&lamports.to_le_bytes());
+
+    anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(proposal, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(
+                proposal_deposit_pda(&proposal),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(depositor, true),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                system_program::ID,
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+        ],
+        data,
+    }
+}
+
+// Permissionless; the refund and the deposit account's rent go to `depositor`, a slashed
+// deposit to the group treasury
+fn build_settle_proposal_deposit_instruction(
+    group_id: &str,
+    proposal_id: &str,
+    depositor: Pubkey,
+    settler: Pubkey,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let proposal = proposal_pda(group_id, proposal_id);
+
+    anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(proposal, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(
+                proposal_deposit_pda(&proposal),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(depositor, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(
+                treasury_pda(&group_pda),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(settler, true),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+        ],
+        data: vec![234, 65, 163, 18, 118, 83, 128, 133], // settle_proposal_deposit
+    }
+}
+
+// `authority` must be the group authority: the bot payer for groups the bot created
+fn build_activate_proposal_instruction(
+    group_id: &str,
+    proposal_id: &str,
+    authority: Pubkey,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let proposal = proposal_pda(group_id, proposal_id);
+
+    anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(proposal, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(authority, true),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                proposal_deposit_pda(&proposal),
+                false,
+            ),
+        ],
+        data: vec![90, 186, 203, 234, 70, 185, 191, 21], // activate_proposal
+    }
+}
+
+// `sponsor` must be a listed member; it pays for its ProposalSponsor receipt
+fn build_co_sponsor_instruction(
+    group_id: &str,
+    proposal_id: &str,
+    sponsor: Pubkey,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let proposal = proposal_pda(group_id, proposal_id);
+    let (sponsorship, _) = Pubkey::find_program_address(
+        &[b"sponsor", proposal.as_ref(), sponsor.as_ref()],
+        &solana_dao::ID,
+    );
+
+    anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(proposal, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                member_pda(&group_pda, &sponsor),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(sponsorship, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(sponsor, true),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                system_program::ID,
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+            // proposal_deposit - the last sponsor opens the draft, which needs its deposit
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                proposal_deposit_pda(&proposal),
+                false,
+            ),
+        ],
+        data: vec![55, 221, 195, 112, 10, 213, 60, 100], // co_sponsor
+    }
+}
+
+// `authority` must be the group authority: the bot payer for groups the bot created
+fn build_set_tie_break_instruction(
+    group_id: &str,
+    tie_break: solana_dao::TieBreak,
+    authority: Pubkey,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let mut data = vec![134, 214, 250, 162, 215, 83, 143, 199]; // set_tie_break
+    match tie_break {
+        solana_dao::TieBreak::Fail => data.push(0),
+        solana_dao::TieBreak::Revote { window_secs } => {
+            data.push(1);
+            data.extend_from_slice(&window_secs.to_le_bytes());
+        }
+        solana_dao::TieBreak::EarliestLeading => data.push(2),
+        solana_dao::TieBreak::Council => data.push(3),
+    }
+
+    anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(authority, true),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+        ],
+        data,
+    }
+}
+
+// `authority` must be the group authority; `member_record` is the wallet's when listed
+fn build_ban_member_instruction(
+    group_id: &str,
+    wallet: Pubkey,
+    reason: &str,
+    expires_at: i64,
+    member_record: Option<Pubkey>,
+    authority: Pubkey,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let mut data = vec![139, 8, 15, 248, 77, 196, 194, 230]; // ban_member
+    data.extend_from_slice(wallet.as_ref());
+    data.extend_from_slice(&(reason.len() as u32).to_le_bytes());
+    data.extend_from_slice(reason.as_bytes());
+    data.extend_from_slice(&expires_at.to_le_bytes());
+
+    anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(
+                ban_pda(&group_pda, &wallet),
+                false,
+            ),
+            optional_writable_account_meta(member_record),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(authority, true),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                system_program::ID,
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+        ],
+        data,
+    }
+}
+
+// `authority` must be the group authority, and receives the ban's rent
+fn build_unban_member_instruction(
+    group_id: &str,
+    wallet: Pubkey,
+    authority: Pubkey,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let mut data = vec![25, 233, 98, 49, 153, 158, 207, 192]; // unban_member
+    data.extend_from_slice(wallet.as_ref());
+
+    anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(
+                ban_pda(&group_pda, &wallet),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(authority, true),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+        ],
+        data,
+    }
+}
+
+fn sol_registrar_pda(group_pda: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"registrar",
+            group_pda.as_ref(),
+            solana_dao::NATIVE_MINT.as_ref(),
+        ],
+        &solana_dao::ID,
+    )
+    .0
+}
+
+fn voter_deposit_pda(registrar_pda: &Pubkey, voter: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"voter_deposit", registrar_pda.as_ref(), voter.as_ref()],
+        &solana_dao::ID,
+    )
+    .0
+}
+
+// The bot only runs SOL registrars, which keep deposits in each VoterDeposit
+fn build_configure_deposit_registrar_instruction(
+    group_id: &str,
+    min_holding_secs: i64,
+    authority: Pubkey,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let mut data = vec![214, 223, 238, 67, 227, 235, 191, 43]; // configure_deposit_registrar
+    data.extend_from_slice(&solana_dao::NATIVE_MINT.to_bytes());
+    data.extend_from_slice(&min_holding_secs.to_le_bytes());
+
+    anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(
+                sol_registrar_pda(&group_pda),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(authority, true),
+            // vault - SPL registrars only
+            optional_account_meta(None),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                system_program::ID,
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+        ],
+        data,
+    }
+}
+
+// `authority` must be the group authority: the bot payer for groups the bot created
+fn build_set_dues_policy_instruction(
+    group_id: &str,
+    dues: Option<&solana_dao::DuesPolicy>,
+    authority: Pubkey,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let mut data = vec![172, 28, 166, 86, 143, 144, 250, 200]; // set_dues_policy
+    anchor_lang::AnchorSerialize::serialize(&dues.cloned(), &mut data)
+        .expect("writing to a Vec cannot fail");
+
+    anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(authority, true),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+        ],
+        data,
+    }
+}
+
+// The bot only pays SOL dues, straight into the group treasury
+fn build_pay_dues_instruction(
+    group_id: &str,
+    periods: u32,
+    member: Pubkey,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let mut data = vec![96, 90, 64, 205, 128, 32, 220, 68]; // pay_dues
+    data.extend_from_slice(&periods.to_le_bytes());
+
+    anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(
+                dues_record_pda(&group_pda, &member),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(member, true),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(
+                treasury_pda(&group_pda),
+                false,
+            ),
+            // treasury_token_account, member_token_account, token_program - SPL dues only
+            optional_account_meta(None),
+            optional_account_meta(None),
+            optional_account_meta(None),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                system_program::ID,
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+        ],
+        data,
+    }
+}
+
+fn build_set_stake_to_join_instruction(
+    group_id: &str,
+    price_lamports: Option<u64>,
+    authority: Pubkey,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let mut data = vec![180, 54, 58, 140, 46, 141, 153, 177]; // set_stake_to_join
+    anchor_lang::AnchorSerialize::serialize(&price_lamports, &mut data)
+        .expect("writing to a Vec cannot fail");
+
+    anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(authority, true),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+        ],
+        data,
+    }
+}
+
+fn build_stake_shares_instruction(
+    group_id: &str,
+    shares: u64,
+    member: Pubkey,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let mut data = vec![74, 147, 241, 180, 228, 25, 198, 190]; // stake_shares
+    data.extend_from_slice(&shares.to_le_bytes());
+
+    anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(
+                member_shares_pda(&group_pda, &member),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(
+                member_pda(&group_pda, &member),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(member, true),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(
+                treasury_pda(&group_pda),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                system_program::ID,
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                ban_pda(&group_pda, &member),
+                false,
+            ),
+        ],
+        data,
+    }
+}
+
+// The bot only ragequits the treasury's SOL; SPL balances need their token accounts
+// appended as remaining accounts
+fn build_ragequit_instruction(
+    group_id: &str,
+    shares: u64,
+    member: Pubkey,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let mut data = vec![142, 182, 225, 76, 251, 128, 172, 31]; // ragequit
+    data.extend_from_slice(&shares.to_le_bytes());
+
+    anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(
+                member_shares_pda(&group_pda, &member),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(member, true),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(
+                treasury_pda(&group_pda),
+                false,
+            ),
+            // token_program - SPL payouts only
+            optional_account_meta(None),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                system_program::ID,
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+        ],
+        data,
+    }
+}
+
+fn build_delegate_votes_instruction(
+    group_pda: &Pubkey,
+    delegator: Pubkey,
+    delegate: Pubkey,
+    expires_at: Option<i64>,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    let mut data = vec![134, 214, 180, 254, 134, 143, 185, 247]; // delegate_votes
+    data.extend_from_slice(group_pda.as_ref());
+    data.extend_from_slice(delegate.as_ref());
+    data.push(0); // kind: None, every proposal kind
+    match expires_at {
+        Some(expires_at) => {
+            data.push(1);
+            data.extend_from_slice(&expires_at.to_le_bytes());
+        }
+        None => data.push(0),
+    }
+
+    anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(
+                delegation_pda(&delegator, group_pda),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(delegator, true),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                system_program::ID,
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+        ],
+        data,
+    }
+}
+
+fn build_undelegate_votes_instruction(
+    group_pda: &Pubkey,
+    delegator: Pubkey,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(
+                delegation_pda(&delegator, group_pda),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(delegator, true),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+        ],
+        data: vec![3, 16, 78, 162, 64, 40, 201, 107], // undelegate_votes
+    }
+}
+
+fn build_post_bounty_instruction(
+    group_id: &str,
+    bounty_id: &str,
+    description_uri: &str,
+    reward: u64,
+    authority: Pubkey,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let mut data = vec![40, 217, 222, 103, 151, 83, 147, 130]; // post_bounty
+    data.extend_from_slice(&(bounty_id.len() as u32).to_le_bytes());
+    data.extend_from_slice(bounty_id.as_bytes());
+    data.extend_from_slice(&(description_uri.len() as u32).to_le_bytes());
+    data.extend_from_slice(description_uri.as_bytes());
+    data.extend_from_slice(&reward.to_le_bytes());
+
+    anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(
+                bounty_pda(&group_pda, bounty_id),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(authority, true),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                system_program::ID,
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+        ],
+        data,
+    }
+}
+
+fn build_claim_bounty_instruction(
+    group_id: &str,
+    bounty_id: &str,
+    hunter: Pubkey,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    let data = vec![225, 157, 163, 238, 239, 169, 75, 226]; // claim_bounty
+    bounty_hunter_instruction(group_id, bounty_id, hunter, data)
+}
+
+fn build_submit_bounty_instruction(
+    group_id: &str,
+    bounty_id: &str,
+    submission_uri: &str,
+    hunter: Pubkey,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    let mut data = vec![139, 122, 83, 221, 66, 227, 132, 37]; // submit_bounty
+    data.extend_from_slice(&(submission_uri.len() as u32).to_le_bytes());
+    data.extend_from_slice(submission_uri.as_bytes());
+    bounty_hunter_instruction(group_id, bounty_id, hunter, data)
+}
+
+// claim_bounty and submit_bounty take the same accounts
+fn bounty_hunter_instruction(
+    group_id: &str,
+    bounty_id: &str,
+    hunter: Pubkey,
+    data: Vec<u8>,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+
+    anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(
+                bounty_pda(&group_pda, bounty_id),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(hunter, true),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+        ],
+        data,
+    }
+}
+
+fn build_approve_bounty_instruction(
+    group_id: &str,
+    bounty_id: &str,
+    hunter: Pubkey,
+    authority: Pubkey,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+
+    anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(
+                bounty_pda(&group_pda, bounty_id),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(
+                treasury_pda(&group_pda),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(hunter, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(authority, true),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                system_program::ID,
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+        ],
+        data: vec![159, 69, 100, 84, 88, 57, 93, 29], // approve_bounty
+    }
+}
+
+fn build_cancel_bounty_instruction(
+    group_id: &str,
+    bounty_id: &str,
+    authority: Pubkey,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+
+    anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(
+                bounty_pda(&group_pda, bounty_id),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(authority, true),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+        ],
+        data: vec![79, 65, 107, 143, 128, 165, 135, 46], // cancel_bounty
+    }
+}
+
+fn build_deposit_voting_tokens_instruction(
+    group_id: &str,
+    lamports: u64,
+    voter: Pubkey,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let registrar = sol_registrar_pda(&group_pda);
+    let mut data = vec![152, 94, 137, 105, 197, 93, 78, 49]; // deposit_voting_tokens
+    data.extend_from_slice(&lamports.to_le_bytes());
+
+    anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(registrar, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(
+                voter_deposit_pda(&registrar, &voter),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(voter, true),
+            // vault, voter_token_account, token_program - SPL registrars only
+            optional_account_meta(None),
+            optional_account_meta(None),
+            optional_account_meta(None),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                system_program::ID,
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+        ],
+        data,
+    }
+}
+
+fn build_withdraw_voting_tokens_instruction(
+    group_id: &str,
+    lamports: u64,
+    voter: Pubkey,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let registrar = sol_registrar_pda(&group_pda);
+    let mut data = vec![230, 200, 189, 31, 228, 96, 65, 77]; // withdraw_voting_tokens
+    data.extend_from_slice(&lamports.to_le_bytes());
+
+    anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(registrar, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(
+                voter_deposit_pda(&registrar, &voter),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(voter, true),
+            // vault, voter_token_account, token_program - SPL registrars only
+            optional_account_meta(None),
+            optional_account_meta(None),
+            optional_account_meta(None),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+        ],
+        data,
+    }
+}
+
+// `authority` must be the group authority; requires deposits in the SOL registrar
+fn build_set_deposit_voting_instruction(
+    group_id: &str,
+    proposal_id: &str,
+    authority: Pubkey,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let (proposal_pda, _) = Pubkey::find_program_address(
+        &[
+            b"proposal",
+            &group_pda.to_bytes()[..8],
+            &proposal_id.as_bytes()[..8],
+        ],
+        &solana_dao::ID,
+    );
+    let mut data = vec![39, 214, 43, 210, 187, 132, 43, 1]; // set_deposit_voting
+    data.push(1); // enabled
+
+    anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(proposal_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                sol_registrar_pda(&group_pda),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(authority, true),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+        ],
+        data,
+    }
+}
+
+fn proposal_template_pda(group_pda: &Pubkey, template_id: &str) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"proposal_template",
+            group_pda.as_ref(),
+            template_id.as_bytes(),
+        ],
+        &solana_dao::ID,
+    )
+    .0
+}
+
+// Saves `template`'s content and settings; its group, count and bump are ignored
+fn build_create_proposal_template_instruction(
+    group_id: &str,
+    template: &solana_dao::ProposalTemplate,
+    authority: Pubkey,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    use anchor_lang::AnchorSerialize;
+
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let mut data = vec![155, 14, 2, 213, 64, 126, 66, 59]; // create_proposal_template
+    (
+        &template.template_id,
+        &template.title,
+        &template.description,
+        &template.choices,
+        template.voting_duration,
+        template.token_mint,
+        &template.metadata_uri,
+        &template.kind,
+        template.member_weighted,
+        template.split_voting,
+    )
+        .serialize(&mut data)
+        .expect("writing to a Vec cannot fail");
+
+    anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(
+                proposal_template_pda(&group_pda, &template.template_id),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(authority, true),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                system_program::ID,
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+        ],
+        data,
+    }
+}
+
+// `proposal_index` is the group's proposal_count when the instruction lands
+fn build_instantiate_from_template_instruction(
+    group_id: &str,
+    template_id: &str,
+    proposal_id: &str,
+    voting_start: i64,
+    proposal_index: u64,
+    authority: Pubkey,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let (proposal_pda, _) = Pubkey::find_program_address(
+        &[
+            b"proposal",
+            &group_pda.to_bytes()[..8],
+            &proposal_id.as_bytes()[..8],
+        ],
+        &solana_dao::ID,
+    );
+    let (proposal_index_pda, _) = Pubkey::find_program_address(
+        &[
+            b"proposal_index",
+            group_pda.as_ref(),
+            &proposal_index.to_le_bytes(),
+        ],
+        &solana_dao::ID,
+    );
+    let mut data = vec![21, 249, 153, 224, 142, 129, 22, 223]; // instantiate_from_template
+    data.extend_from_slice(&(proposal_id.len() as u32).to_le_bytes());
+    data.extend_from_slice(proposal_id.as_bytes());
+    data.extend_from_slice(&voting_start.to_le_bytes());
+    data.push(0); // draft: false, voting opens at voting_start
+
+    anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(proposal_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(proposal_index_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(
+                proposal_template_pda(&group_pda, template_id),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(authority, true),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(
+                participation_pda(&group_pda, &authority),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(
+                treasury_pda(&group_pda),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                system_program::ID,
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+        ],
+        data,
+    }
+}
+
+// The joiner signs and pays for their member record
+fn build_join_with_invite_instruction(
+    group_id: &str,
+    code: &str,
+    joiner: Pubkey,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    let mut data = vec![123, 115, 24, 162, 214, 219, 190, 97]; // join_with_invite
+    data.extend_from_slice(&(code.len() as u32).to_le_bytes());
+    data.extend_from_slice(code.as_bytes());
+
+    anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(
+                invite_pda(&group_pda, code),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(joiner, true),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(
+                member_pda(&group_pda, &joiner),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                system_program::ID,
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                ban_pda(&group_pda, &joiner),
+                false,
+            ),
+        ],
+        data,
+    }
+}
+
+// Permissionless; `cranker` pays the result certificate's rent and collects the
+// proposal's finalize bounty. `lottery` is the proposal's voter lottery, drawn when
+// passed, and `quorum_supply` the mint or registrar a weight quorum is measured against.
+fn build_finalize_proposal_instruction(
+    group_id: &str,
+    proposal_id: &str,
+    cranker: Pubkey,
+    lottery: Option<Pubkey>,
+    quorum_supply: Option<Pubkey>,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    let data = vec![23, 68, 51, 167, 109, 173, 187, 164]; // finalize_proposal
 
-                    match solana_dao::Group::try_from_slice(actual_data) {
-                        Ok(group) => {
-                            log::info!(
-                                "Manual deserialization successful - Group: '{}', Authority: {}",
-                                group.name,
-                                group.authority
-                            );
-                            if group.authority != state.payer.pubkey() {
-                                return Err(anyhow::anyhow!(
-                                    "Unauthorized: Group authority ({}) does not match current payer ({})",
-                                    group.authority,
-                                    state.payer.pubkey()
-                                ));
-                            }
-                        }
-                        Err(deser_err) => {
-                            log::error!("Manual deserialization also failed: {}", deser_err);
-                            return Err(anyhow::anyhow!("Group '{}' exists but data is corrupted and cannot be deserialized.", group_id));
-                        }
-                    }
-                }
-                Err(acc_err) => {
-                    log::error!(
-                        "Failed to get account for manual deserialization: {}",
-                        acc_err
-                    );
-                    return Err(anyhow::anyhow!(
-                        "Group '{}' does not exist. Please create the group first.",
-                        group_id
-                    ));
-                }
-            }
-        }
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+
+    anchor_client::solana_sdk::instruction::Instruction {
+        program_id: solana_dao::ID,
+        accounts: vec![
+            anchor_client::solana_sdk::instruction::AccountMeta::new(
+                proposal_pda(group_id, proposal_id),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(
+                proposal_result_pda(group_id, proposal_id),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(cranker, true),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                system_program::ID,
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                program_config_pda(),
+                false,
+            ),
+            optional_writable_account_meta(lottery),
+            optional_account_meta(
+                lottery.map(|_| anchor_client::solana_sdk::sysvar::slot_hashes::ID),
+            ),
+            optional_account_meta(quorum_supply),
+        ],
+        data,
     }
+}
 
-    // Get the proposal PDA
-    // Use first 8 bytes of group_pda and proposal_id to stay within 32-byte seed limit (8 + 8 + 8 = 24 bytes)
+// Permissionless; the rent of the proposal and its index goes to `fee_destination`
+fn build_close_stale_proposal_instruction(
+    group_id: &str,
+    proposal_id: &str,
+    index: u64,
+    closer: Pubkey,
+    fee_destination: Pubkey,
+) -> anchor_client::solana_sdk::instruction::Instruction {
+    let data = vec![104, 131, 81, 118, 115, 63, 84, 141]; // close_stale_proposal
+
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
     let (proposal_pda, _) = Pubkey::find_program_address(
         &[
             b"proposal",
@@ -1715,192 +10068,255 @@ async fn create_solana_proposal(
         ],
         &solana_dao::ID,
     );
+    let (proposal_index_pda, _) = Pubkey::find_program_address(
+        &[b"proposal_index", group_pda.as_ref(), &index.to_le_bytes()],
+        &solana_dao::ID,
+    );
+    // Checked for a pending callback, program upgrade or unsettled deposit, which keep
+    // the proposal open
+    let (callback_pda, _) =
+        Pubkey::find_program_address(&[b"callback", proposal_pda.as_ref()], &solana_dao::ID);
+    let (upgrade_proposal_pda, _) = Pubkey::find_program_address(
+        &[b"program_upgrade", proposal_pda.as_ref()],
+        &solana_dao::ID,
+    );
 
-    log::info!("Proposal PDA: {}", proposal_pda);
-
-    // Build instruction data using correct discriminator
-    let mut instruction_data = vec![132, 116, 68, 174, 216, 160, 198, 22]; // create_proposal discriminator from IDL
-    instruction_data.extend_from_slice(&(proposal_id.len() as u32).to_le_bytes());
-    instruction_data.extend_from_slice(proposal_id.as_bytes());
-    instruction_data.extend_from_slice(&(title.len() as u32).to_le_bytes());
-    instruction_data.extend_from_slice(title.as_bytes());
-    instruction_data.extend_from_slice(&(description.len() as u32).to_le_bytes());
-    instruction_data.extend_from_slice(description.as_bytes());
-    instruction_data.extend_from_slice(&(choices.len() as u32).to_le_bytes());
-    for choice in &choices {
-        instruction_data.extend_from_slice(&(choice.len() as u32).to_le_bytes());
-        instruction_data.extend_from_slice(choice.as_bytes());
-    }
-    instruction_data.extend_from_slice(&voting_start.to_le_bytes());
-    instruction_data.extend_from_slice(&voting_end.to_le_bytes());
-    // Use NATIVE_MINT for SOL-weighted voting
-    instruction_data.push(1); // Some for token_mint
-                              // Native SOL mint address: So11111111111111111111111111111111111111112
-    let native_mint = match Pubkey::from_str("So11111111111111111111111111111111111111112") {
-        Ok(pubkey) => pubkey,
-        Err(e) => {
-            log::error!("Failed to parse native mint address: {}", e);
-            return Err(anyhow::anyhow!(
-                "Failed to parse native mint address: {}",
-                e
-            ));
-        }
-    };
-    instruction_data.extend_from_slice(&native_mint.to_bytes());
-
-    let instruction = anchor_client::solana_sdk::instruction::Instruction {
+    anchor_client::solana_sdk::instruction::Instruction {
         program_id: solana_dao::ID,
         accounts: vec![
             anchor_client::solana_sdk::instruction::AccountMeta::new(proposal_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(proposal_index_pda, false),
             anchor_client::solana_sdk::instruction::AccountMeta::new(group_pda, false),
-            anchor_client::solana_sdk::instruction::AccountMeta::new(state.payer.pubkey(), true),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(closer, true),
+            anchor_client::solana_sdk::instruction::AccountMeta::new(fee_destination, false),
             anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
-                system_program::ID,
+                program_config_pda(),
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(callback_pda, false),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                upgrade_proposal_pda,
+                false,
+            ),
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                proposal_deposit_pda(&proposal_pda),
                 false,
             ),
         ],
-        data: instruction_data,
-    };
-
-    let program_id = instruction.program_id.clone();
-    let recent_blockhash = state
-        .solana_client
-        .program(program_id)?
-        .rpc()
-        .get_latest_blockhash()
-        .await?;
-    let transaction = anchor_client::solana_sdk::transaction::Transaction::new_signed_with_payer(
-        &[instruction],
-        Some(&state.payer.pubkey()),
-        &[&state.payer],
-        recent_blockhash,
-    );
-    let tx = state
-        .solana_client
-        .program(program_id)?
-        .rpc()
-        .send_and_confirm_transaction(&transaction)
-        .await?;
-
-    Ok(tx.to_string())
+        data,
+    }
 }
 
-async fn get_group_proposals(
+#[allow(clippy::too_many_arguments)]
+async fn vote_on_proposal(
     state: &BotState,
+    telegram_id: i64,
     group_id: &str,
-) -> anyhow::Result<Vec<solana_dao::Proposal>> {
-    // Get the group PDA
-    let (group_pda, _) =
-        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
-
-    // Fetch the group account manually (same approach as get_all_groups)
-    let group = match state.program.rpc().get_account(&group_pda).await {
-        Ok(account) => {
-            if account.data.len() < 8 {
-                return Err(anyhow::anyhow!(
-                    "Group account data too short: {} bytes",
-                    account.data.len()
-                ));
-            }
+    proposal_id: &str,
+    choice: u8,
+    allocations: Option<&[u64]>,
+    disclosure: Option<&str>,
+    voter_wallet: Pubkey,
+) -> anyhow::Result<String> {
+    // Find the user's seed and generate keypair
+    let voter_keypair = stored_seed(state, telegram_id)
+        .await?
+        .map(Keypair::new_from_array)
+        .filter(|kp| kp.pubkey() == voter_wallet)
+        .ok_or_else(|| anyhow::anyhow!("User seed not found"))?;
 
-            // Skip the 8-byte discriminator
-            let data = &account.data[8..];
+    log::info!("Voter Keypair: {}", voter_keypair.pubkey());
 
-            // Find the actual data length by looking for the end of meaningful data
-            let mut actual_data_len = data.len();
-            for (i, &byte) in data.iter().enumerate().rev() {
-                if byte != 0 {
-                    actual_data_len = i + 1;
-                    break;
-                }
-            }
+    // Check if user has enough SOL balance for transaction fees
+    let program = state.solana_client.program(solana_dao::ID)?;
+    let balance = program.rpc().get_balance(&voter_wallet).await?;
+    log::info!("User SOL balance: {} lamports", balance);
 
-            // Only deserialize the actual data portion
-            let actual_data = &data[..actual_data_len];
+    if balance < 5000 {
+        // Less than 0.000005 SOL (minimum for transaction fees)
+        return Err(anyhow::anyhow!(
+            "You don't have enough SOL balance to vote. Please fund your account with at least 0.001 SOL for transaction fees."
+        ));
+    }
 
-            match solana_dao::Group::try_from_slice(actual_data) {
-                Ok(group) => group,
-                Err(e) => {
-                    log::error!("Failed to deserialize group {}: {}", group_id, e);
-                    return Err(anyhow::anyhow!("Failed to deserialize group: {}", e));
+    let mut instruction = build_vote_instruction(
+        group_id,
+        proposal_id,
+        choice,
+        allocations,
+        disclosure,
+        voter_wallet,
+    );
+    let voter_deposit = voter_deposit_pda_for(state, group_id, proposal_id, voter_wallet).await?;
+    // Linked wallets aren't counted on price-weighted or deposit-weighted proposals
+    let (user_account, price_weighting, remaining) =
+        match price_weighting_metas(state, group_id, proposal_id, voter_wallet).await? {
+            Some((weighting, pairs)) => (None, Some(weighting), pairs),
+            None if voter_deposit.is_some() => (None, None, Vec::new()),
+            None => {
+                match linked_wallet_metas(state, telegram_id, group_id, proposal_id, voter_wallet)
+                    .await?
+                {
+                    Some((user_account, wallets)) => (Some(user_account), None, wallets),
+                    None => (None, None, Vec::new()),
                 }
             }
-        }
-        Err(e) => {
-            log::error!("Failed to get group account {}: {}", group_id, e);
-            return Err(anyhow::anyhow!("Failed to get group account: {}", e));
-        }
-    };
+        };
+    let member_record = listed_member_record(state, group_id, voter_wallet).await?;
+    let dues_record = paid_dues_record(state, group_id, voter_wallet).await?;
+    let member_shares = staked_member_shares(state, group_id, voter_wallet).await?;
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    instruction.accounts.extend([
+        optional_account_meta(user_account),
+        optional_account_meta(price_weighting),
+        optional_account_meta(member_record),
+        optional_account_meta(voter_deposit),
+        optional_account_meta(dues_record),
+        optional_writable_account_meta(member_shares),
+        anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+            ban_pda(&group_pda, &voter_wallet),
+            false,
+        ),
+    ]);
+    instruction.accounts.extend(remaining);
 
-    // Fetch all proposal accounts manually (same approach as groups)
-    let mut proposals = Vec::new();
-    for proposal_info in group.proposals {
-        match state.program.rpc().get_account(&proposal_info.pubkey).await {
-            Ok(account) => {
-                if account.data.len() < 8 {
-                    log::error!(
-                        "Proposal account data too short: {} bytes",
-                        account.data.len()
-                    );
-                    continue;
-                }
+    log::info!(
+        "Created instruction with {} accounts, sending...",
+        instruction.accounts.len()
+    );
 
-                // Skip the 8-byte discriminator
-                let data = &account.data[8..];
+    let tx = send_instructions(state, &[instruction], &voter_wallet, &[&voter_keypair]).await?;
 
-                // Find the actual data length by looking for the end of meaningful data
-                let mut actual_data_len = data.len();
-                for (i, &byte) in data.iter().enumerate().rev() {
-                    if byte != 0 {
-                        actual_data_len = i + 1;
-                        break;
-                    }
-                }
+    log::info!("Transaction sent successfully: {}", tx);
+    Ok(tx.to_string())
+}
 
-                // Only deserialize the actual data portion
-                let actual_data = &data[..actual_data_len];
+// "Linked wallets" line for /account, empty when none are linked
+fn linked_wallets_line(wallets: &[Pubkey]) -> String {
+    if wallets.is_empty() {
+        return String::new();
+    }
+    let list = wallets
+        .iter()
+        .map(|w| format!("<code>{}</code>", w))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("🔗 Linked wallets: {}\n", list)
+}
 
-                match solana_dao::Proposal::try_from_slice(actual_data) {
-                    Ok(proposal) => {
-                        log::info!("Successfully fetched proposal: {}", proposal.title);
-                        proposals.push(proposal);
-                    }
-                    Err(e) => {
-                        log::error!(
-                            "Failed to deserialize proposal {}: {}",
-                            proposal_info.proposal_id,
-                            e
-                        );
-                    }
-                }
-            }
-            Err(e) => {
-                log::error!(
-                    "Failed to get proposal account {}: {}",
-                    proposal_info.proposal_id,
-                    e
-                );
-            }
+// The user account and linked wallets that add their weight to the user's vote, None
+// when there are none. SOL-weighted proposals count the wallets themselves, SPL
+// proposals the associated token accounts of those that hold the mint.
+async fn linked_wallet_metas(
+    state: &BotState,
+    telegram_id: i64,
+    group_id: &str,
+    proposal_id: &str,
+    voter_wallet: Pubkey,
+) -> anyhow::Result<
+    Option<(
+        Pubkey,
+        Vec<anchor_client::solana_sdk::instruction::AccountMeta>,
+    )>,
+> {
+    let (user_account_pda, _) = Pubkey::find_program_address(
+        &[b"user_account", telegram_id.to_le_bytes().as_ref()],
+        &solana_dao::ID,
+    );
+    let user_account = match state
+        .program
+        .account::<solana_dao::UserAccount>(user_account_pda)
+        .await
+    {
+        Ok(user_account) => user_account,
+        Err(_) => return Ok(None),
+    };
+    if user_account.linked_wallets.is_empty() || user_account.wallet_pubkey != voter_wallet {
+        return Ok(None);
+    }
+
+    let proposal = get_proposal_results(state, group_id, proposal_id).await?;
+    let accounts: Vec<Pubkey> = match proposal.token_mint {
+        None => return Ok(None),
+        Some(mint) if mint == solana_dao::NATIVE_MINT => user_account.linked_wallets,
+        Some(mint) => {
+            let token_accounts: Vec<Pubkey> = user_account
+                .linked_wallets
+                .iter()
+                .map(|wallet| {
+                    spl_associated_token_account::get_associated_token_address(wallet, &mint)
+                })
+                .collect();
+            // A wallet that never held the mint has no token account to pass
+            let existing = state
+                .program
+                .rpc()
+                .get_multiple_accounts(&token_accounts)
+                .await?;
+            token_accounts
+                .into_iter()
+                .zip(existing)
+                .filter_map(|(token_account, account)| account.map(|_| token_account))
+                .collect()
         }
+    };
+    if accounts.is_empty() {
+        return Ok(None);
     }
 
-    Ok(proposals)
+    let metas = accounts
+        .into_iter()
+        .map(|account| {
+            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(account, false)
+        })
+        .collect();
+    Ok(Some((user_account_pda, metas)))
+}
+
+// The wallet's VoterDeposit in the registrar of a deposit-weighted proposal, None for
+// any other proposal
+async fn voter_deposit_pda_for(
+    state: &BotState,
+    group_id: &str,
+    proposal_id: &str,
+    voter_wallet: Pubkey,
+) -> anyhow::Result<Option<Pubkey>> {
+    let proposal = get_proposal_results(state, group_id, proposal_id).await?;
+    Ok(proposal.deposit_voting.map(|deposit_voting| {
+        Pubkey::find_program_address(
+            &[
+                b"voter_deposit",
+                deposit_voting.registrar.as_ref(),
+                voter_wallet.as_ref(),
+            ],
+            &solana_dao::ID,
+        )
+        .0
+    }))
 }
 
-async fn vote_on_proposal(
+// The PriceWeighting of a price-weighted proposal, None for any other, with a
+// (holding, price account) pair for each accepted mint the wallet holds. The holding is
+// the wallet itself for SOL and its associated token account otherwise.
+async fn price_weighting_metas(
     state: &BotState,
     group_id: &str,
     proposal_id: &str,
-    choice: u8,
     voter_wallet: Pubkey,
-) -> anyhow::Result<String> {
-    // Get the group PDA
+) -> anyhow::Result<
+    Option<(
+        Pubkey,
+        Vec<anchor_client::solana_sdk::instruction::AccountMeta>,
+    )>,
+> {
+    let proposal = get_proposal_results(state, group_id, proposal_id).await?;
+    if !proposal.price_weighted {
+        return Ok(None);
+    }
+
     let (group_pda, _) =
         Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
-
-    log::info!("Group PDA: {}", group_pda);
-
-    // Get the proposal PDA - use first 8 bytes of group_pda and proposal_id to stay within 32-byte seed limit
     let (proposal_pda, _) = Pubkey::find_program_address(
         &[
             b"proposal",
@@ -1909,89 +10325,44 @@ async fn vote_on_proposal(
         ],
         &solana_dao::ID,
     );
-
-    log::info!("Proposal PDA: {}", proposal_pda);
-
-    // Find the user's seed and generate keypair
-    let voter_keypair = {
-        let user_seeds = state.user_seeds.lock().await;
-        let seed = user_seeds
-            .values()
-            .find(|seed| {
-                let kp = Keypair::new_from_array(**seed);
-                kp.pubkey() == voter_wallet
-            })
-            .copied()
-            .ok_or_else(|| anyhow::anyhow!("User seed not found"))?;
-        Keypair::new_from_array(seed)
-    };
-
-    log::info!("Voter Keypair: {}", voter_keypair.pubkey());
-
-    // Check if user has enough SOL balance for transaction fees
-    let program = state.solana_client.program(solana_dao::ID)?;
-    let balance = program.rpc().get_balance(&voter_wallet).await?;
-    log::info!("User SOL balance: {} lamports", balance);
-
-    if balance < 5000 {
-        // Less than 0.000005 SOL (minimum for transaction fees)
-        return Err(anyhow::anyhow!(
-            "You don't have enough SOL balance to vote. Please fund your account with at least 0.001 SOL for transaction fees."
-        ));
-    }
-
-    // For SOL-weighted voting, we can use simple placeholders since the program
-    // uses ctx.accounts.voter.lamports() directly and doesn't validate the token accounts
-    let instruction = anchor_client::solana_sdk::instruction::Instruction {
-        program_id: solana_dao::ID,
-        accounts: vec![
-            anchor_client::solana_sdk::instruction::AccountMeta::new(proposal_pda, false),
-            anchor_client::solana_sdk::instruction::AccountMeta::new(voter_wallet, true),
-            // voter_token_account - use voter wallet as placeholder (not validated for SOL voting)
-            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
-                voter_wallet, // Use voter wallet as placeholder
-                false,
-            ),
-            // token_program - use system program as placeholder (not validated for SOL voting)
-            anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
-                system_program::ID, // Use system program as placeholder
-                false,
-            ),
-        ],
-        data: vec![188, 239, 13, 88, 119, 199, 251, 119, choice], // discriminator + choice
-    };
-
-    let program_id = instruction.program_id.clone();
-    log::info!(
-        "Created instruction with {} accounts",
-        instruction.accounts.len()
-    );
-
-    let recent_blockhash = state
-        .solana_client
-        .program(program_id)?
-        .rpc()
-        .get_latest_blockhash()
-        .await?;
-    log::info!("Got recent blockhash: {}", recent_blockhash);
-
-    let transaction = anchor_client::solana_sdk::transaction::Transaction::new_signed_with_payer(
-        &[instruction],
-        Some(&voter_wallet),
-        &[&voter_keypair],
-        recent_blockhash,
+    let (weighting_pda, _) = Pubkey::find_program_address(
+        &[b"price_weighting", proposal_pda.as_ref()],
+        &solana_dao::ID,
     );
-    log::info!("Created transaction, sending...");
-
-    let tx = state
-        .solana_client
-        .program(program_id)?
-        .rpc()
-        .send_and_confirm_transaction(&transaction)
-        .await?;
+    let account = state.program.rpc().get_account(&weighting_pda).await?;
+    let weighting = solana_dao::PriceWeighting::deserialize(&mut &account.data[8..])?;
+
+    let holdings: Vec<Pubkey> = weighting
+        .feeds
+        .iter()
+        .map(|feed| {
+            if feed.mint == solana_dao::NATIVE_MINT {
+                voter_wallet
+            } else {
+                spl_associated_token_account::get_associated_token_address(
+                    &voter_wallet,
+                    &feed.mint,
+                )
+            }
+        })
+        .collect();
+    let held = state.program.rpc().get_multiple_accounts(&holdings).await?;
 
-    log::info!("Transaction sent successfully: {}", tx);
-    Ok(tx.to_string())
+    let mut metas = Vec::new();
+    for ((feed, holding), account) in weighting.feeds.iter().zip(&holdings).zip(held) {
+        if account.is_some() {
+            metas.push(
+                anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(*holding, false),
+            );
+            metas.push(
+                anchor_client::solana_sdk::instruction::AccountMeta::new_readonly(
+                    feed.oracle,
+                    false,
+                ),
+            );
+        }
+    }
+    Ok(Some((weighting_pda, metas)))
 }
 
 async fn get_proposal_results(
@@ -2155,14 +10526,28 @@ async fn ensure_payer_funded(
     Ok(())
 }
 
-async fn message_handler(bot: Bot, msg: Message) -> ResponseResult<()> {
+async fn message_handler(bot: Bot, msg: Message, state: BotState) -> ResponseResult<()> {
+    let vocab = chat_vocabulary(&state, msg.chat.id).await;
     log::info!("Received message: {:?}", msg.text());
     if let Some(text) = msg.text() {
         if text.starts_with("/login") {
             log::info!("Login command detected, but not processed by command handler");
-            bot.send_message(msg.chat.id, "🤖 Bot is working! Login command detected but there might be an issue with command processing.").await?;
+            bot.send_localized(&vocab,msg.chat.id, "🤖 Bot is working! Login command detected but there might be an issue with command processing.").await?;
         } else {
             log::info!("Non-command message: {}", text);
+            if !msg.chat.is_private() {
+                let reply_to = msg.reply_to_message().map(|parent| parent.id.0);
+                let date = msg.date.timestamp();
+                if let Err(e) = state
+                    .discussions
+                    .update(msg.chat.id.0, |log| {
+                        log.record(msg.id.0, reply_to, date, text)
+                    })
+                    .await
+                {
+                    log::error!("Failed to log message for discussion archives: {}", e);
+                }
+            }
         }
     }
     Ok(())
@@ -2192,27 +10577,846 @@ async fn main() {
         BotCommand::new("createproposal", "Create a new proposal"),
         BotCommand::new("listproposals", "List proposals for a group"),
         BotCommand::new("vote", "Vote on a proposal"),
+        BotCommand::new(
+            "shadowvote",
+            "Cast a non-counting vote as a prospective member",
+        ),
         BotCommand::new("results", "Get proposal results"),
         BotCommand::new("login", "Create or access your Solana account"),
         BotCommand::new("account", "Show your account information"),
         BotCommand::new("balance", "Show your SOL balance"),
         BotCommand::new("fundaccount", "Fund your account with SOL for voting"),
+        BotCommand::new("kiosk", "Open the voting kiosk"),
+        BotCommand::new("settings", "Show or change group settings"),
+        BotCommand::new("translate", "Translate proposals into your language"),
+        BotCommand::new("calendar", "Show voting deadlines for the next two weeks"),
+        BotCommand::new("archive", "Archive the discussion around a proposal"),
+        BotCommand::new(
+            "eligible",
+            "Preview who can vote on a proposal and with what weight",
+        ),
+        BotCommand::new(
+            "invite",
+            "Create an invite code for joining the group's DAO",
+        ),
+        BotCommand::new("join", "Join the group's DAO with an invite code"),
+        BotCommand::new("membership", "Set the group's member cap and visibility"),
+        BotCommand::new("template", "List, save or reuse proposal templates"),
+        BotCommand::new("deposit", "Lock SOL for deposit-weighted votes"),
+        BotCommand::new("language", "Show or change the bot's language in this chat"),
+        BotCommand::new(
+            "delegate",
+            "Delegate your votes to another member, or see who delegates to you",
+        ),
+        BotCommand::new("undelegate", "Take back the votes you delegated"),
+        BotCommand::new("withdraw", "Send SOL from your wallet to another address"),
+        BotCommand::new(
+            "linkwallet",
+            "Link your own wallet so its holdings count toward your votes",
+        ),
     ];
 
-    if let Err(e) = bot.set_my_commands(commands).await {
+    if let Err(e) = bot.set_my_commands(commands.clone()).await {
         log::error!("Failed to set bot commands: {}", e);
         // Continue execution even if command setting fails
     }
+    // Users whose Telegram app is in one of the bot's other languages see the menu in it
+    for language in i18n::Language::ALL {
+        if language == i18n::Language::En {
+            continue;
+        }
+        let translated: Vec<BotCommand> = commands
+            .iter()
+            .map(|c| BotCommand::new(c.command.clone(), i18n::translate(language, &c.description)))
+            .collect();
+        if let Err(e) = bot
+            .set_my_commands(translated)
+            .language_code(language.code())
+            .await
+        {
+            log::error!("Failed to set {} bot commands: {}", language.code(), e);
+        }
+    }
+
+    // Serve the voting kiosk and its API alongside the bot
+    let bind_addr = std::env::var("WEB_BIND_ADDR").unwrap_or_else(|_| "0.0.0.0:8080".to_string());
+    match bind_addr.parse() {
+        Ok(addr) => {
+            let web_state = state.clone();
+            let bot_token = bot.token().to_string();
+            tokio::spawn(async move {
+                if let Err(e) = web::serve(web_state, addr, bot_token).await {
+                    log::error!("Web service stopped: {}", e);
+                }
+            });
+        }
+        Err(e) => log::error!("Invalid WEB_BIND_ADDR '{}': {}", bind_addr, e),
+    }
+
+    // Stream proposal and vote updates over gRPC when GRPC_BIND_ADDR is set
+    if let Ok(bind_addr) = std::env::var("GRPC_BIND_ADDR") {
+        match bind_addr.parse() {
+            Ok(addr) => {
+                let grpc_state = state.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = grpc::serve(grpc_state, addr).await {
+                        log::error!("gRPC service stopped: {}", e);
+                    }
+                });
+            }
+            Err(e) => log::error!("Invalid GRPC_BIND_ADDR '{}': {}", bind_addr, e),
+        }
+    }
+
+    // Push votes and new proposals into linked chats as the program emits them
+    tokio::spawn(events::run(bot.clone(), state.clone()));
+
+    // Keep pinned scoreboards of active proposals current every SCOREBOARD_INTERVAL_SECS
+    let scoreboard_interval = match std::env::var("SCOREBOARD_INTERVAL_SECS") {
+        Ok(interval) => interval.parse().unwrap_or_else(|_| {
+            log::error!("Invalid SCOREBOARD_INTERVAL_SECS '{}'", interval);
+            scoreboard::DEFAULT_SCOREBOARD_INTERVAL_SECS
+        }),
+        Err(_) => scoreboard::DEFAULT_SCOREBOARD_INTERVAL_SECS,
+    };
+    if scoreboard_interval > 0 {
+        tokio::spawn(scoreboard::run(
+            bot.clone(),
+            state.clone(),
+            std::time::Duration::from_secs(scoreboard_interval),
+        ));
+    }
+
+    // Finalize ended proposals and post their results every RESULTS_INTERVAL_SECS
+    let results_interval = match std::env::var("RESULTS_INTERVAL_SECS") {
+        Ok(interval) => interval.parse().unwrap_or_else(|_| {
+            log::error!("Invalid RESULTS_INTERVAL_SECS '{}'", interval);
+            announcer::DEFAULT_RESULTS_INTERVAL_SECS
+        }),
+        Err(_) => announcer::DEFAULT_RESULTS_INTERVAL_SECS,
+    };
+    if results_interval > 0 {
+        tokio::spawn(announcer::run(
+            bot.clone(),
+            state.clone(),
+            std::time::Duration::from_secs(results_interval),
+        ));
+    }
+
+    // Close settled proposals past their grace period when GC_INTERVAL_SECS is set
+    if let Ok(interval) = std::env::var("GC_INTERVAL_SECS") {
+        match interval.parse() {
+            Ok(secs) if secs > 0 => {
+                tokio::spawn(gc::run(state.clone(), std::time::Duration::from_secs(secs)));
+            }
+            _ => log::error!("Invalid GC_INTERVAL_SECS '{}'", interval),
+        }
+    }
+
+    let webhook_options = match webhook::options_from_env() {
+        Ok(options) => options,
+        Err(e) => {
+            log::error!("Invalid webhook configuration: {}", e);
+            return;
+        }
+    };
 
-    Dispatcher::builder(
-        bot,
-        Update::filter_message()
-            .branch(dptree::entry().filter_command::<Command>().endpoint(answer))
-            .branch(dptree::endpoint(message_handler)),
+    let mut dispatcher = Dispatcher::builder(
+        bot.clone(),
+        dptree::entry()
+            .branch(
+                Update::filter_message()
+                    .branch(dptree::entry().filter_command::<Command>().endpoint(answer))
+                    .branch(dptree::endpoint(message_handler)),
+            )
+            .branch(Update::filter_callback_query().endpoint(callbacks::handle_callback)),
     )
     .dependencies(dptree::deps![state])
     .enable_ctrlc_handler()
-    .build()
-    .dispatch()
-    .await;
+    .build();
+
+    // Receive updates through a webhook when WEBHOOK_URL is set, otherwise long poll
+    match webhook_options {
+        Some(options) => {
+            log::info!("Listening for webhook updates on {}", options.address);
+            let listener = match teloxide::update_listeners::webhooks::axum(bot, options).await {
+                Ok(listener) => listener,
+                Err(e) => {
+                    log::error!("Failed to set up the webhook: {}", e);
+                    return;
+                }
+            };
+            dispatcher
+                .dispatch_with_listener(
+                    listener,
+                    LoggingErrorHandler::with_custom_text("An error from the webhook listener"),
+                )
+                .await;
+        }
+        None => dispatcher.dispatch().await,
+    }
+}
+
+// The mirror layouts and hand-built instruction data above must stay byte-compatible
+// with the program; its golden fixtures (programs/solana-dao/tests/golden) are the
+// shared reference.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::AnchorSerialize;
+
+    fn golden(hex: &str) -> Vec<u8> {
+        let hex: String = hex.split_whitespace().collect();
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    // Decode an account with its mirror type and check nothing is left over or lost
+    fn assert_round_trip<T: AnchorDeserialize + AnchorSerialize>(hex: &str) -> T {
+        let bytes = golden(hex);
+        let mut data = &bytes[8..];
+        let account = T::deserialize(&mut data).unwrap();
+        assert!(data.is_empty(), "mirror layout is missing trailing fields");
+        assert_eq!(account.try_to_vec().unwrap(), &bytes[8..]);
+        account
+    }
+
+    #[test]
+    fn mirror_accounts_match_program_layouts() {
+        let proposal: solana_dao::Proposal = assert_round_trip(include_str!(
+            "../../programs/solana-dao/tests/golden/proposal.hex"
+        ));
+        assert_eq!(proposal.ballots().count(), 2);
+        let choices = vec!["Yes".to_string(), "No".to_string()];
+        assert_eq!(outcome_line(&proposal, &choices), "🏆 Winner: Yes\n");
+        assert_round_trip::<solana_dao::DaoRegistry>(include_str!(
+            "../../programs/solana-dao/tests/golden/dao_registry.hex"
+        ));
+        assert_round_trip::<solana_dao::Group>(include_str!(
+            "../../programs/solana-dao/tests/golden/group.hex"
+        ));
+        for fixture in [
+            include_str!("../../programs/solana-dao/tests/golden/proposal_election.hex"),
+            include_str!("../../programs/solana-dao/tests/golden/proposal_config_change.hex"),
+            include_str!("../../programs/solana-dao/tests/golden/proposal_membership_change.hex"),
+            include_str!("../../programs/solana-dao/tests/golden/proposal_grant.hex"),
+            include_str!("../../programs/solana-dao/tests/golden/proposal_stream.hex"),
+            include_str!("../../programs/solana-dao/tests/golden/proposal_bounty.hex"),
+        ] {
+            assert_round_trip::<solana_dao::Proposal>(fixture);
+        }
+        assert_round_trip::<solana_dao::Member>(include_str!(
+            "../../programs/solana-dao/tests/golden/member.hex"
+        ));
+        assert_round_trip::<solana_dao::ProposalIndex>(include_str!(
+            "../../programs/solana-dao/tests/golden/proposal_index.hex"
+        ));
+        assert_round_trip::<solana_dao::UserAccount>(include_str!(
+            "../../programs/solana-dao/tests/golden/user_account.hex"
+        ));
+        assert_round_trip::<solana_dao::ProgramConfig>(include_str!(
+            "../../programs/solana-dao/tests/golden/program_config.hex"
+        ));
+        assert_round_trip::<solana_dao::PriceWeighting>(include_str!(
+            "../../programs/solana-dao/tests/golden/price_weighting.hex"
+        ));
+        assert_round_trip::<solana_dao::ProposalTemplate>(include_str!(
+            "../../programs/solana-dao/tests/golden/proposal_template.hex"
+        ));
+        assert_round_trip::<solana_dao::DepositRegistrar>(include_str!(
+            "../../programs/solana-dao/tests/golden/deposit_registrar.hex"
+        ));
+        assert_round_trip::<solana_dao::VoterDeposit>(include_str!(
+            "../../programs/solana-dao/tests/golden/voter_deposit.hex"
+        ));
+        let record: solana_dao::DuesRecord = assert_round_trip(include_str!(
+            "../../programs/solana-dao/tests/golden/dues_record.hex"
+        ));
+        let group: solana_dao::Group = assert_round_trip(include_str!(
+            "../../programs/solana-dao/tests/golden/group.hex"
+        ));
+        let dues = group.dues.unwrap();
+        let report = overdue_dues_report(&[record], &dues, 1_702_592_000 + 10 * 86_400);
+        assert!(report.contains("10 days late (can't vote)"));
+        let lottery: solana_dao::VoterLottery = assert_round_trip(include_str!(
+            "../../programs/solana-dao/tests/golden/voter_lottery.hex"
+        ));
+        assert!(lottery_line(&lottery).contains("winners (1 SOL each)"));
+        let grant: solana_dao::Grant = assert_round_trip(include_str!(
+            "../../programs/solana-dao/tests/golden/grant.hex"
+        ));
+        assert!(grant_line(&grant).contains("1/2 milestones approved, 2 of 5 SOL released"));
+        let stream: solana_dao::Stream = assert_round_trip(include_str!(
+            "../../programs/solana-dao/tests/golden/stream.hex"
+        ));
+        assert!(stream_line(&stream, stream.start + 86_400).contains("0 of 12 SOL vested"));
+        let halfway = (stream.start + stream.end) / 2;
+        assert!(stream_line(&stream, halfway).contains("6 of 12 SOL vested, 1 SOL withdrawn"));
+        let bounty: solana_dao::Bounty = assert_round_trip(include_str!(
+            "../../programs/solana-dao/tests/golden/bounty.hex"
+        ));
+        assert!(bounty_board(&[bounty]).contains("docs-translation</code> - 0.5 SOL, submitted by"));
+        let member_shares: solana_dao::MemberShares = assert_round_trip(include_str!(
+            "../../programs/solana-dao/tests/golden/member_shares.hex"
+        ));
+        let stake_to_join = group.stake_to_join.unwrap();
+        let summary = share_summary(
+            &stake_to_join,
+            Some(&member_shares),
+            8 * LAMPORTS_PER_SOL,
+            member_shares.locked_until - 60,
+        );
+        assert!(summary.contains("You hold 15 shares, worth about 3 SOL"));
+        assert!(summary.contains("Locked until 2023-11-17"));
+        let delegation: solana_dao::Delegation = assert_round_trip(include_str!(
+            "../../programs/solana-dao/tests/golden/delegation.hex"
+        ));
+        assert!(!delegation.expired(delegation.created_at));
+        assert!(delegation.expired(1_702_592_001));
+    }
+
+    #[test]
+    fn instruction_data_matches_program_encoding() {
+        let vote = build_vote_instruction(
+            "tg_1",
+            "golden-proposal",
+            1,
+            None,
+            Some("grant recipient"),
+            Pubkey::default(),
+        );
+        assert_eq!(
+            vote.data,
+            golden(include_str!(
+                "../../programs/solana-dao/tests/golden/ix_vote_on_proposal.hex"
+            ))
+        );
+
+        let split_vote = build_vote_instruction(
+            "tg_1",
+            "golden-proposal",
+            0,
+            Some(&[600, 300, 100]),
+            None,
+            Pubkey::default(),
+        );
+        assert_eq!(
+            split_vote.data,
+            golden(include_str!(
+                "../../programs/solana-dao/tests/golden/ix_vote_on_proposal_split.hex"
+            ))
+        );
+
+        let set_metadata_uri = build_set_metadata_uri_instruction(
+            "tg_1",
+            "golden-proposal",
+            Some("https://example.org/archive.json"),
+            Pubkey::default(),
+        );
+        assert_eq!(
+            set_metadata_uri.data,
+            golden(include_str!(
+                "../../programs/solana-dao/tests/golden/ix_set_proposal_metadata_uri.hex"
+            ))
+        );
+
+        let relay = build_relay_vote_instructions(
+            "tg_1",
+            "golden-proposal",
+            1,
+            1_700_000_600,
+            Pubkey::default(),
+            &[0; 64],
+            None,
+            None,
+            None,
+            None,
+            Pubkey::default(),
+        );
+        assert_eq!(
+            relay[1].data,
+            golden(include_str!(
+                "../../programs/solana-dao/tests/golden/ix_relay_vote.hex"
+            ))
+        );
+        // The signed payload the precompile checks ends with the choice and expiry
+        assert!(relay[0]
+            .data
+            .ends_with(&[[1].as_slice(), &1_700_000_600i64.to_le_bytes()].concat()));
+
+        let enable_offchain = build_enable_offchain_voting_instruction(
+            "tg_1",
+            "golden-proposal",
+            Pubkey::new_from_array([17; 32]),
+            Pubkey::default(),
+        );
+        assert_eq!(
+            enable_offchain.data,
+            golden(include_str!(
+                "../../programs/solana-dao/tests/golden/ix_enable_offchain_voting.hex"
+            ))
+        );
+
+        let submit_tally = build_submit_offchain_tally_instruction(
+            "tg_1",
+            "golden-proposal",
+            [18; 32],
+            &[40, 25, 3],
+            68,
+            Pubkey::default(),
+        );
+        assert_eq!(
+            submit_tally.data,
+            golden(include_str!(
+                "../../programs/solana-dao/tests/golden/ix_submit_offchain_tally.hex"
+            ))
+        );
+
+        let settle_tally = build_settle_offchain_tally_instruction("tg_1", "golden-proposal");
+        assert_eq!(
+            settle_tally.data,
+            golden(include_str!(
+                "../../programs/solana-dao/tests/golden/ix_settle_offchain_tally.hex"
+            ))
+        );
+
+        let shadow_vote =
+            build_shadow_vote_instruction("tg_1", "golden-proposal", 2, Pubkey::default());
+        assert_eq!(
+            shadow_vote.data,
+            golden(include_str!(
+                "../../programs/solana-dao/tests/golden/ix_cast_shadow_vote.hex"
+            ))
+        );
+
+        let join = build_join_with_invite_instruction("tg_1", "garden-2024", Pubkey::default());
+        assert_eq!(
+            join.data,
+            golden(include_str!(
+                "../../programs/solana-dao/tests/golden/ix_join_with_invite.hex"
+            ))
+        );
+
+        let membership_policy = build_set_membership_policy_instruction(
+            "tg_1",
+            25,
+            solana_dao::GroupVisibility::Private,
+            Pubkey::default(),
+        );
+        assert_eq!(
+            membership_policy.data,
+            golden(include_str!(
+                "../../programs/solana-dao/tests/golden/ix_set_membership_policy.hex"
+            ))
+        );
+
+        let proposal_cap = build_set_max_active_proposals_instruction("tg_1", 3, Pubkey::default());
+        assert_eq!(
+            proposal_cap.data,
+            golden(include_str!(
+                "../../programs/solana-dao/tests/golden/ix_set_max_active_proposals.hex"
+            ))
+        );
+
+        let choice_details = build_set_choice_details_instruction(
+            "tg_1",
+            "golden-proposal",
+            &[
+                solana_dao::ChoiceDetail {
+                    description: Some("Runs the seed library".to_string()),
+                    uri: Some("https://example.org/candidates/ana".to_string()),
+                },
+                solana_dao::ChoiceDetail::default(),
+                solana_dao::ChoiceDetail {
+                    description: Some("Abstain from the election".to_string()),
+                    uri: None,
+                },
+            ],
+            Pubkey::default(),
+        );
+        assert_eq!(
+            choice_details.data,
+            golden(include_str!(
+                "../../programs/solana-dao/tests/golden/ix_set_choice_details.hex"
+            ))
+        );
+
+        let profile = build_set_group_profile_instruction(
+            "tg_1",
+            &solana_dao::GroupProfile {
+                avatar_uri: Some("ipfs://bafkreiavatar".to_string()),
+                socials: vec![
+                    "https://x.com/seedlibrary".to_string(),
+                    "https://github.com/seedlibrary".to_string(),
+                ],
+                contact: Some("hello@seedlibrary.example.org".to_string()),
+                ..Default::default()
+            },
+            Pubkey::default(),
+        );
+        assert_eq!(
+            profile.data,
+            golden(include_str!(
+                "../../programs/solana-dao/tests/golden/ix_set_group_profile.hex"
+            ))
+        );
+
+        let cooldown =
+            build_set_proposal_cooldown_instruction("tg_1", 12 * 60 * 60, Pubkey::default());
+        assert_eq!(
+            cooldown.data,
+            golden(include_str!(
+                "../../programs/solana-dao/tests/golden/ix_set_proposal_cooldown.hex"
+            ))
+        );
+
+        let tie_break = build_set_tie_break_instruction(
+            "tg_1",
+            parse_tie_break("revote 24").unwrap(),
+            Pubkey::default(),
+        );
+        assert_eq!(
+            tie_break.data,
+            golden(include_str!(
+                "../../programs/solana-dao/tests/golden/ix_set_tie_break.hex"
+            ))
+        );
+        assert_eq!(parse_tie_break("revote 200"), None);
+
+        let threshold = build_set_sponsor_threshold_instruction("tg_1", 3, Pubkey::default());
+        assert_eq!(
+            threshold.data,
+            golden(include_str!(
+                "../../programs/solana-dao/tests/golden/ix_set_sponsor_threshold.hex"
+            ))
+        );
+        let co_sponsor = build_co_sponsor_instruction("tg_1", "golden-proposal", Pubkey::default());
+        assert_eq!(
+            co_sponsor.data,
+            golden(include_str!(
+                "../../programs/solana-dao/tests/golden/ix_co_sponsor.hex"
+            ))
+        );
+
+        let deposit = build_post_proposal_deposit_instruction(
+            "tg_1",
+            "golden-proposal",
+            Pubkey::default(),
+            50_000_000,
+        );
+        assert_eq!(
+            deposit.data,
+            golden(include_str!(
+                "../../programs/solana-dao/tests/golden/ix_post_proposal_deposit.hex"
+            ))
+        );
+
+        let activate =
+            build_activate_proposal_instruction("tg_1", "golden-proposal", Pubkey::default());
+        assert_eq!(
+            activate.data,
+            golden(include_str!(
+                "../../programs/solana-dao/tests/golden/ix_activate_proposal.hex"
+            ))
+        );
+
+        let banned = Pubkey::new_from_array([7; 32]);
+        let ban = build_ban_member_instruction(
+            "tg_1",
+            banned,
+            "Spamming proposals",
+            1_702_592_000,
+            None,
+            Pubkey::default(),
+        );
+        assert_eq!(
+            ban.data,
+            golden(include_str!(
+                "../../programs/solana-dao/tests/golden/ix_ban_member.hex"
+            ))
+        );
+        let unban = build_unban_member_instruction("tg_1", banned, Pubkey::default());
+        assert_eq!(
+            unban.data,
+            golden(include_str!(
+                "../../programs/solana-dao/tests/golden/ix_unban_member.hex"
+            ))
+        );
+
+        let create_template = build_create_proposal_template_instruction(
+            "tg_1",
+            &solana_dao::ProposalTemplate {
+                group: Pubkey::default(),
+                template_id: "monthly-budget".to_string(),
+                title: "Monthly budget".to_string(),
+                description: "Split this month's budget".to_string(),
+                choices: vec!["Events".to_string(), "Tools".to_string()],
+                voting_duration: 2 * 24 * 60 * 60,
+                token_mint: Some(solana_dao::NATIVE_MINT),
+                metadata_uri: None,
+                kind: solana_dao::ProposalKind::Text,
+                member_weighted: false,
+                split_voting: true,
+                instance_count: 0,
+                created_at: 0,
+                bump: 0,
+            },
+            Pubkey::default(),
+        );
+        assert_eq!(
+            create_template.data,
+            golden(include_str!(
+                "../../programs/solana-dao/tests/golden/ix_create_proposal_template.hex"
+            ))
+        );
+
+        let configure_registrar = build_configure_deposit_registrar_instruction(
+            "tg_1",
+            7 * 24 * 60 * 60,
+            Pubkey::default(),
+        );
+        assert_eq!(
+            configure_registrar.data,
+            golden(include_str!(
+                "../../programs/solana-dao/tests/golden/ix_configure_deposit_registrar.hex"
+            ))
+        );
+        let deposit =
+            build_deposit_voting_tokens_instruction("tg_1", 4_000_000_000, Pubkey::default());
+        assert_eq!(
+            deposit.data,
+            golden(include_str!(
+                "../../programs/solana-dao/tests/golden/ix_deposit_voting_tokens.hex"
+            ))
+        );
+        let withdraw =
+            build_withdraw_voting_tokens_instruction("tg_1", 1_000_000_000, Pubkey::default());
+        assert_eq!(
+            withdraw.data,
+            golden(include_str!(
+                "../../programs/solana-dao/tests/golden/ix_withdraw_voting_tokens.hex"
+            ))
+        );
+        let require_deposits =
+            build_set_deposit_voting_instruction("tg_1", "golden-proposal", Pubkey::default());
+        assert_eq!(
+            require_deposits.data,
+            golden(include_str!(
+                "../../programs/solana-dao/tests/golden/ix_set_deposit_voting.hex"
+            ))
+        );
+        let dues = solana_dao::DuesPolicy {
+            mint: solana_dao::NATIVE_MINT,
+            amount: 10_000_000,
+            period_secs: 30 * 86_400,
+            grace_secs: 7 * 86_400,
+            required_for_voting: true,
+        };
+        let set_dues = build_set_dues_policy_instruction("tg_1", Some(&dues), Pubkey::default());
+        assert_eq!(
+            set_dues.data,
+            golden(include_str!(
+                "../../programs/solana-dao/tests/golden/ix_set_dues_policy.hex"
+            ))
+        );
+        let pay_dues = build_pay_dues_instruction("tg_1", 3, Pubkey::default());
+        assert_eq!(
+            pay_dues.data,
+            golden(include_str!(
+                "../../programs/solana-dao/tests/golden/ix_pay_dues.hex"
+            ))
+        );
+        let bounty_instructions = [
+            (
+                build_post_bounty_instruction(
+                    "tg_1",
+                    "docs-translation",
+                    "https://example.com/bounties/docs-translation",
+                    500_000_000,
+                    Pubkey::default(),
+                ),
+                include_str!("../../programs/solana-dao/tests/golden/ix_post_bounty.hex"),
+            ),
+            (
+                build_claim_bounty_instruction("tg_1", "docs-translation", Pubkey::default()),
+                include_str!("../../programs/solana-dao/tests/golden/ix_claim_bounty.hex"),
+            ),
+            (
+                build_submit_bounty_instruction(
+                    "tg_1",
+                    "docs-translation",
+                    "https://example.com/pull/42",
+                    Pubkey::default(),
+                ),
+                include_str!("../../programs/solana-dao/tests/golden/ix_submit_bounty.hex"),
+            ),
+            (
+                build_approve_bounty_instruction(
+                    "tg_1",
+                    "docs-translation",
+                    Pubkey::default(),
+                    Pubkey::default(),
+                ),
+                include_str!("../../programs/solana-dao/tests/golden/ix_approve_bounty.hex"),
+            ),
+            (
+                build_cancel_bounty_instruction("tg_1", "docs-translation", Pubkey::default()),
+                include_str!("../../programs/solana-dao/tests/golden/ix_cancel_bounty.hex"),
+            ),
+        ];
+        for (instruction, hex) in bounty_instructions {
+            assert_eq!(instruction.data, golden(hex));
+        }
+        let share_instructions = [
+            (
+                build_set_stake_to_join_instruction("tg_1", Some(100_000_000), Pubkey::default()),
+                include_str!("../../programs/solana-dao/tests/golden/ix_set_stake_to_join.hex"),
+            ),
+            (
+                build_stake_shares_instruction("tg_1", 5, Pubkey::default()),
+                include_str!("../../programs/solana-dao/tests/golden/ix_stake_shares.hex"),
+            ),
+            (
+                build_ragequit_instruction("tg_1", 15, Pubkey::default()),
+                include_str!("../../programs/solana-dao/tests/golden/ix_ragequit.hex"),
+            ),
+        ];
+        for (instruction, hex) in share_instructions {
+            assert_eq!(instruction.data, golden(hex));
+        }
+
+        let linked_wallet = Pubkey::new_from_array([5; 32]);
+        let [verify, link] = build_link_wallet_instructions(
+            123_456_789,
+            Pubkey::default(),
+            linked_wallet,
+            &[0; 64],
+            "solana-dao link wallet:challenge:0",
+        );
+        assert!(verify.data.ends_with(b"solana-dao link wallet:challenge:0"));
+        assert_eq!(
+            link.data,
+            golden(include_str!(
+                "../../programs/solana-dao/tests/golden/ix_link_wallet_with_signature.hex"
+            ))
+        );
+        let unlink = build_unlink_wallet_instruction(123_456_789, Pubkey::default(), linked_wallet);
+        assert_eq!(
+            unlink.data,
+            golden(include_str!(
+                "../../programs/solana-dao/tests/golden/ix_unlink_wallet.hex"
+            ))
+        );
+
+        let group_pda = Pubkey::new_from_array([10; 32]);
+        let delegate = build_delegate_votes_instruction(
+            &group_pda,
+            Pubkey::default(),
+            Pubkey::new_from_array([4; 32]),
+            Some(1_702_592_000),
+        );
+        // The golden call is limited to one proposal kind; the bot always delegates every kind
+        let mut expected = golden(include_str!(
+            "../../programs/solana-dao/tests/golden/ix_delegate_votes.hex"
+        ));
+        expected.splice(72..74, [0]);
+        assert_eq!(delegate.data, expected);
+        let undelegate = build_undelegate_votes_instruction(&group_pda, Pubkey::default());
+        assert_eq!(
+            undelegate.data,
+            golden(include_str!(
+                "../../programs/solana-dao/tests/golden/ix_undelegate_votes.hex"
+            ))
+        );
+
+        let instantiate = build_instantiate_from_template_instruction(
+            "tg_1",
+            "monthly-budget",
+            "budget-2023-11",
+            1_700_000_000,
+            0,
+            Pubkey::default(),
+        );
+        assert_eq!(
+            instantiate.data,
+            golden(include_str!(
+                "../../programs/solana-dao/tests/golden/ix_instantiate_from_template.hex"
+            ))
+        );
+
+        let close = build_close_stale_proposal_instruction(
+            "tg_1",
+            "golden-proposal",
+            0,
+            Pubkey::default(),
+            Pubkey::default(),
+        );
+        assert_eq!(
+            close.data,
+            golden(include_str!(
+                "../../programs/solana-dao/tests/golden/ix_close_stale_proposal.hex"
+            ))
+        );
+
+        let settle = build_settle_proposal_deposit_instruction(
+            "tg_1",
+            "golden-proposal",
+            Pubkey::default(),
+            Pubkey::default(),
+        );
+        assert_eq!(
+            settle.data,
+            golden(include_str!(
+                "../../programs/solana-dao/tests/golden/ix_settle_proposal_deposit.hex"
+            ))
+        );
+    }
+
+    #[test]
+    fn withdrawals_keep_both_accounts_rent_exempt() {
+        let balances = WithdrawalBalances {
+            wallet: LAMPORTS_PER_SOL,
+            destination: 0,
+            rent_minimum: 890_880,
+        };
+        // Emptying the wallet is fine, leaving dust behind is not
+        assert!(balances
+            .check(LAMPORTS_PER_SOL - WITHDRAWAL_FEE_LAMPORTS)
+            .is_ok());
+        assert!(balances
+            .check(LAMPORTS_PER_SOL - WITHDRAWAL_FEE_LAMPORTS - 1)
+            .unwrap_err()
+            .contains("below the 0.00089088 SOL it needs to stay open"));
+        assert!(balances.check(LAMPORTS_PER_SOL).is_err());
+        assert!(balances
+            .check(890_879)
+            .unwrap_err()
+            .contains("needs at least 0.00089088 SOL to open"));
+        let funded = WithdrawalBalances {
+            destination: 890_880,
+            ..balances
+        };
+        assert!(funded.check(1).is_ok());
+        let full = WithdrawalBalances {
+            destination: u64::MAX,
+            ..balances
+        };
+        assert!(full.check(1).is_ok());
+    }
+
+    #[test]
+    fn sol_amounts_parse_to_exact_lamports() {
+        assert_eq!(parse_sol_amount("0.1"), Some(100_000_000));
+        assert_eq!(parse_sol_amount("1.000000001"), Some(1_000_000_001));
+        assert_eq!(parse_sol_amount(".5"), Some(500_000_000));
+        assert_eq!(parse_sol_amount("2"), Some(2 * LAMPORTS_PER_SOL));
+        assert_eq!(parse_sol_amount("0.0000000001"), None);
+        assert_eq!(parse_sol_amount("0"), None);
+        assert_eq!(parse_sol_amount("-1"), None);
+        assert_eq!(parse_sol_amount("1e3"), None);
+        assert_eq!(parse_sol_amount("."), None);
+        assert_eq!(parse_sol_amount("18446744074"), None);
+    }
 }
@@ -0,0 +1,241 @@
+// Which Solana cluster the bot talks to, how, and where its explorer links point.
+//
+// SOLANA_CLUSTER picks localnet (the default), devnet, testnet or mainnet, or is an RPC
+// URL for any other cluster. SOLANA_RPC_URL overrides the cluster's public endpoint with
+// one or more comma-separated endpoints, e.g. a paid provider and a fallback; the first
+// that reports healthy at startup is used. SOLANA_WS_URL overrides the websocket
+// endpoint, which is otherwise derived from the RPC URL. SOLANA_COMMITMENT sets the
+// commitment for reads and confirmations (processed, confirmed or finalized).
+//
+// Explorer links default to explorer.solana.com for the cluster. EXPLORER_TX_URL and
+// EXPLORER_ADDRESS_URL replace them with templates containing {signature} and {address},
+// e.g. for Solscan, or for a custom cluster whose RPC URL shouldn't appear in chats.
+
+use anchor_client::solana_client::nonblocking::rpc_client::RpcClient;
+use anchor_client::solana_sdk::commitment_config::CommitmentConfig;
+use anchor_client::Cluster;
+use std::str::FromStr;
+
+const EXPLORER: &str = "https://explorer.solana.com";
+
+#[derive(Clone, Debug)]
+pub struct NetworkConfig {
+    // Name shown by /network: localnet, devnet, testnet, mainnet-beta or custom
+    pub name: &'static str,
+    pub cluster: Cluster,
+    pub commitment: CommitmentConfig,
+    // Every configured RPC endpoint; `cluster` holds the one in use
+    pub rpc_urls: Vec<String>,
+    tx_url: String,
+    address_url: String,
+}
+
+impl NetworkConfig {
+    pub async fn from_env() -> anyhow::Result<Self> {
+        let mut network = Self::from(|name| std::env::var(name).ok())?;
+        if network.rpc_urls.len() > 1 {
+            let healthy = first_healthy(&network.rpc_urls).await;
+            let ws_url = std::env::var("SOLANA_WS_URL").ok();
+            network.cluster = custom_cluster(&healthy, ws_url.as_deref())?;
+        }
+        Ok(network)
+    }
+
+    fn from(var: impl Fn(&str) -> Option<String>) -> anyhow::Result<Self> {
+        let cluster_name = var("SOLANA_CLUSTER").unwrap_or_else(|| "localnet".to_string());
+        let mut cluster = match cluster_name.as_str() {
+            "mainnet-beta" => Cluster::Mainnet,
+            "localnet" | "devnet" | "testnet" | "mainnet" => Cluster::from_str(&cluster_name)?,
+            url if url.starts_with("http://") || url.starts_with("https://") => {
+                custom_cluster(url, None)?
+            }
+            other => anyhow::bail!(
+                "SOLANA_CLUSTER must be localnet, devnet, testnet, mainnet or an RPC URL, not {}",
+                other
+            ),
+        };
+        let name = match cluster {
+            Cluster::Localnet => "localnet",
+            Cluster::Devnet => "devnet",
+            Cluster::Testnet => "testnet",
+            Cluster::Mainnet => "mainnet-beta",
+            _ => "custom",
+        };
+
+        let mut rpc_urls: Vec<String> = var("SOLANA_RPC_URL")
+            .map(|urls| {
+                urls.split(',')
+                    .map(|url| url.trim().to_string())
+                    .filter(|url| !url.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let ws_url = var("SOLANA_WS_URL");
+        if let Some(url) = rpc_urls.first() {
+            cluster = custom_cluster(url, ws_url.as_deref())?;
+        } else {
+            rpc_urls.push(cluster.url().to_string());
+            if let Some(ws_url) = ws_url {
+                cluster = Cluster::Custom(cluster.url().to_string(), ws_url);
+            }
+        }
+
+        let commitment = match var("SOLANA_COMMITMENT").as_deref() {
+            None | Some("processed") => CommitmentConfig::processed(),
+            Some("confirmed") => CommitmentConfig::confirmed(),
+            Some("finalized") => CommitmentConfig::finalized(),
+            Some(other) => anyhow::bail!(
+                "SOLANA_COMMITMENT must be processed, confirmed or finalized, not {}",
+                other
+            ),
+        };
+
+        let query = match name {
+            "mainnet-beta" => String::new(),
+            "devnet" | "testnet" => format!("?cluster={}", name),
+            // The explorer's custom cluster defaults to a local validator
+            _ => "?cluster=custom".to_string(),
+        };
+        let tx_url = var("EXPLORER_TX_URL")
+            .unwrap_or_else(|| format!("{}/tx/{{signature}}{}", EXPLORER, query));
+        let address_url = var("EXPLORER_ADDRESS_URL")
+            .unwrap_or_else(|| format!("{}/address/{{address}}{}", EXPLORER, query));
+        if !tx_url.contains("{signature}") {
+            anyhow::bail!("EXPLORER_TX_URL must contain {{signature}}");
+        }
+        if !address_url.contains("{address}") {
+            anyhow::bail!("EXPLORER_ADDRESS_URL must contain {{address}}");
+        }
+
+        Ok(Self {
+            name,
+            cluster,
+            commitment,
+            rpc_urls,
+            tx_url,
+            address_url,
+        })
+    }
+
+    pub fn is_mainnet(&self) -> bool {
+        self.name == "mainnet-beta"
+    }
+
+    pub fn tx_url(&self, signature: impl std::fmt::Display) -> String {
+        self.tx_url.replace("{signature}", &signature.to_string())
+    }
+
+    pub fn address_url(&self, address: impl std::fmt::Display) -> String {
+        self.address_url.replace("{address}", &address.to_string())
+    }
+
+    // The RPC endpoint without path, query or credentials, for showing in chats
+    pub fn rpc_host(&self) -> String {
+        redact(self.cluster.url())
+    }
+
+    pub fn ws_host(&self) -> String {
+        redact(self.cluster.ws_url())
+    }
+}
+
+fn custom_cluster(url: &str, ws_url: Option<&str>) -> anyhow::Result<Cluster> {
+    Ok(match (Cluster::from_str(url)?, ws_url) {
+        (Cluster::Custom(url, _), Some(ws_url)) => Cluster::Custom(url, ws_url.to_string()),
+        (cluster, _) => cluster,
+    })
+}
+
+// Falls back to the first endpoint when none is healthy, so startup errors name it
+async fn first_healthy(urls: &[String]) -> String {
+    for url in urls {
+        match RpcClient::new(url.clone()).get_health().await {
+            Ok(()) => return url.clone(),
+            Err(e) => log::warn!("RPC endpoint {} is unhealthy: {}", redact(url), e),
+        }
+    }
+    urls[0].clone()
+}
+
+// Provider URLs often carry an API key in the path or query
+fn redact(url: &str) -> String {
+    match url::Url::parse(url) {
+        Ok(url) => match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{}://{}:{}", url.scheme(), host, port),
+            (Some(host), None) => format!("{}://{}", url.scheme(), host),
+            _ => url.scheme().to_string(),
+        },
+        Err(_) => "(invalid URL)".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn network(vars: &[(&str, &str)]) -> anyhow::Result<NetworkConfig> {
+        let vars: HashMap<_, _> = vars.iter().copied().collect();
+        NetworkConfig::from(|name| vars.get(name).map(|value| value.to_string()))
+    }
+
+    #[test]
+    fn clusters_follow_the_environment() {
+        let local = network(&[]).unwrap();
+        assert_eq!(local.name, "localnet");
+        assert_eq!(local.cluster.url(), "http://127.0.0.1:8899");
+        assert_eq!(local.commitment, CommitmentConfig::processed());
+        assert_eq!(
+            local.tx_url("abc"),
+            "https://explorer.solana.com/tx/abc?cluster=custom"
+        );
+
+        let devnet = network(&[
+            ("SOLANA_CLUSTER", "devnet"),
+            (
+                "SOLANA_RPC_URL",
+                "https://rpc.example.com/key123, https://backup.example.com",
+            ),
+            ("SOLANA_COMMITMENT", "confirmed"),
+        ])
+        .unwrap();
+        assert_eq!(devnet.name, "devnet");
+        assert_eq!(devnet.rpc_urls.len(), 2);
+        assert_eq!(devnet.cluster.url(), "https://rpc.example.com/key123");
+        assert_eq!(devnet.cluster.ws_url(), "wss://rpc.example.com/key123");
+        assert_eq!(devnet.rpc_host(), "https://rpc.example.com");
+        assert_eq!(devnet.commitment, CommitmentConfig::confirmed());
+        assert_eq!(
+            devnet.address_url("xyz"),
+            "https://explorer.solana.com/address/xyz?cluster=devnet"
+        );
+
+        let mainnet = network(&[
+            ("SOLANA_CLUSTER", "mainnet"),
+            ("EXPLORER_TX_URL", "https://solscan.io/tx/{signature}"),
+        ])
+        .unwrap();
+        assert!(mainnet.is_mainnet());
+        assert_eq!(mainnet.tx_url("abc"), "https://solscan.io/tx/abc");
+        assert_eq!(
+            mainnet.address_url("xyz"),
+            "https://explorer.solana.com/address/xyz"
+        );
+
+        let custom = network(&[
+            ("SOLANA_CLUSTER", "http://10.0.0.5:8899"),
+            ("SOLANA_WS_URL", "ws://10.0.0.5:9000"),
+        ])
+        .unwrap();
+        assert_eq!(custom.name, "custom");
+        assert_eq!(custom.cluster.ws_url(), "ws://10.0.0.5:9000");
+
+        for bad in [
+            vec![("SOLANA_CLUSTER", "moon")],
+            vec![("SOLANA_COMMITMENT", "recent")],
+            vec![("EXPLORER_TX_URL", "https://solscan.io/tx/")],
+        ] {
+            assert!(network(&bad).is_err(), "{:?}", bad);
+        }
+    }
+}
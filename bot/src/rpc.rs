@@ -0,0 +1,411 @@
+// Transaction submission with retries and reconciliation.
+//
+// `send_and_confirm_transaction` gives up on the first timeout and can't tell whether a
+// transaction that timed out actually landed. `send_with_retry` re-signs with a fresh
+// blockhash only when it is safe to: an earlier copy is never resubmitted while its
+// blockhash is still valid, and every earlier signature is checked before each retry, so
+// a vote or transfer executes at most once.
+//
+// The RPC calls go through the `SolanaRpc` trait so tests can inject faults (timeouts,
+// stale blockhashes, dropped confirmations) deterministically.
+
+use anchor_client::solana_client::client_error::{ClientError, ClientErrorKind};
+use anchor_client::solana_client::nonblocking::rpc_client::RpcClient;
+use anchor_client::solana_sdk::{
+    hash::Hash,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature},
+    transaction::{Transaction, TransactionError},
+};
+use std::time::Duration;
+
+#[derive(Debug)]
+pub enum RpcFault {
+    // The request timed out; a submitted transaction may or may not have landed
+    Timeout,
+    // The transaction referenced a blockhash the cluster doesn't know (yet or anymore)
+    BlockhashNotFound,
+    // The cluster rejected the transaction (preflight or program error); retrying won't help
+    Rejected(String),
+    // Any other transport failure
+    Transport(String),
+}
+
+impl std::fmt::Display for RpcFault {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Timeout => write!(f, "RPC request timed out"),
+            Self::BlockhashNotFound => write!(f, "Blockhash not found"),
+            Self::Rejected(e) => write!(f, "Transaction rejected: {}", e),
+            Self::Transport(e) => write!(f, "RPC transport error: {}", e),
+        }
+    }
+}
+
+impl From<ClientError> for RpcFault {
+    fn from(e: ClientError) -> Self {
+        let message = e.to_string();
+        match e.kind() {
+            ClientErrorKind::Reqwest(inner) if inner.is_timeout() => Self::Timeout,
+            ClientErrorKind::Io(_) | ClientErrorKind::Reqwest(_) => Self::Transport(message),
+            _ if message.contains("Blockhash not found") => Self::BlockhashNotFound,
+            _ => Self::Rejected(message),
+        }
+    }
+}
+
+// The subset of RPC calls transaction submission depends on
+pub trait SolanaRpc {
+    async fn get_latest_blockhash(&self) -> Result<Hash, RpcFault>;
+    async fn is_blockhash_valid(&self, blockhash: &Hash) -> Result<bool, RpcFault>;
+    async fn send_transaction(&self, transaction: &Transaction) -> Result<Signature, RpcFault>;
+    async fn get_signature_status(
+        &self,
+        signature: &Signature,
+    ) -> Result<Option<Result<(), TransactionError>>, RpcFault>;
+}
+
+impl SolanaRpc for RpcClient {
+    async fn get_latest_blockhash(&self) -> Result<Hash, RpcFault> {
+        Ok(RpcClient::get_latest_blockhash(self).await?)
+    }
+
+    async fn is_blockhash_valid(&self, blockhash: &Hash) -> Result<bool, RpcFault> {
+        Ok(RpcClient::is_blockhash_valid(self, blockhash, self.commitment()).await?)
+    }
+
+    async fn send_transaction(&self, transaction: &Transaction) -> Result<Signature, RpcFault> {
+        Ok(RpcClient::send_transaction(self, transaction).await?)
+    }
+
+    async fn get_signature_status(
+        &self,
+        signature: &Signature,
+    ) -> Result<Option<Result<(), TransactionError>>, RpcFault> {
+        Ok(
+            RpcClient::get_signature_status_with_commitment(self, signature, self.commitment())
+                .await?,
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    pub max_attempts: usize,       // distinct signed copies of the transaction
+    pub confirm_polls: usize,      // status polls per round before checking blockhash expiry
+    pub poll_interval: Duration,   // delay between status polls
+    pub max_confirm_rounds: usize, // upper bound on rounds spent waiting for one copy
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            confirm_polls: 30,
+            poll_interval: Duration::from_millis(500),
+            max_confirm_rounds: 10,
+        }
+    }
+}
+
+// Sign and submit `instructions`, retrying through timeouts, stale blockhashes and lost
+// confirmations without ever letting two copies of the transaction both execute.
+pub async fn send_with_retry<R: SolanaRpc>(
+    rpc: &R,
+    instructions: &[Instruction],
+    payer: &Pubkey,
+    signers: &[&Keypair],
+    policy: &RetryPolicy,
+) -> anyhow::Result<Signature> {
+    let mut sent: Vec<Signature> = Vec::new();
+    let mut attempts = 0;
+
+    while attempts < policy.max_attempts {
+        attempts += 1;
+
+        let blockhash = match rpc.get_latest_blockhash().await {
+            Ok(blockhash) => blockhash,
+            Err(e) => {
+                log::warn!("Attempt {}: failed to fetch blockhash: {}", attempts, e);
+                continue;
+            }
+        };
+        let transaction =
+            Transaction::new_signed_with_payer(instructions, Some(payer), signers, blockhash);
+        let signature = transaction.signatures[0];
+
+        match rpc.send_transaction(&transaction).await {
+            Ok(_) => {}
+            Err(RpcFault::BlockhashNotFound) => {
+                // Nothing was accepted; re-sign with a fresh blockhash
+                log::warn!("Attempt {}: stale blockhash, re-signing", attempts);
+                continue;
+            }
+            Err(RpcFault::Rejected(e)) => {
+                if let Some(landed) = find_landed(rpc, &sent).await? {
+                    return landed;
+                }
+                return Err(anyhow::anyhow!(e));
+            }
+            Err(e) => {
+                // Timeouts and transport errors are ambiguous: the copy may still land
+                log::warn!("Attempt {}: send failed ({}), reconciling", attempts, e);
+            }
+        }
+        sent.push(signature);
+
+        // Wait for this copy (or any earlier one) to confirm; only move on once its
+        // blockhash has expired, at which point it can no longer land
+        for _ in 0..policy.max_confirm_rounds {
+            for _ in 0..policy.confirm_polls {
+                if let Some(landed) = find_landed(rpc, &sent).await? {
+                    return landed;
+                }
+                tokio::time::sleep(policy.poll_interval).await;
+            }
+
+            match rpc.is_blockhash_valid(&blockhash).await {
+                Ok(false) => break,
+                Ok(true) => {}
+                Err(e) => log::warn!("Failed to check blockhash validity: {}", e),
+            }
+        }
+
+        // Final reconciliation now that every earlier copy has expired
+        if let Some(landed) = find_landed(rpc, &sent).await? {
+            return landed;
+        }
+        log::warn!(
+            "Attempt {}: transaction {} expired without landing",
+            attempts,
+            signature
+        );
+    }
+
+    Err(anyhow::anyhow!(
+        "Transaction was not confirmed after {} attempts",
+        policy.max_attempts
+    ))
+}
+
+// Look for any submitted copy that has been processed, successfully or not
+async fn find_landed<R: SolanaRpc>(
+    rpc: &R,
+    sent: &[Signature],
+) -> anyhow::Result<Option<anyhow::Result<Signature>>> {
+    for signature in sent {
+        match rpc.get_signature_status(signature).await {
+            Ok(Some(Ok(()))) => return Ok(Some(Ok(*signature))),
+            Ok(Some(Err(e))) => {
+                return Ok(Some(Err(anyhow::anyhow!(
+                    "Transaction {} failed: {}",
+                    signature,
+                    e
+                ))))
+            }
+            Ok(None) => {}
+            Err(e) => log::warn!("Failed to fetch status of {}: {}", signature, e),
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_client::solana_sdk::{signer::Signer, system_instruction};
+    use std::collections::{HashMap, VecDeque};
+    use std::sync::Mutex;
+
+    // How the chaos RPC reacts to one send_transaction call
+    enum SendFault {
+        Accept,
+        StaleBlockhash,
+        TimeoutAfterLanding,
+        TimeoutDropped,
+        Reject,
+    }
+
+    #[derive(Default)]
+    struct ChaosState {
+        send_faults: VecDeque<SendFault>, // consumed per send; Accept when empty
+        hidden_status_polls: usize,       // status queries answered with None regardless
+        valid_blockhash_checks: usize,    // is_blockhash_valid answers true this many times
+        ledger: HashMap<Signature, Result<(), TransactionError>>,
+        sends: usize,
+        blockhashes: usize,
+    }
+
+    // Deterministic in-memory RPC that injects scripted faults
+    #[derive(Default)]
+    struct ChaosRpc {
+        state: Mutex<ChaosState>,
+    }
+
+    impl ChaosRpc {
+        fn new(send_faults: Vec<SendFault>) -> Self {
+            let rpc = Self::default();
+            rpc.state.lock().unwrap().send_faults = send_faults.into();
+            rpc
+        }
+
+        fn hide_statuses(self, polls: usize) -> Self {
+            self.state.lock().unwrap().hidden_status_polls = polls;
+            self
+        }
+
+        fn blockhash_valid_for(self, checks: usize) -> Self {
+            self.state.lock().unwrap().valid_blockhash_checks = checks;
+            self
+        }
+    }
+
+    impl SolanaRpc for ChaosRpc {
+        async fn get_latest_blockhash(&self) -> Result<Hash, RpcFault> {
+            self.state.lock().unwrap().blockhashes += 1;
+            Ok(Hash::new_unique())
+        }
+
+        async fn is_blockhash_valid(&self, _blockhash: &Hash) -> Result<bool, RpcFault> {
+            let mut state = self.state.lock().unwrap();
+            if state.valid_blockhash_checks > 0 {
+                state.valid_blockhash_checks -= 1;
+                return Ok(true);
+            }
+            Ok(false)
+        }
+
+        async fn send_transaction(&self, transaction: &Transaction) -> Result<Signature, RpcFault> {
+            let mut state = self.state.lock().unwrap();
+            state.sends += 1;
+            let signature = transaction.signatures[0];
+            match state.send_faults.pop_front().unwrap_or(SendFault::Accept) {
+                SendFault::Accept => {
+                    state.ledger.insert(signature, Ok(()));
+                    Ok(signature)
+                }
+                SendFault::StaleBlockhash => Err(RpcFault::BlockhashNotFound),
+                SendFault::TimeoutAfterLanding => {
+                    state.ledger.insert(signature, Ok(()));
+                    Err(RpcFault::Timeout)
+                }
+                SendFault::TimeoutDropped => Err(RpcFault::Timeout),
+                SendFault::Reject => Err(RpcFault::Rejected("custom program error".into())),
+            }
+        }
+
+        async fn get_signature_status(
+            &self,
+            signature: &Signature,
+        ) -> Result<Option<Result<(), TransactionError>>, RpcFault> {
+            let mut state = self.state.lock().unwrap();
+            if state.hidden_status_polls > 0 {
+                state.hidden_status_polls -= 1;
+                return Ok(None);
+            }
+            Ok(state.ledger.get(signature).cloned())
+        }
+    }
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            confirm_polls: 2,
+            poll_interval: Duration::ZERO,
+            max_confirm_rounds: 5,
+        }
+    }
+
+    async fn send(rpc: &ChaosRpc, payer: &Keypair) -> anyhow::Result<Signature> {
+        let instruction =
+            system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1_000);
+        send_with_retry(rpc, &[instruction], &payer.pubkey(), &[payer], &policy()).await
+    }
+
+    fn landed(rpc: &ChaosRpc) -> usize {
+        rpc.state.lock().unwrap().ledger.len()
+    }
+
+    #[tokio::test]
+    async fn stale_blockhash_is_re_signed() {
+        let rpc = ChaosRpc::new(vec![SendFault::StaleBlockhash]);
+        let payer = Keypair::new();
+
+        let signature = send(&rpc, &payer).await.unwrap();
+
+        let state = rpc.state.lock().unwrap();
+        assert_eq!(state.blockhashes, 2);
+        assert_eq!(state.sends, 2);
+        assert_eq!(state.ledger.len(), 1);
+        assert!(state.ledger.contains_key(&signature));
+    }
+
+    #[tokio::test]
+    async fn timeout_after_landing_is_reconciled_without_resending() {
+        let rpc = ChaosRpc::new(vec![SendFault::TimeoutAfterLanding]);
+        let payer = Keypair::new();
+
+        let signature = send(&rpc, &payer).await.unwrap();
+
+        assert_eq!(rpc.state.lock().unwrap().sends, 1);
+        assert_eq!(landed(&rpc), 1);
+        assert!(rpc.state.lock().unwrap().ledger.contains_key(&signature));
+    }
+
+    #[tokio::test]
+    async fn dropped_confirmation_waits_for_blockhash_expiry() {
+        // Statuses stay hidden past several polling rounds while the blockhash is valid
+        let rpc = ChaosRpc::new(vec![SendFault::Accept])
+            .hide_statuses(5)
+            .blockhash_valid_for(3);
+        let payer = Keypair::new();
+
+        let signature = send(&rpc, &payer).await.unwrap();
+
+        // The first copy is found instead of a second one being submitted
+        assert_eq!(rpc.state.lock().unwrap().sends, 1);
+        assert_eq!(landed(&rpc), 1);
+        assert!(rpc.state.lock().unwrap().ledger.contains_key(&signature));
+    }
+
+    #[tokio::test]
+    async fn dropped_transaction_is_resent_after_expiry() {
+        let rpc = ChaosRpc::new(vec![SendFault::TimeoutDropped]).blockhash_valid_for(1);
+        let payer = Keypair::new();
+
+        let signature = send(&rpc, &payer).await.unwrap();
+
+        assert_eq!(rpc.state.lock().unwrap().sends, 2);
+        assert_eq!(landed(&rpc), 1);
+        assert!(rpc.state.lock().unwrap().ledger.contains_key(&signature));
+    }
+
+    #[tokio::test]
+    async fn rejection_is_not_retried() {
+        let rpc = ChaosRpc::new(vec![SendFault::Reject]);
+        let payer = Keypair::new();
+
+        let result = send(&rpc, &payer).await;
+
+        assert!(result.is_err());
+        assert_eq!(rpc.state.lock().unwrap().sends, 1);
+        assert_eq!(landed(&rpc), 0);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_attempts() {
+        let rpc = ChaosRpc::new(vec![
+            SendFault::TimeoutDropped,
+            SendFault::TimeoutDropped,
+            SendFault::TimeoutDropped,
+        ]);
+        let payer = Keypair::new();
+
+        let result = send(&rpc, &payer).await;
+
+        assert!(result.is_err());
+        assert_eq!(rpc.state.lock().unwrap().sends, 3);
+        assert_eq!(landed(&rpc), 0);
+    }
+}
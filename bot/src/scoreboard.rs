@@ -0,0 +1,367 @@
+// Live scoreboards: one pinned message per active proposal in its group's chat, showing
+// the current tally and turnout, which the bot edits as votes come in.
+//
+// A scoreboard is refreshed on every VoteCastEvent (see events.rs) and on a sweep every
+// SCOREBOARD_INTERVAL_SECS (default 120, 0 disables the sweep), which also catches votes
+// missed while the event subscription was down. The first refresh of a proposal whose
+// voting is open posts and pins its scoreboard. Edits are skipped when the text hasn't
+// changed. Once voting ends the scoreboard gets a final edit and is unpinned; the
+// results announcement (see announcer.rs) takes over from there. Each chat's scoreboard
+// message ids are kept so a restart goes on editing the same messages.
+
+use crate::{
+    chat_vocabulary, get_all_groups, get_group_proposals, get_proposal_results, html_escape,
+    solana_dao, BotState, SendLocalized,
+};
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use teloxide::prelude::*;
+use teloxide::types::{MessageId, ParseMode};
+use tokio::sync::Mutex;
+
+pub const DEFAULT_SCOREBOARDS_PATH: &str = "bot/scoreboards.json";
+pub const DEFAULT_SCOREBOARD_INTERVAL_SECS: u64 = 120;
+const BAR_WIDTH: usize = 10;
+
+// Serializes refreshes, so an event and the sweep can't both post a chat's first
+// scoreboard for a proposal
+static REFRESHING: Mutex<()> = Mutex::const_new(());
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Scoreboard {
+    pub message_id: i32,
+    // Last text shown, to skip edits that change nothing
+    pub text: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Scoreboards {
+    // Proposal id -> its scoreboard message
+    #[serde(default)]
+    pub boards: BTreeMap<String, Scoreboard>,
+}
+
+// Whether voting is open, by slot for slot-windowed proposals and by timestamp otherwise
+pub fn voting_open(proposal: &solana_dao::Proposal, now: i64, slot: u64) -> bool {
+    let open = match proposal.slot_window {
+        Some(window) => (window.start_slot..=window.end_slot).contains(&slot),
+        None => (proposal.voting_start..=proposal.voting_end).contains(&now),
+    };
+    proposal.status == solana_dao::ProposalStatus::Active && open
+}
+
+fn bar(share: f64) -> String {
+    let filled = ((share * BAR_WIDTH as f64).round() as usize).min(BAR_WIDTH);
+    format!("{}{}", "▓".repeat(filled), "░".repeat(BAR_WIDTH - filled))
+}
+
+// `members` is the group's member count, 0 for groups without a member list
+pub fn scoreboard_text(proposal: &solana_dao::Proposal, members: u32, open: bool) -> String {
+    // Summed as u128: lamport-weighted tallies can overflow u64 when added together
+    let total: u128 = proposal.choice_votes.iter().map(|&v| v as u128).sum();
+    let mut text = if open {
+        format!(
+            "📊 <b>Live results: {}</b>\n\n",
+            html_escape(&proposal.title)
+        )
+    } else {
+        format!(
+            "🔒 <b>Voting closed: {}</b>\n\n",
+            html_escape(&proposal.title)
+        )
+    };
+    for (i, (choice, votes)) in proposal
+        .choices
+        .iter()
+        .zip(proposal.choice_votes.iter())
+        .enumerate()
+    {
+        let share = if total > 0 {
+            *votes as f64 / total as f64
+        } else {
+            0.0
+        };
+        text.push_str(&format!(
+            "{}. {} - {} ({:.1}%)\n{}\n",
+            i,
+            html_escape(choice),
+            votes,
+            share * 100.0,
+            bar(share)
+        ));
+    }
+
+    let voters = proposal.ballots().count();
+    if members > 0 {
+        text.push_str(&format!(
+            "\n👥 Turnout: {} of {} members ({:.1}%)\n",
+            voters,
+            members,
+            voters as f64 / members as f64 * 100.0
+        ));
+    } else {
+        text.push_str(&format!("\n👥 Voters: {}\n", voters));
+    }
+    text.push_str(&format!(
+        "⏰ Voting ends: {}\n🆔 <code>{}</code>",
+        DateTime::<Utc>::from_timestamp(proposal.voting_end, 0)
+            .map(|dt| dt.format("%Y-%m-%d %H:%M UTC").to_string())
+            .unwrap_or_else(|| "Unknown time".to_string()),
+        proposal.proposal_id
+    ));
+    text
+}
+
+async fn member_count(state: &BotState, group_id: &str) -> u32 {
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    state
+        .program
+        .account::<solana_dao::Group>(group_pda)
+        .await
+        .map(|group| group.member_count)
+        .unwrap_or_default()
+}
+
+// Post, edit or retire the proposal's scoreboard in `chat_id` to match `proposal`
+async fn show(
+    bot: &Bot,
+    state: &BotState,
+    chat_id: ChatId,
+    proposal: &solana_dao::Proposal,
+    members: u32,
+    open: bool,
+) -> anyhow::Result<()> {
+    let _guard = REFRESHING.lock().await;
+    let board = state
+        .scoreboards
+        .get(chat_id.0)
+        .await
+        .boards
+        .get(&proposal.proposal_id)
+        .cloned();
+    if board.is_none() && !open {
+        return Ok(());
+    }
+
+    let vocab = chat_vocabulary(state, chat_id).await;
+    let text = scoreboard_text(proposal, members, open);
+    let proposal_id = proposal.proposal_id.clone();
+    match board {
+        None => {
+            let message = bot
+                .send_localized(&vocab, chat_id, text.clone())
+                .parse_mode(ParseMode::Html)
+                .await?;
+            if let Err(e) = bot
+                .pin_chat_message(chat_id, message.id)
+                .disable_notification(true)
+                .await
+            {
+                log::info!("Couldn't pin the scoreboard in {}: {}", chat_id, e);
+            }
+            state
+                .scoreboards
+                .update(chat_id.0, |boards| {
+                    boards.boards.insert(
+                        proposal_id,
+                        Scoreboard {
+                            message_id: message.id.0,
+                            text,
+                        },
+                    );
+                })
+                .await?;
+        }
+        Some(board) => {
+            let message_id = MessageId(board.message_id);
+            if board.text != text {
+                bot.edit_message_text(chat_id, message_id, vocab.apply(&text))
+                    .parse_mode(ParseMode::Html)
+                    .await?;
+            }
+            if open {
+                if board.text != text {
+                    state
+                        .scoreboards
+                        .update(chat_id.0, |boards| {
+                            if let Some(board) = boards.boards.get_mut(&proposal_id) {
+                                board.text = text;
+                            }
+                        })
+                        .await?;
+                }
+            } else {
+                if let Err(e) = bot.unpin_chat_message(chat_id).message_id(message_id).await {
+                    log::info!("Couldn't unpin the scoreboard in {}: {}", chat_id, e);
+                }
+                state
+                    .scoreboards
+                    .update(chat_id.0, |boards| {
+                        boards.boards.remove(&proposal_id);
+                    })
+                    .await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Bring one proposal's scoreboard up to date, e.g. after a vote
+pub async fn refresh(
+    bot: &Bot,
+    state: &BotState,
+    chat_id: ChatId,
+    group_id: &str,
+    proposal_id: &str,
+) -> anyhow::Result<()> {
+    let proposal = get_proposal_results(state, group_id, proposal_id).await?;
+    let slot = state.program.rpc().get_slot().await?;
+    let open = voting_open(&proposal, Utc::now().timestamp(), slot);
+    let members = member_count(state, group_id).await;
+    show(bot, state, chat_id, &proposal, members, open).await
+}
+
+// One pass over every group linked to a chat
+async fn sweep(bot: &Bot, state: &BotState) -> anyhow::Result<()> {
+    let slot = state.program.rpc().get_slot().await?;
+    for group in get_all_groups(state).await? {
+        let Some(chat_id) = group.external_chat_id.map(ChatId) else {
+            continue;
+        };
+        let boards = state.scoreboards.get(chat_id.0).await.boards;
+        let now = Utc::now().timestamp();
+        let proposals = get_group_proposals(state, &group.group_id).await?;
+        for proposal in &proposals {
+            let open = voting_open(proposal, now, slot);
+            if !open && !boards.contains_key(&proposal.proposal_id) {
+                continue;
+            }
+            if let Err(e) = show(bot, state, chat_id, proposal, group.member_count, open).await {
+                log::warn!(
+                    "Failed to update the scoreboard of {}: {}",
+                    proposal.proposal_id,
+                    e
+                );
+            }
+        }
+
+        // Scoreboards of proposals that were closed or cancelled away
+        let gone: Vec<_> = boards
+            .keys()
+            .filter(|id| !proposals.iter().any(|p| p.proposal_id == **id))
+            .cloned()
+            .collect();
+        if !gone.is_empty() {
+            state
+                .scoreboards
+                .update(chat_id.0, |boards| {
+                    boards.boards.retain(|id, _| !gone.contains(id));
+                })
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+// Sweep every `interval` until the process exits
+pub async fn run(bot: Bot, state: BotState, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        ticker.tick().await;
+        if let Err(e) = sweep(&bot, &state).await {
+            log::warn!("Scoreboard sweep failed: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proposal(choice_votes: Vec<u64>, voters: usize) -> solana_dao::Proposal {
+        solana_dao::Proposal {
+            version: 1,
+            proposal_id: "p1".to_string(),
+            group_id: "tg_1".to_string(),
+            title: "Budget <2025>".to_string(),
+            description: String::new(),
+            choices: vec!["Yes".to_string(), "No".to_string()],
+            choice_votes,
+            voting_start: 100,
+            voting_end: 200,
+            token_mint: None,
+            creator: Pubkey::default(),
+            voters: (0..voters)
+                .map(|_| solana_dao::VoterInfo {
+                    voter: Pubkey::new_unique(),
+                    choice: 0,
+                    vote_weight: 1,
+                    timestamp: 0,
+                    disclosure: None,
+                })
+                .collect(),
+            created_at: 0,
+            index: 0,
+            metadata_uri: None,
+            status: solana_dao::ProposalStatus::Active,
+            finalize_bounty: 0,
+            shadow_votes: Vec::new(),
+            offchain_aggregator: None,
+            offchain_voter_count: None,
+            price_weighted: false,
+            member_weighted: false,
+            kind: solana_dao::ProposalKind::Text,
+            emergency: false,
+            split_voting: false,
+            slot_window: None,
+            weight_decay_bps: 0,
+            deposit_voting: None,
+            council_chamber: None,
+            winner: None,
+            tied: false,
+            sponsors_needed: 0,
+            sponsor_count: 0,
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn scoreboard_shows_tally_and_turnout() {
+        let text = scoreboard_text(&proposal(vec![3, 1], 4), 8, true);
+        assert!(text.starts_with("📊 <b>Live results: Budget &lt;2025&gt;</b>"));
+        assert!(text.contains("0. Yes - 3 (75.0%)\n▓▓▓▓▓▓▓▓░░\n"));
+        assert!(text.contains("1. No - 1 (25.0%)\n▓▓▓░░░░░░░\n"));
+        assert!(text.contains("👥 Turnout: 4 of 8 members (50.0%)"));
+
+        let text = scoreboard_text(&proposal(vec![0, 0], 0), 0, false);
+        assert!(text.starts_with("🔒"));
+        assert!(text.contains("0. Yes - 0 (0.0%)\n░░░░░░░░░░\n"));
+        assert!(text.contains("👥 Voters: 0"));
+    }
+
+    #[test]
+    fn scoreboards_follow_the_voting_window() {
+        let mut p = proposal(vec![0, 0], 0);
+        assert!(!voting_open(&p, 99, 0));
+        assert!(voting_open(&p, 150, 0));
+        assert!(!voting_open(&p, 201, 0));
+
+        p.slot_window = Some(solana_dao::SlotWindow {
+            start_slot: 10,
+            end_slot: 20,
+        });
+        assert!(voting_open(&p, 0, 15));
+        assert!(!voting_open(&p, 150, 21));
+
+        p.slot_window = None;
+        p.status = solana_dao::ProposalStatus::Defeated;
+        assert!(!voting_open(&p, 150, 0));
+    }
+}
@@ -0,0 +1,112 @@
+// Per-chat and per-user bot settings, persisted as JSON next to the payer keypair.
+//
+// These only affect how the bot and web service present data; nothing here is stored
+// on-chain, so anyone reading the program accounts directly still sees everything.
+
+use crate::i18n::Language;
+use crate::localization::Vocabulary;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tokio::sync::Mutex;
+
+pub const DEFAULT_GROUP_SETTINGS_PATH: &str = "bot/group-settings.json";
+pub const DEFAULT_USER_SETTINGS_PATH: &str = "bot/user-settings.json";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum VotePrivacy {
+    // Only aggregate tallies and voter counts are shown
+    #[default]
+    Private,
+    // Individual voters, their choices and weights are listed
+    Public,
+}
+
+impl VotePrivacy {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "private" | "hidden" | "off" => Some(Self::Private),
+            "public" | "visible" | "on" => Some(Self::Public),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Private => "private",
+            Self::Public => "public",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct GroupSettings {
+    #[serde(default)]
+    pub vote_privacy: VotePrivacy,
+    // Language proposal announcements are translated into, if any
+    #[serde(default)]
+    pub announcement_language: Option<String>,
+    // Custom terminology and emoji for bot output in this chat
+    #[serde(default)]
+    pub vocabulary: Vocabulary,
+    // Language the bot answers in, set with /language
+    #[serde(default)]
+    pub language: Language,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct UserSettings {
+    // Target language for translated listings: a language code, or "auto" to follow
+    // the user's Telegram client language. None disables translation.
+    #[serde(default)]
+    pub translate_to: Option<String>,
+}
+
+pub struct SettingsStore<T> {
+    path: PathBuf,
+    settings: Mutex<HashMap<i64, T>>, // chat_id or telegram user id -> settings
+}
+
+impl<T> SettingsStore<T>
+where
+    T: Serialize + DeserializeOwned + Clone + Default,
+{
+    // Load settings from the path in `env_var` (or `default_path`); a missing file starts empty
+    pub fn load(env_var: &str, default_path: &str) -> anyhow::Result<Self> {
+        let path = std::env::var(env_var)
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| PathBuf::from(default_path));
+
+        let settings = if Path::new(&path).exists() {
+            serde_json::from_str(&fs::read_to_string(&path)?)?
+        } else {
+            HashMap::new()
+        };
+
+        Ok(Self {
+            path,
+            settings: Mutex::new(settings),
+        })
+    }
+
+    pub async fn get(&self, key: i64) -> T {
+        self.settings
+            .lock()
+            .await
+            .get(&key)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    // Apply `change` to one entry and write the whole store back to disk
+    pub async fn update(&self, key: i64, change: impl FnOnce(&mut T)) -> anyhow::Result<T> {
+        let mut settings = self.settings.lock().await;
+        let entry = settings.entry(key).or_default();
+        change(entry);
+        let updated = entry.clone();
+        fs::write(&self.path, serde_json::to_string_pretty(&*settings)?)?;
+        Ok(updated)
+    }
+}
@@ -0,0 +1,199 @@
+// Optional machine translation of proposal text for multilingual groups.
+//
+// Proposals are stored on-chain in whatever language their creator used; this module
+// translates them for display only. Supported providers are LibreTranslate (self-hosted
+// or public) and DeepL. When no provider is configured the bot shows the original text.
+
+use crate::solana_dao;
+use serde::Deserialize;
+use std::collections::HashMap;
+use tokio::sync::Mutex;
+
+const DEFAULT_LIBRETRANSLATE_URL: &str = "https://libretranslate.com";
+const DEFAULT_DEEPL_URL: &str = "https://api-free.deepl.com";
+
+enum TranslationProvider {
+    LibreTranslate {
+        url: String,
+        api_key: Option<String>,
+    },
+    DeepL {
+        url: String,
+        api_key: String,
+    },
+}
+
+pub struct Translator {
+    provider: TranslationProvider,
+    http: reqwest::Client,
+    cache: Mutex<HashMap<(String, String), String>>, // (target language, text) -> translation
+}
+
+// Proposal text as shown to a reader, translated when possible
+pub struct TranslatedProposal {
+    pub title: String,
+    pub description: String,
+    pub choices: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct LibreTranslateResponse {
+    #[serde(rename = "translatedText")]
+    translated_text: String,
+}
+
+#[derive(Deserialize)]
+struct DeepLResponse {
+    translations: Vec<DeepLTranslation>,
+}
+
+#[derive(Deserialize)]
+struct DeepLTranslation {
+    text: String,
+}
+
+impl Translator {
+    // Build a translator from TRANSLATION_PROVIDER / TRANSLATION_API_URL / TRANSLATION_API_KEY.
+    // Returns Ok(None) when translation is not configured.
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let provider = match std::env::var("TRANSLATION_PROVIDER") {
+            Ok(provider) => provider.to_lowercase(),
+            Err(_) => return Ok(None),
+        };
+        let url = std::env::var("TRANSLATION_API_URL").ok();
+        let api_key = std::env::var("TRANSLATION_API_KEY").ok();
+
+        let provider = match provider.as_str() {
+            "libretranslate" => TranslationProvider::LibreTranslate {
+                url: url.unwrap_or_else(|| DEFAULT_LIBRETRANSLATE_URL.to_string()),
+                api_key,
+            },
+            "deepl" => TranslationProvider::DeepL {
+                url: url.unwrap_or_else(|| DEFAULT_DEEPL_URL.to_string()),
+                api_key: api_key.ok_or_else(|| {
+                    anyhow::anyhow!("TRANSLATION_API_KEY is required for the DeepL provider")
+                })?,
+            },
+            other => {
+                return Err(anyhow::anyhow!(
+                    "Unknown TRANSLATION_PROVIDER '{}' (expected libretranslate or deepl)",
+                    other
+                ))
+            }
+        };
+
+        Ok(Some(Self {
+            provider,
+            http: reqwest::Client::new(),
+            cache: Mutex::new(HashMap::new()),
+        }))
+    }
+
+    // Translate `text` into `target`; on provider errors the original text is returned
+    pub async fn translate(&self, text: &str, target: &str) -> String {
+        if text.trim().is_empty() {
+            return text.to_string();
+        }
+
+        let key = (target.to_lowercase(), text.to_string());
+        if let Some(cached) = self.cache.lock().await.get(&key) {
+            return cached.clone();
+        }
+
+        match self.request(text, &key.0).await {
+            Ok(translated) => {
+                self.cache.lock().await.insert(key, translated.clone());
+                translated
+            }
+            Err(e) => {
+                log::warn!("Translation to '{}' failed: {}", target, e);
+                text.to_string()
+            }
+        }
+    }
+
+    pub async fn translate_proposal(
+        &self,
+        proposal: &solana_dao::Proposal,
+        target: &str,
+    ) -> TranslatedProposal {
+        let mut choices = Vec::with_capacity(proposal.choices.len());
+        for choice in &proposal.choices {
+            choices.push(self.translate(choice, target).await);
+        }
+
+        TranslatedProposal {
+            title: self.translate(&proposal.title, target).await,
+            description: self.translate(&proposal.description, target).await,
+            choices,
+        }
+    }
+
+    async fn request(&self, text: &str, target: &str) -> anyhow::Result<String> {
+        match &self.provider {
+            TranslationProvider::LibreTranslate { url, api_key } => {
+                let mut body = serde_json::json!({
+                    "q": text,
+                    "source": "auto",
+                    "target": target,
+                    "format": "text",
+                });
+                if let Some(api_key) = api_key {
+                    body["api_key"] = serde_json::Value::String(api_key.clone());
+                }
+                let response: LibreTranslateResponse = self
+                    .http
+                    .post(format!("{}/translate", url.trim_end_matches('/')))
+                    .json(&body)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+                Ok(response.translated_text)
+            }
+            TranslationProvider::DeepL { url, api_key } => {
+                let target = target.to_uppercase();
+                let response: DeepLResponse = self
+                    .http
+                    .post(format!("{}/v2/translate", url.trim_end_matches('/')))
+                    .header("Authorization", format!("DeepL-Auth-Key {}", api_key))
+                    .form(&[("text", text), ("target_lang", target.as_str())])
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+                response
+                    .translations
+                    .into_iter()
+                    .next()
+                    .map(|t| t.text)
+                    .ok_or_else(|| anyhow::anyhow!("DeepL returned no translations"))
+            }
+        }
+    }
+}
+
+impl TranslatedProposal {
+    // The proposal's original text, used when no translation applies
+    pub fn original(proposal: &solana_dao::Proposal) -> Self {
+        Self {
+            title: proposal.title.clone(),
+            description: proposal.description.clone(),
+            choices: proposal.choices.clone(),
+        }
+    }
+}
+
+// Translate a proposal if both a translator and a target language are available
+pub async fn display_proposal(
+    translator: Option<&Translator>,
+    proposal: &solana_dao::Proposal,
+    target: Option<&str>,
+) -> TranslatedProposal {
+    match (translator, target) {
+        (Some(translator), Some(target)) => translator.translate_proposal(proposal, target).await,
+        _ => TranslatedProposal::original(proposal),
+    }
+}
@@ -0,0 +1,768 @@
+// HTTP service backing the voting kiosk Telegram Mini App.
+//
+// Serves the kiosk page plus a small JSON API: proposal listings per chat, custodial
+// voting authenticated with Telegram Web App init data, and unsigned vote transactions
+// for users who prefer to sign with a connected wallet, or who sign only a vote payload
+// and let the bot relay it and pay the fees. Proposals voted off-chain take signed
+// ballots that the bot tallies and settles on-chain later. Each chat's voting windows are
+// also published as an iCalendar feed, archived proposal discussions as JSON, and a
+// preview of who is eligible to vote on each proposal. A second page signs /linkwallet
+// challenges in a wallet's in-app browser.
+
+use crate::ballots::{self, Ballot, CastOutcome};
+use crate::optional_writable_account_meta;
+use crate::settings::VotePrivacy;
+use crate::translation::{display_proposal, TranslatedProposal};
+use crate::{archive, calendar, eligibility, offchain_ballot_message};
+use crate::{ban_pda, build_relay_vote_instructions, relayed_vote_message, send_instructions};
+use crate::{build_vote_instruction, ensure_user_account, get_group_proposals, solana_dao};
+use crate::{get_proposal_results, listed_member_record, optional_account_meta};
+use crate::{
+    paid_dues_record, staked_member_shares, vote_on_proposal, voter_deposit_pda_for, BotState,
+};
+
+use anchor_client::solana_sdk::{
+    instruction::AccountMeta, pubkey::Pubkey, signature::Signature, signer::Signer,
+    transaction::Transaction,
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{Html, IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::Arc;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// Init data older than this is rejected to limit replay of captured payloads
+const INIT_DATA_MAX_AGE_SECS: i64 = 24 * 60 * 60;
+// How long a signed relay payload stays valid
+const RELAY_VOTE_TTL_SECS: i64 = 10 * 60;
+
+#[derive(Clone)]
+struct WebState {
+    bot_state: BotState,
+    bot_token: String,
+}
+
+#[derive(Serialize)]
+struct ChoiceView {
+    index: u8,
+    label: String,
+    votes: u64,
+}
+
+#[derive(Serialize)]
+struct VoterView {
+    voter: String,
+    choice: u8,
+    vote_weight: u64,
+    timestamp: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    disclosure: Option<String>,
+}
+
+// A conflict-of-interest disclosure without the voter's identity
+#[derive(Serialize)]
+struct DisclosureView {
+    choice: u8,
+    note: String,
+}
+
+#[derive(Serialize)]
+struct ProposalView {
+    proposal_id: String,
+    title: String,
+    description: String,
+    choices: Vec<ChoiceView>,
+    total_voters: usize,
+    // Only present when the chat's vote privacy setting is public
+    #[serde(skip_serializing_if = "Option::is_none")]
+    voters: Option<Vec<VoterView>>,
+    // Conflict-of-interest disclosures, listed even when voters are private
+    disclosures: Vec<DisclosureView>,
+    // Off-chain long-form content (IPFS, Arweave or HTTPS)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata_uri: Option<String>,
+    voting_start: i64,
+    voting_end: i64,
+    status: &'static str,
+}
+
+#[derive(Deserialize)]
+struct ProposalListQuery {
+    // Optional language code to translate proposal text into
+    lang: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CustodialVoteRequest {
+    init_data: String,
+    chat_id: i64,
+    proposal_id: String,
+    choice: u8,
+    // Optional conflict-of-interest disclosure ("" flags without a note)
+    #[serde(default)]
+    disclosure: Option<String>,
+    // Weight per choice on split-weight proposals; `choice` names the largest
+    #[serde(default)]
+    allocations: Option<Vec<u64>>,
+}
+
+#[derive(Deserialize)]
+struct WalletVoteRequest {
+    chat_id: i64,
+    proposal_id: String,
+    choice: u8,
+    wallet: String,
+    #[serde(default)]
+    disclosure: Option<String>,
+    #[serde(default)]
+    allocations: Option<Vec<u64>>,
+}
+
+#[derive(Deserialize)]
+struct RelayPayloadRequest {
+    chat_id: i64,
+    proposal_id: String,
+    choice: u8,
+}
+
+#[derive(Serialize)]
+struct RelayPayloadResponse {
+    // Base58-encoded bytes for the wallet's signMessage
+    message: String,
+    expiry: i64,
+}
+
+#[derive(Deserialize)]
+struct RelayVoteRequest {
+    chat_id: i64,
+    proposal_id: String,
+    choice: u8,
+    wallet: String,
+    expiry: i64,
+    // Base58-encoded ed25519 signature of the payload
+    signature: String,
+}
+
+#[derive(Deserialize)]
+struct BallotPayloadRequest {
+    chat_id: i64,
+    proposal_id: String,
+    choice: u8,
+}
+
+#[derive(Serialize)]
+struct BallotPayloadResponse {
+    // Base58-encoded bytes for the wallet's signMessage
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct BallotRequest {
+    chat_id: i64,
+    proposal_id: String,
+    choice: u8,
+    wallet: String,
+    // Base58-encoded ed25519 signature of the ballot message
+    signature: String,
+}
+
+#[derive(Serialize)]
+struct BallotResponse {
+    // "recorded", "updated", or "equivocated" when the wallet signed another choice
+    status: &'static str,
+    vote_weight: u64,
+}
+
+#[derive(Serialize)]
+struct BallotView {
+    voter: String,
+    choice: u8,
+    vote_weight: u64,
+    signature: String,
+    // Sibling subtrees from the ballot's leaf up to the root
+    proof: Vec<SubtreeView>,
+}
+
+// A subtree of the tally tree: its hex hash and what it counts
+#[derive(Serialize)]
+struct SubtreeView {
+    hash: String,
+    choice_votes: Vec<u64>,
+    voter_count: u32,
+}
+
+// Everything needed to rebuild the tally root and dispute a miscounted ballot
+#[derive(Serialize)]
+struct BallotSetView {
+    proposal_id: String,
+    merkle_root: String,
+    // Root last submitted on-chain, which may predate later ballots
+    submitted_root: Option<String>,
+    choice_votes: Vec<u64>,
+    voter_count: u32,
+    ballots: Vec<BallotView>,
+    equivocators: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct VoteResponse {
+    signature: String,
+}
+
+#[derive(Serialize)]
+struct TransactionResponse {
+    // Base58-encoded unsigned transaction, ready for a wallet's signAndSendTransaction
+    transaction: String,
+}
+
+struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (
+            self.status,
+            Json(serde_json::json!({ "error": self.message })),
+        )
+            .into_response()
+    }
+}
+
+// Start the HTTP service; runs until the listener fails
+pub async fn serve(
+    bot_state: BotState,
+    bind_addr: SocketAddr,
+    bot_token: String,
+) -> anyhow::Result<()> {
+    let state = Arc::new(WebState {
+        bot_state,
+        bot_token,
+    });
+
+    let app = Router::new()
+        .route("/kiosk", get(kiosk_page))
+        .route("/link", get(link_wallet_page))
+        .route("/api/chats/:chat_id/proposals", get(list_proposals))
+        .route("/api/chats/:chat_id/calendar.ics", get(calendar_feed))
+        .route(
+            "/api/chats/:chat_id/proposals/:proposal_id/archive.json",
+            get(discussion_archive),
+        )
+        .route(
+            "/api/chats/:chat_id/proposals/:proposal_id/eligibility.json",
+            get(voter_eligibility),
+        )
+        .route("/api/vote", post(custodial_vote))
+        .route("/api/vote/transaction", post(wallet_vote_transaction))
+        .route("/api/vote/relay/payload", post(relay_vote_payload))
+        .route("/api/vote/relay", post(relay_vote))
+        .route(
+            "/api/chats/:chat_id/proposals/:proposal_id/ballots.json",
+            get(offchain_ballots),
+        )
+        .route("/api/ballots/payload", post(ballot_payload))
+        .route("/api/ballots", post(cast_ballot))
+        .with_state(state);
+
+    let listener = tokio::net::TcpListener::bind(bind_addr).await?;
+    log::info!("Web service listening on {}", bind_addr);
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+async fn kiosk_page() -> Html<&'static str> {
+    Html(include_str!("../webapp/kiosk.html"))
+}
+
+async fn link_wallet_page() -> Html<&'static str> {
+    Html(include_str!("../webapp/link.html"))
+}
+
+async fn list_proposals(
+    State(state): State<Arc<WebState>>,
+    Path(chat_id): Path<i64>,
+    Query(query): Query<ProposalListQuery>,
+) -> Result<Json<Vec<ProposalView>>, ApiError> {
+    let group_id = format!("tg_{}", chat_id.abs());
+    let proposals = get_group_proposals(&state.bot_state, &group_id).await?;
+    let privacy = state.bot_state.settings.get(chat_id).await.vote_privacy;
+
+    let mut views = Vec::with_capacity(proposals.len());
+    for proposal in &proposals {
+        let text = display_proposal(
+            state.bot_state.translator.as_deref(),
+            proposal,
+            query.lang.as_deref(),
+        )
+        .await;
+        views.push(proposal_view(proposal, text, privacy));
+    }
+    Ok(Json(views))
+}
+
+async fn calendar_feed(
+    State(state): State<Arc<WebState>>,
+    Path(chat_id): Path<i64>,
+) -> Result<Response, ApiError> {
+    let group_id = format!("tg_{}", chat_id.abs());
+    let proposals = get_group_proposals(&state.bot_state, &group_id).await?;
+    let feed = calendar::ics_feed(&group_id, &proposals, Utc::now().timestamp());
+    Ok((
+        [(header::CONTENT_TYPE, "text/calendar; charset=utf-8")],
+        feed,
+    )
+        .into_response())
+}
+
+// Published by /archive; proposals link here from their metadata URI
+async fn discussion_archive(
+    State(state): State<Arc<WebState>>,
+    Path((chat_id, proposal_id)): Path<(i64, String)>,
+) -> Result<Json<archive::Archive>, ApiError> {
+    state
+        .bot_state
+        .discussions
+        .get(chat_id)
+        .await
+        .archives
+        .remove(&proposal_id)
+        .map(Json)
+        .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, "No archive for this proposal"))
+}
+
+// Backs /eligible: the chat's members and their current voting weight on a proposal
+async fn voter_eligibility(
+    State(state): State<Arc<WebState>>,
+    Path((chat_id, proposal_id)): Path<(i64, String)>,
+) -> Result<Json<eligibility::EligibilityPreview>, ApiError> {
+    let group_id = format!("tg_{}", chat_id.abs());
+    Ok(Json(
+        eligibility::preview(&state.bot_state, &group_id, &proposal_id).await?,
+    ))
+}
+
+async fn custodial_vote(
+    State(state): State<Arc<WebState>>,
+    Json(request): Json<CustodialVoteRequest>,
+) -> Result<Json<VoteResponse>, ApiError> {
+    let telegram_id = verify_init_data(&request.init_data, &state.bot_token)
+        .map_err(|e| ApiError::new(StatusCode::UNAUTHORIZED, e.to_string()))?;
+
+    let group_id = format!("tg_{}", request.chat_id.abs());
+    let keypair = ensure_user_account(&state.bot_state, telegram_id).await?;
+
+    let signature = vote_on_proposal(
+        &state.bot_state,
+        telegram_id,
+        &group_id,
+        &request.proposal_id,
+        request.choice,
+        request.allocations.as_deref(),
+        request.disclosure.as_deref(),
+        keypair.pubkey(),
+    )
+    .await
+    .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, e.to_string()))?;
+
+    Ok(Json(VoteResponse { signature }))
+}
+
+async fn wallet_vote_transaction(
+    State(state): State<Arc<WebState>>,
+    Json(request): Json<WalletVoteRequest>,
+) -> Result<Json<TransactionResponse>, ApiError> {
+    let wallet = Pubkey::from_str(&request.wallet)
+        .map_err(|_| ApiError::new(StatusCode::BAD_REQUEST, "Invalid wallet address"))?;
+
+    let group_id = format!("tg_{}", request.chat_id.abs());
+    let mut instruction = build_vote_instruction(
+        &group_id,
+        &request.proposal_id,
+        request.choice,
+        request.allocations.as_deref(),
+        request.disclosure.as_deref(),
+        wallet,
+    );
+    // No user account or price weighting; the member record if the wallet is listed, its
+    // deposit on deposit-weighted proposals, its dues and shares records once it has paid
+    // dues or bought shares, and its ban PDA
+    let member_record = listed_member_record(&state.bot_state, &group_id, wallet).await?;
+    let voter_deposit =
+        voter_deposit_pda_for(&state.bot_state, &group_id, &request.proposal_id, wallet).await?;
+    let dues_record = paid_dues_record(&state.bot_state, &group_id, wallet).await?;
+    let member_shares = staked_member_shares(&state.bot_state, &group_id, wallet).await?;
+    let (group_pda, _) =
+        Pubkey::find_program_address(&[b"group", group_id.as_bytes()], &solana_dao::ID);
+    instruction.accounts.extend([
+        optional_account_meta(None),
+        optional_account_meta(None),
+        optional_account_meta(member_record),
+        optional_account_meta(voter_deposit),
+        optional_account_meta(dues_record),
+        optional_writable_account_meta(member_shares),
+        AccountMeta::new_readonly(ban_pda(&group_pda, &wallet), false),
+    ]);
+
+    let program = state
+        .bot_state
+        .solana_client
+        .program(solana_dao::ID)
+        .map_err(anyhow::Error::from)?;
+    let recent_blockhash = program
+        .rpc()
+        .get_latest_blockhash()
+        .await
+        .map_err(anyhow::Error::from)?;
+
+    let mut transaction = Transaction::new_with_payer(&[instruction], Some(&wallet));
+    transaction.message.recent_blockhash = recent_blockhash;
+
+    let bytes = bincode::serialize(&transaction).map_err(anyhow::Error::from)?;
+    Ok(Json(TransactionResponse {
+        transaction: bs58::encode(bytes).into_string(),
+    }))
+}
+
+async fn relay_vote_payload(
+    Json(request): Json<RelayPayloadRequest>,
+) -> Json<RelayPayloadResponse> {
+    let group_id = format!("tg_{}", request.chat_id.abs());
+    let expiry = Utc::now().timestamp() + RELAY_VOTE_TTL_SECS;
+    let message = relayed_vote_message(&group_id, &request.proposal_id, request.choice, expiry);
+
+    Json(RelayPayloadResponse {
+        message: bs58::encode(message).into_string(),
+        expiry,
+    })
+}
+
+// Submit a vote the wallet signed off-chain; the bot's payer covers fees and rent
+async fn relay_vote(
+    State(state): State<Arc<WebState>>,
+    Json(request): Json<RelayVoteRequest>,
+) -> Result<Json<VoteResponse>, ApiError> {
+    let wallet = Pubkey::from_str(&request.wallet)
+        .map_err(|_| ApiError::new(StatusCode::BAD_REQUEST, "Invalid wallet address"))?;
+    let signature: [u8; 64] = bs58::decode(&request.signature)
+        .into_vec()
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, "Invalid signature"))?;
+
+    let group_id = format!("tg_{}", request.chat_id.abs());
+    let proposal = get_proposal_results(&state.bot_state, &group_id, &request.proposal_id)
+        .await
+        .map_err(|e| ApiError::new(StatusCode::NOT_FOUND, e.to_string()))?;
+    let voter_token_account = proposal
+        .token_mint
+        .filter(|mint| *mint != solana_dao::NATIVE_MINT)
+        .map(|mint| spl_associated_token_account::get_associated_token_address(&wallet, &mint));
+
+    let member_record = listed_member_record(&state.bot_state, &group_id, wallet).await?;
+    let dues_record = paid_dues_record(&state.bot_state, &group_id, wallet).await?;
+    let member_shares = staked_member_shares(&state.bot_state, &group_id, wallet).await?;
+
+    let payer = &state.bot_state.payer;
+    let instructions = build_relay_vote_instructions(
+        &group_id,
+        &request.proposal_id,
+        request.choice,
+        request.expiry,
+        wallet,
+        &signature,
+        voter_token_account,
+        member_record,
+        dues_record,
+        member_shares,
+        payer.pubkey(),
+    );
+    let signature = send_instructions(
+        &state.bot_state,
+        &instructions,
+        &payer.pubkey(),
+        &[&**payer],
+    )
+    .await
+    .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, e.to_string()))?;
+
+    Ok(Json(VoteResponse {
+        signature: signature.to_string(),
+    }))
+}
+
+async fn ballot_payload(Json(request): Json<BallotPayloadRequest>) -> Json<BallotPayloadResponse> {
+    let group_id = format!("tg_{}", request.chat_id.abs());
+    let message = offchain_ballot_message(&group_id, &request.proposal_id, request.choice);
+
+    Json(BallotPayloadResponse {
+        message: bs58::encode(message).into_string(),
+    })
+}
+
+// Record a ballot for a proposal whose votes the bot aggregates off-chain
+async fn cast_ballot(
+    State(state): State<Arc<WebState>>,
+    Json(request): Json<BallotRequest>,
+) -> Result<Json<BallotResponse>, ApiError> {
+    let wallet = Pubkey::from_str(&request.wallet)
+        .map_err(|_| ApiError::new(StatusCode::BAD_REQUEST, "Invalid wallet address"))?;
+    let signature = bs58::decode(&request.signature)
+        .into_vec()
+        .ok()
+        .and_then(|bytes| Signature::try_from(bytes.as_slice()).ok())
+        .ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, "Invalid signature"))?;
+
+    let group_id = format!("tg_{}", request.chat_id.abs());
+    let message = offchain_ballot_message(&group_id, &request.proposal_id, request.choice);
+    if !signature.verify(wallet.as_ref(), &message) {
+        return Err(ApiError::new(
+            StatusCode::UNAUTHORIZED,
+            "Signature does not match the ballot",
+        ));
+    }
+
+    let proposal = get_proposal_results(&state.bot_state, &group_id, &request.proposal_id)
+        .await
+        .map_err(|e| ApiError::new(StatusCode::NOT_FOUND, e.to_string()))?;
+    if proposal.offchain_aggregator != Some(state.bot_state.payer.pubkey()) {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "This proposal's ballots are not collected by this bot",
+        ));
+    }
+    let now = Utc::now().timestamp();
+    if proposal.status != solana_dao::ProposalStatus::Active
+        || now < proposal.voting_start
+        || now > proposal.voting_end
+    {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "Voting is not open"));
+    }
+    if request.choice as usize >= proposal.choices.len() {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "Invalid choice"));
+    }
+
+    let vote_weight =
+        eligibility::wallet_weight(&state.bot_state, proposal.token_mint, &wallet).await?;
+    if vote_weight == 0 {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "No voting power"));
+    }
+
+    let ballot = Ballot {
+        voter: wallet.to_string(),
+        choice: request.choice,
+        vote_weight,
+        signature: request.signature,
+        received_at: now,
+    };
+    let mut outcome = CastOutcome::Recorded;
+    state
+        .bot_state
+        .ballots
+        .update(request.chat_id, |ballot_box| {
+            outcome = ballot_box.cast(&request.proposal_id, ballot);
+        })
+        .await?;
+
+    Ok(Json(BallotResponse {
+        status: match outcome {
+            CastOutcome::Recorded => "recorded",
+            CastOutcome::Updated => "updated",
+            CastOutcome::Equivocated => "equivocated",
+        },
+        vote_weight,
+    }))
+}
+
+// The collected ballots with their proofs. Off-chain tallies are only verifiable if
+// the ballots are public, so these are published whatever the chat's privacy setting.
+async fn offchain_ballots(
+    State(state): State<Arc<WebState>>,
+    Path((chat_id, proposal_id)): Path<(i64, String)>,
+) -> Result<Json<BallotSetView>, ApiError> {
+    let group_id = format!("tg_{}", chat_id.abs());
+    let proposal = get_proposal_results(&state.bot_state, &group_id, &proposal_id)
+        .await
+        .map_err(|e| ApiError::new(StatusCode::NOT_FOUND, e.to_string()))?;
+    let ballot_set = state
+        .bot_state
+        .ballots
+        .get(chat_id)
+        .await
+        .proposals
+        .remove(&proposal_id)
+        .unwrap_or_default();
+
+    let leaves = ballot_set.leaves(proposal.choices.len());
+    let subtrees: Vec<ballots::Subtree> = leaves.iter().map(|(_, leaf)| leaf.clone()).collect();
+    let (choice_votes, voter_count) = ballot_set.tally(proposal.choices.len());
+    Ok(Json(BallotSetView {
+        proposal_id,
+        merkle_root: hex::encode(ballots::merkle_root(&subtrees)),
+        submitted_root: ballot_set.submitted_root.clone(),
+        choice_votes,
+        voter_count,
+        ballots: leaves
+            .iter()
+            .enumerate()
+            .map(|(index, (ballot, _))| BallotView {
+                voter: ballot.voter.clone(),
+                choice: ballot.choice,
+                vote_weight: ballot.vote_weight,
+                signature: ballot.signature.clone(),
+                proof: ballots::merkle_proof(&subtrees, index)
+                    .into_iter()
+                    .map(|sibling| SubtreeView {
+                        hash: hex::encode(sibling.hash),
+                        choice_votes: sibling.choice_votes,
+                        voter_count: sibling.voter_count,
+                    })
+                    .collect(),
+            })
+            .collect(),
+        equivocators: ballot_set.equivocators.iter().cloned().collect(),
+    }))
+}
+
+fn proposal_view(
+    proposal: &solana_dao::Proposal,
+    text: TranslatedProposal,
+    privacy: VotePrivacy,
+) -> ProposalView {
+    let now = Utc::now().timestamp();
+    let status = if proposal.status == solana_dao::ProposalStatus::Draft {
+        "draft"
+    } else if proposal.status == solana_dao::ProposalStatus::Cancelled {
+        "cancelled"
+    } else if now > proposal.voting_end {
+        "ended"
+    } else if now < proposal.voting_start {
+        "pending"
+    } else {
+        "active"
+    };
+
+    ProposalView {
+        proposal_id: proposal.proposal_id.clone(),
+        title: text.title,
+        description: text.description,
+        choices: text
+            .choices
+            .into_iter()
+            .zip(proposal.choice_votes.iter())
+            .enumerate()
+            .map(|(i, (label, votes))| ChoiceView {
+                index: i as u8,
+                label,
+                votes: *votes,
+            })
+            .collect(),
+        total_voters: proposal.ballots().count(),
+        voters: (privacy == VotePrivacy::Public).then(|| {
+            proposal
+                .ballots()
+                .map(|v| VoterView {
+                    voter: v.voter.to_string(),
+                    choice: v.choice,
+                    vote_weight: v.vote_weight,
+                    timestamp: v.timestamp,
+                    disclosure: v.disclosure.clone(),
+                })
+                .collect()
+        }),
+        disclosures: proposal
+            .ballots()
+            .filter_map(|v| {
+                v.disclosure.as_ref().map(|note| DisclosureView {
+                    choice: v.choice,
+                    note: note.clone(),
+                })
+            })
+            .collect(),
+        metadata_uri: proposal.metadata_uri.clone(),
+        voting_start: proposal.voting_start,
+        voting_end: proposal.voting_end,
+        status,
+    }
+}
+
+// Validate Telegram Web App init data and return the authenticated user's Telegram ID.
+// See https://core.telegram.org/bots/webapps#validating-data-received-via-the-mini-app
+fn verify_init_data(init_data: &str, bot_token: &str) -> anyhow::Result<i64> {
+    let mut hash = None;
+    let mut fields = Vec::new();
+    for (key, value) in url::form_urlencoded::parse(init_data.as_bytes()) {
+        if key == "hash" {
+            hash = Some(value.into_owned());
+        } else {
+            fields.push((key.into_owned(), value.into_owned()));
+        }
+    }
+    let hash = hash.ok_or_else(|| anyhow::anyhow!("Init data is missing its hash"))?;
+
+    fields.sort_by(|a, b| a.0.cmp(&b.0));
+    let data_check_string = fields
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let mut secret = HmacSha256::new_from_slice(b"WebAppData")?;
+    secret.update(bot_token.as_bytes());
+    let secret_key = secret.finalize().into_bytes();
+
+    let mut mac = HmacSha256::new_from_slice(&secret_key)?;
+    mac.update(data_check_string.as_bytes());
+    let expected =
+        hex::decode(&hash).map_err(|_| anyhow::anyhow!("Init data hash is malformed"))?;
+    mac.verify_slice(&expected)
+        .map_err(|_| anyhow::anyhow!("Init data signature is invalid"))?;
+
+    let field = |name: &str| {
+        fields
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    };
+
+    let auth_date: i64 = field("auth_date")
+        .and_then(|v| v.parse().ok())
+        .ok_or_else(|| anyhow::anyhow!("Init data is missing auth_date"))?;
+    if Utc::now().timestamp() - auth_date > INIT_DATA_MAX_AGE_SECS {
+        return Err(anyhow::anyhow!("Init data has expired"));
+    }
+
+    let user: serde_json::Value = serde_json::from_str(
+        field("user").ok_or_else(|| anyhow::anyhow!("Init data is missing the user"))?,
+    )?;
+    user["id"]
+        .as_i64()
+        .ok_or_else(|| anyhow::anyhow!("Init data user has no id"))
+}
@@ -0,0 +1,131 @@
+// Webhook mode: with WEBHOOK_URL set, Telegram pushes updates to the bot instead of the
+// bot long polling for them, which suits containers and serverless hosts that scale on
+// incoming requests.
+//
+// The listener speaks plain HTTP on WEBHOOK_BIND_ADDR (or 0.0.0.0:$PORT, as most
+// container platforms provide), so TLS is terminated by the reverse proxy or load
+// balancer in front of it. The proxy forwards WEBHOOK_URL's path unchanged. Requests
+// without Telegram's X-Telegram-Bot-Api-Secret-Token header matching
+// WEBHOOK_SECRET_TOKEN are rejected; set it explicitly when several instances share one
+// webhook, since a generated token differs per instance. WEBHOOK_CERT uploads a
+// self-signed certificate for the proxy.
+
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use teloxide::types::InputFile;
+use teloxide::update_listeners::webhooks::Options;
+
+pub const DEFAULT_WEBHOOK_PORT: u16 = 8443;
+// Ports Telegram delivers webhooks to
+pub const TELEGRAM_WEBHOOK_PORTS: [u16; 4] = [443, 80, 88, 8443];
+
+pub fn options_from_env() -> anyhow::Result<Option<Options>> {
+    options_from(|name| std::env::var(name).ok())
+}
+
+// None when WEBHOOK_URL is unset, so the bot long polls
+fn options_from(var: impl Fn(&str) -> Option<String>) -> anyhow::Result<Option<Options>> {
+    let Some(url) = var("WEBHOOK_URL") else {
+        return Ok(None);
+    };
+    let url = url::Url::parse(&url)?;
+    if url.scheme() != "https" {
+        anyhow::bail!("WEBHOOK_URL must be an https URL");
+    }
+    let port = url.port_or_known_default().unwrap_or_default();
+    if !TELEGRAM_WEBHOOK_PORTS.contains(&port) {
+        anyhow::bail!(
+            "Telegram only delivers webhooks to ports 443, 80, 88 and 8443, not {}",
+            port
+        );
+    }
+
+    let address: SocketAddr = match var("WEBHOOK_BIND_ADDR") {
+        Some(address) => address.parse()?,
+        None => {
+            let port = match var("PORT") {
+                Some(port) => port.parse()?,
+                None => DEFAULT_WEBHOOK_PORT,
+            };
+            SocketAddr::from(([0, 0, 0, 0], port))
+        }
+    };
+
+    let mut options = Options::new(address, url);
+    if let Some(token) = var("WEBHOOK_SECRET_TOKEN") {
+        let valid = (1..=256).contains(&token.len())
+            && token
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !valid {
+            anyhow::bail!("WEBHOOK_SECRET_TOKEN must be 1-256 of A-Z, a-z, 0-9, _ and -");
+        }
+        options = options.secret_token(token);
+    }
+    if let Some(max) = var("WEBHOOK_MAX_CONNECTIONS") {
+        match max.parse() {
+            Ok(max @ 1..=100) => options = options.max_connections(max),
+            _ => anyhow::bail!("WEBHOOK_MAX_CONNECTIONS must be between 1 and 100"),
+        }
+    }
+    if let Some(path) = var("WEBHOOK_CERT") {
+        options = options.certificate(InputFile::file(PathBuf::from(path)));
+    }
+    if var("WEBHOOK_DROP_PENDING_UPDATES").is_some_and(|value| value == "true" || value == "1") {
+        options = options.drop_pending_updates();
+    }
+    Ok(Some(options))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn options(vars: &[(&str, &str)]) -> anyhow::Result<Option<Options>> {
+        let vars: HashMap<_, _> = vars.iter().copied().collect();
+        options_from(|name| vars.get(name).map(|value| value.to_string()))
+    }
+
+    #[test]
+    fn webhook_options_follow_the_environment() {
+        assert!(options(&[]).unwrap().is_none());
+
+        let opts = options(&[
+            ("WEBHOOK_URL", "https://bot.example.com/telegram"),
+            ("PORT", "3000"),
+            ("WEBHOOK_SECRET_TOKEN", "s3cret_token-1"),
+            ("WEBHOOK_MAX_CONNECTIONS", "10"),
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(opts.address, SocketAddr::from(([0, 0, 0, 0], 3000)));
+        assert_eq!(opts.url.path(), "/telegram");
+        assert_eq!(opts.secret_token.as_deref(), Some("s3cret_token-1"));
+        assert_eq!(opts.max_connections, Some(10));
+
+        let opts = options(&[
+            ("WEBHOOK_URL", "https://bot.example.com:8443/hook"),
+            ("WEBHOOK_BIND_ADDR", "127.0.0.1:9000"),
+            ("PORT", "3000"),
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(opts.address, SocketAddr::from(([127, 0, 0, 1], 9000)));
+
+        for bad in [
+            vec![("WEBHOOK_URL", "http://bot.example.com/hook")],
+            vec![("WEBHOOK_URL", "https://bot.example.com:9443/hook")],
+            vec![
+                ("WEBHOOK_URL", "https://bot.example.com/hook"),
+                ("WEBHOOK_SECRET_TOKEN", "not allowed!"),
+            ],
+            vec![
+                ("WEBHOOK_URL", "https://bot.example.com/hook"),
+                ("WEBHOOK_MAX_CONNECTIONS", "0"),
+            ],
+        ] {
+            assert!(options(&bad).is_err(), "{:?}", bad);
+        }
+    }
+}
@@ -1,8 +1,16 @@
 #![allow(clippy::result_large_err)]
+#![allow(clippy::too_many_arguments)]
 #![allow(unexpected_cfgs)]
 #![allow(deprecated)]
 
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program::MAX_RETURN_DATA;
+#[cfg(feature = "cnft-membership")]
+use anchor_lang::solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program::{invoke, invoke_signed},
+};
+use anchor_lang::system_program;
 use std::str::FromStr;
 
 declare_id!("4mwBvEQbpGJKDDZCvEPTujCefmphw1fZ99Jxhz69oHcT");
@@ -11,39 +19,164 @@ declare_id!("4mwBvEQbpGJKDDZCvEPTujCefmphw1fZ99Jxhz69oHcT");
 pub mod solana_dao {
     use super::*;
 
-    pub fn initialize(ctx: Context<Initialize>) -> Result<()> {
+    // Create a registry. Operators run independent registries side by side under
+    // different names; the default registry, named "", also governs the program-wide
+    // config, pause switch and upgrades.
+    pub fn initialize(ctx: Context<Initialize>, name: String) -> Result<()> {
+        require!(
+            name.len() <= MAX_REGISTRY_NAME_LEN,
+            DaoError::InvalidRegistryName
+        );
         let dao_registry = &mut ctx.accounts.dao_registry;
         dao_registry.authority = ctx.accounts.authority.key();
+        dao_registry.name = name;
         dao_registry.groups = Vec::new();
+        dao_registry.open_registration = true;
+        dao_registry.group_deposit_lamports = DEFAULT_GROUP_DEPOSIT_LAMPORTS;
+        dao_registry.group_deposit_lock_days = DEFAULT_GROUP_DEPOSIT_LOCK_DAYS;
+        dao_registry.event_seq = 0;
         dao_registry.bump = ctx.bumps.dao_registry;
 
         msg!(
-            "DAO Registry initialized by: {:?}",
+            "DAO Registry {:?} initialized by: {:?}",
+            dao_registry.name,
             ctx.accounts.authority.key()
         );
         Ok(())
     }
 
+    pub fn initialize_program_config(
+        ctx: Context<InitializeProgramConfig>,
+        guardian: Option<Pubkey>,
+    ) -> Result<()> {
+        let program_config = &mut ctx.accounts.program_config;
+        program_config.paused = false;
+        program_config.guardian = guardian;
+        program_config.max_groups = DEFAULT_MAX_GROUPS;
+        program_config.max_choices = MAX_CHOICES as u8;
+        program_config.fee_destination = ctx.accounts.authority.key();
+        program_config.updated_at = Clock::get()?.unix_timestamp;
+        program_config.bump = ctx.bumps.program_config;
+        Ok(())
+    }
+
+    pub fn update_program_config(
+        ctx: Context<UpdateProgramConfig>,
+        max_groups: u32,
+        max_choices: u8,
+        fee_destination: Pubkey,
+        guardian: Option<Pubkey>,
+    ) -> Result<()> {
+        apply_program_config(
+            &mut ctx.accounts.program_config,
+            &mut ctx.accounts.dao_registry,
+            max_groups,
+            max_choices,
+            fee_destination,
+            guardian,
+        )
+    }
+
+    // Freeze state-changing group, proposal and vote instructions; reads keep working
+    pub fn pause(ctx: Context<SetPause>) -> Result<()> {
+        set_paused(ctx, true)
+    }
+
+    pub fn unpause(ctx: Context<SetPause>) -> Result<()> {
+        set_paused(ctx, false)
+    }
+
     pub fn create_group(
         ctx: Context<CreateGroup>,
         group_id: String,
         name: String,
         description: String,
+        metadata_uri: Option<String>,
+        external_chat_id: Option<i64>,
     ) -> Result<()> {
         require!(group_id.len() <= 50, DaoError::GroupIdTooLong);
         require!(name.len() <= 100, DaoError::NameTooLong);
         require!(description.len() <= 500, DaoError::DescriptionTooLong);
+        validate_metadata_uri(&metadata_uri)?;
+
+        // Groups created by anyone other than the registry authority go through open
+        // registration and lock a refundable deposit to discourage squatting group IDs
+        let dao_registry = &ctx.accounts.dao_registry;
+        require!(
+            dao_registry.groups.len() < ctx.accounts.program_config.max_groups as usize,
+            DaoError::RegistryFull
+        );
+        let is_registry_authority = ctx.accounts.authority.key() == dao_registry.authority;
+        require!(
+            is_registry_authority || dao_registry.open_registration,
+            DaoError::RegistrationClosed
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        let (deposit_lamports, deposit_unlock_at) = if is_registry_authority {
+            (0, 0)
+        } else {
+            let lock_secs = i64::from(dao_registry.group_deposit_lock_days) * SECONDS_PER_DAY;
+            (dao_registry.group_deposit_lamports, now + lock_secs)
+        };
+
+        if deposit_lamports > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.authority.to_account_info(),
+                        to: ctx.accounts.group.to_account_info(),
+                    },
+                ),
+                deposit_lamports,
+            )?;
+        }
 
         let group = &mut ctx.accounts.group;
         group.group_id = group_id.clone();
         group.name = name;
         group.description = description;
         group.authority = ctx.accounts.authority.key();
-        group.proposals = Vec::new();
-        group.members = Vec::new();
-        group.created_at = Clock::get()?.unix_timestamp;
+        group.proposal_count = 0;
+        group.member_count = 0;
+        group.created_at = now;
+        group.deposit_lamports = deposit_lamports;
+        group.deposit_unlock_at = deposit_unlock_at;
+        group.slug = String::new();
+        group.metadata_uri = metadata_uri.clone();
+        group.event_seq = 0;
+        group.proposal_fee_lamports = 0;
+        group.members_only_voting = false;
+        group.max_members = 0;
+        group.visibility = GroupVisibility::Public;
+        group.governance = GovernanceConfig::default();
+        group.dues = None;
+        group.stake_to_join = None;
+        group.max_active_proposals = 0;
+        group.active_proposals = 0;
+        group.proposal_cooldown_secs = 0;
+        group.external_chat_id = external_chat_id;
+        group.tie_break = TieBreak::Fail;
+        group.sponsor_threshold = 0;
+        group.registry = ctx.accounts.dao_registry.key();
+        group.proposal_deposit_lamports = 0;
+        group.committed_lamports = 0;
+        group.version = GROUP_VERSION;
         group.bump = ctx.bumps.group;
 
+        // The chat link PDA is what lets clients resolve the chat to this group
+        let group_key = group.key();
+        match (external_chat_id, ctx.accounts.chat_link.as_mut()) {
+            (Some(chat_id), Some(chat_link)) => {
+                let bump = ctx.bumps.chat_link.ok_or(DaoError::ChatLinkRequired)?;
+                link_chat(chat_link, chat_id, group_key, bump, now);
+            }
+            (Some(_), None) => return err!(DaoError::ChatLinkRequired),
+            (None, Some(_)) => return err!(DaoError::ChatLinkMismatch),
+            (None, None) => {}
+        }
+
         // Add to registry
         let dao_registry = &mut ctx.accounts.dao_registry;
         dao_registry.groups.push(GroupInfo {
@@ -52,539 +185,16529 @@ pub mod solana_dao {
             pubkey: group.key(),
         });
 
+        let event_seq = next_event_seq(&mut group.event_seq)?;
         emit!(GroupCreatedEvent {
             group_id,
+            event_seq,
             authority: ctx.accounts.authority.key(),
             group_pubkey: group.key(),
-            timestamp: Clock::get()?.unix_timestamp,
+            registry: group.registry,
+            deposit_lamports,
+            deposit_unlock_at,
+            metadata_uri,
+            timestamp: now,
         });
 
         Ok(())
     }
 
-    pub fn create_proposal(
-        ctx: Context<CreateProposal>,
-        proposal_id: String,
-        title: String,
-        description: String,
-        choices: Vec<String>,
-        voting_start: i64,
-        voting_end: i64,
-        token_mint: Option<Pubkey>,
+    pub fn set_registration_config(
+        ctx: Context<SetRegistrationConfig>,
+        open_registration: bool,
+        group_deposit_lamports: u64,
+        group_deposit_lock_days: u32,
     ) -> Result<()> {
-        require!(proposal_id.len() <= 50, DaoError::ProposalIdTooLong);
-        require!(title.len() <= 200, DaoError::TitleTooLong);
-        require!(description.len() <= 1000, DaoError::DescriptionTooLong);
-        require!(
-            choices.len() >= 2 && choices.len() <= 10,
-            DaoError::InvalidChoiceCount
-        );
-        require!(voting_start < voting_end, DaoError::InvalidVotingPeriod);
+        apply_registration_config(
+            &mut ctx.accounts.dao_registry,
+            open_registration,
+            group_deposit_lamports,
+            group_deposit_lock_days,
+        )
+    }
+
+    // Hand the registry to another key, e.g. to migrate off a compromised hot key
+    pub fn set_registry_authority(
+        ctx: Context<SetRegistryAuthority>,
+        new_authority: Pubkey,
+    ) -> Result<()> {
+        apply_registry_authority(&mut ctx.accounts.dao_registry, new_authority)
+    }
+
+    // Put the registry under an M-of-N multisig: its PDA becomes the registry authority,
+    // so registry-level actions only run through execute_registry_action
+    #[cfg(feature = "registry-multisig")]
+    pub fn create_registry_multisig(
+        ctx: Context<CreateRegistryMultisig>,
+        members: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        validate_multisig_signers(&members, threshold)?;
+
+        let multisig = &mut ctx.accounts.multisig;
+        multisig.registry = ctx.accounts.dao_registry.key();
+        multisig.members = members;
+        multisig.threshold = threshold;
+        multisig.action_count = 0;
+        multisig.bump = ctx.bumps.multisig;
+
+        let multisig_key = multisig.key();
+        apply_registry_authority(&mut ctx.accounts.dao_registry, multisig_key)
+    }
+
+    #[cfg(feature = "registry-multisig")]
+    pub fn propose_registry_action(
+        ctx: Context<ProposeRegistryAction>,
+        action: RegistryAction,
+    ) -> Result<()> {
+        if let RegistryAction::SetSigners { members, threshold } = &action {
+            validate_multisig_signers(members, *threshold)?;
+        }
+        // Only the default registry speaks for the whole program
+        if matches!(
+            action,
+            RegistryAction::Pause
+                | RegistryAction::Unpause
+                | RegistryAction::UpdateProgramConfig { .. }
+        ) {
+            require!(
+                ctx.accounts.dao_registry.name.is_empty(),
+                DaoError::NotDefaultRegistry
+            );
+        }
+
+        let multisig = &mut ctx.accounts.multisig;
+        let proposer = ctx.accounts.proposer.key();
         require!(
-            voting_start > Clock::get()?.unix_timestamp,
-            DaoError::VotingStartInPast
+            multisig.members.contains(&proposer),
+            DaoError::NotMultisigMember
         );
 
-        let proposal = &mut ctx.accounts.proposal;
-        proposal.proposal_id = proposal_id.clone();
-        proposal.group_id = ctx.accounts.group.group_id.clone();
-        proposal.title = title;
-        proposal.description = description;
-        proposal.choices = choices.clone();
-        proposal.choice_votes = vec![0u64; choices.len()];
-        proposal.voting_start = voting_start;
-        proposal.voting_end = voting_end;
-        proposal.token_mint = token_mint;
-        proposal.creator = ctx.accounts.authority.key();
-        proposal.voters = Vec::new();
-        proposal.created_at = Clock::get()?.unix_timestamp;
-        proposal.bump = ctx.bumps.proposal;
-
-        // Add to group
-        let group = &mut ctx.accounts.group;
-        group.proposals.push(ProposalInfo {
-            proposal_id: proposal_id.clone(),
-            pubkey: proposal.key(),
-            created_at: Clock::get()?.unix_timestamp,
-        });
+        let now = Clock::get()?.unix_timestamp;
+        let registry_action = &mut ctx.accounts.registry_action;
+        registry_action.multisig = multisig.key();
+        registry_action.index = multisig.action_count;
+        registry_action.action = action.clone();
+        registry_action.proposer = proposer;
+        registry_action.approvals = vec![proposer];
+        registry_action.created_at = now;
+        registry_action.executed_at = 0;
+        registry_action.bump = ctx.bumps.registry_action;
+        multisig.action_count += 1;
 
-        emit!(ProposalCreatedEvent {
-            group_id: group.group_id.clone(),
-            proposal_id,
-            creator: ctx.accounts.authority.key(),
-            proposal_pubkey: proposal.key(),
-            voting_start,
-            voting_end,
-            timestamp: Clock::get()?.unix_timestamp,
+        emit!(RegistryActionProposedEvent {
+            event_seq: next_event_seq(&mut ctx.accounts.dao_registry.event_seq)?,
+            index: registry_action.index,
+            action,
+            proposer,
+            timestamp: now,
         });
 
         Ok(())
     }
 
-    pub fn vote_on_proposal(ctx: Context<VoteOnProposal>, choice_index: u8) -> Result<()> {
-        let proposal = &mut ctx.accounts.proposal;
-        let current_time = Clock::get()?.unix_timestamp;
-
+    #[cfg(feature = "registry-multisig")]
+    pub fn approve_registry_action(ctx: Context<ApproveRegistryAction>) -> Result<()> {
+        let approver = ctx.accounts.approver.key();
         require!(
-            current_time >= proposal.voting_start && current_time <= proposal.voting_end,
-            DaoError::VotingNotActive
+            ctx.accounts.multisig.members.contains(&approver),
+            DaoError::NotMultisigMember
         );
 
+        let registry_action = &mut ctx.accounts.registry_action;
         require!(
-            (choice_index as usize) < proposal.choices.len(),
-            DaoError::InvalidChoice
+            !registry_action.approvals.contains(&approver),
+            DaoError::RegistryActionAlreadyApproved
         );
+        // Approvals from signers since removed don't count, and dropping them keeps the
+        // list within the multisig's size however often its members change
+        let members = &ctx.accounts.multisig.members;
+        registry_action
+            .approvals
+            .retain(|approval| members.contains(approval));
+        registry_action.approvals.push(approver);
 
-        // Check if user already voted
-        let voter_key = ctx.accounts.voter.key();
+        emit!(RegistryActionApprovedEvent {
+            event_seq: next_event_seq(&mut ctx.accounts.dao_registry.event_seq)?,
+            index: registry_action.index,
+            approver,
+            approvals: registry_action.approvals.len() as u8,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Anyone can carry out an action once the multisig's threshold of current members
+    // approved it. Pausing works even while the program is paused, so it isn't gated.
+    #[cfg(feature = "registry-multisig")]
+    pub fn execute_registry_action(ctx: Context<ExecuteRegistryAction>) -> Result<()> {
+        let multisig = &ctx.accounts.multisig;
+        let registry_action = &ctx.accounts.registry_action;
+        let approvals = registry_action
+            .approvals
+            .iter()
+            .filter(|approver| multisig.members.contains(approver))
+            .count();
         require!(
-            !proposal.voters.iter().any(|v| v.voter == voter_key),
-            DaoError::AlreadyVoted
+            approvals >= multisig.threshold as usize,
+            DaoError::RegistryActionNotApproved
         );
 
-        let vote_weight = if let Some(token_mint) = proposal.token_mint {
-            if token_mint
-                == Pubkey::from_str("So11111111111111111111111111111111111111112").unwrap()
-            {
-                // SOL-weighted voting
-                let voter_balance = ctx.accounts.voter.lamports();
-                voter_balance
-            } else {
-                // SPL Token-weighted voting
-                require!(
-                    ctx.accounts.voter_token_account.is_some(),
-                    DaoError::TokenAccountRequired
-                );
-                // For SPL token voting, we would need to deserialize the token account
-                // For now, return 1 as a placeholder since we're focusing on SOL voting
-                1u64
+        let action = registry_action.action.clone();
+        let index = registry_action.index;
+        let multisig_key = multisig.key();
+        let dao_registry = &mut ctx.accounts.dao_registry;
+        let program_config = &mut ctx.accounts.program_config;
+        match action {
+            RegistryAction::Pause => apply_pause(program_config, dao_registry, true, multisig_key)?,
+            RegistryAction::Unpause => {
+                apply_pause(program_config, dao_registry, false, multisig_key)?
             }
-        } else {
-            // One person, one vote
-            1u64
-        };
+            RegistryAction::UpdateProgramConfig {
+                max_groups,
+                max_choices,
+                fee_destination,
+                guardian,
+            } => apply_program_config(
+                program_config,
+                dao_registry,
+                max_groups,
+                max_choices,
+                fee_destination,
+                guardian,
+            )?,
+            RegistryAction::SetRegistrationConfig {
+                open_registration,
+                group_deposit_lamports,
+                group_deposit_lock_days,
+            } => apply_registration_config(
+                dao_registry,
+                open_registration,
+                group_deposit_lamports,
+                group_deposit_lock_days,
+            )?,
+            RegistryAction::SetSigners { members, threshold } => {
+                let multisig = &mut ctx.accounts.multisig;
+                multisig.members = members;
+                multisig.threshold = threshold;
+            }
+            RegistryAction::SetAuthority { new_authority } => {
+                apply_registry_authority(dao_registry, new_authority)?
+            }
+        }
 
-        require!(vote_weight > 0, DaoError::NoVotingPower);
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.registry_action.executed_at = now;
 
-        // Record the vote
-        proposal.choice_votes[choice_index as usize] += vote_weight;
-        proposal.voters.push(VoterInfo {
-            voter: voter_key,
-            choice: choice_index,
-            vote_weight,
-            timestamp: current_time,
+        emit!(RegistryActionExecutedEvent {
+            event_seq: next_event_seq(&mut ctx.accounts.dao_registry.event_seq)?,
+            index,
+            timestamp: now,
         });
 
-        emit!(VoteCastEvent {
-            group_id: proposal.group_id.clone(),
-            proposal_id: proposal.proposal_id.clone(),
-            voter: voter_key,
-            choice: choice_index,
-            vote_weight,
-            timestamp: current_time,
+        Ok(())
+    }
+
+    pub fn withdraw_group_deposit(ctx: Context<WithdrawGroupDeposit>) -> Result<()> {
+        let group = &mut ctx.accounts.group;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(group.deposit_lamports > 0, DaoError::NoDeposit);
+        require!(now >= group.deposit_unlock_at, DaoError::DepositLocked);
+
+        let amount = group.deposit_lamports;
+        group.deposit_lamports = 0;
+
+        // The group PDA is program-owned, so the deposit can be moved out directly
+        **group.to_account_info().try_borrow_mut_lamports()? -= amount;
+        **ctx
+            .accounts
+            .authority
+            .to_account_info()
+            .try_borrow_mut_lamports()? += amount;
+
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(GroupDepositWithdrawnEvent {
+            group_id: group.group_id.clone(),
+            event_seq,
+            authority: ctx.accounts.authority.key(),
+            amount,
+            timestamp: now,
         });
 
         Ok(())
     }
 
-    pub fn add_group_member(ctx: Context<AddGroupMember>, member: Pubkey) -> Result<()> {
+    pub fn set_group_chat(ctx: Context<SetGroupChat>, external_chat_id: Option<i64>) -> Result<()> {
+        let group_key = ctx.accounts.group.key();
+        let old_chat_id = ctx.accounts.group.external_chat_id;
+        let now = Clock::get()?.unix_timestamp;
+
+        // The previous link is closed so the old chat no longer resolves to this group
+        if let Some(old_id) = old_chat_id {
+            let old_chat_link = ctx
+                .accounts
+                .old_chat_link
+                .as_ref()
+                .ok_or(DaoError::ChatLinkRequired)?;
+            require!(
+                old_chat_link.chat_id == old_id && old_chat_link.group == group_key,
+                DaoError::ChatLinkMismatch
+            );
+            old_chat_link.close(ctx.accounts.authority.to_account_info())?;
+        }
+
+        match (external_chat_id, ctx.accounts.new_chat_link.as_mut()) {
+            (Some(chat_id), Some(chat_link)) => {
+                let bump = ctx.bumps.new_chat_link.ok_or(DaoError::ChatLinkRequired)?;
+                link_chat(chat_link, chat_id, group_key, bump, now);
+            }
+            (Some(_), None) => return err!(DaoError::ChatLinkRequired),
+            (None, Some(_)) => return err!(DaoError::ChatLinkMismatch),
+            (None, None) => {}
+        }
+
         let group = &mut ctx.accounts.group;
+        group.external_chat_id = external_chat_id;
+
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(GroupChatChangedEvent {
+            group_id: group.group_id.clone(),
+            event_seq,
+            old_chat_id,
+            new_chat_id: external_chat_id,
+            timestamp: now,
+        });
 
+        Ok(())
+    }
+
+    // Set the group's public profile card in one go: avatar, website, social links and
+    // a contact. Fields left out are cleared.
+    #[cfg(feature = "profiles")]
+    pub fn set_group_profile(
+        ctx: Context<SetGroupProfile>,
+        avatar_uri: Option<String>,
+        website: Option<String>,
+        socials: Vec<String>,
+        contact: Option<String>,
+    ) -> Result<()> {
+        validate_metadata_uri(&avatar_uri)?;
+        validate_metadata_uri(&website)?;
         require!(
-            !group.members.iter().any(|m| m.pubkey == member),
-            DaoError::MemberAlreadyExists
+            socials.len() <= MAX_PROFILE_SOCIALS,
+            DaoError::InvalidGroupProfile
+        );
+        for (i, social) in socials.iter().enumerate() {
+            require!(
+                !socials[..i].contains(social),
+                DaoError::InvalidGroupProfile
+            );
+            validate_metadata_uri(&Some(social.clone()))?;
+        }
+        require!(
+            contact.as_ref().is_none_or(|c| {
+                !c.is_empty()
+                    && c.len() <= MAX_PROFILE_CONTACT_LEN
+                    && !c.chars().any(char::is_control)
+            }),
+            DaoError::InvalidGroupProfile
         );
 
-        group.members.push(GroupMember {
-            pubkey: member,
-            joined_at: Clock::get()?.unix_timestamp,
-        });
+        let now = Clock::get()?.unix_timestamp;
+        let profile = &mut ctx.accounts.group_profile;
+        profile.group = ctx.accounts.group.key();
+        profile.avatar_uri = avatar_uri;
+        profile.website = website;
+        profile.socials = socials;
+        profile.contact = contact;
+        profile.updated_at = now;
+        profile.bump = ctx.bumps.group_profile;
 
-        emit!(MemberAddedEvent {
+        let group = &mut ctx.accounts.group;
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(GroupProfileUpdatedEvent {
             group_id: group.group_id.clone(),
-            member,
+            event_seq,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Remove the profile card and return its rent to the group authority
+    #[cfg(feature = "profiles")]
+    pub fn close_group_profile(_ctx: Context<CloseGroupProfile>) -> Result<()> {
+        Ok(())
+    }
+
+    #[cfg(feature = "slugs")]
+    pub fn reserve_slug(ctx: Context<ReserveSlug>, slug: String) -> Result<()> {
+        validate_slug(&slug)?;
+
+        let reservation = &mut ctx.accounts.reservation;
+        reservation.registry = ctx.accounts.dao_registry.key();
+        reservation.slug = slug.clone();
+        reservation.owner = ctx.accounts.owner.key();
+        reservation.group = None;
+        reservation.reserved_at = Clock::get()?.unix_timestamp;
+        reservation.bump = ctx.bumps.reservation;
+
+        emit!(SlugReservedEvent {
+            event_seq: next_event_seq(&mut ctx.accounts.dao_registry.event_seq)?,
+            slug,
+            owner: ctx.accounts.owner.key(),
+            timestamp: reservation.reserved_at,
+        });
+
+        Ok(())
+    }
+
+    #[cfg(feature = "slugs")]
+    pub fn release_slug(ctx: Context<ReleaseSlug>) -> Result<()> {
+        emit!(SlugReleasedEvent {
+            event_seq: next_event_seq(&mut ctx.accounts.dao_registry.event_seq)?,
+            slug: ctx.accounts.reservation.slug.clone(),
+            owner: ctx.accounts.owner.key(),
             timestamp: Clock::get()?.unix_timestamp,
         });
 
         Ok(())
     }
 
-    pub fn remove_group_member(ctx: Context<RemoveGroupMember>, member: Pubkey) -> Result<()> {
-        let group = &mut ctx.accounts.group;
+    #[cfg(feature = "slugs")]
+    pub fn set_group_slug(ctx: Context<SetGroupSlug>, new_slug: String) -> Result<()> {
+        let group_key = ctx.accounts.group.key();
+        let old_slug = ctx.accounts.group.slug.clone();
 
-        let member_index = group
-            .members
-            .iter()
-            .position(|m| m.pubkey == member)
-            .ok_or(DaoError::MemberNotFound)?;
+        // The previous reservation is released back to the authority when the slug changes
+        if !old_slug.is_empty() {
+            let old_reservation = ctx
+                .accounts
+                .old_reservation
+                .as_ref()
+                .ok_or(DaoError::SlugReservationRequired)?;
+            require!(
+                old_reservation.slug == old_slug && old_reservation.group == Some(group_key),
+                DaoError::SlugReservationMismatch
+            );
+            old_reservation.close(ctx.accounts.authority.to_account_info())?;
+        }
 
-        group.members.remove(member_index);
+        let new_reservation = &mut ctx.accounts.new_reservation;
+        new_reservation.group = Some(group_key);
 
-        emit!(MemberRemovedEvent {
+        let group = &mut ctx.accounts.group;
+        group.slug = new_slug.clone();
+
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(GroupSlugChangedEvent {
             group_id: group.group_id.clone(),
-            member,
+            event_seq,
+            old_slug,
+            new_slug,
             timestamp: Clock::get()?.unix_timestamp,
         });
 
         Ok(())
     }
 
-    pub fn create_user_account(ctx: Context<CreateUserAccount>, telegram_id: i64) -> Result<()> {
-        let user_account = &mut ctx.accounts.user_account;
-        user_account.telegram_id = telegram_id;
-        user_account.wallet_pubkey = ctx.accounts.user_wallet.key();
-        user_account.created_at = Clock::get()?.unix_timestamp;
-        user_account.bump = ctx.bumps.user_account;
+    pub fn create_proposal(
+        ctx: Context<CreateProposal>,
+        proposal_id: String,
+        title: String,
+        description: String,
+        choices: Vec<String>,
+        voting_start: i64,
+        voting_end: i64,
+        token_mint: Option<Pubkey>,
+        metadata_uri: Option<String>,
+        draft: bool,
+        kind: ProposalKind,
+        emergency: bool,
+    ) -> Result<()> {
+        let creator = ctx.accounts.authority.key();
+        if emergency {
+            // Council members raise emergencies themselves, without the group authority
+            let council = ctx
+                .accounts
+                .council
+                .as_ref()
+                .ok_or(DaoError::CouncilRequired)?;
+            require!(
+                council.members.contains(&creator),
+                DaoError::NotCouncilMember
+            );
+            require!(!draft, DaoError::EmergencyDraft);
+        } else {
+            require!(
+                ctx.accounts.group.authority == creator,
+                DaoError::Unauthorized
+            );
+        }
+        validate_proposal_content(
+            &title,
+            &description,
+            &choices,
+            ctx.accounts.program_config.max_choices,
+            &metadata_uri,
+            token_mint,
+            &kind,
+        )?;
 
-        emit!(UserAccountCreatedEvent {
-            telegram_id,
-            wallet_pubkey: ctx.accounts.user_wallet.key(),
+        record_new_proposal(
+            NewProposal {
+                proposal_id,
+                title,
+                description,
+                choices,
+                voting_start,
+                voting_end,
+                token_mint,
+                metadata_uri,
+                draft,
+                kind,
+                emergency,
+                member_weighted: false,
+                split_voting: false,
+            },
+            &mut ctx.accounts.proposal,
+            ctx.bumps.proposal,
+            &mut ctx.accounts.proposal_index,
+            ctx.bumps.proposal_index,
+            &mut ctx.accounts.group,
+            &ctx.accounts.authority,
+            &mut ctx.accounts.participation,
+            ctx.bumps.participation,
+            &ctx.accounts.treasury,
+            &ctx.accounts.system_program,
+        )
+    }
+
+    // Save a routine vote (monthly budget, contributor payroll) as a template that
+    // instantiate_from_template can spawn again and again with fresh timing
+    #[cfg(feature = "templates")]
+    pub fn create_proposal_template(
+        ctx: Context<CreateProposalTemplate>,
+        template_id: String,
+        title: String,
+        description: String,
+        choices: Vec<String>,
+        voting_duration: i64,
+        token_mint: Option<Pubkey>,
+        metadata_uri: Option<String>,
+        kind: ProposalKind,
+        member_weighted: bool,
+        split_voting: bool,
+    ) -> Result<()> {
+        require!(
+            !template_id.is_empty() && template_id.len() <= MAX_TEMPLATE_ID_LEN,
+            DaoError::InvalidTemplateId
+        );
+        validate_proposal_content(
+            &title,
+            &description,
+            &choices,
+            ctx.accounts.program_config.max_choices,
+            &metadata_uri,
+            token_mint,
+            &kind,
+        )?;
+        require!(voting_duration > 0, DaoError::InvalidVotingPeriod);
+        // Member weights only apply to one-person-one-vote proposals
+        require!(
+            !member_weighted || token_mint.is_none(),
+            DaoError::MemberWeightingUnsupported
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        let template = &mut ctx.accounts.template;
+        template.group = ctx.accounts.group.key();
+        template.template_id = template_id.clone();
+        template.title = title;
+        template.description = description;
+        template.choices = choices;
+        template.voting_duration = voting_duration;
+        template.token_mint = token_mint;
+        template.metadata_uri = metadata_uri;
+        template.kind = kind;
+        template.member_weighted = member_weighted;
+        template.split_voting = split_voting;
+        template.instance_count = 0;
+        template.created_at = now;
+        template.bump = ctx.bumps.template;
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(ProposalTemplateCreatedEvent {
+            group_id: ctx.accounts.group.group_id.clone(),
+            event_seq,
+            template_id,
+            template: template.key(),
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Spawn a proposal from a template: its content and settings, voting from
+    // `voting_start` for the template's duration
+    #[cfg(feature = "templates")]
+    pub fn instantiate_from_template(
+        ctx: Context<InstantiateFromTemplate>,
+        proposal_id: String,
+        voting_start: i64,
+        draft: bool,
+    ) -> Result<()> {
+        let template = &mut ctx.accounts.template;
+        // Re-checked against today's program config and features
+        validate_proposal_content(
+            &template.title,
+            &template.description,
+            &template.choices,
+            ctx.accounts.program_config.max_choices,
+            &template.metadata_uri,
+            template.token_mint,
+            &template.kind,
+        )?;
+        let voting_end = voting_start
+            .checked_add(template.voting_duration)
+            .ok_or(DaoError::InvalidVotingPeriod)?;
+        template.instance_count = template
+            .instance_count
+            .checked_add(1)
+            .ok_or(DaoError::ProposalCountOverflow)?;
+
+        record_new_proposal(
+            NewProposal {
+                proposal_id: proposal_id.clone(),
+                title: template.title.clone(),
+                description: template.description.clone(),
+                choices: template.choices.clone(),
+                voting_start,
+                voting_end,
+                token_mint: template.token_mint,
+                metadata_uri: template.metadata_uri.clone(),
+                draft,
+                kind: template.kind.clone(),
+                emergency: false,
+                member_weighted: template.member_weighted,
+                split_voting: template.split_voting,
+            },
+            &mut ctx.accounts.proposal,
+            ctx.bumps.proposal,
+            &mut ctx.accounts.proposal_index,
+            ctx.bumps.proposal_index,
+            &mut ctx.accounts.group,
+            &ctx.accounts.authority,
+            &mut ctx.accounts.participation,
+            ctx.bumps.participation,
+            &ctx.accounts.treasury,
+            &ctx.accounts.system_program,
+        )?;
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(ProposalInstantiatedEvent {
+            group_id: ctx.accounts.group.group_id.clone(),
+            event_seq,
+            template_id: template.template_id.clone(),
+            proposal_id,
+            instance_count: template.instance_count,
             timestamp: Clock::get()?.unix_timestamp,
         });
 
         Ok(())
     }
 
-    pub fn login_user(ctx: Context<LoginUser>, telegram_id: i64) -> Result<()> {
-        // This function can be used to verify/retrieve existing user account
-        let user_account = &ctx.accounts.user_account;
+    // Retire a template; its rent goes back to the group authority
+    #[cfg(feature = "templates")]
+    pub fn close_proposal_template(ctx: Context<CloseProposalTemplate>) -> Result<()> {
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(ProposalTemplateClosedEvent {
+            group_id: ctx.accounts.group.group_id.clone(),
+            event_seq,
+            template_id: ctx.accounts.template.template_id.clone(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
 
+    // Open a draft for voting. A window planned for the future is kept; one whose start
+    // has already passed is moved to start now, keeping its length.
+    pub fn activate_proposal(ctx: Context<ActivateProposal>) -> Result<()> {
+        let clock = Clock::get()?;
+        let proposal = &ctx.accounts.proposal;
         require!(
-            user_account.telegram_id == telegram_id,
-            DaoError::InvalidTelegramId
+            proposal.status == ProposalStatus::Draft,
+            DaoError::InvalidProposalStatus
+        );
+        require!(
+            proposal.sponsor_count >= proposal.sponsors_needed,
+            DaoError::NotEnoughSponsors
         );
+        open_proposal(
+            &mut ctx.accounts.proposal,
+            &mut ctx.accounts.group,
+            &ctx.accounts.proposal_deposit,
+            &clock,
+        )
+    }
 
-        emit!(UserLoginEvent {
-            telegram_id,
-            wallet_pubkey: user_account.wallet_pubkey,
+    // Require new proposals to start as drafts and gather `threshold` co-sponsoring
+    // members before they open (0 turns it off). Emergencies are exempt.
+    #[cfg(feature = "sponsorship")]
+    pub fn set_sponsor_threshold(ctx: Context<SetProposalCooldown>, threshold: u8) -> Result<()> {
+        require!(
+            threshold <= MAX_SPONSOR_THRESHOLD,
+            DaoError::InvalidSponsorThreshold
+        );
+        let group = &mut ctx.accounts.group;
+        group.sponsor_threshold = threshold;
+
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(SponsorThresholdUpdatedEvent {
+            group_id: group.group_id.clone(),
+            event_seq,
+            threshold,
             timestamp: Clock::get()?.unix_timestamp,
         });
 
         Ok(())
     }
 
-    pub fn get_all_groups(ctx: Context<GetAllGroups>) -> Result<()> {
-        // This function just returns the DAO registry account
-        // The client will deserialize it to get the groups
-        let dao_registry = &ctx.accounts.dao_registry;
+    // A listed member backs a draft. Each member sponsors a draft once, as recorded by
+    // their ProposalSponsor PDA; the sponsor that completes the count opens the draft.
+    #[cfg(feature = "sponsorship")]
+    pub fn co_sponsor(ctx: Context<CoSponsor>) -> Result<()> {
+        let clock = Clock::get()?;
+        let proposal = &mut ctx.accounts.proposal;
+        require!(
+            proposal.status == ProposalStatus::Draft,
+            DaoError::InvalidProposalStatus
+        );
+        require!(
+            proposal.sponsor_count < proposal.sponsors_needed,
+            DaoError::SponsorshipComplete
+        );
+        proposal.sponsor_count += 1;
+
+        let sponsorship = &mut ctx.accounts.sponsorship;
+        sponsorship.proposal = proposal.key();
+        sponsorship.sponsor = ctx.accounts.sponsor.key();
+        sponsorship.sponsored_at = clock.unix_timestamp;
+        sponsorship.bump = ctx.bumps.sponsorship;
 
-        msg!("DAO Registry has {} groups", dao_registry.groups.len());
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(ProposalSponsoredEvent {
+            group_id: proposal.group_id.clone(),
+            event_seq,
+            proposal_id: proposal.proposal_id.clone(),
+            sponsor: sponsorship.sponsor,
+            sponsor_count: proposal.sponsor_count,
+            sponsors_needed: proposal.sponsors_needed,
+            timestamp: clock.unix_timestamp,
+        });
 
+        if proposal.sponsor_count == proposal.sponsors_needed {
+            open_proposal(
+                proposal,
+                &mut ctx.accounts.group,
+                &ctx.accounts.proposal_deposit,
+                &clock,
+            )?;
+        }
         Ok(())
     }
-}
 
-// Account Structs
-#[account]
-pub struct DaoRegistry {
-    pub authority: Pubkey,
-    pub groups: Vec<GroupInfo>,
-    pub bump: u8,
-}
+    // Withdraw a draft or active proposal. Any finalize bounty goes to the protocol fee
+    // destination, since nobody will collect it by finalizing.
+    pub fn cancel_proposal(ctx: Context<CancelProposal>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let proposal = &mut ctx.accounts.proposal;
+        require!(
+            matches!(
+                proposal.status,
+                ProposalStatus::Draft | ProposalStatus::Active
+            ),
+            DaoError::InvalidProposalStatus
+        );
 
-#[account]
-pub struct Group {
-    pub group_id: String,
-    pub name: String,
-    pub description: String,
-    pub authority: Pubkey,
-    pub proposals: Vec<ProposalInfo>,
-    pub members: Vec<GroupMember>,
-    pub created_at: i64,
-    pub bump: u8,
-}
+        if proposal.status == ProposalStatus::Active {
+            ctx.accounts.group.close_active_proposal();
+        }
+        proposal.status = ProposalStatus::Cancelled;
+        let bounty = proposal.finalize_bounty;
+        proposal.finalize_bounty = 0;
+        if bounty > 0 {
+            **proposal.to_account_info().try_borrow_mut_lamports()? -= bounty;
+            **ctx.accounts.fee_destination.try_borrow_mut_lamports()? += bounty;
+        }
 
-#[account]
-pub struct Proposal {
-    pub proposal_id: String,
-    pub group_id: String,
-    pub title: String,
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(ProposalStatusChangedEvent {
+            group_id: proposal.group_id.clone(),
+            event_seq,
+            proposal_id: proposal.proposal_id.clone(),
+            status: proposal.status,
+            voting_start: proposal.voting_start,
+            voting_end: proposal.voting_end,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Point a proposal at new off-chain content, e.g. an archive of its discussion
+    pub fn set_proposal_metadata_uri(
+        ctx: Context<ManageProposal>,
+        metadata_uri: Option<String>,
+    ) -> Result<()> {
+        validate_metadata_uri(&metadata_uri)?;
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.metadata_uri = metadata_uri.clone();
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(ProposalMetadataUpdatedEvent {
+            group_id: proposal.group_id.clone(),
+            event_seq,
+            proposal_id: proposal.proposal_id.clone(),
+            metadata_uri,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Record that a succeeded text proposal has been carried out; the other kinds are
+    // executed by their own instructions
+    pub fn mark_proposal_executed(ctx: Context<ManageProposal>) -> Result<()> {
+        let proposal = &mut ctx.accounts.proposal;
+        require!(
+            proposal.status == ProposalStatus::Succeeded,
+            DaoError::InvalidProposalStatus
+        );
+        require!(
+            proposal.kind == ProposalKind::Text,
+            DaoError::WrongProposalKind
+        );
+        proposal.status = ProposalStatus::Executed;
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(ProposalStatusChangedEvent {
+            group_id: proposal.group_id.clone(),
+            event_seq,
+            proposal_id: proposal.proposal_id.clone(),
+            status: proposal.status,
+            voting_start: proposal.voting_start,
+            voting_end: proposal.voting_end,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Name a program to call once the proposal passes, so other protocols (vesting,
+    // registries, games) can react to the decision. Only until voting starts.
+    #[cfg(feature = "callbacks")]
+    pub fn set_execution_callback(
+        ctx: Context<SetExecutionCallback>,
+        on_success_program: Pubkey,
+        payload: Vec<u8>,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let proposal = &ctx.accounts.proposal;
+        require!(
+            proposal.status == ProposalStatus::Draft
+                || (proposal.status == ProposalStatus::Active && proposal.before_voting(&clock)),
+            DaoError::InvalidProposalStatus
+        );
+        // Calling back into this program could re-enter its own instructions
+        require!(
+            on_success_program != crate::ID && payload.len() <= MAX_CALLBACK_PAYLOAD_LEN,
+            DaoError::InvalidCallback
+        );
+
+        let callback = &mut ctx.accounts.callback;
+        callback.proposal = proposal.key();
+        callback.on_success_program = on_success_program;
+        callback.payload = payload;
+        callback.bump = ctx.bumps.callback;
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(ExecutionCallbackSetEvent {
+            group_id: proposal.group_id.clone(),
+            event_seq,
+            proposal_id: proposal.proposal_id.clone(),
+            on_success_program,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Programs this group's execution callbacks may call. Replaces the whole list; an
+    // empty list blocks all callbacks.
+    #[cfg(feature = "callbacks")]
+    pub fn set_program_allowlist(
+        ctx: Context<SetProgramAllowlist>,
+        programs: Vec<Pubkey>,
+    ) -> Result<()> {
+        require!(
+            programs.len() <= MAX_ALLOWLISTED_PROGRAMS,
+            DaoError::InvalidProgramAllowlist
+        );
+        for (i, program) in programs.iter().enumerate() {
+            require!(
+                *program != crate::ID && !programs[..i].contains(program),
+                DaoError::InvalidProgramAllowlist
+            );
+        }
+
+        let allowlist = &mut ctx.accounts.program_allowlist;
+        allowlist.group = ctx.accounts.group.key();
+        allowlist.programs = programs.clone();
+        allowlist.bump = ctx.bumps.program_allowlist;
+
+        let group = &mut ctx.accounts.group;
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(ProgramAllowlistUpdatedEvent {
+            group_id: group.group_id.clone(),
+            event_seq,
+            programs,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Permissionless, once the proposal has passed: CPI into the callback program with the
+    // payload followed by the Borsh-encoded CallbackResult. The callee gets the proposal,
+    // then the callback PDA as a signer proving the call came from this program, then the
+    // remaining accounts. The callback is closed to the group authority, so it runs once.
+    // The target must be on the group's program allowlist when the callback runs.
+    #[cfg(feature = "callbacks")]
+    pub fn execute_proposal_callback<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ExecuteProposalCallback<'info>>,
+    ) -> Result<()> {
+        let proposal = &ctx.accounts.proposal;
+        require!(
+            matches!(
+                proposal.status,
+                ProposalStatus::Succeeded | ProposalStatus::Executed
+            ),
+            DaoError::InvalidProposalStatus
+        );
+        require!(
+            ctx.accounts
+                .program_allowlist
+                .programs
+                .contains(&ctx.accounts.callback.on_success_program),
+            DaoError::ProgramNotAllowlisted
+        );
+
+        let result = CallbackResult {
+            proposal: proposal.key(),
+            group: ctx.accounts.group.key(),
+            status: proposal.status,
+            winning_choice: proposal.winner.ok_or(DaoError::InvalidProposalStatus)?,
+            choice_votes: proposal.choice_votes.clone(),
+        };
+        let callback = &ctx.accounts.callback;
+        let mut data = callback.payload.clone();
+        result.serialize(&mut data)?;
+
+        let mut accounts = vec![
+            anchor_lang::solana_program::instruction::AccountMeta::new_readonly(
+                proposal.key(),
+                false,
+            ),
+            anchor_lang::solana_program::instruction::AccountMeta::new_readonly(
+                callback.key(),
+                true,
+            ),
+        ];
+        accounts.extend(ctx.remaining_accounts.iter().map(|account| {
+            if account.is_writable {
+                anchor_lang::solana_program::instruction::AccountMeta::new(
+                    account.key(),
+                    account.is_signer,
+                )
+            } else {
+                anchor_lang::solana_program::instruction::AccountMeta::new_readonly(
+                    account.key(),
+                    account.is_signer,
+                )
+            }
+        }));
+        let mut infos = vec![proposal.to_account_info(), callback.to_account_info()];
+        infos.extend(ctx.remaining_accounts.iter().cloned());
+        infos.push(ctx.accounts.on_success_program.to_account_info());
+
+        let proposal_key = proposal.key();
+        anchor_lang::solana_program::program::invoke_signed(
+            &anchor_lang::solana_program::instruction::Instruction {
+                program_id: callback.on_success_program,
+                accounts,
+                data,
+            },
+            &infos,
+            &[&[b"callback", proposal_key.as_ref(), &[callback.bump]]],
+        )?;
+
+        let group = &mut ctx.accounts.group;
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(ExecutionCallbackRunEvent {
+            group_id: group.group_id.clone(),
+            event_seq,
+            proposal_id: ctx.accounts.proposal.proposal_id.clone(),
+            on_success_program: ctx.accounts.callback.on_success_program,
+            executed_by: ctx.accounts.executor.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Permissionless: pay an approved treasury transfer from the group treasury PDA
+    #[cfg(feature = "treasury")]
+    pub fn execute_treasury_transfer(ctx: Context<ExecuteTreasuryTransfer>) -> Result<()> {
+        let proposal = &ctx.accounts.proposal;
+        require_approved(proposal)?;
+        let ProposalKind::TreasuryTransfer(transfer) = &proposal.kind else {
+            return err!(DaoError::WrongProposalKind);
+        };
+        require_keys_eq!(
+            ctx.accounts.recipient.key(),
+            transfer.recipient,
+            DaoError::ExecutionAccountMismatch
+        );
+
+        let group_key = ctx.accounts.group.key();
+        let treasury_seeds: &[&[u8]] = &[b"treasury", group_key.as_ref(), &[ctx.bumps.treasury]];
+        system_program::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.treasury.to_account_info(),
+                    to: ctx.accounts.recipient.to_account_info(),
+                },
+                &[treasury_seeds],
+            ),
+            transfer.lamports,
+        )?;
+
+        let transfers = vec![ExecutedTransfer {
+            mint: None,
+            recipient: transfer.recipient,
+            amount: transfer.lamports,
+        }];
+        finish_execution(
+            &mut ctx.accounts.proposal,
+            &mut ctx.accounts.group,
+            ctx.accounts.executor.key(),
+            transfers,
+        )
+    }
+
+    // Permissionless: drain the group treasury to the recovery address of a recovery
+    // proposal the first choice won with a supermajority and enough turnout, in a group
+    // that still takes votes only from its members. SPL balances
+    // are drained too, passed in the remaining accounts as (treasury token account,
+    // recovery token account) pairs, treasury accounts in ascending address order.
+    #[cfg(feature = "treasury")]
+    pub fn execute_treasury_recovery<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ExecuteTreasuryRecovery<'info>>,
+    ) -> Result<()> {
+        let proposal = &ctx.accounts.proposal;
+        require_approved(proposal)?;
+        let ProposalKind::TreasuryRecovery(recovery) = &proposal.kind else {
+            return err!(DaoError::WrongProposalKind);
+        };
+        require_keys_eq!(
+            ctx.accounts.recovery.key(),
+            recovery.recovery,
+            DaoError::ExecutionAccountMismatch
+        );
+        let voter_count = proposal
+            .offchain_voter_count
+            .unwrap_or_else(|| proposal.voters.iter().filter(|v| v.vote_weight > 0).count() as u32);
+        require!(
+            ctx.accounts.group.members_only_voting,
+            DaoError::RecoveryNeedsMembersOnlyVoting
+        );
+        require!(
+            recovery_carries(
+                &proposal.choice_votes,
+                voter_count,
+                ctx.accounts.group.member_count
+            ),
+            DaoError::RecoveryNotCarried
+        );
+
+        let group_key = ctx.accounts.group.key();
+        let treasury_seeds: &[&[u8]] = &[b"treasury", group_key.as_ref(), &[ctx.bumps.treasury]];
+        let treasury = ctx.accounts.treasury.to_account_info();
+        let lamports = treasury.lamports();
+        let mut transfers = Vec::new();
+        if lamports > 0 {
+            system_program::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: treasury.clone(),
+                        to: ctx.accounts.recovery.to_account_info(),
+                    },
+                    &[treasury_seeds],
+                ),
+                lamports,
+            )?;
+            transfers.push(ExecutedTransfer {
+                mint: None,
+                recipient: recovery.recovery,
+                amount: lamports,
+            });
+        }
+
+        require!(
+            ctx.remaining_accounts.len().is_multiple_of(2),
+            DaoError::InvalidTokenAccount
+        );
+        let mut previous = None;
+        for pair in ctx.remaining_accounts.chunks(2) {
+            let (treasury_token_account, recovery_token_account) = (&pair[0], &pair[1]);
+            require!(
+                previous < Some(treasury_token_account.key()),
+                DaoError::InvalidTokenAccount
+            );
+            previous = Some(treasury_token_account.key());
+            let token_program = ctx
+                .accounts
+                .token_program
+                .as_ref()
+                .ok_or(DaoError::TokenAccountRequired)?;
+            let (owner, balance) = spl_token_holding(treasury_token_account)?;
+            require_keys_eq!(owner, treasury.key(), DaoError::InvalidTokenAccount);
+            let (recipient, _) = spl_token_holding(recovery_token_account)?;
+            require_keys_eq!(recipient, recovery.recovery, DaoError::InvalidTokenAccount);
+            if balance > 0 {
+                transfer_spl(
+                    token_program,
+                    treasury_token_account,
+                    recovery_token_account,
+                    &treasury,
+                    &[treasury_seeds],
+                    balance,
+                )?;
+                // An SPL token account starts with its mint
+                let mint = Pubkey::try_from(&treasury_token_account.try_borrow_data()?[..32])
+                    .map_err(|_| DaoError::InvalidTokenAccount)?;
+                transfers.push(ExecutedTransfer {
+                    mint: Some(mint),
+                    recipient: recovery.recovery,
+                    amount: balance,
+                });
+            }
+        }
+
+        let group = &mut ctx.accounts.group;
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(TreasuryRecoveredEvent {
+            group_id: group.group_id.clone(),
+            event_seq,
+            proposal_id: ctx.accounts.proposal.proposal_id.clone(),
+            recovery: recovery.recovery,
+            lamports,
+            token_accounts: (ctx.remaining_accounts.len() / 2) as u8,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        finish_execution(
+            &mut ctx.accounts.proposal,
+            &mut ctx.accounts.group,
+            ctx.accounts.executor.key(),
+            transfers,
+        )
+    }
+
+    // Permissionless: carry out an approved staking action with treasury SOL. A delegation
+    // moves the lamports into a new stake account (seeds: "treasury_stake", proposal) whose
+    // staker and withdrawer are the treasury PDA, so only later proposals can deactivate
+    // it and withdraw its whole balance back to the treasury.
+    #[cfg(feature = "treasury-staking")]
+    pub fn execute_treasury_stake(ctx: Context<ExecuteTreasuryStake>) -> Result<()> {
+        use anchor_lang::solana_program::{
+            program::{invoke, invoke_signed},
+            stake,
+        };
+
+        let proposal = &ctx.accounts.proposal;
+        require_approved(proposal)?;
+        let ProposalKind::TreasuryStake(action) = proposal.kind.clone() else {
+            return err!(DaoError::WrongProposalKind);
+        };
+
+        let group_key = ctx.accounts.group.key();
+        let proposal_key = proposal.key();
+        let treasury_seeds: &[&[u8]] = &[b"treasury", group_key.as_ref(), &[ctx.bumps.treasury]];
+        let treasury = ctx.accounts.treasury.to_account_info();
+        let stake_account = ctx.accounts.stake_account.to_account_info();
+        let clock = ctx.accounts.clock.to_account_info();
+        let stake_history = ctx.accounts.stake_history.to_account_info();
+        let mut transfers = Vec::new();
+
+        match action {
+            StakeAction::Delegate {
+                vote_account,
+                lamports,
+            } => {
+                let (expected, stake_bump) = Pubkey::find_program_address(
+                    &[b"treasury_stake", proposal_key.as_ref()],
+                    &crate::ID,
+                );
+                require_keys_eq!(
+                    stake_account.key(),
+                    expected,
+                    DaoError::ExecutionAccountMismatch
+                );
+                let vote = ctx
+                    .accounts
+                    .vote_account
+                    .as_ref()
+                    .ok_or(DaoError::ExecutionAccountMismatch)?;
+                require_keys_eq!(vote.key(), vote_account, DaoError::ExecutionAccountMismatch);
+                let stake_seeds: &[&[u8]] =
+                    &[b"treasury_stake", proposal_key.as_ref(), &[stake_bump]];
+
+                system_program::create_account(
+                    CpiContext::new_with_signer(
+                        ctx.accounts.system_program.to_account_info(),
+                        system_program::CreateAccount {
+                            from: treasury.clone(),
+                            to: stake_account.clone(),
+                        },
+                        &[treasury_seeds, stake_seeds],
+                    ),
+                    lamports,
+                    stake::state::StakeStateV2::size_of() as u64,
+                    &stake::program::ID,
+                )?;
+                let authorized = stake::state::Authorized {
+                    staker: treasury.key(),
+                    withdrawer: treasury.key(),
+                };
+                invoke(
+                    &stake::instruction::initialize(
+                        &stake_account.key(),
+                        &authorized,
+                        &stake::state::Lockup::default(),
+                    ),
+                    &[stake_account.clone(), ctx.accounts.rent.to_account_info()],
+                )?;
+                invoke_signed(
+                    &stake::instruction::delegate_stake(
+                        &stake_account.key(),
+                        &treasury.key(),
+                        &vote_account,
+                    ),
+                    &[
+                        stake_account.clone(),
+                        vote.to_account_info(),
+                        clock,
+                        stake_history,
+                        ctx.accounts.stake_config.to_account_info(),
+                        treasury.clone(),
+                    ],
+                    &[treasury_seeds],
+                )?;
+                transfers.push(ExecutedTransfer {
+                    mint: None,
+                    recipient: stake_account.key(),
+                    amount: lamports,
+                });
+            }
+            StakeAction::Deactivate {
+                stake_account: expected,
+            } => {
+                require_keys_eq!(
+                    stake_account.key(),
+                    expected,
+                    DaoError::ExecutionAccountMismatch
+                );
+                invoke_signed(
+                    &stake::instruction::deactivate_stake(&stake_account.key(), &treasury.key()),
+                    &[stake_account.clone(), clock, treasury.clone()],
+                    &[treasury_seeds],
+                )?;
+            }
+            StakeAction::Withdraw {
+                stake_account: expected,
+            } => {
+                require_keys_eq!(
+                    stake_account.key(),
+                    expected,
+                    DaoError::ExecutionAccountMismatch
+                );
+                // The stake program refuses while any of it is still active
+                let lamports = stake_account.lamports();
+                invoke_signed(
+                    &stake::instruction::withdraw(
+                        &stake_account.key(),
+                        &treasury.key(),
+                        &treasury.key(),
+                        lamports,
+                        None,
+                    ),
+                    &[
+                        stake_account.clone(),
+                        treasury.clone(),
+                        clock,
+                        stake_history,
+                        treasury.clone(),
+                    ],
+                    &[treasury_seeds],
+                )?;
+                transfers.push(ExecutedTransfer {
+                    mint: None,
+                    recipient: treasury.key(),
+                    amount: lamports,
+                });
+            }
+        }
+
+        finish_execution(
+            &mut ctx.accounts.proposal,
+            &mut ctx.accounts.group,
+            ctx.accounts.executor.key(),
+            transfers,
+        )
+    }
+
+    // Permissionless: run an approved swap of treasury tokens through the DEX program the
+    // proposal names, e.g. a Jupiter route. The route's instruction data and accounts are
+    // built off-chain for a fresh quote; the treasury PDA signs the CPI, so the route may
+    // touch no treasury token accounts but the proposal's input and output, no mint or
+    // stake account the treasury has authority over, nor take its lamports. Afterwards
+    // both token accounts must still be the treasury's alone, with no delegate or close
+    // authority. The swap must spend at most amount_in and return at least min_amount_out.
+    #[cfg(feature = "treasury-swaps")]
+    pub fn execute_treasury_swap<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ExecuteTreasurySwap<'info>>,
+        route_data: Vec<u8>,
+    ) -> Result<()> {
+        let proposal = &ctx.accounts.proposal;
+        require_approved(proposal)?;
+        let ProposalKind::TreasurySwap(terms) = proposal.kind.clone() else {
+            return err!(DaoError::WrongProposalKind);
+        };
+        require_keys_eq!(
+            ctx.accounts.swap_program.key(),
+            terms.swap_program,
+            DaoError::ExecutionAccountMismatch
+        );
+
+        let treasury = ctx.accounts.treasury.to_account_info();
+        let input = ctx.accounts.treasury_input.to_account_info();
+        let output = ctx.accounts.treasury_output.to_account_info();
+        let (input_owner, input_before) = spl_token_balance(terms.input_mint, &input)?;
+        let (output_owner, output_before) = spl_token_balance(terms.output_mint, &output)?;
+        require_keys_eq!(input_owner, treasury.key(), DaoError::InvalidTokenAccount);
+        require_keys_eq!(output_owner, treasury.key(), DaoError::InvalidTokenAccount);
+        for account in ctx.remaining_accounts {
+            require!(
+                account.key() == input.key()
+                    || account.key() == output.key()
+                    || !spl_token_account_of(account, treasury.key()),
+                DaoError::SwapRouteRejected
+            );
+            require!(
+                !treasury_authority_account(account, treasury.key()),
+                DaoError::SwapRouteRejected
+            );
+        }
+        let lamports_before = treasury.lamports();
+
+        let accounts = ctx
+            .remaining_accounts
+            .iter()
+            .map(|account| {
+                let is_signer = account.is_signer || account.key() == treasury.key();
+                if account.is_writable {
+                    anchor_lang::solana_program::instruction::AccountMeta::new(
+                        account.key(),
+                        is_signer,
+                    )
+                } else {
+                    anchor_lang::solana_program::instruction::AccountMeta::new_readonly(
+                        account.key(),
+                        is_signer,
+                    )
+                }
+            })
+            .collect();
+        let mut infos = ctx.remaining_accounts.to_vec();
+        infos.push(ctx.accounts.swap_program.to_account_info());
+
+        let group_key = ctx.accounts.group.key();
+        anchor_lang::solana_program::program::invoke_signed(
+            &anchor_lang::solana_program::instruction::Instruction {
+                program_id: terms.swap_program,
+                accounts,
+                data: route_data,
+            },
+            &infos,
+            &[&[b"treasury", group_key.as_ref(), &[ctx.bumps.treasury]]],
+        )?;
+
+        let input_after = untouched_treasury_token_account(&input, treasury.key())?;
+        let output_after = untouched_treasury_token_account(&output, treasury.key())?;
+        let spent = input_before.saturating_sub(input_after);
+        let received = output_after.saturating_sub(output_before);
+        require!(
+            treasury.lamports() >= lamports_before,
+            DaoError::SwapRouteRejected
+        );
+        require!(
+            spent <= terms.amount_in && received >= terms.min_amount_out,
+            DaoError::SwapSlippageExceeded
+        );
+
+        let group = &mut ctx.accounts.group;
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(TreasurySwappedEvent {
+            group_id: group.group_id.clone(),
+            event_seq,
+            proposal_id: ctx.accounts.proposal.proposal_id.clone(),
+            swap_program: terms.swap_program,
+            input_mint: terms.input_mint,
+            output_mint: terms.output_mint,
+            amount_in: spent,
+            amount_out: received,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        finish_execution(
+            &mut ctx.accounts.proposal,
+            &mut ctx.accounts.group,
+            ctx.accounts.executor.key(),
+            Vec::new(),
+        )
+    }
+
+    // Permissionless: open the TokenDistribution of a succeeded distribution proposal.
+    // Nothing is paid yet; each recipient claims their own allocation.
+    #[cfg(feature = "token-distribution")]
+    pub fn execute_token_distribution(ctx: Context<ExecuteTokenDistribution>) -> Result<()> {
+        let proposal = &ctx.accounts.proposal;
+        require_approved(proposal)?;
+        let ProposalKind::TokenDistribution(terms) = &proposal.kind else {
+            return err!(DaoError::WrongProposalKind);
+        };
+        let now = Clock::get()?.unix_timestamp;
+
+        let distribution = &mut ctx.accounts.distribution;
+        distribution.group = ctx.accounts.group.key();
+        distribution.proposal = proposal.key();
+        distribution.mint = terms.mint;
+        distribution.source = terms.source;
+        distribution.merkle_root = terms.merkle_root;
+        distribution.total_amount = terms.total_amount;
+        distribution.claimed_amount = 0;
+        distribution.claim_count = 0;
+        distribution.created_at = now;
+        distribution.bump = ctx.bumps.distribution;
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(TokenDistributionCreatedEvent {
+            group_id: proposal.group_id.clone(),
+            event_seq,
+            proposal_id: proposal.proposal_id.clone(),
+            mint: terms.mint,
+            source: terms.source,
+            merkle_root: terms.merkle_root,
+            total_amount: terms.total_amount,
+            timestamp: now,
+        });
+
+        finish_execution(
+            &mut ctx.accounts.proposal,
+            &mut ctx.accounts.group,
+            ctx.accounts.executor.key(),
+            Vec::new(),
+        )
+    }
+
+    // Claim the allocation the distribution's Merkle root commits to for the signer:
+    // minted by the treasury PDA as mint authority, or paid from a treasury token account.
+    // The claim record makes it one claim per recipient.
+    #[cfg(feature = "token-distribution")]
+    pub fn claim_token_distribution(
+        ctx: Context<ClaimTokenDistribution>,
+        amount: u64,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let recipient = ctx.accounts.recipient.key();
+        let distribution = &mut ctx.accounts.distribution;
+        require!(
+            proof.len() <= MAX_MERKLE_PROOF_LEN
+                && merkle_root_from_proof(distribution_leaf(&recipient, amount), &proof)
+                    == distribution.merkle_root,
+            DaoError::InvalidMerkleProof
+        );
+        let claimed_amount = distribution
+            .claimed_amount
+            .checked_add(amount)
+            .filter(|&claimed| claimed <= distribution.total_amount)
+            .ok_or(DaoError::DistributionExhausted)?;
+        spl_token_balance(distribution.mint, &ctx.accounts.recipient_token_account)?;
+
+        let group_key = ctx.accounts.group.key();
+        let treasury_seeds: &[&[u8]] = &[b"treasury", group_key.as_ref(), &[ctx.bumps.treasury]];
+        let treasury = ctx.accounts.treasury.to_account_info();
+        let token_program = ctx.accounts.token_program.to_account_info();
+        let recipient_token_account = ctx.accounts.recipient_token_account.to_account_info();
+        match distribution.source {
+            DistributionSource::Mint => anchor_spl::token::mint_to(
+                CpiContext::new_with_signer(
+                    token_program,
+                    anchor_spl::token::MintTo {
+                        mint: ctx.accounts.mint.to_account_info(),
+                        to: recipient_token_account,
+                        authority: treasury,
+                    },
+                    &[treasury_seeds],
+                ),
+                amount,
+            )?,
+            DistributionSource::Treasury => {
+                let source = ctx
+                    .accounts
+                    .treasury_token_account
+                    .as_ref()
+                    .ok_or(DaoError::TokenAccountRequired)?;
+                let (owner, _) = spl_token_balance(distribution.mint, source)?;
+                require_keys_eq!(owner, treasury.key(), DaoError::InvalidTokenAccount);
+                transfer_spl(
+                    &token_program,
+                    source,
+                    &recipient_token_account,
+                    &treasury,
+                    &[treasury_seeds],
+                    amount,
+                )?;
+            }
+        }
+        distribution.claimed_amount = claimed_amount;
+        distribution.claim_count = distribution.claim_count.saturating_add(1);
+
+        let claim = &mut ctx.accounts.claim;
+        claim.distribution = distribution.key();
+        claim.recipient = recipient;
+        claim.amount = amount;
+        claim.claimed_at = now;
+        claim.bump = ctx.bumps.claim;
+
+        let group = &mut ctx.accounts.group;
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(TokenDistributionClaimedEvent {
+            group_id: group.group_id.clone(),
+            event_seq,
+            distribution: distribution.key(),
+            recipient,
+            amount,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Permissionless: apply an approved change to the group's settings
+    pub fn execute_config_change(ctx: Context<ExecuteConfigChange>) -> Result<()> {
+        let proposal = &ctx.accounts.proposal;
+        require_approved(proposal)?;
+        let ProposalKind::ConfigChange(change) = &proposal.kind else {
+            return err!(DaoError::WrongProposalKind);
+        };
+
+        let group = &mut ctx.accounts.group;
+        if let Some(max_members) = change.max_members {
+            require!(
+                max_members == 0 || max_members >= group.member_count,
+                DaoError::InvalidMemberCap
+            );
+            group.max_members = max_members;
+        }
+        if let Some(fee_lamports) = change.proposal_fee_lamports {
+            group.proposal_fee_lamports = fee_lamports;
+        }
+        if let Some(members_only_voting) = change.members_only_voting {
+            group.members_only_voting = members_only_voting;
+        }
+        if let Some(visibility) = change.visibility {
+            group.visibility = visibility;
+        }
+        if let Some(governance) = change.governance {
+            group.governance = governance;
+        }
+
+        finish_execution(
+            &mut ctx.accounts.proposal,
+            &mut ctx.accounts.group,
+            ctx.accounts.executor.key(),
+            Vec::new(),
+        )
+    }
+
+    // Permissionless: add or remove the member named by an approved membership change.
+    // An added member's record is paid for by the executor; a removed one's rent goes
+    // to the group authority.
+    pub fn execute_membership_change(ctx: Context<ExecuteMembershipChange>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let proposal = &ctx.accounts.proposal;
+        require_approved(proposal)?;
+        let ProposalKind::MembershipChange(change) = proposal.kind.clone() else {
+            return err!(DaoError::WrongProposalKind);
+        };
+
+        let group = &mut ctx.accounts.group;
+        match change.action {
+            MembershipAction::Add => admit_member(
+                group,
+                &mut ctx.accounts.member_record,
+                change.member,
+                &ctx.accounts.ban,
+                ctx.bumps.member_record,
+                now,
+            )?,
+            MembershipAction::Remove => {
+                require!(
+                    ctx.accounts.member_record.wallet == change.member,
+                    DaoError::MemberNotFound
+                );
+                ctx.accounts
+                    .member_record
+                    .close(ctx.accounts.group_authority.to_account_info())?;
+                group.member_count = group.member_count.saturating_sub(1);
+
+                let event_seq = next_event_seq(&mut group.event_seq)?;
+                emit!(MemberRemovedEvent {
+                    group_id: group.group_id.clone(),
+                    event_seq,
+                    member: change.member,
+                    timestamp: now,
+                });
+            }
+        }
+
+        finish_execution(
+            &mut ctx.accounts.proposal,
+            &mut ctx.accounts.group,
+            ctx.accounts.executor.key(),
+            Vec::new(),
+        )
+    }
+
+    // Permissionless: seat the winners of a finalized council election, replacing the
+    // group's council
+    pub fn execute_election(ctx: Context<ExecuteElection>) -> Result<()> {
+        let proposal = &ctx.accounts.proposal;
+        require!(
+            proposal.status == ProposalStatus::Succeeded,
+            DaoError::InvalidProposalStatus
+        );
+        let ProposalKind::Election(election) = &proposal.kind else {
+            return err!(DaoError::WrongProposalKind);
+        };
+        let members = election_winners(election, &proposal.choice_votes)?;
+
+        let council = &mut ctx.accounts.council;
+        council.group = ctx.accounts.group.key();
+        council.members = members.clone();
+        council.threshold = election.threshold;
+        council.bump = ctx.bumps.council;
+
+        let group = &mut ctx.accounts.group;
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(CouncilUpdatedEvent {
+            group_id: group.group_id.clone(),
+            event_seq,
+            members,
+            threshold: election.threshold,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        finish_execution(
+            &mut ctx.accounts.proposal,
+            &mut ctx.accounts.group,
+            ctx.accounts.executor.key(),
+            Vec::new(),
+        )
+    }
+
+    // Permissionless: open the Grant an approved grant proposal describes. Nothing is
+    // paid yet; each milestone's tranche is released from the group treasury once it is
+    // approved.
+    #[cfg(feature = "grants")]
+    pub fn execute_grant(ctx: Context<ExecuteGrant>) -> Result<()> {
+        let proposal = &ctx.accounts.proposal;
+        require_approved(proposal)?;
+        let ProposalKind::Grant(terms) = &proposal.kind else {
+            return err!(DaoError::WrongProposalKind);
+        };
+        let now = Clock::get()?.unix_timestamp;
+
+        let grant = &mut ctx.accounts.grant;
+        grant.group = ctx.accounts.group.key();
+        grant.proposal = proposal.key();
+        grant.recipient = terms.recipient;
+        grant.approval = terms.approval;
+        grant.milestones = terms
+            .milestones
+            .iter()
+            .map(|&amount| Milestone {
+                amount,
+                approvals: 0,
+                approved_at: 0,
+                claimed: false,
+            })
+            .collect();
+        grant.released = 0;
+        grant.created_at = now;
+        grant.bump = ctx.bumps.grant;
+        let total = terms
+            .milestones
+            .iter()
+            .try_fold(0u64, |total, &amount| total.checked_add(amount))
+            .ok_or(DaoError::TallyOverflow)?;
+        ctx.accounts.group.commit_treasury(total)?;
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(GrantCreatedEvent {
+            group_id: proposal.group_id.clone(),
+            event_seq,
+            proposal_id: proposal.proposal_id.clone(),
+            recipient: terms.recipient,
+            milestones: terms.milestones.clone(),
+            approval: terms.approval,
+            timestamp: now,
+        });
+
+        finish_execution(
+            &mut ctx.accounts.proposal,
+            &mut ctx.accounts.group,
+            ctx.accounts.executor.key(),
+            Vec::new(),
+        )
+    }
+
+    // Sign off a grant milestone, in order. Council grants need the council's threshold
+    // of its members; member-voted grants need more than half the listed members. Each
+    // approver signs once per milestone.
+    #[cfg(feature = "grants")]
+    pub fn approve_milestone(ctx: Context<ApproveMilestone>, milestone: u8) -> Result<()> {
+        let approver = ctx.accounts.approver.key();
+        let grant_key = ctx.accounts.grant.key();
+        let grant = &mut ctx.accounts.grant;
+        let index = milestone as usize;
+        require!(
+            index < grant.milestones.len(),
+            DaoError::InvalidGrantMilestone
+        );
+        require!(
+            grant.milestones[..index].iter().all(|m| m.approved_at > 0),
+            DaoError::MilestoneOutOfOrder
+        );
+        require!(
+            grant.milestones[index].approved_at == 0,
+            DaoError::MilestoneAlreadyApproved
+        );
+
+        let needed = match grant.approval {
+            MilestoneApproval::Council => {
+                let council = ctx
+                    .accounts
+                    .council
+                    .as_ref()
+                    .ok_or(DaoError::CouncilRequired)?;
+                require!(
+                    council.members.contains(&approver),
+                    DaoError::NotCouncilMember
+                );
+                council.threshold as u32
+            }
+            MilestoneApproval::MemberVote => {
+                require!(
+                    ctx.accounts.member_record.is_some(),
+                    DaoError::NotAGroupMember
+                );
+                ctx.accounts.group.member_count / 2 + 1
+            }
+        };
+
+        let now = Clock::get()?.unix_timestamp;
+        let entry = &mut grant.milestones[index];
+        entry.approvals += 1;
+        if entry.approvals >= needed {
+            entry.approved_at = now;
+        }
+
+        let signoff = &mut ctx.accounts.signoff;
+        signoff.grant = grant_key;
+        signoff.milestone = milestone;
+        signoff.approver = approver;
+        signoff.signed_at = now;
+        signoff.bump = ctx.bumps.signoff;
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(MilestoneApprovedEvent {
+            group_id: ctx.accounts.group.group_id.clone(),
+            event_seq,
+            grant: grant_key,
+            milestone,
+            approver,
+            approvals: entry.approvals,
+            approved: entry.approved_at > 0,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Pay the recipient an approved milestone's tranche from the group treasury
+    #[cfg(feature = "grants")]
+    pub fn claim_grant_tranche(ctx: Context<ClaimGrantTranche>, milestone: u8) -> Result<()> {
+        let grant = &mut ctx.accounts.grant;
+        let entry = grant
+            .milestones
+            .get_mut(milestone as usize)
+            .ok_or(DaoError::InvalidGrantMilestone)?;
+        require!(entry.approved_at > 0, DaoError::MilestoneNotApproved);
+        require!(!entry.claimed, DaoError::TrancheAlreadyClaimed);
+        entry.claimed = true;
+        let amount = entry.amount;
+
+        let group_key = ctx.accounts.group.key();
+        let treasury_seeds: &[&[u8]] = &[b"treasury", group_key.as_ref(), &[ctx.bumps.treasury]];
+        system_program::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.treasury.to_account_info(),
+                    to: ctx.accounts.recipient.to_account_info(),
+                },
+                &[treasury_seeds],
+            ),
+            amount,
+        )?;
+        grant.released = grant
+            .released
+            .checked_add(amount)
+            .ok_or(DaoError::TallyOverflow)?;
+        ctx.accounts.group.release_treasury(amount);
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(GrantTrancheClaimedEvent {
+            group_id: ctx.accounts.group.group_id.clone(),
+            event_seq,
+            grant: grant.key(),
+            milestone,
+            recipient: grant.recipient,
+            amount,
+            released: grant.released,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Permissionless: open the Stream an approved stream proposal describes. Its amount
+    // vests linearly from start to end, none of it before the cliff.
+    #[cfg(feature = "streams")]
+    pub fn execute_stream(ctx: Context<ExecuteStream>) -> Result<()> {
+        let proposal = &ctx.accounts.proposal;
+        require_approved(proposal)?;
+        let ProposalKind::Stream(terms) = &proposal.kind else {
+            return err!(DaoError::WrongProposalKind);
+        };
+        let now = Clock::get()?.unix_timestamp;
+
+        let stream = &mut ctx.accounts.stream;
+        stream.group = ctx.accounts.group.key();
+        stream.proposal = proposal.key();
+        stream.recipient = terms.recipient;
+        stream.amount = terms.amount;
+        stream.start = terms.start;
+        stream.cliff = terms.cliff;
+        stream.end = terms.end;
+        stream.withdrawn = 0;
+        stream.bump = ctx.bumps.stream;
+        ctx.accounts.group.commit_treasury(terms.amount)?;
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(StreamCreatedEvent {
+            group_id: proposal.group_id.clone(),
+            event_seq,
+            proposal_id: proposal.proposal_id.clone(),
+            recipient: terms.recipient,
+            amount: terms.amount,
+            start: terms.start,
+            cliff: terms.cliff,
+            end: terms.end,
+            timestamp: now,
+        });
+
+        finish_execution(
+            &mut ctx.accounts.proposal,
+            &mut ctx.accounts.group,
+            ctx.accounts.executor.key(),
+            Vec::new(),
+        )
+    }
+
+    // Pay the recipient everything vested and not yet withdrawn from the group treasury
+    #[cfg(feature = "streams")]
+    pub fn withdraw_vested(ctx: Context<WithdrawVested>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let stream = &mut ctx.accounts.stream;
+        let amount = stream.vested_at(now) - stream.withdrawn;
+        require!(amount > 0, DaoError::NothingVested);
+        stream.withdrawn += amount;
+
+        let group_key = ctx.accounts.group.key();
+        let treasury_seeds: &[&[u8]] = &[b"treasury", group_key.as_ref(), &[ctx.bumps.treasury]];
+        system_program::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.treasury.to_account_info(),
+                    to: ctx.accounts.recipient.to_account_info(),
+                },
+                &[treasury_seeds],
+            ),
+            amount,
+        )?;
+        ctx.accounts.group.release_treasury(amount);
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(VestedWithdrawnEvent {
+            group_id: ctx.accounts.group.group_id.clone(),
+            event_seq,
+            stream: stream.key(),
+            recipient: stream.recipient,
+            amount,
+            withdrawn: stream.withdrawn,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Post a bounty on the group's board, paid from the treasury once its work is
+    // approved
+    #[cfg(feature = "bounties")]
+    pub fn post_bounty(
+        ctx: Context<PostBounty>,
+        bounty_id: String,
+        description_uri: String,
+        reward: u64,
+    ) -> Result<()> {
+        let terms = BountyTerms {
+            bounty_id,
+            description_uri,
+            reward,
+        };
+        validate_bounty(&terms)?;
+        open_bounty(
+            &mut ctx.accounts.bounty,
+            ctx.bumps.bounty,
+            &mut ctx.accounts.group,
+            terms,
+            ctx.accounts.authority.key(),
+        )
+    }
+
+    // Permissionless: post the bounty an approved bounty proposal describes
+    #[cfg(feature = "bounties")]
+    pub fn execute_bounty(ctx: Context<ExecuteBounty>, bounty_id: String) -> Result<()> {
+        let proposal = &ctx.accounts.proposal;
+        require_approved(proposal)?;
+        let ProposalKind::Bounty(terms) = &proposal.kind else {
+            return err!(DaoError::WrongProposalKind);
+        };
+        require!(terms.bounty_id == bounty_id, DaoError::InvalidBounty);
+        let terms = terms.clone();
+
+        open_bounty(
+            &mut ctx.accounts.bounty,
+            ctx.bumps.bounty,
+            &mut ctx.accounts.group,
+            terms,
+            ctx.accounts.proposal.key(),
+        )?;
+        finish_execution(
+            &mut ctx.accounts.proposal,
+            &mut ctx.accounts.group,
+            ctx.accounts.executor.key(),
+            Vec::new(),
+        )
+    }
+
+    // Take an open bounty; nobody else can claim it until it is paid or cancelled
+    #[cfg(feature = "bounties")]
+    pub fn claim_bounty(ctx: Context<ClaimBounty>) -> Result<()> {
+        let bounty = &mut ctx.accounts.bounty;
+        require!(bounty.status == BountyStatus::Open, DaoError::BountyNotOpen);
+        let hunter = ctx.accounts.hunter.key();
+        bounty.status = BountyStatus::Claimed;
+        bounty.hunter = Some(hunter);
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(BountyClaimedEvent {
+            group_id: ctx.accounts.group.group_id.clone(),
+            event_seq,
+            bounty_id: bounty.bounty_id.clone(),
+            hunter,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Hand in the work for a claimed bounty. A rejected submission can be replaced
+    // until the authority approves one.
+    #[cfg(feature = "bounties")]
+    pub fn submit_bounty(ctx: Context<SubmitBounty>, submission_uri: String) -> Result<()> {
+        validate_metadata_uri(&Some(submission_uri.clone()))?;
+        let bounty = &mut ctx.accounts.bounty;
+        require!(
+            matches!(
+                bounty.status,
+                BountyStatus::Claimed | BountyStatus::Submitted
+            ),
+            DaoError::BountyNotClaimed
+        );
+        require!(
+            bounty.hunter == Some(ctx.accounts.hunter.key()),
+            DaoError::Unauthorized
+        );
+        bounty.status = BountyStatus::Submitted;
+        bounty.submission_uri = Some(submission_uri.clone());
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(BountySubmittedEvent {
+            group_id: ctx.accounts.group.group_id.clone(),
+            event_seq,
+            bounty_id: bounty.bounty_id.clone(),
+            hunter: ctx.accounts.hunter.key(),
+            submission_uri,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Accept a submission and pay the hunter the reward from the group treasury
+    #[cfg(feature = "bounties")]
+    pub fn approve_bounty(ctx: Context<ApproveBounty>) -> Result<()> {
+        let bounty = &mut ctx.accounts.bounty;
+        require!(
+            bounty.status == BountyStatus::Submitted,
+            DaoError::BountyNotSubmitted
+        );
+        require!(
+            bounty.hunter == Some(ctx.accounts.hunter.key()),
+            DaoError::Unauthorized
+        );
+        bounty.status = BountyStatus::Paid;
+
+        let group_key = ctx.accounts.group.key();
+        let treasury_seeds: &[&[u8]] = &[b"treasury", group_key.as_ref(), &[ctx.bumps.treasury]];
+        system_program::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.treasury.to_account_info(),
+                    to: ctx.accounts.hunter.to_account_info(),
+                },
+                &[treasury_seeds],
+            ),
+            bounty.reward,
+        )?;
+        ctx.accounts.group.release_treasury(bounty.reward);
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(BountyPaidEvent {
+            group_id: ctx.accounts.group.group_id.clone(),
+            event_seq,
+            bounty_id: bounty.bounty_id.clone(),
+            hunter: ctx.accounts.hunter.key(),
+            reward: bounty.reward,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Take a bounty that hasn't been paid off the board
+    #[cfg(feature = "bounties")]
+    pub fn cancel_bounty(ctx: Context<CancelBounty>) -> Result<()> {
+        let bounty = &mut ctx.accounts.bounty;
+        require!(
+            !matches!(bounty.status, BountyStatus::Paid | BountyStatus::Cancelled),
+            DaoError::BountyNotOpen
+        );
+        bounty.status = BountyStatus::Cancelled;
+        ctx.accounts.group.release_treasury(bounty.reward);
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(BountyCancelledEvent {
+            group_id: ctx.accounts.group.group_id.clone(),
+            event_seq,
+            bounty_id: bounty.bounty_id.clone(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // On split-weight proposals `allocations` spreads the voter's weight over the
+    // choices; `choice_index` then names the choice given the most.
+    pub fn vote_on_proposal<'info>(
+        ctx: Context<'_, '_, 'info, 'info, VoteOnProposal<'info>>,
+        choice_index: u8,
+        disclosure: Option<String>,
+        allocations: Option<Vec<u64>>,
+    ) -> Result<()> {
+        validate_disclosure(&disclosure)?;
+        let proposal = &mut ctx.accounts.proposal;
+        let clock = Clock::get()?;
+        let current_time = clock.unix_timestamp;
+        let voter_key = ctx.accounts.voter.key();
+        require_can_vote(proposal, &voter_key, choice_index, &clock)?;
+        require_listed_member(&ctx.accounts.group, ctx.accounts.member_record.as_ref())?;
+        require_not_banned(&ctx.accounts.ban, current_time)?;
+        require_dues_current(
+            &ctx.accounts.group,
+            ctx.accounts.dues_record.as_ref(),
+            current_time,
+        )?;
+        lock_voted_shares(
+            &ctx.accounts.group,
+            ctx.accounts.member_shares.as_mut(),
+            proposal.voting_end,
+        )?;
+
+        let (own_weight, (linked_weight, linked_wallets)) =
+            if let Some(deposit_voting) = proposal.deposit_voting {
+                // Deposits are counted per wallet; linked wallets deposit on their own
+                require!(
+                    ctx.accounts.user_account.is_none(),
+                    DaoError::InvalidLinkedWallet
+                );
+                let weight = held_deposit_weight(
+                    &deposit_voting,
+                    &voter_key,
+                    ctx.accounts.voter_deposit.as_deref(),
+                )?;
+                (weight, (0, Vec::new()))
+            } else if proposal.price_weighted {
+                let weighting = ctx
+                    .accounts
+                    .price_weighting
+                    .as_deref()
+                    .ok_or(DaoError::PriceWeightingRequired)?;
+                // Linked wallets aren't priced; the remaining accounts carry the holdings
+                require!(
+                    ctx.accounts.user_account.is_none(),
+                    DaoError::InvalidLinkedWallet
+                );
+                let weight = priced_vote_weight(
+                    weighting,
+                    &ctx.accounts.voter,
+                    ctx.remaining_accounts,
+                    current_time,
+                )?;
+                (weight, (0, Vec::new()))
+            } else if proposal.member_weighted {
+                (
+                    member_vote_weight(ctx.accounts.member_record.as_ref())?,
+                    (0, Vec::new()),
+                )
+            } else {
+                let own_weight = compute_vote_weight(
+                    proposal.token_mint,
+                    &ctx.accounts.voter,
+                    &ctx.accounts.voter_token_account,
+                )?;
+                let linked = linked_wallet_weight(
+                    proposal.token_mint,
+                    &voter_key,
+                    ctx.accounts.user_account.as_deref(),
+                    ctx.remaining_accounts,
+                )?;
+                (own_weight, linked)
+            };
+        let vote_weight = own_weight
+            .checked_add(linked_weight)
+            .ok_or(DaoError::TallyOverflow)?;
+        let vote_weight = proposal.decayed_weight(vote_weight, &clock);
+        require!(vote_weight > 0, DaoError::NoVotingPower);
+        require!(
+            !proposal
+                .voters
+                .iter()
+                .any(|v| linked_wallets.contains(&v.voter)),
+            DaoError::AlreadyVoted
+        );
+
+        // Record the vote
+        let vote_weight = match &allocations {
+            Some(allocations) if proposal.split_voting => {
+                let allocated = split_allocation_total(
+                    allocations,
+                    proposal.choices.len(),
+                    choice_index,
+                    vote_weight,
+                )?;
+                for (tally, &amount) in proposal.choice_votes.iter_mut().zip(allocations) {
+                    add_to_tally(tally, amount)?;
+                }
+                allocated
+            }
+            None if !proposal.split_voting => {
+                add_to_tally(
+                    &mut proposal.choice_votes[choice_index as usize],
+                    vote_weight,
+                )?;
+                vote_weight
+            }
+            _ => return err!(DaoError::InvalidSplitAllocation),
+        };
+        proposal.voters.push(VoterInfo {
+            voter: voter_key,
+            choice: choice_index,
+            vote_weight,
+            timestamp: current_time,
+            disclosure: disclosure.clone(),
+        });
+
+        // Linked wallets whose weight was counted get zero-weight entries so they can't
+        // vote again on their own; the realloc constraint only budgeted the voter's entry
+        if !linked_wallets.is_empty() {
+            for wallet in &linked_wallets {
+                proposal.voters.push(VoterInfo {
+                    voter: *wallet,
+                    choice: choice_index,
+                    vote_weight: 0,
+                    timestamp: current_time,
+                    disclosure: None,
+                });
+            }
+            grow_account(
+                &proposal.to_account_info(),
+                Proposal::space(proposal.voters.len()) + proposal.disclosure_space(),
+                &ctx.accounts.voter,
+                &ctx.accounts.system_program,
+            )?;
+        }
+
+        ctx.accounts.participation.record_vote(
+            ctx.accounts.group.key(),
+            voter_key,
+            ctx.bumps.participation,
+            proposal.index,
+            current_time,
+        )?;
+        ctx.accounts.turnout.record_vote(
+            proposal.key(),
+            ctx.bumps.turnout,
+            &proposal.choice_votes,
+            proposal.voting_start,
+            current_time,
+        )?;
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(VoteCastEvent {
+            group_id: proposal.group_id.clone(),
+            event_seq,
+            proposal_id: proposal.proposal_id.clone(),
+            voter: voter_key,
+            choice: choice_index,
+            vote_weight,
+            timestamp: current_time,
+            disclosure,
+            allocations,
+        });
+
+        Ok(())
+    }
+
+    // Gasless voting: a relayer submits a vote the voter signed off-chain. The transaction
+    // carries an ed25519 precompile instruction over the relayed vote payload (proposal,
+    // choice, expiry); the vote counts for the wallet that signed it, with its own
+    // weight, while the relayer pays the fees and any rent.
+    pub fn relay_vote(ctx: Context<RelayVote>, choice_index: u8, expiry: i64) -> Result<()> {
+        let clock = Clock::get()?;
+        let current_time = clock.unix_timestamp;
+        require!(current_time <= expiry, DaoError::VotePayloadExpired);
+
+        let proposal = &mut ctx.accounts.proposal;
+        let voter_key = ctx.accounts.voter.key();
+        let signer = verified_ed25519_signer(
+            &ctx.accounts.instructions_sysvar,
+            &relayed_vote_message(&proposal.key(), choice_index, expiry),
+            DaoError::InvalidVoteSignature,
+        )?;
+        require_keys_eq!(signer, voter_key, DaoError::InvalidVoteSignature);
+        require_can_vote(proposal, &voter_key, choice_index, &clock)?;
+        require_listed_member(&ctx.accounts.group, ctx.accounts.member_record.as_ref())?;
+        require_not_banned(&ctx.accounts.ban, current_time)?;
+        require_dues_current(
+            &ctx.accounts.group,
+            ctx.accounts.dues_record.as_ref(),
+            current_time,
+        )?;
+        lock_voted_shares(
+            &ctx.accounts.group,
+            ctx.accounts.member_shares.as_mut(),
+            proposal.voting_end,
+        )?;
+        require!(
+            !proposal.price_weighted,
+            DaoError::PriceWeightingUnsupported
+        );
+        require!(!proposal.split_voting, DaoError::SplitVotingUnsupported);
+        require!(
+            proposal.deposit_voting.is_none(),
+            DaoError::DepositVotingUnsupported
+        );
+
+        let vote_weight = if proposal.member_weighted {
+            member_vote_weight(ctx.accounts.member_record.as_ref())?
+        } else {
+            compute_vote_weight(
+                proposal.token_mint,
+                &ctx.accounts.voter,
+                &ctx.accounts.voter_token_account,
+            )?
+        };
+        let vote_weight = proposal.decayed_weight(vote_weight, &clock);
+        require!(vote_weight > 0, DaoError::NoVotingPower);
+
+        add_to_tally(
+            &mut proposal.choice_votes[choice_index as usize],
+            vote_weight,
+        )?;
+        proposal.voters.push(VoterInfo {
+            voter: voter_key,
+            choice: choice_index,
+            vote_weight,
+            timestamp: current_time,
+            disclosure: None,
+        });
+
+        ctx.accounts.participation.record_vote(
+            ctx.accounts.group.key(),
+            voter_key,
+            ctx.bumps.participation,
+            proposal.index,
+            current_time,
+        )?;
+        ctx.accounts.turnout.record_vote(
+            proposal.key(),
+            ctx.bumps.turnout,
+            &proposal.choice_votes,
+            proposal.voting_start,
+            current_time,
+        )?;
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(VoteCastEvent {
+            group_id: proposal.group_id.clone(),
+            event_seq,
+            proposal_id: proposal.proposal_id.clone(),
+            voter: voter_key,
+            choice: choice_index,
+            vote_weight,
+            timestamp: current_time,
+            disclosure: None,
+            allocations: None,
+        });
+
+        Ok(())
+    }
+
+    // Lend the signer's voting power to `delegate`, in `group` only or in every group
+    // (the default key), optionally for one proposal kind (ProposalKind::tag) and until
+    // `expires_at`. Delegating again in the same scope replaces the delegation.
+    #[cfg(feature = "delegation")]
+    pub fn delegate_votes(
+        ctx: Context<DelegateVotes>,
+        group: Pubkey,
+        delegate: Pubkey,
+        kind: Option<u8>,
+        expires_at: Option<i64>,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let delegator = ctx.accounts.delegator.key();
+        require_keys_neq!(delegate, delegator, DaoError::SelfDelegation);
+        require!(
+            kind.is_none_or(|kind| kind < PROPOSAL_KIND_COUNT),
+            DaoError::InvalidDelegationScope
+        );
+        require!(
+            expires_at.is_none_or(|expires_at| expires_at > now),
+            DaoError::InvalidDelegationExpiry
+        );
+
+        let delegation = &mut ctx.accounts.delegation;
+        delegation.delegator = delegator;
+        delegation.delegate = delegate;
+        delegation.group = group;
+        delegation.kind = kind;
+        delegation.expires_at = expires_at;
+        delegation.created_at = now;
+        delegation.bump = ctx.bumps.delegation;
+
+        emit!(DelegationSetEvent {
+            delegator,
+            delegate,
+            group,
+            kind,
+            expires_at,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    #[cfg(feature = "delegation")]
+    pub fn undelegate_votes(ctx: Context<UndelegateVotes>) -> Result<()> {
+        let delegation = &ctx.accounts.delegation;
+        emit!(DelegationRevokedEvent {
+            delegator: delegation.delegator,
+            delegate: delegation.delegate,
+            group: delegation.group,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    // Permissionless: close a delegation past its expiry, returning the rent to the
+    // delegator, so indexers see the expiry as an event
+    #[cfg(feature = "delegation")]
+    pub fn expire_delegation(ctx: Context<ExpireDelegation>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let delegation = &ctx.accounts.delegation;
+        require!(delegation.expired(now), DaoError::DelegationNotExpired);
+        emit!(DelegationExpiredEvent {
+            delegator: delegation.delegator,
+            delegate: delegation.delegate,
+            group: delegation.group,
+            expired_at: delegation.expires_at.unwrap_or_default(),
+            timestamp: now,
+        });
+        Ok(())
+    }
+
+    // Cast the delegator's vote with the delegator's weight. The ballot is recorded under
+    // the delegator, so they can't vote again themselves; a delegator who already voted
+    // keeps their own vote. A group's delegation overrides one covering every group.
+    #[cfg(feature = "delegation")]
+    pub fn vote_as_delegate(ctx: Context<VoteAsDelegate>, choice_index: u8) -> Result<()> {
+        let clock = Clock::get()?;
+        let current_time = clock.unix_timestamp;
+        let proposal = &mut ctx.accounts.proposal;
+        let group_key = ctx.accounts.group.key();
+        let delegation = &ctx.accounts.delegation;
+        require!(
+            !delegation.expired(current_time),
+            DaoError::DelegationExpired
+        );
+        require!(
+            delegation.covers(&group_key, &proposal.kind),
+            DaoError::DelegationOutOfScope
+        );
+        if delegation.group != group_key {
+            require!(
+                ctx.accounts.group_delegation.data_is_empty(),
+                DaoError::DelegationOverridden
+            );
+        }
+
+        let voter_key = ctx.accounts.delegator.key();
+        require_can_vote(proposal, &voter_key, choice_index, &clock)?;
+        require_listed_member(&ctx.accounts.group, ctx.accounts.member_record.as_ref())?;
+        require_not_banned(&ctx.accounts.ban, current_time)?;
+        require_dues_current(
+            &ctx.accounts.group,
+            ctx.accounts.dues_record.as_ref(),
+            current_time,
+        )?;
+        lock_voted_shares(
+            &ctx.accounts.group,
+            ctx.accounts.member_shares.as_mut(),
+            proposal.voting_end,
+        )?;
+        require!(
+            !proposal.price_weighted,
+            DaoError::PriceWeightingUnsupported
+        );
+        require!(!proposal.split_voting, DaoError::SplitVotingUnsupported);
+        require!(
+            proposal.deposit_voting.is_none(),
+            DaoError::DepositVotingUnsupported
+        );
+
+        let vote_weight = if proposal.member_weighted {
+            member_vote_weight(ctx.accounts.member_record.as_ref())?
+        } else {
+            compute_vote_weight(
+                proposal.token_mint,
+                &ctx.accounts.delegator,
+                &ctx.accounts.voter_token_account,
+            )?
+        };
+        let vote_weight = proposal.decayed_weight(vote_weight, &clock);
+        require!(vote_weight > 0, DaoError::NoVotingPower);
+
+        add_to_tally(
+            &mut proposal.choice_votes[choice_index as usize],
+            vote_weight,
+        )?;
+        proposal.voters.push(VoterInfo {
+            voter: voter_key,
+            choice: choice_index,
+            vote_weight,
+            timestamp: current_time,
+            disclosure: None,
+        });
+
+        ctx.accounts.participation.record_vote(
+            group_key,
+            voter_key,
+            ctx.bumps.participation,
+            proposal.index,
+            current_time,
+        )?;
+        ctx.accounts.turnout.record_vote(
+            proposal.key(),
+            ctx.bumps.turnout,
+            &proposal.choice_votes,
+            proposal.voting_start,
+            current_time,
+        )?;
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(VoteCastEvent {
+            group_id: proposal.group_id.clone(),
+            event_seq,
+            proposal_id: proposal.proposal_id.clone(),
+            voter: voter_key,
+            choice: choice_index,
+            vote_weight,
+            timestamp: current_time,
+            disclosure: None,
+            allocations: None,
+        });
+        emit!(DelegatedVoteEvent {
+            group_id: proposal.group_id.clone(),
+            event_seq,
+            proposal_id: proposal.proposal_id.clone(),
+            delegator: voter_key,
+            delegate: ctx.accounts.delegate.key(),
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+
+    // Weigh votes on this proposal by the value of what the voter holds rather than a raw
+    // balance: for each accepted mint, balance × oracle price, summed across mints (e.g.
+    // USD-normalized when every feed quotes USD). The mint accounts follow as remaining
+    // accounts in `feeds` order, for their decimals. Can be replaced until voting starts.
+    #[cfg(feature = "oracle-weights")]
+    pub fn set_price_weighting<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SetPriceWeighting<'info>>,
+        feeds: Vec<PriceFeed>,
+        max_staleness_secs: u32,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp;
+        let proposal = &mut ctx.accounts.proposal;
+        require!(
+            proposal.status == ProposalStatus::Draft
+                || (proposal.status == ProposalStatus::Active && proposal.before_voting(&clock)),
+            DaoError::InvalidProposalStatus
+        );
+        require!(proposal.voters.is_empty(), DaoError::OnchainVotesCast);
+        require!(
+            proposal.offchain_aggregator.is_none()
+                && !proposal.member_weighted
+                && proposal.deposit_voting.is_none(),
+            DaoError::PriceWeightingUnsupported
+        );
+        require!(
+            !feeds.is_empty()
+                && feeds.len() <= MAX_PRICED_MINTS
+                && feeds.len() == ctx.remaining_accounts.len()
+                && max_staleness_secs > 0,
+            DaoError::InvalidPriceFeeds
+        );
+
+        let mut priced: Vec<PricedMint> = Vec::with_capacity(feeds.len());
+        for (feed, mint_account) in feeds.iter().zip(ctx.remaining_accounts) {
+            require_keys_eq!(mint_account.key(), feed.mint, DaoError::InvalidPriceFeeds);
+            // One feed per mint, and each price account identifies its mint when voting
+            require!(
+                !priced
+                    .iter()
+                    .any(|p| p.mint == feed.mint || p.oracle == feed.oracle),
+                DaoError::InvalidPriceFeeds
+            );
+            priced.push(PricedMint {
+                mint: feed.mint,
+                oracle: feed.oracle,
+                decimals: mint_decimals(mint_account)?,
+            });
+        }
+
+        let weighting = &mut ctx.accounts.price_weighting;
+        weighting.proposal = proposal.key();
+        weighting.feeds = priced;
+        weighting.max_staleness_secs = max_staleness_secs;
+        weighting.bump = ctx.bumps.price_weighting;
+        proposal.price_weighted = true;
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(PriceWeightingSetEvent {
+            group_id: proposal.group_id.clone(),
+            event_seq,
+            proposal_id: proposal.proposal_id.clone(),
+            mints: feeds.iter().map(|f| f.mint).collect(),
+            max_staleness_secs,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Count votes on this proposal with each member's weight (see set_member_weight)
+    // instead of one per member, e.g. for committee votes where founders count 3x.
+    // Only for one-person-one-vote proposals, and only until voting starts.
+    pub fn set_member_weighted_voting(ctx: Context<ManageProposal>, enabled: bool) -> Result<()> {
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp;
+        let proposal = &mut ctx.accounts.proposal;
+        require!(
+            proposal.status == ProposalStatus::Draft
+                || (proposal.status == ProposalStatus::Active && proposal.before_voting(&clock)),
+            DaoError::InvalidProposalStatus
+        );
+        require!(proposal.voters.is_empty(), DaoError::OnchainVotesCast);
+        require!(
+            proposal.token_mint.is_none()
+                && !proposal.price_weighted
+                && proposal.offchain_aggregator.is_none(),
+            DaoError::MemberWeightingUnsupported
+        );
+        proposal.member_weighted = enabled;
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(MemberWeightedVotingSetEvent {
+            group_id: proposal.group_id.clone(),
+            event_seq,
+            proposal_id: proposal.proposal_id.clone(),
+            enabled,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Budget-box voting: each voter spreads their weight over the choices in any
+    // proportion (e.g. 60/30/10) instead of giving all of it to one. Only for votes
+    // cast with vote_on_proposal, and only until voting starts.
+    pub fn set_split_voting(ctx: Context<ManageProposal>, enabled: bool) -> Result<()> {
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp;
+        let proposal = &mut ctx.accounts.proposal;
+        require!(
+            proposal.status == ProposalStatus::Draft
+                || (proposal.status == ProposalStatus::Active && proposal.before_voting(&clock)),
+            DaoError::InvalidProposalStatus
+        );
+        require!(proposal.voters.is_empty(), DaoError::OnchainVotesCast);
+        require!(
+            proposal.offchain_aggregator.is_none(),
+            DaoError::SplitVotingUnsupported
+        );
+        proposal.split_voting = enabled;
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(SplitVotingSetEvent {
+            group_id: proposal.group_id.clone(),
+            event_seq,
+            proposal_id: proposal.proposal_id.clone(),
+            enabled,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Attach a short description and link to each choice, e.g. candidate bios for an
+    // election. Only until voting starts, so voters all see the same ballot.
+    #[cfg(feature = "choice-details")]
+    pub fn set_choice_details(
+        ctx: Context<SetChoiceDetails>,
+        details: Vec<ChoiceDetail>,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let proposal = &ctx.accounts.proposal;
+        require!(
+            proposal.status == ProposalStatus::Draft
+                || (proposal.status == ProposalStatus::Active && proposal.before_voting(&clock)),
+            DaoError::InvalidProposalStatus
+        );
+        require!(
+            details.len() == proposal.choices.len(),
+            DaoError::InvalidChoiceDetails
+        );
+        for detail in &details {
+            require!(
+                detail
+                    .description
+                    .as_ref()
+                    .is_none_or(|d| d.len() <= MAX_CHOICE_DESCRIPTION_LEN),
+                DaoError::InvalidChoiceDetails
+            );
+            validate_metadata_uri(&detail.uri)?;
+        }
+
+        let choice_details = &mut ctx.accounts.choice_details;
+        choice_details.proposal = proposal.key();
+        choice_details.details = details;
+        choice_details.bump = ctx.bumps.choice_details;
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(ChoiceDetailsSetEvent {
+            group_id: proposal.group_id.clone(),
+            event_seq,
+            proposal_id: proposal.proposal_id.clone(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Return the details' rent to the group authority, any time but while voting is open
+    #[cfg(feature = "choice-details")]
+    pub fn close_choice_details(ctx: Context<CloseChoiceDetails>) -> Result<()> {
+        let proposal = &ctx.accounts.proposal;
+        require!(
+            proposal.status != ProposalStatus::Active || !proposal.voting_open(&Clock::get()?),
+            DaoError::InvalidProposalStatus
+        );
+        Ok(())
+    }
+
+    // Keep this proposal's voting window in slots rather than unix time, so it can't
+    // drift with the cluster clock and tests can pin it exactly. Only until voting
+    // starts; voting_start and voting_end are kept as estimates for display.
+    pub fn set_slot_window(
+        ctx: Context<ManageProposal>,
+        start_slot: u64,
+        end_slot: u64,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let proposal = &mut ctx.accounts.proposal;
+        require!(
+            proposal.status == ProposalStatus::Draft
+                || (proposal.status == ProposalStatus::Active && proposal.before_voting(&clock)),
+            DaoError::InvalidProposalStatus
+        );
+        require!(start_slot < end_slot, DaoError::InvalidVotingPeriod);
+        let duration_secs = (end_slot - start_slot).saturating_mul(ESTIMATED_MS_PER_SLOT) / 1000;
+        require!(
+            ctx.accounts.group.governance.allows_duration(
+                i64::try_from(duration_secs).unwrap_or(i64::MAX),
+                proposal.emergency
+            ),
+            DaoError::VotingDurationOutOfBounds
+        );
+        require!(
+            proposal.status == ProposalStatus::Draft || start_slot > clock.slot,
+            DaoError::VotingStartInPast
+        );
+        apply_slot_window(proposal, start_slot, end_slot, &clock);
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(SlotWindowSetEvent {
+            group_id: proposal.group_id.clone(),
+            event_seq,
+            proposal_id: proposal.proposal_id.clone(),
+            start_slot,
+            end_slot,
+            voting_start: proposal.voting_start,
+            voting_end: proposal.voting_end,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Make later votes count for less: a vote's weight shrinks linearly over the window,
+    // losing `decay_bps` of it at the very end, so a whale can't swing the result at
+    // the last second. Only for token-weighted proposals, and only until voting starts.
+    pub fn set_weight_decay(ctx: Context<ManageProposal>, decay_bps: u16) -> Result<()> {
+        let clock = Clock::get()?;
+        let proposal = &mut ctx.accounts.proposal;
+        require!(
+            proposal.status == ProposalStatus::Draft
+                || (proposal.status == ProposalStatus::Active && proposal.before_voting(&clock)),
+            DaoError::InvalidProposalStatus
+        );
+        require!(proposal.voters.is_empty(), DaoError::OnchainVotesCast);
+        require!(decay_bps < 10_000, DaoError::InvalidWeightDecay);
+        require!(
+            proposal.token_mint.is_some() && proposal.offchain_aggregator.is_none(),
+            DaoError::WeightDecayUnsupported
+        );
+        proposal.weight_decay_bps = decay_bps;
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(WeightDecaySetEvent {
+            group_id: proposal.group_id.clone(),
+            event_seq,
+            proposal_id: proposal.proposal_id.clone(),
+            decay_bps,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Let the group count deposits of `mint` held for at least `min_holding_secs` before
+    // a proposal was created (see set_deposit_voting), so borrowed tokens can't swing a
+    // vote. SPL registrars keep deposits in `vault`, a token account the registrar owns;
+    // SOL deposits stay in each VoterDeposit. Calling again changes the holding period.
+    #[cfg(feature = "deposit-voting")]
+    pub fn configure_deposit_registrar(
+        ctx: Context<ConfigureDepositRegistrar>,
+        mint: Pubkey,
+        min_holding_secs: i64,
+    ) -> Result<()> {
+        require!(min_holding_secs >= 0, DaoError::InvalidHoldingPeriod);
+        let registrar = &mut ctx.accounts.registrar;
+        if registrar.group == Pubkey::default() {
+            registrar.group = ctx.accounts.group.key();
+            registrar.mint = mint;
+            registrar.vault = if mint == native_mint() {
+                registrar.key()
+            } else {
+                let vault = ctx
+                    .accounts
+                    .vault
+                    .as_ref()
+                    .ok_or(DaoError::TokenAccountRequired)?;
+                let (owner, _) = spl_token_balance(mint, vault)?;
+                require_keys_eq!(owner, registrar.key(), DaoError::InvalidTokenAccount);
+                vault.key()
+            };
+            registrar.bump = ctx.bumps.registrar;
+        }
+        registrar.min_holding_secs = min_holding_secs;
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(DepositRegistrarConfiguredEvent {
+            group_id: ctx.accounts.group.group_id.clone(),
+            event_seq,
+            registrar: registrar.key(),
+            mint,
+            min_holding_secs,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Lock tokens in the registrar for voting. A top-up restarts the deposit's holding
+    // period, since the added tokens are new.
+    #[cfg(feature = "deposit-voting")]
+    pub fn deposit_voting_tokens(ctx: Context<DepositVotingTokens>, amount: u64) -> Result<()> {
+        require!(amount > 0, DaoError::InvalidDepositAmount);
+        let now = Clock::get()?.unix_timestamp;
+        let registrar = &ctx.accounts.registrar;
+        if registrar.mint == native_mint() {
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.voter.to_account_info(),
+                        to: ctx.accounts.voter_deposit.to_account_info(),
+                    },
+                ),
+                amount,
+            )?;
+        } else {
+            let vault = ctx
+                .accounts
+                .vault
+                .as_ref()
+                .ok_or(DaoError::TokenAccountRequired)?;
+            require_keys_eq!(vault.key(), registrar.vault, DaoError::InvalidTokenAccount);
+            transfer_spl(
+                ctx.accounts
+                    .token_program
+                    .as_ref()
+                    .ok_or(DaoError::TokenAccountRequired)?,
+                ctx.accounts
+                    .voter_token_account
+                    .as_ref()
+                    .ok_or(DaoError::TokenAccountRequired)?,
+                vault,
+                &ctx.accounts.voter.to_account_info(),
+                &[],
+                amount,
+            )?;
+        }
+
+        let deposit = &mut ctx.accounts.voter_deposit;
+        deposit.registrar = registrar.key();
+        deposit.voter = ctx.accounts.voter.key();
+        deposit.amount = deposit
+            .amount
+            .checked_add(amount)
+            .ok_or(DaoError::TallyOverflow)?;
+        deposit.deposited_at = now;
+        deposit.bump = ctx.bumps.voter_deposit;
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(VotingTokensDepositedEvent {
+            group_id: ctx.accounts.group.group_id.clone(),
+            event_seq,
+            registrar: registrar.key(),
+            voter: deposit.voter,
+            amount,
+            total: deposit.amount,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Take deposited tokens back. Votes already cast keep their weight; the tokens were
+    // held for the whole period they were counted for.
+    #[cfg(feature = "deposit-voting")]
+    pub fn withdraw_voting_tokens(ctx: Context<WithdrawVotingTokens>, amount: u64) -> Result<()> {
+        require!(amount > 0, DaoError::InvalidDepositAmount);
+        let deposit = &mut ctx.accounts.voter_deposit;
+        require!(amount <= deposit.amount, DaoError::InsufficientDeposit);
+        let registrar = &ctx.accounts.registrar;
+        if registrar.mint == native_mint() {
+            // The deposit PDA is program-owned, so lamports can be moved out directly
+            **deposit.to_account_info().try_borrow_mut_lamports()? -= amount;
+            **ctx
+                .accounts
+                .voter
+                .to_account_info()
+                .try_borrow_mut_lamports()? += amount;
+        } else {
+            let vault = ctx
+                .accounts
+                .vault
+                .as_ref()
+                .ok_or(DaoError::TokenAccountRequired)?;
+            require_keys_eq!(vault.key(), registrar.vault, DaoError::InvalidTokenAccount);
+            let registrar_seeds: &[&[u8]] = &[
+                b"registrar",
+                registrar.group.as_ref(),
+                registrar.mint.as_ref(),
+                &[registrar.bump],
+            ];
+            transfer_spl(
+                ctx.accounts
+                    .token_program
+                    .as_ref()
+                    .ok_or(DaoError::TokenAccountRequired)?,
+                vault,
+                ctx.accounts
+                    .voter_token_account
+                    .as_ref()
+                    .ok_or(DaoError::TokenAccountRequired)?,
+                &registrar.to_account_info(),
+                &[registrar_seeds],
+                amount,
+            )?;
+        }
+        deposit.amount -= amount;
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(VotingTokensWithdrawnEvent {
+            group_id: ctx.accounts.group.group_id.clone(),
+            event_seq,
+            registrar: registrar.key(),
+            voter: deposit.voter,
+            amount,
+            total: deposit.amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Weigh this proposal's votes by registrar deposits made at least the registrar's
+    // holding period before the proposal was created, instead of live balances. The
+    // proposal's token must be the registrar's mint. Only until voting starts.
+    #[cfg(feature = "deposit-voting")]
+    pub fn set_deposit_voting(ctx: Context<SetDepositVoting>, enabled: bool) -> Result<()> {
+        let clock = Clock::get()?;
+        let registrar = &ctx.accounts.registrar;
+        let proposal = &mut ctx.accounts.proposal;
+        require!(
+            proposal.status == ProposalStatus::Draft
+                || (proposal.status == ProposalStatus::Active && proposal.before_voting(&clock)),
+            DaoError::InvalidProposalStatus
+        );
+        require!(proposal.voters.is_empty(), DaoError::OnchainVotesCast);
+        require!(
+            proposal.token_mint == Some(registrar.mint)
+                && !proposal.price_weighted
+                && proposal.offchain_aggregator.is_none(),
+            DaoError::DepositVotingUnsupported
+        );
+        proposal.deposit_voting = enabled.then(|| DepositVoting {
+            registrar: registrar.key(),
+            cutoff: proposal
+                .created_at
+                .saturating_sub(registrar.min_holding_secs),
+        });
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(DepositVotingSetEvent {
+            group_id: proposal.group_id.clone(),
+            event_seq,
+            proposal_id: proposal.proposal_id.clone(),
+            registrar: registrar.key(),
+            deposit_voting: proposal.deposit_voting,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Add a council chamber to this proposal: besides the community's weighted vote,
+    // the group council votes one per member with council_vote, and the proposal only
+    // succeeds if the community's winning choice also gets `threshold` council votes.
+    // The council is snapshotted here. Only until voting starts.
+    #[cfg(feature = "bicameral")]
+    pub fn set_bicameral(ctx: Context<SetBicameral>, enabled: bool) -> Result<()> {
+        let clock = Clock::get()?;
+        let proposal = &mut ctx.accounts.proposal;
+        require!(
+            proposal.status == ProposalStatus::Draft
+                || (proposal.status == ProposalStatus::Active && proposal.before_voting(&clock)),
+            DaoError::InvalidProposalStatus
+        );
+        proposal.council_chamber = if enabled {
+            let council = ctx
+                .accounts
+                .council
+                .as_ref()
+                .ok_or(DaoError::CouncilRequired)?;
+            Some(CouncilChamber {
+                members: council.members.clone(),
+                threshold: council.threshold,
+                choice_votes: vec![0; proposal.choices.len()],
+                voted: 0,
+            })
+        } else {
+            None
+        };
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(BicameralSetEvent {
+            group_id: proposal.group_id.clone(),
+            event_seq,
+            proposal_id: proposal.proposal_id.clone(),
+            enabled,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // A council member's ballot in a bicameral proposal's council chamber, one each
+    #[cfg(feature = "bicameral")]
+    pub fn council_vote(ctx: Context<CouncilVote>, choice_index: u8) -> Result<()> {
+        let clock = Clock::get()?;
+        let member = ctx.accounts.council_member.key();
+        let proposal = &mut ctx.accounts.proposal;
+        require!(
+            proposal.status == ProposalStatus::Active && proposal.voting_open(&clock),
+            DaoError::VotingNotActive
+        );
+        require!(
+            (choice_index as usize) < proposal.choices.len(),
+            DaoError::InvalidChoice
+        );
+        let chamber = proposal
+            .council_chamber
+            .as_mut()
+            .ok_or(DaoError::NotBicameral)?;
+        let seat = chamber
+            .members
+            .iter()
+            .position(|m| *m == member)
+            .ok_or(DaoError::NotCouncilMember)?;
+        require!(chamber.voted & (1 << seat) == 0, DaoError::AlreadyVoted);
+        chamber.voted |= 1 << seat;
+        chamber.choice_votes[choice_index as usize] += 1;
+        let choice_votes = chamber.choice_votes.clone();
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(CouncilVoteCastEvent {
+            group_id: proposal.group_id.clone(),
+            event_seq,
+            proposal_id: proposal.proposal_id.clone(),
+            council_member: member,
+            choice_index,
+            choice_votes,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Collect this proposal's ballots off-chain. Wallets sign ballots (see
+    // offchain_ballot_message) and hand them to `aggregator`, who submits a tally tree
+    // root and totals after voting ends; on-chain votes are refused from then on. The
+    // aggregator is trusted with ballot weights, which aren't checked on-chain, and with
+    // publishing its tree, without which nobody can dispute the tally. The authority can
+    // replace one that stalls, or whose tally was disputed, until the tally is settled;
+    // an undisputed tally can't be swapped out.
+    #[cfg(feature = "offchain-votes")]
+    pub fn enable_offchain_voting(
+        ctx: Context<EnableOffchainVoting>,
+        aggregator: Pubkey,
+    ) -> Result<()> {
+        let tally_info = ctx.accounts.offchain_tally.to_account_info();
+        if !tally_info.data_is_empty() {
+            let tally = OffchainTally::try_deserialize(&mut &tally_info.try_borrow_data()?[..])?;
+            require!(
+                tally.disputed_by.is_some(),
+                DaoError::OffchainTallyAlreadySubmitted
+            );
+        }
+        let proposal = &mut ctx.accounts.proposal;
+        require!(
+            matches!(
+                proposal.status,
+                ProposalStatus::Draft | ProposalStatus::Active
+            ) && proposal.offchain_voter_count.is_none(),
+            DaoError::InvalidProposalStatus
+        );
+        require!(proposal.voters.is_empty(), DaoError::OnchainVotesCast);
+        require!(
+            !proposal.price_weighted,
+            DaoError::PriceWeightingUnsupported
+        );
+        require!(
+            !proposal.member_weighted,
+            DaoError::MemberWeightingUnsupported
+        );
+        require!(!proposal.split_voting, DaoError::SplitVotingUnsupported);
+        require!(
+            proposal.weight_decay_bps == 0,
+            DaoError::WeightDecayUnsupported
+        );
+        require!(
+            proposal.deposit_voting.is_none(),
+            DaoError::DepositVotingUnsupported
+        );
+        // Off-chain ballots aren't checked against the member list
+        require!(
+            !matches!(proposal.kind, ProposalKind::TreasuryRecovery(_)),
+            DaoError::RecoveryNeedsMembersOnlyVoting
+        );
+        proposal.offchain_aggregator = Some(aggregator);
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(OffchainVotingEnabledEvent {
+            group_id: proposal.group_id.clone(),
+            event_seq,
+            proposal_id: proposal.proposal_id.clone(),
+            aggregator,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // The aggregator's tally of the off-chain ballots: the root of a Merkle sum tree over
+    // one leaf per counted ballot (see offchain_ballot_leaf and offchain_tally_node) and
+    // the per-choice totals it adds up to. It opens a challenge window of
+    // OFFCHAIN_CHALLENGE_SECS; a disputed tally may be resubmitted.
+    #[cfg(feature = "offchain-votes")]
+    pub fn submit_offchain_tally(
+        ctx: Context<SubmitOffchainTally>,
+        merkle_root: [u8; 32],
+        choice_votes: Vec<u64>,
+        voter_count: u32,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp;
+        let proposal = &ctx.accounts.proposal;
+        require!(
+            proposal.status == ProposalStatus::Active && proposal.offchain_voter_count.is_none(),
+            DaoError::InvalidProposalStatus
+        );
+        require!(proposal.after_voting(&clock), DaoError::VotingNotEnded);
+        require!(
+            choice_votes.len() == proposal.choices.len(),
+            DaoError::InvalidOffchainTally
+        );
+        // An empty tree has nothing to open in a dispute, so it can only count nothing
+        require!(
+            merkle_root != [0; 32] || (voter_count == 0 && choice_votes.iter().all(|&v| v == 0)),
+            DaoError::InvalidOffchainTally
+        );
+
+        let tally = &mut ctx.accounts.offchain_tally;
+        // A fresh account has no challenge window yet
+        require!(
+            tally.challenge_end == 0 || tally.disputed_by.is_some(),
+            DaoError::OffchainTallyAlreadySubmitted
+        );
+        tally.proposal = proposal.key();
+        tally.aggregator = ctx.accounts.aggregator.key();
+        tally.merkle_root = merkle_root;
+        tally.choice_votes = choice_votes;
+        tally.voter_count = voter_count;
+        tally.submitted_at = now;
+        tally.challenge_end = now
+            .checked_add(OFFCHAIN_CHALLENGE_SECS)
+            .ok_or(DaoError::InvalidVotingPeriod)?;
+        tally.disputed_by = None;
+        tally.bump = ctx.bumps.offchain_tally;
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(OffchainTallySubmittedEvent {
+            group_id: proposal.group_id.clone(),
+            event_seq,
+            proposal_id: proposal.proposal_id.clone(),
+            aggregator: tally.aggregator,
+            merkle_root,
+            choice_votes: tally.choice_votes.clone(),
+            voter_count,
+            challenge_end: tally.challenge_end,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Permissionless challenge: prove with `proof` that the tally counted `voter` for
+    // `tallied_choice`, alongside the voter's own signed ballot for `ballot_choice`,
+    // verified by the ed25519 instruction just before this one. A contradiction voids
+    // the tally until the aggregator resubmits. Aggregators must therefore leave out
+    // any voter who signed ballots for different choices.
+    #[cfg(feature = "offchain-votes")]
+    pub fn dispute_offchain_tally(
+        ctx: Context<DisputeOffchainTally>,
+        voter: Pubkey,
+        tallied_choice: u8,
+        vote_weight: u64,
+        proof: Vec<TallySubtree>,
+        ballot_choice: u8,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let proposal = &ctx.accounts.proposal;
+        let tally = &mut ctx.accounts.offchain_tally;
+        require!(tally.disputed_by.is_none(), DaoError::OffchainTallyDisputed);
+        require!(now <= tally.challenge_end, DaoError::ChallengeWindowClosed);
+        let leaf =
+            TallySums::ballot(proposal.choices.len(), tallied_choice, vote_weight).map(|sums| {
+                TallySubtree {
+                    hash: offchain_ballot_leaf(&voter, tallied_choice, vote_weight),
+                    sums,
+                }
+            });
+        require!(
+            proof.len() <= MAX_MERKLE_PROOF_LEN
+                && leaf
+                    .and_then(|leaf| offchain_tally_root(leaf, &proof))
+                    .is_some_and(|root| root.hash == tally.merkle_root),
+            DaoError::InvalidMerkleProof
+        );
+
+        let signer = verified_ed25519_signer(
+            &ctx.accounts.instructions_sysvar,
+            &offchain_ballot_message(&proposal.key(), ballot_choice),
+            DaoError::InvalidVoteSignature,
+        )?;
+        require_keys_eq!(signer, voter, DaoError::InvalidVoteSignature);
+        require!(
+            ballot_choice != tallied_choice,
+            DaoError::BallotNotContradicting
+        );
+
+        let disputer = ctx.accounts.disputer.key();
+        tally.disputed_by = Some(disputer);
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(OffchainTallyDisputedEvent {
+            group_id: proposal.group_id.clone(),
+            event_seq,
+            proposal_id: proposal.proposal_id.clone(),
+            disputer,
+            voter,
+            tallied_choice,
+            ballot_choice,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Permissionless challenge to the tally's arithmetic: open `node` of the tally tree,
+    // whose parent committed it to `claimed`, and prove with `proof` that it sits under
+    // the submitted root and totals. A ballot leaf must count its weight for its choice
+    // and one voter, and an inner node the sum of its children; if it doesn't, the tally
+    // is void until the aggregator resubmits. Disputing the highest node that doesn't add
+    // up keeps every step of the proof above it consistent.
+    #[cfg(feature = "offchain-votes")]
+    pub fn dispute_offchain_total(
+        ctx: Context<DisputeOffchainTotal>,
+        node: TallyNode,
+        claimed: TallySums,
+        proof: Vec<TallySubtree>,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let proposal = &ctx.accounts.proposal;
+        let choice_count = proposal.choices.len();
+        let tally = &mut ctx.accounts.offchain_tally;
+        require!(tally.disputed_by.is_none(), DaoError::OffchainTallyDisputed);
+        require!(now <= tally.challenge_end, DaoError::ChallengeWindowClosed);
+
+        let (hash, actual) = match &node {
+            TallyNode::Ballot {
+                voter,
+                choice,
+                vote_weight,
+            } => (
+                offchain_ballot_leaf(voter, *choice, *vote_weight),
+                TallySums::ballot(choice_count, *choice, *vote_weight),
+            ),
+            TallyNode::Inner { left, right } => (
+                offchain_tally_node(left, right),
+                left.sums.checked_add(&right.sums, choice_count),
+            ),
+        };
+        let totals = TallySums {
+            choice_votes: tally.choice_votes.clone(),
+            voter_count: tally.voter_count,
+        };
+        require!(
+            proof.len() <= MAX_MERKLE_PROOF_LEN
+                && claimed.choice_votes.len() == choice_count
+                && offchain_tally_root(
+                    TallySubtree {
+                        hash,
+                        sums: claimed.clone(),
+                    },
+                    &proof
+                )
+                .is_some_and(|root| root.hash == tally.merkle_root && root.sums == totals),
+            DaoError::InvalidMerkleProof
+        );
+        require!(actual != Some(claimed), DaoError::TallyNotContradicted);
+
+        let disputer = ctx.accounts.disputer.key();
+        tally.disputed_by = Some(disputer);
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(OffchainTotalDisputedEvent {
+            group_id: proposal.group_id.clone(),
+            event_seq,
+            proposal_id: proposal.proposal_id.clone(),
+            disputer,
+            node: hash,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Permissionless: once the challenge window has passed undisputed, copy the tally
+    // into the proposal so it can be finalized like any other
+    #[cfg(feature = "offchain-votes")]
+    pub fn settle_offchain_tally(ctx: Context<SettleOffchainTally>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let proposal = &mut ctx.accounts.proposal;
+        let tally = &ctx.accounts.offchain_tally;
+        require!(
+            proposal.status == ProposalStatus::Active && proposal.offchain_voter_count.is_none(),
+            DaoError::InvalidProposalStatus
+        );
+        require!(tally.disputed_by.is_none(), DaoError::OffchainTallyDisputed);
+        require!(now > tally.challenge_end, DaoError::ChallengeWindowOpen);
+
+        proposal.choice_votes = tally.choice_votes.clone();
+        proposal.offchain_voter_count = Some(tally.voter_count);
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(OffchainTallySettledEvent {
+            group_id: proposal.group_id.clone(),
+            event_seq,
+            proposal_id: proposal.proposal_id.clone(),
+            aggregator: tally.aggregator,
+            choice_votes: tally.choice_votes.clone(),
+            voter_count: tally.voter_count,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Permissionless: mirror a proposal into an spl-governance ProposalV2 account so
+    // Realms tooling pointed at this program can list it. Re-run to pick up new votes
+    // or status changes.
+    #[cfg(feature = "realms-compat")]
+    pub fn sync_realms_proposal(ctx: Context<SyncRealmsProposal>) -> Result<()> {
+        let proposal = &ctx.accounts.proposal;
+        let group = ctx.accounts.group.key();
+        let (token_owner_record, _) = Pubkey::find_program_address(
+            &[b"participation", group.as_ref(), proposal.creator.as_ref()],
+            &crate::ID,
+        );
+        let data =
+            realms::ProposalV2::from_proposal(proposal, group, token_owner_record).try_to_vec()?;
+
+        let governing_token_mint = proposal.token_mint.unwrap_or_default();
+        let proposal_key = proposal.key();
+        write_realms_account(
+            &ctx.accounts.realms_proposal,
+            &[
+                b"governance",
+                group.as_ref(),
+                governing_token_mint.as_ref(),
+                proposal_key.as_ref(),
+                &[ctx.bumps.realms_proposal],
+            ],
+            &data,
+            &ctx.accounts.payer,
+            &ctx.accounts.system_program,
+        )
+    }
+
+    // Permissionless: mirror one voter's ballot into an spl-governance VoteRecordV2
+    // account keyed by the mirrored proposal and the voter's Participation PDA
+    #[cfg(feature = "realms-compat")]
+    pub fn sync_realms_vote_record(
+        ctx: Context<SyncRealmsVoteRecord>,
+        voter: Pubkey,
+    ) -> Result<()> {
+        let proposal = &ctx.accounts.proposal;
+        let ballot = proposal
+            .voters
+            .iter()
+            .find(|v| v.voter == voter && v.vote_weight > 0)
+            .ok_or(DaoError::BallotNotFound)?;
+        let realms_proposal = ctx.accounts.realms_proposal.key();
+        let data =
+            realms::VoteRecordV2::from_ballot(realms_proposal, ballot, proposal.choices.len())
+                .try_to_vec()?;
+
+        let participation = ctx.accounts.participation.key();
+        write_realms_account(
+            &ctx.accounts.realms_vote_record,
+            &[
+                b"governance",
+                realms_proposal.as_ref(),
+                participation.as_ref(),
+                &[ctx.bumps.realms_vote_record],
+            ],
+            &data,
+            &ctx.accounts.payer,
+            &ctx.accounts.system_program,
+        )
+    }
+
+    // Let prospective members weigh in without counting toward the result
+    pub fn cast_shadow_vote(ctx: Context<CastShadowVote>, choice_index: u8) -> Result<()> {
+        let proposal = &mut ctx.accounts.proposal;
+        let clock = Clock::get()?;
+        let current_time = clock.unix_timestamp;
+
+        require!(
+            proposal.status == ProposalStatus::Active && proposal.voting_open(&clock),
+            DaoError::VotingNotActive
+        );
+        require!(
+            (choice_index as usize) < proposal.shadow_votes.len(),
+            DaoError::InvalidChoice
+        );
+
+        let voter_key = ctx.accounts.voter.key();
+        require!(
+            ctx.accounts.member_record.data_is_empty(),
+            DaoError::ShadowVoterIsMember
+        );
+
+        let tally = &mut proposal.shadow_votes[choice_index as usize];
+        *tally = tally.checked_add(1).ok_or(DaoError::TallyOverflow)?;
+
+        let ballot = &mut ctx.accounts.shadow_ballot;
+        ballot.proposal = proposal.key();
+        ballot.voter = voter_key;
+        ballot.choice = choice_index;
+        ballot.timestamp = current_time;
+        ballot.bump = ctx.bumps.shadow_ballot;
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(ShadowVoteCastEvent {
+            group_id: proposal.group_id.clone(),
+            event_seq,
+            proposal_id: proposal.proposal_id.clone(),
+            voter: voter_key,
+            choice: choice_index,
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+
+    #[cfg(feature = "large-proposals")]
+    pub fn create_large_proposal(
+        ctx: Context<CreateLargeProposal>,
+        proposal_id: String,
+        title: String,
+        description: String,
+        choices: Vec<String>,
+        voting_start: i64,
+        voting_end: i64,
+        token_mint: Option<Pubkey>,
+    ) -> Result<()> {
+        require!(proposal_id.len() <= 50, DaoError::ProposalIdTooLong);
+        require!(title.len() <= LARGE_TITLE_LEN, DaoError::TitleTooLong);
+        require!(
+            description.len() <= LARGE_DESCRIPTION_LEN,
+            DaoError::DescriptionTooLong
+        );
+        validate_token_mint(token_mint)?;
+        require!(
+            choices.len() >= 2
+                && choices.len()
+                    <= LARGE_MAX_CHOICES.min(ctx.accounts.program_config.max_choices as usize),
+            DaoError::InvalidChoiceCount
+        );
+        require!(
+            choices.iter().all(|c| c.len() <= LARGE_CHOICE_LEN),
+            DaoError::ChoiceTooLong
+        );
+        require!(voting_start < voting_end, DaoError::InvalidVotingPeriod);
+        require!(
+            ctx.accounts
+                .group
+                .governance
+                .allows_duration(voting_end - voting_start, false),
+            DaoError::VotingDurationOutOfBounds
+        );
+        let now = Clock::get()?.unix_timestamp;
+        require!(voting_start > now, DaoError::VotingStartInPast);
+        // Large proposals have no drafts to gather co-sponsors on
+        require!(
+            ctx.accounts.group.sponsor_threshold == 0,
+            DaoError::SponsorshipRequired
+        );
+
+        let group = &mut ctx.accounts.group;
+        let mut proposal = ctx.accounts.proposal.load_init()?;
+        proposal.group = group.key();
+        proposal.creator = ctx.accounts.authority.key();
+        proposal.token_mint = token_mint.unwrap_or_default();
+        proposal.has_token_mint = token_mint.is_some() as u8;
+        proposal.voting_start = voting_start;
+        proposal.voting_end = voting_end;
+        proposal.created_at = now;
+        proposal.index = group.proposal_count;
+        proposal.proposal_id[..proposal_id.len()].copy_from_slice(proposal_id.as_bytes());
+        proposal.proposal_id_len = proposal_id.len() as u8;
+        proposal.title[..title.len()].copy_from_slice(title.as_bytes());
+        proposal.title_len = title.len() as u16;
+        proposal.description[..description.len()].copy_from_slice(description.as_bytes());
+        proposal.description_len = description.len() as u16;
+        for (slot, choice) in proposal.choices.iter_mut().zip(choices.iter()) {
+            slot[..choice.len()].copy_from_slice(choice.as_bytes());
+        }
+        proposal.choice_count = choices.len() as u8;
+        proposal.bump = ctx.bumps.proposal;
+
+        let proposal_index = &mut ctx.accounts.proposal_index;
+        proposal_index.group = group.key();
+        proposal_index.index = group.proposal_count;
+        proposal_index.proposal_id = proposal_id.clone();
+        proposal_index.pubkey = ctx.accounts.proposal.key();
+        proposal_index.created_at = now;
+        proposal_index.large = true;
+        proposal_index.bump = ctx.bumps.proposal_index;
+
+        group.proposal_count = group
+            .proposal_count
+            .checked_add(1)
+            .ok_or(DaoError::ProposalCountOverflow)?;
+
+        let creation_fee = group.proposal_fee_lamports;
+        pay_proposal_fee(
+            creation_fee,
+            &ctx.accounts.authority,
+            &ctx.accounts.treasury,
+            &ctx.accounts.system_program,
+        )?;
+
+        ctx.accounts.participation.record_proposal(
+            group.key(),
+            ctx.accounts.authority.key(),
+            ctx.bumps.participation,
+            group.proposal_cooldown_secs,
+            now,
+        )?;
+
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(ProposalCreatedEvent {
+            group_id: group.group_id.clone(),
+            event_seq,
+            proposal_id,
+            creator: ctx.accounts.authority.key(),
+            proposal_pubkey: ctx.accounts.proposal.key(),
+            voting_start,
+            voting_end,
+            metadata_uri: None,
+            draft: false, // large proposals have no lifecycle status
+            emergency: false,
+            creation_fee,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    #[cfg(feature = "large-proposals")]
+    pub fn vote_on_large_proposal(
+        ctx: Context<VoteOnLargeProposal>,
+        choice_index: u8,
+        disclosure: Option<String>,
+    ) -> Result<()> {
+        validate_disclosure(&disclosure)?;
+        let current_time = Clock::get()?.unix_timestamp;
+        let voter_key = ctx.accounts.voter.key();
+        require_listed_member(&ctx.accounts.group, ctx.accounts.member_record.as_ref())?;
+        require_not_banned(&ctx.accounts.ban, current_time)?;
+        require_dues_current(
+            &ctx.accounts.group,
+            ctx.accounts.dues_record.as_ref(),
+            current_time,
+        )?;
+
+        // Only the fixed-size header and tally region of the zero-copy account are touched
+        let mut proposal = ctx.accounts.proposal.load_mut()?;
+        require!(
+            current_time >= proposal.voting_start && current_time <= proposal.voting_end,
+            DaoError::VotingNotActive
+        );
+        lock_voted_shares(
+            &ctx.accounts.group,
+            ctx.accounts.member_shares.as_mut(),
+            proposal.voting_end,
+        )?;
+        require!(
+            choice_index < proposal.choice_count,
+            DaoError::InvalidChoice
+        );
+
+        let token_mint = (proposal.has_token_mint != 0).then_some(proposal.token_mint);
+        let vote_weight = compute_vote_weight(
+            token_mint,
+            &ctx.accounts.voter,
+            &ctx.accounts.voter_token_account,
+        )?;
+        require!(vote_weight > 0, DaoError::NoVotingPower);
+
+        add_to_tally(
+            &mut proposal.choice_votes[choice_index as usize],
+            vote_weight,
+        )?;
+        proposal.voter_count = proposal
+            .voter_count
+            .checked_add(1)
+            .ok_or(DaoError::TallyOverflow)?;
+
+        // The vote record PDA can only be initialized once, which prevents double voting
+        let vote_record = &mut ctx.accounts.vote_record;
+        vote_record.proposal = ctx.accounts.proposal.key();
+        vote_record.voter = voter_key;
+        vote_record.choice = choice_index;
+        vote_record.vote_weight = vote_weight;
+        vote_record.timestamp = current_time;
+        vote_record.disclosure = disclosure.clone();
+        vote_record.bump = ctx.bumps.vote_record;
+
+        ctx.accounts.participation.record_vote(
+            ctx.accounts.group.key(),
+            voter_key,
+            ctx.bumps.participation,
+            proposal.index,
+            current_time,
+        )?;
+        ctx.accounts.turnout.record_vote(
+            ctx.accounts.proposal.key(),
+            ctx.bumps.turnout,
+            &proposal.choice_votes[..proposal.choice_count as usize],
+            proposal.voting_start,
+            current_time,
+        )?;
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(VoteCastEvent {
+            group_id: ctx.accounts.group.group_id.clone(),
+            event_seq,
+            proposal_id: proposal.proposal_id_str(),
+            voter: voter_key,
+            choice: choice_index,
+            vote_weight,
+            timestamp: current_time,
+            disclosure,
+            allocations: None,
+        });
+
+        Ok(())
+    }
+
+    // Escrow lamports in a proposal as a bounty for whoever finalizes it; anyone can fund
+    pub fn fund_finalize_bounty(ctx: Context<FundFinalizeBounty>, amount: u64) -> Result<()> {
+        require!(amount > 0, DaoError::InvalidBountyAmount);
+        require!(
+            matches!(
+                ctx.accounts.proposal.status,
+                ProposalStatus::Draft | ProposalStatus::Active
+            ),
+            DaoError::InvalidProposalStatus
+        );
+
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.funder.to_account_info(),
+                    to: ctx.accounts.proposal.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.finalize_bounty = proposal
+            .finalize_bounty
+            .checked_add(amount)
+            .ok_or(DaoError::TallyOverflow)?;
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(FinalizeBountyFundedEvent {
+            group_id: proposal.group_id.clone(),
+            event_seq,
+            proposal_id: proposal.proposal_id.clone(),
+            funder: ctx.accounts.funder.key(),
+            amount,
+            total_bounty: proposal.finalize_bounty,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Permissionless crank: once voting has ended anyone can seal the result, so it
+    // doesn't depend on the creator coming back. The caller collects any escrowed bounty.
+    pub fn finalize_proposal(ctx: Context<FinalizeProposal>) -> Result<()> {
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp;
+        let proposal = &mut ctx.accounts.proposal;
+
+        match proposal.status {
+            ProposalStatus::Active => {}
+            ProposalStatus::Succeeded | ProposalStatus::Defeated | ProposalStatus::Executed => {
+                return err!(DaoError::ProposalAlreadyFinalized)
+            }
+            ProposalStatus::Draft | ProposalStatus::Cancelled => {
+                return err!(DaoError::InvalidProposalStatus)
+            }
+        }
+        require!(proposal.after_voting(&clock), DaoError::VotingNotEnded);
+        require!(
+            proposal.offchain_aggregator.is_none() || proposal.offchain_voter_count.is_some(),
+            DaoError::OffchainTallyPending
+        );
+
+        match hold_for_tie_break(&mut ctx.accounts.proposal, &mut ctx.accounts.group, &clock)? {
+            TieHold::None => {}
+            TieHold::Reopened => {
+                // Nothing is sealed yet; the result is written when the revote ends
+                return ctx
+                    .accounts
+                    .proposal_result
+                    .close(ctx.accounts.cranker.to_account_info());
+            }
+            TieHold::AwaitingCouncil => return err!(DaoError::TieBreakPending),
+        }
+
+        let result = seal_proposal(
+            &mut ctx.accounts.proposal,
+            &mut ctx.accounts.group,
+            &ctx.accounts.cranker,
+            ctx.accounts.quorum_supply.as_ref(),
+            ctx.bumps.proposal_result,
+            now,
+            None,
+        )?;
+        ctx.accounts.proposal_result.set_inner(result);
+
+        if let Some(lottery) = ctx.accounts.lottery.as_mut() {
+            let slot_hashes = ctx
+                .accounts
+                .slot_hashes
+                .as_ref()
+                .ok_or(DaoError::LotteryRandomnessUnavailable)?;
+            draw_lottery(
+                lottery,
+                &ctx.accounts.proposal,
+                slot_hashes,
+                &mut ctx.accounts.group,
+                now,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    // Under the Council tie-break, council members pick the winner among the choices tied
+    // for first and seal the result. `council_member` pays for the result and collects any
+    // finalize bounty; the other members needed for the council threshold sign the same
+    // transaction and are passed as remaining accounts.
+    pub fn resolve_tie(ctx: Context<ResolveTie>, choice_index: u8) -> Result<()> {
+        let clock = Clock::get()?;
+        let proposal = &ctx.accounts.proposal;
+        require!(
+            proposal.status == ProposalStatus::Active
+                && proposal.tied
+                && ctx.accounts.group.tie_break == TieBreak::Council,
+            DaoError::NotTied
+        );
+        require!(proposal.after_voting(&clock), DaoError::VotingNotEnded);
+        require!(
+            leading_choices(&proposal.choice_votes).contains(&choice_index),
+            DaoError::NotTied
+        );
+
+        let council = &ctx.accounts.council;
+        let mut signers = vec![ctx.accounts.council_member.key()];
+        for account in ctx.remaining_accounts {
+            if account.is_signer && !signers.contains(account.key) {
+                signers.push(account.key());
+            }
+        }
+        require!(
+            signers
+                .iter()
+                .all(|signer| council.members.contains(signer)),
+            DaoError::NotCouncilMember
+        );
+        require!(
+            signers.len() >= council.threshold as usize,
+            DaoError::CouncilThresholdNotMet
+        );
+
+        let result = seal_proposal(
+            &mut ctx.accounts.proposal,
+            &mut ctx.accounts.group,
+            &ctx.accounts.council_member,
+            ctx.accounts.quorum_supply.as_ref(),
+            ctx.bumps.proposal_result,
+            clock.unix_timestamp,
+            Some(choice_index),
+        )?;
+        ctx.accounts.proposal_result.set_inner(result);
+
+        Ok(())
+    }
+
+    // Let a draft be opened by `tick` once its voting start arrives, instead of waiting
+    // for the authority to activate it
+    #[cfg(feature = "automation")]
+    pub fn schedule_activation(ctx: Context<ScheduleActivation>) -> Result<()> {
+        let proposal = &ctx.accounts.proposal;
+        require!(
+            proposal.status == ProposalStatus::Draft,
+            DaoError::InvalidProposalStatus
+        );
+
+        let schedule = &mut ctx.accounts.schedule;
+        schedule.proposal = proposal.key();
+        schedule.scheduled_by = ctx.accounts.authority.key();
+        schedule.bump = ctx.bumps.schedule;
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(ActivationScheduledEvent {
+            group_id: proposal.group_id.clone(),
+            event_seq,
+            proposal_id: proposal.proposal_id.clone(),
+            voting_start: proposal.voting_start,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Permissionless crank for automation networks: opens a scheduled draft once its
+    // voting start has arrived and finalizes the proposal once voting has ended. Any
+    // other call succeeds without doing anything, so a thread can tick on an interval
+    // with the same account list for the proposal's whole life.
+    #[cfg(feature = "automation")]
+    pub fn tick(ctx: Context<Tick>) -> Result<()> {
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp;
+        let proposal = &ctx.accounts.proposal;
+
+        match proposal.status {
+            ProposalStatus::Draft
+                if ctx.accounts.schedule.is_some()
+                    && !proposal.before_voting(&clock)
+                    && proposal.sponsor_count >= proposal.sponsors_needed =>
+            {
+                open_proposal(
+                    &mut ctx.accounts.proposal,
+                    &mut ctx.accounts.group,
+                    &ctx.accounts.proposal_deposit,
+                    &clock,
+                )
+            }
+            ProposalStatus::Active
+                if proposal.after_voting(&clock)
+                    && (proposal.offchain_aggregator.is_none()
+                        || proposal.offchain_voter_count.is_some()) =>
+            {
+                if hold_for_tie_break(&mut ctx.accounts.proposal, &mut ctx.accounts.group, &clock)?
+                    != TieHold::None
+                {
+                    return Ok(());
+                }
+                let proposal_key = ctx.accounts.proposal.key();
+                let result_info = ctx.accounts.proposal_result.to_account_info();
+                create_pda_account(
+                    &result_info,
+                    &[
+                        b"proposal_result",
+                        proposal_key.as_ref(),
+                        &[ctx.bumps.proposal_result],
+                    ],
+                    8 + ProposalResult::INIT_SPACE,
+                    &ctx.accounts.cranker,
+                    &ctx.accounts.system_program,
+                )?;
+
+                let result = seal_proposal(
+                    &mut ctx.accounts.proposal,
+                    &mut ctx.accounts.group,
+                    &ctx.accounts.cranker,
+                    ctx.accounts.quorum_supply.as_ref(),
+                    ctx.bumps.proposal_result,
+                    now,
+                    None,
+                )?;
+                let mut data = result_info.try_borrow_mut_data()?;
+                result.try_serialize(&mut &mut data[..])
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Permissionless GC: once a settled proposal has been past its voting window for
+    // STALE_PROPOSAL_GRACE_SECS, anyone can close it and its index entry. The rent goes to
+    // the protocol fee destination; the outcome lives on in its ProposalResult.
+    pub fn close_stale_proposal(ctx: Context<CloseStaleProposal>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let proposal = &ctx.accounts.proposal;
+        require!(
+            matches!(
+                proposal.status,
+                ProposalStatus::Succeeded
+                    | ProposalStatus::Defeated
+                    | ProposalStatus::Executed
+                    | ProposalStatus::Cancelled
+            ),
+            DaoError::InvalidProposalStatus
+        );
+        require!(
+            now > proposal
+                .voting_end
+                .saturating_add(STALE_PROPOSAL_GRACE_SECS),
+            DaoError::ProposalNotStale
+        );
+        // A passed proposal stays until its kind's execute instruction has run, and until
+        // any callback or program upgrade hanging off it has, since those need the account
+        require!(
+            proposal.status != ProposalStatus::Succeeded || !proposal.awaits_execution(),
+            DaoError::ProposalAwaitsExecution
+        );
+        if matches!(
+            proposal.status,
+            ProposalStatus::Succeeded | ProposalStatus::Executed
+        ) {
+            require!(
+                ctx.accounts.callback.data_is_empty(),
+                DaoError::ProposalAwaitsExecution
+            );
+        }
+        #[cfg(feature = "treasury")]
+        require!(
+            proposal.status != ProposalStatus::Succeeded
+                || !upgrade_pending(proposal, &ctx.accounts.upgrade_proposal)?,
+            DaoError::ProposalAwaitsExecution
+        );
+        // Settling a deposit reads the proposal, so it has to happen first
+        require!(
+            ctx.accounts.proposal_deposit.data_is_empty(),
+            DaoError::ProposalDepositUnsettled
+        );
+
+        let reclaimed_lamports = proposal
+            .to_account_info()
+            .lamports()
+            .saturating_add(ctx.accounts.proposal_index.to_account_info().lamports());
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(ProposalClosedEvent {
+            group_id: proposal.group_id.clone(),
+            event_seq,
+            proposal_id: proposal.proposal_id.clone(),
+            index: proposal.index,
+            reclaimed_lamports,
+            closed_by: ctx.accounts.closer.key(),
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Add to a proposal's voter reward pool, in SOL or one SPL token. The pool is created
+    // by its first funding, which fixes the funder and token; SPL pools hold their tokens
+    // in `vault`, a token account owned by the pool PDA. Funding closes once the proposal
+    // is finalized, so every voter's share is known.
+    #[cfg(feature = "vote-rewards")]
+    pub fn fund_vote_rewards(
+        ctx: Context<FundVoteRewards>,
+        token_mint: Pubkey,
+        amount: u64,
+    ) -> Result<()> {
+        require!(amount > 0, DaoError::InvalidRewardAmount);
+        require!(
+            matches!(
+                ctx.accounts.proposal.status,
+                ProposalStatus::Draft | ProposalStatus::Active
+            ),
+            DaoError::InvalidProposalStatus
+        );
+
+        let funder = ctx.accounts.funder.key();
+        let pool = &mut ctx.accounts.reward_pool;
+        if pool.funder == Pubkey::default() {
+            pool.group = ctx.accounts.group.key();
+            pool.proposal = ctx.accounts.proposal.key();
+            pool.funder = funder;
+            pool.token_mint = token_mint;
+            pool.vault = if token_mint == native_mint() {
+                pool.key()
+            } else {
+                let vault = ctx
+                    .accounts
+                    .vault
+                    .as_ref()
+                    .ok_or(DaoError::TokenAccountRequired)?;
+                let (owner, _) = spl_token_balance(token_mint, vault)?;
+                require_keys_eq!(owner, pool.key(), DaoError::InvalidTokenAccount);
+                vault.key()
+            };
+            pool.bump = ctx.bumps.reward_pool;
+        }
+        require!(
+            pool.funder == funder && pool.token_mint == token_mint,
+            DaoError::RewardPoolMismatch
+        );
+
+        if token_mint == native_mint() {
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.funder.to_account_info(),
+                        to: pool.to_account_info(),
+                    },
+                ),
+                amount,
+            )?;
+        } else {
+            let vault = ctx
+                .accounts
+                .vault
+                .as_ref()
+                .ok_or(DaoError::TokenAccountRequired)?;
+            require_keys_eq!(vault.key(), pool.vault, DaoError::RewardPoolMismatch);
+            transfer_spl(
+                ctx.accounts
+                    .token_program
+                    .as_ref()
+                    .ok_or(DaoError::TokenAccountRequired)?,
+                ctx.accounts
+                    .funder_token_account
+                    .as_ref()
+                    .ok_or(DaoError::TokenAccountRequired)?,
+                vault,
+                &ctx.accounts.funder.to_account_info(),
+                &[],
+                amount,
+            )?;
+        }
+
+        pool.total_amount = pool
+            .total_amount
+            .checked_add(amount)
+            .ok_or(DaoError::TallyOverflow)?;
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(VoteRewardsFundedEvent {
+            group_id: ctx.accounts.proposal.group_id.clone(),
+            event_seq,
+            proposal_id: ctx.accounts.proposal.proposal_id.clone(),
+            funder,
+            token_mint,
+            amount,
+            total_amount: pool.total_amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Pay a voter their equal share of the reward pool once the proposal is finalized.
+    // Each voter claims once; the last claim also takes the rounding remainder. Claims
+    // close with the stale-proposal grace period, after which the funder can reclaim.
+    #[cfg(feature = "vote-rewards")]
+    pub fn claim_vote_reward(ctx: Context<ClaimVoteReward>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let proposal = &ctx.accounts.proposal;
+        require!(
+            now <= proposal
+                .voting_end
+                .saturating_add(STALE_PROPOSAL_GRACE_SECS),
+            DaoError::RewardClaimWindowClosed
+        );
+        let voter = ctx.accounts.voter.key();
+        require!(
+            proposal
+                .voters
+                .iter()
+                .any(|v| v.voter == voter && v.vote_weight > 0),
+            DaoError::NotAVoter
+        );
+
+        let voter_count = ctx.accounts.proposal_result.voter_count;
+        let pool = &mut ctx.accounts.reward_pool;
+        let claim_count = pool
+            .claim_count
+            .checked_add(1)
+            .ok_or(DaoError::TallyOverflow)?;
+        let amount = if claim_count >= voter_count {
+            pool.total_amount - pool.claimed_amount
+        } else {
+            pool.total_amount / voter_count as u64
+        };
+
+        if pool.token_mint == native_mint() {
+            // The pool PDA is program-owned, so the reward can be moved out directly
+            **pool.to_account_info().try_borrow_mut_lamports()? -= amount;
+            **ctx
+                .accounts
+                .voter
+                .to_account_info()
+                .try_borrow_mut_lamports()? += amount;
+        } else {
+            let vault = ctx
+                .accounts
+                .vault
+                .as_ref()
+                .ok_or(DaoError::TokenAccountRequired)?;
+            require_keys_eq!(vault.key(), pool.vault, DaoError::RewardPoolMismatch);
+            let pool_seeds: &[&[u8]] = &[b"reward_pool", pool.proposal.as_ref(), &[pool.bump]];
+            transfer_spl(
+                ctx.accounts
+                    .token_program
+                    .as_ref()
+                    .ok_or(DaoError::TokenAccountRequired)?,
+                vault,
+                ctx.accounts
+                    .voter_token_account
+                    .as_ref()
+                    .ok_or(DaoError::TokenAccountRequired)?,
+                &pool.to_account_info(),
+                &[pool_seeds],
+                amount,
+            )?;
+        }
+
+        pool.claim_count = claim_count;
+        pool.claimed_amount += amount;
+
+        let claim = &mut ctx.accounts.reward_claim;
+        claim.pool = pool.key();
+        claim.voter = voter;
+        claim.amount = amount;
+        claim.claimed_at = now;
+        claim.bump = ctx.bumps.reward_claim;
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(VoteRewardClaimedEvent {
+            group_id: proposal.group_id.clone(),
+            event_seq,
+            proposal_id: proposal.proposal_id.clone(),
+            voter,
+            token_mint: pool.token_mint,
+            amount,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Return whatever voters haven't claimed to the funder and close the pool: right away
+    // if the proposal was cancelled, otherwise once the claim window has closed
+    #[cfg(feature = "vote-rewards")]
+    pub fn reclaim_vote_rewards(ctx: Context<ReclaimVoteRewards>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        // A closed proposal was past its grace period, which is also the claim window
+        if !ctx.accounts.proposal.data_is_empty() {
+            require_keys_eq!(
+                *ctx.accounts.proposal.owner,
+                crate::ID,
+                DaoError::RewardPoolMismatch
+            );
+            let proposal =
+                Proposal::try_deserialize(&mut &ctx.accounts.proposal.try_borrow_data()?[..])?;
+            require!(
+                proposal.status == ProposalStatus::Cancelled
+                    || now
+                        > proposal
+                            .voting_end
+                            .saturating_add(STALE_PROPOSAL_GRACE_SECS),
+                DaoError::RewardsStillClaimable
+            );
+        }
+
+        let pool = &ctx.accounts.reward_pool;
+        let amount = pool.total_amount - pool.claimed_amount;
+        if pool.token_mint != native_mint() {
+            let vault = ctx
+                .accounts
+                .vault
+                .as_ref()
+                .ok_or(DaoError::TokenAccountRequired)?;
+            require_keys_eq!(vault.key(), pool.vault, DaoError::RewardPoolMismatch);
+            let token_program = ctx
+                .accounts
+                .token_program
+                .as_ref()
+                .ok_or(DaoError::TokenAccountRequired)?;
+            let pool_seeds: &[&[u8]] = &[b"reward_pool", pool.proposal.as_ref(), &[pool.bump]];
+            // Sweep the whole vault, including anything sent to it directly, so it can close
+            let (_, balance) = spl_token_balance(pool.token_mint, vault)?;
+            transfer_spl(
+                token_program,
+                vault,
+                ctx.accounts
+                    .funder_token_account
+                    .as_ref()
+                    .ok_or(DaoError::TokenAccountRequired)?,
+                &pool.to_account_info(),
+                &[pool_seeds],
+                balance,
+            )?;
+            close_spl_account(
+                token_program,
+                vault,
+                &ctx.accounts.funder.to_account_info(),
+                &pool.to_account_info(),
+                &[pool_seeds],
+            )?;
+        }
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(VoteRewardsReclaimedEvent {
+            group_id: ctx.accounts.group.group_id.clone(),
+            event_seq,
+            proposal: pool.proposal,
+            funder: pool.funder,
+            token_mint: pool.token_mint,
+            amount,
+            claim_count: pool.claim_count,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Add SOL to a proposal's voter lottery, which pays `winner_count` voters drawn at
+    // random when the proposal is finalized. The first funding creates the lottery and
+    // fixes its funder and number of winners.
+    #[cfg(feature = "voter-lottery")]
+    pub fn fund_voter_lottery(
+        ctx: Context<FundVoterLottery>,
+        winner_count: u8,
+        amount: u64,
+    ) -> Result<()> {
+        require!(amount > 0, DaoError::InvalidRewardAmount);
+        require!(
+            (1..=MAX_LOTTERY_WINNERS as u8).contains(&winner_count),
+            DaoError::InvalidLotteryWinners
+        );
+        require!(
+            matches!(
+                ctx.accounts.proposal.status,
+                ProposalStatus::Draft | ProposalStatus::Active
+            ),
+            DaoError::InvalidProposalStatus
+        );
+
+        let funder = ctx.accounts.funder.key();
+        let lottery = &mut ctx.accounts.lottery;
+        if lottery.funder == Pubkey::default() {
+            lottery.group = ctx.accounts.group.key();
+            lottery.proposal = ctx.accounts.proposal.key();
+            lottery.funder = funder;
+            lottery.winner_count = winner_count;
+            lottery.bump = ctx.bumps.lottery;
+        }
+        require!(
+            lottery.funder == funder && lottery.winner_count == winner_count,
+            DaoError::RewardPoolMismatch
+        );
+
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.funder.to_account_info(),
+                    to: lottery.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+        lottery.total_amount = lottery
+            .total_amount
+            .checked_add(amount)
+            .ok_or(DaoError::TallyOverflow)?;
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(VoterLotteryFundedEvent {
+            group_id: ctx.accounts.proposal.group_id.clone(),
+            event_seq,
+            proposal_id: ctx.accounts.proposal.proposal_id.clone(),
+            funder,
+            winner_count,
+            amount,
+            total_amount: lottery.total_amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Draw a finalized proposal's lottery when finalize_proposal was sent without it, as
+    // `tick` does. Anyone may call it.
+    #[cfg(feature = "voter-lottery")]
+    pub fn draw_voter_lottery(ctx: Context<DrawVoterLottery>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        draw_lottery(
+            &mut ctx.accounts.lottery,
+            &ctx.accounts.proposal,
+            &ctx.accounts.slot_hashes,
+            &mut ctx.accounts.group,
+            now,
+        )
+    }
+
+    // Pay a drawn winner their prize. Unclaimed prizes go back to the funder once the
+    // claim window closes.
+    #[cfg(feature = "voter-lottery")]
+    pub fn claim_lottery_prize(ctx: Context<ClaimLotteryPrize>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let lottery = &mut ctx.accounts.lottery;
+        require!(lottery.drawn, DaoError::LotteryNotDrawn);
+        require!(
+            now <= lottery.claim_deadline,
+            DaoError::RewardClaimWindowClosed
+        );
+        let winner = ctx.accounts.winner.key();
+        let position = lottery
+            .winners
+            .iter()
+            .position(|w| *w == winner)
+            .ok_or(DaoError::NotALotteryWinner)?;
+        require!(
+            lottery.claimed_mask & (1 << position) == 0,
+            DaoError::PrizeAlreadyClaimed
+        );
+
+        // The lottery PDA is program-owned, so the prize can be moved out directly
+        let amount = lottery.prize;
+        **lottery.to_account_info().try_borrow_mut_lamports()? -= amount;
+        **ctx
+            .accounts
+            .winner
+            .to_account_info()
+            .try_borrow_mut_lamports()? += amount;
+        lottery.claimed_mask |= 1 << position;
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(LotteryPrizeClaimedEvent {
+            group_id: ctx.accounts.group.group_id.clone(),
+            event_seq,
+            proposal: lottery.proposal,
+            winner,
+            amount,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Return the lottery's unclaimed SOL to the funder and close it: right away if the
+    // proposal was cancelled or nobody voted, otherwise once the claim window has closed
+    #[cfg(feature = "voter-lottery")]
+    pub fn reclaim_voter_lottery(ctx: Context<ReclaimVoterLottery>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let lottery = &ctx.accounts.lottery;
+        let reclaimable = if lottery.drawn {
+            lottery.winners.is_empty() || now > lottery.claim_deadline
+        } else if ctx.accounts.proposal.data_is_empty() {
+            // A closed proposal was past its grace period, which is also the claim window
+            true
+        } else {
+            require_keys_eq!(
+                *ctx.accounts.proposal.owner,
+                crate::ID,
+                DaoError::RewardPoolMismatch
+            );
+            let proposal =
+                Proposal::try_deserialize(&mut &ctx.accounts.proposal.try_borrow_data()?[..])?;
+            proposal.status == ProposalStatus::Cancelled
+                || now
+                    > proposal
+                        .voting_end
+                        .saturating_add(STALE_PROPOSAL_GRACE_SECS)
+        };
+        require!(reclaimable, DaoError::RewardsStillClaimable);
+
+        let claimed = lottery.claimed_mask.count_ones() as u64 * lottery.prize;
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(VoterLotteryReclaimedEvent {
+            group_id: ctx.accounts.group.group_id.clone(),
+            event_seq,
+            proposal: lottery.proposal,
+            funder: lottery.funder,
+            amount: lottery.total_amount - claimed,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Set the flat fee charged for each new proposal, paid into the group treasury PDA.
+    // A non-zero fee must at least cover the rent-exempt minimum, so the first payment
+    // can open the treasury account.
+    #[cfg(feature = "treasury")]
+    pub fn set_proposal_fee(ctx: Context<SetProposalFee>, fee_lamports: u64) -> Result<()> {
+        require!(
+            fee_lamports == 0
+                || (Rent::get()?.minimum_balance(0)..=MAX_PROPOSAL_FEE_LAMPORTS)
+                    .contains(&fee_lamports),
+            DaoError::InvalidProposalFee
+        );
+
+        let group = &mut ctx.accounts.group;
+        group.proposal_fee_lamports = fee_lamports;
+
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(ProposalFeeUpdatedEvent {
+            group_id: group.group_id.clone(),
+            event_seq,
+            fee_lamports,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Require each proposal's creator to escrow `lamports` before it opens for voting, so
+    // new proposals must start as drafts (0 turns it off). At least the rent minimum, so
+    // a slashed deposit can always land in the treasury PDA.
+    #[cfg(feature = "treasury")]
+    pub fn set_proposal_deposit(ctx: Context<SetProposalCooldown>, lamports: u64) -> Result<()> {
+        require!(
+            lamports == 0
+                || (Rent::get()?.minimum_balance(0)..=MAX_PROPOSAL_DEPOSIT_LAMPORTS)
+                    .contains(&lamports),
+            DaoError::InvalidProposalDeposit
+        );
+        let group = &mut ctx.accounts.group;
+        group.proposal_deposit_lamports = lamports;
+
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(ProposalDepositRequirementUpdatedEvent {
+            group_id: group.group_id.clone(),
+            event_seq,
+            lamports,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Escrow a deposit against a draft as a pledge that it isn't spam. The program adds a
+    // "Spam" choice of its own to the draft; the deposit is slashed to the group treasury
+    // if that choice wins a two-thirds supermajority or the group council flags the
+    // proposal. It must cover the group's required deposit and the rent minimum.
+    #[cfg(feature = "treasury")]
+    pub fn post_proposal_deposit(ctx: Context<PostProposalDeposit>, amount: u64) -> Result<()> {
+        require!(
+            amount >= ctx.accounts.group.proposal_deposit_lamports
+                && amount >= Rent::get()?.minimum_balance(0),
+            DaoError::DepositTooSmall
+        );
+        let proposal = &mut ctx.accounts.proposal;
+        require!(
+            proposal.status == ProposalStatus::Draft,
+            DaoError::InvalidProposalStatus
+        );
+        require!(
+            proposal.choices.len() < MAX_CHOICES,
+            DaoError::InvalidSpamChoice
+        );
+        let spam_choice = proposal.choices.len() as u8;
+        proposal.choices.push(SPAM_CHOICE.to_string());
+        proposal.choice_votes.push(0);
+        proposal.shadow_votes.push(0);
+        if let Some(chamber) = proposal.council_chamber.as_mut() {
+            chamber.choice_votes.push(0);
+        }
+
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.depositor.to_account_info(),
+                    to: ctx.accounts.proposal_deposit.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        let proposal = &ctx.accounts.proposal;
+        let deposit = &mut ctx.accounts.proposal_deposit;
+        deposit.proposal = proposal.key();
+        deposit.group = ctx.accounts.group.key();
+        deposit.depositor = ctx.accounts.depositor.key();
+        deposit.amount = amount;
+        deposit.spam_choice = spam_choice;
+        deposit.flags = Vec::new();
+        deposit.flagged = false;
+        deposit.bump = ctx.bumps.proposal_deposit;
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(ProposalDepositPostedEvent {
+            group_id: proposal.group_id.clone(),
+            event_seq,
+            proposal_id: proposal.proposal_id.clone(),
+            depositor: deposit.depositor,
+            amount,
+            spam_choice,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // A council member's vote to flag a proposal as spam. Once `threshold` members have
+    // flagged it, its deposit is slashed when settled, whatever the vote result.
+    #[cfg(feature = "treasury")]
+    pub fn flag_as_spam(ctx: Context<FlagAsSpam>) -> Result<()> {
+        let member = ctx.accounts.council_member.key();
+        let council = &ctx.accounts.council;
+        require!(
+            council.members.contains(&member),
+            DaoError::NotCouncilMember
+        );
+
+        let deposit = &mut ctx.accounts.proposal_deposit;
+        require!(!deposit.flags.contains(&member), DaoError::AlreadyFlagged);
+        deposit.flags.push(member);
+        deposit.flagged = deposit.flags.len() >= council.threshold as usize;
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(ProposalFlaggedAsSpamEvent {
+            group_id: ctx.accounts.proposal.group_id.clone(),
+            event_seq,
+            proposal_id: ctx.accounts.proposal.proposal_id.clone(),
+            council_member: member,
+            flags: deposit.flags.len() as u8,
+            flagged: deposit.flagged,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Permissionless: release a proposal's deposit once the proposal is settled, or as
+    // soon as the council has flagged it. A flagged proposal, or one whose spam choice
+    // won a supermajority, forfeits the deposit to the group treasury; otherwise it is
+    // refunded. The deposit account's rent always goes back to the depositor.
+    #[cfg(feature = "treasury")]
+    pub fn settle_proposal_deposit(ctx: Context<SettleProposalDeposit>) -> Result<()> {
+        let proposal = &ctx.accounts.proposal;
+        let deposit = &ctx.accounts.proposal_deposit;
+        require!(
+            deposit.flagged
+                || matches!(
+                    proposal.status,
+                    ProposalStatus::Succeeded
+                        | ProposalStatus::Defeated
+                        | ProposalStatus::Executed
+                        | ProposalStatus::Cancelled
+                ),
+            DaoError::InvalidProposalStatus
+        );
+
+        let slashed = deposit.flagged
+            || (proposal.status != ProposalStatus::Cancelled
+                && is_spam_supermajority(&proposal.choice_votes, deposit.spam_choice));
+        let amount = deposit.amount;
+        let recipient = if slashed {
+            ctx.accounts.treasury.to_account_info()
+        } else {
+            ctx.accounts.depositor.to_account_info()
+        };
+        // The deposit PDA is program-owned, so the escrow can be moved out directly; the
+        // remaining rent follows when the account closes
+        **deposit.to_account_info().try_borrow_mut_lamports()? -= amount;
+        **recipient.try_borrow_mut_lamports()? += amount;
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(ProposalDepositSettledEvent {
+            group_id: proposal.group_id.clone(),
+            event_seq,
+            proposal_id: proposal.proposal_id.clone(),
+            depositor: deposit.depositor,
+            amount,
+            slashed,
+            flagged: deposit.flagged,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Start a proposal co-owned by several groups, seeded with the calling group's own
+    // proposal. `required_passes` is how many participants must pass it (0 means all).
+    #[cfg(feature = "joint-proposals")]
+    pub fn create_joint_proposal(
+        ctx: Context<CreateJointProposal>,
+        joint_id: String,
+        required_passes: u8,
+    ) -> Result<()> {
+        require!(
+            !joint_id.is_empty() && joint_id.len() <= MAX_JOINT_ID_LEN,
+            DaoError::InvalidJointId
+        );
+        require!(
+            required_passes as usize <= MAX_JOINT_GROUPS,
+            DaoError::InvalidRequiredPasses
+        );
+        let proposal = &ctx.accounts.proposal;
+        require!(
+            matches!(
+                proposal.status,
+                ProposalStatus::Draft | ProposalStatus::Active
+            ),
+            DaoError::InvalidProposalStatus
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        let joint_proposal = &mut ctx.accounts.joint_proposal;
+        joint_proposal.joint_id = joint_id.clone();
+        joint_proposal.creator = ctx.accounts.authority.key();
+        joint_proposal.participants = vec![JointParticipant {
+            group: ctx.accounts.group.key(),
+            proposal: proposal.key(),
+        }];
+        joint_proposal.required_passes = required_passes;
+        joint_proposal.status = ProposalStatus::Active;
+        joint_proposal.created_at = now;
+        joint_proposal.resolved_at = 0;
+        joint_proposal.bump = ctx.bumps.joint_proposal;
+
+        let group = &mut ctx.accounts.group;
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(JointProposalCreatedEvent {
+            group_id: group.group_id.clone(),
+            event_seq,
+            joint_id,
+            joint_proposal: joint_proposal.key(),
+            proposal_id: proposal.proposal_id.clone(),
+            required_passes,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Add another group to a joint proposal; its members vote on the group's own proposal
+    #[cfg(feature = "joint-proposals")]
+    pub fn join_joint_proposal(ctx: Context<JoinJointProposal>) -> Result<()> {
+        let proposal = &ctx.accounts.proposal;
+        require!(
+            matches!(
+                proposal.status,
+                ProposalStatus::Draft | ProposalStatus::Active
+            ),
+            DaoError::InvalidProposalStatus
+        );
+
+        let group_key = ctx.accounts.group.key();
+        let joint_proposal = &mut ctx.accounts.joint_proposal;
+        require!(
+            joint_proposal.status == ProposalStatus::Active,
+            DaoError::InvalidProposalStatus
+        );
+        require!(
+            !joint_proposal
+                .participants
+                .iter()
+                .any(|p| p.group == group_key || p.proposal == proposal.key()),
+            DaoError::AlreadyParticipating
+        );
+        require!(
+            joint_proposal.participants.len() < MAX_JOINT_GROUPS,
+            DaoError::JointProposalFull
+        );
+        joint_proposal.participants.push(JointParticipant {
+            group: group_key,
+            proposal: proposal.key(),
+        });
+
+        let group = &mut ctx.accounts.group;
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(JointProposalJoinedEvent {
+            group_id: group.group_id.clone(),
+            event_seq,
+            joint_id: joint_proposal.joint_id.clone(),
+            proposal_id: proposal.proposal_id.clone(),
+            participant_count: joint_proposal.participants.len() as u8,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Permissionless: once every participant's proposal is settled, record whether enough
+    // of them passed. Remaining accounts hold one entry per participant, in order: its
+    // ProposalResult, or the proposal itself if it was cancelled (counts as not passed).
+    #[cfg(feature = "joint-proposals")]
+    pub fn resolve_joint_proposal<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ResolveJointProposal<'info>>,
+    ) -> Result<()> {
+        let joint_proposal = &mut ctx.accounts.joint_proposal;
+        require!(
+            joint_proposal.status == ProposalStatus::Active,
+            DaoError::ProposalAlreadyFinalized
+        );
+        let participant_count = joint_proposal.participants.len();
+        require!(participant_count >= 2, DaoError::NotEnoughJointParticipants);
+        require!(
+            ctx.remaining_accounts.len() == participant_count,
+            DaoError::JointResultMismatch
+        );
+
+        let mut passes = 0u8;
+        for (participant, account) in joint_proposal
+            .participants
+            .iter()
+            .zip(ctx.remaining_accounts)
+        {
+            if joint_participant_passed(participant, account)? {
+                passes += 1;
+            }
+        }
+
+        // Enough passes to clear the bar succeed; a bar above the participant count can't
+        let required = match joint_proposal.required_passes {
+            0 => participant_count as u8,
+            n => n,
+        };
+        let now = Clock::get()?.unix_timestamp;
+        joint_proposal.status = if passes >= required {
+            ProposalStatus::Succeeded
+        } else {
+            ProposalStatus::Defeated
+        };
+        joint_proposal.resolved_at = now;
+
+        emit!(JointProposalResolvedEvent {
+            event_seq: next_event_seq(&mut ctx.accounts.dao_registry.event_seq)?,
+            joint_id: joint_proposal.joint_id.clone(),
+            status: joint_proposal.status,
+            passes,
+            required_passes: required,
+            participant_count: participant_count as u8,
+            resolved_by: ctx.accounts.resolver.key(),
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Hand governance of this program's upgrades to a group. The registry authority does
+    // this once; afterwards the program's upgrade authority should be moved to the group's
+    // treasury PDA, which can then only sign upgrades approved by that group's proposals.
+    #[cfg(feature = "treasury")]
+    pub fn initialize_program_governance(
+        ctx: Context<InitializeProgramGovernance>,
+        timelock_secs: i64,
+    ) -> Result<()> {
+        require!(
+            (MIN_UPGRADE_TIMELOCK_SECS..=MAX_UPGRADE_TIMELOCK_SECS).contains(&timelock_secs),
+            DaoError::InvalidTimelock
+        );
+
+        let governance = &mut ctx.accounts.program_governance;
+        governance.group = ctx.accounts.group.key();
+        governance.treasury = ctx.accounts.treasury.key();
+        governance.timelock_secs = timelock_secs;
+        governance.upgrade_count = 0;
+        governance.bump = ctx.bumps.program_governance;
+
+        emit!(ProgramGovernanceInitializedEvent {
+            event_seq: next_event_seq(&mut ctx.accounts.dao_registry.event_seq)?,
+            group_id: ctx.accounts.group.group_id.clone(),
+            treasury: governance.treasury,
+            timelock_secs,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Attach a program upgrade to one of the governing group's proposals. It can run
+    // only if the proposal is finalized with `approve_choice` winning, and only after the
+    // timelock. The buffer's authority must already be the treasury PDA.
+    #[cfg(feature = "treasury")]
+    pub fn propose_program_upgrade(
+        ctx: Context<ProposeProgramUpgrade>,
+        approve_choice: u8,
+    ) -> Result<()> {
+        let proposal = &ctx.accounts.proposal;
+        require!(
+            matches!(
+                proposal.status,
+                ProposalStatus::Draft | ProposalStatus::Active
+            ),
+            DaoError::InvalidProposalStatus
+        );
+        require!(
+            (approve_choice as usize) < proposal.choices.len(),
+            DaoError::InvalidChoice
+        );
+
+        let upgrade = &mut ctx.accounts.upgrade_proposal;
+        upgrade.proposal = proposal.key();
+        upgrade.buffer = ctx.accounts.buffer.key();
+        upgrade.approve_choice = approve_choice;
+        upgrade.eta = 0;
+        upgrade.executed_at = 0;
+        upgrade.bump = ctx.bumps.upgrade_proposal;
+
+        let group = &mut ctx.accounts.group;
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(ProgramUpgradeProposedEvent {
+            group_id: group.group_id.clone(),
+            event_seq,
+            proposal_id: proposal.proposal_id.clone(),
+            buffer: upgrade.buffer,
+            approve_choice,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Permissionless: start the timelock once the proposal's result approves the upgrade
+    #[cfg(feature = "treasury")]
+    pub fn queue_program_upgrade(ctx: Context<QueueProgramUpgrade>) -> Result<()> {
+        let result = &ctx.accounts.proposal_result;
+        let upgrade = &mut ctx.accounts.upgrade_proposal;
+        require!(upgrade.eta == 0, DaoError::UpgradeAlreadyQueued);
+        require!(
+            result.status == ProposalStatus::Succeeded
+                && result.winning_choice == Some(upgrade.approve_choice),
+            DaoError::UpgradeNotApproved
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        upgrade.eta = now
+            .checked_add(ctx.accounts.program_governance.timelock_secs)
+            .ok_or(DaoError::InvalidTimelock)?;
+
+        let group = &mut ctx.accounts.group;
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(ProgramUpgradeQueuedEvent {
+            group_id: group.group_id.clone(),
+            event_seq,
+            proposal_id: result.proposal_id.clone(),
+            buffer: upgrade.buffer,
+            eta: upgrade.eta,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Permissionless: once the timelock has passed, upgrade the program from the approved
+    // buffer, signing as the treasury PDA, and mark the proposal executed
+    #[cfg(feature = "treasury")]
+    pub fn execute_program_upgrade(ctx: Context<ExecuteProgramUpgrade>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let upgrade = &mut ctx.accounts.upgrade_proposal;
+        require!(upgrade.eta != 0, DaoError::UpgradeNotQueued);
+        require!(upgrade.executed_at == 0, DaoError::UpgradeAlreadyExecuted);
+        require!(now >= upgrade.eta, DaoError::TimelockNotElapsed);
+        require!(
+            ctx.accounts.proposal.status == ProposalStatus::Succeeded,
+            DaoError::InvalidProposalStatus
+        );
+
+        let group_key = ctx.accounts.group.key();
+        let treasury_seeds: &[&[u8]] = &[b"treasury", group_key.as_ref(), &[ctx.bumps.treasury]];
+        anchor_lang::solana_program::program::invoke_signed(
+            &anchor_lang::solana_program::bpf_loader_upgradeable::upgrade(
+                &crate::ID,
+                &upgrade.buffer,
+                &ctx.accounts.treasury.key(),
+                &ctx.accounts.spill.key(),
+            ),
+            &[
+                ctx.accounts.program_data.to_account_info(),
+                ctx.accounts.program.to_account_info(),
+                ctx.accounts.buffer.to_account_info(),
+                ctx.accounts.spill.to_account_info(),
+                ctx.accounts.rent.to_account_info(),
+                ctx.accounts.clock.to_account_info(),
+                ctx.accounts.treasury.to_account_info(),
+            ],
+            &[treasury_seeds],
+        )?;
+
+        upgrade.executed_at = now;
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.status = ProposalStatus::Executed;
+        let governance = &mut ctx.accounts.program_governance;
+        governance.upgrade_count = governance
+            .upgrade_count
+            .checked_add(1)
+            .ok_or(DaoError::TallyOverflow)?;
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(ProgramUpgradeExecutedEvent {
+            group_id: proposal.group_id.clone(),
+            event_seq,
+            proposal_id: proposal.proposal_id.clone(),
+            buffer: upgrade.buffer,
+            executed_by: ctx.accounts.executor.key(),
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Groups with a membership tree issue the member a compressed NFT credential instead
+    // of listing them, so the group account doesn't grow; the Bubblegum accounts follow
+    // as remaining accounts (see mint_membership_credential)
+    pub fn add_group_member<'info>(
+        ctx: Context<'_, '_, 'info, 'info, AddGroupMember<'info>>,
+        member: Pubkey,
+    ) -> Result<()> {
+        let group = &mut ctx.accounts.group;
+        let now = Clock::get()?.unix_timestamp;
+
+        // An unused member record would read as membership once created
+        require!(
+            ctx.accounts.member_record.is_some() != ctx.accounts.membership_tree.is_some(),
+            DaoError::MemberRecordRequired
+        );
+
+        if let Some(membership_tree) = ctx.accounts.membership_tree.as_mut() {
+            require_not_banned(&ctx.accounts.ban, now)?;
+            let (asset_id, nonce) = mint_membership_credential(
+                membership_tree,
+                &group.key(),
+                member,
+                &ctx.accounts.authority,
+                &ctx.accounts.system_program,
+                ctx.remaining_accounts,
+            )?;
+            let event_seq = next_event_seq(&mut group.event_seq)?;
+            emit!(MembershipCredentialIssuedEvent {
+                group_id: group.group_id.clone(),
+                event_seq,
+                member,
+                asset_id,
+                nonce,
+                timestamp: now,
+            });
+            let event_seq = next_event_seq(&mut group.event_seq)?;
+            emit!(MemberAddedEvent {
+                group_id: group.group_id.clone(),
+                event_seq,
+                member,
+                timestamp: now,
+            });
+        } else if let Some(member_record) = ctx.accounts.member_record.as_mut() {
+            let bump = ctx
+                .bumps
+                .member_record
+                .ok_or(DaoError::MemberRecordRequired)?;
+            admit_member(group, member_record, member, &ctx.accounts.ban, bump, now)?;
+        }
+
+        Ok(())
+    }
+
+    // Closes the member's record, refunding its rent to the authority
+    pub fn remove_group_member(ctx: Context<RemoveGroupMember>, member: Pubkey) -> Result<()> {
+        let group = &mut ctx.accounts.group;
+        group.member_count = group.member_count.saturating_sub(1);
+
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(MemberRemovedEvent {
+            group_id: group.group_id.clone(),
+            event_seq,
+            member,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Bar a wallet from the group until `expires_at` (0 for good): it is removed if listed,
+    // and can't join or vote while the ban is in force. Banning again replaces the reason
+    // and expiry.
+    #[cfg(feature = "bans")]
+    pub fn ban_member(
+        ctx: Context<BanMember>,
+        wallet: Pubkey,
+        reason: String,
+        expires_at: i64,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            reason.len() <= MAX_BAN_REASON_LEN && (expires_at == 0 || expires_at > now),
+            DaoError::InvalidBan
+        );
+        let group = &mut ctx.accounts.group;
+        let removed = match ctx.accounts.member_record.as_mut() {
+            Some(member_record) => {
+                member_record.close(ctx.accounts.authority.to_account_info())?;
+                group.member_count = group.member_count.saturating_sub(1);
+                true
+            }
+            None => false,
+        };
+
+        let ban = &mut ctx.accounts.ban;
+        ban.group = group.key();
+        ban.wallet = wallet;
+        ban.reason = reason.clone();
+        ban.banned_by = ctx.accounts.authority.key();
+        ban.banned_at = now;
+        ban.expires_at = expires_at;
+        ban.bump = ctx.bumps.ban;
+
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(MemberBannedEvent {
+            group_id: group.group_id.clone(),
+            event_seq,
+            wallet,
+            reason,
+            expires_at,
+            removed,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Lift a ban early, refunding its rent to the authority. Expired bans need no lifting
+    // but can be closed the same way.
+    #[cfg(feature = "bans")]
+    pub fn unban_member(ctx: Context<UnbanMember>, wallet: Pubkey) -> Result<()> {
+        let group = &mut ctx.accounts.group;
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(MemberUnbannedEvent {
+            group_id: group.group_id.clone(),
+            event_seq,
+            wallet,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Set the weight a listed member votes with on member-weighted proposals. Votes
+    // already cast keep the weight they were cast with.
+    pub fn set_member_weight(
+        ctx: Context<SetMemberWeight>,
+        member: Pubkey,
+        weight: u32,
+    ) -> Result<()> {
+        require!(weight > 0, DaoError::InvalidMemberWeight);
+        ctx.accounts.member_record.weight = weight;
+        let group = &mut ctx.accounts.group;
+
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(MemberWeightUpdatedEvent {
+            group_id: group.group_id.clone(),
+            event_seq,
+            member,
+            weight,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Accept votes on the group's proposals only from members: listed ones through the
+    // usual vote instructions, credential holders through vote_with_credential
+    pub fn set_members_only_voting(
+        ctx: Context<SetMembersOnlyVoting>,
+        enabled: bool,
+    ) -> Result<()> {
+        let group = &mut ctx.accounts.group;
+        group.members_only_voting = enabled;
+
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(MembersOnlyVotingUpdatedEvent {
+            group_id: group.group_id.clone(),
+            event_seq,
+            enabled,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Set or clear the group's membership dues: `amount` of `mint` per period, paid into
+    // the group treasury PDA. When `required_for_voting` is set, members whose dues have
+    // lapsed for longer than the grace period can't vote. SOL dues must at least cover
+    // the rent-exempt minimum, so the first payment can open the treasury account.
+    #[cfg(feature = "dues")]
+    pub fn set_dues_policy(ctx: Context<SetDuesPolicy>, dues: Option<DuesPolicy>) -> Result<()> {
+        if let Some(dues) = dues {
+            require!(
+                dues.amount > 0
+                    && dues.period_secs >= SECONDS_PER_DAY
+                    && (0..=dues.period_secs).contains(&dues.grace_secs),
+                DaoError::InvalidDuesPolicy
+            );
+            validate_token_mint(Some(dues.mint))?;
+            if dues.mint == native_mint() {
+                require!(
+                    dues.amount >= Rent::get()?.minimum_balance(0),
+                    DaoError::InvalidDuesPolicy
+                );
+            }
+        }
+
+        let group = &mut ctx.accounts.group;
+        group.dues = dues;
+
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(DuesPolicyUpdatedEvent {
+            group_id: group.group_id.clone(),
+            event_seq,
+            dues,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Pay dues for `periods` periods into the group treasury. Payments extend the paid-up
+    // date; a member who lapsed past the grace period starts again from now rather than
+    // paying for the time they were out.
+    #[cfg(feature = "dues")]
+    pub fn pay_dues(ctx: Context<PayDues>, periods: u32) -> Result<()> {
+        require!(periods > 0, DaoError::InvalidDuesPeriods);
+        let now = Clock::get()?.unix_timestamp;
+        let dues = ctx.accounts.group.dues.ok_or(DaoError::DuesNotConfigured)?;
+        let amount = dues
+            .amount
+            .checked_mul(periods as u64)
+            .ok_or(DaoError::TallyOverflow)?;
+
+        if dues.mint == native_mint() {
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.member.to_account_info(),
+                        to: ctx.accounts.treasury.to_account_info(),
+                    },
+                ),
+                amount,
+            )?;
+        } else {
+            let treasury_token_account = ctx
+                .accounts
+                .treasury_token_account
+                .as_ref()
+                .ok_or(DaoError::TokenAccountRequired)?;
+            let (owner, _) = spl_token_balance(dues.mint, treasury_token_account)?;
+            require_keys_eq!(
+                owner,
+                ctx.accounts.treasury.key(),
+                DaoError::InvalidTokenAccount
+            );
+            transfer_spl(
+                ctx.accounts
+                    .token_program
+                    .as_ref()
+                    .ok_or(DaoError::TokenAccountRequired)?,
+                ctx.accounts
+                    .member_token_account
+                    .as_ref()
+                    .ok_or(DaoError::TokenAccountRequired)?,
+                treasury_token_account,
+                &ctx.accounts.member.to_account_info(),
+                &[],
+                amount,
+            )?;
+        }
+
+        let record = &mut ctx.accounts.dues_record;
+        let start = if now <= record.paid_until.saturating_add(dues.grace_secs) {
+            record.paid_until
+        } else {
+            now
+        };
+        record.group = ctx.accounts.group.key();
+        record.member = ctx.accounts.member.key();
+        record.paid_until = (periods as i64)
+            .checked_mul(dues.period_secs)
+            .and_then(|secs| start.checked_add(secs))
+            .ok_or(DaoError::TallyOverflow)?;
+        record.last_paid_at = now;
+        record.overdue_flagged = false;
+        record.bump = ctx.bumps.dues_record;
+
+        let group = &mut ctx.accounts.group;
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(DuesPaidEvent {
+            group_id: group.group_id.clone(),
+            event_seq,
+            member: record.member,
+            mint: dues.mint,
+            amount,
+            periods,
+            paid_until: record.paid_until,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Permissionless: record that a member's dues are past due, once per lapse, so bots
+    // and indexers can remind them before the grace period runs out
+    #[cfg(feature = "dues")]
+    pub fn flag_overdue_dues(ctx: Context<FlagOverdueDues>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let dues = ctx.accounts.group.dues.ok_or(DaoError::DuesNotConfigured)?;
+        let record = &mut ctx.accounts.dues_record;
+        require!(
+            now > record.paid_until && !record.overdue_flagged,
+            DaoError::DuesNotOverdue
+        );
+        record.overdue_flagged = true;
+
+        let group = &mut ctx.accounts.group;
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(DuesOverdueEvent {
+            group_id: group.group_id.clone(),
+            event_seq,
+            member: record.member,
+            paid_until: record.paid_until,
+            grace_ends_at: record.paid_until.saturating_add(dues.grace_secs),
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Turn stake-to-join on at `price_lamports` per share, reprice it, or turn it off
+    // once nobody holds shares. While it is on, only shareholders can vote.
+    #[cfg(feature = "shares")]
+    pub fn set_stake_to_join(
+        ctx: Context<SetStakeToJoin>,
+        price_lamports: Option<u64>,
+    ) -> Result<()> {
+        let group = &mut ctx.accounts.group;
+        let (total_shares, unowned_shares) = group
+            .stake_to_join
+            .map_or((0, 0), |stake| (stake.total_shares, stake.unowned_shares));
+        group.stake_to_join = match price_lamports {
+            Some(price_lamports) => {
+                // The first purchase has to leave the treasury rent-exempt
+                require!(
+                    price_lamports >= Rent::get()?.minimum_balance(0),
+                    DaoError::InvalidSharePrice
+                );
+                Some(StakeToJoin {
+                    price_lamports,
+                    total_shares,
+                    unowned_shares,
+                })
+            }
+            None => {
+                require!(total_shares == unowned_shares, DaoError::SharesOutstanding);
+                None
+            }
+        };
+
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(StakeToJoinUpdatedEvent {
+            group_id: group.group_id.clone(),
+            event_seq,
+            price_lamports,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Buy `shares` shares into the group treasury, at the share price or the treasury's
+    // value per share if that is higher. New shares are locked for the ragequit guard
+    // window. A wallet that isn't listed yet joins the group with its first purchase,
+    // within the member cap; public groups only.
+    #[cfg(feature = "shares")]
+    pub fn stake_shares(ctx: Context<StakeShares>, shares: u64) -> Result<()> {
+        require!(shares > 0, DaoError::InvalidShareAmount);
+        let now = Clock::get()?.unix_timestamp;
+        let member = ctx.accounts.member.key();
+        let mut stake = ctx
+            .accounts
+            .group
+            .stake_to_join
+            .ok_or(DaoError::StakeToJoinDisabled)?;
+        let pool = share_pool_lamports(&ctx.accounts.treasury, &ctx.accounts.group)?;
+        if stake.total_shares == 0 && pool > 0 {
+            stake.unowned_shares = pool.div_ceil(stake.price_lamports);
+            stake.total_shares = stake.unowned_shares;
+        }
+        let lamports = stake.cost(pool, shares).ok_or(DaoError::TallyOverflow)?;
+
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.member.to_account_info(),
+                    to: ctx.accounts.treasury.to_account_info(),
+                },
+            ),
+            lamports,
+        )?;
+
+        if ctx.accounts.member_record.wallet == Pubkey::default() {
+            require!(
+                ctx.accounts.group.visibility == GroupVisibility::Public,
+                DaoError::GroupIsPrivate
+            );
+            admit_member(
+                &mut ctx.accounts.group,
+                &mut ctx.accounts.member_record,
+                member,
+                &ctx.accounts.ban,
+                ctx.bumps.member_record,
+                now,
+            )?;
+        }
+
+        let member_shares = &mut ctx.accounts.member_shares;
+        member_shares.group = ctx.accounts.group.key();
+        member_shares.member = member;
+        member_shares.shares = member_shares
+            .shares
+            .checked_add(shares)
+            .ok_or(DaoError::TallyOverflow)?;
+        member_shares.locked_until = member_shares
+            .locked_until
+            .max(now.saturating_add(RAGEQUIT_GUARD_SECS));
+        member_shares.bump = ctx.bumps.member_shares;
+        stake.total_shares = stake
+            .total_shares
+            .checked_add(shares)
+            .ok_or(DaoError::TallyOverflow)?;
+
+        let group = &mut ctx.accounts.group;
+        group.stake_to_join = Some(stake);
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(SharesStakedEvent {
+            group_id: group.group_id.clone(),
+            event_seq,
+            member,
+            shares,
+            lamports,
+            total_shares: stake.total_shares,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Burn `shares` shares for their pro-rata cut of the treasury: its SOL above the rent
+    // minimum and what open grants, streams and bounties are owed, plus each SPL balance passed in the remaining accounts as (treasury token
+    // account, member token account) pairs, treasury accounts in ascending address order.
+    // Shares stay locked for the guard window after they were bought, and after the end
+    // of any vote they were cast in.
+    #[cfg(feature = "shares")]
+    pub fn ragequit<'info>(
+        ctx: Context<'_, '_, 'info, 'info, Ragequit<'info>>,
+        shares: u64,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let member_shares = &mut ctx.accounts.member_shares;
+        require!(
+            shares > 0 && shares <= member_shares.shares,
+            DaoError::InvalidShareAmount
+        );
+        require!(now > member_shares.locked_until, DaoError::SharesLocked);
+        let mut stake = ctx
+            .accounts
+            .group
+            .stake_to_join
+            .ok_or(DaoError::StakeToJoinDisabled)?;
+        let cut =
+            |balance: u64| (balance as u128 * shares as u128 / stake.total_shares as u128) as u64;
+
+        let group_key = ctx.accounts.group.key();
+        let treasury_seeds: &[&[u8]] = &[b"treasury", group_key.as_ref(), &[ctx.bumps.treasury]];
+        let treasury = ctx.accounts.treasury.to_account_info();
+        let lamports = cut(share_pool_lamports(&treasury, &ctx.accounts.group)?);
+        if lamports > 0 {
+            system_program::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: treasury.clone(),
+                        to: ctx.accounts.member.to_account_info(),
+                    },
+                    &[treasury_seeds],
+                ),
+                lamports,
+            )?;
+        }
+
+        require!(
+            ctx.remaining_accounts.len().is_multiple_of(2),
+            DaoError::InvalidTokenAccount
+        );
+        let mut previous = None;
+        for pair in ctx.remaining_accounts.chunks(2) {
+            let (treasury_token_account, member_token_account) = (&pair[0], &pair[1]);
+            // Ascending order rules out paying the same balance twice
+            require!(
+                previous < Some(treasury_token_account.key()),
+                DaoError::InvalidTokenAccount
+            );
+            previous = Some(treasury_token_account.key());
+            let token_program = ctx
+                .accounts
+                .token_program
+                .as_ref()
+                .ok_or(DaoError::TokenAccountRequired)?;
+            let (owner, balance) = spl_token_holding(treasury_token_account)?;
+            require_keys_eq!(owner, treasury.key(), DaoError::InvalidTokenAccount);
+            let amount = cut(balance);
+            if amount > 0 {
+                // The token program checks both accounts hold the same mint
+                transfer_spl(
+                    token_program,
+                    treasury_token_account,
+                    member_token_account,
+                    &treasury,
+                    &[treasury_seeds],
+                    amount,
+                )?;
+            }
+        }
+
+        member_shares.shares -= shares;
+        stake.total_shares -= shares;
+        let group = &mut ctx.accounts.group;
+        group.stake_to_join = Some(stake);
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(RagequitEvent {
+            group_id: group.group_id.clone(),
+            event_seq,
+            member: member_shares.member,
+            shares,
+            lamports,
+            token_accounts: (ctx.remaining_accounts.len() / 2) as u8,
+            remaining_shares: member_shares.shares,
+            total_shares: stake.total_shares,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Cap the number of listed members (0 lifts the cap) and choose whether the group is
+    // public or private. The cap can't be set below the current member count, and like
+    // member_count it leaves out credential holders.
+    pub fn set_membership_policy(
+        ctx: Context<SetMembershipPolicy>,
+        max_members: u32,
+        visibility: GroupVisibility,
+    ) -> Result<()> {
+        let group = &mut ctx.accounts.group;
+        require!(
+            max_members == 0 || max_members >= group.member_count,
+            DaoError::InvalidMemberCap
+        );
+        group.max_members = max_members;
+        group.visibility = visibility;
+
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(MembershipPolicyUpdatedEvent {
+            group_id: group.group_id.clone(),
+            event_seq,
+            max_members,
+            visibility,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Cap how many proposals can be open for voting at once (0 lifts the cap), so a
+    // small group isn't flooded with votes. Creating or activating a proposal beyond it
+    // fails; proposals already open run their course if the cap is lowered.
+    pub fn set_max_active_proposals(
+        ctx: Context<SetMaxActiveProposals>,
+        max_active_proposals: u16,
+    ) -> Result<()> {
+        let group = &mut ctx.accounts.group;
+        group.max_active_proposals = max_active_proposals;
+
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(MaxActiveProposalsUpdatedEvent {
+            group_id: group.group_id.clone(),
+            event_seq,
+            max_active_proposals,
+            active_proposals: group.active_proposals,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Make each creator wait `cooldown_secs` after one proposal before creating the next
+    // (0 turns it off), so broadened proposal rights can't be used to flood the group.
+    // Emergencies are exempt.
+    pub fn set_proposal_cooldown(
+        ctx: Context<SetProposalCooldown>,
+        cooldown_secs: i64,
+    ) -> Result<()> {
+        require!(cooldown_secs >= 0, DaoError::InvalidProposalCooldown);
+        let group = &mut ctx.accounts.group;
+        group.proposal_cooldown_secs = cooldown_secs;
+
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(ProposalCooldownUpdatedEvent {
+            group_id: group.group_id.clone(),
+            event_seq,
+            cooldown_secs,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Choose what finalization does when the top choices tie
+    pub fn set_tie_break(ctx: Context<SetProposalCooldown>, tie_break: TieBreak) -> Result<()> {
+        if let TieBreak::Revote { window_secs } = tie_break {
+            require!(
+                window_secs > 0 && window_secs <= MAX_TIE_REVOTE_SECS,
+                DaoError::InvalidTieBreak
+            );
+        }
+        let group = &mut ctx.accounts.group;
+        group.tie_break = tie_break;
+
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(TieBreakUpdatedEvent {
+            group_id: group.group_id.clone(),
+            event_seq,
+            tie_break,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Register a Bubblegum tree for the group's membership credentials. The caller
+    // allocates `merkle_tree` for the account compression program beforehand; this
+    // creates its Bubblegum tree config with the membership tree PDA as the only minter.
+    #[cfg(feature = "cnft-membership")]
+    pub fn init_membership_tree(
+        ctx: Context<InitMembershipTree>,
+        max_depth: u32,
+        max_buffer_size: u32,
+    ) -> Result<()> {
+        let group_key = ctx.accounts.group.key();
+        let signer_seeds: &[&[u8]] = &[
+            b"membership_tree",
+            group_key.as_ref(),
+            &[ctx.bumps.membership_tree],
+        ];
+
+        let mut data = BUBBLEGUM_CREATE_TREE_CONFIG_DISCRIMINATOR.to_vec();
+        (max_depth, max_buffer_size, Some(false)).serialize(&mut data)?;
+        invoke_signed(
+            &Instruction {
+                program_id: BUBBLEGUM_PROGRAM_ID,
+                accounts: vec![
+                    AccountMeta::new(ctx.accounts.tree_config.key(), false),
+                    AccountMeta::new(ctx.accounts.merkle_tree.key(), false),
+                    AccountMeta::new(ctx.accounts.authority.key(), true),
+                    AccountMeta::new_readonly(ctx.accounts.membership_tree.key(), true),
+                    AccountMeta::new_readonly(NOOP_PROGRAM_ID, false),
+                    AccountMeta::new_readonly(ACCOUNT_COMPRESSION_PROGRAM_ID, false),
+                    AccountMeta::new_readonly(system_program::ID, false),
+                ],
+                data,
+            },
+            &[
+                ctx.accounts.tree_config.to_account_info(),
+                ctx.accounts.merkle_tree.to_account_info(),
+                ctx.accounts.authority.to_account_info(),
+                ctx.accounts.membership_tree.to_account_info(),
+                ctx.accounts.log_wrapper.to_account_info(),
+                ctx.accounts.compression_program.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                ctx.accounts.bubblegum_program.to_account_info(),
+            ],
+            &[signer_seeds],
+        )?;
+
+        let membership_tree = &mut ctx.accounts.membership_tree;
+        membership_tree.group = group_key;
+        membership_tree.merkle_tree = ctx.accounts.merkle_tree.key();
+        membership_tree.credentials_issued = 0;
+        membership_tree.bump = ctx.bumps.membership_tree;
+
+        let group = &mut ctx.accounts.group;
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(MembershipTreeCreatedEvent {
+            group_id: group.group_id.clone(),
+            event_seq,
+            merkle_tree: membership_tree.merkle_tree,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Vote as a credential holder. The voter proves the leaf of their membership cNFT
+    // (minted to them, still delegated to the membership tree) against a recent root of
+    // the tree; the proof nodes follow as remaining accounts. Weight and the other rules
+    // are those of vote_on_proposal, without linked wallets or price weighting.
+    #[cfg(feature = "cnft-membership")]
+    pub fn vote_with_credential<'info>(
+        ctx: Context<'_, '_, 'info, 'info, VoteWithCredential<'info>>,
+        choice_index: u8,
+        disclosure: Option<String>,
+        nonce: u64,
+        root: [u8; 32],
+    ) -> Result<()> {
+        validate_disclosure(&disclosure)?;
+        let proposal = &mut ctx.accounts.proposal;
+        let clock = Clock::get()?;
+        let current_time = clock.unix_timestamp;
+        let voter_key = ctx.accounts.voter.key();
+        require_can_vote(proposal, &voter_key, choice_index, &clock)?;
+        require_not_banned(&ctx.accounts.ban, current_time)?;
+        require_dues_current(
+            &ctx.accounts.group,
+            ctx.accounts.dues_record.as_ref(),
+            current_time,
+        )?;
+        lock_voted_shares(
+            &ctx.accounts.group,
+            ctx.accounts.member_shares.as_mut(),
+            proposal.voting_end,
+        )?;
+        require!(
+            !proposal.price_weighted,
+            DaoError::PriceWeightingUnsupported
+        );
+        // Credential holders have no member entry to carry a weight
+        require!(
+            !proposal.member_weighted,
+            DaoError::MemberWeightingUnsupported
+        );
+        require!(!proposal.split_voting, DaoError::SplitVotingUnsupported);
+        require!(
+            proposal.deposit_voting.is_none(),
+            DaoError::DepositVotingUnsupported
+        );
+
+        let leaf = membership_credential_leaf(
+            &ctx.accounts.membership_tree,
+            &ctx.accounts.group.key(),
+            &voter_key,
+            nonce,
+        );
+        let index = u32::try_from(nonce).map_err(|_| DaoError::InvalidMembershipCredential)?;
+        let mut data = ACCOUNT_COMPRESSION_VERIFY_LEAF_DISCRIMINATOR.to_vec();
+        (root, leaf, index).serialize(&mut data)?;
+        let mut accounts = vec![AccountMeta::new_readonly(
+            ctx.accounts.merkle_tree.key(),
+            false,
+        )];
+        accounts.extend(
+            ctx.remaining_accounts
+                .iter()
+                .map(|node| AccountMeta::new_readonly(node.key(), false)),
+        );
+        let mut infos = vec![ctx.accounts.merkle_tree.to_account_info()];
+        infos.extend(ctx.remaining_accounts.iter().cloned());
+        infos.push(ctx.accounts.compression_program.to_account_info());
+        invoke(
+            &Instruction {
+                program_id: ACCOUNT_COMPRESSION_PROGRAM_ID,
+                accounts,
+                data,
+            },
+            &infos,
+        )
+        .map_err(|_| DaoError::InvalidMembershipCredential)?;
+
+        let vote_weight = compute_vote_weight(
+            proposal.token_mint,
+            &ctx.accounts.voter,
+            &ctx.accounts.voter_token_account,
+        )?;
+        let vote_weight = proposal.decayed_weight(vote_weight, &clock);
+        require!(vote_weight > 0, DaoError::NoVotingPower);
+
+        add_to_tally(
+            &mut proposal.choice_votes[choice_index as usize],
+            vote_weight,
+        )?;
+        proposal.voters.push(VoterInfo {
+            voter: voter_key,
+            choice: choice_index,
+            vote_weight,
+            timestamp: current_time,
+            disclosure: disclosure.clone(),
+        });
+
+        ctx.accounts.participation.record_vote(
+            ctx.accounts.group.key(),
+            voter_key,
+            ctx.bumps.participation,
+            proposal.index,
+            current_time,
+        )?;
+        ctx.accounts.turnout.record_vote(
+            proposal.key(),
+            ctx.bumps.turnout,
+            &proposal.choice_votes,
+            proposal.voting_start,
+            current_time,
+        )?;
+
+        let event_seq = next_event_seq(&mut ctx.accounts.group.event_seq)?;
+        emit!(VoteCastEvent {
+            group_id: proposal.group_id.clone(),
+            event_seq,
+            proposal_id: proposal.proposal_id.clone(),
+            voter: voter_key,
+            choice: choice_index,
+            vote_weight,
+            timestamp: current_time,
+            disclosure,
+            allocations: None,
+        });
+
+        Ok(())
+    }
+
+    // Burn a member's credential, which the membership tree can do as its delegate. The
+    // proof nodes for the leaf follow as remaining accounts.
+    #[cfg(feature = "cnft-membership")]
+    pub fn revoke_membership_credential<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RevokeMembershipCredential<'info>>,
+        nonce: u64,
+        root: [u8; 32],
+    ) -> Result<()> {
+        let group_key = ctx.accounts.group.key();
+        let membership_tree = &ctx.accounts.membership_tree;
+        let member = ctx.accounts.member.key();
+        let index = u32::try_from(nonce).map_err(|_| DaoError::InvalidMembershipCredential)?;
+
+        let mut data = BUBBLEGUM_BURN_DISCRIMINATOR.to_vec();
+        (
+            root,
+            membership_credential_data_hash(&group_key, &member),
+            membership_credential_creator_hash(),
+            nonce,
+            index,
+        )
+            .serialize(&mut data)?;
+        let mut accounts = vec![
+            AccountMeta::new_readonly(ctx.accounts.tree_config.key(), false),
+            AccountMeta::new_readonly(member, false),
+            AccountMeta::new_readonly(membership_tree.key(), true),
+            AccountMeta::new(ctx.accounts.merkle_tree.key(), false),
+            AccountMeta::new_readonly(NOOP_PROGRAM_ID, false),
+            AccountMeta::new_readonly(ACCOUNT_COMPRESSION_PROGRAM_ID, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ];
+        accounts.extend(
+            ctx.remaining_accounts
+                .iter()
+                .map(|node| AccountMeta::new_readonly(node.key(), false)),
+        );
+        let mut infos = vec![
+            ctx.accounts.tree_config.to_account_info(),
+            ctx.accounts.member.to_account_info(),
+            membership_tree.to_account_info(),
+            ctx.accounts.merkle_tree.to_account_info(),
+            ctx.accounts.log_wrapper.to_account_info(),
+            ctx.accounts.compression_program.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+        ];
+        infos.extend(ctx.remaining_accounts.iter().cloned());
+        infos.push(ctx.accounts.bubblegum_program.to_account_info());
+        invoke_signed(
+            &Instruction {
+                program_id: BUBBLEGUM_PROGRAM_ID,
+                accounts,
+                data,
+            },
+            &infos,
+            &[&[
+                b"membership_tree",
+                group_key.as_ref(),
+                &[membership_tree.bump],
+            ]],
+        )?;
+
+        let group = &mut ctx.accounts.group;
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(MemberRemovedEvent {
+            group_id: group.group_id.clone(),
+            event_seq,
+            member,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Let wallets join the group on their own by proving they hold the membership token
+    // or an NFT of a verified collection. Replaces any earlier requirement.
+    #[cfg(feature = "token-gated-join")]
+    pub fn set_membership_gate(
+        ctx: Context<SetMembershipGate>,
+        requirement: MembershipRequirement,
+    ) -> Result<()> {
+        if let MembershipRequirement::Token { min_amount, .. } = requirement {
+            require!(min_amount > 0, DaoError::InvalidMembershipGate);
+        }
+
+        let membership_gate = &mut ctx.accounts.membership_gate;
+        membership_gate.group = ctx.accounts.group.key();
+        membership_gate.requirement = requirement.clone();
+        membership_gate.bump = ctx.bumps.membership_gate;
+
+        let group = &mut ctx.accounts.group;
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(MembershipGateSetEvent {
+            group_id: group.group_id.clone(),
+            event_seq,
+            requirement: Some(requirement),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Close the gate, returning to authority-only membership
+    #[cfg(feature = "token-gated-join")]
+    pub fn clear_membership_gate(ctx: Context<ClearMembershipGate>) -> Result<()> {
+        let group = &mut ctx.accounts.group;
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(MembershipGateSetEvent {
+            group_id: group.group_id.clone(),
+            event_seq,
+            requirement: None,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Join a gated group without the authority. `holding` is the joiner's token account
+    // for the membership token or the NFT; collection gates also take the NFT's Metaplex
+    // metadata account. The joiner pays for their member record. Public groups only.
+    #[cfg(feature = "token-gated-join")]
+    pub fn join_group(ctx: Context<JoinGroup>) -> Result<()> {
+        require!(
+            ctx.accounts.group.visibility == GroupVisibility::Public,
+            DaoError::GroupIsPrivate
+        );
+        let joiner = ctx.accounts.joiner.key();
+        require_membership_requirement(
+            &ctx.accounts.membership_gate.requirement,
+            &joiner,
+            &ctx.accounts.holding,
+            ctx.accounts.nft_metadata.as_ref(),
+        )?;
+
+        admit_member(
+            &mut ctx.accounts.group,
+            &mut ctx.accounts.member_record,
+            joiner,
+            &ctx.accounts.ban,
+            ctx.bumps.member_record,
+            Clock::get()?.unix_timestamp,
+        )
+    }
+
+    // Create an invite that lets up to `max_uses` wallets join the group with
+    // join_with_invite until `expires_at`. Only the hash of the code is stored on-chain
+    // (see invite_code_hash); the code itself is shared off-chain, e.g. in a Telegram chat.
+    pub fn create_invite(
+        ctx: Context<CreateInvite>,
+        code_hash: [u8; 32],
+        max_uses: u32,
+        expires_at: i64,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(max_uses > 0, DaoError::InvalidInvite);
+        require!(expires_at > now, DaoError::InvalidInvite);
+
+        let invite = &mut ctx.accounts.invite;
+        invite.group = ctx.accounts.group.key();
+        invite.code_hash = code_hash;
+        invite.created_by = ctx.accounts.authority.key();
+        invite.max_uses = max_uses;
+        invite.uses = 0;
+        invite.expires_at = expires_at;
+        invite.bump = ctx.bumps.invite;
+
+        let group = &mut ctx.accounts.group;
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(InviteCreatedEvent {
+            group_id: group.group_id.clone(),
+            event_seq,
+            invite: invite.key(),
+            max_uses,
+            expires_at,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Close an invite before it runs out, refunding its rent
+    pub fn revoke_invite(ctx: Context<RevokeInvite>) -> Result<()> {
+        let group = &mut ctx.accounts.group;
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(InviteRevokedEvent {
+            group_id: group.group_id.clone(),
+            event_seq,
+            invite: ctx.accounts.invite.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Join a group with an invite code, using up one of the invite's uses. The joiner
+    // pays for the member entry.
+    pub fn join_with_invite(ctx: Context<JoinWithInvite>, _code: String) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let invite = &mut ctx.accounts.invite;
+        require!(now < invite.expires_at, DaoError::InviteExpired);
+        require!(invite.uses < invite.max_uses, DaoError::InviteExhausted);
+
+        let joiner = ctx.accounts.joiner.key();
+        let group = &mut ctx.accounts.group;
+        invite.uses += 1;
+        admit_member(
+            group,
+            &mut ctx.accounts.member_record,
+            joiner,
+            &ctx.accounts.ban,
+            ctx.bumps.member_record,
+            now,
+        )?;
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(InviteRedeemedEvent {
+            group_id: group.group_id.clone(),
+            event_seq,
+            invite: invite.key(),
+            member: joiner,
+            uses_left: invite.max_uses - invite.uses,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Replace the group's council, a small set of members who can act together (e.g.
+    // flag spam) once `threshold` of them agree
+    pub fn set_council(
+        ctx: Context<SetCouncil>,
+        members: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        require!(
+            !members.is_empty()
+                && members.len() <= MAX_COUNCIL_MEMBERS
+                && threshold > 0
+                && threshold as usize <= members.len(),
+            DaoError::InvalidCouncil
+        );
+        for (i, member) in members.iter().enumerate() {
+            require!(!members[..i].contains(member), DaoError::InvalidCouncil);
+        }
+
+        let council = &mut ctx.accounts.council;
+        council.group = ctx.accounts.group.key();
+        council.members = members.clone();
+        council.threshold = threshold;
+        council.bump = ctx.bumps.council;
+
+        let group = &mut ctx.accounts.group;
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(CouncilUpdatedEvent {
+            group_id: group.group_id.clone(),
+            event_seq,
+            members,
+            threshold,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn create_user_account(ctx: Context<CreateUserAccount>, telegram_id: i64) -> Result<()> {
+        let user_account = &mut ctx.accounts.user_account;
+        user_account.version = USER_ACCOUNT_VERSION;
+        user_account.telegram_id = telegram_id;
+        user_account.wallet_pubkey = ctx.accounts.user_wallet.key();
+        user_account.created_at = Clock::get()?.unix_timestamp;
+        user_account.linked_wallets = Vec::new();
+        user_account.last_login = 0;
+        user_account.login_nonce = 0;
+        user_account.bump = ctx.bumps.user_account;
+
+        emit!(UserAccountCreatedEvent {
+            telegram_id,
+            wallet_pubkey: ctx.accounts.user_wallet.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn login_user(ctx: Context<LoginUser>, telegram_id: i64) -> Result<()> {
+        // This function can be used to verify/retrieve existing user account
+        let user_account = &ctx.accounts.user_account;
+
+        require!(
+            user_account.telegram_id == telegram_id,
+            DaoError::InvalidTelegramId
+        );
+
+        emit!(UserLoginEvent {
+            telegram_id,
+            wallet_pubkey: user_account.wallet_pubkey,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Challenge-based login: the transaction carries an ed25519 precompile instruction,
+    // right before this one, in which the primary or a linked wallet signs the login
+    // challenge for the account's current nonce. Nobody else needs to sign, so the bot
+    // can relay logins signed elsewhere; the nonce makes each signature single-use.
+    pub fn login_with_signature(ctx: Context<LoginWithSignature>, telegram_id: i64) -> Result<()> {
+        let user_account_key = ctx.accounts.user_account.key();
+        let user_account = &mut ctx.accounts.user_account;
+
+        let nonce = user_account.login_nonce;
+        let signer = verified_ed25519_signer(
+            &ctx.accounts.instructions_sysvar,
+            &login_message(&user_account_key, nonce),
+            DaoError::InvalidLoginSignature,
+        )?;
+        require!(
+            signer == user_account.wallet_pubkey || user_account.linked_wallets.contains(&signer),
+            DaoError::InvalidLoginSignature
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        user_account.login_nonce = nonce.checked_add(1).ok_or(DaoError::LoginNonceOverflow)?;
+        user_account.last_login = now;
+
+        emit!(UserAuthenticatedEvent {
+            telegram_id,
+            wallet: signer,
+            nonce,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Link another wallet to a user account; both the primary wallet and the wallet being
+    // linked must sign, so nobody can borrow someone else's balance
+    pub fn link_wallet(ctx: Context<LinkWallet>, _telegram_id: i64) -> Result<()> {
+        let wallet = ctx.accounts.wallet.key();
+        add_linked_wallet(&mut ctx.accounts.user_account, wallet)
+    }
+
+    // Link a wallet that signed the account's current link challenge instead of the
+    // transaction, for browser and mobile wallets that can only sign messages. The
+    // primary wallet still signs, and the challenge is spent like a login's.
+    pub fn link_wallet_with_signature(
+        ctx: Context<LinkWalletWithSignature>,
+        _telegram_id: i64,
+    ) -> Result<()> {
+        let user_account_key = ctx.accounts.user_account.key();
+        let user_account = &mut ctx.accounts.user_account;
+
+        let nonce = user_account.login_nonce;
+        let wallet = verified_ed25519_signer(
+            &ctx.accounts.instructions_sysvar,
+            &link_wallet_message(&user_account_key, nonce),
+            DaoError::InvalidLinkSignature,
+        )?;
+        user_account.login_nonce = nonce.checked_add(1).ok_or(DaoError::LoginNonceOverflow)?;
+
+        add_linked_wallet(user_account, wallet)
+    }
+
+    // Unlink a wallet; either the primary wallet or the linked wallet itself may sign
+    pub fn unlink_wallet(
+        ctx: Context<UnlinkWallet>,
+        _telegram_id: i64,
+        wallet: Pubkey,
+    ) -> Result<()> {
+        let user_account = &mut ctx.accounts.user_account;
+
+        let index = user_account
+            .linked_wallets
+            .iter()
+            .position(|w| *w == wallet)
+            .ok_or(DaoError::WalletNotLinked)?;
+        user_account.linked_wallets.remove(index);
+
+        emit!(WalletUnlinkedEvent {
+            telegram_id: user_account.telegram_id,
+            wallet,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Permissionless upgrades of accounts written under an older layout. Each rewrites
+    // the account in place at the current version, growing it if needed with rent from
+    // `payer`, so a layout change doesn't strand the data already on-chain.
+    pub fn migrate_group(ctx: Context<MigrateAccount>) -> Result<()> {
+        migrate_account(
+            ctx.accounts,
+            Group::DISCRIMINATOR,
+            GROUP_VERSION,
+            upgrade_group,
+        )
+    }
+
+    pub fn migrate_proposal(ctx: Context<MigrateAccount>) -> Result<()> {
+        migrate_account(
+            ctx.accounts,
+            Proposal::DISCRIMINATOR,
+            PROPOSAL_VERSION,
+            upgrade_proposal,
+        )
+    }
+
+    pub fn migrate_user_account(ctx: Context<MigrateAccount>) -> Result<()> {
+        migrate_account(
+            ctx.accounts,
+            UserAccount::DISCRIMINATOR,
+            USER_ACCOUNT_VERSION,
+            upgrade_user_account,
+        )
+    }
+
+    // Returns as many of the registry's groups from `start` on as fit in the return data;
+    // callers page through with the next start until they have `total`
+    pub fn get_all_groups(ctx: Context<GetAllGroups>, start: u32) -> Result<GroupPage> {
+        let groups = &ctx.accounts.dao_registry.groups;
+
+        msg!("DAO Registry has {} groups", groups.len());
+
+        Ok(group_page(groups, start as usize))
+    }
+
+    pub fn get_proposal_summary(ctx: Context<GetProposalSummary>) -> Result<ProposalSummary> {
+        Ok(ProposalSummary::of(&ctx.accounts.proposal))
+    }
+}
+
+// Account Structs
+// Protocol parameters tunable by the registry authority after deployment
+#[account]
+#[derive(InitSpace)]
+pub struct ProgramConfig {
+    pub paused: bool,
+    pub guardian: Option<Pubkey>, // may pause/unpause alongside the registry authority
+    pub max_groups: u32,          // groups the registry accepts
+    pub max_choices: u8,          // choices per proposal (capped by each layout's capacity)
+    pub fee_destination: Pubkey,  // receives protocol fees and reclaimed rent
+    pub updated_at: i64,
+    pub bump: u8,
+}
+
+// M-of-N signers holding the registry authority (seeds: "registry_multisig", registry)
+#[cfg(feature = "registry-multisig")]
+#[account]
+#[derive(InitSpace)]
+pub struct RegistryMultisig {
+    pub registry: Pubkey,
+    #[max_len(MAX_COUNCIL_MEMBERS)]
+    pub members: Vec<Pubkey>,
+    pub threshold: u8, // approvals needed to execute an action
+    pub action_count: u64,
+    pub bump: u8,
+}
+
+// A registry-level action awaiting multisig approval (seeds: "registry_action", multisig, index)
+#[cfg(feature = "registry-multisig")]
+#[account]
+#[derive(InitSpace)]
+pub struct RegistryActionProposal {
+    pub multisig: Pubkey,
+    pub index: u64,
+    pub action: RegistryAction,
+    pub proposer: Pubkey,
+    // Only approvers who are still members when it executes count
+    #[max_len(MAX_COUNCIL_MEMBERS)]
+    pub approvals: Vec<Pubkey>,
+    pub created_at: i64,
+    pub executed_at: i64, // 0 until executed
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq, InitSpace)]
+pub enum RegistryAction {
+    Pause,
+    Unpause,
+    UpdateProgramConfig {
+        max_groups: u32,
+        max_choices: u8,
+        fee_destination: Pubkey,
+        guardian: Option<Pubkey>,
+    },
+    SetRegistrationConfig {
+        open_registration: bool,
+        group_deposit_lamports: u64,
+        group_deposit_lock_days: u32,
+    },
+    // Change the multisig's own members and threshold
+    SetSigners {
+        #[max_len(MAX_COUNCIL_MEMBERS)]
+        members: Vec<Pubkey>,
+        threshold: u8,
+    },
+    // Migrate the registry to another authority, retiring this multisig
+    SetAuthority {
+        new_authority: Pubkey,
+    },
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct DaoRegistry {
+    pub authority: Pubkey,
+    // Seeds the registry's PDA with "dao_registry"; "" for the default registry
+    #[max_len(MAX_REGISTRY_NAME_LEN)]
+    pub name: String,
+    #[max_len(0)]
+    pub groups: Vec<GroupInfo>,
+    pub open_registration: bool,
+    pub group_deposit_lamports: u64,
+    pub group_deposit_lock_days: u32,
+    // Sequence number of the last registry-level event (config, pause, registration, slugs)
+    pub event_seq: u64,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Group {
+    // Layout version (GROUP_VERSION). It comes first so migrate_group can read it before
+    // knowing the rest of the layout.
+    pub version: u8,
+    #[max_len(50)]
+    pub group_id: String,
+    #[max_len(100)]
+    pub name: String,
+    #[max_len(500)]
+    pub description: String,
+    pub authority: Pubkey,
+    pub proposal_count: u64,
+    // Wallets with a Member account; credential holders aren't counted
+    pub member_count: u32,
+    pub created_at: i64,
+    pub deposit_lamports: u64,
+    pub deposit_unlock_at: i64,
+    #[max_len(32)]
+    pub slug: String,
+    // Off-chain long-form content (IPFS, Arweave or HTTPS)
+    #[max_len(MAX_METADATA_URI_LEN)]
+    pub metadata_uri: Option<String>,
+    // Sequence number of the last event emitted for this group
+    pub event_seq: u64,
+    // Flat fee paid into the group treasury PDA for each new proposal
+    pub proposal_fee_lamports: u64,
+    // Only members may vote: listed ones, or holders of a membership credential
+    pub members_only_voting: bool,
+    // Cap on member_count; 0 means no cap
+    pub max_members: u32,
+    pub visibility: GroupVisibility,
+    // Quorum, approval threshold and voting duration bounds for proposals
+    pub governance: GovernanceConfig,
+    // Recurring membership dues, tracked per wallet in DuesRecord
+    pub dues: Option<DuesPolicy>,
+    // Set when wallets join by buying shares into the treasury
+    pub stake_to_join: Option<StakeToJoin>,
+    // Cap on active_proposals; 0 means no cap
+    pub max_active_proposals: u16,
+    // Proposals open for voting: activated, and not yet finalized or cancelled
+    pub active_proposals: u16,
+    // Seconds a creator must wait after one proposal before the next; 0 means none
+    pub proposal_cooldown_secs: i64,
+    // Chat the group belongs to (a Telegram chat id), indexed by its ChatLink PDA
+    pub external_chat_id: Option<i64>,
+    // How finalization settles a tie for first
+    pub tie_break: TieBreak,
+    // Members who must co-sponsor a draft before it opens; 0 when drafts need none
+    pub sponsor_threshold: u8,
+    // DaoRegistry the group is listed in
+    pub registry: Pubkey,
+    // Deposit a proposal's creator must escrow before it opens for voting; 0 when none
+    pub proposal_deposit_lamports: u64,
+    // Treasury SOL promised to open grants, streams and bounties, which shares have no
+    // claim on
+    pub committed_lamports: u64,
+    pub bump: u8,
+}
+
+impl Group {
+    // Count a proposal opening for voting, within max_active_proposals
+    fn open_active_proposal(&mut self) -> Result<()> {
+        require!(
+            self.max_active_proposals == 0 || self.active_proposals < self.max_active_proposals,
+            DaoError::TooManyActiveProposals
+        );
+        self.active_proposals += 1;
+        Ok(())
+    }
+
+    // Saturating, for proposals opened before the count was kept
+    fn close_active_proposal(&mut self) {
+        self.active_proposals = self.active_proposals.saturating_sub(1);
+    }
+
+    // Promise `lamports` of the treasury to a grant, stream or bounty
+    #[cfg(any(feature = "grants", feature = "streams", feature = "bounties"))]
+    fn commit_treasury(&mut self, lamports: u64) -> Result<()> {
+        self.committed_lamports = self
+            .committed_lamports
+            .checked_add(lamports)
+            .ok_or(DaoError::TallyOverflow)?;
+        Ok(())
+    }
+
+    // Saturating, for grants, streams and bounties opened before commitments were kept
+    #[cfg(any(feature = "grants", feature = "streams", feature = "bounties"))]
+    fn release_treasury(&mut self, lamports: u64) {
+        self.committed_lamports = self.committed_lamports.saturating_sub(lamports);
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Proposal {
+    // Layout version (PROPOSAL_VERSION), first for the same reason as Group's
+    pub version: u8,
+    #[max_len(50)]
+    pub proposal_id: String,
+    #[max_len(50)]
+    pub group_id: String,
+    #[max_len(200)]
+    pub title: String,
+    #[max_len(1000)]
+    pub description: String,
+    #[max_len(MAX_CHOICES, MAX_CHOICE_LEN)]
+    pub choices: Vec<String>,
+    #[max_len(MAX_CHOICES)]
+    pub choice_votes: Vec<u64>,
+    pub voting_start: i64,
+    pub voting_end: i64,
+    pub token_mint: Option<Pubkey>,
+    pub creator: Pubkey,
+    #[max_len(0)]
+    pub voters: Vec<VoterInfo>,
+    pub created_at: i64,
+    pub index: u64,
+    // Off-chain long-form content: full text, images and discussion links
+    #[max_len(MAX_METADATA_URI_LEN)]
+    pub metadata_uri: Option<String>,
+    // Lifecycle stage; the tallies are final once Succeeded or Defeated
+    pub status: ProposalStatus,
+    // Lamports escrowed in this account for whoever finalizes it
+    pub finalize_bounty: u64,
+    // Non-counting ballots from non-members, one per head (see ShadowBallot)
+    #[max_len(MAX_CHOICES)]
+    pub shadow_votes: Vec<u32>,
+    // Set when ballots are collected off-chain: the wallet that submits their tally
+    pub offchain_aggregator: Option<Pubkey>,
+    // Off-chain ballots counted once the tally is settled; finalizing waits for it
+    pub offchain_voter_count: Option<u32>,
+    // Votes are weighed by oracle-priced holdings (see PriceWeighting)
+    pub price_weighted: bool,
+    // Votes count with the voter's member weight instead of one each
+    pub member_weighted: bool,
+    pub kind: ProposalKind,
+    // Raised by a council member: may vote for a shorter time but needs a supermajority
+    pub emergency: bool,
+    // Voters spread their weight over the choices (see set_split_voting)
+    pub split_voting: bool,
+    // Set when the window is kept in slots (see set_slot_window); voting_start and
+    // voting_end then only estimate it
+    pub slot_window: Option<SlotWindow>,
+    // Share of a vote's weight lost by voting at the very end of the window, shrinking
+    // linearly from nothing at the start (see set_weight_decay)
+    pub weight_decay_bps: u16,
+    // Set when votes count registrar deposits instead of balances (see set_deposit_voting)
+    pub deposit_voting: Option<DepositVoting>,
+    // Set on bicameral proposals: the council's own tally, which must also pass
+    pub council_chamber: Option<CouncilChamber>,
+    // Set when finalized: the choice that carried it, if any
+    pub winner: Option<u8>,
+    // The top choices tied when voting ended, whatever the tie-break made of it
+    pub tied: bool,
+    // Co-sponsors the draft needs before it can open, and how many it has
+    pub sponsors_needed: u8,
+    pub sponsor_count: u8,
+    pub bump: u8,
+}
+
+// The council chamber of a bicameral proposal, one vote per member of the council as it
+// was when the chamber was added
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq, InitSpace)]
+pub struct CouncilChamber {
+    #[max_len(MAX_COUNCIL_MEMBERS)]
+    pub members: Vec<Pubkey>,
+    pub threshold: u8, // council votes the winning choice needs
+    #[max_len(MAX_CHOICES)]
+    pub choice_votes: Vec<u32>,
+    pub voted: u8, // bit i is set once members[i] has voted
+}
+
+// Only deposits into `registrar` made at or before `cutoff` count on the proposal
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub struct DepositVoting {
+    pub registrar: Pubkey,
+    pub cutoff: i64,
+}
+
+// A voting window in slots, immune to cluster clock drift; both ends inclusive
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub struct SlotWindow {
+    pub start_slot: u64,
+    pub end_slot: u64,
+}
+
+impl Proposal {
+    // Whether a succeeded proposal still has its kind's execute instruction to run:
+    // elections always seat their winners, other kinds act only when choice 0 won
+    pub fn awaits_execution(&self) -> bool {
+        match self.kind {
+            ProposalKind::Text => false,
+            ProposalKind::Election(_) => true,
+            _ => self.winner == Some(0),
+        }
+    }
+
+    pub fn before_voting(&self, clock: &Clock) -> bool {
+        match self.slot_window {
+            Some(window) => clock.slot < window.start_slot,
+            None => clock.unix_timestamp < self.voting_start,
+        }
+    }
+
+    pub fn after_voting(&self, clock: &Clock) -> bool {
+        match self.slot_window {
+            Some(window) => clock.slot > window.end_slot,
+            None => clock.unix_timestamp > self.voting_end,
+        }
+    }
+
+    pub fn voting_open(&self, clock: &Clock) -> bool {
+        !self.before_voting(clock) && !self.after_voting(clock)
+    }
+
+    // How far through the voting window the clock is, from 0 to 10_000
+    pub fn voting_progress_bps(&self, clock: &Clock) -> u64 {
+        let (elapsed, duration) = match self.slot_window {
+            Some(window) => (
+                clock.slot.saturating_sub(window.start_slot),
+                window.end_slot - window.start_slot,
+            ),
+            None => (
+                clock
+                    .unix_timestamp
+                    .saturating_sub(self.voting_start)
+                    .max(0) as u64,
+                (self.voting_end - self.voting_start).max(0) as u64,
+            ),
+        };
+        if duration == 0 {
+            return 10_000;
+        }
+        (elapsed as u128 * 10_000 / duration as u128).min(10_000) as u64
+    }
+
+    // `weight` after the proposal's decay for a vote cast now
+    pub fn decayed_weight(&self, weight: u64, clock: &Clock) -> u64 {
+        let lost = weight as u128
+            * self.weight_decay_bps as u128
+            * self.voting_progress_bps(clock) as u128
+            / 100_000_000;
+        weight - lost as u64
+    }
+
+    // The window as unix timestamps, estimated when it is kept in slots
+    pub fn voting_window_timestamps(&self) -> (i64, i64) {
+        (self.voting_start, self.voting_end)
+    }
+
+    // The window as slots, estimated from the clock when it is kept in unix time
+    pub fn voting_window_slots(&self, clock: &Clock) -> (u64, u64) {
+        match self.slot_window {
+            Some(window) => (window.start_slot, window.end_slot),
+            None => (
+                estimated_slot_at(self.voting_start, clock),
+                estimated_slot_at(self.voting_end, clock),
+            ),
+        }
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct ProposalIndex {
+    pub group: Pubkey,
+    pub index: u64,
+    #[max_len(50)]
+    pub proposal_id: String,
+    pub pubkey: Pubkey,
+    pub created_at: i64,
+    pub large: bool, // true when `pubkey` is a ProposalLarge account
+    pub bump: u8,
+}
+
+// Fixed-layout proposal for many choices and long descriptions. Strings are stored in
+// zero-padded byte arrays so votes can update the tally without deserializing the text.
+#[cfg(feature = "large-proposals")]
+#[account(zero_copy)]
+#[repr(C)]
+pub struct ProposalLarge {
+    pub group: Pubkey,
+    pub creator: Pubkey,
+    pub token_mint: Pubkey,
+    pub voting_start: i64,
+    pub voting_end: i64,
+    pub created_at: i64,
+    pub index: u64,
+    pub voter_count: u64,
+    pub choice_votes: [u64; LARGE_MAX_CHOICES],
+    pub proposal_id: [u8; 64],
+    pub title: [u8; LARGE_TITLE_LEN],
+    pub description: [u8; LARGE_DESCRIPTION_LEN],
+    pub choices: [[u8; LARGE_CHOICE_LEN]; LARGE_MAX_CHOICES],
+    pub title_len: u16,
+    pub description_len: u16,
+    pub proposal_id_len: u8,
+    pub choice_count: u8,
+    pub has_token_mint: u8,
+    pub bump: u8,
+}
+
+#[cfg(feature = "large-proposals")]
+impl ProposalLarge {
+    pub fn proposal_id_str(&self) -> String {
+        String::from_utf8_lossy(&self.proposal_id[..self.proposal_id_len as usize]).into_owned()
+    }
+}
+
+#[cfg(feature = "large-proposals")]
+#[account]
+#[derive(InitSpace)]
+pub struct VoteRecord {
+    pub proposal: Pubkey,
+    pub voter: Pubkey,
+    pub choice: u8,
+    pub vote_weight: u64,
+    pub timestamp: i64,
+    #[max_len(MAX_DISCLOSURE_LEN)]
+    pub disclosure: Option<String>,
+    pub bump: u8,
+}
+
+// A member's activity in one group, kept up to date by proposal creation and voting so
+// reputation and leaderboards don't have to scan every proposal. Created on first use.
+#[account]
+#[derive(InitSpace)]
+pub struct Participation {
+    pub group: Pubkey,
+    pub member: Pubkey,
+    pub proposals_created: u32,
+    pub proposals_voted: u32,
+    // Run of consecutive group proposals (by index) voted on, ending at last_voted_index
+    pub current_streak: u32,
+    pub longest_streak: u32,
+    pub last_voted_index: u64,
+    pub last_active_at: i64,
+    // When the member last created a proposal, for the group's proposal cooldown
+    pub last_proposal_at: i64,
+    pub bump: u8,
+}
+
+// Turnout figures for one proposal, kept up to date by every counted vote so
+// participation dashboards don't have to read each ballot. Created by the first vote.
+#[account]
+#[derive(InitSpace)]
+pub struct TurnoutStats {
+    pub proposal: Pubkey,
+    pub unique_voters: u32,
+    pub total_weight: u64,
+    // Each choice's share of total_weight in basis points
+    #[max_len(LARGE_MAX_CHOICES)]
+    pub choice_bps: Vec<u16>,
+    // Votes cast on each day since voting_start; votes after MAX_TURNOUT_DAYS land in
+    // the last bucket
+    #[max_len(MAX_TURNOUT_DAYS)]
+    pub votes_per_day: Vec<u32>,
+    pub bump: u8,
+}
+
+// Immutable outcome of a finalized proposal, kept apart from the much larger Proposal
+// account so results stay verifiable on-chain even if that account is closed for rent
+#[account]
+#[derive(InitSpace)]
+pub struct ProposalResult {
+    pub proposal: Pubkey,
+    pub group: Pubkey,
+    #[max_len(50)]
+    pub proposal_id: String,
+    pub status: ProposalStatus, // Succeeded or Defeated
+    pub winning_choice: Option<u8>,
+    #[max_len(MAX_CHOICES)]
+    pub choice_votes: Vec<u64>,
+    pub voter_count: u32,
+    // Group members when the result was sealed, for turnout (voter_count / member_count)
+    pub member_count: u32,
+    pub finalized_at: i64,
+    pub bump: u8,
+}
+
+// Members who act for the group together, e.g. flagging spam proposals
+#[account]
+#[derive(InitSpace)]
+pub struct Council {
+    pub group: Pubkey,
+    #[max_len(MAX_COUNCIL_MEMBERS)]
+    pub members: Vec<Pubkey>,
+    pub threshold: u8, // members needed to act
+    pub bump: u8,
+}
+
+// A program called with the result once a proposal passes (seeds: "callback", proposal)
+#[cfg(feature = "callbacks")]
+#[account]
+#[derive(InitSpace)]
+pub struct ExecutionCallback {
+    pub proposal: Pubkey,
+    pub on_success_program: Pubkey,
+    // Leading instruction data for the callee, typically its instruction discriminator
+    #[max_len(MAX_CALLBACK_PAYLOAD_LEN)]
+    pub payload: Vec<u8>,
+    pub bump: u8,
+}
+
+// Programs a group's execution callbacks may target (seeds: "program_allowlist", group)
+#[cfg(feature = "callbacks")]
+#[account]
+#[derive(InitSpace)]
+pub struct ProgramAllowlist {
+    pub group: Pubkey,
+    #[max_len(MAX_ALLOWLISTED_PROGRAMS)]
+    pub programs: Vec<Pubkey>,
+    pub bump: u8,
+}
+
+// Appended to a callback's payload when it is called
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CallbackResult {
+    pub proposal: Pubkey,
+    pub group: Pubkey,
+    pub status: ProposalStatus,
+    pub winning_choice: u8,
+    pub choice_votes: Vec<u64>,
+}
+
+// Per-choice descriptions and links for a proposal's ballot, one entry per choice
+// (seeds: "choice_details", proposal)
+#[cfg(feature = "choice-details")]
+#[account]
+#[derive(InitSpace)]
+pub struct ChoiceDetails {
+    pub proposal: Pubkey,
+    #[max_len(MAX_CHOICES)]
+    pub details: Vec<ChoiceDetail>,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default, PartialEq, Eq, InitSpace)]
+pub struct ChoiceDetail {
+    #[max_len(MAX_CHOICE_DESCRIPTION_LEN)]
+    pub description: Option<String>,
+    #[max_len(MAX_METADATA_URI_LEN)]
+    pub uri: Option<String>,
+}
+
+// A grant opened by an approved grant proposal. Its funds stay in the group treasury
+// until each milestone is approved and its tranche claimed.
+// A distribution opened by an approved distribution proposal (seeds: "distribution",
+// proposal)
+#[cfg(feature = "token-distribution")]
+#[account]
+#[derive(InitSpace)]
+pub struct TokenDistribution {
+    pub group: Pubkey,
+    pub proposal: Pubkey,
+    pub mint: Pubkey,
+    pub source: DistributionSource,
+    pub merkle_root: [u8; 32],
+    pub total_amount: u64,
+    pub claimed_amount: u64,
+    pub claim_count: u32,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+// A recipient's claim on a distribution (seeds: "distribution_claim", distribution,
+// recipient)
+#[cfg(feature = "token-distribution")]
+#[account]
+#[derive(InitSpace)]
+pub struct DistributionClaim {
+    pub distribution: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub claimed_at: i64,
+    pub bump: u8,
+}
+
+#[cfg(feature = "grants")]
+#[account]
+#[derive(InitSpace)]
+pub struct Grant {
+    pub group: Pubkey,
+    pub proposal: Pubkey,
+    pub recipient: Pubkey,
+    pub approval: MilestoneApproval,
+    #[max_len(MAX_GRANT_MILESTONES)]
+    pub milestones: Vec<Milestone>,
+    pub released: u64, // lamports claimed so far
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+#[cfg(feature = "grants")]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq, InitSpace)]
+pub struct Milestone {
+    pub amount: u64,
+    pub approvals: u32,
+    pub approved_at: i64, // 0 until approved
+    pub claimed: bool,
+}
+
+// A payment stream opened by an approved stream proposal. Its lamports stay in the
+// group treasury until the recipient withdraws what has vested.
+#[cfg(feature = "streams")]
+#[account]
+#[derive(InitSpace)]
+pub struct Stream {
+    pub group: Pubkey,
+    pub proposal: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub start: i64,
+    pub cliff: i64,
+    pub end: i64,
+    pub withdrawn: u64,
+    pub bump: u8,
+}
+
+#[cfg(feature = "streams")]
+impl Stream {
+    // Lamports vested by `now`: nothing before the cliff, then the elapsed share of the
+    // whole amount, measured from the start
+    pub fn vested_at(&self, now: i64) -> u64 {
+        if now < self.cliff {
+            return 0;
+        }
+        if now >= self.end {
+            return self.amount;
+        }
+        let elapsed = (now - self.start) as u128;
+        let duration = (self.end - self.start) as u128;
+        (self.amount as u128 * elapsed / duration) as u64
+    }
+}
+
+// A bounty on a group's board, posted by the group authority or a passed proposal
+#[cfg(feature = "bounties")]
+#[account]
+#[derive(InitSpace)]
+pub struct Bounty {
+    pub group: Pubkey,
+    #[max_len(MAX_BOUNTY_ID_LEN)]
+    pub bounty_id: String,
+    #[max_len(MAX_METADATA_URI_LEN)]
+    pub description_uri: String,
+    pub reward: u64, // lamports from the group treasury
+    pub status: BountyStatus,
+    pub posted_by: Pubkey, // group authority, or the proposal that posted it
+    pub hunter: Option<Pubkey>,
+    #[max_len(MAX_METADATA_URI_LEN)]
+    pub submission_uri: Option<String>,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+// Open -> Claimed -> Submitted -> Paid, or Cancelled by the authority before payment
+#[cfg(feature = "bounties")]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum BountyStatus {
+    Open,
+    Claimed,
+    Submitted,
+    Paid,
+    Cancelled,
+}
+
+// Receipt of one approver's sign-off on a grant milestone; its existence blocks a
+// second one
+#[cfg(feature = "grants")]
+#[account]
+#[derive(InitSpace)]
+pub struct MilestoneSignoff {
+    pub grant: Pubkey,
+    pub milestone: u8,
+    pub approver: Pubkey,
+    pub signed_at: i64,
+    pub bump: u8,
+}
+
+// A proposer's anti-spam deposit, escrowed until the proposal is settled
+#[cfg(feature = "treasury")]
+#[account]
+#[derive(InitSpace)]
+pub struct ProposalDeposit {
+    pub proposal: Pubkey,
+    pub group: Pubkey,
+    pub depositor: Pubkey,
+    pub amount: u64,
+    pub spam_choice: u8,
+    // Council members who flagged the proposal as spam
+    #[max_len(MAX_COUNCIL_MEMBERS)]
+    pub flags: Vec<Pubkey>,
+    pub flagged: bool, // the council threshold was reached
+    pub bump: u8,
+}
+
+// Rewards split equally among a proposal's voters once it is finalized, held in SOL by
+// the pool itself or in an SPL token account owned by it
+#[cfg(feature = "vote-rewards")]
+#[account]
+#[derive(InitSpace)]
+pub struct RewardPool {
+    pub group: Pubkey,
+    pub proposal: Pubkey,
+    // Only the funder can add to the pool, and unclaimed rewards go back to them
+    pub funder: Pubkey,
+    pub token_mint: Pubkey, // NATIVE_MINT for SOL
+    pub vault: Pubkey,      // the pool itself for SOL
+    pub total_amount: u64,
+    pub claimed_amount: u64,
+    pub claim_count: u32,
+    pub bump: u8,
+}
+
+// Receipt of a voter's reward claim; its existence blocks a second claim
+#[cfg(feature = "vote-rewards")]
+#[account]
+#[derive(InitSpace)]
+pub struct RewardClaim {
+    pub pool: Pubkey,
+    pub voter: Pubkey,
+    pub amount: u64,
+    pub claimed_at: i64,
+    pub bump: u8,
+}
+
+// SOL prize pool paid to voters drawn at random when the proposal is finalized, to
+// encourage turnout. Held by the lottery itself; it stays ungated because
+// finalize_proposal takes it.
+#[account]
+#[derive(InitSpace)]
+pub struct VoterLottery {
+    pub group: Pubkey,
+    pub proposal: Pubkey,
+    // Only the funder can add to the lottery, and unclaimed prizes go back to them
+    pub funder: Pubkey,
+    pub total_amount: u64,
+    pub winner_count: u8, // fewer are drawn when fewer voted
+    #[max_len(MAX_LOTTERY_WINNERS)]
+    pub winners: Vec<Pubkey>,
+    pub prize: u64,          // each winner's share of total_amount
+    pub claimed_mask: u16,   // bit i set once winners[i] has claimed
+    pub claim_deadline: i64, // set on drawing to the stale-proposal grace period
+    pub drawn: bool,
+    pub bump: u8,
+}
+
+// An aggregator's tally of a proposal's off-chain ballots. It is kept after settlement
+// so the published ballots can still be checked against the root.
+#[cfg(feature = "offchain-votes")]
+#[account]
+#[derive(InitSpace)]
+pub struct OffchainTally {
+    pub proposal: Pubkey,
+    pub aggregator: Pubkey,
+    pub merkle_root: [u8; 32],
+    #[max_len(MAX_CHOICES)]
+    pub choice_votes: Vec<u64>,
+    pub voter_count: u32,
+    pub submitted_at: i64,
+    pub challenge_end: i64,
+    // Whoever proved a contradicting ballot or a wrong total; the tally can't settle
+    // until resubmitted
+    pub disputed_by: Option<Pubkey>,
+    pub bump: u8,
+}
+
+// What a subtree of an off-chain tally counts: weight per choice and ballots
+#[cfg(feature = "offchain-votes")]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TallySums {
+    pub choice_votes: Vec<u64>,
+    pub voter_count: u32,
+}
+
+// A subtree of an off-chain tally as its parent commits to it: its hash and its sums
+#[cfg(feature = "offchain-votes")]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TallySubtree {
+    pub hash: [u8; 32],
+    pub sums: TallySums,
+}
+
+// A tally tree node opened in dispute_offchain_total
+#[cfg(feature = "offchain-votes")]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub enum TallyNode {
+    Ballot {
+        voter: Pubkey,
+        choice: u8,
+        vote_weight: u64,
+    },
+    Inner {
+        left: TallySubtree,
+        right: TallySubtree,
+    },
+}
+
+#[cfg(feature = "offchain-votes")]
+impl TallySums {
+    // A ballot leaf's sums; None for a choice the proposal doesn't have
+    fn ballot(choice_count: usize, choice: u8, vote_weight: u64) -> Option<Self> {
+        let mut choice_votes = vec![0; choice_count];
+        *choice_votes.get_mut(choice as usize)? = vote_weight;
+        Some(Self {
+            choice_votes,
+            voter_count: 1,
+        })
+    }
+
+    // None on overflow or when either side isn't one total per choice
+    fn checked_add(&self, other: &Self, choice_count: usize) -> Option<Self> {
+        if self.choice_votes.len() != choice_count || other.choice_votes.len() != choice_count {
+            return None;
+        }
+        Some(Self {
+            choice_votes: self
+                .choice_votes
+                .iter()
+                .zip(&other.choice_votes)
+                .map(|(a, b)| a.checked_add(*b))
+                .collect::<Option<_>>()?,
+            voter_count: self.voter_count.checked_add(other.voter_count)?,
+        })
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.choice_votes.len() * 8 + 4);
+        for votes in &self.choice_votes {
+            bytes.extend_from_slice(&votes.to_le_bytes());
+        }
+        bytes.extend_from_slice(&self.voter_count.to_le_bytes());
+        bytes
+    }
+}
+
+// A shareable invite to a group, found by the hash of its code
+#[account]
+#[derive(InitSpace)]
+pub struct Invite {
+    pub group: Pubkey,
+    pub code_hash: [u8; 32],
+    pub created_by: Pubkey,
+    pub max_uses: u32,
+    pub uses: u32,
+    pub expires_at: i64,
+    pub bump: u8,
+}
+
+// What a wallet must hold to join a group with join_group
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq, InitSpace)]
+pub enum MembershipRequirement {
+    // At least `min_amount` base units of an SPL token
+    Token { mint: Pubkey, min_amount: u64 },
+    // An NFT whose Metaplex metadata names `collection` as its verified collection
+    Collection { collection: Pubkey },
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct MembershipGate {
+    pub group: Pubkey,
+    pub requirement: MembershipRequirement,
+    pub bump: u8,
+}
+
+// A group's Bubblegum tree of membership credentials. This PDA is the tree's creator,
+// so only add_group_member can mint into it, and the delegate of every credential.
+#[account]
+#[derive(InitSpace)]
+pub struct MembershipTree {
+    pub group: Pubkey,
+    pub merkle_tree: Pubkey,
+    pub credentials_issued: u64,
+    pub bump: u8,
+}
+
+// Bubblegum's MetadataArgs in the shape credentials use: no collection, uses or
+// creators, so those are typed loosely here; None and an empty Vec encode the same
+#[cfg(feature = "cnft-membership")]
+#[derive(AnchorSerialize)]
+struct CredentialMetadata {
+    name: String,
+    symbol: String,
+    uri: String,
+    seller_fee_basis_points: u16,
+    primary_sale_happened: bool,
+    is_mutable: bool,
+    edition_nonce: Option<u8>,
+    token_standard: Option<u8>,
+    collection: Option<u8>,
+    uses: Option<u8>,
+    token_program_version: u8,
+    creators: Vec<u8>,
+}
+
+// The mints a price-weighted proposal accepts, each with the price account its
+// holdings are valued by
+#[account]
+#[derive(InitSpace)]
+pub struct PriceWeighting {
+    pub proposal: Pubkey,
+    #[max_len(MAX_PRICED_MINTS)]
+    pub feeds: Vec<PricedMint>,
+    // Oldest price update a vote may use, in seconds
+    pub max_staleness_secs: u32,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct PricedMint {
+    pub mint: Pubkey,
+    // A Pyth PriceUpdateV2 or Switchboard On-Demand pull feed account
+    pub oracle: Pubkey,
+    // Copied from the mint so votes don't need the mint account
+    pub decimals: u8,
+}
+
+// A mint and its price account, as passed to set_price_weighting
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct PriceFeed {
+    pub mint: Pubkey,
+    pub oracle: Pubkey,
+}
+
+// A routine proposal saved for reuse: everything but the proposal id and timing
+#[cfg(feature = "templates")]
+#[account]
+#[derive(InitSpace)]
+pub struct ProposalTemplate {
+    pub group: Pubkey,
+    #[max_len(MAX_TEMPLATE_ID_LEN)]
+    pub template_id: String,
+    #[max_len(200)]
+    pub title: String,
+    #[max_len(1000)]
     pub description: String,
+    #[max_len(MAX_CHOICES, MAX_CHOICE_LEN)]
     pub choices: Vec<String>,
+    // Seconds from voting start to voting end
+    pub voting_duration: i64,
+    pub token_mint: Option<Pubkey>,
+    #[max_len(MAX_METADATA_URI_LEN)]
+    pub metadata_uri: Option<String>,
+    pub kind: ProposalKind,
+    pub member_weighted: bool,
+    pub split_voting: bool,
+    // Proposals spawned so far
+    pub instance_count: u64,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+// Where a group's voters lock one token for deposit-weighted votes, and how long a
+// deposit must be held before a proposal's creation to count on it
+#[account]
+#[derive(InitSpace)]
+pub struct DepositRegistrar {
+    pub group: Pubkey,
+    pub mint: Pubkey,
+    pub vault: Pubkey, // the registrar itself for SOL
+    pub min_holding_secs: i64,
+    pub bump: u8,
+}
+
+// A voter's locked tokens in a registrar and when they were last topped up
+#[account]
+#[derive(InitSpace)]
+pub struct VoterDeposit {
+    pub registrar: Pubkey,
+    pub voter: Pubkey,
+    pub amount: u64,
+    pub deposited_at: i64,
+    pub bump: u8,
+}
+
+// Opt-in for `tick` to open a draft at its voting start
+#[cfg(feature = "automation")]
+#[account]
+#[derive(InitSpace)]
+pub struct ProposalSchedule {
+    pub proposal: Pubkey,
+    pub scheduled_by: Pubkey,
+    pub bump: u8,
+}
+
+// A non-member's sentiment on a proposal. Shadow ballots are tallied apart from the
+// real vote (see Proposal::shadow_votes) and never affect the result.
+#[account]
+#[derive(InitSpace)]
+pub struct ShadowBallot {
+    pub proposal: Pubkey,
+    pub voter: Pubkey,
+    pub choice: u8,
+    pub timestamp: i64,
+    pub bump: u8,
+}
+
+// A proposal co-owned by several groups. Each group votes on its own Proposal, so
+// membership and vote weight stay per-group; the joint outcome only combines results.
+#[cfg(feature = "joint-proposals")]
+#[account]
+#[derive(InitSpace)]
+pub struct JointProposal {
+    #[max_len(MAX_JOINT_ID_LEN)]
+    pub joint_id: String,
+    pub creator: Pubkey,
+    #[max_len(MAX_JOINT_GROUPS)]
+    pub participants: Vec<JointParticipant>,
+    pub required_passes: u8, // participants that must pass it; 0 means all of them
+    pub status: ProposalStatus, // Active until resolved, then Succeeded or Defeated
+    pub created_at: i64,
+    pub resolved_at: i64,
+    pub bump: u8,
+}
+
+// Singleton naming the group that governs upgrades of this program. Its treasury PDA
+// (seeds: "treasury", group) is meant to hold the program's upgrade authority.
+#[cfg(feature = "treasury")]
+#[account]
+#[derive(InitSpace)]
+pub struct ProgramGovernance {
+    pub group: Pubkey,
+    pub treasury: Pubkey,
+    pub timelock_secs: i64, // delay between an approved upgrade being queued and run
+    pub upgrade_count: u64,
+    pub bump: u8,
+}
+
+// A program upgrade riding on one of the governing group's proposals
+#[cfg(feature = "treasury")]
+#[account]
+#[derive(InitSpace)]
+pub struct UpgradeProposal {
+    pub proposal: Pubkey,
+    pub buffer: Pubkey,     // upgradeable-loader buffer holding the new program
+    pub approve_choice: u8, // the choice that must win for the upgrade to run
+    pub eta: i64,           // earliest execution time; 0 until queued
+    pub executed_at: i64,   // 0 until executed
+    pub bump: u8,
+}
+
+// Index from an external chat to its group, so clients can resolve chat -> group on-chain
+#[account]
+#[derive(InitSpace)]
+pub struct ChatLink {
+    pub chat_id: i64,
+    pub group: Pubkey,
+    pub linked_at: i64,
+    pub bump: u8,
+}
+
+// A group's public card for explorers and bots (seeds: "group_profile", group)
+#[cfg(feature = "profiles")]
+#[account]
+#[derive(InitSpace)]
+pub struct GroupProfile {
+    pub group: Pubkey,
+    #[max_len(MAX_METADATA_URI_LEN)]
+    pub avatar_uri: Option<String>,
+    #[max_len(MAX_METADATA_URI_LEN)]
+    pub website: Option<String>,
+    #[max_len(MAX_PROFILE_SOCIALS, MAX_METADATA_URI_LEN)]
+    pub socials: Vec<String>, // links to the group's accounts elsewhere
+    #[max_len(MAX_PROFILE_CONTACT_LEN)]
+    pub contact: Option<String>, // free-form, e.g. an email address or a handle
+    pub updated_at: i64,
+    pub bump: u8,
+}
+
+#[cfg(feature = "slugs")]
+#[account]
+#[derive(InitSpace)]
+pub struct SlugReservation {
+    pub registry: Pubkey,
+    #[max_len(32)]
+    pub slug: String,
+    pub owner: Pubkey,
+    pub group: Option<Pubkey>,
+    pub reserved_at: i64,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct UserAccount {
+    // Layout version (USER_ACCOUNT_VERSION), first for the same reason as Group's
+    pub version: u8,
+    pub telegram_id: i64,
+    pub wallet_pubkey: Pubkey,
+    pub created_at: i64,
+    // Additional wallets (e.g. a personal Phantom wallet) whose weight counts toward
+    // this user's votes in token modes
+    #[max_len(MAX_LINKED_WALLETS)]
+    pub linked_wallets: Vec<Pubkey>,
+    // Time of the last signature-verified login (0 if none yet)
+    pub last_login: i64,
+    // Nonce the next login challenge must sign; bumped on every successful login
+    pub login_nonce: u64,
+    pub bump: u8,
+}
+
+// Helper Structs
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct GroupInfo {
+    #[max_len(50)]
+    pub group_id: String,
+    pub authority: Pubkey,
+    pub pubkey: Pubkey,
+}
+
+// Return data of get_all_groups: one page of the registry's groups
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct GroupPage {
+    pub total: u32,
+    pub start: u32,
+    pub groups: Vec<GroupInfo>,
+}
+
+// The groups from `start` on that fit in MAX_RETURN_DATA alongside the page header
+pub fn group_page(groups: &[GroupInfo], start: usize) -> GroupPage {
+    let mut size = 4 + 4 + 4;
+    let page = groups
+        .iter()
+        .skip(start)
+        .take_while(|info| {
+            size += 4 + info.group_id.len() + 32 + 32;
+            size <= MAX_RETURN_DATA
+        })
+        .cloned()
+        .collect();
+    GroupPage {
+        total: groups.len() as u32,
+        start: start.min(groups.len()) as u32,
+        groups: page,
+    }
+}
+
+// Return data of get_proposal_summary, small enough to fit in any proposal's return data
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ProposalSummary {
+    pub proposal_id: String,
+    pub group_id: String,
+    pub title: String,
+    pub status: ProposalStatus,
+    pub voting_start: i64,
+    pub voting_end: i64,
+    pub choice_votes: Vec<u64>,
+    pub voter_count: u32,
+    pub winner: Option<u8>,
+    pub tied: bool,
+}
+
+// Public groups can be listed and joined by anyone meeting their membership gate;
+// private ones only admit members the authority adds or invites
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum GroupVisibility {
+    Public,
+    Private,
+}
+
+// Rules every proposal in a group is held to. They can only be changed by a passed
+// config-change proposal; the all-zero default keeps plain plurality voting.
+#[derive(
+    AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq, InitSpace,
+)]
+pub struct GovernanceConfig {
+    // Share of member_count, in basis points, that must cast a ballot
+    pub quorum_bps: u16,
+    // Share of the cast votes, in basis points, the winning choice needs
+    pub approval_threshold_bps: u16,
+    // Bounds on voting_end - voting_start in seconds; a max of 0 means unbounded
+    pub min_voting_duration: i64,
+    pub max_voting_duration: i64,
+    // Share of the proposal's voting supply, in basis points, that must be cast (see
+    // weight_turnout). With quorum_bps also set, both quorums must be met.
+    pub weight_quorum_bps: u16,
+}
+
+// How finalization settles a tie for first place
+#[derive(
+    AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq, InitSpace,
+)]
+pub enum TieBreak {
+    // The proposal is defeated
+    #[default]
+    Fail,
+    // Voting reopens once for window_secs; still tied after that, the proposal is defeated
+    Revote {
+        window_secs: i64,
+    },
+    // The tied choice that reached its final tally first wins
+    EarliestLeading,
+    // The group council picks among the tied choices with resolve_tie
+    Council,
+}
+
+impl GovernanceConfig {
+    fn is_valid(&self) -> bool {
+        self.quorum_bps <= 10_000
+            && self.weight_quorum_bps <= 10_000
+            && self.approval_threshold_bps <= 10_000
+            && self.min_voting_duration >= 0
+            && (self.max_voting_duration == 0
+                || self.max_voting_duration >= self.min_voting_duration.max(1))
+    }
+
+    // Emergencies may be as short as EMERGENCY_MIN_VOTING_SECS
+    fn allows_duration(&self, duration: i64, emergency: bool) -> bool {
+        let min_duration = if emergency {
+            self.min_voting_duration.min(EMERGENCY_MIN_VOTING_SECS)
+        } else {
+            self.min_voting_duration
+        };
+        duration >= min_duration
+            && (self.max_voting_duration == 0 || duration <= self.max_voting_duration)
+    }
+
+    // Whether a proposal whose votes produced `winner` also met both quorums and the
+    // threshold. Emergencies need at least EMERGENCY_APPROVAL_THRESHOLD_BPS.
+    fn carries(
+        &self,
+        choice_votes: &[u64],
+        winner: u8,
+        voter_count: u32,
+        member_count: u32,
+        cast_weight: u64,
+        weight_supply: u64,
+        emergency: bool,
+    ) -> bool {
+        let threshold_bps = if emergency {
+            self.approval_threshold_bps
+                .max(EMERGENCY_APPROVAL_THRESHOLD_BPS)
+        } else {
+            self.approval_threshold_bps
+        };
+        let total: u128 = choice_votes.iter().map(|&v| v as u128).sum();
+        let winning = choice_votes[winner as usize] as u128;
+        voter_count as u128 * 10_000 >= self.quorum_bps as u128 * member_count as u128
+            && cast_weight as u128 * 10_000
+                >= self.weight_quorum_bps as u128 * weight_supply as u128
+            && winning * 10_000 >= threshold_bps as u128 * total
+    }
+}
+
+// A listed member of a group, one account per wallet
+#[account]
+#[derive(InitSpace)]
+pub struct Member {
+    pub group: Pubkey,
+    pub wallet: Pubkey,
+    pub joined_at: i64,
+    // Votes this member casts on member-weighted proposals; 1 unless the authority set it
+    pub weight: u32,
+    pub bump: u8,
+}
+
+// A member's backing of a draft (seeds: "sponsor", proposal, sponsor)
+#[cfg(feature = "sponsorship")]
+#[account]
+#[derive(InitSpace)]
+pub struct ProposalSponsor {
+    pub proposal: Pubkey,
+    pub sponsor: Pubkey,
+    pub sponsored_at: i64,
+    pub bump: u8,
+}
+
+// A wallet's voting power lent to another wallet (seeds: "delegation", delegator, group).
+// `group` is the default key for a delegation covering every group.
+#[cfg(feature = "delegation")]
+#[account]
+#[derive(InitSpace)]
+pub struct Delegation {
+    pub delegator: Pubkey,
+    pub delegate: Pubkey,
+    pub group: Pubkey,
+    // Only proposals of this kind (ProposalKind::tag) when set
+    pub kind: Option<u8>,
+    // Delegated weight stops counting after this time
+    pub expires_at: Option<i64>,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+#[cfg(feature = "delegation")]
+impl Delegation {
+    pub fn expired(&self, now: i64) -> bool {
+        self.expires_at.is_some_and(|expires_at| now > expires_at)
+    }
+
+    pub fn covers(&self, group: &Pubkey, kind: &ProposalKind) -> bool {
+        (self.group == Pubkey::default() || self.group == *group)
+            && self.kind.is_none_or(|tag| tag == kind.tag())
+    }
+}
+
+// A wallet barred from a group: it can't join or vote while the ban is in force
+#[account]
+#[derive(InitSpace)]
+pub struct Ban {
+    pub group: Pubkey,
+    pub wallet: Pubkey,
+    #[max_len(MAX_BAN_REASON_LEN)]
+    pub reason: String,
+    pub banned_by: Pubkey,
+    pub banned_at: i64,
+    pub expires_at: i64, // 0 for a ban that never expires
+    pub bump: u8,
+}
+
+impl Ban {
+    pub fn in_force(&self, now: i64) -> bool {
+        self.expires_at == 0 || now < self.expires_at
+    }
+}
+
+// A group's recurring dues: `amount` of `mint` (NATIVE_MINT for SOL) per period
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub struct DuesPolicy {
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub period_secs: i64,
+    // How long after lapsing a member can still vote
+    pub grace_secs: i64,
+    pub required_for_voting: bool,
+}
+
+// Stake-to-join: wallets buy shares at `price_lamports` each, paid into the group
+// treasury, and can ragequit them for their pro-rata cut of it
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub struct StakeToJoin {
+    pub price_lamports: u64,
+    pub total_shares: u64,
+    // Shares nobody holds, booked against what the treasury already held when the first
+    // share was sold; they keep that balance from going to the first buyer
+    pub unowned_shares: u64,
+}
+
+#[cfg(feature = "shares")]
+impl StakeToJoin {
+    // Lamports `shares` new shares cost: the share price, or the treasury's value per
+    // share when that is higher, rounded up so a purchase never dilutes holders
+    fn cost(&self, pool_lamports: u64, shares: u64) -> Option<u64> {
+        let at_price = self.price_lamports.checked_mul(shares)?;
+        if self.total_shares == 0 {
+            return Some(at_price);
+        }
+        let at_value = (pool_lamports as u128 * shares as u128).div_ceil(self.total_shares as u128);
+        u64::try_from(at_value)
+            .ok()
+            .map(|at_value| at_value.max(at_price))
+    }
+}
+
+// A wallet's shares in a stake-to-join group. Buying locks them for RAGEQUIT_GUARD_SECS,
+// and voting until the proposal's voting ends plus RAGEQUIT_GUARD_SECS, so nobody can
+// buy in and leave straight away, or vote and leave with their cut before the outcome
+// is carried out.
+#[account]
+#[derive(InitSpace)]
+pub struct MemberShares {
+    pub group: Pubkey,
+    pub member: Pubkey,
+    pub shares: u64,
+    pub locked_until: i64,
+    pub bump: u8,
+}
+
+// How far a wallet has paid its group's dues. Kept apart from Member, so credential
+// holders can pay too.
+#[account]
+#[derive(InitSpace)]
+pub struct DuesRecord {
+    pub group: Pubkey,
+    pub member: Pubkey,
+    pub paid_until: i64,
+    pub last_paid_at: i64,
+    // Set by flag_overdue_dues, cleared by the next payment
+    pub overdue_flagged: bool,
+    pub bump: u8,
+}
+
+// What a proposal decides. Text proposals only record the group's view and are marked
+// executed by the authority; the other kinds carry a payload, checked when the proposal
+// is created, that their execute_* instruction carries out once the proposal succeeds.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq, InitSpace)]
+pub enum ProposalKind {
+    Text,
+    TreasuryTransfer(TreasuryTransfer),
+    ConfigChange(ConfigChange),
+    MembershipChange(MembershipChange),
+    Election(Election),
+    Grant(GrantTerms),
+    Stream(StreamTerms),
+    Bounty(BountyTerms),
+    TreasuryRecovery(TreasuryRecovery),
+    TreasuryStake(StakeAction),
+    TreasurySwap(SwapTerms),
+    TokenDistribution(DistributionTerms),
+}
+
+impl ProposalKind {
+    // The kind's variant index, which scopes delegations to one kind
+    pub fn tag(&self) -> u8 {
+        match self {
+            ProposalKind::Text => 0,
+            ProposalKind::TreasuryTransfer(_) => 1,
+            ProposalKind::ConfigChange(_) => 2,
+            ProposalKind::MembershipChange(_) => 3,
+            ProposalKind::Election(_) => 4,
+            ProposalKind::Grant(_) => 5,
+            ProposalKind::Stream(_) => 6,
+            ProposalKind::Bounty(_) => 7,
+            ProposalKind::TreasuryRecovery(_) => 8,
+            ProposalKind::TreasuryStake(_) => 9,
+            ProposalKind::TreasurySwap(_) => 10,
+            ProposalKind::TokenDistribution(_) => 11,
+        }
+    }
+
+    // Wallet a membership change adds or removes; the default key for other kinds
+    pub fn membership_member(&self) -> Pubkey {
+        match self {
+            ProposalKind::MembershipChange(change) => change.member,
+            _ => Pubkey::default(),
+        }
+    }
+}
+
+// Lamports paid from the group treasury PDA if the first choice wins
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq, InitSpace)]
+pub struct TreasuryTransfer {
+    pub recipient: Pubkey,
+    pub lamports: u64,
+}
+
+// Last-resort escape hatch: the whole treasury goes to `recovery` if the first choice
+// wins a supermajority with enough of the members voting
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq, InitSpace)]
+pub struct TreasuryRecovery {
+    pub recovery: Pubkey,
+}
+
+// Native staking of treasury SOL, carried out if the first choice wins. The staker and
+// withdrawer of a delegated stake account are the treasury PDA.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq, InitSpace)]
+pub enum StakeAction {
+    // Move `lamports`, rent included, into a new stake account delegated to the validator
+    Delegate { vote_account: Pubkey, lamports: u64 },
+    Deactivate { stake_account: Pubkey },
+    // Return a deactivated stake account's whole balance to the treasury
+    Withdraw { stake_account: Pubkey },
+}
+
+// Treasury tokens swapped through a DEX program if the first choice wins. The route is
+// chosen at execution; these bounds are what the vote approved.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq, InitSpace)]
+pub struct SwapTerms {
+    pub swap_program: Pubkey, // e.g. the Jupiter aggregator
+    pub input_mint: Pubkey,
+    pub output_mint: Pubkey,
+    pub amount_in: u64,      // most the treasury may spend
+    pub min_amount_out: u64, // least it must receive
+}
+
+// Governance tokens handed out to a Merkle-committed list of recipients if the first
+// choice wins. Leaves are distribution_leaf(recipient, amount).
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq, InitSpace)]
+pub struct DistributionTerms {
+    pub mint: Pubkey,
+    pub source: DistributionSource,
+    pub merkle_root: [u8; 32],
+    pub total_amount: u64, // cap on all claims together
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum DistributionSource {
+    Mint,     // minted on claim; the treasury PDA must be the mint authority
+    Treasury, // paid from a token account the treasury PDA owns
+}
+
+// Group settings applied if the first choice wins; None leaves a setting unchanged
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq, InitSpace)]
+pub struct ConfigChange {
+    pub proposal_fee_lamports: Option<u64>,
+    pub members_only_voting: Option<bool>,
+    pub max_members: Option<u32>,
+    pub visibility: Option<GroupVisibility>,
+    pub governance: Option<GovernanceConfig>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum MembershipAction {
+    Add,
+    Remove,
+}
+
+// A wallet added to or removed from the group if the first choice wins
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq, InitSpace)]
+pub struct MembershipChange {
+    pub member: Pubkey,
+    pub action: MembershipAction,
+}
+
+// A council election: choice i stands for candidates[i], and the `seats` candidates with
+// the most votes become the council, acting once `threshold` of them agree
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq, InitSpace)]
+pub struct Election {
+    #[max_len(MAX_CHOICES)]
+    pub candidates: Vec<Pubkey>,
+    pub seats: u8,
+    pub threshold: u8,
+}
+
+// A grant paid from the group treasury in tranches, one per milestone, each released
+// once the milestone is approved
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq, InitSpace)]
+pub struct GrantTerms {
+    pub recipient: Pubkey,
+    // Lamports released at each milestone, in order
+    #[max_len(MAX_GRANT_MILESTONES)]
+    pub milestones: Vec<u64>,
+    pub approval: MilestoneApproval,
+}
+
+// Who signs off a grant's milestones: the group council at its threshold, or more than
+// half the listed members
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum MilestoneApproval {
+    Council,
+    MemberVote,
+}
+
+// A linear payment stream from the group treasury: `amount` lamports vest evenly from
+// `start` to `end`, and none can be withdrawn before `cliff`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq, InitSpace)]
+pub struct StreamTerms {
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub start: i64,
+    pub cliff: i64,
+    pub end: i64,
+}
+
+// A bounty to post on the group's board, paying `reward` lamports from the treasury
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq, InitSpace)]
+pub struct BountyTerms {
+    #[max_len(MAX_BOUNTY_ID_LEN)]
+    pub bounty_id: String,
+    #[max_len(MAX_METADATA_URI_LEN)]
+    pub description_uri: String,
+    pub reward: u64,
+}
+
+// Draft -> Active -> Succeeded/Defeated (finalize_proposal) -> Executed, with Draft or
+// Active proposals also cancellable
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct JointParticipant {
+    pub group: Pubkey,
+    pub proposal: Pubkey,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum ProposalStatus {
+    Draft,
+    Active,
+    Succeeded,
+    Defeated,
+    Cancelled,
+    Executed,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct VoterInfo {
+    pub voter: Pubkey,
+    pub choice: u8,
+    pub vote_weight: u64,
+    pub timestamp: i64,
+    // Conflict-of-interest disclosure; Some("") flags a conflict without a note
+    #[max_len(MAX_DISCLOSURE_LEN)]
+    pub disclosure: Option<String>,
+}
+
+// spl-governance (Realms) v3 account layouts, written by the sync_realms_* instructions
+// so tools that decode spl-governance accounts can read this program's proposals and
+// votes. These accounts start with spl-governance's one-byte account type instead of an
+// Anchor discriminator, and live at the addresses spl-governance would derive, with the
+// group standing in for the Governance and Participation PDAs for TokenOwnerRecords.
+// Enums list only the variants needed to keep the written discriminants right.
+#[cfg(feature = "realms-compat")]
+pub mod realms {
+    use super::*;
+
+    // GovernanceAccountType values
+    pub const VOTE_RECORD_V2: u8 = 12;
+    pub const PROPOSAL_V2: u8 = 14;
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+    pub enum ProposalState {
+        Draft,
+        SigningOff,
+        Voting,
+        Succeeded,
+        Executing,
+        Completed,
+        Cancelled,
+        Defeated,
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+    pub enum VoteType {
+        SingleChoice,
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+    pub enum OptionVoteResult {
+        None,
+        Succeeded,
+        Defeated,
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+    pub struct ProposalOption {
+        pub label: String,
+        pub vote_weight: u64,
+        pub vote_result: OptionVoteResult,
+        pub transactions_executed_count: u16,
+        pub transactions_count: u16,
+        pub transactions_next_index: u16,
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+    pub enum VoteThreshold {
+        YesVotePercentage(u8),
+        QuorumPercentage(u8),
+        Disabled,
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+    pub struct ProposalV2 {
+        pub account_type: u8,
+        pub governance: Pubkey,
+        pub governing_token_mint: Pubkey,
+        pub state: ProposalState,
+        pub token_owner_record: Pubkey,
+        pub signatories_count: u8,
+        pub signatories_signed_off_count: u8,
+        pub vote_type: VoteType,
+        pub options: Vec<ProposalOption>,
+        // No separate "deny" option: every choice is an option of its own
+        pub deny_vote_weight: Option<u64>,
+        pub reserved1: u8,
+        pub abstain_vote_weight: Option<u64>,
+        pub start_voting_at: Option<i64>,
+        pub draft_at: i64,
+        pub signing_off_at: Option<i64>,
+        pub voting_at: Option<i64>,
+        pub voting_at_slot: Option<u64>,
+        pub voting_completed_at: Option<i64>,
+        pub executing_at: Option<i64>,
+        pub closed_at: Option<i64>,
+        pub execution_flags: u8,
+        pub max_vote_weight: Option<u64>,
+        pub max_voting_time: Option<u32>,
+        pub vote_threshold: Option<VoteThreshold>,
+        pub reserved: [u8; 64],
+        pub name: String,
+        pub description_link: String,
+        pub veto_vote_weight: u64,
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+    pub struct VoteChoice {
+        pub rank: u8,
+        pub weight_percentage: u8,
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+    pub enum Vote {
+        Approve(Vec<VoteChoice>),
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+    pub struct VoteRecordV2 {
+        pub account_type: u8,
+        pub proposal: Pubkey,
+        pub governing_token_owner: Pubkey,
+        pub is_relinquished: bool,
+        pub voter_weight: u64,
+        pub vote: Vote,
+        pub reserved_v2: [u8; 8],
+    }
+
+    impl ProposalV2 {
+        pub fn from_proposal(
+            proposal: &Proposal,
+            governance: Pubkey,
+            token_owner_record: Pubkey,
+        ) -> Self {
+            let settled = matches!(
+                proposal.status,
+                ProposalStatus::Succeeded | ProposalStatus::Defeated | ProposalStatus::Executed
+            );
+            let winner = if settled {
+                proposal.winner
+            } else {
+                winning_choice(&proposal.choice_votes)
+            };
+            let options = proposal
+                .choices
+                .iter()
+                .zip(&proposal.choice_votes)
+                .enumerate()
+                .map(|(index, (label, &vote_weight))| ProposalOption {
+                    label: label.clone(),
+                    vote_weight,
+                    vote_result: match (settled, winner) {
+                        (false, _) => OptionVoteResult::None,
+                        (true, Some(winner)) if winner as usize == index => {
+                            OptionVoteResult::Succeeded
+                        }
+                        (true, _) => OptionVoteResult::Defeated,
+                    },
+                    transactions_executed_count: 0,
+                    transactions_count: 0,
+                    transactions_next_index: 0,
+                })
+                .collect();
+
+            ProposalV2 {
+                account_type: PROPOSAL_V2,
+                governance,
+                governing_token_mint: proposal.token_mint.unwrap_or_default(),
+                state: match proposal.status {
+                    ProposalStatus::Draft => ProposalState::Draft,
+                    ProposalStatus::Active => ProposalState::Voting,
+                    ProposalStatus::Succeeded => ProposalState::Succeeded,
+                    ProposalStatus::Defeated => ProposalState::Defeated,
+                    ProposalStatus::Cancelled => ProposalState::Cancelled,
+                    ProposalStatus::Executed => ProposalState::Completed,
+                },
+                token_owner_record,
+                signatories_count: 0,
+                signatories_signed_off_count: 0,
+                vote_type: VoteType::SingleChoice,
+                options,
+                deny_vote_weight: None,
+                reserved1: 0,
+                abstain_vote_weight: None,
+                start_voting_at: Some(proposal.voting_start),
+                draft_at: proposal.created_at,
+                signing_off_at: None,
+                voting_at: (proposal.status != ProposalStatus::Draft)
+                    .then_some(proposal.voting_start),
+                voting_at_slot: None,
+                voting_completed_at: settled.then_some(proposal.voting_end),
+                executing_at: None,
+                closed_at: None,
+                execution_flags: 0,
+                max_vote_weight: None,
+                max_voting_time: u32::try_from(proposal.voting_end - proposal.voting_start).ok(),
+                vote_threshold: None,
+                reserved: [0; 64],
+                name: proposal.title.clone(),
+                description_link: proposal.metadata_uri.clone().unwrap_or_default(),
+                veto_vote_weight: 0,
+            }
+        }
+    }
+
+    impl VoteRecordV2 {
+        // A ballot as full approval of the chosen option and none of the others
+        pub fn from_ballot(proposal: Pubkey, ballot: &VoterInfo, option_count: usize) -> Self {
+            VoteRecordV2 {
+                account_type: VOTE_RECORD_V2,
+                proposal,
+                governing_token_owner: ballot.voter,
+                is_relinquished: false,
+                voter_weight: ballot.vote_weight,
+                vote: Vote::Approve(
+                    (0..option_count)
+                        .map(|index| VoteChoice {
+                            rank: 0,
+                            weight_percentage: if index == ballot.choice as usize {
+                                100
+                            } else {
+                                0
+                            },
+                        })
+                        .collect(),
+                ),
+                reserved_v2: [0; 8],
+            }
+        }
+    }
+}
+
+// Account sizing
+// Vectors marked `#[max_len(0)]` are grown with `realloc` (payer-funded) as instructions
+// append to them; everything else is budgeted by the derived INIT_SPACE.
+pub const MAX_CHOICES: usize = 10;
+pub const MAX_CHOICE_LEN: usize = 50;
+pub const MAX_CHOICE_DESCRIPTION_LEN: usize = 140;
+pub const MAX_METADATA_URI_LEN: usize = 200;
+pub const MAX_BAN_REASON_LEN: usize = 200;
+pub const MAX_PROFILE_SOCIALS: usize = 4;
+pub const MAX_PROFILE_CONTACT_LEN: usize = 100;
+pub const MAX_CALLBACK_PAYLOAD_LEN: usize = 256;
+pub const MAX_ALLOWLISTED_PROGRAMS: usize = 16;
+pub const MAX_LINKED_WALLETS: usize = 4;
+// Registry names are a PDA seed, so they fit in one
+pub const MAX_REGISTRY_NAME_LEN: usize = 32;
+pub const MAX_TURNOUT_DAYS: usize = 30;
+// Number of ProposalKind variants; delegation scopes name one by its tag
+pub const PROPOSAL_KIND_COUNT: u8 = 12;
+
+// Current layout versions of the versioned accounts. Bump one with each change to its
+// layout and teach the matching upgrade_* function to convert from the previous version.
+pub const GROUP_VERSION: u8 = 1;
+pub const PROPOSAL_VERSION: u8 = 1;
+pub const USER_ACCOUNT_VERSION: u8 = 1;
+pub const MAX_DISCLOSURE_LEN: usize = 64;
+pub const MAX_JOINT_GROUPS: usize = 8;
+pub const MAX_JOINT_ID_LEN: usize = 32; // used whole as a PDA seed
+pub const MAX_TEMPLATE_ID_LEN: usize = 32; // used whole as a PDA seed
+pub const MAX_BOUNTY_ID_LEN: usize = 32; // used whole as a PDA seed
+                                         // Target slot time (the runtime's DEFAULT_MS_PER_SLOT), used to translate between
+                                         // slot windows and unix time
+pub const ESTIMATED_MS_PER_SLOT: u64 = 400;
+pub const MAX_TIE_REVOTE_SECS: i64 = 7 * SECONDS_PER_DAY;
+pub const MAX_SPONSOR_THRESHOLD: u8 = 32;
+// Login challenges are LOGIN_MESSAGE_PREFIX || user account address || nonce (u64 LE)
+pub const LOGIN_MESSAGE_PREFIX: &[u8] = b"solana-dao login:";
+// Wallet link challenges are LINK_WALLET_MESSAGE_PREFIX || user account address (base58)
+// || ':' || nonce (decimal), as text so the wallet shows what it is signing
+pub const LINK_WALLET_MESSAGE_PREFIX: &str = "solana-dao link wallet:";
+// Relayed votes are RELAYED_VOTE_MESSAGE_PREFIX || proposal address || choice (u8) || expiry (i64 LE)
+pub const RELAYED_VOTE_MESSAGE_PREFIX: &[u8] = b"solana-dao vote:";
+// Off-chain ballots are OFFCHAIN_BALLOT_MESSAGE_PREFIX || proposal address || choice (u8)
+pub const OFFCHAIN_BALLOT_MESSAGE_PREFIX: &[u8] = b"solana-dao ballot:";
+
+// Accepted metadata URI schemes; ipfs:// and ar:// URIs are content-addressed, so the
+// URI itself commits to the content
+pub const METADATA_URI_SCHEMES: [&str; 3] = ["ipfs://", "ar://", "https://"];
+
+// ProposalLarge limits (LARGE_MAX_CHOICES also sizes TurnoutStats)
+pub const LARGE_MAX_CHOICES: usize = 32;
+pub const LARGE_CHOICE_LEN: usize = 64;
+pub const LARGE_TITLE_LEN: usize = 256;
+pub const LARGE_DESCRIPTION_LEN: usize = 4096;
+
+// Program config defaults
+pub const DEFAULT_MAX_GROUPS: u32 = 10_000;
+
+// Open registration defaults
+pub const DEFAULT_GROUP_DEPOSIT_LAMPORTS: u64 = 100_000_000; // 0.1 SOL
+pub const DEFAULT_GROUP_DEPOSIT_LOCK_DAYS: u32 = 30;
+pub const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+// Program upgrade timelock bounds. The maximum keeps execution well inside the stale
+// proposal grace period, so the proposal can still be marked executed.
+pub const MIN_UPGRADE_TIMELOCK_SECS: i64 = 2 * SECONDS_PER_DAY;
+pub const MAX_UPGRADE_TIMELOCK_SECS: i64 = 14 * SECONDS_PER_DAY;
+
+// How long after voting ends a settled proposal stays open before close_stale_proposal
+// may reclaim it, leaving time to mark it executed or archive its discussion
+pub const STALE_PROPOSAL_GRACE_SECS: i64 = 30 * SECONDS_PER_DAY;
+// Shares stay locked this long after they were bought, and after the end of a vote they
+// were counted in, leaving time to execute the outcome before a voter can ragequit
+pub const RAGEQUIT_GUARD_SECS: i64 = 3 * SECONDS_PER_DAY;
+// A treasury recovery needs the first choice to win this share of the votes, with at
+// least RECOVERY_MIN_TURNOUT_BPS of the listed members voting. Turnout counts voters
+// against member_count, so recoveries are only proposed and carried out in groups with
+// members_only_voting, where wallets outside the group can't make up the numbers.
+pub const RECOVERY_SUPERMAJORITY_BPS: u64 = 8_000;
+pub const RECOVERY_MIN_TURNOUT_BPS: u64 = 5_000;
+
+pub const MAX_COUNCIL_MEMBERS: usize = 7;
+pub const MAX_GRANT_MILESTONES: usize = 8;
+pub const MAX_LOTTERY_WINNERS: usize = 16; // claims are tracked in a u16 bitmask
+pub const MAX_PROPOSAL_FEE_LAMPORTS: u64 = 1_000_000_000; // 1 SOL
+                                                          // Label of the dedicated choice voters pick to call a proposal spam
+pub const SPAM_CHOICE: &str = "Spam";
+pub const MAX_PROPOSAL_DEPOSIT_LAMPORTS: u64 = 100_000_000_000; // 100 SOL
+
+// Shortest voting window an emergency proposal may use, and the share of the cast
+// votes its winning choice needs whatever the group's own threshold
+pub const EMERGENCY_MIN_VOTING_SECS: i64 = 60 * 60;
+pub const EMERGENCY_APPROVAL_THRESHOLD_BPS: u16 = 7_500;
+
+// How long a submitted off-chain tally can be disputed before it settles
+pub const OFFCHAIN_CHALLENGE_SECS: i64 = 2 * SECONDS_PER_DAY;
+// Proof depth for up to 2^32 ballots
+pub const MAX_MERKLE_PROOF_LEN: usize = 32;
+
+// Metaplex Token Metadata, whose metadata accounts name an NFT's collection
+pub const TOKEN_METADATA_PROGRAM_ID: Pubkey =
+    pubkey!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+
+// Compressed NFT membership credentials: Metaplex Bubblegum, SPL account compression and
+// its noop log wrapper
+pub const BUBBLEGUM_PROGRAM_ID: Pubkey = pubkey!("BGUMAp9Gq7iTEuizy4pqaxsTyUCBK68MDfK752saRPUY");
+pub const ACCOUNT_COMPRESSION_PROGRAM_ID: Pubkey =
+    pubkey!("cmtDvXumGCrqC1Age74AVPhSRVXJMd8PJS91L8KbNCK");
+pub const NOOP_PROGRAM_ID: Pubkey = pubkey!("noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV");
+pub const MEMBERSHIP_CREDENTIAL_NAME: &str = "DAO Membership";
+pub const MEMBERSHIP_CREDENTIAL_SYMBOL: &str = "MEMBER";
+// Followed by "<group>/<member>"; names the holder so a transferred credential no
+// longer matches its leaf
+pub const MEMBERSHIP_CREDENTIAL_URI_PREFIX: &str = "solana-dao:membership/";
+#[cfg(feature = "cnft-membership")]
+const BUBBLEGUM_CREATE_TREE_CONFIG_DISCRIMINATOR: [u8; 8] = [165, 83, 136, 142, 89, 202, 47, 220];
+#[cfg(feature = "cnft-membership")]
+const BUBBLEGUM_MINT_V1_DISCRIMINATOR: [u8; 8] = [145, 98, 192, 118, 184, 147, 118, 104];
+#[cfg(feature = "cnft-membership")]
+const BUBBLEGUM_BURN_DISCRIMINATOR: [u8; 8] = [116, 110, 29, 56, 107, 219, 42, 93];
+#[cfg(feature = "cnft-membership")]
+const ACCOUNT_COMPRESSION_VERIFY_LEAF_DISCRIMINATOR: [u8; 8] =
+    [124, 220, 22, 223, 104, 10, 250, 224];
+
+// Price-weighted votes: accepted mints per proposal, and the decimals of the resulting
+// weight in the feeds' quote currency (micro-dollars for USD feeds)
+pub const MAX_PRICED_MINTS: usize = 4;
+pub const PRICE_WEIGHT_DECIMALS: u32 = 6;
+// Owners of the price accounts read for weights: the Pyth Solana receiver, whose
+// PriceUpdateV2 accounts back Pyth's price feeds, and Switchboard On-Demand (mainnet
+// and devnet)
+pub const PYTH_RECEIVER_PROGRAM_ID: Pubkey = pubkey!("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ");
+pub const SWITCHBOARD_ON_DEMAND_PROGRAM_IDS: [Pubkey; 2] = [
+    pubkey!("SBondMDrcV3K4kxZR1HNVT7osZxAHVHgYXL5Ze1oMUv"),
+    pubkey!("Aio4gaXjXzJNVLtzwtNVmSqGKpANtXhybbkhtAC94ji2"),
+];
+
+impl TurnoutStats {
+    // `choice_votes` are the proposal's tallies after the vote was added
+    pub fn record_vote(
+        &mut self,
+        proposal: Pubkey,
+        bump: u8,
+        choice_votes: &[u64],
+        voting_start: i64,
+        now: i64,
+    ) -> Result<()> {
+        if self.proposal == Pubkey::default() {
+            self.proposal = proposal;
+            self.bump = bump;
+        }
+        self.unique_voters = self
+            .unique_voters
+            .checked_add(1)
+            .ok_or(DaoError::TallyOverflow)?;
+        self.total_weight = choice_votes
+            .iter()
+            .try_fold(0u64, |sum, &votes| sum.checked_add(votes))
+            .ok_or(DaoError::TallyOverflow)?;
+        self.choice_bps = choice_votes
+            .iter()
+            .map(|&votes| match self.total_weight {
+                0 => 0,
+                total => (votes as u128 * 10_000 / total as u128) as u16,
+            })
+            .collect();
+
+        let day = (now.saturating_sub(voting_start).max(0) / SECONDS_PER_DAY) as usize;
+        let day = day.min(MAX_TURNOUT_DAYS - 1);
+        if self.votes_per_day.len() <= day {
+            self.votes_per_day.resize(day + 1, 0);
+        }
+        self.votes_per_day[day] = self.votes_per_day[day]
+            .checked_add(1)
+            .ok_or(DaoError::TallyOverflow)?;
+        Ok(())
+    }
+}
+
+impl Participation {
+    fn init_if_new(&mut self, group: Pubkey, member: Pubkey, bump: u8) {
+        if self.member == Pubkey::default() {
+            self.group = group;
+            self.member = member;
+            self.bump = bump;
+        }
+    }
+
+    // Fails while the member's last proposal is less than `cooldown_secs` old
+    pub fn record_proposal(
+        &mut self,
+        group: Pubkey,
+        member: Pubkey,
+        bump: u8,
+        cooldown_secs: i64,
+        now: i64,
+    ) -> Result<()> {
+        self.init_if_new(group, member, bump);
+        require!(
+            self.proposals_created == 0
+                || now >= self.last_proposal_at.saturating_add(cooldown_secs),
+            DaoError::ProposalCooldownActive
+        );
+        self.last_proposal_at = now;
+        self.proposals_created = self
+            .proposals_created
+            .checked_add(1)
+            .ok_or(DaoError::TallyOverflow)?;
+        self.last_active_at = now;
+        Ok(())
+    }
+
+    // A vote on the next proposal extends the streak and a gap restarts it. Votes on
+    // proposals older than the last one voted on count toward the total only.
+    pub fn record_vote(
+        &mut self,
+        group: Pubkey,
+        member: Pubkey,
+        bump: u8,
+        index: u64,
+        now: i64,
+    ) -> Result<()> {
+        self.init_if_new(group, member, bump);
+        if self.proposals_voted == 0 || index > self.last_voted_index {
+            self.current_streak = if self.proposals_voted > 0 && index == self.last_voted_index + 1
+            {
+                self.current_streak.saturating_add(1)
+            } else {
+                1
+            };
+            self.longest_streak = self.longest_streak.max(self.current_streak);
+            self.last_voted_index = index;
+        }
+        self.proposals_voted = self
+            .proposals_voted
+            .checked_add(1)
+            .ok_or(DaoError::TallyOverflow)?;
+        self.last_active_at = now;
+        Ok(())
+    }
+}
+
+impl ProposalSummary {
+    pub fn of(proposal: &Proposal) -> Self {
+        ProposalSummary {
+            proposal_id: proposal.proposal_id.clone(),
+            group_id: proposal.group_id.clone(),
+            title: proposal.title.clone(),
+            status: proposal.status,
+            voting_start: proposal.voting_start,
+            voting_end: proposal.voting_end,
+            choice_votes: proposal.choice_votes.clone(),
+            voter_count: proposal.offchain_voter_count.unwrap_or_else(|| {
+                proposal.voters.iter().filter(|v| v.vote_weight > 0).count() as u32
+            }),
+            winner: proposal.winner,
+            tied: proposal.tied,
+        }
+    }
+}
+
+impl DaoRegistry {
+    pub fn space(group_count: usize) -> usize {
+        8 + DaoRegistry::INIT_SPACE + group_count * GroupInfo::INIT_SPACE
+    }
+}
+
+impl VoterInfo {
+    // Size of an entry without a disclosure; disclosures are budgeted by their length
+    pub const BASE_SPACE: usize = VoterInfo::INIT_SPACE - 4 - MAX_DISCLOSURE_LEN;
+
+    pub fn disclosure_space(disclosure: Option<&String>) -> usize {
+        disclosure.map_or(0, |d| 4 + d.len())
+    }
+}
+
+impl Proposal {
+    // Size for `voter_count` voters, excluding their disclosure notes (see disclosure_space)
+    pub fn space(voter_count: usize) -> usize {
+        8 + Proposal::INIT_SPACE + voter_count * VoterInfo::BASE_SPACE
+    }
+
+    // Bytes taken by the disclosures recorded so far
+    pub fn disclosure_space(&self) -> usize {
+        self.voters
+            .iter()
+            .map(|v| VoterInfo::disclosure_space(v.disclosure.as_ref()))
+            .sum()
+    }
+
+    // Sum of all choice tallies; u128 because SOL-weighted tallies can each approach u64::MAX
+    pub fn total_votes(&self) -> u128 {
+        self.choice_votes.iter().map(|&votes| votes as u128).sum()
+    }
+}
+
+#[cfg(feature = "large-proposals")]
+impl ProposalLarge {
+    pub fn total_votes(&self) -> u128 {
+        self.choice_votes[..self.choice_count as usize]
+            .iter()
+            .map(|&votes| votes as u128)
+            .sum()
+    }
+}
+
+// Context Structs
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct Initialize<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + DaoRegistry::INIT_SPACE,
+        seeds = [b"dao_registry", name.as_bytes()],
+        bump
+    )]
+    pub dao_registry: Account<'info, DaoRegistry>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeProgramConfig<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + ProgramConfig::INIT_SPACE,
+        seeds = [b"program_config"],
+        bump
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    // The default registry: an empty name leaves its seeds at the bare prefix. Program-wide
+    // settings answer to it alone.
+    #[account(
+        seeds = [b"dao_registry"],
+        bump = dao_registry.bump,
+        constraint = dao_registry.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub dao_registry: Account<'info, DaoRegistry>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateProgramConfig<'info> {
+    #[account(mut, seeds = [b"program_config"], bump = program_config.bump)]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"dao_registry"],
+        bump = dao_registry.bump,
+        constraint = dao_registry.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub dao_registry: Account<'info, DaoRegistry>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetRegistryAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [b"dao_registry", dao_registry.name.as_bytes()],
+        bump = dao_registry.bump,
+        constraint = dao_registry.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub dao_registry: Account<'info, DaoRegistry>,
+
+    pub authority: Signer<'info>,
+}
+
+#[cfg(feature = "registry-multisig")]
+#[derive(Accounts)]
+pub struct CreateRegistryMultisig<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + RegistryMultisig::INIT_SPACE,
+        seeds = [b"registry_multisig", dao_registry.key().as_ref()],
+        bump
+    )]
+    pub multisig: Account<'info, RegistryMultisig>,
+
+    #[account(
+        mut,
+        seeds = [b"dao_registry", dao_registry.name.as_bytes()],
+        bump = dao_registry.bump,
+        constraint = dao_registry.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub dao_registry: Account<'info, DaoRegistry>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[cfg(feature = "registry-multisig")]
+#[derive(Accounts)]
+pub struct ProposeRegistryAction<'info> {
+    #[account(
+        init,
+        payer = proposer,
+        space = 8 + RegistryActionProposal::INIT_SPACE,
+        seeds = [
+            b"registry_action",
+            multisig.key().as_ref(),
+            &multisig.action_count.to_le_bytes()
+        ],
+        bump
+    )]
+    pub registry_action: Account<'info, RegistryActionProposal>,
+
+    #[account(
+        mut,
+        seeds = [b"registry_multisig", dao_registry.key().as_ref()],
+        bump = multisig.bump
+    )]
+    pub multisig: Account<'info, RegistryMultisig>,
+
+    #[account(mut, seeds = [b"dao_registry", dao_registry.name.as_bytes()], bump = dao_registry.bump)]
+    pub dao_registry: Account<'info, DaoRegistry>,
+
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[cfg(feature = "registry-multisig")]
+#[derive(Accounts)]
+pub struct ApproveRegistryAction<'info> {
+    #[account(
+        mut,
+        has_one = multisig @ DaoError::Unauthorized,
+        constraint = registry_action.executed_at == 0 @ DaoError::RegistryActionExecuted
+    )]
+    pub registry_action: Account<'info, RegistryActionProposal>,
+
+    #[account(
+        seeds = [b"registry_multisig", dao_registry.key().as_ref()],
+        bump = multisig.bump
+    )]
+    pub multisig: Account<'info, RegistryMultisig>,
+
+    #[account(mut, seeds = [b"dao_registry", dao_registry.name.as_bytes()], bump = dao_registry.bump)]
+    pub dao_registry: Account<'info, DaoRegistry>,
+
+    pub approver: Signer<'info>,
+}
+
+#[cfg(feature = "registry-multisig")]
+#[derive(Accounts)]
+pub struct ExecuteRegistryAction<'info> {
+    #[account(
+        mut,
+        has_one = multisig @ DaoError::Unauthorized,
+        constraint = registry_action.executed_at == 0 @ DaoError::RegistryActionExecuted
+    )]
+    pub registry_action: Account<'info, RegistryActionProposal>,
+
+    #[account(
+        mut,
+        seeds = [b"registry_multisig", dao_registry.key().as_ref()],
+        bump = multisig.bump
+    )]
+    pub multisig: Account<'info, RegistryMultisig>,
+
+    // A multisig that handed the registry to another authority can no longer act
+    #[account(
+        mut,
+        seeds = [b"dao_registry", dao_registry.name.as_bytes()],
+        bump = dao_registry.bump,
+        constraint = dao_registry.authority == multisig.key() @ DaoError::Unauthorized
+    )]
+    pub dao_registry: Account<'info, DaoRegistry>,
+
+    #[account(mut, seeds = [b"program_config"], bump = program_config.bump)]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+pub struct SetPause<'info> {
+    #[account(
+        mut,
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = dao_registry.authority == authority.key()
+            || program_config.guardian == Some(authority.key()) @ DaoError::Unauthorized
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    #[account(mut, seeds = [b"dao_registry"], bump = dao_registry.bump)]
+    pub dao_registry: Account<'info, DaoRegistry>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(group_id: String, name: String, description: String, metadata_uri: Option<String>, external_chat_id: Option<i64>)]
+pub struct CreateGroup<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Group::INIT_SPACE,
+        seeds = [b"group", group_id.as_bytes()],
+        bump
+    )]
+    pub group: Account<'info, Group>,
+
+    #[account(
+        mut,
+        seeds = [b"dao_registry", dao_registry.name.as_bytes()],
+        bump = dao_registry.bump,
+        realloc = DaoRegistry::space(dao_registry.groups.len() + 1),
+        realloc::payer = authority,
+        realloc::zero = false
+    )]
+    pub dao_registry: Account<'info, DaoRegistry>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    // Required when external_chat_id is set; fails if the chat is already linked
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + ChatLink::INIT_SPACE,
+        seeds = [b"chat_link".as_ref(), &external_chat_id.unwrap_or_default().to_le_bytes()],
+        bump
+    )]
+    pub chat_link: Option<Account<'info, ChatLink>>,
+}
+
+#[derive(Accounts)]
+#[instruction(external_chat_id: Option<i64>)]
+pub struct SetGroupChat<'info> {
+    #[account(
+        mut,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    // Required when external_chat_id is set; fails if the chat is already linked
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + ChatLink::INIT_SPACE,
+        seeds = [b"chat_link".as_ref(), &external_chat_id.unwrap_or_default().to_le_bytes()],
+        bump
+    )]
+    pub new_chat_link: Option<Account<'info, ChatLink>>,
+
+    // The group's current link, closed to the authority
+    #[account(mut)]
+    pub old_chat_link: Option<Account<'info, ChatLink>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "profiles")]
+#[derive(Accounts)]
+pub struct SetGroupProfile<'info> {
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + GroupProfile::INIT_SPACE,
+        seeds = [b"group_profile", group.key().as_ref()],
+        bump
+    )]
+    pub group_profile: Account<'info, GroupProfile>,
+
+    #[account(
+        mut,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "profiles")]
+#[derive(Accounts)]
+pub struct CloseGroupProfile<'info> {
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"group_profile", group.key().as_ref()],
+        bump = group_profile.bump
+    )]
+    pub group_profile: Account<'info, GroupProfile>,
+
+    #[account(constraint = group.authority == authority.key() @ DaoError::Unauthorized)]
+    pub group: Account<'info, Group>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetRegistrationConfig<'info> {
+    #[account(
+        mut,
+        seeds = [b"dao_registry", dao_registry.name.as_bytes()],
+        bump = dao_registry.bump,
+        constraint = dao_registry.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub dao_registry: Account<'info, DaoRegistry>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawGroupDeposit<'info> {
+    #[account(
+        mut,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "slugs")]
+#[derive(Accounts)]
+#[instruction(slug: String)]
+pub struct ReserveSlug<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + SlugReservation::INIT_SPACE,
+        seeds = [b"slug", dao_registry.key().as_ref(), slug.as_bytes()],
+        bump
+    )]
+    pub reservation: Account<'info, SlugReservation>,
+
+    #[account(mut, seeds = [b"dao_registry", dao_registry.name.as_bytes()], bump = dao_registry.bump)]
+    pub dao_registry: Account<'info, DaoRegistry>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[cfg(feature = "slugs")]
+#[derive(Accounts)]
+pub struct ReleaseSlug<'info> {
+    #[account(
+        mut,
+        close = owner,
+        constraint = reservation.owner == owner.key() @ DaoError::Unauthorized,
+        constraint = reservation.group.is_none() @ DaoError::SlugInUse
+    )]
+    pub reservation: Account<'info, SlugReservation>,
+
+    #[account(
+        mut,
+        seeds = [b"dao_registry", dao_registry.name.as_bytes()],
+        bump = dao_registry.bump,
+        constraint = reservation.registry == dao_registry.key() @ DaoError::SlugReservationMismatch
+    )]
+    pub dao_registry: Account<'info, DaoRegistry>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[cfg(feature = "slugs")]
+#[derive(Accounts)]
+#[instruction(new_slug: String)]
+pub struct SetGroupSlug<'info> {
+    #[account(
+        mut,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized,
+        constraint = group.registry == dao_registry.key() @ DaoError::WrongRegistry
+    )]
+    pub group: Account<'info, Group>,
+
+    #[account(
+        mut,
+        seeds = [b"slug", dao_registry.key().as_ref(), new_slug.as_bytes()],
+        bump = new_reservation.bump,
+        constraint = new_reservation.owner == authority.key() @ DaoError::Unauthorized,
+        constraint = new_reservation.group.is_none() @ DaoError::SlugInUse
+    )]
+    pub new_reservation: Account<'info, SlugReservation>,
+
+    #[account(mut)]
+    pub old_reservation: Option<Account<'info, SlugReservation>>,
+
+    #[account(seeds = [b"dao_registry", dao_registry.name.as_bytes()], bump = dao_registry.bump)]
+    pub dao_registry: Account<'info, DaoRegistry>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: String)]
+pub struct CreateProposal<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Proposal::INIT_SPACE,
+        seeds = [b"proposal", &group.key().to_bytes()[..8], &proposal_id.as_bytes()[..8]],
+        bump
+    )]
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + ProposalIndex::INIT_SPACE,
+        seeds = [b"proposal_index", group.key().as_ref(), &group.proposal_count.to_le_bytes()],
+        bump
+    )]
+    pub proposal_index: Account<'info, ProposalIndex>,
+
+    // The creator must be the group authority, or a council member for an emergency
+    #[account(mut)]
+    pub group: Account<'info, Group>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + Participation::INIT_SPACE,
+        seeds = [b"participation", group.key().as_ref(), authority.key().as_ref()],
+        bump
+    )]
+    pub participation: Account<'info, Participation>,
+
+    /// CHECK: The group's treasury PDA; receives the proposal creation fee
+    #[account(mut, seeds = [b"treasury", group.key().as_ref()], bump)]
+    pub treasury: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    // Required for emergency proposals, whose creator must sit on it
+    #[account(seeds = [b"council", group.key().as_ref()], bump = council.bump)]
+    pub council: Option<Account<'info, Council>>,
+}
+
+#[derive(Accounts)]
+#[instruction(choice_index: u8, disclosure: Option<String>)]
+pub struct VoteOnProposal<'info> {
+    #[account(
+        mut,
+        realloc = Proposal::space(proposal.voters.len() + 1)
+            + proposal.disclosure_space()
+            + VoterInfo::disclosure_space(disclosure.as_ref()),
+        realloc::payer = voter,
+        realloc::zero = false
+    )]
+    pub proposal: Account<'info, Proposal>,
+
+    // Writable only to advance its event sequence
+    #[account(mut, seeds = [b"group", proposal.group_id.as_bytes()], bump = group.bump)]
+    pub group: Account<'info, Group>,
+
+    #[account(mut)]
+    pub voter: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = voter,
+        space = 8 + Participation::INIT_SPACE,
+        seeds = [b"participation", group.key().as_ref(), voter.key().as_ref()],
+        bump
+    )]
+    pub participation: Account<'info, Participation>,
+
+    #[account(
+        init_if_needed,
+        payer = voter,
+        space = 8 + TurnoutStats::INIT_SPACE,
+        seeds = [b"turnout", proposal.key().as_ref()],
+        bump
+    )]
+    pub turnout: Account<'info, TurnoutStats>,
+
+    /// CHECK: This account is only used for SPL token voting, not for SOL voting
+    pub voter_token_account: Option<AccountInfo<'info>>,
+
+    /// CHECK: This account is only used for SPL token voting, not for SOL voting  
+    pub token_program: Option<AccountInfo<'info>>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    // The voter's user account, when voting with linked-wallet weight. The linked
+    // wallets (SOL) or their token accounts (SPL) follow as remaining accounts.
+    pub user_account: Option<Account<'info, UserAccount>>,
+
+    // Required on price-weighted proposals; the (holding, price account) pairs follow
+    // as remaining accounts (see priced_vote_weight)
+    #[account(seeds = [b"price_weighting", proposal.key().as_ref()], bump = price_weighting.bump)]
+    pub price_weighting: Option<Account<'info, PriceWeighting>>,
+
+    // The voter's member record; required in members-only groups and on member-weighted
+    // proposals
+    #[account(
+        seeds = [b"member", group.key().as_ref(), voter.key().as_ref()],
+        bump = member_record.bump
+    )]
+    pub member_record: Option<Account<'info, Member>>,
+
+    // Required on deposit-weighted proposals: the voter's deposit in their registrar
+    pub voter_deposit: Option<Account<'info, VoterDeposit>>,
+
+    // Required in groups that gate voting on dues: the voter's dues record
+    #[account(
+        seeds = [b"dues_record", group.key().as_ref(), voter.key().as_ref()],
+        bump = dues_record.bump
+    )]
+    pub dues_record: Option<Account<'info, DuesRecord>>,
+
+    // Required in stake-to-join groups: the voter's shares, locked against ragequit
+    // until the proposal's voting ends plus RAGEQUIT_GUARD_SECS
+    #[account(
+        mut,
+        seeds = [b"member_shares", group.key().as_ref(), voter.key().as_ref()],
+        bump = member_shares.bump
+    )]
+    pub member_shares: Option<Account<'info, MemberShares>>,
+
+    /// CHECK: The voter's ban, which need not exist; checked in require_not_banned
+    #[account(seeds = [b"ban", group.key().as_ref(), voter.key().as_ref()], bump)]
+    pub ban: UncheckedAccount<'info>,
+}
+
+#[cfg(feature = "large-proposals")]
+#[derive(Accounts)]
+pub struct CreateLargeProposal<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + std::mem::size_of::<ProposalLarge>(),
+        seeds = [b"proposal_large", group.key().as_ref(), &group.proposal_count.to_le_bytes()],
+        bump
+    )]
+    pub proposal: AccountLoader<'info, ProposalLarge>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + ProposalIndex::INIT_SPACE,
+        seeds = [b"proposal_index", group.key().as_ref(), &group.proposal_count.to_le_bytes()],
+        bump
+    )]
+    pub proposal_index: Account<'info, ProposalIndex>,
+
+    #[account(
+        mut,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + Participation::INIT_SPACE,
+        seeds = [b"participation", group.key().as_ref(), authority.key().as_ref()],
+        bump
+    )]
+    pub participation: Account<'info, Participation>,
+
+    /// CHECK: The group's treasury PDA; receives the proposal creation fee
+    #[account(mut, seeds = [b"treasury", group.key().as_ref()], bump)]
+    pub treasury: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+pub struct FundFinalizeBounty<'info> {
+    #[account(mut)]
+    pub proposal: Account<'info, Proposal>,
+
+    // Writable only to advance its event sequence
+    #[account(mut, seeds = [b"group", proposal.group_id.as_bytes()], bump = group.bump)]
+    pub group: Account<'info, Group>,
+
+    #[account(mut)]
+    pub funder: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+// Status changes made by the group authority
+#[cfg(feature = "deposit-voting")]
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct ConfigureDepositRegistrar<'info> {
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + DepositRegistrar::INIT_SPACE,
+        seeds = [b"registrar", group.key().as_ref(), mint.as_ref()],
+        bump
+    )]
+    pub registrar: Account<'info, DepositRegistrar>,
+
+    #[account(
+        mut,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// CHECK: SPL registrars only; must be a token account of the mint owned by the registrar
+    pub vault: Option<AccountInfo<'info>>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "deposit-voting")]
+#[derive(Accounts)]
+pub struct DepositVotingTokens<'info> {
+    #[account(
+        seeds = [b"registrar", group.key().as_ref(), registrar.mint.as_ref()],
+        bump = registrar.bump
+    )]
+    pub registrar: Account<'info, DepositRegistrar>,
+
+    // Writable only to advance its event sequence
+    #[account(mut, address = registrar.group)]
+    pub group: Account<'info, Group>,
+
+    #[account(
+        init_if_needed,
+        payer = voter,
+        space = 8 + VoterDeposit::INIT_SPACE,
+        seeds = [b"voter_deposit", registrar.key().as_ref(), voter.key().as_ref()],
+        bump
+    )]
+    pub voter_deposit: Account<'info, VoterDeposit>,
+
+    #[account(mut)]
+    pub voter: Signer<'info>,
+
+    /// CHECK: SPL registrars only; must be the registrar's vault
+    #[account(mut)]
+    pub vault: Option<AccountInfo<'info>>,
+
+    /// CHECK: SPL registrars only; the token program checks the voter owns it
+    #[account(mut)]
+    pub voter_token_account: Option<AccountInfo<'info>>,
+
+    /// CHECK: SPL registrars only; checked to be the token program
+    pub token_program: Option<AccountInfo<'info>>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "deposit-voting")]
+#[derive(Accounts)]
+pub struct WithdrawVotingTokens<'info> {
+    #[account(
+        seeds = [b"registrar", group.key().as_ref(), registrar.mint.as_ref()],
+        bump = registrar.bump
+    )]
+    pub registrar: Account<'info, DepositRegistrar>,
+
+    // Writable only to advance its event sequence
+    #[account(mut, address = registrar.group)]
+    pub group: Account<'info, Group>,
+
+    #[account(
+        mut,
+        seeds = [b"voter_deposit", registrar.key().as_ref(), voter.key().as_ref()],
+        bump = voter_deposit.bump
+    )]
+    pub voter_deposit: Account<'info, VoterDeposit>,
+
+    #[account(mut)]
+    pub voter: Signer<'info>,
+
+    /// CHECK: SPL registrars only; must be the registrar's vault
+    #[account(mut)]
+    pub vault: Option<AccountInfo<'info>>,
+
+    /// CHECK: SPL registrars only; receives the withdrawn tokens
+    #[account(mut)]
+    pub voter_token_account: Option<AccountInfo<'info>>,
+
+    /// CHECK: SPL registrars only; checked to be the token program
+    pub token_program: Option<AccountInfo<'info>>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "deposit-voting")]
+#[derive(Accounts)]
+pub struct SetDepositVoting<'info> {
+    #[account(mut)]
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(
+        mut,
+        seeds = [b"group", proposal.group_id.as_bytes()],
+        bump = group.bump,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    #[account(
+        seeds = [b"registrar", group.key().as_ref(), registrar.mint.as_ref()],
+        bump = registrar.bump
+    )]
+    pub registrar: Account<'info, DepositRegistrar>,
+
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "bicameral")]
+#[derive(Accounts)]
+pub struct SetBicameral<'info> {
+    #[account(mut)]
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(
+        mut,
+        seeds = [b"group", proposal.group_id.as_bytes()],
+        bump = group.bump,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    // Needed only to turn the chamber on
+    #[account(seeds = [b"council", group.key().as_ref()], bump = council.bump)]
+    pub council: Option<Account<'info, Council>>,
+
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "bicameral")]
+#[derive(Accounts)]
+pub struct CouncilVote<'info> {
+    #[account(mut)]
+    pub proposal: Account<'info, Proposal>,
+
+    // Writable only to advance its event sequence
+    #[account(mut, seeds = [b"group", proposal.group_id.as_bytes()], bump = group.bump)]
+    pub group: Account<'info, Group>,
+
+    pub council_member: Signer<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+// As ManageProposal, with the draft's deposit PDA for groups that require one
+#[derive(Accounts)]
+pub struct ActivateProposal<'info> {
+    #[account(mut)]
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(
+        mut,
+        seeds = [b"group", proposal.group_id.as_bytes()],
+        bump = group.bump,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    /// CHECK: Seeds-checked; may not exist when the group requires no deposit
+    #[account(seeds = [b"proposal_deposit", proposal.key().as_ref()], bump)]
+    pub proposal_deposit: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ManageProposal<'info> {
+    #[account(mut)]
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(
+        mut,
+        seeds = [b"group", proposal.group_id.as_bytes()],
+        bump = group.bump,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "sponsorship")]
+#[derive(Accounts)]
+pub struct CoSponsor<'info> {
+    #[account(mut)]
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(mut, seeds = [b"group", proposal.group_id.as_bytes()], bump = group.bump)]
+    pub group: Account<'info, Group>,
+
+    // Only listed members sponsor; credential holders without a Member account can't
+    #[account(
+        seeds = [b"member", group.key().as_ref(), sponsor.key().as_ref()],
+        bump = member_record.bump
+    )]
+    pub member_record: Account<'info, Member>,
+
+    #[account(
+        init,
+        payer = sponsor,
+        space = 8 + ProposalSponsor::INIT_SPACE,
+        seeds = [b"sponsor", proposal.key().as_ref(), sponsor.key().as_ref()],
+        bump
+    )]
+    pub sponsorship: Account<'info, ProposalSponsor>,
+
+    #[account(mut)]
+    pub sponsor: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    /// CHECK: Seeds-checked; the last sponsor opens the draft, which needs its deposit
+    #[account(seeds = [b"proposal_deposit", proposal.key().as_ref()], bump)]
+    pub proposal_deposit: UncheckedAccount<'info>,
+}
+
+#[cfg(feature = "callbacks")]
+#[derive(Accounts)]
+pub struct SetExecutionCallback<'info> {
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + ExecutionCallback::INIT_SPACE,
+        seeds = [b"callback", proposal.key().as_ref()],
+        bump
+    )]
+    pub callback: Account<'info, ExecutionCallback>,
+
+    #[account(
+        mut,
+        seeds = [b"group", proposal.group_id.as_bytes()],
+        bump = group.bump,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "callbacks")]
+#[derive(Accounts)]
+pub struct ExecuteProposalCallback<'info> {
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(mut, seeds = [b"group", proposal.group_id.as_bytes()], bump = group.bump)]
+    pub group: Account<'info, Group>,
+
+    #[account(
+        mut,
+        close = group_authority,
+        seeds = [b"callback", proposal.key().as_ref()],
+        bump = callback.bump
+    )]
+    pub callback: Account<'info, ExecutionCallback>,
+
+    /// CHECK: The program named by the callback; only invoked
+    #[account(
+        executable,
+        address = callback.on_success_program @ DaoError::ExecutionAccountMismatch
+    )]
+    pub on_success_program: UncheckedAccount<'info>,
+
+    /// CHECK: Receives the callback's rent
+    #[account(mut, address = group.authority @ DaoError::ExecutionAccountMismatch)]
+    pub group_authority: UncheckedAccount<'info>,
+
+    pub executor: Signer<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    #[account(seeds = [b"program_allowlist", group.key().as_ref()], bump = program_allowlist.bump)]
+    pub program_allowlist: Account<'info, ProgramAllowlist>,
+}
+
+#[cfg(feature = "callbacks")]
+#[derive(Accounts)]
+pub struct SetProgramAllowlist<'info> {
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + ProgramAllowlist::INIT_SPACE,
+        seeds = [b"program_allowlist", group.key().as_ref()],
+        bump
+    )]
+    pub program_allowlist: Account<'info, ProgramAllowlist>,
+
+    #[account(
+        mut,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "choice-details")]
+#[derive(Accounts)]
+pub struct SetChoiceDetails<'info> {
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + ChoiceDetails::INIT_SPACE,
+        seeds = [b"choice_details", proposal.key().as_ref()],
+        bump
+    )]
+    pub choice_details: Account<'info, ChoiceDetails>,
+
+    #[account(
+        mut,
+        seeds = [b"group", proposal.group_id.as_bytes()],
+        bump = group.bump,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "choice-details")]
+#[derive(Accounts)]
+pub struct CloseChoiceDetails<'info> {
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"choice_details", proposal.key().as_ref()],
+        bump = choice_details.bump
+    )]
+    pub choice_details: Account<'info, ChoiceDetails>,
+
+    #[account(
+        seeds = [b"group", proposal.group_id.as_bytes()],
+        bump = group.bump,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelProposal<'info> {
+    #[account(mut)]
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(
+        mut,
+        seeds = [b"group", proposal.group_id.as_bytes()],
+        bump = group.bump,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    pub authority: Signer<'info>,
+
+    /// CHECK: Only receives lamports; must be the configured fee destination
+    #[account(mut, address = program_config.fee_destination)]
+    pub fee_destination: AccountInfo<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "treasury")]
+#[derive(Accounts)]
+pub struct ExecuteTreasuryTransfer<'info> {
+    #[account(mut)]
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(mut, seeds = [b"group", proposal.group_id.as_bytes()], bump = group.bump)]
+    pub group: Account<'info, Group>,
+
+    /// CHECK: The group's treasury PDA, which pays the transfer
+    #[account(mut, seeds = [b"treasury", group.key().as_ref()], bump)]
+    pub treasury: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the transfer's recipient in the handler
+    #[account(mut)]
+    pub recipient: UncheckedAccount<'info>,
+
+    pub executor: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "treasury")]
+#[derive(Accounts)]
+pub struct ExecuteTreasuryRecovery<'info> {
+    #[account(mut)]
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(mut, seeds = [b"group", proposal.group_id.as_bytes()], bump = group.bump)]
+    pub group: Account<'info, Group>,
+
+    /// CHECK: The group's treasury PDA, drained to the recovery address
+    #[account(mut, seeds = [b"treasury", group.key().as_ref()], bump)]
+    pub treasury: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the proposal's recovery address in the handler
+    #[account(mut)]
+    pub recovery: UncheckedAccount<'info>,
+
+    pub executor: Signer<'info>,
+
+    /// CHECK: Only needed to drain SPL balances; checked to be the token program
+    pub token_program: Option<AccountInfo<'info>>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "treasury-staking")]
+#[derive(Accounts)]
+pub struct ExecuteTreasuryStake<'info> {
+    #[account(mut)]
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(mut, seeds = [b"group", proposal.group_id.as_bytes()], bump = group.bump)]
+    pub group: Account<'info, Group>,
+
+    /// CHECK: The group's treasury PDA, staker and withdrawer of its stake accounts
+    #[account(mut, seeds = [b"treasury", group.key().as_ref()], bump)]
+    pub treasury: UncheckedAccount<'info>,
+
+    /// CHECK: Created at the proposal's stake PDA for a delegation, otherwise checked
+    /// against the proposal's stake account in the handler
+    #[account(mut)]
+    pub stake_account: UncheckedAccount<'info>,
+
+    /// CHECK: The validator vote account a delegation names; validated by the stake program
+    pub vote_account: Option<UncheckedAccount<'info>>,
+
+    pub executor: Signer<'info>,
+
+    pub clock: Sysvar<'info, Clock>,
+    pub rent: Sysvar<'info, Rent>,
+
+    /// CHECK: The stake history sysvar
+    #[account(address = anchor_lang::solana_program::sysvar::stake_history::ID)]
+    pub stake_history: UncheckedAccount<'info>,
+
+    /// CHECK: The stake config account, still passed to delegations
+    #[account(address = anchor_lang::solana_program::stake::config::ID)]
+    pub stake_config: UncheckedAccount<'info>,
+
+    /// CHECK: The native stake program
+    #[account(address = anchor_lang::solana_program::stake::program::ID)]
+    pub stake_program: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "treasury-swaps")]
+#[derive(Accounts)]
+pub struct ExecuteTreasurySwap<'info> {
+    #[account(mut)]
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(mut, seeds = [b"group", proposal.group_id.as_bytes()], bump = group.bump)]
+    pub group: Account<'info, Group>,
+
+    /// CHECK: The group's treasury PDA, which signs the swap as the tokens' owner
+    #[account(mut, seeds = [b"treasury", group.key().as_ref()], bump)]
+    pub treasury: UncheckedAccount<'info>,
+
+    /// CHECK: Treasury token account of the input mint; checked in the handler
+    #[account(mut)]
+    pub treasury_input: UncheckedAccount<'info>,
+
+    /// CHECK: Treasury token account of the output mint; checked in the handler
+    #[account(mut)]
+    pub treasury_output: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the proposal's swap program in the handler
+    pub swap_program: UncheckedAccount<'info>,
+
+    pub executor: Signer<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteConfigChange<'info> {
+    #[account(mut)]
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(mut, seeds = [b"group", proposal.group_id.as_bytes()], bump = group.bump)]
+    pub group: Account<'info, Group>,
+
+    pub executor: Signer<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteMembershipChange<'info> {
+    #[account(mut)]
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(mut, seeds = [b"group", proposal.group_id.as_bytes()], bump = group.bump)]
+    pub group: Account<'info, Group>,
+
+    // Record of the wallet the change names: created for an addition, closed for a removal
+    #[account(
+        init_if_needed,
+        payer = executor,
+        space = 8 + Member::INIT_SPACE,
+        seeds = [b"member", group.key().as_ref(), proposal.kind.membership_member().as_ref()],
+        bump
+    )]
+    pub member_record: Account<'info, Member>,
+
+    /// CHECK: Receives a removed member's record rent
+    #[account(mut, address = group.authority @ DaoError::ExecutionAccountMismatch)]
+    pub group_authority: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub executor: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    /// CHECK: The added member's ban, which need not exist; checked in admit_member
+    #[account(seeds = [b"ban", group.key().as_ref(), proposal.kind.membership_member().as_ref()], bump)]
+    pub ban: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteElection<'info> {
+    #[account(mut)]
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(mut, seeds = [b"group", proposal.group_id.as_bytes()], bump = group.bump)]
+    pub group: Account<'info, Group>,
+
+    #[account(
+        init_if_needed,
+        payer = executor,
+        space = 8 + Council::INIT_SPACE,
+        seeds = [b"council", group.key().as_ref()],
+        bump
+    )]
+    pub council: Account<'info, Council>,
+
+    #[account(mut)]
+    pub executor: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "token-distribution")]
+#[derive(Accounts)]
+pub struct ExecuteTokenDistribution<'info> {
+    #[account(mut)]
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(mut, seeds = [b"group", proposal.group_id.as_bytes()], bump = group.bump)]
+    pub group: Account<'info, Group>,
+
+    #[account(
+        init,
+        payer = executor,
+        space = 8 + TokenDistribution::INIT_SPACE,
+        seeds = [b"distribution", proposal.key().as_ref()],
+        bump
+    )]
+    pub distribution: Account<'info, TokenDistribution>,
+
+    #[account(mut)]
+    pub executor: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "token-distribution")]
+#[derive(Accounts)]
+pub struct ClaimTokenDistribution<'info> {
+    #[account(
+        mut,
+        seeds = [b"distribution", distribution.proposal.as_ref()],
+        bump = distribution.bump
+    )]
+    pub distribution: Account<'info, TokenDistribution>,
+
+    // Fails if the recipient has already claimed
+    #[account(
+        init,
+        payer = recipient,
+        space = 8 + DistributionClaim::INIT_SPACE,
+        seeds = [
+            b"distribution_claim",
+            distribution.key().as_ref(),
+            recipient.key().as_ref()
+        ],
+        bump
+    )]
+    pub claim: Account<'info, DistributionClaim>,
+
+    // Writable only to advance its event sequence
+    #[account(mut, address = distribution.group)]
+    pub group: Account<'info, Group>,
+
+    /// CHECK: The group's treasury PDA: mint authority or owner of the paying account
+    #[account(seeds = [b"treasury", group.key().as_ref()], bump)]
+    pub treasury: UncheckedAccount<'info>,
+
+    /// CHECK: The distributed mint, validated by the token program
+    #[account(mut, address = distribution.mint)]
+    pub mint: UncheckedAccount<'info>,
+
+    /// CHECK: Treasury token account paying a Treasury-sourced distribution; checked
+    /// in the handler
+    #[account(mut)]
+    pub treasury_token_account: Option<AccountInfo<'info>>,
+
+    /// CHECK: Token account of the mint receiving the tokens; checked in the handler
+    #[account(mut)]
+    pub recipient_token_account: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub recipient: Signer<'info>,
+
+    /// CHECK: The SPL token program
+    #[account(address = anchor_spl::token::ID)]
+    pub token_program: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "grants")]
+#[derive(Accounts)]
+pub struct ExecuteGrant<'info> {
+    #[account(mut)]
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(mut, seeds = [b"group", proposal.group_id.as_bytes()], bump = group.bump)]
+    pub group: Account<'info, Group>,
+
+    #[account(
+        init,
+        payer = executor,
+        space = 8 + Grant::INIT_SPACE,
+        seeds = [b"grant", proposal.key().as_ref()],
+        bump
+    )]
+    pub grant: Account<'info, Grant>,
+
+    #[account(mut)]
+    pub executor: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "grants")]
+#[derive(Accounts)]
+#[instruction(milestone: u8)]
+pub struct ApproveMilestone<'info> {
+    #[account(
+        mut,
+        seeds = [b"grant", grant.proposal.as_ref()],
+        bump = grant.bump
+    )]
+    pub grant: Account<'info, Grant>,
+
+    // Writable only to advance its event sequence
+    #[account(mut, address = grant.group)]
+    pub group: Account<'info, Group>,
+
+    #[account(
+        init,
+        payer = approver,
+        space = 8 + MilestoneSignoff::INIT_SPACE,
+        seeds = [
+            b"milestone_signoff",
+            grant.key().as_ref(),
+            &[milestone],
+            approver.key().as_ref()
+        ],
+        bump
+    )]
+    pub signoff: Account<'info, MilestoneSignoff>,
+
+    #[account(mut)]
+    pub approver: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    // Required on council-approved grants
+    #[account(seeds = [b"council", group.key().as_ref()], bump = council.bump)]
+    pub council: Option<Account<'info, Council>>,
+
+    // Required on member-voted grants: the approver's member record
+    #[account(
+        seeds = [b"member", group.key().as_ref(), approver.key().as_ref()],
+        bump = member_record.bump
+    )]
+    pub member_record: Option<Account<'info, Member>>,
+}
+
+#[cfg(feature = "grants")]
+#[derive(Accounts)]
+pub struct ClaimGrantTranche<'info> {
+    #[account(
+        mut,
+        seeds = [b"grant", grant.proposal.as_ref()],
+        bump = grant.bump,
+        has_one = recipient @ DaoError::Unauthorized
+    )]
+    pub grant: Account<'info, Grant>,
+
+    // Writable only to advance its event sequence
+    #[account(mut, address = grant.group)]
+    pub group: Account<'info, Group>,
+
+    /// CHECK: The group's treasury PDA, which pays the tranche
+    #[account(mut, seeds = [b"treasury", group.key().as_ref()], bump)]
+    pub treasury: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub recipient: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "streams")]
+#[derive(Accounts)]
+pub struct ExecuteStream<'info> {
+    #[account(mut)]
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(mut, seeds = [b"group", proposal.group_id.as_bytes()], bump = group.bump)]
+    pub group: Account<'info, Group>,
+
+    #[account(
+        init,
+        payer = executor,
+        space = 8 + Stream::INIT_SPACE,
+        seeds = [b"stream", proposal.key().as_ref()],
+        bump
+    )]
+    pub stream: Account<'info, Stream>,
+
+    #[account(mut)]
+    pub executor: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "streams")]
+#[derive(Accounts)]
+pub struct WithdrawVested<'info> {
+    #[account(
+        mut,
+        seeds = [b"stream", stream.proposal.as_ref()],
+        bump = stream.bump,
+        has_one = recipient @ DaoError::Unauthorized
+    )]
+    pub stream: Account<'info, Stream>,
+
+    // Writable only to advance its event sequence
+    #[account(mut, address = stream.group)]
+    pub group: Account<'info, Group>,
+
+    /// CHECK: The group's treasury PDA, which pays the stream
+    #[account(mut, seeds = [b"treasury", group.key().as_ref()], bump)]
+    pub treasury: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub recipient: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "bounties")]
+#[derive(Accounts)]
+#[instruction(bounty_id: String)]
+pub struct PostBounty<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Bounty::INIT_SPACE,
+        seeds = [b"bounty", group.key().as_ref(), bounty_id.as_bytes()],
+        bump
+    )]
+    pub bounty: Account<'info, Bounty>,
+
+    #[account(
+        mut,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "bounties")]
+#[derive(Accounts)]
+#[instruction(bounty_id: String)]
+pub struct ExecuteBounty<'info> {
+    #[account(mut)]
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(mut, seeds = [b"group", proposal.group_id.as_bytes()], bump = group.bump)]
+    pub group: Account<'info, Group>,
+
+    #[account(
+        init,
+        payer = executor,
+        space = 8 + Bounty::INIT_SPACE,
+        seeds = [b"bounty", group.key().as_ref(), bounty_id.as_bytes()],
+        bump
+    )]
+    pub bounty: Account<'info, Bounty>,
+
+    #[account(mut)]
+    pub executor: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "bounties")]
+#[derive(Accounts)]
+pub struct ClaimBounty<'info> {
+    #[account(
+        mut,
+        seeds = [b"bounty", group.key().as_ref(), bounty.bounty_id.as_bytes()],
+        bump = bounty.bump
+    )]
+    pub bounty: Account<'info, Bounty>,
+
+    // Writable only to advance its event sequence
+    #[account(mut, address = bounty.group)]
+    pub group: Account<'info, Group>,
+
+    pub hunter: Signer<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "bounties")]
+#[derive(Accounts)]
+pub struct SubmitBounty<'info> {
+    #[account(
+        mut,
+        seeds = [b"bounty", group.key().as_ref(), bounty.bounty_id.as_bytes()],
+        bump = bounty.bump
+    )]
+    pub bounty: Account<'info, Bounty>,
+
+    // Writable only to advance its event sequence
+    #[account(mut, address = bounty.group)]
+    pub group: Account<'info, Group>,
+
+    pub hunter: Signer<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "bounties")]
+#[derive(Accounts)]
+pub struct ApproveBounty<'info> {
+    #[account(
+        mut,
+        seeds = [b"bounty", group.key().as_ref(), bounty.bounty_id.as_bytes()],
+        bump = bounty.bump
+    )]
+    pub bounty: Account<'info, Bounty>,
+
+    #[account(
+        mut,
+        address = bounty.group,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    /// CHECK: The group's treasury PDA, which pays the reward
+    #[account(mut, seeds = [b"treasury", group.key().as_ref()], bump)]
+    pub treasury: UncheckedAccount<'info>,
+
+    /// CHECK: Receives the reward; must be the bounty's hunter
+    #[account(mut)]
+    pub hunter: UncheckedAccount<'info>,
+
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "bounties")]
+#[derive(Accounts)]
+pub struct CancelBounty<'info> {
+    #[account(
+        mut,
+        seeds = [b"bounty", group.key().as_ref(), bounty.bounty_id.as_bytes()],
+        bump = bounty.bump
+    )]
+    pub bounty: Account<'info, Bounty>,
+
+    #[account(
+        mut,
+        address = bounty.group,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeProposal<'info> {
+    #[account(mut)]
+    pub proposal: Account<'info, Proposal>,
+
+    // Writable only to advance its event sequence
+    #[account(mut, seeds = [b"group", proposal.group_id.as_bytes()], bump = group.bump)]
+    pub group: Account<'info, Group>,
+
+    // Written once here and never modified
+    #[account(
+        init,
+        payer = cranker,
+        space = 8 + ProposalResult::INIT_SPACE,
+        seeds = [b"proposal_result", proposal.key().as_ref()],
+        bump
+    )]
+    pub proposal_result: Account<'info, ProposalResult>,
+
+    // Anyone may finalize; they pay the result's rent and receive the proposal's bounty
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    // The proposal's voter lottery, drawn here when passed along with the slot hashes
+    #[account(
+        mut,
+        seeds = [b"lottery", proposal.key().as_ref()],
+        bump = lottery.bump
+    )]
+    pub lottery: Option<Account<'info, VoterLottery>>,
+
+    /// CHECK: Address-constrained to the slot hashes sysvar, read for the lottery's randomness
+    #[account(address = anchor_lang::solana_program::sysvar::slot_hashes::ID)]
+    pub slot_hashes: Option<AccountInfo<'info>>,
+
+    /// CHECK: Needed only under a weight quorum: the token mint of SPL proposals, or the
+    /// registrar of SOL deposit-weighted ones; checked in weight_turnout
+    pub quorum_supply: Option<AccountInfo<'info>>,
+}
+
+// No pause check: migrations are meant to run during a maintenance pause, between a
+// program upgrade and unpausing
+#[derive(Accounts)]
+pub struct MigrateAccount<'info> {
+    /// CHECK: Any Group, Proposal or UserAccount; migrate_account checks the discriminator
+    #[account(mut, owner = crate::ID)]
+    pub account: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ResolveTie<'info> {
+    #[account(mut)]
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(mut, seeds = [b"group", proposal.group_id.as_bytes()], bump = group.bump)]
+    pub group: Account<'info, Group>,
+
+    #[account(seeds = [b"council", group.key().as_ref()], bump = council.bump)]
+    pub council: Account<'info, Council>,
+
+    #[account(
+        init,
+        payer = council_member,
+        space = 8 + ProposalResult::INIT_SPACE,
+        seeds = [b"proposal_result", proposal.key().as_ref()],
+        bump
+    )]
+    pub proposal_result: Account<'info, ProposalResult>,
+
+    #[account(mut)]
+    pub council_member: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    /// CHECK: As in FinalizeProposal; checked in weight_turnout
+    pub quorum_supply: Option<AccountInfo<'info>>,
+}
+
+#[cfg(feature = "automation")]
+#[derive(Accounts)]
+pub struct ScheduleActivation<'info> {
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(
+        mut,
+        seeds = [b"group", proposal.group_id.as_bytes()],
+        bump = group.bump,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + ProposalSchedule::INIT_SPACE,
+        seeds = [b"schedule", proposal.key().as_ref()],
+        bump
+    )]
+    pub schedule: Account<'info, ProposalSchedule>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "templates")]
+#[derive(Accounts)]
+#[instruction(template_id: String)]
+pub struct CreateProposalTemplate<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + ProposalTemplate::INIT_SPACE,
+        seeds = [b"proposal_template", group.key().as_ref(), template_id.as_bytes()],
+        bump
+    )]
+    pub template: Account<'info, ProposalTemplate>,
+
+    #[account(
+        mut,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+// The accounts of create_proposal, plus the template
+#[cfg(feature = "templates")]
+#[derive(Accounts)]
+#[instruction(proposal_id: String)]
+pub struct InstantiateFromTemplate<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Proposal::INIT_SPACE,
+        seeds = [b"proposal", &group.key().to_bytes()[..8], &proposal_id.as_bytes()[..8]],
+        bump
+    )]
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + ProposalIndex::INIT_SPACE,
+        seeds = [b"proposal_index", group.key().as_ref(), &group.proposal_count.to_le_bytes()],
+        bump
+    )]
+    pub proposal_index: Account<'info, ProposalIndex>,
+
+    #[account(
+        mut,
+        seeds = [b"proposal_template", group.key().as_ref(), template.template_id.as_bytes()],
+        bump = template.bump
+    )]
+    pub template: Account<'info, ProposalTemplate>,
+
+    #[account(
+        mut,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + Participation::INIT_SPACE,
+        seeds = [b"participation", group.key().as_ref(), authority.key().as_ref()],
+        bump
+    )]
+    pub participation: Account<'info, Participation>,
+
+    /// CHECK: The group's treasury PDA; receives the proposal creation fee
+    #[account(mut, seeds = [b"treasury", group.key().as_ref()], bump)]
+    pub treasury: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "templates")]
+#[derive(Accounts)]
+pub struct CloseProposalTemplate<'info> {
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"proposal_template", group.key().as_ref(), template.template_id.as_bytes()],
+        bump = template.bump
+    )]
+    pub template: Account<'info, ProposalTemplate>,
+
+    #[account(
+        mut,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+// The same accounts on every call, whichever step is due
+#[cfg(feature = "automation")]
+#[derive(Accounts)]
+pub struct Tick<'info> {
+    #[account(mut)]
+    pub proposal: Account<'info, Proposal>,
+
+    // Writable only to advance its event sequence
+    #[account(mut, seeds = [b"group", proposal.group_id.as_bytes()], bump = group.bump)]
+    pub group: Account<'info, Group>,
+
+    // Present only for drafts scheduled to open; the program id stands for None
+    #[account(seeds = [b"schedule", proposal.key().as_ref()], bump = schedule.bump)]
+    pub schedule: Option<Account<'info, ProposalSchedule>>,
+
+    /// CHECK: Created and written here when the proposal is finalized; the address is
+    /// fixed by seeds, and whichever of tick or finalize_proposal writes it first leaves
+    /// the proposal settled, so the other never touches it
+    #[account(mut, seeds = [b"proposal_result", proposal.key().as_ref()], bump)]
+    pub proposal_result: UncheckedAccount<'info>,
+
+    /// CHECK: Seeds-checked; opening a draft needs its deposit if the group requires one
+    #[account(seeds = [b"proposal_deposit", proposal.key().as_ref()], bump)]
+    pub proposal_deposit: UncheckedAccount<'info>,
+
+    // The automation thread's signer; pays the result's rent and collects the bounty
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    /// CHECK: The voting supply for weight quorums, as in finalize_proposal
+    pub quorum_supply: Option<AccountInfo<'info>>,
+}
+
+#[cfg(feature = "joint-proposals")]
+#[derive(Accounts)]
+#[instruction(joint_id: String)]
+pub struct CreateJointProposal<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + JointProposal::INIT_SPACE,
+        seeds = [b"joint_proposal", joint_id.as_bytes()],
+        bump
+    )]
+    pub joint_proposal: Account<'info, JointProposal>,
+
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(
+        mut,
+        seeds = [b"group", proposal.group_id.as_bytes()],
+        bump = group.bump,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "joint-proposals")]
+#[derive(Accounts)]
+pub struct JoinJointProposal<'info> {
+    #[account(
+        mut,
+        seeds = [b"joint_proposal", joint_proposal.joint_id.as_bytes()],
+        bump = joint_proposal.bump
+    )]
+    pub joint_proposal: Account<'info, JointProposal>,
+
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(
+        mut,
+        seeds = [b"group", proposal.group_id.as_bytes()],
+        bump = group.bump,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "joint-proposals")]
+#[derive(Accounts)]
+pub struct ResolveJointProposal<'info> {
+    #[account(
+        mut,
+        seeds = [b"joint_proposal", joint_proposal.joint_id.as_bytes()],
+        bump = joint_proposal.bump
+    )]
+    pub joint_proposal: Account<'info, JointProposal>,
+
+    // Writable only to advance its event sequence; joint proposals span groups
+    #[account(mut, seeds = [b"dao_registry"], bump = dao_registry.bump)]
+    pub dao_registry: Account<'info, DaoRegistry>,
+
+    pub resolver: Signer<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "treasury")]
+#[derive(Accounts)]
+pub struct InitializeProgramGovernance<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + ProgramGovernance::INIT_SPACE,
+        seeds = [b"program_governance"],
+        bump
+    )]
+    pub program_governance: Account<'info, ProgramGovernance>,
+
+    pub group: Account<'info, Group>,
+
+    /// CHECK: PDA address only; it holds no data and signs upgrades via invoke_signed
+    #[account(seeds = [b"treasury", group.key().as_ref()], bump)]
+    pub treasury: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"dao_registry"],
+        bump = dao_registry.bump,
+        constraint = dao_registry.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub dao_registry: Account<'info, DaoRegistry>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "treasury")]
+#[derive(Accounts)]
+pub struct ProposeProgramUpgrade<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + UpgradeProposal::INIT_SPACE,
+        seeds = [b"program_upgrade", proposal.key().as_ref()],
+        bump
+    )]
+    pub upgrade_proposal: Account<'info, UpgradeProposal>,
+
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(
+        mut,
+        seeds = [b"group", proposal.group_id.as_bytes()],
+        bump = group.bump,
+        constraint = group.key() == program_governance.group @ DaoError::NotGoverningGroup,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    #[account(seeds = [b"program_governance"], bump = program_governance.bump)]
+    pub program_governance: Account<'info, ProgramGovernance>,
+
+    /// CHECK: Must be an upgradeable-loader buffer; the loader checks its authority on upgrade
+    #[account(
+        owner = anchor_lang::solana_program::bpf_loader_upgradeable::ID
+            @ DaoError::InvalidUpgradeBuffer
+    )]
+    pub buffer: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "treasury")]
+#[derive(Accounts)]
+pub struct QueueProgramUpgrade<'info> {
+    #[account(
+        mut,
+        seeds = [b"program_upgrade", upgrade_proposal.proposal.as_ref()],
+        bump = upgrade_proposal.bump
+    )]
+    pub upgrade_proposal: Account<'info, UpgradeProposal>,
+
+    #[account(
+        seeds = [b"proposal_result", upgrade_proposal.proposal.as_ref()],
+        bump = proposal_result.bump
+    )]
+    pub proposal_result: Account<'info, ProposalResult>,
+
+    // Writable only to advance its event sequence
+    #[account(mut, address = program_governance.group)]
+    pub group: Account<'info, Group>,
+
+    #[account(seeds = [b"program_governance"], bump = program_governance.bump)]
+    pub program_governance: Account<'info, ProgramGovernance>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "treasury")]
+#[derive(Accounts)]
+pub struct ExecuteProgramUpgrade<'info> {
+    #[account(
+        mut,
+        seeds = [b"program_upgrade", proposal.key().as_ref()],
+        bump = upgrade_proposal.bump
+    )]
+    pub upgrade_proposal: Account<'info, UpgradeProposal>,
+
+    #[account(mut)]
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(mut, address = program_governance.group)]
+    pub group: Account<'info, Group>,
+
+    #[account(mut, seeds = [b"program_governance"], bump = program_governance.bump)]
+    pub program_governance: Account<'info, ProgramGovernance>,
+
+    /// CHECK: The group's treasury PDA, which must be the program's upgrade authority
+    #[account(seeds = [b"treasury", group.key().as_ref()], bump)]
+    pub treasury: UncheckedAccount<'info>,
+
+    /// CHECK: This program's ProgramData account; validated by the loader
+    #[account(
+        mut,
+        address = anchor_lang::solana_program::bpf_loader_upgradeable::get_program_data_address(
+            &crate::ID
+        )
+    )]
+    pub program_data: UncheckedAccount<'info>,
+
+    /// CHECK: This program itself
+    #[account(mut, address = crate::ID)]
+    pub program: UncheckedAccount<'info>,
+
+    /// CHECK: The buffer approved with the proposal; validated by the loader
+    #[account(mut, address = upgrade_proposal.buffer)]
+    pub buffer: UncheckedAccount<'info>,
+
+    /// CHECK: Receives the buffer's lamports once it is consumed
+    #[account(mut)]
+    pub spill: UncheckedAccount<'info>,
+
+    pub executor: Signer<'info>,
+
+    pub rent: Sysvar<'info, Rent>,
+    pub clock: Sysvar<'info, Clock>,
+
+    /// CHECK: The upgradeable BPF loader
+    #[account(address = anchor_lang::solana_program::bpf_loader_upgradeable::ID)]
+    pub bpf_loader_upgradeable: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+pub struct CloseStaleProposal<'info> {
+    #[account(mut, close = fee_destination)]
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(
+        mut,
+        close = fee_destination,
+        seeds = [b"proposal_index", group.key().as_ref(), &proposal.index.to_le_bytes()],
+        bump = proposal_index.bump,
+        constraint = proposal_index.pubkey == proposal.key() @ DaoError::InvalidProposalIndex
+    )]
+    pub proposal_index: Account<'info, ProposalIndex>,
+
+    // Writable only to advance its event sequence
+    #[account(mut, seeds = [b"group", proposal.group_id.as_bytes()], bump = group.bump)]
+    pub group: Account<'info, Group>,
+
+    // Anyone may close; the rent goes to the fee destination, not the caller
+    pub closer: Signer<'info>,
+
+    /// CHECK: Only receives lamports; must be the configured fee destination
+    #[account(mut, address = program_config.fee_destination)]
+    pub fee_destination: AccountInfo<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    /// CHECK: Seeds-checked; a callback still here hasn't run yet
+    #[account(seeds = [b"callback", proposal.key().as_ref()], bump)]
+    pub callback: UncheckedAccount<'info>,
+
+    /// CHECK: Seeds-checked; an upgrade still here may be waiting on its timelock
+    #[account(seeds = [b"program_upgrade", proposal.key().as_ref()], bump)]
+    pub upgrade_proposal: UncheckedAccount<'info>,
+
+    /// CHECK: Seeds-checked; a deposit still here hasn't been settled yet
+    #[account(seeds = [b"proposal_deposit", proposal.key().as_ref()], bump)]
+    pub proposal_deposit: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetCouncil<'info> {
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + Council::INIT_SPACE,
+        seeds = [b"council", group.key().as_ref()],
+        bump
+    )]
+    pub council: Account<'info, Council>,
+
+    #[account(
+        mut,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "treasury")]
+#[derive(Accounts)]
+pub struct SetProposalFee<'info> {
+    #[account(
+        mut,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "treasury")]
+#[derive(Accounts)]
+pub struct PostProposalDeposit<'info> {
+    // Writable to add the spam choice
+    #[account(mut, constraint = proposal.creator == depositor.key() @ DaoError::Unauthorized)]
+    pub proposal: Account<'info, Proposal>,
+
+    // Writable only to advance its event sequence
+    #[account(mut, seeds = [b"group", proposal.group_id.as_bytes()], bump = group.bump)]
+    pub group: Account<'info, Group>,
+
+    #[account(
+        init,
+        payer = depositor,
+        space = 8 + ProposalDeposit::INIT_SPACE,
+        seeds = [b"proposal_deposit", proposal.key().as_ref()],
+        bump
+    )]
+    pub proposal_deposit: Account<'info, ProposalDeposit>,
+
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "treasury")]
+#[derive(Accounts)]
+pub struct FlagAsSpam<'info> {
+    pub proposal: Account<'info, Proposal>,
+
+    // Writable only to advance its event sequence
+    #[account(mut, seeds = [b"group", proposal.group_id.as_bytes()], bump = group.bump)]
+    pub group: Account<'info, Group>,
+
+    #[account(seeds = [b"council", group.key().as_ref()], bump = council.bump)]
+    pub council: Account<'info, Council>,
+
+    #[account(
+        mut,
+        seeds = [b"proposal_deposit", proposal.key().as_ref()],
+        bump = proposal_deposit.bump
+    )]
+    pub proposal_deposit: Account<'info, ProposalDeposit>,
+
+    pub council_member: Signer<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "treasury")]
+#[derive(Accounts)]
+pub struct SettleProposalDeposit<'info> {
+    pub proposal: Account<'info, Proposal>,
+
+    // Writable only to advance its event sequence
+    #[account(mut, seeds = [b"group", proposal.group_id.as_bytes()], bump = group.bump)]
+    pub group: Account<'info, Group>,
+
+    #[account(
+        mut,
+        close = depositor,
+        seeds = [b"proposal_deposit", proposal.key().as_ref()],
+        bump = proposal_deposit.bump,
+        has_one = depositor
+    )]
+    pub proposal_deposit: Account<'info, ProposalDeposit>,
+
+    /// CHECK: Receives the refund and the deposit account's rent
+    #[account(mut)]
+    pub depositor: AccountInfo<'info>,
+
+    /// CHECK: The group's treasury PDA; receives slashed deposits
+    #[account(mut, seeds = [b"treasury", group.key().as_ref()], bump)]
+    pub treasury: UncheckedAccount<'info>,
+
+    // Anyone may settle
+    pub settler: Signer<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "vote-rewards")]
+#[derive(Accounts)]
+pub struct FundVoteRewards<'info> {
+    pub proposal: Account<'info, Proposal>,
+
+    // Writable only to advance its event sequence
+    #[account(mut, seeds = [b"group", proposal.group_id.as_bytes()], bump = group.bump)]
+    pub group: Account<'info, Group>,
+
+    #[account(
+        init_if_needed,
+        payer = funder,
+        space = 8 + RewardPool::INIT_SPACE,
+        seeds = [b"reward_pool", proposal.key().as_ref()],
+        bump
+    )]
+    pub reward_pool: Account<'info, RewardPool>,
+
+    #[account(mut)]
+    pub funder: Signer<'info>,
+
+    /// CHECK: SPL pools only; validated against the pool's mint and vault
+    #[account(mut)]
+    pub vault: Option<AccountInfo<'info>>,
+
+    /// CHECK: SPL pools only; the token program checks the funder owns it
+    #[account(mut)]
+    pub funder_token_account: Option<AccountInfo<'info>>,
+
+    /// CHECK: SPL pools only; checked to be the token program
+    pub token_program: Option<AccountInfo<'info>>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "vote-rewards")]
+#[derive(Accounts)]
+pub struct ClaimVoteReward<'info> {
+    pub proposal: Account<'info, Proposal>,
+
+    // Writable only to advance its event sequence
+    #[account(mut, seeds = [b"group", proposal.group_id.as_bytes()], bump = group.bump)]
+    pub group: Account<'info, Group>,
+
+    // Rewards are claimable only once the proposal is finalized
+    #[account(
+        seeds = [b"proposal_result", proposal.key().as_ref()],
+        bump = proposal_result.bump
+    )]
+    pub proposal_result: Account<'info, ProposalResult>,
+
+    #[account(
+        mut,
+        seeds = [b"reward_pool", proposal.key().as_ref()],
+        bump = reward_pool.bump
+    )]
+    pub reward_pool: Account<'info, RewardPool>,
+
+    #[account(
+        init,
+        payer = voter,
+        space = 8 + RewardClaim::INIT_SPACE,
+        seeds = [b"reward_claim", reward_pool.key().as_ref(), voter.key().as_ref()],
+        bump
+    )]
+    pub reward_claim: Account<'info, RewardClaim>,
+
+    #[account(mut)]
+    pub voter: Signer<'info>,
+
+    /// CHECK: SPL pools only; must be the pool's vault
+    #[account(mut)]
+    pub vault: Option<AccountInfo<'info>>,
+
+    /// CHECK: SPL pools only; any token account of the pool's mint
+    #[account(mut)]
+    pub voter_token_account: Option<AccountInfo<'info>>,
+
+    /// CHECK: SPL pools only; checked to be the token program
+    pub token_program: Option<AccountInfo<'info>>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "vote-rewards")]
+#[derive(Accounts)]
+pub struct ReclaimVoteRewards<'info> {
+    #[account(
+        mut,
+        close = funder,
+        seeds = [b"reward_pool", reward_pool.proposal.as_ref()],
+        bump = reward_pool.bump,
+        has_one = funder @ DaoError::Unauthorized
+    )]
+    pub reward_pool: Account<'info, RewardPool>,
+
+    /// CHECK: The pool's proposal, which may already have been closed
+    #[account(address = reward_pool.proposal)]
+    pub proposal: AccountInfo<'info>,
+
+    // Writable only to advance its event sequence
+    #[account(mut, address = reward_pool.group)]
+    pub group: Account<'info, Group>,
+
+    #[account(mut)]
+    pub funder: Signer<'info>,
+
+    /// CHECK: SPL pools only; must be the pool's vault
+    #[account(mut)]
+    pub vault: Option<AccountInfo<'info>>,
+
+    /// CHECK: SPL pools only; receives the unclaimed tokens
+    #[account(mut)]
+    pub funder_token_account: Option<AccountInfo<'info>>,
+
+    /// CHECK: SPL pools only; checked to be the token program
+    pub token_program: Option<AccountInfo<'info>>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "voter-lottery")]
+#[derive(Accounts)]
+pub struct FundVoterLottery<'info> {
+    pub proposal: Account<'info, Proposal>,
+
+    // Writable only to advance its event sequence
+    #[account(mut, seeds = [b"group", proposal.group_id.as_bytes()], bump = group.bump)]
+    pub group: Account<'info, Group>,
+
+    #[account(
+        init_if_needed,
+        payer = funder,
+        space = 8 + VoterLottery::INIT_SPACE,
+        seeds = [b"lottery", proposal.key().as_ref()],
+        bump
+    )]
+    pub lottery: Account<'info, VoterLottery>,
+
+    #[account(mut)]
+    pub funder: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "voter-lottery")]
+#[derive(Accounts)]
+pub struct DrawVoterLottery<'info> {
+    pub proposal: Account<'info, Proposal>,
+
+    // Writable only to advance its event sequence
+    #[account(mut, seeds = [b"group", proposal.group_id.as_bytes()], bump = group.bump)]
+    pub group: Account<'info, Group>,
+
+    // The lottery is drawn only once the proposal is finalized
+    #[account(
+        seeds = [b"proposal_result", proposal.key().as_ref()],
+        bump = proposal_result.bump
+    )]
+    pub proposal_result: Account<'info, ProposalResult>,
+
+    #[account(
+        mut,
+        seeds = [b"lottery", proposal.key().as_ref()],
+        bump = lottery.bump
+    )]
+    pub lottery: Account<'info, VoterLottery>,
+
+    /// CHECK: Address-constrained to the slot hashes sysvar, read for the lottery's randomness
+    #[account(address = anchor_lang::solana_program::sysvar::slot_hashes::ID)]
+    pub slot_hashes: AccountInfo<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "voter-lottery")]
+#[derive(Accounts)]
+pub struct ClaimLotteryPrize<'info> {
+    #[account(
+        mut,
+        seeds = [b"lottery", lottery.proposal.as_ref()],
+        bump = lottery.bump
+    )]
+    pub lottery: Account<'info, VoterLottery>,
+
+    // Writable only to advance its event sequence
+    #[account(mut, address = lottery.group)]
+    pub group: Account<'info, Group>,
+
+    #[account(mut)]
+    pub winner: Signer<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "voter-lottery")]
+#[derive(Accounts)]
+pub struct ReclaimVoterLottery<'info> {
+    #[account(
+        mut,
+        close = funder,
+        seeds = [b"lottery", lottery.proposal.as_ref()],
+        bump = lottery.bump,
+        has_one = funder @ DaoError::Unauthorized
+    )]
+    pub lottery: Account<'info, VoterLottery>,
+
+    /// CHECK: The lottery's proposal, which may already have been closed
+    #[account(address = lottery.proposal)]
+    pub proposal: AccountInfo<'info>,
+
+    // Writable only to advance its event sequence
+    #[account(mut, address = lottery.group)]
+    pub group: Account<'info, Group>,
+
+    #[account(mut)]
+    pub funder: Signer<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "oracle-weights")]
+#[derive(Accounts)]
+pub struct SetPriceWeighting<'info> {
+    #[account(mut)]
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(
+        mut,
+        seeds = [b"group", proposal.group_id.as_bytes()],
+        bump = group.bump,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + PriceWeighting::INIT_SPACE,
+        seeds = [b"price_weighting", proposal.key().as_ref()],
+        bump
+    )]
+    pub price_weighting: Account<'info, PriceWeighting>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+// Relayed votes carry no disclosure and don't count linked wallets
+#[derive(Accounts)]
+pub struct RelayVote<'info> {
+    #[account(
+        mut,
+        realloc = Proposal::space(proposal.voters.len() + 1) + proposal.disclosure_space(),
+        realloc::payer = relayer,
+        realloc::zero = false
+    )]
+    pub proposal: Account<'info, Proposal>,
+
+    // Writable only to advance its event sequence
+    #[account(mut, seeds = [b"group", proposal.group_id.as_bytes()], bump = group.bump)]
+    pub group: Account<'info, Group>,
+
+    /// CHECK: The wallet the vote counts for; proven by its ed25519 signature of the payload
+    pub voter: AccountInfo<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        space = 8 + Participation::INIT_SPACE,
+        seeds = [b"participation", group.key().as_ref(), voter.key().as_ref()],
+        bump
+    )]
+    pub participation: Account<'info, Participation>,
+
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        space = 8 + TurnoutStats::INIT_SPACE,
+        seeds = [b"turnout", proposal.key().as_ref()],
+        bump
+    )]
+    pub turnout: Account<'info, TurnoutStats>,
+
+    // Pays the transaction fee and rent on the voter's behalf
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+
+    /// CHECK: This account is only used for SPL token voting, not for SOL voting
+    pub voter_token_account: Option<AccountInfo<'info>>,
+
+    /// CHECK: Address-constrained to the instructions sysvar, read to find the ed25519 instruction
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: AccountInfo<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    // The voter's member record, as in vote_on_proposal
+    #[account(
+        seeds = [b"member", group.key().as_ref(), voter.key().as_ref()],
+        bump = member_record.bump
+    )]
+    pub member_record: Option<Account<'info, Member>>,
+
+    // The voter's dues record, as in vote_on_proposal
+    #[account(
+        seeds = [b"dues_record", group.key().as_ref(), voter.key().as_ref()],
+        bump = dues_record.bump
+    )]
+    pub dues_record: Option<Account<'info, DuesRecord>>,
+
+    // The voter's shares, as in vote_on_proposal
+    #[account(
+        mut,
+        seeds = [b"member_shares", group.key().as_ref(), voter.key().as_ref()],
+        bump = member_shares.bump
+    )]
+    pub member_shares: Option<Account<'info, MemberShares>>,
+
+    /// CHECK: The voter's ban, as in vote_on_proposal
+    #[account(seeds = [b"ban", group.key().as_ref(), voter.key().as_ref()], bump)]
+    pub ban: UncheckedAccount<'info>,
+}
+
+#[cfg(feature = "delegation")]
+#[derive(Accounts)]
+#[instruction(group: Pubkey)]
+pub struct DelegateVotes<'info> {
+    #[account(
+        init_if_needed,
+        payer = delegator,
+        space = 8 + Delegation::INIT_SPACE,
+        seeds = [b"delegation", delegator.key().as_ref(), group.as_ref()],
+        bump
+    )]
+    pub delegation: Account<'info, Delegation>,
+
+    #[account(mut)]
+    pub delegator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "delegation")]
+#[derive(Accounts)]
+pub struct UndelegateVotes<'info> {
+    #[account(
+        mut,
+        close = delegator,
+        seeds = [b"delegation", delegator.key().as_ref(), delegation.group.as_ref()],
+        bump = delegation.bump
+    )]
+    pub delegation: Account<'info, Delegation>,
+
+    #[account(mut)]
+    pub delegator: Signer<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "delegation")]
+#[derive(Accounts)]
+pub struct ExpireDelegation<'info> {
+    #[account(
+        mut,
+        close = delegator,
+        seeds = [b"delegation", delegator.key().as_ref(), delegation.group.as_ref()],
+        bump = delegation.bump
+    )]
+    pub delegation: Account<'info, Delegation>,
+
+    /// CHECK: Receives the rent; the delegation's seeds tie it to the delegator
+    #[account(mut)]
+    pub delegator: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "delegation")]
+#[derive(Accounts)]
+pub struct VoteAsDelegate<'info> {
+    #[account(
+        mut,
+        realloc = Proposal::space(proposal.voters.len() + 1) + proposal.disclosure_space(),
+        realloc::payer = delegate,
+        realloc::zero = false
+    )]
+    pub proposal: Account<'info, Proposal>,
+
+    // Writable only to advance its event sequence
+    #[account(mut, seeds = [b"group", proposal.group_id.as_bytes()], bump = group.bump)]
+    pub group: Account<'info, Group>,
+
+    /// CHECK: The wallet the vote counts for; its delegation names the signer
+    pub delegator: AccountInfo<'info>,
+
+    #[account(
+        seeds = [b"delegation", delegator.key().as_ref(), delegation.group.as_ref()],
+        bump = delegation.bump,
+        constraint = delegation.delegate == delegate.key() @ DaoError::Unauthorized
+    )]
+    pub delegation: Account<'info, Delegation>,
+
+    /// CHECK: The delegator's delegation for this group, which need not exist; checked in
+    /// vote_as_delegate when `delegation` covers every group
+    #[account(seeds = [b"delegation", delegator.key().as_ref(), group.key().as_ref()], bump)]
+    pub group_delegation: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = delegate,
+        space = 8 + Participation::INIT_SPACE,
+        seeds = [b"participation", group.key().as_ref(), delegator.key().as_ref()],
+        bump
+    )]
+    pub participation: Account<'info, Participation>,
+
+    #[account(
+        init_if_needed,
+        payer = delegate,
+        space = 8 + TurnoutStats::INIT_SPACE,
+        seeds = [b"turnout", proposal.key().as_ref()],
+        bump
+    )]
+    pub turnout: Account<'info, TurnoutStats>,
+
+    #[account(mut)]
+    pub delegate: Signer<'info>,
+
+    /// CHECK: The delegator's token account; only used for SPL token voting
+    pub voter_token_account: Option<AccountInfo<'info>>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    // The delegator's member record, as in vote_on_proposal
+    #[account(
+        seeds = [b"member", group.key().as_ref(), delegator.key().as_ref()],
+        bump = member_record.bump
+    )]
+    pub member_record: Option<Account<'info, Member>>,
+
+    // The delegator's dues record, as in vote_on_proposal
+    #[account(
+        seeds = [b"dues_record", group.key().as_ref(), delegator.key().as_ref()],
+        bump = dues_record.bump
+    )]
+    pub dues_record: Option<Account<'info, DuesRecord>>,
+
+    // The delegator's shares, as in vote_on_proposal
+    #[account(
+        mut,
+        seeds = [b"member_shares", group.key().as_ref(), delegator.key().as_ref()],
+        bump = member_shares.bump
+    )]
+    pub member_shares: Option<Account<'info, MemberShares>>,
+
+    /// CHECK: The delegator's ban, as in vote_on_proposal
+    #[account(seeds = [b"ban", group.key().as_ref(), delegator.key().as_ref()], bump)]
+    pub ban: UncheckedAccount<'info>,
+}
+
+#[cfg(feature = "offchain-votes")]
+#[derive(Accounts)]
+pub struct EnableOffchainVoting<'info> {
+    #[account(mut)]
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(
+        mut,
+        seeds = [b"group", proposal.group_id.as_bytes()],
+        bump = group.bump,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    /// CHECK: Seeds-checked; an undisputed tally here keeps its aggregator
+    #[account(seeds = [b"offchain_tally", proposal.key().as_ref()], bump)]
+    pub offchain_tally: UncheckedAccount<'info>,
+}
+
+#[cfg(feature = "offchain-votes")]
+#[derive(Accounts)]
+pub struct SubmitOffchainTally<'info> {
+    pub proposal: Account<'info, Proposal>,
+
+    // Writable only to advance its event sequence
+    #[account(mut, seeds = [b"group", proposal.group_id.as_bytes()], bump = group.bump)]
+    pub group: Account<'info, Group>,
+
+    #[account(
+        init_if_needed,
+        payer = aggregator,
+        space = 8 + OffchainTally::INIT_SPACE,
+        seeds = [b"offchain_tally", proposal.key().as_ref()],
+        bump
+    )]
+    pub offchain_tally: Account<'info, OffchainTally>,
+
+    #[account(
+        mut,
+        constraint = proposal.offchain_aggregator == Some(aggregator.key()) @ DaoError::Unauthorized
+    )]
+    pub aggregator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "offchain-votes")]
+#[derive(Accounts)]
+pub struct DisputeOffchainTally<'info> {
+    pub proposal: Account<'info, Proposal>,
+
+    // Writable only to advance its event sequence
+    #[account(mut, seeds = [b"group", proposal.group_id.as_bytes()], bump = group.bump)]
+    pub group: Account<'info, Group>,
+
+    #[account(
+        mut,
+        seeds = [b"offchain_tally", proposal.key().as_ref()],
+        bump = offchain_tally.bump
+    )]
+    pub offchain_tally: Account<'info, OffchainTally>,
+
+    pub disputer: Signer<'info>,
+
+    /// CHECK: Address-constrained to the instructions sysvar, read to find the ed25519 instruction
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: AccountInfo<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "offchain-votes")]
+#[derive(Accounts)]
+pub struct DisputeOffchainTotal<'info> {
+    pub proposal: Account<'info, Proposal>,
+
+    // Writable only to advance its event sequence
+    #[account(mut, seeds = [b"group", proposal.group_id.as_bytes()], bump = group.bump)]
+    pub group: Account<'info, Group>,
+
+    #[account(
+        mut,
+        seeds = [b"offchain_tally", proposal.key().as_ref()],
+        bump = offchain_tally.bump
+    )]
+    pub offchain_tally: Account<'info, OffchainTally>,
+
+    pub disputer: Signer<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "offchain-votes")]
+#[derive(Accounts)]
+pub struct SettleOffchainTally<'info> {
+    #[account(mut)]
+    pub proposal: Account<'info, Proposal>,
+
+    // Writable only to advance its event sequence
+    #[account(mut, seeds = [b"group", proposal.group_id.as_bytes()], bump = group.bump)]
+    pub group: Account<'info, Group>,
+
+    // A tally from a replaced aggregator no longer counts
+    #[account(
+        seeds = [b"offchain_tally", proposal.key().as_ref()],
+        bump = offchain_tally.bump,
+        constraint = proposal.offchain_aggregator == Some(offchain_tally.aggregator) @ DaoError::Unauthorized
+    )]
+    pub offchain_tally: Account<'info, OffchainTally>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "realms-compat")]
+#[derive(Accounts)]
+pub struct SyncRealmsProposal<'info> {
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(seeds = [b"group", proposal.group_id.as_bytes()], bump = group.bump)]
+    pub group: Account<'info, Group>,
+
+    /// CHECK: Written raw in the spl-governance ProposalV2 layout; the address is the
+    /// one spl-governance derives from governance, mint and proposal seed
+    #[account(
+        mut,
+        seeds = [
+            b"governance",
+            group.key().as_ref(),
+            proposal.token_mint.unwrap_or_default().as_ref(),
+            proposal.key().as_ref()
+        ],
+        bump
+    )]
+    pub realms_proposal: UncheckedAccount<'info>,
+
+    // Pays rent for the mirror account
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "realms-compat")]
+#[derive(Accounts)]
+#[instruction(voter: Pubkey)]
+pub struct SyncRealmsVoteRecord<'info> {
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(seeds = [b"group", proposal.group_id.as_bytes()], bump = group.bump)]
+    pub group: Account<'info, Group>,
+
+    /// CHECK: Address of the mirrored ProposalV2, which keys the vote record
+    #[account(
+        seeds = [
+            b"governance",
+            group.key().as_ref(),
+            proposal.token_mint.unwrap_or_default().as_ref(),
+            proposal.key().as_ref()
+        ],
+        bump
+    )]
+    pub realms_proposal: UncheckedAccount<'info>,
+
+    // Stands in for the voter's TokenOwnerRecord
+    #[account(
+        seeds = [b"participation", group.key().as_ref(), voter.as_ref()],
+        bump = participation.bump
+    )]
+    pub participation: Account<'info, Participation>,
+
+    /// CHECK: Written raw in the spl-governance VoteRecordV2 layout at the address
+    /// spl-governance derives from proposal and token owner record
+    #[account(
+        mut,
+        seeds = [b"governance", realms_proposal.key().as_ref(), participation.key().as_ref()],
+        bump
+    )]
+    pub realms_vote_record: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+pub struct CastShadowVote<'info> {
+    #[account(mut)]
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(mut, seeds = [b"group", proposal.group_id.as_bytes()], bump = group.bump)]
+    pub group: Account<'info, Group>,
+
+    // One shadow ballot per non-member per proposal
+    #[account(
+        init,
+        payer = voter,
+        space = 8 + ShadowBallot::INIT_SPACE,
+        seeds = [b"shadow_ballot", proposal.key().as_ref(), voter.key().as_ref()],
+        bump
+    )]
+    pub shadow_ballot: Account<'info, ShadowBallot>,
+
+    #[account(mut)]
+    pub voter: Signer<'info>,
+
+    /// CHECK: The voter's member record address; it must hold no account
+    #[account(seeds = [b"member", group.key().as_ref(), voter.key().as_ref()], bump)]
+    pub member_record: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "large-proposals")]
+#[derive(Accounts)]
+pub struct VoteOnLargeProposal<'info> {
+    #[account(mut, has_one = group)]
+    pub proposal: AccountLoader<'info, ProposalLarge>,
+
+    // Writable only to advance its event sequence
+    #[account(mut)]
+    pub group: Account<'info, Group>,
+
+    #[account(
+        init,
+        payer = voter,
+        space = 8 + VoteRecord::INIT_SPACE,
+        seeds = [b"vote_record", proposal.key().as_ref(), voter.key().as_ref()],
+        bump
+    )]
+    pub vote_record: Account<'info, VoteRecord>,
+
+    #[account(mut)]
+    pub voter: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = voter,
+        space = 8 + Participation::INIT_SPACE,
+        seeds = [b"participation", group.key().as_ref(), voter.key().as_ref()],
+        bump
+    )]
+    pub participation: Account<'info, Participation>,
+
+    #[account(
+        init_if_needed,
+        payer = voter,
+        space = 8 + TurnoutStats::INIT_SPACE,
+        seeds = [b"turnout", proposal.key().as_ref()],
+        bump
+    )]
+    pub turnout: Account<'info, TurnoutStats>,
+
+    /// CHECK: This account is only used for SPL token voting, not for SOL voting
+    pub voter_token_account: Option<AccountInfo<'info>>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    // The voter's member record; required in members-only groups
+    #[account(
+        seeds = [b"member", group.key().as_ref(), voter.key().as_ref()],
+        bump = member_record.bump
+    )]
+    pub member_record: Option<Account<'info, Member>>,
+
+    // The voter's dues record, as in vote_on_proposal
+    #[account(
+        seeds = [b"dues_record", group.key().as_ref(), voter.key().as_ref()],
+        bump = dues_record.bump
+    )]
+    pub dues_record: Option<Account<'info, DuesRecord>>,
+
+    // The voter's shares, as in vote_on_proposal
+    #[account(
+        mut,
+        seeds = [b"member_shares", group.key().as_ref(), voter.key().as_ref()],
+        bump = member_shares.bump
+    )]
+    pub member_shares: Option<Account<'info, MemberShares>>,
+
+    /// CHECK: The voter's ban, as in vote_on_proposal
+    #[account(seeds = [b"ban", group.key().as_ref(), voter.key().as_ref()], bump)]
+    pub ban: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(member: Pubkey)]
+pub struct AddGroupMember<'info> {
+    #[account(
+        mut,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    // The member's record, to list them; exactly one of this and membership_tree is passed
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + Member::INIT_SPACE,
+        seeds = [b"member", group.key().as_ref(), member.as_ref()],
+        bump
+    )]
+    pub member_record: Option<Account<'info, Member>>,
+
+    // The group's credential tree, to issue a cNFT instead of listing the member
+    #[account(mut, seeds = [b"membership_tree", group.key().as_ref()], bump = membership_tree.bump)]
+    pub membership_tree: Option<Account<'info, MembershipTree>>,
+
+    /// CHECK: The member's ban, which need not exist; checked in require_not_banned
+    #[account(seeds = [b"ban", group.key().as_ref(), member.as_ref()], bump)]
+    pub ban: UncheckedAccount<'info>,
+}
+
+#[cfg(feature = "token-gated-join")]
+#[derive(Accounts)]
+pub struct SetMembershipGate<'info> {
+    #[account(
+        mut,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + MembershipGate::INIT_SPACE,
+        seeds = [b"membership_gate", group.key().as_ref()],
+        bump
+    )]
+    pub membership_gate: Account<'info, MembershipGate>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "token-gated-join")]
+#[derive(Accounts)]
+pub struct ClearMembershipGate<'info> {
+    #[account(
+        mut,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"membership_gate", group.key().as_ref()],
+        bump = membership_gate.bump
+    )]
+    pub membership_gate: Account<'info, MembershipGate>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "token-gated-join")]
+#[derive(Accounts)]
+pub struct JoinGroup<'info> {
+    #[account(mut)]
+    pub group: Account<'info, Group>,
+
+    #[account(seeds = [b"membership_gate", group.key().as_ref()], bump = membership_gate.bump)]
+    pub membership_gate: Account<'info, MembershipGate>,
+
+    #[account(mut)]
+    pub joiner: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = joiner,
+        space = 8 + Member::INIT_SPACE,
+        seeds = [b"member", group.key().as_ref(), joiner.key().as_ref()],
+        bump
+    )]
+    pub member_record: Account<'info, Member>,
+
+    /// CHECK: The joiner's token account; owner, mint and amount are checked in the handler
+    pub holding: UncheckedAccount<'info>,
+
+    /// CHECK: Metaplex metadata of the held NFT, for collection gates; address, owner and
+    /// contents are checked in the handler
+    pub nft_metadata: Option<UncheckedAccount<'info>>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    /// CHECK: The joiner's ban, which need not exist; checked in admit_member
+    #[account(seeds = [b"ban", group.key().as_ref(), joiner.key().as_ref()], bump)]
+    pub ban: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(code_hash: [u8; 32])]
+pub struct CreateInvite<'info> {
+    #[account(
+        mut,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Invite::INIT_SPACE,
+        seeds = [b"invite", group.key().as_ref(), code_hash.as_ref()],
+        bump
+    )]
+    pub invite: Account<'info, Invite>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeInvite<'info> {
+    #[account(
+        mut,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"invite", group.key().as_ref(), invite.code_hash.as_ref()],
+        bump = invite.bump
+    )]
+    pub invite: Account<'info, Invite>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+// The invite is found from the code itself, so only someone who knows it can join
+#[derive(Accounts)]
+#[instruction(code: String)]
+pub struct JoinWithInvite<'info> {
+    #[account(mut)]
+    pub group: Account<'info, Group>,
+
+    #[account(
+        mut,
+        seeds = [b"invite", group.key().as_ref(), invite_code_hash(&group.key(), &code).as_ref()],
+        bump = invite.bump
+    )]
+    pub invite: Account<'info, Invite>,
+
+    #[account(mut)]
+    pub joiner: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = joiner,
+        space = 8 + Member::INIT_SPACE,
+        seeds = [b"member", group.key().as_ref(), joiner.key().as_ref()],
+        bump
+    )]
+    pub member_record: Account<'info, Member>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    /// CHECK: The joiner's ban, which need not exist; checked in admit_member
+    #[account(seeds = [b"ban", group.key().as_ref(), joiner.key().as_ref()], bump)]
+    pub ban: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(member: Pubkey)]
+pub struct SetMemberWeight<'info> {
+    #[account(
+        mut,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    #[account(
+        mut,
+        seeds = [b"member", group.key().as_ref(), member.as_ref()],
+        bump = member_record.bump
+    )]
+    pub member_record: Account<'info, Member>,
+
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+pub struct SetMembersOnlyVoting<'info> {
+    #[account(
+        mut,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "dues")]
+#[derive(Accounts)]
+pub struct SetDuesPolicy<'info> {
+    #[account(
+        mut,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "dues")]
+#[derive(Accounts)]
+pub struct PayDues<'info> {
+    // Writable only to advance its event sequence
+    #[account(mut)]
+    pub group: Account<'info, Group>,
+
+    #[account(
+        init_if_needed,
+        payer = member,
+        space = 8 + DuesRecord::INIT_SPACE,
+        seeds = [b"dues_record", group.key().as_ref(), member.key().as_ref()],
+        bump
+    )]
+    pub dues_record: Account<'info, DuesRecord>,
+
+    #[account(mut)]
+    pub member: Signer<'info>,
+
+    /// CHECK: The group's treasury PDA; receives SOL dues and owns the SPL dues account
+    #[account(mut, seeds = [b"treasury", group.key().as_ref()], bump)]
+    pub treasury: UncheckedAccount<'info>,
+
+    /// CHECK: SPL dues only; a token account of the dues mint owned by the treasury
+    #[account(mut)]
+    pub treasury_token_account: Option<AccountInfo<'info>>,
+
+    /// CHECK: SPL dues only; the token program checks the member owns it
+    #[account(mut)]
+    pub member_token_account: Option<AccountInfo<'info>>,
+
+    /// CHECK: SPL dues only; checked to be the token program
+    pub token_program: Option<AccountInfo<'info>>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "dues")]
+#[derive(Accounts)]
+pub struct FlagOverdueDues<'info> {
+    // Writable only to advance its event sequence
+    #[account(mut, address = dues_record.group)]
+    pub group: Account<'info, Group>,
+
+    #[account(
+        mut,
+        seeds = [b"dues_record", dues_record.group.as_ref(), dues_record.member.as_ref()],
+        bump = dues_record.bump
+    )]
+    pub dues_record: Account<'info, DuesRecord>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "shares")]
+#[derive(Accounts)]
+pub struct SetStakeToJoin<'info> {
+    #[account(
+        mut,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "shares")]
+#[derive(Accounts)]
+pub struct StakeShares<'info> {
+    #[account(mut)]
+    pub group: Account<'info, Group>,
+
+    #[account(
+        init_if_needed,
+        payer = member,
+        space = 8 + MemberShares::INIT_SPACE,
+        seeds = [b"member_shares", group.key().as_ref(), member.key().as_ref()],
+        bump
+    )]
+    pub member_shares: Account<'info, MemberShares>,
+
+    // Created on the first purchase of a wallet that isn't listed yet
+    #[account(
+        init_if_needed,
+        payer = member,
+        space = 8 + Member::INIT_SPACE,
+        seeds = [b"member", group.key().as_ref(), member.key().as_ref()],
+        bump
+    )]
+    pub member_record: Account<'info, Member>,
+
+    #[account(mut)]
+    pub member: Signer<'info>,
+
+    /// CHECK: The group's treasury PDA, which receives the purchase
+    #[account(mut, seeds = [b"treasury", group.key().as_ref()], bump)]
+    pub treasury: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    /// CHECK: The buyer's ban, which need not exist; checked in admit_member
+    #[account(seeds = [b"ban", group.key().as_ref(), member.key().as_ref()], bump)]
+    pub ban: UncheckedAccount<'info>,
+}
+
+#[cfg(feature = "shares")]
+#[derive(Accounts)]
+pub struct Ragequit<'info> {
+    #[account(mut, address = member_shares.group)]
+    pub group: Account<'info, Group>,
+
+    #[account(
+        mut,
+        seeds = [b"member_shares", group.key().as_ref(), member.key().as_ref()],
+        bump = member_shares.bump
+    )]
+    pub member_shares: Account<'info, MemberShares>,
+
+    #[account(mut)]
+    pub member: Signer<'info>,
+
+    /// CHECK: The group's treasury PDA, which pays out the member's cut
+    #[account(mut, seeds = [b"treasury", group.key().as_ref()], bump)]
+    pub treasury: UncheckedAccount<'info>,
+
+    /// CHECK: Only needed to withdraw SPL balances; checked to be the token program
+    pub token_program: Option<AccountInfo<'info>>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+pub struct SetMembershipPolicy<'info> {
+    #[account(
+        mut,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+pub struct SetMaxActiveProposals<'info> {
+    #[account(
+        mut,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+pub struct SetProposalCooldown<'info> {
+    #[account(
+        mut,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "cnft-membership")]
+#[derive(Accounts)]
+pub struct InitMembershipTree<'info> {
+    #[account(
+        mut,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + MembershipTree::INIT_SPACE,
+        seeds = [b"membership_tree", group.key().as_ref()],
+        bump
+    )]
+    pub membership_tree: Account<'info, MembershipTree>,
+
+    /// CHECK: Bubblegum's tree config PDA for `merkle_tree`; created and checked by Bubblegum
+    #[account(mut)]
+    pub tree_config: UncheckedAccount<'info>,
+
+    /// CHECK: Pre-allocated concurrent Merkle tree account; initialized by Bubblegum
+    #[account(mut)]
+    pub merkle_tree: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// CHECK: Address-constrained to the noop program
+    #[account(address = NOOP_PROGRAM_ID)]
+    pub log_wrapper: UncheckedAccount<'info>,
+
+    /// CHECK: Address-constrained to the account compression program
+    #[account(address = ACCOUNT_COMPRESSION_PROGRAM_ID)]
+    pub compression_program: UncheckedAccount<'info>,
+
+    /// CHECK: Address-constrained to Bubblegum
+    #[account(address = BUBBLEGUM_PROGRAM_ID)]
+    pub bubblegum_program: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "cnft-membership")]
+#[derive(Accounts)]
+#[instruction(choice_index: u8, disclosure: Option<String>)]
+pub struct VoteWithCredential<'info> {
+    #[account(
+        mut,
+        realloc = Proposal::space(proposal.voters.len() + 1)
+            + proposal.disclosure_space()
+            + VoterInfo::disclosure_space(disclosure.as_ref()),
+        realloc::payer = voter,
+        realloc::zero = false
+    )]
+    pub proposal: Account<'info, Proposal>,
+
+    // Writable only to advance its event sequence
+    #[account(mut, seeds = [b"group", proposal.group_id.as_bytes()], bump = group.bump)]
+    pub group: Account<'info, Group>,
+
+    #[account(seeds = [b"membership_tree", group.key().as_ref()], bump = membership_tree.bump)]
+    pub membership_tree: Account<'info, MembershipTree>,
+
+    /// CHECK: Address-constrained to the group's credential tree; verify_leaf reads it
+    #[account(address = membership_tree.merkle_tree)]
+    pub merkle_tree: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub voter: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = voter,
+        space = 8 + Participation::INIT_SPACE,
+        seeds = [b"participation", group.key().as_ref(), voter.key().as_ref()],
+        bump
+    )]
+    pub participation: Account<'info, Participation>,
+
+    #[account(
+        init_if_needed,
+        payer = voter,
+        space = 8 + TurnoutStats::INIT_SPACE,
+        seeds = [b"turnout", proposal.key().as_ref()],
+        bump
+    )]
+    pub turnout: Account<'info, TurnoutStats>,
+
+    /// CHECK: This account is only used for SPL token voting, not for SOL voting
+    pub voter_token_account: Option<AccountInfo<'info>>,
+
+    /// CHECK: Address-constrained to the account compression program
+    #[account(address = ACCOUNT_COMPRESSION_PROGRAM_ID)]
+    pub compression_program: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    // The voter's dues record, as in vote_on_proposal
+    #[account(
+        seeds = [b"dues_record", group.key().as_ref(), voter.key().as_ref()],
+        bump = dues_record.bump
+    )]
+    pub dues_record: Option<Account<'info, DuesRecord>>,
+
+    // The voter's shares, as in vote_on_proposal
+    #[account(
+        mut,
+        seeds = [b"member_shares", group.key().as_ref(), voter.key().as_ref()],
+        bump = member_shares.bump
+    )]
+    pub member_shares: Option<Account<'info, MemberShares>>,
+
+    /// CHECK: The voter's ban, as in vote_on_proposal
+    #[account(seeds = [b"ban", group.key().as_ref(), voter.key().as_ref()], bump)]
+    pub ban: UncheckedAccount<'info>,
+}
+
+#[cfg(feature = "cnft-membership")]
+#[derive(Accounts)]
+pub struct RevokeMembershipCredential<'info> {
+    #[account(
+        mut,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    #[account(seeds = [b"membership_tree", group.key().as_ref()], bump = membership_tree.bump)]
+    pub membership_tree: Account<'info, MembershipTree>,
+
+    /// CHECK: Bubblegum's tree config PDA; checked by Bubblegum
+    pub tree_config: UncheckedAccount<'info>,
+
+    /// CHECK: Address-constrained to the group's credential tree
+    #[account(mut, address = membership_tree.merkle_tree)]
+    pub merkle_tree: UncheckedAccount<'info>,
+
+    /// CHECK: The credential's owner; part of the leaf Bubblegum checks, never signs
+    pub member: UncheckedAccount<'info>,
+
+    pub authority: Signer<'info>,
+
+    /// CHECK: Address-constrained to the noop program
+    #[account(address = NOOP_PROGRAM_ID)]
+    pub log_wrapper: UncheckedAccount<'info>,
+
+    /// CHECK: Address-constrained to the account compression program
+    #[account(address = ACCOUNT_COMPRESSION_PROGRAM_ID)]
+    pub compression_program: UncheckedAccount<'info>,
+
+    /// CHECK: Address-constrained to Bubblegum
+    #[account(address = BUBBLEGUM_PROGRAM_ID)]
+    pub bubblegum_program: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(member: Pubkey)]
+pub struct RemoveGroupMember<'info> {
+    #[account(
+        mut,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"member", group.key().as_ref(), member.as_ref()],
+        bump = member_record.bump
+    )]
+    pub member_record: Account<'info, Member>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "bans")]
+#[derive(Accounts)]
+#[instruction(wallet: Pubkey)]
+pub struct BanMember<'info> {
+    #[account(
+        mut,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + Ban::INIT_SPACE,
+        seeds = [b"ban", group.key().as_ref(), wallet.as_ref()],
+        bump
+    )]
+    pub ban: Account<'info, Ban>,
+
+    // The wallet's member record when it is listed, closed to the authority
+    #[account(
+        mut,
+        seeds = [b"member", group.key().as_ref(), wallet.as_ref()],
+        bump = member_record.bump
+    )]
+    pub member_record: Option<Account<'info, Member>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[cfg(feature = "bans")]
+#[derive(Accounts)]
+#[instruction(wallet: Pubkey)]
+pub struct UnbanMember<'info> {
+    #[account(
+        mut,
+        constraint = group.authority == authority.key() @ DaoError::Unauthorized
+    )]
+    pub group: Account<'info, Group>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"ban", group.key().as_ref(), wallet.as_ref()],
+        bump = ban.bump
+    )]
+    pub ban: Account<'info, Ban>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ DaoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(telegram_id: i64)]
+pub struct CreateUserAccount<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + UserAccount::INIT_SPACE,
+        seeds = [b"user_account", telegram_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub user_account: Account<'info, UserAccount>,
+
+    /// CHECK: This is the wallet that will be associated with the user account
+    pub user_wallet: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(telegram_id: i64)]
+pub struct LoginUser<'info> {
+    #[account(
+        seeds = [b"user_account", telegram_id.to_le_bytes().as_ref()],
+        bump = user_account.bump
+    )]
+    pub user_account: Account<'info, UserAccount>,
+}
+
+#[derive(Accounts)]
+#[instruction(telegram_id: i64)]
+pub struct LoginWithSignature<'info> {
+    #[account(
+        mut,
+        seeds = [b"user_account", telegram_id.to_le_bytes().as_ref()],
+        bump = user_account.bump
+    )]
+    pub user_account: Account<'info, UserAccount>,
+
+    /// CHECK: Address-constrained to the instructions sysvar, read to find the ed25519 instruction
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(telegram_id: i64)]
+pub struct LinkWallet<'info> {
+    #[account(
+        mut,
+        seeds = [b"user_account", telegram_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+        constraint = user_account.wallet_pubkey == owner.key() @ DaoError::Unauthorized
+    )]
+    pub user_account: Account<'info, UserAccount>,
+
+    pub owner: Signer<'info>,
+
+    pub wallet: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(telegram_id: i64)]
+pub struct LinkWalletWithSignature<'info> {
+    #[account(
+        mut,
+        seeds = [b"user_account", telegram_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+        constraint = user_account.wallet_pubkey == owner.key() @ DaoError::Unauthorized
+    )]
+    pub user_account: Account<'info, UserAccount>,
+
+    pub owner: Signer<'info>,
+
+    /// CHECK: Address-constrained to the instructions sysvar, read to find the ed25519 instruction
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(telegram_id: i64, wallet: Pubkey)]
+pub struct UnlinkWallet<'info> {
+    #[account(
+        mut,
+        seeds = [b"user_account", telegram_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+        constraint = user_account.wallet_pubkey == authority.key()
+            || wallet == authority.key() @ DaoError::Unauthorized
+    )]
+    pub user_account: Account<'info, UserAccount>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GetAllGroups<'info> {
+    #[account(
+        seeds = [b"dao_registry", dao_registry.name.as_bytes()],
+        bump = dao_registry.bump
+    )]
+    pub dao_registry: Account<'info, DaoRegistry>,
+}
+
+#[derive(Accounts)]
+pub struct GetProposalSummary<'info> {
+    pub proposal: Account<'info, Proposal>,
+}
+
+// Events
+// Group events carry the group's `event_seq`; registry-level events carry the registry's.
+// User account events are not sequenced since they don't change DAO state.
+#[event]
+pub struct ProgramConfigUpdatedEvent {
+    pub event_seq: u64,
+    pub max_groups: u32,
+    pub max_choices: u8,
+    pub fee_destination: Pubkey,
+    pub guardian: Option<Pubkey>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RegistryAuthorityChangedEvent {
+    pub event_seq: u64,
+    pub old_authority: Pubkey,
+    pub new_authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "registry-multisig")]
+#[event]
+pub struct RegistryActionProposedEvent {
+    pub event_seq: u64,
+    pub index: u64,
+    pub action: RegistryAction,
+    pub proposer: Pubkey,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "registry-multisig")]
+#[event]
+pub struct RegistryActionApprovedEvent {
+    pub event_seq: u64,
+    pub index: u64,
+    pub approver: Pubkey,
+    pub approvals: u8,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "registry-multisig")]
+#[event]
+pub struct RegistryActionExecutedEvent {
+    pub event_seq: u64,
+    pub index: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PauseChangedEvent {
+    pub event_seq: u64,
+    pub paused: bool,
+    pub by: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct GroupCreatedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub authority: Pubkey,
+    pub group_pubkey: Pubkey,
+    pub registry: Pubkey,
+    pub deposit_lamports: u64,
+    pub deposit_unlock_at: i64,
+    pub metadata_uri: Option<String>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RegistrationConfigUpdatedEvent {
+    pub event_seq: u64,
+    pub open_registration: bool,
+    pub group_deposit_lamports: u64,
+    pub group_deposit_lock_days: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct GroupDepositWithdrawnEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub authority: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "slugs")]
+#[event]
+pub struct SlugReservedEvent {
+    pub event_seq: u64,
+    pub slug: String,
+    pub owner: Pubkey,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "slugs")]
+#[event]
+pub struct SlugReleasedEvent {
+    pub event_seq: u64,
+    pub slug: String,
+    pub owner: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct GroupChatChangedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub old_chat_id: Option<i64>,
+    pub new_chat_id: Option<i64>,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "profiles")]
+#[event]
+pub struct GroupProfileUpdatedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "slugs")]
+#[event]
+pub struct GroupSlugChangedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub old_slug: String,
+    pub new_slug: String,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FinalizeBountyFundedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub funder: Pubkey,
+    pub amount: u64,
+    pub total_bounty: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ProposalFinalizedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub choice_votes: Vec<u64>,
+    pub winning_choice: Option<u8>, // None when nobody voted or the top choices tie
+    pub status: ProposalStatus,     // Succeeded or Defeated
+    pub voter_count: u32,
+    pub member_count: u32, // listed members when sealed; turnout is voter_count over this
+    pub cast_weight: u64,  // weight turnout, both 0 unless the group has a weight quorum
+    pub weight_supply: u64,
+    pub finalized_by: Pubkey,
+    pub bounty: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ProposalClosedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub index: u64,
+    pub reclaimed_lamports: u64, // proposal and index rent sent to the fee destination
+    pub closed_by: Pubkey,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "vote-rewards")]
+#[event]
+pub struct VoteRewardsFundedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub funder: Pubkey,
+    pub token_mint: Pubkey,
+    pub amount: u64,
+    pub total_amount: u64,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "vote-rewards")]
+#[event]
+pub struct VoteRewardClaimedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub voter: Pubkey,
+    pub token_mint: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "vote-rewards")]
+#[event]
+pub struct VoteRewardsReclaimedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal: Pubkey, // the proposal account may already be closed
+    pub funder: Pubkey,
+    pub token_mint: Pubkey,
+    pub amount: u64, // unclaimed rewards returned to the funder
+    pub claim_count: u32,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "voter-lottery")]
+#[event]
+pub struct VoterLotteryFundedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub funder: Pubkey,
+    pub winner_count: u8,
+    pub amount: u64,
+    pub total_amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct VoterLotteryDrawnEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub winners: Vec<Pubkey>,
+    pub prize: u64,
+    pub slot: u64, // slot whose hash seeded the draw
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "voter-lottery")]
+#[event]
+pub struct LotteryPrizeClaimedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal: Pubkey,
+    pub winner: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "voter-lottery")]
+#[event]
+pub struct VoterLotteryReclaimedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal: Pubkey, // the proposal account may already be closed
+    pub funder: Pubkey,
+    pub amount: u64, // unclaimed prizes returned to the funder
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "joint-proposals")]
+#[event]
+pub struct JointProposalCreatedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub joint_id: String,
+    pub joint_proposal: Pubkey,
+    pub proposal_id: String,
+    pub required_passes: u8,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "joint-proposals")]
+#[event]
+pub struct JointProposalJoinedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub joint_id: String,
+    pub proposal_id: String,
+    pub participant_count: u8,
+    pub timestamp: i64,
+}
+
+// Registry-level: a joint proposal belongs to no single group
+#[cfg(feature = "joint-proposals")]
+#[event]
+pub struct JointProposalResolvedEvent {
+    pub event_seq: u64,
+    pub joint_id: String,
+    pub status: ProposalStatus, // Succeeded or Defeated
+    pub passes: u8,
+    pub required_passes: u8, // effective bar, with 0 resolved to the participant count
+    pub participant_count: u8,
+    pub resolved_by: Pubkey,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "treasury")]
+#[event]
+pub struct ProgramGovernanceInitializedEvent {
+    pub event_seq: u64,
+    pub group_id: String,
+    pub treasury: Pubkey,
+    pub timelock_secs: i64,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "treasury")]
+#[event]
+pub struct ProgramUpgradeProposedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub buffer: Pubkey,
+    pub approve_choice: u8,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "treasury")]
+#[event]
+pub struct ProgramUpgradeQueuedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub buffer: Pubkey,
+    pub eta: i64,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "treasury")]
+#[event]
+pub struct ProgramUpgradeExecutedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub buffer: Pubkey,
+    pub executed_by: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ProposalCreatedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub creator: Pubkey,
+    pub proposal_pubkey: Pubkey,
+    pub voting_start: i64,
+    pub voting_end: i64,
+    pub metadata_uri: Option<String>,
+    pub draft: bool,
+    pub emergency: bool,
+    pub creation_fee: u64, // paid into the group treasury
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ProposalMetadataUpdatedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub metadata_uri: Option<String>,
+    pub timestamp: i64,
+}
+
+// A payment made from the group treasury when a proposal was executed
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ExecutedTransfer {
+    pub mint: Option<Pubkey>, // None for lamports
+    pub recipient: Pubkey,
+    pub amount: u64,
+}
+
+// A typed proposal was carried out by its execute_* instruction
+#[event]
+pub struct ProposalExecutedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub kind: ProposalKind,
+    pub executed_by: Pubkey,
+    pub transfers: Vec<ExecutedTransfer>, // empty for kinds that pay nothing on execution
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ProposalStatusChangedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub status: ProposalStatus,
+    pub voting_start: i64,
+    pub voting_end: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct VoteCastEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub voter: Pubkey,
+    pub choice: u8,
+    pub vote_weight: u64,
+    pub timestamp: i64,
+    pub disclosure: Option<String>,
+    // Split-weight votes: the weight given to each choice
+    pub allocations: Option<Vec<u64>>,
+}
+
+#[event]
+pub struct ShadowVoteCastEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub voter: Pubkey,
+    pub choice: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MemberWeightUpdatedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub member: Pubkey,
+    pub weight: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MemberWeightedVotingSetEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub enabled: bool,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "callbacks")]
+#[event]
+pub struct ExecutionCallbackSetEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub on_success_program: Pubkey,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "callbacks")]
+#[event]
+pub struct ProgramAllowlistUpdatedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub programs: Vec<Pubkey>,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "callbacks")]
+#[event]
+pub struct ExecutionCallbackRunEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub on_success_program: Pubkey,
+    pub executed_by: Pubkey,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "choice-details")]
+#[event]
+pub struct ChoiceDetailsSetEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SplitVotingSetEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub enabled: bool,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "deposit-voting")]
+#[event]
+pub struct DepositRegistrarConfiguredEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub registrar: Pubkey,
+    pub mint: Pubkey,
+    pub min_holding_secs: i64,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "deposit-voting")]
+#[event]
+pub struct VotingTokensDepositedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub registrar: Pubkey,
+    pub voter: Pubkey,
+    pub amount: u64,
+    pub total: u64,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "deposit-voting")]
+#[event]
+pub struct VotingTokensWithdrawnEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub registrar: Pubkey,
+    pub voter: Pubkey,
+    pub amount: u64,
+    pub total: u64,
+    pub timestamp: i64,
+}
+
+// `deposit_voting` is None when deposit voting was turned off
+#[cfg(feature = "deposit-voting")]
+#[event]
+pub struct DepositVotingSetEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub registrar: Pubkey,
+    pub deposit_voting: Option<DepositVoting>,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "bicameral")]
+#[event]
+pub struct BicameralSetEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub enabled: bool,
+    pub timestamp: i64,
+}
+
+// `choice_votes` is the council chamber's tally after this vote
+#[cfg(feature = "bicameral")]
+#[event]
+pub struct CouncilVoteCastEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub council_member: Pubkey,
+    pub choice_index: u8,
+    pub choice_votes: Vec<u32>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WeightDecaySetEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub decay_bps: u16,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SlotWindowSetEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub start_slot: u64,
+    pub end_slot: u64,
+    // Unix-time estimates of the window
+    pub voting_start: i64,
+    pub voting_end: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MemberAddedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub member: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MembersOnlyVotingUpdatedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub enabled: bool,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "dues")]
+#[event]
+pub struct DuesPolicyUpdatedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub dues: Option<DuesPolicy>, // None when dues were cleared
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "dues")]
+#[event]
+pub struct DuesPaidEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub member: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub periods: u32,
+    pub paid_until: i64,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "dues")]
+#[event]
+pub struct DuesOverdueEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub member: Pubkey,
+    pub paid_until: i64,
+    pub grace_ends_at: i64, // the member can't vote after this if dues gate voting
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "shares")]
+#[event]
+pub struct StakeToJoinUpdatedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub price_lamports: Option<u64>, // None when stake-to-join was turned off
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "shares")]
+#[event]
+pub struct SharesStakedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub member: Pubkey,
+    pub shares: u64,
+    pub lamports: u64,
+    pub total_shares: u64,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "treasury")]
+#[event]
+pub struct TreasuryRecoveredEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub recovery: Pubkey,
+    pub lamports: u64,
+    pub token_accounts: u8, // SPL balances drained alongside the SOL
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "treasury-swaps")]
+#[event]
+pub struct TreasurySwappedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub swap_program: Pubkey,
+    pub input_mint: Pubkey,
+    pub output_mint: Pubkey,
+    pub amount_in: u64,  // actually spent
+    pub amount_out: u64, // actually received
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "shares")]
+#[event]
+pub struct RagequitEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub member: Pubkey,
+    pub shares: u64,
+    pub lamports: u64,
+    pub token_accounts: u8, // SPL balances paid out alongside the SOL
+    pub remaining_shares: u64,
+    pub total_shares: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MembershipPolicyUpdatedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub max_members: u32,
+    pub visibility: GroupVisibility,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MaxActiveProposalsUpdatedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub max_active_proposals: u16,
+    pub active_proposals: u16,
+    pub timestamp: i64,
+}
+
+// Delegation events aren't sequenced: a delegation may span every group
+#[cfg(feature = "delegation")]
+#[event]
+pub struct DelegationSetEvent {
+    pub delegator: Pubkey,
+    pub delegate: Pubkey,
+    pub group: Pubkey,
+    pub kind: Option<u8>,
+    pub expires_at: Option<i64>,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "delegation")]
+#[event]
+pub struct DelegationRevokedEvent {
+    pub delegator: Pubkey,
+    pub delegate: Pubkey,
+    pub group: Pubkey,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "delegation")]
+#[event]
+pub struct DelegationExpiredEvent {
+    pub delegator: Pubkey,
+    pub delegate: Pubkey,
+    pub group: Pubkey,
+    pub expired_at: i64,
+    pub timestamp: i64,
+}
+
+// Follows the VoteCastEvent of a delegated vote, with the same event_seq
+#[cfg(feature = "delegation")]
+#[event]
+pub struct DelegatedVoteEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub delegator: Pubkey,
+    pub delegate: Pubkey,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "sponsorship")]
+#[event]
+pub struct SponsorThresholdUpdatedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub threshold: u8,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "sponsorship")]
+#[event]
+pub struct ProposalSponsoredEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub sponsor: Pubkey,
+    pub sponsor_count: u8,
+    pub sponsors_needed: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TieBreakUpdatedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub tie_break: TieBreak,
+    pub timestamp: i64,
+}
+
+// Voting ended in a tie the policy doesn't settle at once: it reopened, or awaits the
+// council
+#[event]
+pub struct TieBreakStartedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub tied_choices: Vec<u8>,
+    pub tie_break: TieBreak,
+    pub voting_end: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ProposalCooldownUpdatedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub cooldown_secs: i64,
+    pub timestamp: i64,
+}
+
+// The group has as many listed members as its cap allows
+#[event]
+pub struct MemberCapReachedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub max_members: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct InviteCreatedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub invite: Pubkey,
+    pub max_uses: u32,
+    pub expires_at: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct InviteRevokedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub invite: Pubkey,
+    pub timestamp: i64,
+}
+
+// Follows the MemberAddedEvent of the member who used the invite
+#[event]
+pub struct InviteRedeemedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub invite: Pubkey,
+    pub member: Pubkey,
+    pub uses_left: u32,
+    pub timestamp: i64,
+}
+
+// `requirement` is None when the gate was cleared
+#[cfg(feature = "token-gated-join")]
+#[event]
+pub struct MembershipGateSetEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub requirement: Option<MembershipRequirement>,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "cnft-membership")]
+#[event]
+pub struct MembershipTreeCreatedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub merkle_tree: Pubkey,
+    pub timestamp: i64,
+}
+
+// Followed by MemberAddedEvent for the same member
+#[event]
+pub struct MembershipCredentialIssuedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub member: Pubkey,
+    pub asset_id: Pubkey,
+    pub nonce: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MemberRemovedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub member: Pubkey,
+    pub timestamp: i64,
+}
+
+// `removed` is set when the wallet was a listed member; expires_at is 0 for good
+#[cfg(feature = "bans")]
+#[event]
+pub struct MemberBannedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub wallet: Pubkey,
+    pub reason: String,
+    pub expires_at: i64,
+    pub removed: bool,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "bans")]
+#[event]
+pub struct MemberUnbannedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub wallet: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CouncilUpdatedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub members: Vec<Pubkey>,
+    pub threshold: u8,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "token-distribution")]
+#[event]
+pub struct TokenDistributionCreatedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub mint: Pubkey,
+    pub source: DistributionSource,
+    pub merkle_root: [u8; 32],
+    pub total_amount: u64,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "token-distribution")]
+#[event]
+pub struct TokenDistributionClaimedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub distribution: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "grants")]
+#[event]
+pub struct GrantCreatedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub recipient: Pubkey,
+    pub milestones: Vec<u64>,
+    pub approval: MilestoneApproval,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "grants")]
+#[event]
+pub struct MilestoneApprovedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub grant: Pubkey,
+    pub milestone: u8,
+    pub approver: Pubkey,
+    pub approvals: u32,
+    pub approved: bool, // this sign-off completed the approval
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "grants")]
+#[event]
+pub struct GrantTrancheClaimedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub grant: Pubkey,
+    pub milestone: u8,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub released: u64,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "streams")]
+#[event]
+pub struct StreamCreatedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub start: i64,
+    pub cliff: i64,
+    pub end: i64,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "streams")]
+#[event]
+pub struct VestedWithdrawnEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub stream: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub withdrawn: u64,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "bounties")]
+#[event]
+pub struct BountyPostedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub bounty_id: String,
+    pub description_uri: String,
+    pub reward: u64,
+    pub posted_by: Pubkey,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "bounties")]
+#[event]
+pub struct BountyClaimedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub bounty_id: String,
+    pub hunter: Pubkey,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "bounties")]
+#[event]
+pub struct BountySubmittedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub bounty_id: String,
+    pub hunter: Pubkey,
+    pub submission_uri: String,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "bounties")]
+#[event]
+pub struct BountyPaidEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub bounty_id: String,
+    pub hunter: Pubkey,
+    pub reward: u64,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "bounties")]
+#[event]
+pub struct BountyCancelledEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub bounty_id: String,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "treasury")]
+#[event]
+pub struct ProposalFeeUpdatedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub fee_lamports: u64,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "treasury")]
+#[event]
+pub struct ProposalDepositRequirementUpdatedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub lamports: u64,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "treasury")]
+#[event]
+pub struct ProposalDepositPostedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub depositor: Pubkey,
+    pub amount: u64,
+    pub spam_choice: u8,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "treasury")]
+#[event]
+pub struct ProposalFlaggedAsSpamEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub council_member: Pubkey,
+    pub flags: u8,
+    pub flagged: bool, // the council threshold has been reached
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "treasury")]
+#[event]
+pub struct ProposalDepositSettledEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub depositor: Pubkey,
+    pub amount: u64,
+    pub slashed: bool, // sent to the group treasury instead of refunded
+    pub flagged: bool,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "oracle-weights")]
+#[event]
+pub struct PriceWeightingSetEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub mints: Vec<Pubkey>,
+    pub max_staleness_secs: u32,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "offchain-votes")]
+#[event]
+pub struct OffchainVotingEnabledEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub aggregator: Pubkey,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "offchain-votes")]
+#[event]
+pub struct OffchainTallySubmittedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub aggregator: Pubkey,
+    pub merkle_root: [u8; 32],
+    pub choice_votes: Vec<u64>,
+    pub voter_count: u32,
+    pub challenge_end: i64,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "offchain-votes")]
+#[event]
+pub struct OffchainTotalDisputedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub disputer: Pubkey,
+    pub node: [u8; 32], // hash of the node that didn't add up
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "offchain-votes")]
+#[event]
+pub struct OffchainTallyDisputedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub disputer: Pubkey,
+    pub voter: Pubkey,
+    pub tallied_choice: u8,
+    pub ballot_choice: u8,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "offchain-votes")]
+#[event]
+pub struct OffchainTallySettledEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
+    pub aggregator: Pubkey,
     pub choice_votes: Vec<u64>,
+    pub voter_count: u32,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "templates")]
+#[event]
+pub struct ProposalTemplateCreatedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub template_id: String,
+    pub template: Pubkey,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "templates")]
+#[event]
+pub struct ProposalInstantiatedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub template_id: String,
+    pub proposal_id: String,
+    pub instance_count: u64,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "templates")]
+#[event]
+pub struct ProposalTemplateClosedEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub template_id: String,
+    pub timestamp: i64,
+}
+
+#[cfg(feature = "automation")]
+#[event]
+pub struct ActivationScheduledEvent {
+    pub group_id: String,
+    pub event_seq: u64,
+    pub proposal_id: String,
     pub voting_start: i64,
-    pub voting_end: i64,
-    pub token_mint: Option<Pubkey>,
-    pub creator: Pubkey,
-    pub voters: Vec<VoterInfo>,
-    pub created_at: i64,
-    pub bump: u8,
+    pub timestamp: i64,
+}
+
+// A Group, Proposal or UserAccount was rewritten at a newer layout version
+#[event]
+pub struct AccountMigratedEvent {
+    pub account: Pubkey,
+    pub from_version: u8,
+    pub to_version: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct UserAccountCreatedEvent {
+    pub telegram_id: i64,
+    pub wallet_pubkey: Pubkey,
+    pub timestamp: i64,
+}
+
+// Emitted only for logins proven with a wallet signature, unlike UserLoginEvent
+#[event]
+pub struct UserAuthenticatedEvent {
+    pub telegram_id: i64,
+    pub wallet: Pubkey,
+    pub nonce: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WalletLinkedEvent {
+    pub telegram_id: i64,
+    pub wallet: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WalletUnlinkedEvent {
+    pub telegram_id: i64,
+    pub wallet: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct UserLoginEvent {
+    pub telegram_id: i64,
+    pub wallet_pubkey: Pubkey,
+    pub timestamp: i64,
+}
+
+// Error Codes
+#[error_code]
+pub enum DaoError {
+    #[msg("Group ID too long (max 50 characters)")]
+    GroupIdTooLong,
+    #[msg("Name too long (max 100 characters)")]
+    NameTooLong,
+    #[msg("Description too long (max 500 characters)")]
+    DescriptionTooLong,
+    #[msg("Proposal ID too long (max 50 characters)")]
+    ProposalIdTooLong,
+    #[msg("Title too long (max 200 characters)")]
+    TitleTooLong,
+    #[msg("Invalid choice count (must be between 2 and 10)")]
+    InvalidChoiceCount,
+    #[msg("Invalid voting period")]
+    InvalidVotingPeriod,
+    #[msg("Voting start time cannot be in the past")]
+    VotingStartInPast,
+    #[msg("Voting is not currently active")]
+    VotingNotActive,
+    #[msg("Invalid choice")]
+    InvalidChoice,
+    #[msg("User has already voted")]
+    AlreadyVoted,
+    #[msg("Token account is required for token-weighted voting")]
+    TokenAccountRequired,
+    #[msg("Invalid token mint")]
+    InvalidTokenMint,
+    #[msg("No voting power")]
+    NoVotingPower,
+    #[msg("Unauthorized")]
+    Unauthorized,
+    #[msg("Member already exists")]
+    MemberAlreadyExists,
+    #[msg("Member not found")]
+    MemberNotFound,
+    #[msg("Invalid Telegram ID")]
+    InvalidTelegramId,
+    #[msg("Proposal count overflow")]
+    ProposalCountOverflow,
+    #[msg("Choice too long (max 50 characters)")]
+    ChoiceTooLong,
+    #[msg("Group registration is closed")]
+    RegistrationClosed,
+    #[msg("No deposit to withdraw")]
+    NoDeposit,
+    #[msg("Deposit is still locked")]
+    DepositLocked,
+    #[msg("Invalid slug (3-32 characters: lowercase letters, digits and dashes)")]
+    InvalidSlug,
+    #[msg("Slug is already assigned to a group")]
+    SlugInUse,
+    #[msg("The group's current slug reservation must be provided")]
+    SlugReservationRequired,
+    #[msg("Slug reservation does not match the group's current slug")]
+    SlugReservationMismatch,
+    #[msg("Vote tally overflow")]
+    TallyOverflow,
+    #[msg("The program is paused")]
+    ProgramPaused,
+    #[msg("Invalid program config")]
+    InvalidConfig,
+    #[msg("The registry has reached its group limit")]
+    RegistryFull,
+    #[msg("Token account is not owned by the voter or does not match the proposal mint")]
+    InvalidTokenAccount,
+    #[msg("Invalid metadata URI (max 200 characters, ipfs://, ar:// or https://)")]
+    InvalidMetadataUri,
+    #[msg("Event sequence overflow")]
+    EventSeqOverflow,
+    #[msg("This feature is not enabled in this program build")]
+    FeatureDisabled,
+    #[msg("Wallet is already linked to this user account")]
+    WalletAlreadyLinked,
+    #[msg("Wallet is not linked to this user account")]
+    WalletNotLinked,
+    #[msg("Too many linked wallets")]
+    TooManyLinkedWallets,
+    #[msg("Account is not a linked wallet (or its token account) of the voter")]
+    InvalidLinkedWallet,
+    #[msg("Missing or invalid ed25519 signature of the login challenge")]
+    InvalidLoginSignature,
+    #[msg("Login nonce overflow")]
+    LoginNonceOverflow,
+    #[msg("Disclosure note is too long or contains control characters")]
+    InvalidDisclosure,
+    #[msg("Voting has not ended yet")]
+    VotingNotEnded,
+    #[msg("Proposal is already finalized")]
+    ProposalAlreadyFinalized,
+    #[msg("Bounty amount must be greater than zero")]
+    InvalidBountyAmount,
+    #[msg("Group members cast real votes, not shadow votes")]
+    ShadowVoterIsMember,
+    #[msg("Proposal status does not allow this action")]
+    InvalidProposalStatus,
+    #[msg("Proposal is still within its grace period")]
+    ProposalNotStale,
+    #[msg("Proposal index does not point at this proposal")]
+    InvalidProposalIndex,
+    #[msg("Joint proposal id must be 1 to 32 bytes")]
+    InvalidJointId,
+    #[msg("Required passes exceed the maximum number of participating groups")]
+    InvalidRequiredPasses,
+    #[msg("Group or proposal already participates in this joint proposal")]
+    AlreadyParticipating,
+    #[msg("Joint proposal has the maximum number of participating groups")]
+    JointProposalFull,
+    #[msg("A joint proposal needs at least two participating groups")]
+    NotEnoughJointParticipants,
+    #[msg("Expected one settled result per participant, in order")]
+    JointResultMismatch,
+    #[msg("Upgrade timelock must be between 2 and 14 days")]
+    InvalidTimelock,
+    #[msg("Only the governing group can propose program upgrades")]
+    NotGoverningGroup,
+    #[msg("Buffer is not owned by the upgradeable BPF loader")]
+    InvalidUpgradeBuffer,
+    #[msg("Proposal result does not approve this upgrade")]
+    UpgradeNotApproved,
+    #[msg("Upgrade is already queued")]
+    UpgradeAlreadyQueued,
+    #[msg("Upgrade has not been queued")]
+    UpgradeNotQueued,
+    #[msg("Upgrade timelock has not elapsed")]
+    TimelockNotElapsed,
+    #[msg("Upgrade was already executed")]
+    UpgradeAlreadyExecuted,
+    #[msg("Reward amount must be greater than zero")]
+    InvalidRewardAmount,
+    #[msg("Reward pool belongs to another funder, token or vault")]
+    RewardPoolMismatch,
+    #[msg("Only wallets that voted on the proposal can claim its rewards")]
+    NotAVoter,
+    #[msg("The reward claim window has closed")]
+    RewardClaimWindowClosed,
+    #[msg("Voters can still claim these rewards")]
+    RewardsStillClaimable,
+    #[msg("Council needs 1 to 7 distinct members and a threshold between 1 and their count")]
+    InvalidCouncil,
+    #[msg("Signer is not on the group council")]
+    NotCouncilMember,
+    #[msg("Council member already flagged this proposal")]
+    AlreadyFlagged,
+    #[msg("Deposit amount must be greater than zero")]
+    InvalidDepositAmount,
+    #[msg("The draft has no room left for the spam choice")]
+    InvalidSpamChoice,
+    #[msg("Proposal fee must be zero or between the rent-exempt minimum and 1 SOL")]
+    InvalidProposalFee,
+    #[msg("Missing or invalid ed25519 signature of the relayed vote")]
+    InvalidVoteSignature,
+    #[msg("Relayed vote payload has expired")]
+    VotePayloadExpired,
+    #[msg("Votes on this proposal are collected off-chain")]
+    OffchainVotingOnly,
+    #[msg("Proposal already has on-chain votes")]
+    OnchainVotesCast,
+    #[msg("Off-chain tally has not been settled")]
+    OffchainTallyPending,
+    #[msg("Off-chain tally needs one total per choice")]
+    InvalidOffchainTally,
+    #[msg("An off-chain tally was already submitted and has not been disputed")]
+    OffchainTallyAlreadySubmitted,
+    #[msg("Off-chain tally has been disputed")]
+    OffchainTallyDisputed,
+    #[msg("Merkle proof does not match the tally root")]
+    InvalidMerkleProof,
+    #[msg("Signed ballot matches the tallied choice")]
+    BallotNotContradicting,
+    #[msg("The challenge window has closed")]
+    ChallengeWindowClosed,
+    #[msg("The challenge window is still open")]
+    ChallengeWindowOpen,
+    #[msg("Wallet has no ballot on this proposal")]
+    BallotNotFound,
+    #[msg("Price-weighted votes need the proposal's price weighting account")]
+    PriceWeightingRequired,
+    #[msg("Price-weighted proposals only take direct on-chain votes")]
+    PriceWeightingUnsupported,
+    #[msg("Price weighting needs 1-4 distinct mints, their mint accounts and a staleness limit")]
+    InvalidPriceFeeds,
+    #[msg("Not an accepted price account for this proposal")]
+    InvalidPriceAccount,
+    #[msg("Oracle price is too old")]
+    StalePrice,
+    #[msg("Oracle price is not positive")]
+    InvalidPrice,
+    #[msg("Only group members can vote on this group's proposals")]
+    NotAGroupMember,
+    #[msg("Membership credential does not match the group's credential tree")]
+    InvalidMembershipCredential,
+    #[msg("Membership token amount must be positive")]
+    InvalidMembershipGate,
+    #[msg("Wallet does not hold what this group requires of members")]
+    MembershipRequirementNotMet,
+    #[msg("Invite needs at least one use and an expiry in the future")]
+    InvalidInvite,
+    #[msg("Invite has expired")]
+    InviteExpired,
+    #[msg("Invite has no uses left")]
+    InviteExhausted,
+    #[msg("Member weight must be positive")]
+    InvalidMemberWeight,
+    #[msg("Member weights only apply to one-person-one-vote proposals voted on-chain by listed members")]
+    MemberWeightingUnsupported,
+    #[msg("Pass either the member's record or the group's membership tree")]
+    MemberRecordRequired,
+    #[msg("Member cap can't be below the current member count")]
+    InvalidMemberCap,
+    #[msg("Group has reached its member cap")]
+    GroupFull,
+    #[msg("Private groups only admit members the authority adds or invites")]
+    GroupIsPrivate,
+    #[msg("Proposal kind payload is invalid")]
+    InvalidProposalKind,
+    #[msg("Proposal is not of the kind this instruction executes")]
+    WrongProposalKind,
+    #[msg("Proposal did not pass with its first choice")]
+    ProposalNotApproved,
+    #[msg("Account does not match the proposal's payload")]
+    ExecutionAccountMismatch,
+    #[msg("Election is tied for the last seat")]
+    ElectionTied,
+    #[msg("Voting period is outside the group's allowed duration")]
+    VotingDurationOutOfBounds,
+    #[msg("Emergency proposals need the group's council account")]
+    CouncilRequired,
+    #[msg("Emergency proposals open for voting immediately and can't be drafts")]
+    EmergencyDraft,
+    #[msg("Split-weight votes need one allocation per choice, most on the named choice, totalling at most the voter's weight")]
+    InvalidSplitAllocation,
+    #[msg("Split-weight proposals only take votes cast with vote_on_proposal")]
+    SplitVotingUnsupported,
+    #[msg("Template ID must be 1 to 32 characters")]
+    InvalidTemplateId,
+    #[msg("Weight decay must be below 100%")]
+    InvalidWeightDecay,
+    #[msg("Weight decay needs a token-weighted proposal with on-chain ballots")]
+    WeightDecayUnsupported,
+    #[msg("Minimum holding period cannot be negative")]
+    InvalidHoldingPeriod,
+    #[msg("Withdrawal exceeds the deposit")]
+    InsufficientDeposit,
+    #[msg("Deposit-weighted proposals need the voter's deposit in their registrar")]
+    DepositRequired,
+    #[msg("Deposit was made too recently to count on this proposal")]
+    HoldingPeriodNotMet,
+    #[msg("Deposit voting needs a proposal in the registrar's token with on-chain ballots")]
+    DepositVotingUnsupported,
+    #[msg("A lottery draws 1 to 16 winners")]
+    InvalidLotteryWinners,
+    #[msg("Lottery has already been drawn")]
+    LotteryAlreadyDrawn,
+    #[msg("Lottery has not been drawn yet")]
+    LotteryNotDrawn,
+    #[msg("Signer was not drawn in this lottery")]
+    NotALotteryWinner,
+    #[msg("Prize has already been claimed")]
+    PrizeAlreadyClaimed,
+    #[msg("Drawing the lottery needs the slot hashes sysvar")]
+    LotteryRandomnessUnavailable,
+    #[msg("Dues need an amount, a period of at least a day and a grace period no longer than the period")]
+    InvalidDuesPolicy,
+    #[msg("This group doesn't charge dues")]
+    DuesNotConfigured,
+    #[msg("Pay for at least one period")]
+    InvalidDuesPeriods,
+    #[msg("Membership dues must be paid up to vote in this group")]
+    DuesNotCurrent,
+    #[msg("Dues aren't overdue, or were already flagged")]
+    DuesNotOverdue,
+    #[msg("Grant has no such milestone")]
+    InvalidGrantMilestone,
+    #[msg("Earlier milestones must be approved first")]
+    MilestoneOutOfOrder,
+    #[msg("Milestone has already been approved")]
+    MilestoneAlreadyApproved,
+    #[msg("Milestone has not been approved yet")]
+    MilestoneNotApproved,
+    #[msg("Tranche has already been claimed")]
+    TrancheAlreadyClaimed,
+    #[msg("Nothing has vested since the last withdrawal")]
+    NothingVested,
+    #[msg("Bounty needs an id of up to 32 bytes, a description URI and a reward")]
+    InvalidBounty,
+    #[msg("Bounty is not open")]
+    BountyNotOpen,
+    #[msg("Bounty has not been claimed")]
+    BountyNotClaimed,
+    #[msg("Bounty has no submission to approve")]
+    BountyNotSubmitted,
+    #[msg("Group doesn't sell shares")]
+    StakeToJoinDisabled,
+    #[msg("Share price must cover the treasury's rent-exempt minimum")]
+    InvalidSharePrice,
+    #[msg("Stake-to-join can't be turned off while members hold shares")]
+    SharesOutstanding,
+    #[msg("Invalid number of shares")]
+    InvalidShareAmount,
+    #[msg("Shares are locked by a recent purchase or vote")]
+    SharesLocked,
+    #[msg("Only shareholders can vote in this group")]
+    NoShares,
+    #[msg("Weight quorum needs the proposal's token mint or SOL deposit registrar")]
+    InvalidQuorumSupply,
+    #[msg("Proposal has no council chamber")]
+    NotBicameral,
+    #[msg("Group already has as many active proposals as it allows")]
+    TooManyActiveProposals,
+    #[msg("Proposal cooldown can't be negative")]
+    InvalidProposalCooldown,
+    #[msg("Creator must wait out the group's proposal cooldown")]
+    ProposalCooldownActive,
+    #[msg("Ban reason too long or expiry already passed")]
+    InvalidBan,
+    #[msg("Wallet is banned from this group")]
+    MemberBanned,
+    #[msg("The chat link PDA must be provided when setting or replacing a group's chat")]
+    ChatLinkRequired,
+    #[msg("Chat link does not match the group's chat")]
+    ChatLinkMismatch,
+    #[msg("Multisig needs 1 to 7 distinct members and a threshold between 1 and the member count")]
+    InvalidMultisig,
+    #[msg("Signer is not a member of the registry multisig")]
+    NotMultisigMember,
+    #[msg("Signer already approved this registry action")]
+    RegistryActionAlreadyApproved,
+    #[msg("Registry action has not reached the multisig threshold")]
+    RegistryActionNotApproved,
+    #[msg("Registry action has already been executed")]
+    RegistryActionExecuted,
+    #[msg("Choice details need one entry per choice, with descriptions of at most 140 characters")]
+    InvalidChoiceDetails,
+    #[msg("Callbacks can't target this program and their payload is limited to 256 bytes")]
+    InvalidCallback,
+    #[msg("Program allowlists hold up to 16 distinct programs other than this one")]
+    InvalidProgramAllowlist,
+    #[msg("The callback program is not on the group's program allowlist")]
+    ProgramNotAllowlisted,
+    #[msg(
+        "Treasury recovery needs 80% of the votes for the first choice and half the members voting"
+    )]
+    RecoveryNotCarried,
+    #[msg(
+        "Group profiles hold up to 4 distinct social links and a contact of at most 100 characters"
+    )]
+    InvalidGroupProfile,
+    #[msg("The swap spent more or returned less than the proposal allows")]
+    SwapSlippageExceeded,
+    #[msg("Swap routes can only use the proposal's treasury token accounts and can't take treasury SOL")]
+    SwapRouteRejected,
+    #[msg("This claim would exceed the distribution's total amount")]
+    DistributionExhausted,
+    #[msg("Revote windows must be between 1 second and 7 days")]
+    InvalidTieBreak,
+    #[msg("The proposal is tied and waits for the council to pick the winner")]
+    TieBreakPending,
+    #[msg("The proposal has no tie for the council to break, or the choice isn't tied for first")]
+    NotTied,
+    #[msg("Not enough council members signed")]
+    CouncilThresholdNotMet,
+    #[msg("This group's proposals must start as drafts and gather co-sponsors")]
+    SponsorshipRequired,
+    #[msg("The draft doesn't have enough co-sponsors yet")]
+    NotEnoughSponsors,
+    #[msg("The draft already has all the co-sponsors it needs")]
+    SponsorshipComplete,
+    #[msg("A proposal can need at most 32 co-sponsors")]
+    InvalidSponsorThreshold,
+    #[msg("The account is already at the current layout version")]
+    AccountUpToDate,
+    #[msg("The account's layout version has no known upgrade path")]
+    UnknownAccountVersion,
+    #[msg("Registry names can be at most 32 bytes")]
+    InvalidRegistryName,
+    #[msg("Only the default registry can change program-wide settings")]
+    NotDefaultRegistry,
+    #[msg("The group belongs to another registry")]
+    WrongRegistry,
+    #[msg("A wallet can't delegate to itself")]
+    SelfDelegation,
+    #[msg("Delegations can only be scoped to an existing proposal kind")]
+    InvalidDelegationScope,
+    #[msg("A delegation's expiry must be in the future")]
+    InvalidDelegationExpiry,
+    #[msg("The delegation has expired")]
+    DelegationExpired,
+    #[msg("The delegation hasn't expired")]
+    DelegationNotExpired,
+    #[msg("The delegation doesn't cover this proposal")]
+    DelegationOutOfScope,
+    #[msg("The delegator has a delegation for this group that takes precedence")]
+    DelegationOverridden,
+    #[msg("Missing or invalid ed25519 signature of the wallet link challenge")]
+    InvalidLinkSignature,
+    #[msg("Proposal still has an execution pending")]
+    ProposalAwaitsExecution,
+    #[msg("Proposal deposits must be 0 or between the rent minimum and the maximum")]
+    InvalidProposalDeposit,
+    #[msg("The group requires a proposal deposit before voting opens")]
+    ProposalDepositRequired,
+    #[msg("Deposit is below the group's required deposit or the rent minimum")]
+    DepositTooSmall,
+    #[msg("The proposal's deposit must be settled before it can be closed")]
+    ProposalDepositUnsettled,
+    #[msg("The opened tally node adds up")]
+    TallyNotContradicted,
+    #[msg("Treasury recovery needs on-chain votes in a members-only voting group")]
+    RecoveryNeedsMembersOnlyVoting,
+}
+
+// Advance an event sequence and return the new value. Group events are numbered per
+// group and registry-level events per registry, starting at 1 with no gaps, so indexers
+// can detect missed or replayed events.
+fn next_event_seq(event_seq: &mut u64) -> Result<u64> {
+    *event_seq = event_seq.checked_add(1).ok_or(DaoError::EventSeqOverflow)?;
+    Ok(*event_seq)
+}
+
+fn set_paused(ctx: Context<SetPause>, paused: bool) -> Result<()> {
+    let by = ctx.accounts.authority.key();
+    apply_pause(
+        &mut ctx.accounts.program_config,
+        &mut ctx.accounts.dao_registry,
+        paused,
+        by,
+    )
+}
+
+// Registry-level actions, taken by the registry authority directly or through an
+// approved registry multisig action
+fn apply_pause(
+    program_config: &mut ProgramConfig,
+    dao_registry: &mut DaoRegistry,
+    paused: bool,
+    by: Pubkey,
+) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    program_config.paused = paused;
+    program_config.updated_at = now;
+
+    emit!(PauseChangedEvent {
+        event_seq: next_event_seq(&mut dao_registry.event_seq)?,
+        paused,
+        by,
+        timestamp: now,
+    });
+    Ok(())
+}
+
+fn apply_program_config(
+    program_config: &mut ProgramConfig,
+    dao_registry: &mut DaoRegistry,
+    max_groups: u32,
+    max_choices: u8,
+    fee_destination: Pubkey,
+    guardian: Option<Pubkey>,
+) -> Result<()> {
+    require!(
+        (2..=LARGE_MAX_CHOICES as u8).contains(&max_choices),
+        DaoError::InvalidConfig
+    );
+    require!(
+        max_groups as usize >= dao_registry.groups.len(),
+        DaoError::InvalidConfig
+    );
+
+    let now = Clock::get()?.unix_timestamp;
+    program_config.max_groups = max_groups;
+    program_config.max_choices = max_choices;
+    program_config.fee_destination = fee_destination;
+    program_config.guardian = guardian;
+    program_config.updated_at = now;
+
+    emit!(ProgramConfigUpdatedEvent {
+        event_seq: next_event_seq(&mut dao_registry.event_seq)?,
+        max_groups,
+        max_choices,
+        fee_destination,
+        guardian,
+        timestamp: now,
+    });
+    Ok(())
+}
+
+fn apply_registration_config(
+    dao_registry: &mut DaoRegistry,
+    open_registration: bool,
+    group_deposit_lamports: u64,
+    group_deposit_lock_days: u32,
+) -> Result<()> {
+    dao_registry.open_registration = open_registration;
+    dao_registry.group_deposit_lamports = group_deposit_lamports;
+    dao_registry.group_deposit_lock_days = group_deposit_lock_days;
+
+    emit!(RegistrationConfigUpdatedEvent {
+        event_seq: next_event_seq(&mut dao_registry.event_seq)?,
+        open_registration,
+        group_deposit_lamports,
+        group_deposit_lock_days,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    Ok(())
+}
+
+fn apply_registry_authority(dao_registry: &mut DaoRegistry, new_authority: Pubkey) -> Result<()> {
+    let old_authority = dao_registry.authority;
+    dao_registry.authority = new_authority;
+
+    emit!(RegistryAuthorityChangedEvent {
+        event_seq: next_event_seq(&mut dao_registry.event_seq)?,
+        old_authority,
+        new_authority,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    Ok(())
+}
+
+// 1 to MAX_COUNCIL_MEMBERS distinct members and a threshold no larger than their number
+#[cfg(feature = "registry-multisig")]
+fn validate_multisig_signers(members: &[Pubkey], threshold: u8) -> Result<()> {
+    require!(
+        !members.is_empty()
+            && members.len() <= MAX_COUNCIL_MEMBERS
+            && threshold > 0
+            && threshold as usize <= members.len(),
+        DaoError::InvalidMultisig
+    );
+    for (i, member) in members.iter().enumerate() {
+        require!(!members[..i].contains(member), DaoError::InvalidMultisig);
+    }
+    Ok(())
+}
+
+// Whether the spam choice drew at least two thirds of all votes cast
+#[cfg(feature = "treasury")]
+fn is_spam_supermajority(choice_votes: &[u64], spam_choice: u8) -> bool {
+    let total: u128 = choice_votes.iter().map(|&v| v as u128).sum();
+    let spam = choice_votes
+        .get(spam_choice as usize)
+        .map_or(0, |&v| v as u128);
+    total > 0 && spam * 3 >= total * 2
+}
+
+// Charge a group's proposal creation fee, on top of rent, into its treasury PDA
+fn pay_proposal_fee<'info>(
+    fee_lamports: u64,
+    payer: &Signer<'info>,
+    treasury: &UncheckedAccount<'info>,
+    system_program: &Program<'info, System>,
+) -> Result<()> {
+    if fee_lamports == 0 {
+        return Ok(());
+    }
+    system_program::transfer(
+        CpiContext::new(
+            system_program.to_account_info(),
+            system_program::Transfer {
+                from: payer.to_account_info(),
+                to: treasury.to_account_info(),
+            },
+        ),
+        fee_lamports,
+    )
+}
+
+// Checks shared by every way of voting on a standard proposal
+fn require_can_vote(
+    proposal: &Proposal,
+    voter: &Pubkey,
+    choice_index: u8,
+    clock: &Clock,
+) -> Result<()> {
+    require!(
+        proposal.status == ProposalStatus::Active && proposal.voting_open(clock),
+        DaoError::VotingNotActive
+    );
+    require!(
+        proposal.offchain_aggregator.is_none(),
+        DaoError::OffchainVotingOnly
+    );
+    require!(
+        (choice_index as usize) < proposal.choices.len(),
+        DaoError::InvalidChoice
+    );
+    require!(
+        !proposal.voters.iter().any(|v| v.voter == *voter),
+        DaoError::AlreadyVoted
+    );
+    Ok(())
+}
+
+// Hash an invite is stored under: SHA-256 of the group and the code, so the same code
+// can be reused across groups
+pub fn invite_code_hash(group: &Pubkey, code: &str) -> [u8; 32] {
+    anchor_lang::solana_program::hash::hashv(&[b"invite", group.as_ref(), code.as_bytes()])
+        .to_bytes()
+}
+
+fn link_chat(chat_link: &mut ChatLink, chat_id: i64, group: Pubkey, bump: u8, now: i64) {
+    chat_link.chat_id = chat_id;
+    chat_link.group = group;
+    chat_link.linked_at = now;
+    chat_link.bump = bump;
+}
+
+// Fails while the wallet's ban is in force. Join and vote contexts take the ban PDA
+// whether or not it exists, so a banned wallet can't leave it out.
+fn require_not_banned(ban: &AccountInfo, now: i64) -> Result<()> {
+    if ban.data_is_empty() {
+        return Ok(());
+    }
+    require_keys_eq!(*ban.owner, crate::ID, DaoError::MemberBanned);
+    let ban = Ban::try_deserialize(&mut &ban.try_borrow_data()?[..])?;
+    require!(!ban.in_force(now), DaoError::MemberBanned);
+    Ok(())
+}
+
+// Members-only groups take votes from listed members here, who pass their member
+// record; credential holders vote through vote_with_credential
+fn require_listed_member(group: &Group, member_record: Option<&Account<Member>>) -> Result<()> {
+    require!(
+        !group.members_only_voting || member_record.is_some(),
+        DaoError::NotAGroupMember
+    );
+    Ok(())
+}
+
+// Groups that gate voting on dues take votes only from wallets whose dues are paid up,
+// or lapsed no longer than the grace period
+fn require_dues_current(
+    group: &Group,
+    dues_record: Option<&Account<DuesRecord>>,
+    now: i64,
+) -> Result<()> {
+    if let Some(dues) = group.dues.filter(|dues| dues.required_for_voting) {
+        let paid_until = dues_record.map_or(0, |record| record.paid_until);
+        require!(
+            now <= paid_until.saturating_add(dues.grace_secs),
+            DaoError::DuesNotCurrent
+        );
+    }
+    Ok(())
+}
+
+// Treasury SOL that shares are a claim on: its balance above the rent minimum, less what
+// open grants, streams and bounties are owed
+#[cfg(feature = "shares")]
+fn share_pool_lamports(treasury: &AccountInfo, group: &Group) -> Result<u64> {
+    Ok(treasury
+        .lamports()
+        .saturating_sub(Rent::get()?.minimum_balance(0))
+        .saturating_sub(group.committed_lamports))
+}
+
+// Stake-to-join groups take votes only from shareholders, and lock their shares against
+// ragequit until the guard window after the proposal's voting ends
+fn lock_voted_shares(
+    group: &Group,
+    member_shares: Option<&mut Account<MemberShares>>,
+    voting_end: i64,
+) -> Result<()> {
+    if group.stake_to_join.is_some() {
+        let member_shares = member_shares
+            .filter(|member_shares| member_shares.shares > 0)
+            .ok_or(DaoError::NoShares)?;
+        member_shares.locked_until = member_shares
+            .locked_until
+            .max(voting_end.saturating_add(RAGEQUIT_GUARD_SECS));
+    }
+    Ok(())
+}
+
+// Weight of a listed member on member-weighted proposals
+fn member_vote_weight(member_record: Option<&Account<Member>>) -> Result<u64> {
+    member_record
+        .map(|m| u64::from(m.weight))
+        .ok_or_else(|| error!(DaoError::NotAGroupMember))
+}
+
+// Fill in a freshly created member record, within the group's member cap. init_if_needed
+// hands back an existing record unchanged, so a record that already names a wallet means
+// the wallet is a member.
+fn admit_member(
+    group: &mut Account<Group>,
+    member_record: &mut Account<Member>,
+    wallet: Pubkey,
+    ban: &AccountInfo,
+    bump: u8,
+    now: i64,
+) -> Result<()> {
+    require!(
+        member_record.wallet == Pubkey::default(),
+        DaoError::MemberAlreadyExists
+    );
+    require_not_banned(ban, now)?;
+    require!(
+        group.max_members == 0 || group.member_count < group.max_members,
+        DaoError::GroupFull
+    );
+    member_record.group = group.key();
+    member_record.wallet = wallet;
+    member_record.joined_at = now;
+    member_record.weight = 1;
+    member_record.bump = bump;
+    group.member_count = group
+        .member_count
+        .checked_add(1)
+        .ok_or(DaoError::TallyOverflow)?;
+
+    let event_seq = next_event_seq(&mut group.event_seq)?;
+    emit!(MemberAddedEvent {
+        group_id: group.group_id.clone(),
+        event_seq,
+        member: wallet,
+        timestamp: now,
+    });
+    if group.member_count == group.max_members {
+        let event_seq = next_event_seq(&mut group.event_seq)?;
+        emit!(MemberCapReachedEvent {
+            group_id: group.group_id.clone(),
+            event_seq,
+            max_members: group.max_members,
+            timestamp: now,
+        });
+    }
+    Ok(())
+}
+
+// Voting weight of the signing wallet under a proposal's token mode. Callers add any
+// linked-wallet weight and then reject a zero total with NoVotingPower.
+fn compute_vote_weight<'info>(
+    token_mint: Option<Pubkey>,
+    voter: &AccountInfo<'info>,
+    voter_token_account: &Option<AccountInfo<'info>>,
+) -> Result<u64> {
+    let vote_weight = if let Some(token_mint) = token_mint {
+        if token_mint == native_mint() {
+            // SOL-weighted voting
+            voter.lamports()
+        } else {
+            let token_account_info = voter_token_account
+                .as_ref()
+                .ok_or(DaoError::TokenAccountRequired)?;
+            let (owner, amount) = spl_token_balance(token_mint, token_account_info)?;
+            require_keys_eq!(owner, voter.key(), DaoError::InvalidTokenAccount);
+            amount
+        }
+    } else {
+        // One person, one vote
+        1u64
+    };
+
+    Ok(vote_weight)
+}
+
+// A voter's registrar deposit, counted only if made by the proposal's cutoff
+fn held_deposit_weight(
+    deposit_voting: &DepositVoting,
+    voter: &Pubkey,
+    deposit: Option<&VoterDeposit>,
+) -> Result<u64> {
+    let deposit = deposit.ok_or(DaoError::DepositRequired)?;
+    require!(
+        deposit.registrar == deposit_voting.registrar && deposit.voter == *voter,
+        DaoError::DepositRequired
+    );
+    require!(
+        deposit.deposited_at <= deposit_voting.cutoff,
+        DaoError::HoldingPeriodNotMet
+    );
+    Ok(deposit.amount)
+}
+
+// Extra weight from wallets linked to the voter's user account, for token modes only.
+// SOL-weighted proposals take the linked wallets themselves as remaining accounts, SPL
+// proposals take their token accounts. Returns the weight and the wallets it came from.
+fn linked_wallet_weight(
+    token_mint: Option<Pubkey>,
+    voter: &Pubkey,
+    user_account: Option<&UserAccount>,
+    linked_accounts: &[AccountInfo],
+) -> Result<(u64, Vec<Pubkey>)> {
+    let (Some(token_mint), Some(user_account)) = (token_mint, user_account) else {
+        return Ok((0, Vec::new()));
+    };
+    require_keys_eq!(user_account.wallet_pubkey, *voter, DaoError::Unauthorized);
+
+    let mut weight = 0u64;
+    let mut counted = Vec::with_capacity(linked_accounts.len());
+    for account in linked_accounts {
+        let (wallet, amount) = if token_mint == native_mint() {
+            (account.key(), account.lamports())
+        } else {
+            spl_token_balance(token_mint, account)?
+        };
+        require!(
+            user_account.linked_wallets.contains(&wallet) && !counted.contains(&wallet),
+            DaoError::InvalidLinkedWallet
+        );
+        weight = weight.checked_add(amount).ok_or(DaoError::TallyOverflow)?;
+        counted.push(wallet);
+    }
+    Ok((weight, counted))
+}
+
+// Voting weight on a price-weighted proposal: the value of the voter's holdings of the
+// accepted mints, with PRICE_WEIGHT_DECIMALS decimals. `accounts` are (holding, price
+// account) pairs, at most one per mint; the holding is the voter's own wallet for
+// native SOL and their token account for anything else.
+#[cfg(feature = "oracle-weights")]
+fn priced_vote_weight(
+    weighting: &PriceWeighting,
+    voter: &AccountInfo,
+    accounts: &[AccountInfo],
+    now: i64,
+) -> Result<u64> {
+    require!(
+        accounts.len().is_multiple_of(2),
+        DaoError::InvalidPriceAccount
+    );
+
+    let mut weight = 0u64;
+    let mut counted = Vec::with_capacity(accounts.len() / 2);
+    for pair in accounts.chunks(2) {
+        let (holding, oracle) = (&pair[0], &pair[1]);
+        let feed = weighting
+            .feeds
+            .iter()
+            .find(|f| f.oracle == oracle.key())
+            .ok_or(DaoError::InvalidPriceAccount)?;
+        require!(!counted.contains(&feed.mint), DaoError::InvalidPriceAccount);
+        counted.push(feed.mint);
+
+        let amount = if feed.mint == native_mint() {
+            require_keys_eq!(holding.key(), voter.key(), DaoError::InvalidTokenAccount);
+            voter.lamports()
+        } else {
+            let (owner, amount) = spl_token_balance(feed.mint, holding)?;
+            require_keys_eq!(owner, voter.key(), DaoError::InvalidTokenAccount);
+            amount
+        };
+
+        let (price, exponent) = oracle_price(oracle, weighting.max_staleness_secs, now)?;
+        let value = (amount as u128)
+            .checked_mul(price)
+            .ok_or(DaoError::TallyOverflow)?;
+        let shift = exponent + PRICE_WEIGHT_DECIMALS as i32 - feed.decimals as i32;
+        let value = if shift >= 0 {
+            10u128
+                .checked_pow(shift as u32)
+                .and_then(|factor| value.checked_mul(factor))
+                .ok_or(DaoError::TallyOverflow)?
+        } else {
+            10u128
+                .checked_pow(shift.unsigned_abs())
+                .map_or(0, |factor| value / factor)
+        };
+        weight = u64::try_from(value)
+            .ok()
+            .and_then(|value| weight.checked_add(value))
+            .ok_or(DaoError::TallyOverflow)?;
+    }
+    Ok(weight)
+}
+
+#[cfg(not(feature = "oracle-weights"))]
+fn priced_vote_weight(
+    _weighting: &PriceWeighting,
+    _voter: &AccountInfo,
+    _accounts: &[AccountInfo],
+    _now: i64,
+) -> Result<u64> {
+    err!(DaoError::FeatureDisabled)
+}
+
+// Price from a Pyth PriceUpdateV2 or Switchboard On-Demand pull feed, as a positive
+// mantissa and a base-10 exponent. Pyth updates must be fully verified by Wormhole
+// guardians, and either kind must have been published within `max_staleness_secs`.
+#[cfg(feature = "oracle-weights")]
+fn oracle_price(oracle: &AccountInfo, max_staleness_secs: u32, now: i64) -> Result<(u128, i32)> {
+    // PriceUpdateV2: discriminator, write authority, verification level (1 = Full), then
+    // the price message: feed id, price, conf, exponent, publish time
+    const PYTH_DISCRIMINATOR: [u8; 8] = [34, 241, 35, 99, 157, 126, 244, 205];
+    const PYTH_VERIFICATION_LEVEL: usize = 40;
+    const PYTH_PRICE: usize = 73;
+    const PYTH_EXPONENT: usize = 89;
+    const PYTH_PUBLISH_TIME: usize = 93;
+    // PullFeedAccountData (repr(C)): last_update_timestamp, then the current result,
+    // whose value is an i128 with 18 decimals
+    const SWITCHBOARD_DISCRIMINATOR: [u8; 8] = [196, 27, 108, 196, 10, 215, 219, 40];
+    const SWITCHBOARD_UPDATED_AT: usize = 2216;
+    const SWITCHBOARD_VALUE: usize = 2264;
+
+    fn field<const N: usize>(data: &[u8], offset: usize) -> Result<[u8; N]> {
+        data.get(offset..offset + N)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| DaoError::InvalidPriceAccount.into())
+    }
+
+    let data = oracle.try_borrow_data()?;
+    let (price, exponent, published_at) = if *oracle.owner == PYTH_RECEIVER_PROGRAM_ID {
+        require!(
+            field::<8>(&data, 0)? == PYTH_DISCRIMINATOR
+                && field::<1>(&data, PYTH_VERIFICATION_LEVEL)? == [1],
+            DaoError::InvalidPriceAccount
+        );
+        (
+            i64::from_le_bytes(field(&data, PYTH_PRICE)?) as i128,
+            i32::from_le_bytes(field(&data, PYTH_EXPONENT)?),
+            i64::from_le_bytes(field(&data, PYTH_PUBLISH_TIME)?),
+        )
+    } else if SWITCHBOARD_ON_DEMAND_PROGRAM_IDS.contains(oracle.owner) {
+        require!(
+            field::<8>(&data, 0)? == SWITCHBOARD_DISCRIMINATOR,
+            DaoError::InvalidPriceAccount
+        );
+        // Cut to 9 decimals so a full u64 balance times the price stays within u128
+        (
+            i128::from_le_bytes(field(&data, SWITCHBOARD_VALUE)?) / 1_000_000_000,
+            -9,
+            i64::from_le_bytes(field(&data, SWITCHBOARD_UPDATED_AT)?),
+        )
+    } else {
+        return err!(DaoError::InvalidPriceAccount);
+    };
+
+    require!(
+        now.saturating_sub(published_at) <= max_staleness_secs as i64,
+        DaoError::StalePrice
+    );
+    require!(price > 0, DaoError::InvalidPrice);
+    Ok((price as u128, exponent))
+}
+
+// Decimals of an SPL mint account
+#[cfg(feature = "oracle-weights")]
+fn mint_decimals(mint_account: &AccountInfo) -> Result<u8> {
+    require_keys_eq!(
+        *mint_account.owner,
+        anchor_spl::token::ID,
+        DaoError::InvalidPriceFeeds
+    );
+    let mint = anchor_spl::token::Mint::try_deserialize(&mut &mint_account.try_borrow_data()?[..])
+        .map_err(|_| DaoError::InvalidPriceFeeds)?;
+    Ok(mint.decimals)
+}
+
+// Check that `joiner` meets a group's membership requirement with `holding`, a token
+// account they own, and for collection gates the held NFT's metadata
+#[cfg(feature = "token-gated-join")]
+fn require_membership_requirement(
+    requirement: &MembershipRequirement,
+    joiner: &Pubkey,
+    holding: &AccountInfo,
+    nft_metadata: Option<&UncheckedAccount>,
+) -> Result<()> {
+    require_keys_eq!(
+        *holding.owner,
+        anchor_spl::token::ID,
+        DaoError::InvalidTokenAccount
+    );
+    let token_account =
+        anchor_spl::token::TokenAccount::try_deserialize(&mut &holding.try_borrow_data()?[..])
+            .map_err(|_| DaoError::InvalidTokenAccount)?;
+    require_keys_eq!(token_account.owner, *joiner, DaoError::InvalidTokenAccount);
+
+    match requirement {
+        MembershipRequirement::Token { mint, min_amount } => {
+            require_keys_eq!(token_account.mint, *mint, DaoError::InvalidTokenAccount);
+            require!(
+                token_account.amount >= *min_amount,
+                DaoError::MembershipRequirementNotMet
+            );
+        }
+        MembershipRequirement::Collection { collection } => {
+            require!(
+                token_account.amount >= 1,
+                DaoError::MembershipRequirementNotMet
+            );
+            let metadata = nft_metadata.ok_or(DaoError::MembershipRequirementNotMet)?;
+            let (expected, _) = Pubkey::find_program_address(
+                &[
+                    b"metadata",
+                    TOKEN_METADATA_PROGRAM_ID.as_ref(),
+                    token_account.mint.as_ref(),
+                ],
+                &TOKEN_METADATA_PROGRAM_ID,
+            );
+            require!(
+                metadata.key() == expected && *metadata.owner == TOKEN_METADATA_PROGRAM_ID,
+                DaoError::MembershipRequirementNotMet
+            );
+            require!(
+                verified_collection(&metadata.try_borrow_data()?) == Some(*collection),
+                DaoError::MembershipRequirementNotMet
+            );
+        }
+    }
+    Ok(())
+}
+
+// The verified collection in a Metaplex metadata account, read past its variable-length
+// fields: key, update authority, mint, name, symbol, uri, seller fee, creators, primary
+// sale, mutability, edition nonce and token standard
+#[cfg(feature = "token-gated-join")]
+fn verified_collection(data: &[u8]) -> Option<Pubkey> {
+    let mut offset = 1 + 32 + 32;
+    for _ in 0..3 {
+        let len = u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?) as usize;
+        offset += 4 + len;
+    }
+    offset += 2;
+    if *data.get(offset)? == 1 {
+        let count = u32::from_le_bytes(data.get(offset + 1..offset + 5)?.try_into().ok()?);
+        offset += 4 + count as usize * 34;
+    }
+    offset += 1 + 2;
+    for _ in 0..2 {
+        offset += if *data.get(offset)? == 1 { 2 } else { 1 };
+    }
+    if *data.get(offset)? != 1 || *data.get(offset + 1)? != 1 {
+        return None;
+    }
+    Some(Pubkey::new_from_array(
+        data.get(offset + 2..offset + 34)?.try_into().ok()?,
+    ))
+}
+
+// Mint `member` a credential into the group's tree via Bubblegum, returning its asset id
+// and nonce. `accounts` are the member, Bubblegum's tree config, the Merkle tree, the
+// noop and account compression programs and Bubblegum itself.
+#[cfg(feature = "cnft-membership")]
+fn mint_membership_credential<'info>(
+    membership_tree: &mut Account<'info, MembershipTree>,
+    group: &Pubkey,
+    member: Pubkey,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    accounts: &[AccountInfo<'info>],
+) -> Result<(Pubkey, u64)> {
+    let [leaf_owner, tree_config, merkle_tree, log_wrapper, compression_program, bubblegum_program] =
+        accounts
+    else {
+        return err!(DaoError::InvalidMembershipCredential);
+    };
+    require_keys_eq!(
+        leaf_owner.key(),
+        member,
+        DaoError::InvalidMembershipCredential
+    );
+    require_keys_eq!(
+        merkle_tree.key(),
+        membership_tree.merkle_tree,
+        DaoError::InvalidMembershipCredential
+    );
+    require!(
+        log_wrapper.key() == NOOP_PROGRAM_ID
+            && compression_program.key() == ACCOUNT_COMPRESSION_PROGRAM_ID
+            && bubblegum_program.key() == BUBBLEGUM_PROGRAM_ID,
+        DaoError::InvalidMembershipCredential
+    );
+
+    let mut data = BUBBLEGUM_MINT_V1_DISCRIMINATOR.to_vec();
+    membership_credential_metadata(group, &member).serialize(&mut data)?;
+    invoke_signed(
+        &Instruction {
+            program_id: BUBBLEGUM_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(tree_config.key(), false),
+                AccountMeta::new_readonly(member, false),
+                AccountMeta::new_readonly(membership_tree.key(), false),
+                AccountMeta::new(merkle_tree.key(), false),
+                AccountMeta::new_readonly(payer.key(), true),
+                AccountMeta::new_readonly(membership_tree.key(), true),
+                AccountMeta::new_readonly(NOOP_PROGRAM_ID, false),
+                AccountMeta::new_readonly(ACCOUNT_COMPRESSION_PROGRAM_ID, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+            data,
+        },
+        &[
+            tree_config.clone(),
+            leaf_owner.clone(),
+            membership_tree.to_account_info(),
+            merkle_tree.clone(),
+            payer.to_account_info(),
+            log_wrapper.clone(),
+            compression_program.clone(),
+            system_program.to_account_info(),
+            bubblegum_program.clone(),
+        ],
+        &[&[b"membership_tree", group.as_ref(), &[membership_tree.bump]]],
+    )?;
+
+    // Only this PDA mints into the tree, so its count is the Bubblegum nonce
+    let nonce = membership_tree.credentials_issued;
+    membership_tree.credentials_issued = nonce.checked_add(1).ok_or(DaoError::TallyOverflow)?;
+    Ok((
+        credential_asset_id(&membership_tree.merkle_tree, nonce),
+        nonce,
+    ))
+}
+
+#[cfg(not(feature = "cnft-membership"))]
+fn mint_membership_credential<'info>(
+    _membership_tree: &mut Account<'info, MembershipTree>,
+    _group: &Pubkey,
+    _member: Pubkey,
+    _payer: &Signer<'info>,
+    _system_program: &Program<'info, System>,
+    _accounts: &[AccountInfo<'info>],
+) -> Result<(Pubkey, u64)> {
+    err!(DaoError::FeatureDisabled)
+}
+
+#[cfg(feature = "cnft-membership")]
+fn membership_credential_metadata(group: &Pubkey, member: &Pubkey) -> CredentialMetadata {
+    CredentialMetadata {
+        name: MEMBERSHIP_CREDENTIAL_NAME.to_string(),
+        symbol: MEMBERSHIP_CREDENTIAL_SYMBOL.to_string(),
+        uri: format!("{MEMBERSHIP_CREDENTIAL_URI_PREFIX}{group}/{member}"),
+        seller_fee_basis_points: 0,
+        primary_sale_happened: false,
+        is_mutable: false,
+        edition_nonce: None,
+        token_standard: Some(0), // NonFungible
+        collection: None,
+        uses: None,
+        token_program_version: 0, // Original
+        creators: Vec::new(),
+    }
+}
+
+// Bubblegum's data hash: keccak(keccak(metadata) || seller fee basis points)
+#[cfg(feature = "cnft-membership")]
+fn membership_credential_data_hash(group: &Pubkey, member: &Pubkey) -> [u8; 32] {
+    use anchor_lang::solana_program::keccak::hashv;
+
+    let metadata = membership_credential_metadata(group, member);
+    let metadata_hash = hashv(&[&metadata.try_to_vec().unwrap_or_default()]);
+    hashv(&[
+        metadata_hash.as_ref(),
+        &metadata.seller_fee_basis_points.to_le_bytes(),
+    ])
+    .to_bytes()
+}
+
+// Bubblegum's creator hash of an empty creator list
+#[cfg(feature = "cnft-membership")]
+fn membership_credential_creator_hash() -> [u8; 32] {
+    anchor_lang::solana_program::keccak::hashv(&[]).to_bytes()
+}
+
+#[cfg(feature = "cnft-membership")]
+fn credential_asset_id(merkle_tree: &Pubkey, nonce: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"asset", merkle_tree.as_ref(), &nonce.to_le_bytes()],
+        &BUBBLEGUM_PROGRAM_ID,
+    )
+    .0
+}
+
+// Bubblegum's V1 leaf for `member`'s credential as minted: owned by the member and
+// still delegated to the membership tree
+#[cfg(feature = "cnft-membership")]
+fn membership_credential_leaf(
+    membership_tree: &Account<MembershipTree>,
+    group: &Pubkey,
+    member: &Pubkey,
+    nonce: u64,
+) -> [u8; 32] {
+    anchor_lang::solana_program::keccak::hashv(&[
+        &[1], // LeafSchema version V1
+        credential_asset_id(&membership_tree.merkle_tree, nonce).as_ref(),
+        member.as_ref(),
+        membership_tree.key().as_ref(),
+        &nonce.to_le_bytes(),
+        &membership_credential_data_hash(group, member),
+        &membership_credential_creator_hash(),
+    ])
+    .to_bytes()
+}
+
+// Whether a joint proposal participant passed, from its ProposalResult or, for a
+// cancelled proposal, the proposal itself. Anything unsettled is rejected.
+#[cfg(feature = "joint-proposals")]
+fn joint_participant_passed<'info>(
+    participant: &JointParticipant,
+    account: &'info AccountInfo<'info>,
+) -> Result<bool> {
+    if let Ok(result) = Account::<ProposalResult>::try_from(account) {
+        require_keys_eq!(
+            result.proposal,
+            participant.proposal,
+            DaoError::JointResultMismatch
+        );
+        return Ok(result.status == ProposalStatus::Succeeded);
+    }
+    let proposal =
+        Account::<Proposal>::try_from(account).map_err(|_| DaoError::JointResultMismatch)?;
+    require!(
+        proposal.key() == participant.proposal && proposal.status == ProposalStatus::Cancelled,
+        DaoError::JointResultMismatch
+    );
+    Ok(false)
+}
+
+// Owner and balance of an SPL token account for `token_mint`. The account must be a
+// real token account for this mint, or anyone could pass a crafted balance.
+#[cfg(feature = "token-voting")]
+fn spl_token_balance(
+    token_mint: Pubkey,
+    token_account_info: &AccountInfo,
+) -> Result<(Pubkey, u64)> {
+    require_keys_eq!(
+        *token_account_info.owner,
+        anchor_spl::token::ID,
+        DaoError::InvalidTokenAccount
+    );
+    let token_account = anchor_spl::token::TokenAccount::try_deserialize(
+        &mut &token_account_info.try_borrow_data()?[..],
+    )
+    .map_err(|_| DaoError::InvalidTokenAccount)?;
+    require_keys_eq!(
+        token_account.mint,
+        token_mint,
+        DaoError::InvalidTokenAccount
+    );
+    Ok((token_account.owner, token_account.amount))
+}
+
+// Whether an account is an SPL token account owned by `owner`
+#[cfg(feature = "treasury-swaps")]
+fn spl_token_account_of(account: &AccountInfo, owner: Pubkey) -> bool {
+    *account.owner == anchor_spl::token::ID
+        && account.try_borrow_data().is_ok_and(|data| {
+            data.len() == anchor_spl::token::TokenAccount::LEN && data[32..64] == owner.to_bytes()
+        })
+}
+
+// Whether a draft's ProposalDeposit PDA holds at least `required` lamports of deposit
+fn deposit_posted(deposit: &AccountInfo, required: u64) -> Result<bool> {
+    if required == 0 {
+        return Ok(true);
+    }
+    #[cfg(feature = "treasury")]
+    if !deposit.data_is_empty() {
+        let data = deposit.try_borrow_data()?;
+        return Ok(ProposalDeposit::try_deserialize(&mut &data[..])?.amount >= required);
+    }
+    #[cfg(not(feature = "treasury"))]
+    let _ = deposit;
+    Ok(false)
+}
+
+// Whether a program upgrade proposed against `proposal` can still run: it exists, hasn't
+// executed, and the proposal's winner is the choice that approves it
+#[cfg(feature = "treasury")]
+fn upgrade_pending(proposal: &Proposal, upgrade: &AccountInfo) -> Result<bool> {
+    if upgrade.data_is_empty() {
+        return Ok(false);
+    }
+    let data = upgrade.try_borrow_data()?;
+    let upgrade = UpgradeProposal::try_deserialize(&mut &data[..])?;
+    Ok(upgrade.executed_at == 0 && proposal.winner == Some(upgrade.approve_choice))
+}
+
+// Whether the treasury PDA's signature would carry authority over `account` beyond its
+// token accounts: a mint it is mint or freeze authority of (distribution mints), or a
+// stake account it is staker or withdrawer of (treasury staking)
+#[cfg(feature = "treasury-swaps")]
+fn treasury_authority_account(account: &AccountInfo, treasury: Pubkey) -> bool {
+    use anchor_lang::solana_program::program_option::COption;
+
+    let Ok(data) = account.try_borrow_data() else {
+        return true;
+    };
+    if *account.owner == anchor_spl::token::ID && data.len() == anchor_spl::token::Mint::LEN {
+        return anchor_spl::token::Mint::try_deserialize(&mut &data[..]).is_ok_and(|mint| {
+            mint.mint_authority == COption::Some(treasury)
+                || mint.freeze_authority == COption::Some(treasury)
+        });
+    }
+    if *account.owner == anchor_lang::solana_program::stake::program::ID {
+        // StakeStateV2: u32 tag, then Meta { rent_exempt_reserve: u64, staker, withdrawer }
+        return data.len() >= 76
+            && (data[12..44] == treasury.to_bytes() || data[44..76] == treasury.to_bytes());
+    }
+    false
+}
+
+// Balance of a swap's input or output account after the route ran, which must have left
+// it owned by the treasury with no delegate or close authority
+#[cfg(feature = "treasury-swaps")]
+fn untouched_treasury_token_account(account: &AccountInfo, treasury: Pubkey) -> Result<u64> {
+    let token_account =
+        anchor_spl::token::TokenAccount::try_deserialize(&mut &account.try_borrow_data()?[..])
+            .map_err(|_| DaoError::SwapRouteRejected)?;
+    require!(
+        token_account.owner == treasury
+            && token_account.delegate.is_none()
+            && token_account.close_authority.is_none(),
+        DaoError::SwapRouteRejected
+    );
+    Ok(token_account.amount)
+}
+
+// Supply of an SPL mint
+#[cfg(feature = "token-voting")]
+fn spl_mint_supply(mint_info: &AccountInfo) -> Result<u64> {
+    require_keys_eq!(
+        *mint_info.owner,
+        anchor_spl::token::ID,
+        DaoError::InvalidQuorumSupply
+    );
+    let mint = anchor_spl::token::Mint::try_deserialize(&mut &mint_info.try_borrow_data()?[..])
+        .map_err(|_| DaoError::InvalidQuorumSupply)?;
+    Ok(mint.supply)
+}
+
+#[cfg(not(feature = "token-voting"))]
+fn spl_mint_supply(_mint_info: &AccountInfo) -> Result<u64> {
+    err!(DaoError::FeatureDisabled)
+}
+
+// Owner and balance of a token account of any mint
+#[cfg(all(
+    any(feature = "shares", feature = "treasury"),
+    feature = "token-voting"
+))]
+fn spl_token_holding(token_account_info: &AccountInfo) -> Result<(Pubkey, u64)> {
+    require_keys_eq!(
+        *token_account_info.owner,
+        anchor_spl::token::ID,
+        DaoError::InvalidTokenAccount
+    );
+    let token_account = anchor_spl::token::TokenAccount::try_deserialize(
+        &mut &token_account_info.try_borrow_data()?[..],
+    )
+    .map_err(|_| DaoError::InvalidTokenAccount)?;
+    Ok((token_account.owner, token_account.amount))
+}
+
+#[cfg(all(
+    any(feature = "shares", feature = "treasury"),
+    not(feature = "token-voting")
+))]
+fn spl_token_holding(_token_account_info: &AccountInfo) -> Result<(Pubkey, u64)> {
+    err!(DaoError::FeatureDisabled)
+}
+
+#[cfg(not(feature = "token-voting"))]
+fn spl_token_balance(
+    _token_mint: Pubkey,
+    _token_account_info: &AccountInfo,
+) -> Result<(Pubkey, u64)> {
+    err!(DaoError::FeatureDisabled)
+}
+
+// Token transfer for reward pools and deposit registrars. Program-owned accounts sign
+// with `signer_seeds`; a user's own account is signed by the transaction, with no seeds.
+#[cfg(all(
+    any(
+        feature = "vote-rewards",
+        feature = "deposit-voting",
+        feature = "dues",
+        feature = "shares",
+        feature = "treasury",
+        feature = "token-distribution"
+    ),
+    feature = "token-voting"
+))]
+fn transfer_spl<'info>(
+    token_program: &AccountInfo<'info>,
+    from: &AccountInfo<'info>,
+    to: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+    amount: u64,
+) -> Result<()> {
+    require_keys_eq!(
+        token_program.key(),
+        anchor_spl::token::ID,
+        DaoError::InvalidTokenAccount
+    );
+    anchor_spl::token::transfer(
+        CpiContext::new_with_signer(
+            token_program.clone(),
+            anchor_spl::token::Transfer {
+                from: from.clone(),
+                to: to.clone(),
+                authority: authority.clone(),
+            },
+            signer_seeds,
+        ),
+        amount,
+    )
+}
+
+#[cfg(all(
+    any(
+        feature = "vote-rewards",
+        feature = "deposit-voting",
+        feature = "dues",
+        feature = "shares",
+        feature = "treasury"
+    ),
+    not(feature = "token-voting")
+))]
+fn transfer_spl<'info>(
+    _token_program: &AccountInfo<'info>,
+    _from: &AccountInfo<'info>,
+    _to: &AccountInfo<'info>,
+    _authority: &AccountInfo<'info>,
+    _signer_seeds: &[&[&[u8]]],
+    _amount: u64,
+) -> Result<()> {
+    err!(DaoError::FeatureDisabled)
+}
+
+// Close an emptied pool vault, returning its rent to `destination`
+#[cfg(all(feature = "vote-rewards", feature = "token-voting"))]
+fn close_spl_account<'info>(
+    token_program: &AccountInfo<'info>,
+    account: &AccountInfo<'info>,
+    destination: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    anchor_spl::token::close_account(CpiContext::new_with_signer(
+        token_program.clone(),
+        anchor_spl::token::CloseAccount {
+            account: account.clone(),
+            destination: destination.clone(),
+            authority: authority.clone(),
+        },
+        signer_seeds,
+    ))
+}
+
+#[cfg(all(feature = "vote-rewards", not(feature = "token-voting")))]
+fn close_spl_account<'info>(
+    _token_program: &AccountInfo<'info>,
+    _account: &AccountInfo<'info>,
+    _destination: &AccountInfo<'info>,
+    _authority: &AccountInfo<'info>,
+    _signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    err!(DaoError::FeatureDisabled)
+}
+
+// Proposals may only use a token mode this build can count votes for
+fn validate_token_mint(token_mint: Option<Pubkey>) -> Result<()> {
+    if let Some(token_mint) = token_mint {
+        require!(
+            cfg!(feature = "token-voting") || token_mint == native_mint(),
+            DaoError::FeatureDisabled
+        );
+    }
+    Ok(())
+}
+
+fn native_mint() -> Pubkey {
+    Pubkey::from_str("So11111111111111111111111111111111111111112").unwrap()
+}
+
+// The login challenge a wallet signs for `user_account` at `nonce`
+// Shared by both ways of linking: record `wallet` once, within MAX_LINKED_WALLETS
+fn add_linked_wallet(user_account: &mut UserAccount, wallet: Pubkey) -> Result<()> {
+    require!(
+        wallet != user_account.wallet_pubkey && !user_account.linked_wallets.contains(&wallet),
+        DaoError::WalletAlreadyLinked
+    );
+    require!(
+        user_account.linked_wallets.len() < MAX_LINKED_WALLETS,
+        DaoError::TooManyLinkedWallets
+    );
+    user_account.linked_wallets.push(wallet);
+
+    emit!(WalletLinkedEvent {
+        telegram_id: user_account.telegram_id,
+        wallet,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+fn login_message(user_account: &Pubkey, nonce: u64) -> Vec<u8> {
+    let mut message = Vec::with_capacity(LOGIN_MESSAGE_PREFIX.len() + 40);
+    message.extend_from_slice(LOGIN_MESSAGE_PREFIX);
+    message.extend_from_slice(user_account.as_ref());
+    message.extend_from_slice(&nonce.to_le_bytes());
+    message
+}
+
+fn link_wallet_message(user_account: &Pubkey, nonce: u64) -> Vec<u8> {
+    format!("{}{}:{}", LINK_WALLET_MESSAGE_PREFIX, user_account, nonce).into_bytes()
+}
+
+// The payload a voter signs to have `choice` on `proposal` relayed until `expiry`
+fn relayed_vote_message(proposal: &Pubkey, choice: u8, expiry: i64) -> Vec<u8> {
+    let mut message = Vec::with_capacity(RELAYED_VOTE_MESSAGE_PREFIX.len() + 41);
+    message.extend_from_slice(RELAYED_VOTE_MESSAGE_PREFIX);
+    message.extend_from_slice(proposal.as_ref());
+    message.push(choice);
+    message.extend_from_slice(&expiry.to_le_bytes());
+    message
+}
+
+#[cfg(feature = "offchain-votes")]
+fn offchain_ballot_message(proposal: &Pubkey, choice: u8) -> Vec<u8> {
+    let mut message = Vec::with_capacity(OFFCHAIN_BALLOT_MESSAGE_PREFIX.len() + 33);
+    message.extend_from_slice(OFFCHAIN_BALLOT_MESSAGE_PREFIX);
+    message.extend_from_slice(proposal.as_ref());
+    message.push(choice);
+    message
+}
+
+// Merkle leaf for a counted off-chain ballot. Leaves and inner nodes are hashed with
+// different prefixes so a node can't pass for a leaf.
+#[cfg(feature = "offchain-votes")]
+fn offchain_ballot_leaf(voter: &Pubkey, choice: u8, vote_weight: u64) -> [u8; 32] {
+    use anchor_lang::solana_program::hash::hashv;
+
+    hashv(&[&[0], voter.as_ref(), &[choice], &vote_weight.to_le_bytes()]).to_bytes()
+}
+
+// Inner node of an off-chain tally's Merkle sum tree, committing to both children and
+// their sums. Children are ordered by hash, then sums, so proofs carry no left/right
+// bits.
+#[cfg(feature = "offchain-votes")]
+fn offchain_tally_node(a: &TallySubtree, b: &TallySubtree) -> [u8; 32] {
+    use anchor_lang::solana_program::hash::hashv;
+
+    let (a_sums, b_sums) = (a.sums.to_bytes(), b.sums.to_bytes());
+    let ((first, first_sums), (second, second_sums)) = if (a.hash, &a_sums) <= (b.hash, &b_sums) {
+        ((a.hash, a_sums), (b.hash, b_sums))
+    } else {
+        ((b.hash, b_sums), (a.hash, a_sums))
+    };
+    hashv(&[&[1], &first, &first_sums, &second, &second_sums]).to_bytes()
+}
+
+// Fold `node` up through its siblings to the tally root, adding up sums on the way;
+// None if a sibling's sums don't fit the proposal or overflow
+#[cfg(feature = "offchain-votes")]
+fn offchain_tally_root(node: TallySubtree, proof: &[TallySubtree]) -> Option<TallySubtree> {
+    let choice_count = node.sums.choice_votes.len();
+    proof.iter().try_fold(node, |node, sibling| {
+        Some(TallySubtree {
+            hash: offchain_tally_node(&node, sibling),
+            sums: node.sums.checked_add(&sibling.sums, choice_count)?,
+        })
+    })
+}
+
+// Merkle leaf for a recipient's allocation in a token distribution
+#[cfg(feature = "token-distribution")]
+fn distribution_leaf(recipient: &Pubkey, amount: u64) -> [u8; 32] {
+    use anchor_lang::solana_program::hash::hashv;
+
+    hashv(&[&[0], recipient.as_ref(), &amount.to_le_bytes()]).to_bytes()
+}
+
+// Root reached from `leaf` through `proof`, hashing each pair in sorted order so proofs
+// don't need to record left or right
+#[cfg(feature = "token-distribution")]
+fn merkle_root_from_proof(leaf: [u8; 32], proof: &[[u8; 32]]) -> [u8; 32] {
+    use anchor_lang::solana_program::hash::hashv;
+
+    proof.iter().fold(leaf, |node, sibling| {
+        let (first, second) = if node <= *sibling {
+            (node, *sibling)
+        } else {
+            (*sibling, node)
+        };
+        hashv(&[&[1], &first, &second]).to_bytes()
+    })
+}
+
+// Public key that signed `message` in the ed25519 precompile instruction immediately
+// before the current one. The runtime has already verified that signature; this only
+// checks that it covers our message. Key, signature and message must all live in the
+// precompile instruction itself, or its offsets could point at unrelated data. Any
+// mismatch fails with `error`.
+fn verified_ed25519_signer(
+    instructions_sysvar: &AccountInfo,
+    message: &[u8],
+    error: DaoError,
+) -> Result<Pubkey> {
+    use anchor_lang::solana_program::sysvar::instructions::{
+        load_current_index_checked, load_instruction_at_checked,
+    };
+
+    let current = load_current_index_checked(instructions_sysvar)?;
+    if current == 0 {
+        return Err(error.into());
+    }
+    let instruction = load_instruction_at_checked(current as usize - 1, instructions_sysvar)?;
+    require_keys_eq!(
+        instruction.program_id,
+        anchor_lang::solana_program::ed25519_program::ID,
+        error
+    );
+
+    // num_signatures, padding, then one 14-byte offsets record
+    let data = &instruction.data;
+    if data.len() < 16 || data[0] != 1 {
+        return Err(error.into());
+    }
+    let read_u16 = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+    let signature_ix = read_u16(4);
+    let pubkey_offset = read_u16(6) as usize;
+    let pubkey_ix = read_u16(8);
+    let message_offset = read_u16(10) as usize;
+    let message_size = read_u16(12) as usize;
+    let message_ix = read_u16(14);
+    if signature_ix != u16::MAX || pubkey_ix != u16::MAX || message_ix != u16::MAX {
+        return Err(error.into());
+    }
+
+    let signed = data
+        .get(message_offset..message_offset + message_size)
+        .ok_or(error)?;
+    if signed != message {
+        return Err(error.into());
+    }
+    let pubkey = data.get(pubkey_offset..pubkey_offset + 32).ok_or(error)?;
+    Ok(Pubkey::try_from(pubkey).map_err(|_| error)?)
+}
+
+// Create a program-owned PDA of `space` bytes with rent from `payer`, even if someone
+// pre-funded the address (which would make a plain create_account fail). `seeds`
+// include the bump.
+#[cfg(any(feature = "realms-compat", feature = "automation"))]
+fn create_pda_account<'info>(
+    account: &AccountInfo<'info>,
+    seeds: &[&[u8]],
+    space: usize,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+) -> Result<()> {
+    let rent = Rent::get()?
+        .minimum_balance(space)
+        .saturating_sub(account.lamports());
+    if rent > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.to_account_info(),
+                system_program::Transfer {
+                    from: payer.to_account_info(),
+                    to: account.clone(),
+                },
+            ),
+            rent,
+        )?;
+    }
+    system_program::allocate(
+        CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            system_program::Allocate {
+                account_to_allocate: account.clone(),
+            },
+            &[seeds],
+        ),
+        space as u64,
+    )?;
+    system_program::assign(
+        CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            system_program::Assign {
+                account_to_assign: account.clone(),
+            },
+            &[seeds],
+        ),
+        &crate::ID,
+    )
+}
+
+// Store `data` in a raw program-owned PDA, creating it on first use and resizing it to
+// fit. `seeds` include the bump.
+#[cfg(feature = "realms-compat")]
+fn write_realms_account<'info>(
+    account: &UncheckedAccount<'info>,
+    seeds: &[&[u8]],
+    data: &[u8],
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+) -> Result<()> {
+    let info = account.to_account_info();
+    if info.owner != &crate::ID {
+        create_pda_account(&info, seeds, data.len(), payer, system_program)?;
+    } else if info.data_len() < data.len() {
+        grow_account(&info, data.len(), payer, system_program)?;
+    } else if info.data_len() > data.len() {
+        // The freed rent stays in the account
+        info.realloc(data.len(), false)?;
+    }
+
+    info.try_borrow_mut_data()?[..data.len()].copy_from_slice(data);
+    Ok(())
+}
+
+// Bring a versioned account up to `current` by applying `upgrade` one version at a
+// time to its serialized body (everything after the discriminator, starting with the
+// version byte), then write the result back in place.
+fn migrate_account<'info>(
+    accounts: &MigrateAccount<'info>,
+    discriminator: &[u8],
+    current: u8,
+    upgrade: fn(u8, &[u8]) -> Result<Vec<u8>>,
+) -> Result<()> {
+    let info = accounts.account.to_account_info();
+    let mut body = {
+        let data = info.try_borrow_data()?;
+        require!(
+            data.len() > discriminator.len() && data.starts_with(discriminator),
+            ErrorCode::AccountDiscriminatorMismatch
+        );
+        data[discriminator.len()..].to_vec()
+    };
+    let from_version = body[0];
+    require!(from_version <= current, DaoError::UnknownAccountVersion);
+    require!(from_version < current, DaoError::AccountUpToDate);
+
+    for version in from_version..current {
+        body = upgrade(version, &body)?;
+        require!(
+            body.first() == Some(&(version + 1)),
+            DaoError::UnknownAccountVersion
+        );
+    }
+    let new_len = discriminator.len() + body.len();
+    grow_account(&info, new_len, &accounts.payer, &accounts.system_program)?;
+    let mut data = info.try_borrow_mut_data()?;
+    data[discriminator.len()..new_len].copy_from_slice(&body);
+    // A shorter layout leaves zeroed slack behind, as accounts sized for their maximum do
+    data[new_len..].fill(0);
+
+    emit!(AccountMigratedEvent {
+        account: info.key(),
+        from_version,
+        to_version: current,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    Ok(())
 }
 
-#[account]
-pub struct UserAccount {
-    pub telegram_id: i64,
-    pub wallet_pubkey: Pubkey,
-    pub created_at: i64,
-    pub bump: u8,
+// Convert a Group body from `from` to the next version. Add an arm here, e.g.
+// `1 => ...` producing version 2, with each Group layout change.
+fn upgrade_group(from: u8, _body: &[u8]) -> Result<Vec<u8>> {
+    msg!("No upgrade from Group version {}", from);
+    err!(DaoError::UnknownAccountVersion)
 }
 
-// Helper Structs
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
-pub struct GroupInfo {
-    pub group_id: String,
-    pub authority: Pubkey,
-    pub pubkey: Pubkey,
+// As upgrade_group, for Proposal
+fn upgrade_proposal(from: u8, _body: &[u8]) -> Result<Vec<u8>> {
+    msg!("No upgrade from Proposal version {}", from);
+    err!(DaoError::UnknownAccountVersion)
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
-pub struct ProposalInfo {
-    pub proposal_id: String,
-    pub pubkey: Pubkey,
-    pub created_at: i64,
+// As upgrade_group, for UserAccount
+fn upgrade_user_account(from: u8, _body: &[u8]) -> Result<Vec<u8>> {
+    msg!("No upgrade from UserAccount version {}", from);
+    err!(DaoError::UnknownAccountVersion)
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
-pub struct GroupMember {
-    pub pubkey: Pubkey,
-    pub joined_at: i64,
+// Grow a program-owned account to `new_len`, topping up rent from `payer`
+fn grow_account<'info>(
+    account: &AccountInfo<'info>,
+    new_len: usize,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+) -> Result<()> {
+    if account.data_len() >= new_len {
+        return Ok(());
+    }
+    let shortfall = Rent::get()?
+        .minimum_balance(new_len)
+        .saturating_sub(account.lamports());
+    if shortfall > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.to_account_info(),
+                system_program::Transfer {
+                    from: payer.to_account_info(),
+                    to: account.clone(),
+                },
+            ),
+            shortfall,
+        )?;
+    }
+    account.realloc(new_len, false)?;
+    Ok(())
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
-pub struct VoterInfo {
-    pub voter: Pubkey,
-    pub choice: u8,
-    pub vote_weight: u64,
-    pub timestamp: i64,
+// Open a draft for voting. A window planned for the future is kept; one whose start has
+// already passed is moved to start now, keeping its length. `deposit` is the draft's
+// ProposalDeposit PDA, which must hold the group's required deposit if it has one.
+fn open_proposal(
+    proposal: &mut Proposal,
+    group: &mut Group,
+    deposit: &AccountInfo,
+    clock: &Clock,
+) -> Result<()> {
+    require!(
+        deposit_posted(deposit, group.proposal_deposit_lamports)?,
+        DaoError::ProposalDepositRequired
+    );
+    let now = clock.unix_timestamp;
+    match proposal.slot_window {
+        Some(window) if window.start_slot <= clock.slot => {
+            let end_slot = clock
+                .slot
+                .checked_add(window.end_slot - window.start_slot)
+                .ok_or(DaoError::InvalidVotingPeriod)?;
+            apply_slot_window(proposal, clock.slot, end_slot, clock);
+        }
+        Some(_) => {}
+        None if proposal.voting_start <= now => {
+            let duration = proposal.voting_end - proposal.voting_start;
+            proposal.voting_start = now;
+            proposal.voting_end = now
+                .checked_add(duration)
+                .ok_or(DaoError::InvalidVotingPeriod)?;
+        }
+        None => {}
+    }
+    group.open_active_proposal()?;
+    proposal.status = ProposalStatus::Active;
+
+    let event_seq = next_event_seq(&mut group.event_seq)?;
+    emit!(ProposalStatusChangedEvent {
+        group_id: proposal.group_id.clone(),
+        event_seq,
+        proposal_id: proposal.proposal_id.clone(),
+        status: proposal.status,
+        voting_start: proposal.voting_start,
+        voting_end: proposal.voting_end,
+        timestamp: now,
+    });
+    Ok(())
 }
 
-// Context Structs
-#[derive(Accounts)]
-pub struct Initialize<'info> {
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + 32 + 4 + (20 * (4 + 50 + 32 + 32)) + 1, // discriminator + authority + vec length + (max 20 groups * (4 + 50 char max group_id + 2 pubkeys)) + bump
-        seeds = [b"dao_registry"],
-        bump
-    )]
-    pub dao_registry: Account<'info, DaoRegistry>,
+// Keep a proposal's window in slots, refreshing the unix-time estimates alongside
+fn apply_slot_window(proposal: &mut Proposal, start_slot: u64, end_slot: u64, clock: &Clock) {
+    proposal.slot_window = Some(SlotWindow {
+        start_slot,
+        end_slot,
+    });
+    proposal.voting_start = estimated_timestamp_at(start_slot, clock);
+    proposal.voting_end = estimated_timestamp_at(end_slot, clock);
+}
 
-    #[account(mut)]
-    pub authority: Signer<'info>,
+// Unix time `slot` is expected at, from the current slot and its timestamp
+fn estimated_timestamp_at(slot: u64, clock: &Clock) -> i64 {
+    let slots_ahead = slot as i128 - clock.slot as i128;
+    let seconds_ahead = slots_ahead * ESTIMATED_MS_PER_SLOT as i128 / 1000;
+    (clock.unix_timestamp as i128 + seconds_ahead).clamp(i64::MIN as i128, i64::MAX as i128) as i64
+}
 
-    pub system_program: Program<'info, System>,
+// Slot expected at unix time `timestamp`; 0 for times before genesis
+fn estimated_slot_at(timestamp: i64, clock: &Clock) -> u64 {
+    let seconds_ahead = timestamp as i128 - clock.unix_timestamp as i128;
+    let slots_ahead = seconds_ahead * 1000 / ESTIMATED_MS_PER_SLOT as i128;
+    (clock.slot as i128 + slots_ahead).clamp(0, u64::MAX as i128) as u64
 }
 
-#[derive(Accounts)]
-#[instruction(group_id: String)]
-pub struct CreateGroup<'info> {
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + 4 + 50 + 4 + 100 + 4 + 500 + 32 + 4 + 4 + 8 + 1, // discriminator + string lengths + data + vecs + bump
-        seeds = [b"group", group_id.as_bytes()],
-        bump
-    )]
-    pub group: Account<'info, Group>,
+// Cast weight and voting supply for the group's weight quorum. SPL token proposals
+// measure against the mint's supply and SOL deposit-weighted ones against the SOL
+// registrar's deposits, read from `quorum_supply`. Other proposals have no fixed supply,
+// so their ballots and the member count stand in.
+fn weight_turnout(
+    proposal: &Proposal,
+    group: &Group,
+    voter_count: u32,
+    quorum_supply: Option<&AccountInfo>,
+) -> Result<(u64, u64)> {
+    if group.governance.weight_quorum_bps == 0 {
+        return Ok((0, 0));
+    }
+    let cast_weight = proposal
+        .choice_votes
+        .iter()
+        .fold(0u64, |total, &votes| total.saturating_add(votes));
+    let native = proposal.token_mint == Some(native_mint());
+    match (proposal.token_mint, proposal.deposit_voting) {
+        (Some(mint), _) if !native => {
+            let supply = quorum_supply.ok_or(DaoError::InvalidQuorumSupply)?;
+            require_keys_eq!(supply.key(), mint, DaoError::InvalidQuorumSupply);
+            Ok((cast_weight, spl_mint_supply(supply)?))
+        }
+        (Some(_), Some(deposit_voting)) => {
+            // SOL registrars hold their deposits themselves
+            let supply = quorum_supply.ok_or(DaoError::InvalidQuorumSupply)?;
+            require_keys_eq!(
+                supply.key(),
+                deposit_voting.registrar,
+                DaoError::InvalidQuorumSupply
+            );
+            let rent = Rent::get()?.minimum_balance(8 + DepositRegistrar::INIT_SPACE);
+            Ok((cast_weight, supply.lamports().saturating_sub(rent)))
+        }
+        _ => Ok((voter_count as u64, group.member_count as u64)),
+    }
+}
 
-    #[account(mut)]
-    pub dao_registry: Account<'info, DaoRegistry>,
+// Settle a proposal whose voting has ended, pay its finalize bounty to `cranker` and
+// return the result certificate to store. The leading choice carries the proposal if
+// the group's quorum and approval threshold are met; otherwise, or with no votes, it is
+// defeated. A tie for first is broken by the group's tie-break policy: the earliest
+// leader, or `council_pick` under the Council policy; otherwise it defeats the proposal.
+fn seal_proposal<'info>(
+    proposal: &mut Account<'info, Proposal>,
+    group: &mut Account<'info, Group>,
+    cranker: &Signer<'info>,
+    quorum_supply: Option<&AccountInfo<'info>>,
+    result_bump: u8,
+    now: i64,
+    council_pick: Option<u8>,
+) -> Result<ProposalResult> {
+    let voter_count = proposal
+        .offchain_voter_count
+        .unwrap_or_else(|| proposal.voters.iter().filter(|v| v.vote_weight > 0).count() as u32);
+    let (cast_weight, weight_supply) = weight_turnout(proposal, group, voter_count, quorum_supply)?;
+    let leaders = leading_choices(&proposal.choice_votes);
+    proposal.tied = leaders.len() > 1;
+    let leader = match (leaders.as_slice(), group.tie_break) {
+        ([winner], _) => Some(*winner),
+        ([], _) => None,
+        (_, TieBreak::EarliestLeading) => Some(earliest_leader(proposal, &leaders)),
+        (_, TieBreak::Council) => council_pick,
+        (_, TieBreak::Fail | TieBreak::Revote { .. }) => None,
+    };
+    let winning_choice = leader.filter(|&winner| {
+        group.governance.carries(
+            &proposal.choice_votes,
+            winner,
+            voter_count,
+            group.member_count,
+            cast_weight,
+            weight_supply,
+            proposal.emergency,
+        ) && proposal
+            .council_chamber
+            .as_ref()
+            .is_none_or(|chamber| chamber.choice_votes[winner as usize] >= chamber.threshold as u32)
+    });
+    proposal.winner = winning_choice;
+    proposal.status = if winning_choice.is_some() {
+        ProposalStatus::Succeeded
+    } else {
+        ProposalStatus::Defeated
+    };
+    group.close_active_proposal();
+    let bounty = proposal.finalize_bounty;
+    proposal.finalize_bounty = 0;
+    if bounty > 0 {
+        // The proposal PDA is program-owned, so the bounty can be moved out directly
+        **proposal.to_account_info().try_borrow_mut_lamports()? -= bounty;
+        **cranker.to_account_info().try_borrow_mut_lamports()? += bounty;
+    }
 
-    #[account(mut)]
-    pub authority: Signer<'info>,
+    let event_seq = next_event_seq(&mut group.event_seq)?;
+    emit!(ProposalFinalizedEvent {
+        group_id: proposal.group_id.clone(),
+        event_seq,
+        proposal_id: proposal.proposal_id.clone(),
+        choice_votes: proposal.choice_votes.clone(),
+        winning_choice,
+        status: proposal.status,
+        voter_count,
+        member_count: group.member_count,
+        cast_weight,
+        weight_supply,
+        finalized_by: cranker.key(),
+        bounty,
+        timestamp: now,
+    });
 
-    pub system_program: Program<'info, System>,
+    // Compact copy of the outcome that outlives the proposal account
+    Ok(ProposalResult {
+        proposal: proposal.key(),
+        group: group.key(),
+        proposal_id: proposal.proposal_id.clone(),
+        status: proposal.status,
+        winning_choice,
+        choice_votes: proposal.choice_votes.clone(),
+        voter_count,
+        member_count: group.member_count,
+        finalized_at: now,
+        bump: result_bump,
+    })
 }
 
-#[derive(Accounts)]
-#[instruction(proposal_id: String)]
-pub struct CreateProposal<'info> {
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + 4 + 50 + 4 + 50 + 4 + 200 + 4 + 1000 + 4 + 4 + 8 + 8 + 33 + 32 + 4 + 8 + 1, // discriminator + string lengths + data + vecs + bump
-        seeds = [b"proposal", &group.key().to_bytes()[..8], &proposal_id.as_bytes()[..8]],
-        bump
-    )]
-    pub proposal: Account<'info, Proposal>,
+// Draw a finalized proposal's lottery winners among its on-chain voters, seeded by the
+// newest slot hash. A slot leader can bias that hash, so this only suits prizes too
+// small to be worth withholding a block for. Everyone wins when fewer voted than the
+// lottery pays.
+fn draw_lottery<'info>(
+    lottery: &mut Account<'info, VoterLottery>,
+    proposal: &Account<'info, Proposal>,
+    slot_hashes: &AccountInfo<'info>,
+    group: &mut Account<'info, Group>,
+    now: i64,
+) -> Result<()> {
+    use anchor_lang::solana_program::hash::hashv;
 
-    #[account(
-        mut,
-        constraint = group.authority == authority.key() @ DaoError::Unauthorized
-    )]
-    pub group: Account<'info, Group>,
+    require!(!lottery.drawn, DaoError::LotteryAlreadyDrawn);
+    // SlotHashes is a u64 entry count followed by (slot, hash) entries, newest first
+    let data = slot_hashes.try_borrow_data()?;
+    let newest = data
+        .get(8..48)
+        .ok_or(DaoError::LotteryRandomnessUnavailable)?;
+    let slot = u64::from_le_bytes(newest[..8].try_into().unwrap());
+    let mut seed = hashv(&[&newest[8..], proposal.key().as_ref()]).to_bytes();
 
-    #[account(mut)]
-    pub authority: Signer<'info>,
+    let mut candidates: Vec<Pubkey> = proposal
+        .voters
+        .iter()
+        .filter(|v| v.vote_weight > 0)
+        .map(|v| v.voter)
+        .collect();
+    let mut winners = Vec::with_capacity(lottery.winner_count as usize);
+    while winners.len() < lottery.winner_count as usize && !candidates.is_empty() {
+        seed = hashv(&[&seed]).to_bytes();
+        let pick = u64::from_le_bytes(seed[..8].try_into().unwrap()) % candidates.len() as u64;
+        winners.push(candidates.swap_remove(pick as usize));
+    }
 
-    pub system_program: Program<'info, System>,
+    lottery.prize = match winners.len() {
+        0 => 0,
+        n => lottery.total_amount / n as u64,
+    };
+    lottery.winners = winners;
+    lottery.claim_deadline = proposal
+        .voting_end
+        .saturating_add(STALE_PROPOSAL_GRACE_SECS);
+    lottery.drawn = true;
+
+    let event_seq = next_event_seq(&mut group.event_seq)?;
+    emit!(VoterLotteryDrawnEvent {
+        group_id: proposal.group_id.clone(),
+        event_seq,
+        proposal_id: proposal.proposal_id.clone(),
+        winners: lottery.winners.clone(),
+        prize: lottery.prize,
+        slot,
+        timestamp: now,
+    });
+
+    Ok(())
 }
 
-#[derive(Accounts)]
-pub struct VoteOnProposal<'info> {
-    #[account(mut)]
-    pub proposal: Account<'info, Proposal>,
+// Content rules shared by proposals and the templates they may be spawned from
+fn validate_proposal_content(
+    title: &str,
+    description: &str,
+    choices: &[String],
+    max_choices: u8,
+    metadata_uri: &Option<String>,
+    token_mint: Option<Pubkey>,
+    kind: &ProposalKind,
+) -> Result<()> {
+    require!(title.len() <= 200, DaoError::TitleTooLong);
+    require!(description.len() <= 1000, DaoError::DescriptionTooLong);
+    validate_metadata_uri(metadata_uri)?;
+    validate_token_mint(token_mint)?;
+    require!(
+        choices.len() >= 2 && choices.len() <= MAX_CHOICES.min(max_choices as usize),
+        DaoError::InvalidChoiceCount
+    );
+    require!(
+        choices.iter().all(|c| c.len() <= MAX_CHOICE_LEN),
+        DaoError::ChoiceTooLong
+    );
+    validate_proposal_kind(kind, choices.len())
+}
 
-    #[account(mut)]
-    pub voter: Signer<'info>,
+// A proposal as create_proposal or instantiate_from_template opens it
+struct NewProposal {
+    proposal_id: String,
+    title: String,
+    description: String,
+    choices: Vec<String>,
+    voting_start: i64,
+    voting_end: i64,
+    token_mint: Option<Pubkey>,
+    metadata_uri: Option<String>,
+    draft: bool,
+    kind: ProposalKind,
+    emergency: bool,
+    member_weighted: bool,
+    split_voting: bool,
+}
 
-    /// CHECK: This account is only used for SPL token voting, not for SOL voting
-    pub voter_token_account: Option<AccountInfo<'info>>,
+// Check the voting window against the group's rules, then write a new proposal and its
+// index entry, charge the group's creation fee and announce it. The content must
+// already have passed validate_proposal_content.
+fn record_new_proposal<'info>(
+    new: NewProposal,
+    proposal: &mut Account<'info, Proposal>,
+    proposal_bump: u8,
+    proposal_index: &mut Account<'info, ProposalIndex>,
+    index_bump: u8,
+    group: &mut Account<'info, Group>,
+    creator: &Signer<'info>,
+    participation: &mut Account<'info, Participation>,
+    participation_bump: u8,
+    treasury: &UncheckedAccount<'info>,
+    system_program: &Program<'info, System>,
+) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    require!(new.proposal_id.len() <= 50, DaoError::ProposalIdTooLong);
+    require!(
+        new.voting_start < new.voting_end,
+        DaoError::InvalidVotingPeriod
+    );
+    require!(
+        group
+            .governance
+            .allows_duration(new.voting_end - new.voting_start, new.emergency),
+        DaoError::VotingDurationOutOfBounds
+    );
+    // A draft's window is only a plan until activate_proposal, which may shift it
+    require!(
+        new.draft || new.voting_start > now,
+        DaoError::VotingStartInPast
+    );
+    require!(
+        !matches!(new.kind, ProposalKind::TreasuryRecovery(_)) || group.members_only_voting,
+        DaoError::RecoveryNeedsMembersOnlyVoting
+    );
+    let sponsors_needed = if new.emergency {
+        0
+    } else {
+        group.sponsor_threshold
+    };
+    require!(
+        new.draft || sponsors_needed == 0,
+        DaoError::SponsorshipRequired
+    );
+    require!(
+        new.draft || group.proposal_deposit_lamports == 0,
+        DaoError::ProposalDepositRequired
+    );
 
-    /// CHECK: This account is only used for SPL token voting, not for SOL voting  
-    pub token_program: Option<AccountInfo<'info>>,
+    proposal.version = PROPOSAL_VERSION;
+    proposal.proposal_id = new.proposal_id.clone();
+    proposal.group_id = group.group_id.clone();
+    proposal.title = new.title;
+    proposal.description = new.description;
+    proposal.choice_votes = vec![0u64; new.choices.len()];
+    proposal.shadow_votes = vec![0u32; new.choices.len()];
+    proposal.choices = new.choices;
+    proposal.voting_start = new.voting_start;
+    proposal.voting_end = new.voting_end;
+    proposal.token_mint = new.token_mint;
+    proposal.creator = creator.key();
+    proposal.voters = Vec::new();
+    proposal.created_at = now;
+    proposal.index = group.proposal_count;
+    proposal.metadata_uri = new.metadata_uri.clone();
+    proposal.status = if new.draft {
+        ProposalStatus::Draft
+    } else {
+        group.open_active_proposal()?;
+        ProposalStatus::Active
+    };
+    proposal.finalize_bounty = 0;
+    proposal.offchain_aggregator = None;
+    proposal.offchain_voter_count = None;
+    proposal.price_weighted = false;
+    proposal.member_weighted = new.member_weighted;
+    proposal.kind = new.kind;
+    proposal.emergency = new.emergency;
+    proposal.split_voting = new.split_voting;
+    proposal.slot_window = None;
+    proposal.weight_decay_bps = 0;
+    proposal.deposit_voting = None;
+    proposal.council_chamber = None;
+    proposal.winner = None;
+    proposal.tied = false;
+    proposal.sponsors_needed = sponsors_needed;
+    proposal.sponsor_count = 0;
+    proposal.bump = proposal_bump;
+
+    // Record the proposal in its own index PDA instead of growing the group account
+    proposal_index.group = group.key();
+    proposal_index.index = group.proposal_count;
+    proposal_index.proposal_id = new.proposal_id.clone();
+    proposal_index.pubkey = proposal.key();
+    proposal_index.created_at = now;
+    proposal_index.large = false;
+    proposal_index.bump = index_bump;
+
+    group.proposal_count = group
+        .proposal_count
+        .checked_add(1)
+        .ok_or(DaoError::ProposalCountOverflow)?;
+
+    let creation_fee = group.proposal_fee_lamports;
+    pay_proposal_fee(creation_fee, creator, treasury, system_program)?;
+
+    // Emergencies can't wait out the cooldown
+    let cooldown_secs = if new.emergency {
+        0
+    } else {
+        group.proposal_cooldown_secs
+    };
+    participation.record_proposal(
+        group.key(),
+        creator.key(),
+        participation_bump,
+        cooldown_secs,
+        now,
+    )?;
+
+    let event_seq = next_event_seq(&mut group.event_seq)?;
+    emit!(ProposalCreatedEvent {
+        group_id: group.group_id.clone(),
+        event_seq,
+        proposal_id: new.proposal_id,
+        creator: creator.key(),
+        proposal_pubkey: proposal.key(),
+        voting_start: new.voting_start,
+        voting_end: new.voting_end,
+        metadata_uri: new.metadata_uri,
+        draft: new.draft,
+        emergency: new.emergency,
+        creation_fee,
+        timestamp: now,
+    });
+
+    Ok(())
 }
 
-#[derive(Accounts)]
-pub struct AddGroupMember<'info> {
-    #[account(
-        mut,
-        constraint = group.authority == authority.key() @ DaoError::Unauthorized
-    )]
-    pub group: Account<'info, Group>,
+// Reject payloads their execute instruction couldn't carry out. Every choice of an
+// election names a candidate.
+fn validate_proposal_kind(kind: &ProposalKind, choice_count: usize) -> Result<()> {
+    let valid = match kind {
+        ProposalKind::Text => true,
+        ProposalKind::TreasuryTransfer(transfer) => {
+            require!(cfg!(feature = "treasury"), DaoError::FeatureDisabled);
+            transfer.lamports > 0 && transfer.recipient != Pubkey::default()
+        }
+        ProposalKind::ConfigChange(change) => match &change.governance {
+            Some(governance) => governance.is_valid(),
+            None => {
+                change.proposal_fee_lamports.is_some()
+                    || change.members_only_voting.is_some()
+                    || change.max_members.is_some()
+                    || change.visibility.is_some()
+            }
+        },
+        ProposalKind::MembershipChange(change) => change.member != Pubkey::default(),
+        ProposalKind::Grant(terms) => {
+            require!(cfg!(feature = "grants"), DaoError::FeatureDisabled);
+            terms.recipient != Pubkey::default()
+                && (1..=MAX_GRANT_MILESTONES).contains(&terms.milestones.len())
+                && terms.milestones.iter().all(|&amount| amount > 0)
+                && terms
+                    .milestones
+                    .iter()
+                    .try_fold(0u64, |total, &amount| total.checked_add(amount))
+                    .is_some()
+        }
+        ProposalKind::Stream(terms) => {
+            require!(cfg!(feature = "streams"), DaoError::FeatureDisabled);
+            terms.recipient != Pubkey::default()
+                && terms.amount > 0
+                && terms.start <= terms.cliff
+                && terms.cliff <= terms.end
+                && terms.start < terms.end
+        }
+        ProposalKind::Bounty(terms) => {
+            require!(cfg!(feature = "bounties"), DaoError::FeatureDisabled);
+            validate_bounty(terms).is_ok()
+        }
+        ProposalKind::TreasuryRecovery(recovery) => {
+            require!(cfg!(feature = "treasury"), DaoError::FeatureDisabled);
+            recovery.recovery != Pubkey::default()
+        }
+        ProposalKind::TreasuryStake(action) => {
+            require!(
+                cfg!(feature = "treasury-staking"),
+                DaoError::FeatureDisabled
+            );
+            match action {
+                StakeAction::Delegate {
+                    vote_account,
+                    lamports,
+                } => *vote_account != Pubkey::default() && *lamports > 0,
+                StakeAction::Deactivate { stake_account }
+                | StakeAction::Withdraw { stake_account } => *stake_account != Pubkey::default(),
+            }
+        }
+        ProposalKind::TokenDistribution(terms) => {
+            require!(
+                cfg!(feature = "token-distribution"),
+                DaoError::FeatureDisabled
+            );
+            terms.mint != Pubkey::default()
+                && terms.merkle_root != [0; 32]
+                && terms.total_amount > 0
+        }
+        ProposalKind::TreasurySwap(terms) => {
+            require!(cfg!(feature = "treasury-swaps"), DaoError::FeatureDisabled);
+            terms.swap_program != Pubkey::default()
+                && terms.swap_program != crate::ID
+                && terms.input_mint != terms.output_mint
+                && terms.amount_in > 0
+                && terms.min_amount_out > 0
+        }
+        ProposalKind::Election(election) => {
+            let seats = election.seats as usize;
+            election.candidates.len() == choice_count
+                && seats > 0
+                && seats <= choice_count.min(MAX_COUNCIL_MEMBERS)
+                && election.threshold > 0
+                && election.threshold <= election.seats
+                && election
+                    .candidates
+                    .iter()
+                    .enumerate()
+                    .all(|(i, candidate)| !election.candidates[..i].contains(candidate))
+        }
+    };
+    require!(valid, DaoError::InvalidProposalKind);
+    Ok(())
+}
 
-    #[account(mut)]
-    pub authority: Signer<'info>,
+// Treasury transfers, config and membership changes run only when the first choice
+// (e.g. "Yes") won
+fn require_approved(proposal: &Proposal) -> Result<()> {
+    require!(
+        proposal.status == ProposalStatus::Succeeded,
+        DaoError::InvalidProposalStatus
+    );
+    require!(proposal.winner == Some(0), DaoError::ProposalNotApproved);
+    Ok(())
 }
 
-#[derive(Accounts)]
-pub struct RemoveGroupMember<'info> {
-    #[account(
-        mut,
-        constraint = group.authority == authority.key() @ DaoError::Unauthorized
-    )]
-    pub group: Account<'info, Group>,
+// Whether a recovery proposal's first choice has the supermajority and the turnout;
+// groups without listed members can't recover
+#[cfg(feature = "treasury")]
+fn recovery_carries(choice_votes: &[u64], voter_count: u32, member_count: u32) -> bool {
+    let total: u128 = choice_votes.iter().map(|&v| v as u128).sum();
+    let approve = choice_votes.first().map_or(0, |&v| v as u128);
+    member_count > 0
+        && approve * 10_000 >= total * RECOVERY_SUPERMAJORITY_BPS as u128
+        && voter_count as u64 * 10_000 >= member_count as u64 * RECOVERY_MIN_TURNOUT_BPS
+}
 
-    #[account(mut)]
-    pub authority: Signer<'info>,
+// The `seats` candidates with the most votes, in order. The last seat must be won
+// outright, with at least one vote and more than the runner-up.
+fn election_winners(election: &Election, choice_votes: &[u64]) -> Result<Vec<Pubkey>> {
+    let seats = election.seats as usize;
+    let mut ranked: Vec<usize> = (0..election.candidates.len()).collect();
+    ranked.sort_by_key(|&i| std::cmp::Reverse(choice_votes[i]));
+
+    let last_seat = choice_votes[ranked[seats - 1]];
+    let runner_up = ranked.get(seats).map_or(0, |&i| choice_votes[i]);
+    require!(last_seat > runner_up, DaoError::ElectionTied);
+    Ok(ranked[..seats]
+        .iter()
+        .map(|&i| election.candidates[i])
+        .collect())
 }
 
-#[derive(Accounts)]
-#[instruction(telegram_id: i64)]
-pub struct CreateUserAccount<'info> {
-    #[account(
-        init,
-        payer = payer,
-        space = 8 + 8 + 32 + 8 + 1, // discriminator + telegram_id + wallet_pubkey + created_at + bump
-        seeds = [b"user_account", telegram_id.to_le_bytes().as_ref()],
-        bump
-    )]
-    pub user_account: Account<'info, UserAccount>,
+fn finish_execution(
+    proposal: &mut Proposal,
+    group: &mut Group,
+    executed_by: Pubkey,
+    transfers: Vec<ExecutedTransfer>,
+) -> Result<()> {
+    proposal.status = ProposalStatus::Executed;
 
-    /// CHECK: This is the wallet that will be associated with the user account
-    pub user_wallet: AccountInfo<'info>,
+    let event_seq = next_event_seq(&mut group.event_seq)?;
+    emit!(ProposalExecutedEvent {
+        group_id: proposal.group_id.clone(),
+        event_seq,
+        proposal_id: proposal.proposal_id.clone(),
+        kind: proposal.kind.clone(),
+        executed_by,
+        transfers,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    Ok(())
+}
 
-    #[account(mut)]
-    pub payer: Signer<'info>,
+// What finalization does with a tie for first before sealing anything
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TieHold {
+    None,            // seal now
+    Reopened,        // voting was reopened for the revote window
+    AwaitingCouncil, // the council must pick the winner with resolve_tie
+}
 
-    pub system_program: Program<'info, System>,
+// Under the Revote policy a first tie reopens voting for the window, once; under the
+// Council policy the proposal waits for resolve_tie. The first time either happens
+// marks the proposal tied and emits a TieBreakStartedEvent.
+fn hold_for_tie_break(
+    proposal: &mut Proposal,
+    group: &mut Group,
+    clock: &Clock,
+) -> Result<TieHold> {
+    if leading_choices(&proposal.choice_votes).len() < 2 {
+        return Ok(TieHold::None);
+    }
+    let hold = match group.tie_break {
+        TieBreak::Revote { .. } if proposal.tied => return Ok(TieHold::None),
+        TieBreak::Revote { window_secs } => {
+            proposal.voting_end = clock.unix_timestamp.saturating_add(window_secs);
+            if let Some(window) = proposal.slot_window.as_mut() {
+                window.end_slot = clock
+                    .slot
+                    .saturating_add(window_secs as u64 * 1000 / ESTIMATED_MS_PER_SLOT);
+            }
+            TieHold::Reopened
+        }
+        TieBreak::Council if proposal.tied => return Ok(TieHold::AwaitingCouncil),
+        TieBreak::Council => TieHold::AwaitingCouncil,
+        TieBreak::Fail | TieBreak::EarliestLeading => return Ok(TieHold::None),
+    };
+    proposal.tied = true;
+
+    let event_seq = next_event_seq(&mut group.event_seq)?;
+    emit!(TieBreakStartedEvent {
+        group_id: proposal.group_id.clone(),
+        event_seq,
+        proposal_id: proposal.proposal_id.clone(),
+        tied_choices: leading_choices(&proposal.choice_votes),
+        tie_break: group.tie_break,
+        voting_end: proposal.voting_end,
+        timestamp: clock.unix_timestamp,
+    });
+    Ok(hold)
 }
 
-#[derive(Accounts)]
-#[instruction(telegram_id: i64)]
-pub struct LoginUser<'info> {
-    #[account(
-        seeds = [b"user_account", telegram_id.to_le_bytes().as_ref()],
-        bump = user_account.bump
-    )]
-    pub user_account: Account<'info, UserAccount>,
+// Choices sharing the highest non-zero tally, in index order
+fn leading_choices(choice_votes: &[u64]) -> Vec<u8> {
+    let top = choice_votes.iter().copied().max().unwrap_or(0);
+    if top == 0 {
+        return Vec::new();
+    }
+    (0..choice_votes.len() as u8)
+        .filter(|&i| choice_votes[i as usize] == top)
+        .collect()
 }
 
-#[derive(Accounts)]
-pub struct GetAllGroups<'info> {
-    #[account(
-        seeds = [b"dao_registry"],
-        bump = dao_registry.bump
-    )]
-    pub dao_registry: Account<'info, DaoRegistry>,
+// Among tied `leaders`, the one that reached its final tally first: the one whose last
+// recorded ballot came earliest. Proposals without recorded ballots (off-chain tallies)
+// fall back to the first listed leader.
+fn earliest_leader(proposal: &Proposal, leaders: &[u8]) -> u8 {
+    let last_ballot = |choice: u8| {
+        proposal
+            .voters
+            .iter()
+            .rposition(|voter| voter.choice == choice && voter.vote_weight > 0)
+    };
+    *leaders
+        .iter()
+        .min_by_key(|&&choice| (last_ballot(choice), choice))
+        .unwrap_or(&leaders[0])
 }
 
-// Events
-#[event]
-pub struct GroupCreatedEvent {
-    pub group_id: String,
-    pub authority: Pubkey,
-    pub group_pubkey: Pubkey,
-    pub timestamp: i64,
+// Index of the choice with the most votes; None if nobody voted or the lead is tied
+#[cfg(feature = "realms-compat")]
+fn winning_choice(choice_votes: &[u64]) -> Option<u8> {
+    let top = *choice_votes.iter().max()?;
+    let mut leaders = choice_votes.iter().enumerate().filter(|(_, &v)| v == top);
+    match (leaders.next(), leaders.next()) {
+        (Some((index, _)), None) if top > 0 => Some(index as u8),
+        _ => None,
+    }
 }
 
-#[event]
-pub struct ProposalCreatedEvent {
-    pub group_id: String,
-    pub proposal_id: String,
-    pub creator: Pubkey,
-    pub proposal_pubkey: Pubkey,
-    pub voting_start: i64,
-    pub voting_end: i64,
-    pub timestamp: i64,
+// Check a split-weight ballot against the voter's weight and return the weight it
+// allocates. The named choice must get the most, so voter lists can show it.
+fn split_allocation_total(
+    allocations: &[u64],
+    choice_count: usize,
+    choice_index: u8,
+    vote_weight: u64,
+) -> Result<u64> {
+    let named = allocations.get(choice_index as usize).copied();
+    let total = allocations
+        .iter()
+        .try_fold(0u64, |total, &amount| total.checked_add(amount));
+    require!(
+        allocations.len() == choice_count
+            && named == allocations.iter().max().copied()
+            && total.is_some_and(|total| total > 0 && total <= vote_weight),
+        DaoError::InvalidSplitAllocation
+    );
+    Ok(total.unwrap_or_default())
 }
 
-#[event]
-pub struct VoteCastEvent {
-    pub group_id: String,
-    pub proposal_id: String,
-    pub voter: Pubkey,
-    pub choice: u8,
-    pub vote_weight: u64,
-    pub timestamp: i64,
+// Add a vote weight to a choice tally, failing instead of wrapping on overflow
+fn add_to_tally(tally: &mut u64, vote_weight: u64) -> Result<()> {
+    *tally = tally
+        .checked_add(vote_weight)
+        .ok_or(DaoError::TallyOverflow)?;
+    Ok(())
 }
 
-#[event]
-pub struct MemberAddedEvent {
-    pub group_id: String,
-    pub member: Pubkey,
-    pub timestamp: i64,
+#[cfg(feature = "slugs")]
+fn validate_slug(slug: &str) -> Result<()> {
+    require!(
+        (3..=32).contains(&slug.len())
+            && slug
+                .bytes()
+                .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+            && !slug.starts_with('-')
+            && !slug.ends_with('-'),
+        DaoError::InvalidSlug
+    );
+    Ok(())
 }
 
-#[event]
-pub struct MemberRemovedEvent {
-    pub group_id: String,
-    pub member: Pubkey,
-    pub timestamp: i64,
+// Disclosure notes are short free text, e.g. "I am the grant recipient"
+fn validate_disclosure(disclosure: &Option<String>) -> Result<()> {
+    if let Some(note) = disclosure {
+        require!(
+            note.len() <= MAX_DISCLOSURE_LEN && !note.chars().any(|c| c.is_control()),
+            DaoError::InvalidDisclosure
+        );
+    }
+    Ok(())
 }
 
-#[event]
-pub struct UserAccountCreatedEvent {
-    pub telegram_id: i64,
-    pub wallet_pubkey: Pubkey,
-    pub timestamp: i64,
+// Shared by post_bounty and bounty proposals
+fn validate_bounty(terms: &BountyTerms) -> Result<()> {
+    require!(
+        !terms.bounty_id.is_empty()
+            && terms.bounty_id.len() <= MAX_BOUNTY_ID_LEN
+            && terms.reward > 0,
+        DaoError::InvalidBounty
+    );
+    validate_metadata_uri(&Some(terms.description_uri.clone()))
 }
 
-#[event]
-pub struct UserLoginEvent {
-    pub telegram_id: i64,
-    pub wallet_pubkey: Pubkey,
-    pub timestamp: i64,
+// Fill a new bounty and announce it
+#[cfg(feature = "bounties")]
+fn open_bounty(
+    bounty: &mut Account<Bounty>,
+    bump: u8,
+    group: &mut Account<Group>,
+    terms: BountyTerms,
+    posted_by: Pubkey,
+) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    bounty.group = group.key();
+    bounty.bounty_id = terms.bounty_id.clone();
+    bounty.description_uri = terms.description_uri.clone();
+    bounty.reward = terms.reward;
+    bounty.status = BountyStatus::Open;
+    bounty.posted_by = posted_by;
+    bounty.hunter = None;
+    bounty.submission_uri = None;
+    bounty.created_at = now;
+    bounty.bump = bump;
+    group.commit_treasury(terms.reward)?;
+
+    let event_seq = next_event_seq(&mut group.event_seq)?;
+    emit!(BountyPostedEvent {
+        group_id: group.group_id.clone(),
+        event_seq,
+        bounty_id: terms.bounty_id,
+        description_uri: terms.description_uri,
+        reward: terms.reward,
+        posted_by,
+        timestamp: now,
+    });
+
+    Ok(())
 }
 
-// Error Codes
-#[error_code]
-pub enum DaoError {
-    #[msg("Group ID too long (max 50 characters)")]
-    GroupIdTooLong,
-    #[msg("Name too long (max 100 characters)")]
-    NameTooLong,
-    #[msg("Description too long (max 500 characters)")]
-    DescriptionTooLong,
-    #[msg("Proposal ID too long (max 50 characters)")]
-    ProposalIdTooLong,
-    #[msg("Title too long (max 200 characters)")]
-    TitleTooLong,
-    #[msg("Invalid choice count (must be between 2 and 10)")]
-    InvalidChoiceCount,
-    #[msg("Invalid voting period")]
-    InvalidVotingPeriod,
-    #[msg("Voting start time cannot be in the past")]
-    VotingStartInPast,
-    #[msg("Voting is not currently active")]
-    VotingNotActive,
-    #[msg("Invalid choice")]
-    InvalidChoice,
-    #[msg("User has already voted")]
-    AlreadyVoted,
-    #[msg("Token account is required for token-weighted voting")]
-    TokenAccountRequired,
-    #[msg("Invalid token mint")]
-    InvalidTokenMint,
-    #[msg("No voting power")]
-    NoVotingPower,
-    #[msg("Unauthorized")]
-    Unauthorized,
-    #[msg("Member already exists")]
-    MemberAlreadyExists,
-    #[msg("Member not found")]
-    MemberNotFound,
-    #[msg("Invalid Telegram ID")]
-    InvalidTelegramId,
+fn validate_metadata_uri(metadata_uri: &Option<String>) -> Result<()> {
+    if let Some(uri) = metadata_uri {
+        require!(
+            uri.len() <= MAX_METADATA_URI_LEN
+                && METADATA_URI_SCHEMES
+                    .iter()
+                    .any(|scheme| uri.len() > scheme.len() && uri.starts_with(scheme))
+                && !uri.chars().any(|c| c.is_whitespace() || c.is_control()),
+            DaoError::InvalidMetadataUri
+        );
+    }
+    Ok(())
 }
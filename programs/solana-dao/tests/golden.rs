@@ -0,0 +1,1876 @@
+// Golden byte-level fixtures for every account layout and instruction encoding.
+//
+// Each fixture is a deterministic sample value serialized exactly as it appears on
+// chain (discriminator included) and compared with the hex file checked in under
+// tests/golden/. Any layout change - a reordered field, a new field, a renamed
+// instruction - fails here first, so it can't silently break the bot or other clients
+// that decode these bytes by hand. After an intentional change, regenerate with
+//
+//     UPDATE_GOLDEN=1 cargo test -p solana-dao --test golden
+//
+// and review the diff of the .hex files alongside the code.
+
+use std::fs;
+use std::path::PathBuf;
+
+use ::solana_dao::{instruction as ix, *};
+use anchor_lang::prelude::{pubkey, Pubkey};
+use anchor_lang::{AccountSerialize, InstructionData};
+
+// Hex bytes per line in the fixture files, so diffs point at the changed region
+const BYTES_PER_LINE: usize = 32;
+// Wrapped SOL mint, marking SOL-weighted proposals
+const NATIVE_MINT: Pubkey = pubkey!("So11111111111111111111111111111111111111112");
+
+fn key(n: u8) -> Pubkey {
+    Pubkey::new_from_array([n; 32])
+}
+
+fn golden_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes
+        .chunks(BYTES_PER_LINE)
+        .map(|line| {
+            line.iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<String>()
+                + "\n"
+        })
+        .collect()
+}
+
+// Compare `bytes` with tests/golden/<name>.hex, or rewrite it when UPDATE_GOLDEN is set
+fn assert_golden(name: &str, bytes: &[u8]) {
+    let path = golden_dir().join(format!("{}.hex", name));
+    let actual = to_hex(bytes);
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::create_dir_all(golden_dir()).unwrap();
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+
+    let expected = fs::read_to_string(&path).unwrap_or_else(|_| {
+        panic!(
+            "missing golden file {}; run with UPDATE_GOLDEN=1 to create it",
+            path.display()
+        )
+    });
+    assert!(
+        expected == actual,
+        "{} no longer matches {}; if the layout change is intentional, \
+        rerun with UPDATE_GOLDEN=1 and review the diff",
+        name,
+        path.display()
+    );
+}
+
+fn assert_account<T: AccountSerialize>(name: &str, account: &T) {
+    let mut bytes = Vec::new();
+    account.try_serialize(&mut bytes).unwrap();
+    assert_golden(name, &bytes);
+}
+
+fn assert_instruction<T: InstructionData>(name: &str, instruction: T) {
+    assert_golden(&format!("ix_{}", name), &instruction.data());
+}
+
+fn sample_proposal() -> Proposal {
+    Proposal {
+        version: PROPOSAL_VERSION,
+        proposal_id: "golden-proposal".to_string(),
+        group_id: "tg_100200300".to_string(),
+        title: "Fund the community garden".to_string(),
+        description: "Allocate the Q3 budget to the garden project.".to_string(),
+        choices: vec!["Yes".to_string(), "No".to_string(), "Abstain".to_string()],
+        choice_votes: vec![3_000_000_000, 1_500_000_000, 0],
+        voting_start: 1_700_000_000,
+        voting_end: 1_700_086_400,
+        token_mint: Some(NATIVE_MINT),
+        creator: key(2),
+        voters: vec![
+            VoterInfo {
+                voter: key(3),
+                choice: 0,
+                vote_weight: 3_000_000_000,
+                timestamp: 1_700_000_100,
+                disclosure: Some("grant recipient".to_string()),
+            },
+            VoterInfo {
+                voter: key(4),
+                choice: 1,
+                vote_weight: 1_500_000_000,
+                timestamp: 1_700_000_200,
+                disclosure: None,
+            },
+            // Linked wallet counted into key(3)'s vote
+            VoterInfo {
+                voter: key(5),
+                choice: 0,
+                vote_weight: 0,
+                timestamp: 1_700_000_100,
+                disclosure: None,
+            },
+        ],
+        created_at: 1_699_999_000,
+        index: 7,
+        metadata_uri: Some("ipfs://bafygoldenproposal".to_string()),
+        status: ProposalStatus::Succeeded,
+        finalize_bounty: 5_000,
+        shadow_votes: vec![2, 0, 1],
+        offchain_aggregator: None,
+        offchain_voter_count: None,
+        price_weighted: false,
+        member_weighted: true,
+        kind: ProposalKind::TreasuryTransfer(TreasuryTransfer {
+            recipient: key(17),
+            lamports: 2_000_000_000,
+        }),
+        emergency: true,
+        split_voting: true,
+        slot_window: Some(SlotWindow {
+            start_slot: 250_000_000,
+            end_slot: 250_216_000,
+        }),
+        weight_decay_bps: 2_500,
+        deposit_voting: Some(DepositVoting {
+            registrar: key(18),
+            cutoff: 1_699_395_000,
+        }),
+        council_chamber: Some(CouncilChamber {
+            members: vec![key(19), key(20), key(21)],
+            threshold: 2,
+            choice_votes: vec![2, 1, 0],
+            voted: 0b111,
+        }),
+        winner: Some(0),
+        tied: false,
+        sponsors_needed: 3,
+        sponsor_count: 3,
+        bump: 254,
+    }
+}
+
+#[test]
+fn account_layouts_match_golden_files() {
+    assert_account(
+        "program_config",
+        &ProgramConfig {
+            paused: false,
+            guardian: Some(key(9)),
+            max_groups: DEFAULT_MAX_GROUPS,
+            max_choices: MAX_CHOICES as u8,
+            fee_destination: key(8),
+            updated_at: 1_700_000_000,
+            bump: 255,
+        },
+    );
+
+    assert_account(
+        "dao_registry",
+        &DaoRegistry {
+            authority: key(1),
+            name: String::new(),
+            groups: vec![GroupInfo {
+                group_id: "tg_100200300".to_string(),
+                authority: key(1),
+                pubkey: key(10),
+            }],
+            open_registration: true,
+            group_deposit_lamports: DEFAULT_GROUP_DEPOSIT_LAMPORTS,
+            group_deposit_lock_days: DEFAULT_GROUP_DEPOSIT_LOCK_DAYS,
+            event_seq: 42,
+            bump: 253,
+        },
+    );
+
+    assert_account(
+        "group",
+        &Group {
+            version: GROUP_VERSION,
+            group_id: "tg_100200300".to_string(),
+            name: "Garden Club".to_string(),
+            description: "Neighbourhood garden decisions".to_string(),
+            authority: key(1),
+            proposal_count: 8,
+            member_count: 2,
+            created_at: 1_689_000_000,
+            deposit_lamports: DEFAULT_GROUP_DEPOSIT_LAMPORTS,
+            deposit_unlock_at: 1_691_592_000,
+            slug: "garden-club".to_string(),
+            metadata_uri: Some("https://example.org/garden".to_string()),
+            event_seq: 17,
+            proposal_fee_lamports: 5_000_000,
+            members_only_voting: true,
+            max_members: 25,
+            visibility: GroupVisibility::Private,
+            governance: GovernanceConfig {
+                quorum_bps: 2_000,
+                approval_threshold_bps: 5_000,
+                min_voting_duration: 86_400,
+                max_voting_duration: 1_209_600,
+                weight_quorum_bps: 1_000,
+            },
+            dues: Some(DuesPolicy {
+                mint: NATIVE_MINT,
+                amount: 10_000_000,
+                period_secs: 30 * SECONDS_PER_DAY,
+                grace_secs: 7 * SECONDS_PER_DAY,
+                required_for_voting: true,
+            }),
+            stake_to_join: Some(StakeToJoin {
+                price_lamports: 100_000_000,
+                total_shares: 40,
+                unowned_shares: 0,
+            }),
+            max_active_proposals: 3,
+            active_proposals: 2,
+            proposal_cooldown_secs: 12 * 3_600,
+            external_chat_id: Some(-100_200_300),
+            tie_break: TieBreak::Revote {
+                window_secs: 86_400,
+            },
+            sponsor_threshold: 3,
+            registry: key(12),
+            proposal_deposit_lamports: 50_000_000,
+            committed_lamports: 2_000_000_000,
+            bump: 252,
+        },
+    );
+
+    assert_account("proposal", &sample_proposal());
+
+    assert_account(
+        "chat_link",
+        &ChatLink {
+            chat_id: -100_200_300,
+            group: key(10),
+            linked_at: 1_689_000_000,
+            bump: 217,
+        },
+    );
+
+    assert_account(
+        "council",
+        &Council {
+            group: key(10),
+            members: vec![key(6), key(7)],
+            threshold: 2,
+            bump: 237,
+        },
+    );
+
+    assert_account(
+        "participation",
+        &Participation {
+            group: key(10),
+            member: key(6),
+            proposals_created: 2,
+            proposals_voted: 9,
+            current_streak: 3,
+            longest_streak: 5,
+            last_voted_index: 7,
+            last_active_at: 1_700_000_300,
+            last_proposal_at: 1_699_999_000,
+            bump: 240,
+        },
+    );
+
+    assert_account(
+        "turnout_stats",
+        &TurnoutStats {
+            proposal: key(11),
+            unique_voters: 3,
+            total_weight: 4_000_000_000,
+            choice_bps: vec![7_500, 2_500, 0],
+            votes_per_day: vec![2, 0, 1],
+            bump: 207,
+        },
+    );
+
+    assert_account(
+        "proposal_index",
+        &ProposalIndex {
+            group: key(10),
+            index: 7,
+            proposal_id: "golden-proposal".to_string(),
+            pubkey: key(11),
+            created_at: 1_699_999_000,
+            large: false,
+            bump: 251,
+        },
+    );
+
+    assert_account(
+        "proposal_result",
+        &ProposalResult {
+            proposal: key(11),
+            group: key(10),
+            proposal_id: "golden-proposal".to_string(),
+            status: ProposalStatus::Succeeded,
+            winning_choice: Some(0),
+            choice_votes: vec![3_000_000_000, 1_500_000_000, 0],
+            voter_count: 2,
+            member_count: 2,
+            finalized_at: 1_700_086_500,
+            bump: 245,
+        },
+    );
+
+    assert_account(
+        "shadow_ballot",
+        &ShadowBallot {
+            proposal: key(11),
+            voter: key(6),
+            choice: 2,
+            timestamp: 1_700_000_300,
+            bump: 250,
+        },
+    );
+
+    assert_account(
+        "user_account",
+        &UserAccount {
+            version: USER_ACCOUNT_VERSION,
+            telegram_id: 123_456_789,
+            wallet_pubkey: key(3),
+            created_at: 1_680_000_000,
+            linked_wallets: vec![key(5)],
+            last_login: 1_700_000_050,
+            login_nonce: 3,
+            bump: 249,
+        },
+    );
+}
+
+#[cfg(feature = "large-proposals")]
+#[test]
+fn large_proposal_layouts_match_golden_files() {
+    use anchor_lang::Discriminator;
+
+    let mut large: ProposalLarge = bytemuck::Zeroable::zeroed();
+    large.group = key(10);
+    large.creator = key(2);
+    large.token_mint = NATIVE_MINT;
+    large.voting_start = 1_700_000_000;
+    large.voting_end = 1_700_086_400;
+    large.created_at = 1_699_999_000;
+    large.index = 8;
+    large.voter_count = 2;
+    large.choice_votes[0] = 3_000_000_000;
+    large.choice_votes[31] = 1_500_000_000;
+    let proposal_id = b"golden-large";
+    large.proposal_id[..proposal_id.len()].copy_from_slice(proposal_id);
+    large.proposal_id_len = proposal_id.len() as u8;
+    let title = b"Pick the garden mascot";
+    large.title[..title.len()].copy_from_slice(title);
+    large.title_len = title.len() as u16;
+    let description = b"Thirty-two candidates, one winner.";
+    large.description[..description.len()].copy_from_slice(description);
+    large.description_len = description.len() as u16;
+    for (i, choice) in large.choices.iter_mut().enumerate() {
+        let name = format!("Candidate {}", i);
+        choice[..name.len()].copy_from_slice(name.as_bytes());
+    }
+    large.choice_count = LARGE_MAX_CHOICES as u8;
+    large.has_token_mint = 1;
+    large.bump = 248;
+
+    let mut bytes = ProposalLarge::DISCRIMINATOR.to_vec();
+    bytes.extend_from_slice(bytemuck::bytes_of(&large));
+    assert_golden("proposal_large", &bytes);
+
+    assert_account(
+        "vote_record",
+        &VoteRecord {
+            proposal: key(12),
+            voter: key(3),
+            choice: 31,
+            vote_weight: 1_500_000_000,
+            timestamp: 1_700_000_400,
+            disclosure: Some(String::new()),
+            bump: 247,
+        },
+    );
+}
+
+#[cfg(feature = "slugs")]
+#[test]
+fn slug_layouts_match_golden_files() {
+    assert_account(
+        "slug_reservation",
+        &SlugReservation {
+            registry: key(13),
+            slug: "garden-club".to_string(),
+            owner: key(1),
+            group: Some(key(10)),
+            reserved_at: 1_689_000_100,
+            bump: 246,
+        },
+    );
+
+    assert_instruction(
+        "reserve_slug",
+        ix::ReserveSlug {
+            slug: "garden-club".to_string(),
+        },
+    );
+    assert_instruction("release_slug", ix::ReleaseSlug {});
+    assert_instruction(
+        "set_group_slug",
+        ix::SetGroupSlug {
+            new_slug: "garden-club".to_string(),
+        },
+    );
+}
+
+#[cfg(feature = "joint-proposals")]
+#[test]
+fn joint_proposal_layouts_match_golden_files() {
+    assert_account(
+        "joint_proposal",
+        &JointProposal {
+            joint_id: "garden-treasury-pact".to_string(),
+            creator: key(1),
+            participants: vec![
+                JointParticipant {
+                    group: key(10),
+                    proposal: key(11),
+                },
+                JointParticipant {
+                    group: key(20),
+                    proposal: key(21),
+                },
+            ],
+            required_passes: 0,
+            status: ProposalStatus::Succeeded,
+            created_at: 1_689_000_200,
+            resolved_at: 1_700_090_000,
+            bump: 243,
+        },
+    );
+
+    assert_instruction(
+        "create_joint_proposal",
+        ix::CreateJointProposal {
+            joint_id: "garden-treasury-pact".to_string(),
+            required_passes: 2,
+        },
+    );
+    assert_instruction("join_joint_proposal", ix::JoinJointProposal {});
+    assert_instruction("resolve_joint_proposal", ix::ResolveJointProposal {});
+}
+
+#[cfg(feature = "treasury")]
+#[test]
+fn program_governance_layouts_match_golden_files() {
+    assert_account(
+        "program_governance",
+        &ProgramGovernance {
+            group: key(10),
+            treasury: key(14),
+            timelock_secs: 3 * 24 * 60 * 60,
+            upgrade_count: 2,
+            bump: 242,
+        },
+    );
+    assert_account(
+        "upgrade_proposal",
+        &UpgradeProposal {
+            proposal: key(11),
+            buffer: key(15),
+            approve_choice: 0,
+            eta: 1_700_345_600,
+            executed_at: 0,
+            bump: 241,
+        },
+    );
+
+    assert_instruction(
+        "initialize_program_governance",
+        ix::InitializeProgramGovernance {
+            timelock_secs: 3 * 24 * 60 * 60,
+        },
+    );
+    assert_instruction(
+        "propose_program_upgrade",
+        ix::ProposeProgramUpgrade { approve_choice: 0 },
+    );
+    assert_instruction("queue_program_upgrade", ix::QueueProgramUpgrade {});
+    assert_instruction("execute_program_upgrade", ix::ExecuteProgramUpgrade {});
+}
+
+#[cfg(feature = "treasury")]
+#[test]
+fn proposal_deposit_layouts_match_golden_files() {
+    assert_account(
+        "proposal_deposit",
+        &ProposalDeposit {
+            proposal: key(11),
+            group: key(10),
+            depositor: key(4),
+            amount: 50_000_000,
+            spam_choice: 2,
+            flags: vec![key(6)],
+            flagged: false,
+            bump: 236,
+        },
+    );
+
+    assert_instruction(
+        "post_proposal_deposit",
+        ix::PostProposalDeposit { amount: 50_000_000 },
+    );
+    assert_instruction(
+        "set_proposal_deposit",
+        ix::SetProposalDeposit {
+            lamports: 50_000_000,
+        },
+    );
+    assert_instruction(
+        "set_proposal_fee",
+        ix::SetProposalFee {
+            fee_lamports: 5_000_000,
+        },
+    );
+    assert_instruction("flag_as_spam", ix::FlagAsSpam {});
+    assert_instruction("settle_proposal_deposit", ix::SettleProposalDeposit {});
+}
+
+#[cfg(feature = "vote-rewards")]
+#[test]
+fn vote_reward_layouts_match_golden_files() {
+    assert_account(
+        "reward_pool",
+        &RewardPool {
+            group: key(10),
+            proposal: key(11),
+            funder: key(4),
+            token_mint: NATIVE_MINT,
+            vault: key(16),
+            total_amount: 3_000_000_000,
+            claimed_amount: 1_000_000_000,
+            claim_count: 1,
+            bump: 239,
+        },
+    );
+    assert_account(
+        "reward_claim",
+        &RewardClaim {
+            pool: key(16),
+            voter: key(6),
+            amount: 1_000_000_000,
+            claimed_at: 1_700_090_000,
+            bump: 238,
+        },
+    );
+
+    assert_instruction(
+        "fund_vote_rewards",
+        ix::FundVoteRewards {
+            token_mint: NATIVE_MINT,
+            amount: 3_000_000_000,
+        },
+    );
+    assert_instruction("claim_vote_reward", ix::ClaimVoteReward {});
+    assert_instruction("reclaim_vote_rewards", ix::ReclaimVoteRewards {});
+}
+
+#[cfg(feature = "offchain-votes")]
+#[test]
+fn offchain_vote_layouts_match_golden_files() {
+    assert_account(
+        "offchain_tally",
+        &OffchainTally {
+            proposal: key(11),
+            aggregator: key(17),
+            merkle_root: [18; 32],
+            choice_votes: vec![40, 25, 3],
+            voter_count: 68,
+            submitted_at: 1_700_090_000,
+            challenge_end: 1_700_262_800,
+            disputed_by: Some(key(6)),
+            bump: 235,
+        },
+    );
+
+    assert_instruction(
+        "enable_offchain_voting",
+        ix::EnableOffchainVoting {
+            aggregator: key(17),
+        },
+    );
+    assert_instruction(
+        "submit_offchain_tally",
+        ix::SubmitOffchainTally {
+            merkle_root: [18; 32],
+            choice_votes: vec![40, 25, 3],
+            voter_count: 68,
+        },
+    );
+    assert_instruction(
+        "dispute_offchain_tally",
+        ix::DisputeOffchainTally {
+            voter: key(6),
+            tallied_choice: 0,
+            vote_weight: 1,
+            proof: vec![TallySubtree {
+                hash: [19; 32],
+                sums: TallySums {
+                    choice_votes: vec![0, 25, 3],
+                    voter_count: 20,
+                },
+            }],
+            ballot_choice: 1,
+        },
+    );
+    assert_instruction(
+        "dispute_offchain_total",
+        ix::DisputeOffchainTotal {
+            node: TallyNode::Inner {
+                left: TallySubtree {
+                    hash: [19; 32],
+                    sums: TallySums {
+                        choice_votes: vec![40, 0, 0],
+                        voter_count: 48,
+                    },
+                },
+                right: TallySubtree {
+                    hash: [20; 32],
+                    sums: TallySums {
+                        choice_votes: vec![0, 25, 3],
+                        voter_count: 20,
+                    },
+                },
+            },
+            claimed: TallySums {
+                choice_votes: vec![40, 25, 3],
+                voter_count: 68,
+            },
+            proof: Vec::new(),
+        },
+    );
+    assert_instruction("settle_offchain_tally", ix::SettleOffchainTally {});
+}
+
+// spl-governance layouts have no Anchor discriminator, so these are plain borsh
+#[cfg(feature = "realms-compat")]
+#[test]
+fn realms_layouts_match_golden_files() {
+    use anchor_lang::AnchorSerialize;
+
+    let proposal = sample_proposal();
+    let realms_proposal = realms::ProposalV2::from_proposal(&proposal, key(10), key(13));
+    assert_golden("realms_proposal_v2", &realms_proposal.try_to_vec().unwrap());
+    let vote_record =
+        realms::VoteRecordV2::from_ballot(key(14), &proposal.voters[0], proposal.choices.len());
+    assert_golden("realms_vote_record_v2", &vote_record.try_to_vec().unwrap());
+
+    assert_instruction("sync_realms_proposal", ix::SyncRealmsProposal {});
+    assert_instruction(
+        "sync_realms_vote_record",
+        ix::SyncRealmsVoteRecord { voter: key(6) },
+    );
+}
+
+#[cfg(feature = "oracle-weights")]
+#[test]
+fn price_weighting_layouts_match_golden_files() {
+    assert_account(
+        "price_weighting",
+        &PriceWeighting {
+            proposal: key(10),
+            feeds: vec![
+                PricedMint {
+                    mint: key(8),
+                    oracle: key(11),
+                    decimals: 9,
+                },
+                PricedMint {
+                    mint: key(9),
+                    oracle: key(12),
+                    decimals: 6,
+                },
+            ],
+            max_staleness_secs: 60,
+            bump: 233,
+        },
+    );
+
+    assert_instruction(
+        "set_price_weighting",
+        ix::SetPriceWeighting {
+            feeds: vec![PriceFeed {
+                mint: key(8),
+                oracle: key(11),
+            }],
+            max_staleness_secs: 60,
+        },
+    );
+}
+
+#[test]
+fn proposal_kind_layouts_match_golden_files() {
+    assert_account(
+        "proposal_election",
+        &Proposal {
+            kind: ProposalKind::Election(Election {
+                candidates: vec![key(3), key(4), key(5)],
+                seats: 2,
+                threshold: 1,
+            }),
+            ..sample_proposal()
+        },
+    );
+    assert_account(
+        "proposal_config_change",
+        &Proposal {
+            kind: ProposalKind::ConfigChange(ConfigChange {
+                proposal_fee_lamports: None,
+                members_only_voting: Some(true),
+                max_members: Some(25),
+                visibility: None,
+                governance: Some(GovernanceConfig {
+                    quorum_bps: 3_000,
+                    approval_threshold_bps: 6_667,
+                    min_voting_duration: 3_600,
+                    max_voting_duration: 0,
+                    weight_quorum_bps: 0,
+                }),
+            }),
+            ..sample_proposal()
+        },
+    );
+    assert_account(
+        "proposal_membership_change",
+        &Proposal {
+            kind: ProposalKind::MembershipChange(MembershipChange {
+                member: key(4),
+                action: MembershipAction::Remove,
+            }),
+            ..sample_proposal()
+        },
+    );
+    assert_account(
+        "proposal_treasury_recovery",
+        &Proposal {
+            kind: ProposalKind::TreasuryRecovery(TreasuryRecovery { recovery: key(15) }),
+            ..sample_proposal()
+        },
+    );
+
+    #[cfg(feature = "treasury")]
+    assert_instruction("execute_treasury_transfer", ix::ExecuteTreasuryTransfer {});
+    #[cfg(feature = "treasury")]
+    assert_instruction("execute_treasury_recovery", ix::ExecuteTreasuryRecovery {});
+    assert_instruction("execute_config_change", ix::ExecuteConfigChange {});
+    assert_instruction("execute_membership_change", ix::ExecuteMembershipChange {});
+    assert_instruction("execute_election", ix::ExecuteElection {});
+}
+
+#[test]
+fn member_layouts_match_golden_files() {
+    assert_account(
+        "member",
+        &Member {
+            group: key(10),
+            wallet: key(3),
+            joined_at: 1_690_000_000,
+            weight: 3,
+            bump: 229,
+        },
+    );
+}
+
+#[test]
+fn membership_tree_layouts_match_golden_files() {
+    assert_account(
+        "membership_tree",
+        &MembershipTree {
+            group: key(10),
+            merkle_tree: key(13),
+            credentials_issued: 42,
+            bump: 232,
+        },
+    );
+}
+
+#[test]
+fn invite_layouts_match_golden_files() {
+    assert_account(
+        "invite",
+        &Invite {
+            group: key(10),
+            code_hash: invite_code_hash(&key(10), "garden-2024"),
+            created_by: key(1),
+            max_uses: 50,
+            uses: 3,
+            expires_at: 1_700_604_800,
+            bump: 230,
+        },
+    );
+
+    assert_instruction(
+        "create_invite",
+        ix::CreateInvite {
+            code_hash: invite_code_hash(&key(10), "garden-2024"),
+            max_uses: 50,
+            expires_at: 1_700_604_800,
+        },
+    );
+    assert_instruction("revoke_invite", ix::RevokeInvite {});
+    assert_instruction(
+        "join_with_invite",
+        ix::JoinWithInvite {
+            _code: "garden-2024".to_string(),
+        },
+    );
+}
+
+#[test]
+fn membership_gate_layouts_match_golden_files() {
+    assert_account(
+        "membership_gate",
+        &MembershipGate {
+            group: key(10),
+            requirement: MembershipRequirement::Token {
+                mint: key(8),
+                min_amount: 1_000_000,
+            },
+            bump: 231,
+        },
+    );
+    assert_account(
+        "membership_gate_collection",
+        &MembershipGate {
+            group: key(10),
+            requirement: MembershipRequirement::Collection {
+                collection: key(14),
+            },
+            bump: 231,
+        },
+    );
+}
+
+#[cfg(feature = "token-gated-join")]
+#[test]
+fn token_gated_join_instructions_match_golden_files() {
+    assert_instruction(
+        "set_membership_gate",
+        ix::SetMembershipGate {
+            requirement: MembershipRequirement::Token {
+                mint: key(8),
+                min_amount: 1_000_000,
+            },
+        },
+    );
+    assert_instruction("clear_membership_gate", ix::ClearMembershipGate {});
+    assert_instruction("join_group", ix::JoinGroup {});
+}
+
+#[cfg(feature = "cnft-membership")]
+#[test]
+fn cnft_membership_instructions_match_golden_files() {
+    assert_instruction(
+        "init_membership_tree",
+        ix::InitMembershipTree {
+            max_depth: 20,
+            max_buffer_size: 64,
+        },
+    );
+    assert_instruction(
+        "vote_with_credential",
+        ix::VoteWithCredential {
+            choice_index: 1,
+            disclosure: None,
+            nonce: 41,
+            root: [7; 32],
+        },
+    );
+    assert_instruction(
+        "revoke_membership_credential",
+        ix::RevokeMembershipCredential {
+            nonce: 41,
+            root: [7; 32],
+        },
+    );
+}
+
+#[cfg(feature = "automation")]
+#[test]
+fn automation_layouts_match_golden_files() {
+    assert_account(
+        "proposal_schedule",
+        &ProposalSchedule {
+            proposal: key(10),
+            scheduled_by: key(1),
+            bump: 234,
+        },
+    );
+
+    assert_instruction("schedule_activation", ix::ScheduleActivation {});
+    assert_instruction("tick", ix::Tick {});
+}
+
+#[cfg(feature = "deposit-voting")]
+#[test]
+fn deposit_voting_layouts_match_golden_files() {
+    assert_account(
+        "deposit_registrar",
+        &DepositRegistrar {
+            group: key(10),
+            mint: NATIVE_MINT,
+            vault: key(18),
+            min_holding_secs: 7 * SECONDS_PER_DAY,
+            bump: 227,
+        },
+    );
+    assert_account(
+        "voter_deposit",
+        &VoterDeposit {
+            registrar: key(18),
+            voter: key(3),
+            amount: 4_000_000_000,
+            deposited_at: 1_699_000_000,
+            bump: 226,
+        },
+    );
+
+    assert_instruction(
+        "configure_deposit_registrar",
+        ix::ConfigureDepositRegistrar {
+            mint: NATIVE_MINT,
+            min_holding_secs: 7 * SECONDS_PER_DAY,
+        },
+    );
+    assert_instruction(
+        "deposit_voting_tokens",
+        ix::DepositVotingTokens {
+            amount: 4_000_000_000,
+        },
+    );
+    assert_instruction(
+        "withdraw_voting_tokens",
+        ix::WithdrawVotingTokens {
+            amount: 1_000_000_000,
+        },
+    );
+    assert_instruction("set_deposit_voting", ix::SetDepositVoting { enabled: true });
+}
+
+#[cfg(feature = "templates")]
+#[test]
+fn proposal_template_layouts_match_golden_files() {
+    assert_account(
+        "proposal_template",
+        &ProposalTemplate {
+            group: key(10),
+            template_id: "monthly-payroll".to_string(),
+            title: "Contributor payroll".to_string(),
+            description: "Pay this month's contributor stipend".to_string(),
+            choices: vec!["Yes".to_string(), "No".to_string()],
+            voting_duration: 3 * SECONDS_PER_DAY,
+            token_mint: None,
+            metadata_uri: Some("ipfs://bafygoldenpayroll".to_string()),
+            kind: ProposalKind::TreasuryTransfer(TreasuryTransfer {
+                recipient: key(17),
+                lamports: 500_000_000,
+            }),
+            member_weighted: true,
+            split_voting: false,
+            instance_count: 4,
+            created_at: 1_690_000_000,
+            bump: 228,
+        },
+    );
+
+    assert_instruction(
+        "create_proposal_template",
+        ix::CreateProposalTemplate {
+            template_id: "monthly-budget".to_string(),
+            title: "Monthly budget".to_string(),
+            description: "Split this month's budget".to_string(),
+            choices: vec!["Events".to_string(), "Tools".to_string()],
+            voting_duration: 2 * SECONDS_PER_DAY,
+            token_mint: Some(NATIVE_MINT),
+            metadata_uri: None,
+            kind: ProposalKind::Text,
+            member_weighted: false,
+            split_voting: true,
+        },
+    );
+    assert_instruction(
+        "instantiate_from_template",
+        ix::InstantiateFromTemplate {
+            proposal_id: "budget-2023-11".to_string(),
+            voting_start: 1_700_000_000,
+            draft: false,
+        },
+    );
+    assert_instruction("close_proposal_template", ix::CloseProposalTemplate {});
+}
+
+#[cfg(feature = "large-proposals")]
+#[test]
+fn large_proposal_instructions_match_golden_files() {
+    assert_instruction(
+        "create_large_proposal",
+        ix::CreateLargeProposal {
+            proposal_id: "golden-large".to_string(),
+            title: "Pick the garden mascot".to_string(),
+            description: "Thirty-two candidates, one winner.".to_string(),
+            choices: vec!["Candidate 0".to_string(), "Candidate 1".to_string()],
+            voting_start: 1_700_000_000,
+            voting_end: 1_700_086_400,
+            token_mint: None,
+        },
+    );
+    assert_instruction(
+        "vote_on_large_proposal",
+        ix::VoteOnLargeProposal {
+            choice_index: 31,
+            disclosure: Some(String::new()),
+        },
+    );
+}
+
+#[test]
+fn instruction_encodings_match_golden_files() {
+    assert_instruction(
+        "initialize",
+        ix::Initialize {
+            name: String::new(),
+        },
+    );
+    assert_instruction(
+        "initialize_named",
+        ix::Initialize {
+            name: "garden-collective".to_string(),
+        },
+    );
+    assert_instruction("migrate_group", ix::MigrateGroup {});
+    assert_instruction("migrate_proposal", ix::MigrateProposal {});
+    assert_instruction("migrate_user_account", ix::MigrateUserAccount {});
+    assert_instruction(
+        "initialize_program_config",
+        ix::InitializeProgramConfig {
+            guardian: Some(key(9)),
+        },
+    );
+    assert_instruction(
+        "update_program_config",
+        ix::UpdateProgramConfig {
+            max_groups: 500,
+            max_choices: 16,
+            fee_destination: key(8),
+            guardian: None,
+        },
+    );
+    assert_instruction("pause", ix::Pause {});
+    assert_instruction("unpause", ix::Unpause {});
+    assert_instruction(
+        "create_group",
+        ix::CreateGroup {
+            group_id: "tg_100200300".to_string(),
+            name: "Garden Club".to_string(),
+            description: "Neighbourhood garden decisions".to_string(),
+            metadata_uri: Some("https://example.org/garden".to_string()),
+            external_chat_id: Some(-100_200_300),
+        },
+    );
+    assert_instruction(
+        "set_group_chat",
+        ix::SetGroupChat {
+            external_chat_id: Some(-100_200_301),
+        },
+    );
+    assert_instruction(
+        "set_registration_config",
+        ix::SetRegistrationConfig {
+            open_registration: false,
+            group_deposit_lamports: 250_000_000,
+            group_deposit_lock_days: 14,
+        },
+    );
+    assert_instruction(
+        "set_registry_authority",
+        ix::SetRegistryAuthority {
+            new_authority: key(11),
+        },
+    );
+    assert_instruction("withdraw_group_deposit", ix::WithdrawGroupDeposit {});
+    assert_instruction(
+        "create_proposal",
+        ix::CreateProposal {
+            proposal_id: "golden-proposal".to_string(),
+            title: "Fund the community garden".to_string(),
+            description: "Allocate the Q3 budget to the garden project.".to_string(),
+            choices: vec!["Yes".to_string(), "No".to_string(), "Abstain".to_string()],
+            voting_start: 1_700_000_000,
+            voting_end: 1_700_086_400,
+            token_mint: Some(NATIVE_MINT),
+            metadata_uri: Some("ipfs://bafygoldenproposal".to_string()),
+            draft: true,
+            kind: ProposalKind::Text,
+            emergency: false,
+        },
+    );
+    assert_instruction("activate_proposal", ix::ActivateProposal {});
+    assert_instruction("cancel_proposal", ix::CancelProposal {});
+    assert_instruction("mark_proposal_executed", ix::MarkProposalExecuted {});
+    assert_instruction(
+        "set_proposal_metadata_uri",
+        ix::SetProposalMetadataUri {
+            metadata_uri: Some("https://example.org/archive.json".to_string()),
+        },
+    );
+    assert_instruction(
+        "vote_on_proposal",
+        ix::VoteOnProposal {
+            choice_index: 1,
+            disclosure: Some("grant recipient".to_string()),
+            allocations: None,
+        },
+    );
+    assert_instruction(
+        "vote_on_proposal_split",
+        ix::VoteOnProposal {
+            choice_index: 0,
+            disclosure: None,
+            allocations: Some(vec![600, 300, 100]),
+        },
+    );
+    assert_instruction(
+        "relay_vote",
+        ix::RelayVote {
+            choice_index: 1,
+            expiry: 1_700_000_600,
+        },
+    );
+    assert_instruction("cast_shadow_vote", ix::CastShadowVote { choice_index: 2 });
+    assert_instruction(
+        "fund_finalize_bounty",
+        ix::FundFinalizeBounty { amount: 5_000 },
+    );
+    assert_instruction("finalize_proposal", ix::FinalizeProposal {});
+    assert_instruction("close_stale_proposal", ix::CloseStaleProposal {});
+    assert_instruction(
+        "set_council",
+        ix::SetCouncil {
+            members: vec![key(6), key(7)],
+            threshold: 2,
+        },
+    );
+    assert_instruction("add_group_member", ix::AddGroupMember { member: key(4) });
+    assert_instruction(
+        "remove_group_member",
+        ix::RemoveGroupMember { member: key(4) },
+    );
+    assert_instruction(
+        "set_member_weight",
+        ix::SetMemberWeight {
+            member: key(3),
+            weight: 3,
+        },
+    );
+    assert_instruction(
+        "set_member_weighted_voting",
+        ix::SetMemberWeightedVoting { enabled: true },
+    );
+    assert_instruction("set_split_voting", ix::SetSplitVoting { enabled: true });
+    assert_instruction("set_weight_decay", ix::SetWeightDecay { decay_bps: 5_000 });
+    assert_instruction(
+        "set_slot_window",
+        ix::SetSlotWindow {
+            start_slot: 250_000_000,
+            end_slot: 250_216_000,
+        },
+    );
+    assert_instruction(
+        "set_members_only_voting",
+        ix::SetMembersOnlyVoting { enabled: true },
+    );
+    assert_instruction(
+        "set_membership_policy",
+        ix::SetMembershipPolicy {
+            max_members: 25,
+            visibility: GroupVisibility::Private,
+        },
+    );
+    assert_instruction(
+        "set_max_active_proposals",
+        ix::SetMaxActiveProposals {
+            max_active_proposals: 3,
+        },
+    );
+    assert_instruction(
+        "set_proposal_cooldown",
+        ix::SetProposalCooldown {
+            cooldown_secs: 12 * 3_600,
+        },
+    );
+    assert_instruction(
+        "set_tie_break",
+        ix::SetTieBreak {
+            tie_break: TieBreak::Revote {
+                window_secs: 86_400,
+            },
+        },
+    );
+    assert_instruction("resolve_tie", ix::ResolveTie { choice_index: 1 });
+    assert_instruction(
+        "create_user_account",
+        ix::CreateUserAccount {
+            telegram_id: 123_456_789,
+        },
+    );
+    assert_instruction(
+        "login_user",
+        ix::LoginUser {
+            telegram_id: 123_456_789,
+        },
+    );
+    assert_instruction(
+        "login_with_signature",
+        ix::LoginWithSignature {
+            telegram_id: 123_456_789,
+        },
+    );
+    assert_instruction(
+        "link_wallet",
+        ix::LinkWallet {
+            _telegram_id: 123_456_789,
+        },
+    );
+    assert_instruction(
+        "link_wallet_with_signature",
+        ix::LinkWalletWithSignature {
+            _telegram_id: 123_456_789,
+        },
+    );
+    assert_instruction(
+        "unlink_wallet",
+        ix::UnlinkWallet {
+            _telegram_id: 123_456_789,
+            wallet: key(5),
+        },
+    );
+    assert_instruction("get_all_groups", ix::GetAllGroups { start: 8 });
+    assert_instruction("get_proposal_summary", ix::GetProposalSummary {});
+}
+
+#[test]
+// Return data has no discriminator either: it is the plain borsh of the return value
+fn return_data_layouts_match_golden_files() {
+    use anchor_lang::AnchorSerialize;
+
+    let summary = ProposalSummary::of(&sample_proposal());
+    assert_golden("proposal_summary", &summary.try_to_vec().unwrap());
+
+    let groups: Vec<GroupInfo> = (0..20)
+        .map(|n| GroupInfo {
+            group_id: format!("group-{n}"),
+            authority: key(1),
+            pubkey: key(n),
+        })
+        .collect();
+    let page = group_page(&groups, 4);
+    assert_eq!(page.total, 20);
+    assert!(page.try_to_vec().unwrap().len() <= 1024);
+    assert_golden("group_page", &page.try_to_vec().unwrap());
+    assert!(group_page(&groups, 20).groups.is_empty());
+}
+
+#[cfg(feature = "voter-lottery")]
+#[test]
+fn voter_lottery_layouts_match_golden_files() {
+    assert_account(
+        "voter_lottery",
+        &VoterLottery {
+            group: key(10),
+            proposal: key(11),
+            funder: key(4),
+            total_amount: 2_000_000_000,
+            winner_count: 2,
+            winners: vec![key(6), key(3)],
+            prize: 1_000_000_000,
+            claimed_mask: 0b01,
+            claim_deadline: 1_700_000_000 + 30 * SECONDS_PER_DAY,
+            drawn: true,
+            bump: 225,
+        },
+    );
+
+    assert_instruction(
+        "fund_voter_lottery",
+        ix::FundVoterLottery {
+            winner_count: 2,
+            amount: 2_000_000_000,
+        },
+    );
+    assert_instruction("draw_voter_lottery", ix::DrawVoterLottery {});
+    assert_instruction("claim_lottery_prize", ix::ClaimLotteryPrize {});
+    assert_instruction("reclaim_voter_lottery", ix::ReclaimVoterLottery {});
+}
+
+#[cfg(feature = "dues")]
+#[test]
+fn dues_layouts_match_golden_files() {
+    assert_account(
+        "dues_record",
+        &DuesRecord {
+            group: key(10),
+            member: key(3),
+            paid_until: 1_702_592_000,
+            last_paid_at: 1_700_000_000,
+            overdue_flagged: false,
+            bump: 224,
+        },
+    );
+
+    assert_instruction(
+        "set_dues_policy",
+        ix::SetDuesPolicy {
+            dues: Some(DuesPolicy {
+                mint: NATIVE_MINT,
+                amount: 10_000_000,
+                period_secs: 30 * SECONDS_PER_DAY,
+                grace_secs: 7 * SECONDS_PER_DAY,
+                required_for_voting: true,
+            }),
+        },
+    );
+    assert_instruction("pay_dues", ix::PayDues { periods: 3 });
+    assert_instruction("flag_overdue_dues", ix::FlagOverdueDues {});
+}
+
+#[cfg(feature = "grants")]
+#[test]
+fn grant_layouts_match_golden_files() {
+    assert_account(
+        "proposal_grant",
+        &Proposal {
+            kind: ProposalKind::Grant(GrantTerms {
+                recipient: key(4),
+                milestones: vec![2_000_000_000, 3_000_000_000],
+                approval: MilestoneApproval::Council,
+            }),
+            ..sample_proposal()
+        },
+    );
+    assert_account(
+        "grant",
+        &Grant {
+            group: key(10),
+            proposal: key(2),
+            recipient: key(4),
+            approval: MilestoneApproval::MemberVote,
+            milestones: vec![
+                Milestone {
+                    amount: 2_000_000_000,
+                    approvals: 3,
+                    approved_at: 1_700_500_000,
+                    claimed: true,
+                },
+                Milestone {
+                    amount: 3_000_000_000,
+                    approvals: 1,
+                    approved_at: 0,
+                    claimed: false,
+                },
+            ],
+            released: 2_000_000_000,
+            created_at: 1_700_000_000,
+            bump: 223,
+        },
+    );
+    assert_account(
+        "milestone_signoff",
+        &MilestoneSignoff {
+            grant: key(11),
+            milestone: 1,
+            approver: key(3),
+            signed_at: 1_700_600_000,
+            bump: 222,
+        },
+    );
+
+    assert_instruction("execute_grant", ix::ExecuteGrant {});
+    assert_instruction("approve_milestone", ix::ApproveMilestone { milestone: 1 });
+    assert_instruction(
+        "claim_grant_tranche",
+        ix::ClaimGrantTranche { milestone: 0 },
+    );
+}
+
+#[cfg(feature = "streams")]
+#[test]
+fn stream_layouts_match_golden_files() {
+    let terms = StreamTerms {
+        recipient: key(4),
+        amount: 12_000_000_000,
+        start: 1_700_000_000,
+        cliff: 1_702_592_000,
+        end: 1_731_536_000,
+    };
+    assert_account(
+        "proposal_stream",
+        &Proposal {
+            kind: ProposalKind::Stream(terms.clone()),
+            ..sample_proposal()
+        },
+    );
+    assert_account(
+        "stream",
+        &Stream {
+            group: key(10),
+            proposal: key(2),
+            recipient: terms.recipient,
+            amount: terms.amount,
+            start: terms.start,
+            cliff: terms.cliff,
+            end: terms.end,
+            withdrawn: 1_000_000_000,
+            bump: 221,
+        },
+    );
+
+    assert_instruction("execute_stream", ix::ExecuteStream {});
+    assert_instruction("withdraw_vested", ix::WithdrawVested {});
+}
+
+#[cfg(feature = "bounties")]
+#[test]
+fn bounty_layouts_match_golden_files() {
+    let terms = BountyTerms {
+        bounty_id: "docs-translation".to_string(),
+        description_uri: "https://example.com/bounties/docs-translation".to_string(),
+        reward: 500_000_000,
+    };
+    assert_account(
+        "proposal_bounty",
+        &Proposal {
+            kind: ProposalKind::Bounty(terms.clone()),
+            ..sample_proposal()
+        },
+    );
+    assert_account(
+        "bounty",
+        &Bounty {
+            group: key(10),
+            bounty_id: terms.bounty_id.clone(),
+            description_uri: terms.description_uri.clone(),
+            reward: terms.reward,
+            status: BountyStatus::Submitted,
+            posted_by: key(1),
+            hunter: Some(key(3)),
+            submission_uri: Some(
+                "ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi".to_string(),
+            ),
+            created_at: 1_700_000_000,
+            bump: 220,
+        },
+    );
+
+    assert_instruction(
+        "post_bounty",
+        ix::PostBounty {
+            bounty_id: terms.bounty_id.clone(),
+            description_uri: terms.description_uri,
+            reward: terms.reward,
+        },
+    );
+    assert_instruction(
+        "execute_bounty",
+        ix::ExecuteBounty {
+            bounty_id: terms.bounty_id,
+        },
+    );
+    assert_instruction("claim_bounty", ix::ClaimBounty {});
+    assert_instruction(
+        "submit_bounty",
+        ix::SubmitBounty {
+            submission_uri: "https://example.com/pull/42".to_string(),
+        },
+    );
+    assert_instruction("approve_bounty", ix::ApproveBounty {});
+    assert_instruction("cancel_bounty", ix::CancelBounty {});
+}
+
+#[cfg(feature = "shares")]
+#[test]
+fn share_layouts_match_golden_files() {
+    assert_account(
+        "member_shares",
+        &MemberShares {
+            group: key(10),
+            member: key(3),
+            shares: 15,
+            locked_until: 1_700_259_200,
+            bump: 219,
+        },
+    );
+
+    assert_instruction(
+        "set_stake_to_join",
+        ix::SetStakeToJoin {
+            price_lamports: Some(100_000_000),
+        },
+    );
+    assert_instruction("stake_shares", ix::StakeShares { shares: 5 });
+    assert_instruction("ragequit", ix::Ragequit { shares: 15 });
+}
+
+#[cfg(feature = "bicameral")]
+#[test]
+fn bicameral_instructions_match_golden_files() {
+    assert_instruction("set_bicameral", ix::SetBicameral { enabled: true });
+    assert_instruction("council_vote", ix::CouncilVote { choice_index: 1 });
+}
+
+#[cfg(feature = "bans")]
+#[test]
+fn ban_layouts_match_golden_files() {
+    assert_account(
+        "ban",
+        &Ban {
+            group: key(10),
+            wallet: key(7),
+            reason: "Spamming proposals".to_string(),
+            banned_by: key(1),
+            banned_at: 1_700_000_000,
+            expires_at: 1_702_592_000,
+            bump: 218,
+        },
+    );
+
+    assert_instruction(
+        "ban_member",
+        ix::BanMember {
+            wallet: key(7),
+            reason: "Spamming proposals".to_string(),
+            expires_at: 1_702_592_000,
+        },
+    );
+    assert_instruction("unban_member", ix::UnbanMember { wallet: key(7) });
+}
+
+#[cfg(feature = "registry-multisig")]
+#[test]
+fn registry_multisig_layouts_match_golden_files() {
+    assert_account(
+        "registry_multisig",
+        &RegistryMultisig {
+            registry: key(2),
+            members: vec![key(6), key(7), key(9)],
+            threshold: 2,
+            action_count: 4,
+            bump: 216,
+        },
+    );
+
+    let action = RegistryAction::UpdateProgramConfig {
+        max_groups: 500,
+        max_choices: 16,
+        fee_destination: key(8),
+        guardian: Some(key(9)),
+    };
+    assert_account(
+        "registry_action",
+        &RegistryActionProposal {
+            multisig: key(12),
+            index: 3,
+            action: action.clone(),
+            proposer: key(6),
+            approvals: vec![key(6), key(9)],
+            created_at: 1_700_000_000,
+            executed_at: 0,
+            bump: 215,
+        },
+    );
+
+    assert_instruction(
+        "create_registry_multisig",
+        ix::CreateRegistryMultisig {
+            members: vec![key(6), key(7), key(9)],
+            threshold: 2,
+        },
+    );
+    assert_instruction(
+        "propose_registry_action",
+        ix::ProposeRegistryAction { action },
+    );
+    assert_instruction(
+        "propose_registry_action_set_signers",
+        ix::ProposeRegistryAction {
+            action: RegistryAction::SetSigners {
+                members: vec![key(6), key(7)],
+                threshold: 2,
+            },
+        },
+    );
+    assert_instruction("approve_registry_action", ix::ApproveRegistryAction {});
+    assert_instruction("execute_registry_action", ix::ExecuteRegistryAction {});
+}
+
+#[cfg(feature = "choice-details")]
+#[test]
+fn choice_details_layouts_match_golden_files() {
+    let details = vec![
+        ChoiceDetail {
+            description: Some("Runs the seed library".to_string()),
+            uri: Some("https://example.org/candidates/ana".to_string()),
+        },
+        ChoiceDetail::default(),
+        ChoiceDetail {
+            description: Some("Abstain from the election".to_string()),
+            uri: None,
+        },
+    ];
+    assert_account(
+        "choice_details",
+        &ChoiceDetails {
+            proposal: key(11),
+            details: details.clone(),
+            bump: 214,
+        },
+    );
+
+    assert_instruction("set_choice_details", ix::SetChoiceDetails { details });
+    assert_instruction("close_choice_details", ix::CloseChoiceDetails {});
+}
+
+#[cfg(feature = "callbacks")]
+#[test]
+fn execution_callback_layouts_match_golden_files() {
+    use anchor_lang::AnchorSerialize;
+
+    let payload = vec![0xa1, 0x0b, 0x5e, 0x77, 0x42, 0x00, 0x13, 0x37];
+    assert_account(
+        "execution_callback",
+        &ExecutionCallback {
+            proposal: key(11),
+            on_success_program: key(13),
+            payload: payload.clone(),
+            bump: 213,
+        },
+    );
+    assert_golden(
+        "callback_result",
+        &CallbackResult {
+            proposal: key(11),
+            group: key(10),
+            status: ProposalStatus::Succeeded,
+            winning_choice: 0,
+            choice_votes: vec![7, 2],
+        }
+        .try_to_vec()
+        .unwrap(),
+    );
+
+    assert_instruction(
+        "set_execution_callback",
+        ix::SetExecutionCallback {
+            on_success_program: key(13),
+            payload,
+        },
+    );
+    assert_instruction("execute_proposal_callback", ix::ExecuteProposalCallback {});
+
+    assert_account(
+        "program_allowlist",
+        &ProgramAllowlist {
+            group: key(10),
+            programs: vec![key(13), key(14)],
+            bump: 212,
+        },
+    );
+    assert_instruction(
+        "set_program_allowlist",
+        ix::SetProgramAllowlist {
+            programs: vec![key(13), key(14)],
+        },
+    );
+}
+
+#[cfg(feature = "profiles")]
+#[test]
+fn group_profile_layouts_match_golden_files() {
+    let socials = vec![
+        "https://x.com/seedlibrary".to_string(),
+        "https://github.com/seedlibrary".to_string(),
+    ];
+    assert_account(
+        "group_profile",
+        &GroupProfile {
+            group: key(10),
+            avatar_uri: Some("ipfs://bafkreiavatar".to_string()),
+            website: Some("https://seedlibrary.example.org".to_string()),
+            socials: socials.clone(),
+            contact: Some("hello@seedlibrary.example.org".to_string()),
+            updated_at: 1_700_000_000,
+            bump: 211,
+        },
+    );
+
+    assert_instruction(
+        "set_group_profile",
+        ix::SetGroupProfile {
+            avatar_uri: Some("ipfs://bafkreiavatar".to_string()),
+            website: None,
+            socials,
+            contact: Some("hello@seedlibrary.example.org".to_string()),
+        },
+    );
+    assert_instruction("close_group_profile", ix::CloseGroupProfile {});
+}
+
+#[cfg(feature = "treasury-staking")]
+#[test]
+fn treasury_stake_layouts_match_golden_files() {
+    assert_account(
+        "proposal_treasury_stake",
+        &Proposal {
+            kind: ProposalKind::TreasuryStake(StakeAction::Delegate {
+                vote_account: key(16),
+                lamports: 25_000_000_000,
+            }),
+            ..sample_proposal()
+        },
+    );
+
+    assert_instruction("execute_treasury_stake", ix::ExecuteTreasuryStake {});
+}
+
+#[cfg(feature = "treasury-swaps")]
+#[test]
+fn treasury_swap_layouts_match_golden_files() {
+    assert_account(
+        "proposal_treasury_swap",
+        &Proposal {
+            kind: ProposalKind::TreasurySwap(SwapTerms {
+                swap_program: key(17),
+                input_mint: key(6),
+                output_mint: key(18),
+                amount_in: 5_000_000_000,
+                min_amount_out: 4_950_000,
+            }),
+            ..sample_proposal()
+        },
+    );
+
+    assert_instruction(
+        "execute_treasury_swap",
+        ix::ExecuteTreasurySwap {
+            route_data: vec![0xe5, 0x17, 0xcb, 0x97, 0x7a, 0xe3, 0xad, 0x2a, 0x01],
+        },
+    );
+}
+
+#[cfg(feature = "token-distribution")]
+#[test]
+fn token_distribution_layouts_match_golden_files() {
+    let merkle_root = [0x5a; 32];
+    assert_account(
+        "proposal_token_distribution",
+        &Proposal {
+            kind: ProposalKind::TokenDistribution(DistributionTerms {
+                mint: key(6),
+                source: DistributionSource::Mint,
+                merkle_root,
+                total_amount: 1_000_000_000_000,
+            }),
+            ..sample_proposal()
+        },
+    );
+    assert_account(
+        "token_distribution",
+        &TokenDistribution {
+            group: key(10),
+            proposal: key(11),
+            mint: key(6),
+            source: DistributionSource::Treasury,
+            merkle_root,
+            total_amount: 1_000_000_000_000,
+            claimed_amount: 25_000_000_000,
+            claim_count: 3,
+            created_at: 1_700_000_000,
+            bump: 210,
+        },
+    );
+    assert_account(
+        "distribution_claim",
+        &DistributionClaim {
+            distribution: key(19),
+            recipient: key(3),
+            amount: 10_000_000_000,
+            claimed_at: 1_700_086_400,
+            bump: 209,
+        },
+    );
+
+    assert_instruction(
+        "execute_token_distribution",
+        ix::ExecuteTokenDistribution {},
+    );
+    assert_instruction(
+        "claim_token_distribution",
+        ix::ClaimTokenDistribution {
+            amount: 10_000_000_000,
+            proof: vec![[0x11; 32], [0x22; 32]],
+        },
+    );
+}
+
+#[cfg(feature = "sponsorship")]
+#[test]
+fn sponsorship_layouts_match_golden_files() {
+    assert_account(
+        "proposal_sponsor",
+        &ProposalSponsor {
+            proposal: key(11),
+            sponsor: key(3),
+            sponsored_at: 1_699_999_500,
+            bump: 208,
+        },
+    );
+
+    assert_instruction(
+        "set_sponsor_threshold",
+        ix::SetSponsorThreshold { threshold: 3 },
+    );
+    assert_instruction("co_sponsor", ix::CoSponsor {});
+}
+
+#[cfg(feature = "delegation")]
+#[test]
+fn delegation_layouts_match_golden_files() {
+    assert_account(
+        "delegation",
+        &Delegation {
+            delegator: key(3),
+            delegate: key(4),
+            group: key(10),
+            kind: Some(1),
+            expires_at: Some(1_702_592_000),
+            created_at: 1_700_000_000,
+            bump: 206,
+        },
+    );
+
+    assert_instruction(
+        "delegate_votes",
+        ix::DelegateVotes {
+            group: key(10),
+            delegate: key(4),
+            kind: Some(1),
+            expires_at: Some(1_702_592_000),
+        },
+    );
+    assert_instruction("undelegate_votes", ix::UndelegateVotes {});
+    assert_instruction("expire_delegation", ix::ExpireDelegation {});
+    assert_instruction("vote_as_delegate", ix::VoteAsDelegate { choice_index: 1 });
+}
@@ -10,7 +10,7 @@ describe("solana-dao", () => {
 
   it("Is initialized!", async () => {
     // Add your test here.
-    const tx = await program.methods.initialize().rpc();
+    const tx = await program.methods.initialize("").rpc();
     console.log("Your transaction signature", tx);
   });
 });
@@ -0,0 +1,3536 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { expect } from "chai";
+import { createHash } from "crypto";
+import { SolanaDao } from "../target/types/solana_dao";
+
+const { Keypair, LAMPORTS_PER_SOL, PublicKey, SystemProgram } = anchor.web3;
+type PublicKey = anchor.web3.PublicKey;
+
+// Adversarial tests: every instruction below is sent by someone who should not
+// be able to perform it, or with accounts crafted to slip past a constraint.
+// Each one must fail with the specific error that guards it.
+describe("unauthorized access", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.solanaDao as Program<SolanaDao>;
+  const authority = provider.wallet.publicKey;
+
+  const attacker = Keypair.generate();
+  const groupId = `sec-${Date.now().toString(36)}`;
+
+  const [registryPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("dao_registry")],
+    program.programId
+  );
+  const [configPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("program_config")],
+    program.programId
+  );
+  const [groupPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("group"), Buffer.from(groupId)],
+    program.programId
+  );
+
+  const proposalPda = (group: PublicKey, proposalId: string) =>
+    PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("proposal"),
+        group.toBuffer().subarray(0, 8),
+        Buffer.from(proposalId).subarray(0, 8),
+      ],
+      program.programId
+    )[0];
+
+  const indexedPda = (prefix: string, group: PublicKey, index: number) =>
+    PublicKey.findProgramAddressSync(
+      [
+        Buffer.from(prefix),
+        group.toBuffer(),
+        new anchor.BN(index).toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    )[0];
+
+  const memberPda = (wallet: PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("member"), groupPda.toBuffer(), wallet.toBuffer()],
+      program.programId
+    )[0];
+
+  const proposalCount = async () =>
+    (await program.account.group.fetch(groupPda)).proposalCount.toNumber();
+
+  const now = () => Math.floor(Date.now() / 1000);
+  const sleep = (ms: number) => new Promise((r) => setTimeout(r, ms));
+
+  // Assert that `tx` fails with one of `codes` (error names or log fragments)
+  const expectFailure = async (tx: Promise<unknown>, ...codes: string[]) => {
+    let error: any;
+    try {
+      await tx;
+    } catch (e) {
+      error = e;
+    }
+    expect(error, `expected failure with ${codes.join(" | ")}`).to.exist;
+    const haystack = [
+      error.error?.errorCode?.code,
+      error.message,
+      ...(error.logs ?? []),
+    ].join("\n");
+    expect(codes.some((code) => haystack.includes(code))).to.equal(
+      true,
+      `expected ${codes.join(" | ")}, got: ${haystack}`
+    );
+  };
+
+  // Create a proposal in the test group; voting opens a couple of seconds later and
+  // lasts `durationSecs`
+  const createProposal = async (
+    proposalId: string,
+    tokenMint: PublicKey | null,
+    draft = false,
+    choices = ["Yes", "No"],
+    kind: object = { text: {} },
+    durationSecs = 3600
+  ) => {
+    const index = await proposalCount();
+    const start = now() + 2;
+    await program.methods
+      .createProposal(
+        proposalId,
+        "Security test",
+        "Proposal used by adversarial tests",
+        choices,
+        new anchor.BN(start),
+        new anchor.BN(start + durationSecs),
+        tokenMint,
+        null,
+        draft,
+        kind,
+        false
+      )
+      .accountsPartial({
+        proposal: proposalPda(groupPda, proposalId),
+        proposalIndex: indexedPda("proposal_index", groupPda, index),
+        group: groupPda,
+        authority,
+      })
+      .rpc();
+    return proposalPda(groupPda, proposalId);
+  };
+
+  before(async () => {
+    const sig = await provider.connection.requestAirdrop(
+      attacker.publicKey,
+      2 * LAMPORTS_PER_SOL
+    );
+    const latest = await provider.connection.getLatestBlockhash();
+    await provider.connection.confirmTransaction({ signature: sig, ...latest });
+
+    if (!(await program.account.daoRegistry.fetchNullable(registryPda))) {
+      await program.methods.initialize("").rpc();
+    }
+    if (!(await program.account.programConfig.fetchNullable(configPda))) {
+      await program.methods.initializeProgramConfig(null).rpc();
+    }
+
+    await program.methods
+      .createGroup(
+        groupId,
+        "Security",
+        "Group owned by the provider wallet",
+        null,
+        null
+      )
+      .accountsPartial({ group: groupPda, daoRegistry: registryPda, authority, chatLink: null })
+      .rpc();
+  });
+
+  it("rejects adding a member without group authority", async () => {
+    await expectFailure(
+      program.methods
+        .addGroupMember(attacker.publicKey)
+        .accountsPartial({
+          group: groupPda,
+          authority: attacker.publicKey,
+          memberRecord: memberPda(attacker.publicKey),
+        })
+        .signers([attacker])
+        .rpc(),
+      "Unauthorized"
+    );
+  });
+
+  it("rejects removing a member without group authority", async () => {
+    await program.methods
+      .addGroupMember(authority)
+      .accountsPartial({
+        group: groupPda,
+        authority,
+        memberRecord: memberPda(authority),
+      })
+      .rpc();
+
+    await expectFailure(
+      program.methods
+        .removeGroupMember(authority)
+        .accountsPartial({ group: groupPda, authority: attacker.publicKey })
+        .signers([attacker])
+        .rpc(),
+      "Unauthorized"
+    );
+  });
+
+  it("rejects adding a wallet that is already a member", async () => {
+    await expectFailure(
+      program.methods
+        .addGroupMember(authority)
+        .accountsPartial({
+        group: groupPda,
+        authority,
+        memberRecord: memberPda(authority),
+      })
+        .rpc(),
+      "MemberAlreadyExists"
+    );
+  });
+
+  it("rejects adding a member without a record or membership tree", async () => {
+    await expectFailure(
+      program.methods
+        .addGroupMember(attacker.publicKey)
+        .accountsPartial({
+          group: groupPda,
+          authority,
+          memberRecord: null,
+          membershipTree: null,
+        })
+        .rpc(),
+      "MemberRecordRequired"
+    );
+  });
+
+  it("rejects creating a proposal in someone else's group", async () => {
+    const proposalId = "hijack-proposal";
+    const index = await proposalCount();
+    await expectFailure(
+      program.methods
+        .createProposal(
+          proposalId,
+          "Hijack",
+          "",
+          ["Yes", "No"],
+          new anchor.BN(now() + 60),
+          new anchor.BN(now() + 3600),
+          null,
+          null,
+          false,
+          { text: {} },
+          false
+        )
+        .accountsPartial({
+          proposal: proposalPda(groupPda, proposalId),
+          proposalIndex: indexedPda("proposal_index", groupPda, index),
+          group: groupPda,
+          authority: attacker.publicKey,
+        })
+        .signers([attacker])
+        .rpc(),
+      "Unauthorized"
+    );
+  });
+
+  it("rejects withdrawing another group's deposit", async () => {
+    await expectFailure(
+      program.methods
+        .withdrawGroupDeposit()
+        .accountsPartial({ group: groupPda, authority: attacker.publicKey })
+        .signers([attacker])
+        .rpc(),
+      "Unauthorized"
+    );
+  });
+
+  it("rejects a proposal index PDA derived from crafted seeds", async () => {
+    const proposalId = "crafted-index";
+    const index = await proposalCount();
+    await expectFailure(
+      program.methods
+        .createProposal(
+          proposalId,
+          "Crafted index",
+          "",
+          ["Yes", "No"],
+          new anchor.BN(now() + 60),
+          new anchor.BN(now() + 3600),
+          null,
+          null,
+          false,
+          { text: {} },
+          false
+        )
+        .accountsPartial({
+          proposal: proposalPda(groupPda, proposalId),
+          // Skipping ahead would let a later proposal collide with this slot
+          proposalIndex: indexedPda("proposal_index", groupPda, index + 1),
+          group: groupPda,
+          authority,
+        })
+        .rpc(),
+      "ConstraintSeeds"
+    );
+  });
+
+  it("rejects a proposal PDA derived from a different proposal id", async () => {
+    const index = await proposalCount();
+    await expectFailure(
+      program.methods
+        .createProposal(
+          "mismatched-id",
+          "Mismatched PDA",
+          "",
+          ["Yes", "No"],
+          new anchor.BN(now() + 60),
+          new anchor.BN(now() + 3600),
+          null,
+          null,
+          false,
+          { text: {} },
+          false
+        )
+        .accountsPartial({
+          proposal: proposalPda(groupPda, "other-id"),
+          proposalIndex: indexedPda("proposal_index", groupPda, index),
+          group: groupPda,
+          authority,
+        })
+        .rpc(),
+      "ConstraintSeeds"
+    );
+  });
+
+  it("rejects reusing an existing proposal PDA", async () => {
+    const proposalId = "reused-pda";
+    await createProposal(proposalId, null);
+    await expectFailure(createProposal(proposalId, null), "already in use");
+  });
+
+  it("rejects a wrong account in place of the registry", async () => {
+    await expectFailure(
+      program.methods
+        .createGroup(`${groupId}-x`, "Fake registry", "", null, null)
+        .accountsPartial({ daoRegistry: groupPda, authority, chatLink: null })
+        .rpc(),
+      "AccountDiscriminatorMismatch",
+      "ConstraintSeeds"
+    );
+  });
+
+  it("summarizes only proposal accounts", async () => {
+    const proposalId = "summary";
+    const proposal = await createProposal(proposalId, null);
+    const summary = await program.methods
+      .getProposalSummary()
+      .accountsPartial({ proposal })
+      .view();
+    expect(summary.proposalId).to.equal(proposalId);
+
+    await expectFailure(
+      program.methods.getProposalSummary().accountsPartial({ proposal: groupPda }).view(),
+      "AccountDiscriminatorMismatch"
+    );
+  });
+
+  it("rejects a wrong account in place of the program config", async () => {
+    // A real program account of another type must not pass as the pause switch
+    await expectFailure(
+      program.methods
+        .addGroupMember(attacker.publicKey)
+        .accountsPartial({
+          group: groupPda,
+          authority,
+          programConfig: registryPda,
+        })
+        .rpc(),
+      "AccountDiscriminatorMismatch",
+      "ConstraintSeeds"
+    );
+  });
+
+  it("rejects pausing by someone who is neither authority nor guardian", async () => {
+    await expectFailure(
+      program.methods
+        .pause()
+        .accountsPartial({ authority: attacker.publicKey })
+        .signers([attacker])
+        .rpc(),
+      "Unauthorized"
+    );
+  });
+
+  it("rejects updating the program config without registry authority", async () => {
+    await expectFailure(
+      program.methods
+        .updateProgramConfig(
+          1_000_000,
+          32,
+          attacker.publicKey,
+          attacker.publicKey
+        )
+        .accountsPartial({ authority: attacker.publicKey })
+        .signers([attacker])
+        .rpc(),
+      "Unauthorized"
+    );
+  });
+
+  it("rejects changing registration settings without registry authority", async () => {
+    await expectFailure(
+      program.methods
+        .setRegistrationConfig(true, new anchor.BN(0), 0)
+        .accountsPartial({ daoRegistry: registryPda, authority: attacker.publicKey })
+        .signers([attacker])
+        .rpc(),
+      "Unauthorized"
+    );
+  });
+
+  describe("registry multisig", () => {
+    it("rejects handing the registry to another key without registry authority", async () => {
+      await expectFailure(
+        program.methods
+          .setRegistryAuthority(attacker.publicKey)
+          .accountsPartial({ daoRegistry: registryPda, authority: attacker.publicKey })
+          .signers([attacker])
+          .rpc(),
+        "Unauthorized"
+      );
+    });
+
+    it("rejects putting the registry under a multisig without registry authority", async () => {
+      await expectFailure(
+        program.methods
+          .createRegistryMultisig([attacker.publicKey], 1)
+          .accountsPartial({ daoRegistry: registryPda, authority: attacker.publicKey })
+          .signers([attacker])
+          .rpc(),
+        "Unauthorized"
+      );
+    });
+  });
+
+  describe("token-weighted voting", () => {
+    const mint = Keypair.generate().publicKey;
+    let proposal: PublicKey;
+
+    before(async () => {
+      proposal = await createProposal("token-vote", mint);
+      await sleep(3000);
+    });
+
+    it("requires a token account", async () => {
+      await expectFailure(
+        program.methods
+          .voteOnProposal(0, null, null)
+          .accountsPartial({
+            proposal,
+            group: groupPda,
+            voter: attacker.publicKey,
+            voterTokenAccount: null,
+            tokenProgram: null,
+          })
+          .signers([attacker])
+          .rpc(),
+        "TokenAccountRequired"
+      );
+    });
+
+    it("rejects a fake token account", async () => {
+      // The attacker's wallet is not an SPL token account for the proposal mint
+      await expectFailure(
+        program.methods
+          .voteOnProposal(0, null, null)
+          .accountsPartial({
+            proposal,
+            group: groupPda,
+            voter: attacker.publicKey,
+            voterTokenAccount: attacker.publicKey,
+            tokenProgram: null,
+          })
+          .signers([attacker])
+          .rpc(),
+        "InvalidTokenAccount"
+      );
+    });
+  });
+
+  describe("double voting", () => {
+    it("rejects a second vote on a standard proposal", async () => {
+      const proposal = await createProposal("double-vote", null);
+      await sleep(3000);
+
+      const vote = () =>
+        program.methods
+          .voteOnProposal(0, null, null)
+          .accountsPartial({
+            proposal,
+            group: groupPda,
+            voter: attacker.publicKey,
+            voterTokenAccount: null,
+            tokenProgram: null,
+          })
+          .signers([attacker])
+          .rpc();
+
+      await vote();
+      await expectFailure(vote(), "AlreadyVoted");
+
+      // The rejected vote must not count toward turnout
+      const [turnoutPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("turnout"), proposal.toBuffer()],
+        program.programId
+      );
+      const turnout = await program.account.turnoutStats.fetch(turnoutPda);
+      expect(turnout.uniqueVoters).to.equal(1);
+      expect(turnout.votesPerDay).to.deep.equal([1]);
+    });
+
+    it("rejects an oversized disclosure note", async () => {
+      const proposal = await createProposal("long-disclosure", null);
+      await sleep(3000);
+
+      await expectFailure(
+        program.methods
+          .voteOnProposal(0, "x".repeat(65), null)
+          .accountsPartial({
+            proposal,
+            group: groupPda,
+            voter: attacker.publicKey,
+            voterTokenAccount: null,
+            tokenProgram: null,
+          })
+          .signers([attacker])
+          .rpc(),
+        "InvalidDisclosure"
+      );
+    });
+
+    it("rejects a second vote record and a mismatched group on a large proposal", async () => {
+      const index = await proposalCount();
+      const proposal = indexedPda("proposal_large", groupPda, index);
+      const start = now() + 2;
+      await program.methods
+        .createLargeProposal(
+          "large-double-vote",
+          "Large security test",
+          "",
+          ["Yes", "No"],
+          new anchor.BN(start),
+          new anchor.BN(start + 3600),
+          null
+        )
+        .accountsPartial({
+          proposal,
+          proposalIndex: indexedPda("proposal_index", groupPda, index),
+          group: groupPda,
+          authority,
+        })
+        .rpc();
+      await sleep(3000);
+
+      // Another program-owned group must not stand in for the proposal's group
+      const [otherGroup] = PublicKey.findProgramAddressSync(
+        [Buffer.from("group"), Buffer.from(`${groupId}-o`)],
+        program.programId
+      );
+      await program.methods
+        .createGroup(`${groupId}-o`, "Other", "", null, null)
+        .accountsPartial({ group: otherGroup, daoRegistry: registryPda, authority, chatLink: null })
+        .rpc();
+
+      const vote = (group: PublicKey) =>
+        program.methods
+          .voteOnLargeProposal(0, null)
+          .accountsPartial({
+            proposal,
+            group,
+            voter: attacker.publicKey,
+            voterTokenAccount: null,
+          })
+          .signers([attacker])
+          .rpc();
+
+      await expectFailure(vote(otherGroup), "ConstraintHasOne");
+      await vote(groupPda);
+      await expectFailure(vote(groupPda), "already in use");
+    });
+  });
+
+  describe("participation tracking", () => {
+    it("rejects crediting a vote to another member's participation account", async () => {
+      const proposal = await createProposal("borrowed-streak", null);
+      await sleep(3000);
+
+      const [authorityParticipation] = PublicKey.findProgramAddressSync(
+        [Buffer.from("participation"), groupPda.toBuffer(), authority.toBuffer()],
+        program.programId
+      );
+      await expectFailure(
+        program.methods
+          .voteOnProposal(0, null, null)
+          .accountsPartial({
+            proposal,
+            group: groupPda,
+            voter: attacker.publicKey,
+            participation: authorityParticipation,
+            voterTokenAccount: null,
+            tokenProgram: null,
+          })
+          .signers([attacker])
+          .rpc(),
+        "ConstraintSeeds"
+      );
+    });
+  });
+
+  describe("shadow ballots", () => {
+    const shadowVote = (proposal: PublicKey, voter: Keypair | null) =>
+      program.methods
+        .castShadowVote(0)
+        .accountsPartial({
+          proposal,
+          group: groupPda,
+          voter: voter ? voter.publicKey : authority,
+        })
+        .signers(voter ? [voter] : [])
+        .rpc();
+
+    it("rejects a shadow vote from a group member", async () => {
+      const proposal = await createProposal("member-shadow", null);
+      await sleep(3000);
+
+      // The provider wallet was added as a member above
+      await expectFailure(shadowVote(proposal, null), "ShadowVoterIsMember");
+    });
+
+    it("rejects a second shadow vote from the same wallet", async () => {
+      const proposal = await createProposal("double-shadow", null);
+      await sleep(3000);
+
+      await shadowVote(proposal, attacker);
+      await expectFailure(shadowVote(proposal, attacker), "already in use");
+    });
+  });
+
+  describe("proposal lifecycle", () => {
+    it("rejects votes on a draft until it is activated", async () => {
+      const proposal = await createProposal("draft-vote", null, true);
+      await sleep(3000);
+
+      const vote = () =>
+        program.methods
+          .voteOnProposal(0, null, null)
+          .accountsPartial({
+            proposal,
+            group: groupPda,
+            voter: attacker.publicKey,
+            voterTokenAccount: null,
+            tokenProgram: null,
+          })
+          .signers([attacker])
+          .rpc();
+
+      await expectFailure(vote(), "VotingNotActive");
+
+      await program.methods
+        .activateProposal()
+        .accountsPartial({ proposal, group: groupPda, authority })
+        .rpc();
+      await vote();
+    });
+
+    it("rejects activating a draft without group authority", async () => {
+      const proposal = await createProposal("draft-hijack", null, true);
+
+      await expectFailure(
+        program.methods
+          .activateProposal()
+          .accountsPartial({
+            proposal,
+            group: groupPda,
+            authority: attacker.publicKey,
+          })
+          .signers([attacker])
+          .rpc(),
+        "Unauthorized"
+      );
+    });
+
+    it("rejects marking an unfinalized proposal executed", async () => {
+      const proposal = await createProposal("early-execute", null);
+
+      await expectFailure(
+        program.methods
+          .markProposalExecuted()
+          .accountsPartial({ proposal, group: groupPda, authority })
+          .rpc(),
+        "InvalidProposalStatus"
+      );
+    });
+  });
+
+  describe("finalization", () => {
+    it("rejects finalizing before voting ends", async () => {
+      const proposal = await createProposal("early-finalize", null);
+
+      await expectFailure(
+        program.methods
+          .finalizeProposal()
+          .accountsPartial({
+            proposal,
+            group: groupPda,
+            cranker: attacker.publicKey,
+            lottery: null,
+            slotHashes: null,
+            quorumSupply: null,
+          })
+          .signers([attacker])
+          .rpc(),
+        "VotingNotEnded"
+      );
+    });
+
+    it("rejects an empty bounty", async () => {
+      const proposal = await createProposal("empty-bounty", null);
+
+      await expectFailure(
+        program.methods
+          .fundFinalizeBounty(new anchor.BN(0))
+          .accountsPartial({
+            proposal,
+            group: groupPda,
+            funder: attacker.publicKey,
+          })
+          .signers([attacker])
+          .rpc(),
+        "InvalidBountyAmount"
+      );
+    });
+
+    it("rejects closing a proposal that was never finalized", async () => {
+      const index = await proposalCount();
+      const proposal = await createProposal("unsettled-close", null);
+      const { feeDestination } = await program.account.programConfig.fetch(
+        configPda
+      );
+
+      await expectFailure(
+        program.methods
+          .closeStaleProposal()
+          .accountsPartial({
+            proposal,
+            proposalIndex: indexedPda("proposal_index", groupPda, index),
+            group: groupPda,
+            closer: attacker.publicKey,
+            feeDestination,
+          })
+          .signers([attacker])
+          .rpc(),
+        "InvalidProposalStatus"
+      );
+    });
+  });
+
+  describe("proposal fees", () => {
+    it("rejects setting the proposal fee without group authority", async () => {
+      await expectFailure(
+        program.methods
+          .setProposalFee(new anchor.BN(0))
+          .accountsPartial({ group: groupPda, authority: attacker.publicKey })
+          .signers([attacker])
+          .rpc(),
+        "Unauthorized"
+      );
+    });
+
+    it("rejects a fee too small to open the treasury account", async () => {
+      await expectFailure(
+        program.methods
+          .setProposalFee(new anchor.BN(1))
+          .accountsPartial({ group: groupPda, authority })
+          .rpc(),
+        "InvalidProposalFee"
+      );
+    });
+  });
+
+  describe("spam deposits", () => {
+    const [councilPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("council"), groupPda.toBuffer()],
+      program.programId
+    );
+    let proposal: PublicKey;
+
+    before(async () => {
+      await program.methods
+        .setCouncil([authority], 1)
+        .accountsPartial({ council: councilPda, group: groupPda, authority })
+        .rpc();
+      proposal = await createProposal("spam-deposit", null, true);
+      await program.methods
+        .postProposalDeposit(new anchor.BN(LAMPORTS_PER_SOL / 100))
+        .accountsPartial({ proposal, group: groupPda, depositor: authority })
+        .rpc();
+    });
+
+    it("rejects replacing the council without group authority", async () => {
+      await expectFailure(
+        program.methods
+          .setCouncil([attacker.publicKey], 1)
+          .accountsPartial({
+            council: councilPda,
+            group: groupPda,
+            authority: attacker.publicKey,
+          })
+          .signers([attacker])
+          .rpc(),
+        "Unauthorized"
+      );
+    });
+
+    it("rejects a deposit on a proposal that is already open", async () => {
+      const open = await createProposal("open-deposit", null);
+      await expectFailure(
+        program.methods
+          .postProposalDeposit(new anchor.BN(LAMPORTS_PER_SOL / 100))
+          .accountsPartial({
+            proposal: open,
+            group: groupPda,
+            depositor: authority,
+          })
+          .rpc(),
+        "InvalidProposalStatus"
+      );
+    });
+
+    it("rejects a deposit below the rent minimum", async () => {
+      const draft = await createProposal("dust-deposit", null, true);
+      await expectFailure(
+        program.methods
+          .postProposalDeposit(new anchor.BN(1))
+          .accountsPartial({
+            proposal: draft,
+            group: groupPda,
+            depositor: authority,
+          })
+          .rpc(),
+        "DepositTooSmall"
+      );
+    });
+
+    it("rejects requiring deposits without group authority", async () => {
+      await expectFailure(
+        program.methods
+          .setProposalDeposit(new anchor.BN(LAMPORTS_PER_SOL / 100))
+          .accountsPartial({ group: groupPda, authority: attacker.publicKey })
+          .signers([attacker])
+          .rpc(),
+        "Unauthorized"
+      );
+    });
+
+    it("rejects opening a draft without the group's required deposit", async () => {
+      const draft = await createProposal("undeposited-draft", null, true);
+      await program.methods
+        .setProposalDeposit(new anchor.BN(LAMPORTS_PER_SOL / 100))
+        .accountsPartial({ group: groupPda, authority })
+        .rpc();
+      try {
+        await expectFailure(
+          createProposal("undeposited-open", null),
+          "ProposalDepositRequired"
+        );
+        await expectFailure(
+          program.methods
+            .activateProposal()
+            .accountsPartial({ proposal: draft, group: groupPda, authority })
+            .rpc(),
+          "ProposalDepositRequired"
+        );
+      } finally {
+        await program.methods
+          .setProposalDeposit(new anchor.BN(0))
+          .accountsPartial({ group: groupPda, authority })
+          .rpc();
+      }
+    });
+
+    it("rejects a spam flag from outside the council", async () => {
+      await expectFailure(
+        program.methods
+          .flagAsSpam()
+          .accountsPartial({
+            proposal,
+            group: groupPda,
+            council: councilPda,
+            councilMember: attacker.publicKey,
+          })
+          .signers([attacker])
+          .rpc(),
+        "NotCouncilMember"
+      );
+    });
+
+    it("rejects an emergency proposal from outside the council", async () => {
+      const proposalId = "emergency-hijack";
+      const index = await proposalCount();
+      await expectFailure(
+        program.methods
+          .createProposal(
+            proposalId,
+            "Emergency",
+            "",
+            ["Yes", "No"],
+            new anchor.BN(now() + 60),
+            new anchor.BN(now() + 3600),
+            null,
+            null,
+            false,
+            { text: {} },
+            true
+          )
+          .accountsPartial({
+            proposal: proposalPda(groupPda, proposalId),
+            proposalIndex: indexedPda("proposal_index", groupPda, index),
+            group: groupPda,
+            authority: attacker.publicKey,
+            council: councilPda,
+          })
+          .signers([attacker])
+          .rpc(),
+        "NotCouncilMember"
+      );
+    });
+
+    it("rejects settling an unflagged deposit before the proposal is settled", async () => {
+      await expectFailure(
+        program.methods
+          .settleProposalDeposit()
+          .accountsPartial({
+            proposal,
+            group: groupPda,
+            depositor: authority,
+            settler: attacker.publicKey,
+          })
+          .signers([attacker])
+          .rpc(),
+        "InvalidProposalStatus"
+      );
+    });
+  });
+
+  describe("vote rewards", () => {
+    const solMint = new PublicKey("So11111111111111111111111111111111111111112");
+    let proposal: PublicKey;
+
+    before(async () => {
+      proposal = await createProposal("reward-pool", null);
+      await program.methods
+        .fundVoteRewards(solMint, new anchor.BN(LAMPORTS_PER_SOL / 100))
+        .accountsPartial({
+          proposal,
+          group: groupPda,
+          funder: authority,
+          vault: null,
+          funderTokenAccount: null,
+          tokenProgram: null,
+        })
+        .rpc();
+    });
+
+    it("rejects topping up another funder's reward pool", async () => {
+      await expectFailure(
+        program.methods
+          .fundVoteRewards(solMint, new anchor.BN(1))
+          .accountsPartial({
+            proposal,
+            group: groupPda,
+            funder: attacker.publicKey,
+            vault: null,
+            funderTokenAccount: null,
+            tokenProgram: null,
+          })
+          .signers([attacker])
+          .rpc(),
+        "RewardPoolMismatch"
+      );
+    });
+
+    it("rejects a claim before the proposal is finalized", async () => {
+      await expectFailure(
+        program.methods
+          .claimVoteReward()
+          .accountsPartial({
+            proposal,
+            group: groupPda,
+            voter: attacker.publicKey,
+            vault: null,
+            voterTokenAccount: null,
+            tokenProgram: null,
+          })
+          .signers([attacker])
+          .rpc(),
+        "AccountNotInitialized"
+      );
+    });
+
+    it("rejects reclaiming by anyone but the funder or while voters can claim", async () => {
+      const reclaim = (funder: anchor.web3.Keypair) =>
+        program.methods
+          .reclaimVoteRewards()
+          .accountsPartial({
+            rewardPool: PublicKey.findProgramAddressSync(
+              [Buffer.from("reward_pool"), proposal.toBuffer()],
+              program.programId
+            )[0],
+            proposal,
+            group: groupPda,
+            funder: funder.publicKey,
+            vault: null,
+            funderTokenAccount: null,
+            tokenProgram: null,
+          })
+          .signers([funder])
+          .rpc();
+
+      await expectFailure(reclaim(attacker), "Unauthorized");
+      await expectFailure(
+        reclaim((provider.wallet as anchor.Wallet).payer),
+        "RewardsStillClaimable"
+      );
+    });
+  });
+
+  describe("voter lottery", () => {
+    let proposal: PublicKey;
+    let lottery: PublicKey;
+
+    before(async () => {
+      proposal = await createProposal("lottery-pool", null);
+      lottery = PublicKey.findProgramAddressSync(
+        [Buffer.from("lottery"), proposal.toBuffer()],
+        program.programId
+      )[0];
+      await program.methods
+        .fundVoterLottery(2, new anchor.BN(LAMPORTS_PER_SOL / 100))
+        .accountsPartial({ proposal, group: groupPda, funder: authority })
+        .rpc();
+    });
+
+    it("rejects more winners than a lottery can track", async () => {
+      const other = await createProposal("lottery-size", null);
+
+      await expectFailure(
+        program.methods
+          .fundVoterLottery(17, new anchor.BN(1))
+          .accountsPartial({
+            proposal: other,
+            group: groupPda,
+            funder: attacker.publicKey,
+          })
+          .signers([attacker])
+          .rpc(),
+        "InvalidLotteryWinners"
+      );
+    });
+
+    it("rejects topping up another funder's lottery", async () => {
+      await expectFailure(
+        program.methods
+          .fundVoterLottery(2, new anchor.BN(1))
+          .accountsPartial({
+            proposal,
+            group: groupPda,
+            funder: attacker.publicKey,
+          })
+          .signers([attacker])
+          .rpc(),
+        "RewardPoolMismatch"
+      );
+    });
+
+    it("rejects drawing before the proposal is finalized", async () => {
+      await expectFailure(
+        program.methods
+          .drawVoterLottery()
+          .accountsPartial({ proposal, group: groupPda, lottery })
+          .rpc(),
+        "AccountNotInitialized"
+      );
+    });
+
+    it("rejects claiming a prize before the draw", async () => {
+      await expectFailure(
+        program.methods
+          .claimLotteryPrize()
+          .accountsPartial({
+            lottery,
+            group: groupPda,
+            winner: attacker.publicKey,
+          })
+          .signers([attacker])
+          .rpc(),
+        "LotteryNotDrawn"
+      );
+    });
+
+    it("rejects reclaiming by anyone but the funder or while the lottery is open", async () => {
+      const reclaim = (funder: anchor.web3.Keypair) =>
+        program.methods
+          .reclaimVoterLottery()
+          .accountsPartial({
+            lottery,
+            proposal,
+            group: groupPda,
+            funder: funder.publicKey,
+          })
+          .signers([funder])
+          .rpc();
+
+      await expectFailure(reclaim(attacker), "Unauthorized");
+      await expectFailure(
+        reclaim((provider.wallet as anchor.Wallet).payer),
+        "RewardsStillClaimable"
+      );
+    });
+  });
+
+  describe("joint proposals", () => {
+    const jointPda = (jointId: string) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("joint_proposal"), Buffer.from(jointId)],
+        program.programId
+      )[0];
+    const jointId = `pact-${Date.now().toString(36)}`;
+    let proposal: PublicKey;
+
+    before(async () => {
+      proposal = await createProposal("joint-owner", null);
+      await program.methods
+        .createJointProposal(jointId, 0)
+        .accountsPartial({
+          jointProposal: jointPda(jointId),
+          proposal,
+          group: groupPda,
+          authority,
+        })
+        .rpc();
+    });
+
+    it("rejects a pass threshold above the participant limit", async () => {
+      const bar = await createProposal("joint-bar", null);
+
+      await expectFailure(
+        program.methods
+          .createJointProposal(`${jointId}-bar`, 9)
+          .accountsPartial({
+            jointProposal: jointPda(`${jointId}-bar`),
+            proposal: bar,
+            group: groupPda,
+            authority,
+          })
+          .rpc(),
+        "InvalidRequiredPasses"
+      );
+    });
+
+    it("rejects joining on behalf of a group the signer does not run", async () => {
+      const hijack = await createProposal("joint-hijack", null);
+
+      await expectFailure(
+        program.methods
+          .joinJointProposal()
+          .accountsPartial({
+            jointProposal: jointPda(jointId),
+            proposal: hijack,
+            group: groupPda,
+            authority: attacker.publicKey,
+          })
+          .signers([attacker])
+          .rpc(),
+        "Unauthorized"
+      );
+    });
+
+    it("rejects resolving with a single participant", async () => {
+      await expectFailure(
+        program.methods
+          .resolveJointProposal()
+          .accountsPartial({
+            jointProposal: jointPda(jointId),
+            resolver: attacker.publicKey,
+          })
+          .remainingAccounts([
+            { pubkey: proposal, isSigner: false, isWritable: false },
+          ])
+          .signers([attacker])
+          .rpc(),
+        "NotEnoughJointParticipants"
+      );
+    });
+  });
+
+  describe("program upgrade governance", () => {
+    it("rejects handing upgrade governance over without registry authority", async () => {
+      await expectFailure(
+        program.methods
+          .initializeProgramGovernance(new anchor.BN(3 * 24 * 60 * 60))
+          .accountsPartial({
+            group: groupPda,
+            authority: attacker.publicKey,
+          })
+          .signers([attacker])
+          .rpc(),
+        "Unauthorized"
+      );
+    });
+
+    it("rejects a timelock shorter than two days", async () => {
+      await expectFailure(
+        program.methods
+          .initializeProgramGovernance(new anchor.BN(60))
+          .accountsPartial({ group: groupPda, authority })
+          .rpc(),
+        "InvalidTimelock"
+      );
+    });
+  });
+
+  describe("relayed votes", () => {
+    const voter = Keypair.generate();
+    let proposal: PublicKey;
+
+    const payload = (choice: number, expiry: number) =>
+      Buffer.concat([
+        Buffer.from("solana-dao vote:"),
+        proposal.toBuffer(),
+        Buffer.from([choice]),
+        new anchor.BN(expiry).toArrayLike(Buffer, "le", 8),
+      ]);
+
+    // The attacker relays, paying the fees, a vote credited to `voter`
+    const relay = (signer: anchor.web3.Keypair, choice: number, expiry: number) =>
+      program.methods
+        .relayVote(choice, new anchor.BN(expiry))
+        .accountsPartial({
+          proposal,
+          group: groupPda,
+          voter: voter.publicKey,
+          relayer: attacker.publicKey,
+          voterTokenAccount: null,
+          instructionsSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        })
+        .preInstructions([
+          anchor.web3.Ed25519Program.createInstructionWithPrivateKey({
+            privateKey: signer.secretKey,
+            message: payload(choice, expiry),
+          }),
+        ])
+        .signers([attacker])
+        .rpc();
+
+    before(async () => {
+      proposal = await createProposal("relayed-vote", null);
+      await sleep(3000);
+    });
+
+    it("rejects a vote payload signed by someone other than the voter", async () => {
+      await expectFailure(relay(attacker, 0, now() + 600), "InvalidVoteSignature");
+    });
+
+    it("rejects an expired vote payload", async () => {
+      await expectFailure(relay(voter, 0, now() - 60), "VotePayloadExpired");
+    });
+
+    it("credits the voter once", async () => {
+      await relay(voter, 1, now() + 600);
+      const { voters } = await program.account.proposal.fetch(proposal);
+      expect(voters.map((v) => v.voter.toBase58())).to.include(
+        voter.publicKey.toBase58()
+      );
+
+      await expectFailure(relay(voter, 1, now() + 600), "AlreadyVoted");
+    });
+  });
+
+  describe("off-chain votes", () => {
+    let proposal: PublicKey;
+    let offchainTally: PublicKey;
+
+    const submitTally = (aggregator: anchor.web3.Keypair | null) => {
+      const builder = program.methods
+        .submitOffchainTally(
+          Array(32).fill(0),
+          [new anchor.BN(1), new anchor.BN(0)],
+          1
+        )
+        .accountsPartial({
+          proposal,
+          group: groupPda,
+          offchainTally,
+          aggregator: aggregator ? aggregator.publicKey : authority,
+        });
+      return aggregator ? builder.signers([aggregator]).rpc() : builder.rpc();
+    };
+
+    before(async () => {
+      proposal = await createProposal("offchain-votes", null);
+      [offchainTally] = PublicKey.findProgramAddressSync(
+        [Buffer.from("offchain_tally"), proposal.toBuffer()],
+        program.programId
+      );
+    });
+
+    it("rejects enabling off-chain voting without group authority", async () => {
+      await expectFailure(
+        program.methods
+          .enableOffchainVoting(attacker.publicKey)
+          .accountsPartial({
+            proposal,
+            group: groupPda,
+            authority: attacker.publicKey,
+          })
+          .signers([attacker])
+          .rpc(),
+        "Unauthorized"
+      );
+    });
+
+    it("refuses on-chain votes once ballots are collected off-chain", async () => {
+      await program.methods
+        .enableOffchainVoting(authority)
+        .accountsPartial({ proposal, group: groupPda, authority })
+        .rpc();
+      await sleep(3000);
+
+      await expectFailure(
+        program.methods
+          .voteOnProposal(0, null, null)
+          .accountsPartial({
+            proposal,
+            group: groupPda,
+            voter: attacker.publicKey,
+            voterTokenAccount: null,
+            tokenProgram: null,
+          })
+          .signers([attacker])
+          .rpc(),
+        "OffchainVotingOnly"
+      );
+    });
+
+    it("rejects a tally from anyone but the aggregator", async () => {
+      await expectFailure(submitTally(attacker), "Unauthorized");
+    });
+
+    it("rejects a tally before voting ends", async () => {
+      await expectFailure(submitTally(null), "VotingNotEnded");
+    });
+  });
+
+  describe("off-chain tally disputes", () => {
+    type Subtree = {
+      hash: Buffer;
+      sums: { choiceVotes: anchor.BN[]; voterCount: number };
+    };
+    const sha256 = (...parts: Buffer[]) =>
+      createHash("sha256").update(Buffer.concat(parts)).digest();
+    const sumsBytes = ({ sums }: Subtree) =>
+      Buffer.concat([
+        ...sums.choiceVotes.map((votes) => votes.toArrayLike(Buffer, "le", 8)),
+        new anchor.BN(sums.voterCount).toArrayLike(Buffer, "le", 4),
+      ]);
+    // The program's offchain_ballot_leaf, with the sums a ballot counts
+    const leaf = (voter: PublicKey, choice: number, weight: number): Subtree => ({
+      hash: sha256(
+        Buffer.from([0]),
+        voter.toBuffer(),
+        Buffer.from([choice]),
+        new anchor.BN(weight).toArrayLike(Buffer, "le", 8)
+      ),
+      sums: {
+        choiceVotes: [0, 1].map((c) => new anchor.BN(c === choice ? weight : 0)),
+        voterCount: 1,
+      },
+    });
+    // The program's offchain_tally_node, ordering children by hash then sums
+    const join = (a: Subtree, b: Subtree): Buffer => {
+      const [first, second] = [
+        Buffer.concat([a.hash, sumsBytes(a)]),
+        Buffer.concat([b.hash, sumsBytes(b)]),
+      ].sort(Buffer.compare);
+      return sha256(Buffer.from([1]), first, second);
+    };
+    const tallyPda = (proposal: PublicKey) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("offchain_tally"), proposal.toBuffer()],
+        program.programId
+      )[0];
+    const submit = (
+      proposal: PublicKey,
+      root: Buffer,
+      choiceVotes: number[],
+      voterCount: number
+    ) =>
+      program.methods
+        .submitOffchainTally(
+          Array.from(root),
+          choiceVotes.map((votes) => new anchor.BN(votes)),
+          voterCount
+        )
+        .accountsPartial({
+          proposal,
+          group: groupPda,
+          offchainTally: tallyPda(proposal),
+          aggregator: authority,
+        })
+        .rpc();
+    const disputeTotal = (
+      proposal: PublicKey,
+      node: object,
+      claimed: Subtree["sums"],
+      proof: Subtree[]
+    ) =>
+      program.methods
+        .disputeOffchainTotal(
+          node as any,
+          claimed,
+          proof.map(({ hash, sums }) => ({ hash: Array.from(hash), sums }))
+        )
+        .accountsPartial({
+          proposal,
+          group: groupPda,
+          offchainTally: tallyPda(proposal),
+          disputer: attacker.publicKey,
+        })
+        .signers([attacker])
+        .rpc();
+    const enable = (proposal: PublicKey, aggregator: PublicKey) =>
+      program.methods
+        .enableOffchainVoting(aggregator)
+        .accountsPartial({ proposal, group: groupPda, authority })
+        .rpc();
+
+    // One-person-one-vote, yet the aggregator counts the attacker with weight 4
+    const inflated = leaf(attacker.publicKey, 0, 4);
+    const honest = leaf(authority, 1, 1);
+    const root = join(inflated, honest);
+    let trusted: PublicKey;
+    let overcounted: PublicKey;
+
+    before(async () => {
+      const shortVote = (proposalId: string) =>
+        createProposal(proposalId, null, false, undefined, undefined, 3);
+      trusted = await shortVote("trusted-tally");
+      overcounted = await shortVote("overcount-tally");
+      await enable(trusted, authority);
+      await enable(overcounted, authority);
+      await sleep(7000);
+
+      await submit(trusted, root, [4, 1], 2);
+      // The same ballots, reported with a total they don't add up to
+      await submit(overcounted, root, [9, 1], 2);
+    });
+
+    it("rejects replacing the aggregator of an undisputed tally", async () => {
+      await expectFailure(
+        enable(trusted, attacker.publicKey),
+        "OffchainTallyAlreadySubmitted"
+      );
+    });
+
+    it("takes ballot weights on the aggregator's word", async () => {
+      await expectFailure(
+        disputeTotal(
+          trusted,
+          {
+            ballot: {
+              voter: attacker.publicKey,
+              choice: 0,
+              voteWeight: new anchor.BN(4),
+            },
+          },
+          inflated.sums,
+          [honest]
+        ),
+        "TallyNotContradicted"
+      );
+    });
+
+    it("rejects a dispute whose proof doesn't reach the submitted totals", async () => {
+      await expectFailure(
+        disputeTotal(
+          overcounted,
+          {
+            ballot: {
+              voter: attacker.publicKey,
+              choice: 0,
+              voteWeight: new anchor.BN(4),
+            },
+          },
+          inflated.sums,
+          [honest]
+        ),
+        "InvalidMerkleProof"
+      );
+    });
+
+    it("voids a tally whose totals don't add up", async () => {
+      await disputeTotal(
+        overcounted,
+        {
+          inner: {
+            left: { hash: Array.from(inflated.hash), sums: inflated.sums },
+            right: { hash: Array.from(honest.hash), sums: honest.sums },
+          },
+        },
+        { choiceVotes: [new anchor.BN(9), new anchor.BN(1)], voterCount: 2 },
+        []
+      );
+      const tally = await program.account.offchainTally.fetch(tallyPda(overcounted));
+      expect(tally.disputedBy?.toBase58()).to.equal(attacker.publicKey.toBase58());
+
+      // A disputed tally's aggregator can be replaced
+      await enable(overcounted, attacker.publicKey);
+    });
+  });
+
+  describe("realms compatibility", () => {
+    let proposal: PublicKey;
+    let realmsProposal: PublicKey;
+
+    before(async () => {
+      proposal = await createProposal("realms-mirror", null);
+      [realmsProposal] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("governance"),
+          groupPda.toBuffer(),
+          PublicKey.default.toBuffer(),
+          proposal.toBuffer(),
+        ],
+        program.programId
+      );
+    });
+
+    it("lets anyone mirror a proposal as an spl-governance ProposalV2", async () => {
+      await program.methods
+        .syncRealmsProposal()
+        .accountsPartial({
+          proposal,
+          group: groupPda,
+          realmsProposal,
+          payer: attacker.publicKey,
+        })
+        .signers([attacker])
+        .rpc();
+
+      const account = await provider.connection.getAccountInfo(realmsProposal);
+      expect(account.owner.toBase58()).to.equal(program.programId.toBase58());
+      expect(account.data[0]).to.equal(14); // GovernanceAccountType::ProposalV2
+    });
+
+    it("rejects a vote record for a wallet that has not voted", async () => {
+      const [participation] = PublicKey.findProgramAddressSync(
+        [Buffer.from("participation"), groupPda.toBuffer(), authority.toBuffer()],
+        program.programId
+      );
+      const [realmsVoteRecord] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("governance"),
+          realmsProposal.toBuffer(),
+          participation.toBuffer(),
+        ],
+        program.programId
+      );
+
+      await expectFailure(
+        program.methods
+          .syncRealmsVoteRecord(authority)
+          .accountsPartial({
+            proposal,
+            group: groupPda,
+            realmsProposal,
+            participation,
+            realmsVoteRecord,
+            payer: attacker.publicKey,
+          })
+          .signers([attacker])
+          .rpc(),
+        "BallotNotFound"
+      );
+    });
+  });
+
+  describe("price-weighted voting", () => {
+    const mint = Keypair.generate().publicKey;
+    const feeds = [{ mint, oracle: Keypair.generate().publicKey }];
+    const mintAccounts = [{ pubkey: mint, isSigner: false, isWritable: false }];
+    let proposal: PublicKey;
+    let priceWeighting: PublicKey;
+
+    before(async () => {
+      proposal = await createProposal("priced-vote", null, true);
+      [priceWeighting] = PublicKey.findProgramAddressSync(
+        [Buffer.from("price_weighting"), proposal.toBuffer()],
+        program.programId
+      );
+    });
+
+    it("rejects setting price weighting without group authority", async () => {
+      await expectFailure(
+        program.methods
+          .setPriceWeighting(feeds, 60)
+          .accountsPartial({
+            proposal,
+            group: groupPda,
+            priceWeighting,
+            authority: attacker.publicKey,
+          })
+          .remainingAccounts(mintAccounts)
+          .signers([attacker])
+          .rpc(),
+        "Unauthorized"
+      );
+    });
+
+    it("rejects a feed whose mint is not an SPL mint", async () => {
+      // Nothing lives at the random mint address, so there are no decimals to read
+      await expectFailure(
+        program.methods
+          .setPriceWeighting(feeds, 60)
+          .accountsPartial({ proposal, group: groupPda, priceWeighting, authority })
+          .remainingAccounts(mintAccounts)
+          .rpc(),
+        "InvalidPriceFeeds"
+      );
+    });
+
+    it("rejects feeds without their mint accounts", async () => {
+      await expectFailure(
+        program.methods
+          .setPriceWeighting(feeds, 60)
+          .accountsPartial({ proposal, group: groupPda, priceWeighting, authority })
+          .rpc(),
+        "InvalidPriceFeeds"
+      );
+    });
+  });
+
+  describe("members-only voting", () => {
+    const setMembersOnly = (enabled: boolean) =>
+      program.methods
+        .setMembersOnlyVoting(enabled)
+        .accountsPartial({ group: groupPda, authority })
+        .rpc();
+    const [membershipTree] = PublicKey.findProgramAddressSync(
+      [Buffer.from("membership_tree"), groupPda.toBuffer()],
+      program.programId
+    );
+
+    after(async () => {
+      await setMembersOnly(false);
+    });
+
+    it("rejects restricting voting without group authority", async () => {
+      await expectFailure(
+        program.methods
+          .setMembersOnlyVoting(true)
+          .accountsPartial({ group: groupPda, authority: attacker.publicKey })
+          .signers([attacker])
+          .rpc(),
+        "Unauthorized"
+      );
+    });
+
+    it("rejects votes from wallets that are not members", async () => {
+      const proposal = await createProposal("members-only", null);
+      await setMembersOnly(true);
+      await sleep(3000);
+
+      await expectFailure(
+        program.methods
+          .voteOnProposal(0, null, null)
+          .accountsPartial({
+            proposal,
+            group: groupPda,
+            voter: attacker.publicKey,
+            voterTokenAccount: null,
+            tokenProgram: null,
+          })
+          .signers([attacker])
+          .rpc(),
+        "NotAGroupMember"
+      );
+    });
+
+    it("rejects a credential vote in a group without a membership tree", async () => {
+      const proposal = await createProposal("cnft-vote", null);
+      await sleep(3000);
+
+      await expectFailure(
+        program.methods
+          .voteWithCredential(0, null, new anchor.BN(0), Array(32).fill(0))
+          .accountsPartial({
+            proposal,
+            group: groupPda,
+            membershipTree,
+            merkleTree: Keypair.generate().publicKey,
+            voter: attacker.publicKey,
+            voterTokenAccount: null,
+          })
+          .signers([attacker])
+          .rpc(),
+        "AccountNotInitialized"
+      );
+    });
+  });
+
+  describe("membership dues", () => {
+    const solMint = new PublicKey("So11111111111111111111111111111111111111112");
+    const dues = (requiredForVoting: boolean) => ({
+      mint: solMint,
+      amount: new anchor.BN(LAMPORTS_PER_SOL / 100),
+      periodSecs: new anchor.BN(30 * 86_400),
+      graceSecs: new anchor.BN(7 * 86_400),
+      requiredForVoting,
+    });
+    const [duesRecord] = PublicKey.findProgramAddressSync(
+      [Buffer.from("dues_record"), groupPda.toBuffer(), authority.toBuffer()],
+      program.programId
+    );
+
+    after(async () => {
+      await program.methods
+        .setDuesPolicy(null)
+        .accountsPartial({ group: groupPda, authority })
+        .rpc();
+    });
+
+    it("rejects setting dues without group authority", async () => {
+      await expectFailure(
+        program.methods
+          .setDuesPolicy(dues(false))
+          .accountsPartial({ group: groupPda, authority: attacker.publicKey })
+          .signers([attacker])
+          .rpc(),
+        "Unauthorized"
+      );
+    });
+
+    it("rejects a grace period longer than the dues period", async () => {
+      await expectFailure(
+        program.methods
+          .setDuesPolicy({ ...dues(false), graceSecs: new anchor.BN(31 * 86_400) })
+          .accountsPartial({ group: groupPda, authority })
+          .rpc(),
+        "InvalidDuesPolicy"
+      );
+    });
+
+    it("rejects flagging dues that are paid up", async () => {
+      await program.methods
+        .setDuesPolicy(dues(false))
+        .accountsPartial({ group: groupPda, authority })
+        .rpc();
+      await expectFailure(
+        program.methods
+          .payDues(0)
+          .accountsPartial({
+            group: groupPda,
+            member: authority,
+            treasuryTokenAccount: null,
+            memberTokenAccount: null,
+            tokenProgram: null,
+          })
+          .rpc(),
+        "InvalidDuesPeriods"
+      );
+      await program.methods
+        .payDues(1)
+        .accountsPartial({
+          group: groupPda,
+          member: authority,
+          treasuryTokenAccount: null,
+          memberTokenAccount: null,
+          tokenProgram: null,
+        })
+        .rpc();
+
+      await expectFailure(
+        program.methods
+          .flagOverdueDues()
+          .accountsPartial({ group: groupPda, duesRecord })
+          .signers([attacker])
+          .rpc(),
+        "DuesNotOverdue"
+      );
+    });
+
+    it("rejects votes from wallets without paid dues when dues gate voting", async () => {
+      const proposal = await createProposal("dues-vote", null);
+      await program.methods
+        .setDuesPolicy(dues(true))
+        .accountsPartial({ group: groupPda, authority })
+        .rpc();
+      await sleep(3000);
+
+      await expectFailure(
+        program.methods
+          .voteOnProposal(0, null, null)
+          .accountsPartial({
+            proposal,
+            group: groupPda,
+            voter: attacker.publicKey,
+            voterTokenAccount: null,
+            tokenProgram: null,
+          })
+          .signers([attacker])
+          .rpc(),
+        "DuesNotCurrent"
+      );
+    });
+  });
+
+  describe("stake-to-join shares", () => {
+    // A group of its own, since shares stay locked past the end of the test run
+    const sharesGroupId = `${groupId}-s`;
+    const [sharesGroup] = PublicKey.findProgramAddressSync(
+      [Buffer.from("group"), Buffer.from(sharesGroupId)],
+      program.programId
+    );
+    const [treasury] = PublicKey.findProgramAddressSync(
+      [Buffer.from("treasury"), sharesGroup.toBuffer()],
+      program.programId
+    );
+    const [attackerShares] = PublicKey.findProgramAddressSync(
+      [Buffer.from("member_shares"), sharesGroup.toBuffer(), attacker.publicKey.toBuffer()],
+      program.programId
+    );
+    const price = LAMPORTS_PER_SOL / 10;
+    const guardSecs = 3 * 86_400;
+
+    const balance = () => provider.connection.getBalance(treasury);
+    const donate = async (lamports: number) =>
+      provider.sendAndConfirm(
+        new anchor.web3.Transaction().add(
+          SystemProgram.transfer({ fromPubkey: authority, toPubkey: treasury, lamports })
+        )
+      );
+    // Mirrors the program's share pool: treasury SOL above the rent minimum that open
+    // grants, streams and bounties aren't owed
+    const pool = async () => {
+      const rent = await provider.connection.getMinimumBalanceForRentExemption(0);
+      const group = await program.account.group.fetch(sharesGroup);
+      return Math.max((await balance()) - rent - group.committedLamports.toNumber(), 0);
+    };
+    const ragequit = (shares: number) =>
+      program.methods
+        .ragequit(new anchor.BN(shares))
+        .accountsPartial({
+          group: sharesGroup,
+          member: attacker.publicKey,
+          tokenProgram: null,
+        })
+        .signers([attacker])
+        .rpc();
+
+    before(async () => {
+      await program.methods
+        .createGroup(sharesGroupId, "Shares", "", null, null)
+        .accountsPartial({ group: sharesGroup, daoRegistry: registryPda, authority, chatLink: null })
+        .rpc();
+    });
+
+    it("rejects pricing shares without group authority", async () => {
+      await expectFailure(
+        program.methods
+          .setStakeToJoin(new anchor.BN(price))
+          .accountsPartial({ group: sharesGroup, authority: attacker.publicKey })
+          .signers([attacker])
+          .rpc(),
+        "Unauthorized"
+      );
+    });
+
+    it("keeps a funded treasury from going to the first buyer", async () => {
+      await program.methods
+        .setStakeToJoin(new anchor.BN(price))
+        .accountsPartial({ group: sharesGroup, authority })
+        .rpc();
+      await donate(LAMPORTS_PER_SOL);
+      const funded = await pool();
+      const before = await balance();
+
+      await program.methods
+        .stakeShares(new anchor.BN(2))
+        .accountsPartial({ group: sharesGroup, member: attacker.publicKey })
+        .signers([attacker])
+        .rpc();
+
+      expect((await balance()) - before).to.equal(2 * price);
+      const stake = (await program.account.group.fetch(sharesGroup)).stakeToJoin;
+      const unowned = Math.ceil(funded / price);
+      expect(stake.unownedShares.toNumber()).to.equal(unowned);
+      expect(stake.totalShares.toNumber()).to.equal(unowned + 2);
+    });
+
+    it("rejects ragequitting straight after buying", async () => {
+      const shares = await program.account.memberShares.fetch(attackerShares);
+      expect(shares.lockedUntil.toNumber()).to.be.at.least(now() + guardSecs - 60);
+      const before = await balance();
+
+      await expectFailure(ragequit(1), "SharesLocked");
+      expect(await balance()).to.equal(before);
+    });
+
+    it("charges the treasury's value per share when it is above the price", async () => {
+      await donate(2 * LAMPORTS_PER_SOL);
+      const stake = (await program.account.group.fetch(sharesGroup)).stakeToJoin;
+      const cost = Math.ceil(((await pool()) * 3) / stake.totalShares.toNumber());
+      expect(cost).to.be.above(3 * price);
+      const before = await balance();
+
+      await program.methods
+        .stakeShares(new anchor.BN(3))
+        .accountsPartial({ group: sharesGroup, member: attacker.publicKey })
+        .signers([attacker])
+        .rpc();
+
+      expect((await balance()) - before).to.equal(cost);
+    });
+
+    it("rejects ragequitting more shares than the member holds", async () => {
+      await expectFailure(ragequit(6), "InvalidShareAmount");
+    });
+
+    it("rejects ragequitting until the guard window after a vote has passed", async () => {
+      const index = (await program.account.group.fetch(sharesGroup)).proposalCount.toNumber();
+      const proposal = proposalPda(sharesGroup, "shares-vote");
+      const start = now() + 2;
+      await program.methods
+        .createProposal(
+          "shares-vote",
+          "Security test",
+          "Proposal used by adversarial tests",
+          ["Yes", "No"],
+          new anchor.BN(start),
+          new anchor.BN(start + 3600),
+          null,
+          null,
+          false,
+          { text: {} },
+          false
+        )
+        .accountsPartial({
+          proposal,
+          proposalIndex: indexedPda("proposal_index", sharesGroup, index),
+          group: sharesGroup,
+          authority,
+        })
+        .rpc();
+      await sleep(3000);
+
+      await program.methods
+        .voteOnProposal(0, null, null)
+        .accountsPartial({
+          proposal,
+          group: sharesGroup,
+          voter: attacker.publicKey,
+          memberShares: attackerShares,
+          voterTokenAccount: null,
+          tokenProgram: null,
+        })
+        .signers([attacker])
+        .rpc();
+
+      const votingEnd = (await program.account.proposal.fetch(proposal)).votingEnd.toNumber();
+      const shares = await program.account.memberShares.fetch(attackerShares);
+      expect(shares.lockedUntil.toNumber()).to.be.at.least(votingEnd + guardSecs);
+      await expectFailure(ragequit(1), "SharesLocked");
+    });
+
+    it("rejects turning stake-to-join off while shares are outstanding", async () => {
+      await expectFailure(
+        program.methods
+          .setStakeToJoin(null)
+          .accountsPartial({ group: sharesGroup, authority })
+          .rpc(),
+        "SharesOutstanding"
+      );
+    });
+  });
+
+  describe("member weights", () => {
+    it("rejects setting a member's weight without group authority", async () => {
+      await expectFailure(
+        program.methods
+          .setMemberWeight(authority, 3)
+          .accountsPartial({ group: groupPda, authority: attacker.publicKey })
+          .signers([attacker])
+          .rpc(),
+        "Unauthorized"
+      );
+    });
+
+    it("rejects a weight for a wallet that is not a member", async () => {
+      await expectFailure(
+        program.methods
+          .setMemberWeight(attacker.publicKey, 3)
+          .accountsPartial({ group: groupPda, authority })
+          .rpc(),
+        "AccountNotInitialized"
+      );
+    });
+
+    it("rejects member weighting on a token-weighted proposal", async () => {
+      const proposal = await createProposal(
+        "weighted-sol",
+        new PublicKey("So11111111111111111111111111111111111111112"),
+        true
+      );
+
+      await expectFailure(
+        program.methods
+          .setMemberWeightedVoting(true)
+          .accountsPartial({ proposal, group: groupPda, authority })
+          .rpc(),
+        "MemberWeightingUnsupported"
+      );
+    });
+
+    it("rejects votes from non-members on a member-weighted proposal", async () => {
+      const proposal = await createProposal("weighted-vote", null);
+      await program.methods
+        .setMemberWeightedVoting(true)
+        .accountsPartial({ proposal, group: groupPda, authority })
+        .rpc();
+      await sleep(3000);
+
+      await expectFailure(
+        program.methods
+          .voteOnProposal(0, null, null)
+          .accountsPartial({
+            proposal,
+            group: groupPda,
+            voter: attacker.publicKey,
+            voterTokenAccount: null,
+            tokenProgram: null,
+          })
+          .signers([attacker])
+          .rpc(),
+        "NotAGroupMember"
+      );
+    });
+  });
+
+  describe("split-weight voting", () => {
+    it("rejects enabling split voting without group authority", async () => {
+      const proposal = await createProposal("split-hijack", null, true);
+      await expectFailure(
+        program.methods
+          .setSplitVoting(true)
+          .accountsPartial({
+            proposal,
+            group: groupPda,
+            authority: attacker.publicKey,
+          })
+          .signers([attacker])
+          .rpc(),
+        "Unauthorized"
+      );
+    });
+
+    it("rejects allocations beyond the voter's weight", async () => {
+      const proposal = await createProposal(
+        "split-overspend",
+        new PublicKey("So11111111111111111111111111111111111111112")
+      );
+      await program.methods
+        .setSplitVoting(true)
+        .accountsPartial({ proposal, group: groupPda, authority })
+        .rpc();
+      await sleep(3000);
+
+      const overspend = new anchor.BN(100 * LAMPORTS_PER_SOL);
+      await expectFailure(
+        program.methods
+          .voteOnProposal(0, null, [overspend, new anchor.BN(0)])
+          .accountsPartial({
+            proposal,
+            group: groupPda,
+            voter: attacker.publicKey,
+            voterTokenAccount: null,
+            tokenProgram: null,
+          })
+          .signers([attacker])
+          .rpc(),
+        "InvalidSplitAllocation"
+      );
+    });
+  });
+
+  describe("deposit voting", () => {
+    const nativeMint = new PublicKey(
+      "So11111111111111111111111111111111111111112"
+    );
+    const [registrar] = PublicKey.findProgramAddressSync(
+      [Buffer.from("registrar"), groupPda.toBuffer(), nativeMint.toBuffer()],
+      program.programId
+    );
+
+    it("rejects configuring a registrar without group authority", async () => {
+      await expectFailure(
+        program.methods
+          .configureDepositRegistrar(nativeMint, new anchor.BN(0))
+          .accountsPartial({
+            registrar,
+            group: groupPda,
+            authority: attacker.publicKey,
+            vault: null,
+          })
+          .signers([attacker])
+          .rpc(),
+        "Unauthorized"
+      );
+    });
+
+    it("rejects votes from tokens deposited after the proposal", async () => {
+      await program.methods
+        .configureDepositRegistrar(nativeMint, new anchor.BN(0))
+        .accountsPartial({ registrar, group: groupPda, authority, vault: null })
+        .rpc();
+      const proposal = await createProposal("deposit-late", nativeMint);
+      await program.methods
+        .setDepositVoting(true)
+        .accountsPartial({ proposal, group: groupPda, registrar, authority })
+        .rpc();
+
+      const [voterDeposit] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("voter_deposit"),
+          registrar.toBuffer(),
+          attacker.publicKey.toBuffer(),
+        ],
+        program.programId
+      );
+      await sleep(1000);
+      await program.methods
+        .depositVotingTokens(new anchor.BN(LAMPORTS_PER_SOL / 10))
+        .accountsPartial({
+          registrar,
+          group: groupPda,
+          voterDeposit,
+          voter: attacker.publicKey,
+          vault: null,
+          voterTokenAccount: null,
+          tokenProgram: null,
+        })
+        .signers([attacker])
+        .rpc();
+      await sleep(2000);
+
+      await expectFailure(
+        program.methods
+          .voteOnProposal(0, null, null)
+          .accountsPartial({
+            proposal,
+            group: groupPda,
+            voter: attacker.publicKey,
+            voterTokenAccount: null,
+            tokenProgram: null,
+            voterDeposit,
+          })
+          .signers([attacker])
+          .rpc(),
+        "HoldingPeriodNotMet"
+      );
+    });
+  });
+
+  describe("weight decay", () => {
+    it("rejects weight decay without group authority", async () => {
+      const proposal = await createProposal(
+        "decay-hijack",
+        new PublicKey("So11111111111111111111111111111111111111112"),
+        true
+      );
+      await expectFailure(
+        program.methods
+          .setWeightDecay(5000)
+          .accountsPartial({
+            proposal,
+            group: groupPda,
+            authority: attacker.publicKey,
+          })
+          .signers([attacker])
+          .rpc(),
+        "Unauthorized"
+      );
+    });
+
+    it("rejects weight decay on a one-person-one-vote proposal", async () => {
+      const proposal = await createProposal("decay-headcount", null, true);
+      await expectFailure(
+        program.methods
+          .setWeightDecay(5000)
+          .accountsPartial({ proposal, group: groupPda, authority })
+          .rpc(),
+        "WeightDecayUnsupported"
+      );
+    });
+  });
+
+  describe("choice details", () => {
+    const detailsFor = (proposal: PublicKey) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("choice_details"), proposal.toBuffer()],
+        program.programId
+      )[0];
+    const details = [
+      { description: "Fund the seed library", uri: "https://example.org/seeds" },
+      { description: null, uri: null },
+    ];
+
+    it("rejects describing choices without group authority", async () => {
+      const proposal = await createProposal("details-hijack", null, true);
+      await expectFailure(
+        program.methods
+          .setChoiceDetails(details)
+          .accountsPartial({
+            proposal,
+            choiceDetails: detailsFor(proposal),
+            group: groupPda,
+            authority: attacker.publicKey,
+          })
+          .signers([attacker])
+          .rpc(),
+        "Unauthorized"
+      );
+    });
+
+    it("rejects details that don't match the choices", async () => {
+      const proposal = await createProposal("details-mismatch", null, true);
+      await expectFailure(
+        program.methods
+          .setChoiceDetails(details.slice(0, 1))
+          .accountsPartial({
+            proposal,
+            choiceDetails: detailsFor(proposal),
+            group: groupPda,
+            authority,
+          })
+          .rpc(),
+        "InvalidChoiceDetails"
+      );
+    });
+  });
+
+  describe("execution callbacks", () => {
+    const callbackFor = (proposal: PublicKey) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("callback"), proposal.toBuffer()],
+        program.programId
+      )[0];
+    const setCallback = (proposal: PublicKey, target: PublicKey, signer: PublicKey) =>
+      program.methods
+        .setExecutionCallback(target, Buffer.from([1, 2, 3, 4, 5, 6, 7, 8]))
+        .accountsPartial({
+          proposal,
+          callback: callbackFor(proposal),
+          group: groupPda,
+          authority: signer,
+        });
+
+    it("rejects setting a callback without group authority", async () => {
+      const proposal = await createProposal("callback-hijack", null, true);
+      await expectFailure(
+        setCallback(proposal, SystemProgram.programId, attacker.publicKey)
+          .signers([attacker])
+          .rpc(),
+        "Unauthorized"
+      );
+    });
+
+    const allowlistPda = PublicKey.findProgramAddressSync(
+      [Buffer.from("program_allowlist"), groupPda.toBuffer()],
+      program.programId
+    )[0];
+
+    it("rejects changing the program allowlist without group authority", async () => {
+      await expectFailure(
+        program.methods
+          .setProgramAllowlist([attacker.publicKey])
+          .accountsPartial({
+            programAllowlist: allowlistPda,
+            group: groupPda,
+            authority: attacker.publicKey,
+          })
+          .signers([attacker])
+          .rpc(),
+        "Unauthorized"
+      );
+    });
+
+    it("rejects allowlisting this program", async () => {
+      await expectFailure(
+        program.methods
+          .setProgramAllowlist([program.programId])
+          .accountsPartial({ programAllowlist: allowlistPda, group: groupPda, authority })
+          .rpc(),
+        "InvalidProgramAllowlist"
+      );
+    });
+
+    it("rejects a callback into this program", async () => {
+      const proposal = await createProposal("callback-reentry", null, true);
+      await expectFailure(
+        setCallback(proposal, program.programId, authority).rpc(),
+        "InvalidCallback"
+      );
+    });
+  });
+
+  describe("group profiles", () => {
+    const [profilePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("group_profile"), groupPda.toBuffer()],
+      program.programId
+    );
+    const setProfile = (socials: string[], signer: PublicKey) =>
+      program.methods
+        .setGroupProfile("ipfs://bafkreiavatar", "https://example.org", socials, null)
+        .accountsPartial({ groupProfile: profilePda, group: groupPda, authority: signer });
+
+    it("rejects editing the profile without group authority", async () => {
+      await expectFailure(
+        setProfile([], attacker.publicKey).signers([attacker]).rpc(),
+        "Unauthorized"
+      );
+    });
+
+    it("rejects duplicate social links", async () => {
+      await expectFailure(
+        setProfile(["https://x.com/dao", "https://x.com/dao"], authority).rpc(),
+        "InvalidGroupProfile"
+      );
+    });
+
+    it("rejects closing the profile without group authority", async () => {
+      await setProfile(["https://x.com/dao"], authority).rpc();
+      await expectFailure(
+        program.methods
+          .closeGroupProfile()
+          .accountsPartial({
+            groupProfile: profilePda,
+            group: groupPda,
+            authority: attacker.publicKey,
+          })
+          .signers([attacker])
+          .rpc(),
+        "Unauthorized"
+      );
+    });
+  });
+
+  describe("bicameral voting", () => {
+    // The council is set up by the spam deposit tests, with the authority as its member
+    const [councilPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("council"), groupPda.toBuffer()],
+      program.programId
+    );
+
+    it("rejects adding a council chamber without group authority", async () => {
+      const proposal = await createProposal("bicameral-hijack", null, true);
+      await expectFailure(
+        program.methods
+          .setBicameral(true)
+          .accountsPartial({
+            proposal,
+            group: groupPda,
+            council: councilPda,
+            authority: attacker.publicKey,
+          })
+          .signers([attacker])
+          .rpc(),
+        "Unauthorized"
+      );
+    });
+
+    it("rejects council votes from outside the council", async () => {
+      const proposal = await createProposal("bicameral-outsider", null);
+      await program.methods
+        .setBicameral(true)
+        .accountsPartial({ proposal, group: groupPda, council: councilPda, authority })
+        .rpc();
+      await sleep(3000);
+
+      await expectFailure(
+        program.methods
+          .councilVote(0)
+          .accountsPartial({
+            proposal,
+            group: groupPda,
+            councilMember: attacker.publicKey,
+          })
+          .signers([attacker])
+          .rpc(),
+        "NotCouncilMember"
+      );
+    });
+
+    it("rejects council votes on a proposal without a council chamber", async () => {
+      const proposal = await createProposal("bicameral-missing", null);
+      await sleep(3000);
+
+      await expectFailure(
+        program.methods
+          .councilVote(0)
+          .accountsPartial({ proposal, group: groupPda, councilMember: authority })
+          .rpc(),
+        "NotBicameral"
+      );
+    });
+  });
+
+  describe("slot windows", () => {
+    it("rejects a slot window without group authority", async () => {
+      const proposal = await createProposal("slot-hijack", null, true);
+      const slot = await provider.connection.getSlot();
+      await expectFailure(
+        program.methods
+          .setSlotWindow(new anchor.BN(slot + 100), new anchor.BN(slot + 1000))
+          .accountsPartial({
+            proposal,
+            group: groupPda,
+            authority: attacker.publicKey,
+          })
+          .signers([attacker])
+          .rpc(),
+        "Unauthorized"
+      );
+    });
+
+    it("rejects a slot window that ends before it starts", async () => {
+      const proposal = await createProposal("slot-reversed", null, true);
+      const slot = await provider.connection.getSlot();
+      await expectFailure(
+        program.methods
+          .setSlotWindow(new anchor.BN(slot + 1000), new anchor.BN(slot + 100))
+          .accountsPartial({ proposal, group: groupPda, authority })
+          .rpc(),
+        "InvalidVotingPeriod"
+      );
+    });
+  });
+
+  describe("proposal templates", () => {
+    const templatePda = (templateId: string) =>
+      PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("proposal_template"),
+          groupPda.toBuffer(),
+          Buffer.from(templateId),
+        ],
+        program.programId
+      )[0];
+
+    it("rejects saving a template without group authority", async () => {
+      await expectFailure(
+        program.methods
+          .createProposalTemplate(
+            "hijack",
+            "Hijacked budget",
+            "Send the budget elsewhere",
+            ["Yes", "No"],
+            new anchor.BN(86400),
+            null,
+            null,
+            { text: {} },
+            false,
+            false
+          )
+          .accountsPartial({
+            template: templatePda("hijack"),
+            group: groupPda,
+            authority: attacker.publicKey,
+          })
+          .signers([attacker])
+          .rpc(),
+        "Unauthorized"
+      );
+    });
+
+    it("rejects closing a template without group authority", async () => {
+      const template = templatePda("weekly-sync");
+      await program.methods
+        .createProposalTemplate(
+          "weekly-sync",
+          "Weekly sync",
+          "Pick this week's focus",
+          ["Ship", "Fix"],
+          new anchor.BN(86400),
+          null,
+          null,
+          { text: {} },
+          false,
+          false
+        )
+        .accountsPartial({ template, group: groupPda, authority })
+        .rpc();
+
+      await expectFailure(
+        program.methods
+          .closeProposalTemplate()
+          .accountsPartial({
+            template,
+            group: groupPda,
+            authority: attacker.publicKey,
+          })
+          .signers([attacker])
+          .rpc(),
+        "Unauthorized"
+      );
+    });
+  });
+
+  describe("proposal kinds", () => {
+    it("rejects an election whose candidates don't match the choices", async () => {
+      await expectFailure(
+        createProposal("bad-election", null, true, ["Alice", "Bob"], {
+          election: {
+            0: { candidates: [attacker.publicKey], seats: 1, threshold: 1 },
+          },
+        }),
+        "InvalidProposalKind"
+      );
+    });
+
+    it("rejects executing a config change before it has passed", async () => {
+      const proposal = await createProposal(
+        "early-config",
+        null,
+        false,
+        ["Yes", "No"],
+        {
+          configChange: {
+            0: {
+              proposalFeeLamports: null,
+              membersOnlyVoting: true,
+              maxMembers: null,
+              visibility: null,
+              governance: null,
+            },
+          },
+        }
+      );
+
+      await expectFailure(
+        program.methods
+          .executeConfigChange()
+          .accountsPartial({
+            proposal,
+            group: groupPda,
+            executor: attacker.publicKey,
+          })
+          .signers([attacker])
+          .rpc(),
+        "InvalidProposalStatus"
+      );
+    });
+
+    it("rejects a treasury recovery to the default address", async () => {
+      await expectFailure(
+        createProposal("bad-recovery", null, true, ["Yes", "No"], {
+          treasuryRecovery: { 0: { recovery: PublicKey.default } },
+        }),
+        "InvalidProposalKind"
+      );
+    });
+
+    it("rejects a treasury recovery in a group anyone can vote in", async () => {
+      await expectFailure(
+        createProposal("open-recovery", null, false, ["Yes", "No"], {
+          treasuryRecovery: { 0: { recovery: attacker.publicKey } },
+        }),
+        "RecoveryNeedsMembersOnlyVoting"
+      );
+    });
+
+    it("rejects a treasury recovery before it has passed", async () => {
+      const membersOnly = (enabled: boolean) =>
+        program.methods
+          .setMembersOnlyVoting(enabled)
+          .accountsPartial({ group: groupPda, authority })
+          .rpc();
+      await membersOnly(true);
+      let proposal: PublicKey;
+      try {
+        proposal = await createProposal(
+          "early-recovery",
+          null,
+          false,
+          ["Yes", "No"],
+          { treasuryRecovery: { 0: { recovery: attacker.publicKey } } }
+        );
+      } finally {
+        await membersOnly(false);
+      }
+
+      await expectFailure(
+        program.methods
+          .executeTreasuryRecovery()
+          .accountsPartial({
+            proposal,
+            group: groupPda,
+            recovery: attacker.publicKey,
+            executor: attacker.publicKey,
+            tokenProgram: null,
+          })
+          .signers([attacker])
+          .rpc(),
+        "InvalidProposalStatus"
+      );
+    });
+
+    it("rejects staking treasury SOL with no validator", async () => {
+      await expectFailure(
+        createProposal("bad-stake", null, true, ["Yes", "No"], {
+          treasuryStake: {
+            0: {
+              delegate: {
+                voteAccount: PublicKey.default,
+                lamports: new anchor.BN(LAMPORTS_PER_SOL),
+              },
+            },
+          },
+        }),
+        "InvalidProposalKind"
+      );
+    });
+
+    it("rejects unstaking before the proposal has passed", async () => {
+      const stakeAccount = Keypair.generate().publicKey;
+      const proposal = await createProposal("early-unstake", null, false, ["Yes", "No"], {
+        treasuryStake: { 0: { deactivate: { stakeAccount } } },
+      });
+
+      await expectFailure(
+        program.methods
+          .executeTreasuryStake()
+          .accountsPartial({
+            proposal,
+            group: groupPda,
+            stakeAccount,
+            voteAccount: null,
+            executor: attacker.publicKey,
+          })
+          .signers([attacker])
+          .rpc(),
+        "InvalidProposalStatus"
+      );
+    });
+
+    it("rejects a swap into the same mint", async () => {
+      const mint = Keypair.generate().publicKey;
+      await expectFailure(
+        createProposal("bad-swap", null, true, ["Yes", "No"], {
+          treasurySwap: {
+            0: {
+              swapProgram: Keypair.generate().publicKey,
+              inputMint: mint,
+              outputMint: mint,
+              amountIn: new anchor.BN(1_000),
+              minAmountOut: new anchor.BN(1_000),
+            },
+          },
+        }),
+        "InvalidProposalKind"
+      );
+    });
+
+    it("rejects a token distribution without a Merkle root", async () => {
+      await expectFailure(
+        createProposal("bad-distribution", null, true, ["Yes", "No"], {
+          tokenDistribution: {
+            0: {
+              mint: Keypair.generate().publicKey,
+              source: { mint: {} },
+              merkleRoot: Array(32).fill(0),
+              totalAmount: new anchor.BN(1_000),
+            },
+          },
+        }),
+        "InvalidProposalKind"
+      );
+    });
+
+    it("rejects a governance change with a quorum above 100%", async () => {
+      await expectFailure(
+        createProposal("bad-quorum", null, true, ["Yes", "No"], {
+          configChange: {
+            0: {
+              proposalFeeLamports: null,
+              membersOnlyVoting: null,
+              maxMembers: null,
+              visibility: null,
+              governance: {
+                quorumBps: 10_001,
+                approvalThresholdBps: 5_000,
+                minVotingDuration: new anchor.BN(0),
+                maxVotingDuration: new anchor.BN(0),
+                weightQuorumBps: 0,
+              },
+            },
+          },
+        }),
+        "InvalidProposalKind"
+      );
+    });
+
+    it("rejects a governance change with a weight quorum above 100%", async () => {
+      await expectFailure(
+        createProposal("bad-weight-quorum", null, true, ["Yes", "No"], {
+          configChange: {
+            0: {
+              proposalFeeLamports: null,
+              membersOnlyVoting: null,
+              maxMembers: null,
+              visibility: null,
+              governance: {
+                quorumBps: 0,
+                approvalThresholdBps: 5_000,
+                minVotingDuration: new anchor.BN(0),
+                maxVotingDuration: new anchor.BN(0),
+                weightQuorumBps: 10_001,
+              },
+            },
+          },
+        }),
+        "InvalidProposalKind"
+      );
+    });
+  });
+
+  describe("grants", () => {
+    const grant = (milestones: number[]) => ({
+      grant: {
+        0: {
+          recipient: attacker.publicKey,
+          milestones: milestones.map((sol) => new anchor.BN(sol * LAMPORTS_PER_SOL)),
+          approval: { council: {} },
+        },
+      },
+    });
+
+    it("rejects a grant without milestones", async () => {
+      await expectFailure(
+        createProposal("empty-grant", null, true, ["Yes", "No"], grant([])),
+        "InvalidProposalKind"
+      );
+    });
+
+    it("rejects opening a grant before it has passed", async () => {
+      const proposal = await createProposal(
+        "early-grant",
+        null,
+        false,
+        ["Yes", "No"],
+        grant([1, 2])
+      );
+
+      await expectFailure(
+        program.methods
+          .executeGrant()
+          .accountsPartial({
+            proposal,
+            group: groupPda,
+            executor: attacker.publicKey,
+          })
+          .signers([attacker])
+          .rpc(),
+        "InvalidProposalStatus"
+      );
+    });
+  });
+
+  describe("payment streams", () => {
+    const stream = (start: number, cliff: number, end: number) => ({
+      stream: {
+        0: {
+          recipient: attacker.publicKey,
+          amount: new anchor.BN(LAMPORTS_PER_SOL),
+          start: new anchor.BN(start),
+          cliff: new anchor.BN(cliff),
+          end: new anchor.BN(end),
+        },
+      },
+    });
+
+    it("rejects a stream whose cliff falls after its end", async () => {
+      const start = now();
+      await expectFailure(
+        createProposal(
+          "bad-stream",
+          null,
+          true,
+          ["Yes", "No"],
+          stream(start, start + 7_200, start + 3_600)
+        ),
+        "InvalidProposalKind"
+      );
+    });
+
+    it("rejects opening a stream before it has passed", async () => {
+      const start = now();
+      const proposal = await createProposal(
+        "early-stream",
+        null,
+        false,
+        ["Yes", "No"],
+        stream(start, start, start + 86_400)
+      );
+
+      await expectFailure(
+        program.methods
+          .executeStream()
+          .accountsPartial({
+            proposal,
+            group: groupPda,
+            executor: attacker.publicKey,
+          })
+          .signers([attacker])
+          .rpc(),
+        "InvalidProposalStatus"
+      );
+    });
+  });
+
+  describe("bounty board", () => {
+    const bountyId = "security-review";
+    const [bounty] = PublicKey.findProgramAddressSync(
+      [Buffer.from("bounty"), groupPda.toBuffer(), Buffer.from(bountyId)],
+      program.programId
+    );
+
+    it("rejects posting a bounty without group authority", async () => {
+      await expectFailure(
+        program.methods
+          .postBounty(bountyId, "https://example.com/bounty", new anchor.BN(LAMPORTS_PER_SOL))
+          .accountsPartial({ group: groupPda, authority: attacker.publicKey })
+          .signers([attacker])
+          .rpc(),
+        "Unauthorized"
+      );
+    });
+
+    it("rejects approving a bounty that has no submission", async () => {
+      await program.methods
+        .postBounty(bountyId, "https://example.com/bounty", new anchor.BN(LAMPORTS_PER_SOL))
+        .accountsPartial({ group: groupPda, authority })
+        .rpc();
+      await program.methods
+        .claimBounty()
+        .accountsPartial({ bounty, group: groupPda, hunter: attacker.publicKey })
+        .signers([attacker])
+        .rpc();
+
+      await expectFailure(
+        program.methods
+          .approveBounty()
+          .accountsPartial({
+            bounty,
+            group: groupPda,
+            hunter: attacker.publicKey,
+            authority,
+          })
+          .rpc(),
+        "BountyNotSubmitted"
+      );
+    });
+
+    it("rejects submitting work for someone else's claim", async () => {
+      const thief = Keypair.generate();
+      await expectFailure(
+        program.methods
+          .submitBounty("https://example.com/stolen")
+          .accountsPartial({ bounty, group: groupPda, hunter: thief.publicKey })
+          .signers([thief])
+          .rpc(),
+        "Unauthorized"
+      );
+    });
+  });
+
+  describe("membership policy", () => {
+    const setPolicy = (maxMembers: number, signer: PublicKey) =>
+      program.methods
+        .setMembershipPolicy(maxMembers, { public: {} })
+        .accountsPartial({ group: groupPda, authority: signer });
+
+    it("rejects changing the membership policy without group authority", async () => {
+      await expectFailure(
+        setPolicy(10, attacker.publicKey).signers([attacker]).rpc(),
+        "Unauthorized"
+      );
+    });
+
+    it("rejects new members once the group is at its cap", async () => {
+      const { memberCount } = await program.account.group.fetch(groupPda);
+      await setPolicy(memberCount, authority).rpc();
+
+      await expectFailure(
+        program.methods
+          .addGroupMember(attacker.publicKey)
+          .accountsPartial({
+            group: groupPda,
+            authority,
+            memberRecord: memberPda(attacker.publicKey),
+          })
+          .rpc(),
+        "GroupFull"
+      );
+
+      await setPolicy(0, authority).rpc();
+    });
+  });
+
+  describe("active proposal cap", () => {
+    const setCap = (maxActiveProposals: number, signer: PublicKey) =>
+      program.methods
+        .setMaxActiveProposals(maxActiveProposals)
+        .accountsPartial({ group: groupPda, authority: signer });
+
+    it("rejects changing the proposal cap without group authority", async () => {
+      await expectFailure(
+        setCap(1, attacker.publicKey).signers([attacker]).rpc(),
+        "Unauthorized"
+      );
+    });
+
+    it("rejects new active proposals once the group is at its cap", async () => {
+      const { activeProposals } = await program.account.group.fetch(groupPda);
+      await setCap(activeProposals, authority).rpc();
+
+      await expectFailure(createProposal("over-cap", null), "TooManyActiveProposals");
+      // Drafts don't count until they are activated
+      await createProposal("capped-draft", null, true);
+
+      await setCap(0, authority).rpc();
+    });
+  });
+
+  describe("proposal cooldown", () => {
+    const setCooldown = (cooldownSecs: number, signer: PublicKey) =>
+      program.methods
+        .setProposalCooldown(new anchor.BN(cooldownSecs))
+        .accountsPartial({ group: groupPda, authority: signer });
+
+    it("rejects changing the cooldown without group authority", async () => {
+      await expectFailure(
+        setCooldown(3600, attacker.publicKey).signers([attacker]).rpc(),
+        "Unauthorized"
+      );
+    });
+
+    it("rejects a proposal made within the creator's cooldown", async () => {
+      await setCooldown(3600, authority).rpc();
+
+      // The authority has created proposals moments ago
+      await expectFailure(createProposal("too-soon", null, true), "ProposalCooldownActive");
+
+      await setCooldown(0, authority).rpc();
+    });
+  });
+
+  describe("co-sponsorship", () => {
+    it("rejects changing the sponsor threshold without group authority", async () => {
+      await expectFailure(
+        program.methods
+          .setSponsorThreshold(1)
+          .accountsPartial({ group: groupPda, authority: attacker.publicKey })
+          .signers([attacker])
+          .rpc(),
+        "Unauthorized"
+      );
+    });
+
+    it("rejects a co-sponsor who isn't a listed member", async () => {
+      const draft = await createProposal("sponsor-draft", null, true);
+      await expectFailure(
+        program.methods
+          .coSponsor()
+          .accountsPartial({ proposal: draft, group: groupPda, sponsor: attacker.publicKey })
+          .signers([attacker])
+          .rpc(),
+        "AccountNotInitialized"
+      );
+    });
+  });
+
+  describe("tie-break", () => {
+    it("rejects changing the tie-break rule without group authority", async () => {
+      await expectFailure(
+        program.methods
+          .setTieBreak({ council: {} })
+          .accountsPartial({ group: groupPda, authority: attacker.publicKey })
+          .signers([attacker])
+          .rpc(),
+        "Unauthorized"
+      );
+    });
+
+    it("rejects a revote window longer than seven days", async () => {
+      await expectFailure(
+        program.methods
+          .setTieBreak({ revote: { windowSecs: new anchor.BN(8 * 86400) } })
+          .accountsPartial({ group: groupPda, authority })
+          .rpc(),
+        "InvalidTieBreak"
+      );
+    });
+  });
+
+  describe("bans", () => {
+    const ban = (signer: PublicKey, memberRecord: PublicKey | null) =>
+      program.methods
+        .banMember(attacker.publicKey, "Spamming proposals", new anchor.BN(0))
+        .accountsPartial({ group: groupPda, memberRecord, authority: signer });
+
+    it("rejects banning without group authority", async () => {
+      await expectFailure(
+        ban(attacker.publicKey, null).signers([attacker]).rpc(),
+        "Unauthorized"
+      );
+    });
+
+    it("rejects votes from a banned wallet", async () => {
+      const listed = await program.account.member.fetchNullable(
+        memberPda(attacker.publicKey)
+      );
+      await ban(authority, listed ? memberPda(attacker.publicKey) : null).rpc();
+      const proposal = await createProposal(
+        "banned-vote",
+        new PublicKey("So11111111111111111111111111111111111111112")
+      );
+      await sleep(3000);
+
+      await expectFailure(
+        program.methods
+          .voteOnProposal(0, null, null)
+          .accountsPartial({
+            proposal,
+            group: groupPda,
+            voter: attacker.publicKey,
+            voterTokenAccount: null,
+            tokenProgram: null,
+          })
+          .signers([attacker])
+          .rpc(),
+        "MemberBanned"
+      );
+
+      await program.methods
+        .unbanMember(attacker.publicKey)
+        .accountsPartial({ group: groupPda, authority })
+        .rpc();
+    });
+  });
+
+  describe("chat links", () => {
+    const chatId = -Date.now();
+    const chatLinkFor = (chatId: number) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("chat_link"), new anchor.BN(chatId).toTwos(64).toArrayLike(Buffer, "le", 8)],
+        program.programId
+      )[0];
+
+    it("rejects linking a chat without group authority", async () => {
+      await expectFailure(
+        program.methods
+          .setGroupChat(new anchor.BN(chatId))
+          .accountsPartial({
+            group: groupPda,
+            newChatLink: chatLinkFor(chatId),
+            oldChatLink: null,
+            authority: attacker.publicKey,
+          })
+          .signers([attacker])
+          .rpc(),
+        "Unauthorized"
+      );
+    });
+
+    it("rejects a second group for an already linked chat", async () => {
+      await program.methods
+        .setGroupChat(new anchor.BN(chatId))
+        .accountsPartial({
+          group: groupPda,
+          newChatLink: chatLinkFor(chatId),
+          oldChatLink: null,
+          authority,
+        })
+        .rpc();
+
+      const [otherGroup] = PublicKey.findProgramAddressSync(
+        [Buffer.from("group"), Buffer.from(`${groupId}-c`)],
+        program.programId
+      );
+      await expectFailure(
+        program.methods
+          .createGroup(`${groupId}-c`, "Same chat", "", null, new anchor.BN(chatId))
+          .accountsPartial({
+            group: otherGroup,
+            daoRegistry: registryPda,
+            authority,
+            chatLink: chatLinkFor(chatId),
+          })
+          .rpc(),
+        "already in use"
+      );
+
+      await program.methods
+        .setGroupChat(null)
+        .accountsPartial({
+          group: groupPda,
+          newChatLink: null,
+          oldChatLink: chatLinkFor(chatId),
+          authority,
+        })
+        .rpc();
+    });
+  });
+
+  describe("invites", () => {
+    const code = `inv-${Date.now().toString(36)}`;
+    // The program's invite_code_hash
+    const codeHash = (code: string) =>
+      Array.from(
+        createHash("sha256")
+          .update("invite")
+          .update(groupPda.toBuffer())
+          .update(code)
+          .digest()
+      );
+    const inviteFor = (code: string) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("invite"), groupPda.toBuffer(), Buffer.from(codeHash(code))],
+        program.programId
+      )[0];
+    const join = (code: string) =>
+      program.methods
+        .joinWithInvite(code)
+        .accountsPartial({
+          group: groupPda,
+          invite: inviteFor(code),
+          joiner: attacker.publicKey,
+        })
+        .signers([attacker])
+        .rpc();
+
+    it("rejects creating an invite without group authority", async () => {
+      await expectFailure(
+        program.methods
+          .createInvite(codeHash(code), 1, new anchor.BN(now() + 3600))
+          .accountsPartial({
+            group: groupPda,
+            invite: inviteFor(code),
+            authority: attacker.publicKey,
+          })
+          .signers([attacker])
+          .rpc(),
+        "Unauthorized"
+      );
+    });
+
+    it("rejects an invite that has already expired", async () => {
+      await expectFailure(
+        program.methods
+          .createInvite(codeHash(code), 1, new anchor.BN(now() - 60))
+          .accountsPartial({ group: groupPda, invite: inviteFor(code), authority })
+          .rpc(),
+        "InvalidInvite"
+      );
+    });
+
+    it("rejects joining with a code that was never issued", async () => {
+      await expectFailure(join(`${code}-guess`), "AccountNotInitialized");
+    });
+
+    it("admits one member per use, then refuses", async () => {
+      await program.methods
+        .createInvite(codeHash(code), 1, new anchor.BN(now() + 3600))
+        .accountsPartial({ group: groupPda, invite: inviteFor(code), authority })
+        .rpc();
+
+      await join(code);
+      const record = await program.account.member.fetch(
+        memberPda(attacker.publicKey)
+      );
+      expect(record.wallet.equals(attacker.publicKey)).to.equal(true);
+
+      await expectFailure(join(code), "InviteExhausted");
+
+      await program.methods
+        .removeGroupMember(attacker.publicKey)
+        .accountsPartial({ group: groupPda, authority })
+        .rpc();
+    });
+  });
+
+  describe("token-gated joining", () => {
+    const mint = Keypair.generate().publicKey;
+    const [membershipGate] = PublicKey.findProgramAddressSync(
+      [Buffer.from("membership_gate"), groupPda.toBuffer()],
+      program.programId
+    );
+    const setGate = (minAmount: number, signer: PublicKey) =>
+      program.methods
+        .setMembershipGate({
+          token: { mint, minAmount: new anchor.BN(minAmount) },
+        })
+        .accountsPartial({ group: groupPda, membershipGate, authority: signer });
+    const join = (holding: PublicKey) =>
+      program.methods
+        .joinGroup()
+        .accountsPartial({
+          group: groupPda,
+          membershipGate,
+          joiner: attacker.publicKey,
+          holding,
+          nftMetadata: null,
+        })
+        .signers([attacker])
+        .rpc();
+
+    it("rejects joining a group without a membership gate", async () => {
+      await expectFailure(
+        join(Keypair.generate().publicKey),
+        "AccountNotInitialized"
+      );
+    });
+
+    it("rejects setting a gate without group authority", async () => {
+      await expectFailure(
+        setGate(1, attacker.publicKey).signers([attacker]).rpc(),
+        "Unauthorized"
+      );
+    });
+
+    it("rejects a gate that asks for no tokens", async () => {
+      await expectFailure(setGate(0, authority).rpc(), "InvalidMembershipGate");
+    });
+
+    it("rejects joining with an account that is not a token account", async () => {
+      await setGate(1, authority).rpc();
+      try {
+        await expectFailure(join(attacker.publicKey), "InvalidTokenAccount");
+      } finally {
+        await program.methods
+          .clearMembershipGate()
+          .accountsPartial({ group: groupPda, membershipGate, authority })
+          .rpc();
+      }
+    });
+  });
+
+  describe("automation", () => {
+    const tick = (proposal: PublicKey, schedule: PublicKey | null) =>
+      program.methods
+        .tick()
+        .accountsPartial({
+          proposal,
+          group: groupPda,
+          schedule,
+          proposalResult: PublicKey.findProgramAddressSync(
+            [Buffer.from("proposal_result"), proposal.toBuffer()],
+            program.programId
+          )[0],
+          cranker: attacker.publicKey,
+          quorumSupply: null,
+        })
+        .signers([attacker])
+        .rpc();
+    const schedulePda = (proposal: PublicKey) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("schedule"), proposal.toBuffer()],
+        program.programId
+      )[0];
+
+    it("rejects scheduling a draft without group authority", async () => {
+      const proposal = await createProposal("tick-hijack", null, true);
+
+      await expectFailure(
+        program.methods
+          .scheduleActivation()
+          .accountsPartial({
+            proposal,
+            group: groupPda,
+            schedule: schedulePda(proposal),
+            authority: attacker.publicKey,
+          })
+          .signers([attacker])
+          .rpc(),
+        "Unauthorized"
+      );
+    });
+
+    it("leaves an unscheduled draft closed", async () => {
+      const proposal = await createProposal("tick-unscheduled", null, true);
+      await sleep(3000);
+
+      await tick(proposal, null);
+      const { status } = await program.account.proposal.fetch(proposal);
+      expect(status).to.have.property("draft");
+    });
+
+    it("opens a scheduled draft once, then does nothing until voting ends", async () => {
+      const proposal = await createProposal("tick-scheduled", null, true);
+      await program.methods
+        .scheduleActivation()
+        .accountsPartial({
+          proposal,
+          group: groupPda,
+          schedule: schedulePda(proposal),
+          authority,
+        })
+        .rpc();
+      await sleep(3000);
+
+      await tick(proposal, schedulePda(proposal));
+      const opened = await program.account.proposal.fetch(proposal);
+      expect(opened.status).to.have.property("active");
+
+      await tick(proposal, schedulePda(proposal));
+      const again = await program.account.proposal.fetch(proposal);
+      expect(again.status).to.have.property("active");
+      expect(again.votingEnd.toNumber()).to.equal(opened.votingEnd.toNumber());
+    });
+  });
+
+  describe("signed login", () => {
+    const telegramId = new anchor.BN(Date.now());
+    const wallet = Keypair.generate();
+    const [userAccount] = PublicKey.findProgramAddressSync(
+      [Buffer.from("user_account"), telegramId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+
+    const challenge = (nonce: number) =>
+      Buffer.concat([
+        Buffer.from("solana-dao login:"),
+        userAccount.toBuffer(),
+        new anchor.BN(nonce).toArrayLike(Buffer, "le", 8),
+      ]);
+
+    const login = (signer: anchor.web3.Keypair, message: Buffer) =>
+      program.methods
+        .loginWithSignature(telegramId)
+        .accountsPartial({
+          userAccount,
+          instructionsSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        })
+        .preInstructions([
+          anchor.web3.Ed25519Program.createInstructionWithPrivateKey({
+            privateKey: signer.secretKey,
+            message,
+          }),
+        ])
+        .rpc();
+
+    before(async () => {
+      await program.methods
+        .createUserAccount(telegramId)
+        .accountsPartial({ userAccount, userWallet: wallet.publicKey })
+        .rpc();
+    });
+
+    it("rejects a challenge signed by another wallet", async () => {
+      await expectFailure(
+        login(attacker, challenge(0)),
+        "InvalidLoginSignature"
+      );
+    });
+
+    it("rejects a login without a signature instruction", async () => {
+      await expectFailure(
+        program.methods
+          .loginWithSignature(telegramId)
+          .accountsPartial({
+            userAccount,
+            instructionsSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+          })
+          .rpc(),
+        "InvalidLoginSignature"
+      );
+    });
+
+    it("accepts the wallet's signature once", async () => {
+      await login(wallet, challenge(0));
+      const account = await program.account.userAccount.fetch(userAccount);
+      expect(account.loginNonce.toNumber()).to.equal(1);
+      expect(account.lastLogin.toNumber()).to.be.greaterThan(0);
+
+      // Replaying the signed challenge fails once the nonce has moved on
+      await expectFailure(
+        login(wallet, challenge(0)),
+        "InvalidLoginSignature"
+      );
+    });
+  });
+
+  describe("signed wallet link", () => {
+    const telegramId = new anchor.BN(Date.now() + 1);
+    const wallet = Keypair.generate();
+    const external = Keypair.generate();
+    const [userAccount] = PublicKey.findProgramAddressSync(
+      [Buffer.from("user_account"), telegramId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+
+    const challenge = (nonce: number) =>
+      Buffer.from(`solana-dao link wallet:${userAccount.toBase58()}:${nonce}`);
+
+    const link = (owner: anchor.web3.Keypair, signer: anchor.web3.Keypair, message: Buffer) =>
+      program.methods
+        .linkWalletWithSignature(telegramId)
+        .accountsPartial({
+          userAccount,
+          owner: owner.publicKey,
+          instructionsSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        })
+        .preInstructions([
+          anchor.web3.Ed25519Program.createInstructionWithPrivateKey({
+            privateKey: signer.secretKey,
+            message,
+          }),
+        ])
+        .signers([owner])
+        .rpc();
+
+    before(async () => {
+      await program.methods
+        .createUserAccount(telegramId)
+        .accountsPartial({ userAccount, userWallet: wallet.publicKey })
+        .rpc();
+    });
+
+    it("rejects a link the primary wallet didn't sign", async () => {
+      await expectFailure(link(attacker, external, challenge(0)), "Unauthorized");
+    });
+
+    it("rejects a login challenge as a link", async () => {
+      await expectFailure(
+        link(
+          wallet,
+          external,
+          Buffer.concat([
+            Buffer.from("solana-dao login:"),
+            userAccount.toBuffer(),
+            new anchor.BN(0).toArrayLike(Buffer, "le", 8),
+          ])
+        ),
+        "InvalidLinkSignature"
+      );
+    });
+
+    it("links the wallet that signed the challenge, once", async () => {
+      await link(wallet, external, challenge(0));
+      const account = await program.account.userAccount.fetch(userAccount);
+      expect(account.linkedWallets.map((w) => w.toBase58())).to.deep.equal([
+        external.publicKey.toBase58(),
+      ]);
+      expect(account.loginNonce.toNumber()).to.equal(1);
+
+      await expectFailure(link(wallet, external, challenge(0)), "InvalidLinkSignature");
+      await expectFailure(link(wallet, external, challenge(1)), "WalletAlreadyLinked");
+    });
+  });
+
+  describe("delegation", () => {
+    const everyGroup = PublicKey.default;
+    const delegationPda = (group: PublicKey) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("delegation"), authority.toBuffer(), group.toBuffer()],
+        program.programId
+      )[0];
+
+    const delegate = (group: PublicKey, to: PublicKey, expiresAt: number | null = null) =>
+      program.methods
+        .delegateVotes(group, to, null, expiresAt === null ? null : new anchor.BN(expiresAt))
+        .accountsPartial({ delegation: delegationPda(group), delegator: authority })
+        .rpc();
+
+    const voteAs = (proposal: PublicKey, delegation: PublicKey) =>
+      program.methods
+        .voteAsDelegate(0)
+        .accountsPartial({
+          proposal,
+          group: groupPda,
+          delegator: authority,
+          delegation,
+          delegate: attacker.publicKey,
+          voterTokenAccount: null,
+        })
+        .signers([attacker])
+        .rpc();
+
+    it("rejects delegating to oneself", async () => {
+      await expectFailure(delegate(groupPda, authority), "SelfDelegation");
+    });
+
+    it("rejects a vote by a wallet the delegation doesn't name", async () => {
+      const proposal = await createProposal("delegated-other", null);
+      await delegate(groupPda, Keypair.generate().publicKey);
+      await sleep(3000);
+      await expectFailure(voteAs(proposal, delegationPda(groupPda)), "Unauthorized");
+    });
+
+    it("lets a group's delegation override one covering every group", async () => {
+      const proposal = await createProposal("delegated-override", null);
+      await delegate(everyGroup, attacker.publicKey);
+      await sleep(3000);
+      await expectFailure(
+        voteAs(proposal, delegationPda(everyGroup)),
+        "DelegationOverridden"
+      );
+    });
+
+    it("refuses to expire a delegation before its expiry", async () => {
+      await delegate(groupPda, attacker.publicKey, Math.floor(Date.now() / 1000) + 3600);
+      await expectFailure(
+        program.methods
+          .expireDelegation()
+          .accountsPartial({ delegation: delegationPda(groupPda), delegator: authority })
+          .rpc(),
+        "DelegationNotExpired"
+      );
+    });
+  });
+
+  describe("account migrations", () => {
+    it("refuses to migrate an account already at the current version", async () => {
+      await expectFailure(
+        program.methods.migrateGroup().accountsPartial({ account: groupPda }).rpc(),
+        "AccountUpToDate"
+      );
+    });
+
+    it("refuses to migrate an account of another type", async () => {
+      const draft = await createProposal("migrate-mixup", null, true);
+      await expectFailure(
+        program.methods.migrateGroup().accountsPartial({ account: draft }).rpc(),
+        "AccountDiscriminatorMismatch"
+      );
+    });
+  });
+});